@@ -0,0 +1,210 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, Metadata};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+
+use crate::named::FileHead;
+
+/// Interval of checking cached file on disk
+const REVALIDATE: Duration = Duration::from_secs(1);
+
+/// Cache key, requested path and bits of precompressed variants accepted
+/// by client.
+pub(crate) type CacheKey = (String, u64);
+
+struct CachedFile {
+    head: FileHead,
+    data: Bytes,
+    /// Served file on disk, it is checked with `stat` once per interval
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    used: u64,
+}
+
+/// Per-worker LRU cache of small files content.
+///
+/// Files are looked up by request, so cache hit does not touch filesystem
+/// until cached file has to be revalidated.
+pub(crate) struct MemoryCache {
+    max_size: u64,
+    max_file_size: u64,
+    revalidate: Duration,
+    size: u64,
+    tick: u64,
+    files: HashMap<CacheKey, CachedFile>,
+    /// Keys by last use, first entry is least recently used
+    order: BTreeMap<u64, CacheKey>,
+}
+
+impl MemoryCache {
+    pub(crate) fn new(max_size: u64, max_file_size: u64) -> Self {
+        MemoryCache {
+            max_size,
+            max_file_size: std::cmp::min(max_size, max_file_size),
+            revalidate: REVALIDATE,
+            size: 0,
+            tick: 0,
+            files: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    /// Check if file of `len` bytes could be stored in cache.
+    pub(crate) fn is_cacheable(&self, len: u64) -> bool {
+        len <= self.max_file_size
+    }
+
+    /// Get cached file, stale entry is removed from cache.
+    pub(crate) fn get(&mut self, key: &CacheKey) -> Option<(FileHead, Bytes)> {
+        let now = Instant::now();
+        let fresh = match self.files.get_mut(key) {
+            Some(ref file) if now - file.checked < self.revalidate => true,
+            Some(file) => match fs::metadata(&file.path) {
+                Ok(ref md)
+                    if file.len == md.len() && file.modified == md.modified().ok() =>
+                {
+                    file.checked = now;
+                    true
+                }
+                _ => false,
+            },
+            None => return None,
+        };
+
+        if !fresh {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let file = self.files.get_mut(key).unwrap();
+        let key = self.order.remove(&file.used).unwrap();
+        file.used = self.tick;
+        self.order.insert(self.tick, key);
+        Some((file.head.clone(), file.data.clone()))
+    }
+
+    /// Store file content, least recently used files are evicted
+    /// to keep cache within size limit.
+    pub(crate) fn insert(
+        &mut self,
+        key: CacheKey,
+        path: PathBuf,
+        md: &Metadata,
+        head: FileHead,
+        data: Bytes,
+    ) {
+        let len = data.len() as u64;
+        if !self.is_cacheable(len) {
+            return;
+        }
+        self.remove(&key);
+
+        while self.size + len > self.max_size {
+            let lru = self.order.values().next().cloned();
+            match lru {
+                Some(lru) => self.remove(&lru),
+                None => break,
+            }
+        }
+
+        self.tick += 1;
+        self.size += len;
+        self.order.insert(self.tick, key.clone());
+        self.files.insert(
+            key,
+            CachedFile {
+                head,
+                data,
+                path,
+                len: md.len(),
+                modified: md.modified().ok(),
+                checked: Instant::now(),
+                used: self.tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(file) = self.files.remove(key) {
+            self.order.remove(&file.used);
+            self.size -= file.data.len() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+    use crate::NamedFile;
+
+    fn entry(path: &str) -> (CacheKey, Metadata, FileHead) {
+        let md = File::open(path).unwrap().metadata().unwrap();
+        let head = NamedFile::open(path).unwrap().head();
+        ((format!("/{}", path), 0), md, head)
+    }
+
+    /// Store entry of `Cargo.toml`, metadata does not have to match content.
+    fn insert(
+        cache: &mut MemoryCache,
+        key: &CacheKey,
+        md: &Metadata,
+        data: &'static [u8],
+    ) {
+        let head = NamedFile::open("Cargo.toml").unwrap().head();
+        let data = Bytes::from_static(data);
+        cache.insert(key.clone(), "Cargo.toml".into(), md, head, data);
+    }
+
+    #[test]
+    fn test_memory_cache_lru() {
+        let mut cache = MemoryCache::new(10, 10);
+        assert!(cache.is_cacheable(10));
+        assert!(!cache.is_cacheable(11));
+
+        let (key1, md, _) = entry("Cargo.toml");
+        let (key2, _, _) = entry("tests/test.png");
+        let (key3, _, _) = entry("tests/test.binary");
+
+        insert(&mut cache, &key1, &md, b"1111");
+        insert(&mut cache, &key2, &md, b"2222");
+        assert!(cache.get(&key1).is_some());
+
+        // key2 is least recently used
+        insert(&mut cache, &key3, &md, b"3333");
+        assert!(cache.get(&key2).is_none());
+        assert_eq!(cache.get(&key1).unwrap().1, Bytes::from_static(b"1111"));
+        assert_eq!(cache.get(&key3).unwrap().1, Bytes::from_static(b"3333"));
+        assert_eq!(cache.size, 8);
+        assert_eq!(cache.order.len(), 2);
+
+        // too large
+        insert(&mut cache, &key2, &md, b"22222222222");
+        assert!(cache.get(&key2).is_none());
+        assert_eq!(cache.size, 8);
+    }
+
+    #[test]
+    fn test_memory_cache_stale() {
+        let mut cache = MemoryCache::new(100, 100);
+        cache.revalidate = Duration::from_secs(0);
+        let (key, md, _) = entry("Cargo.toml");
+        let (_, other_md, _) = entry("tests/test.binary");
+
+        // file on disk is checked on each lookup
+        insert(&mut cache, &key, &md, b"data");
+        assert!(cache.get(&key).is_some());
+
+        insert(&mut cache, &key, &other_md, b"data");
+        assert!(cache.get(&key).is_none());
+        assert!(cache.get(&key).is_none());
+        assert_eq!(cache.size, 0);
+        assert!(cache.order.is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use actix_web::http::header::EntityTag;
+use actix_web::http::StatusCode;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+
+use crate::named::{content_headers, FileBody, FileHead, Flags};
+use crate::DEFAULT_CHUNK_SIZE;
+
+/// Static assets compiled into the binary.
+///
+/// Implemented for functions and closures, so assets embedded with
+/// `rust-embed` or `include_dir` crates could be served directly:
+///
+/// ```rust,ignore
+/// use actix_files::Files;
+/// use include_dir::{include_dir, Dir};
+///
+/// #[derive(rust_embed::RustEmbed)]
+/// #[folder = "static/"]
+/// struct Assets;
+///
+/// static DIR: Dir = include_dir!("static");
+///
+/// let files = Files::from_embedded("/static", Assets::get);
+/// let files = Files::from_embedded("/static", |path: &str| {
+///     DIR.get_file(path).map(|file| file.contents().into())
+/// });
+/// ```
+pub trait Embedded: 'static {
+    /// Returns content of the asset with `/`-separated relative path.
+    fn get(&self, path: &str) -> Option<Cow<'static, [u8]>>;
+}
+
+impl<F> Embedded for F
+where
+    F: Fn(&str) -> Option<Cow<'static, [u8]>> + 'static,
+{
+    fn get(&self, path: &str) -> Option<Cow<'static, [u8]>> {
+        (self)(path)
+    }
+}
+
+/// ETags of embedded assets.
+///
+/// Content of static assets is hashed once, assets are identified by
+/// address and length. Owned content is hashed on every request.
+#[derive(Default)]
+pub(crate) struct EtagCache(HashMap<(usize, usize), EntityTag>);
+
+impl EtagCache {
+    fn get(&mut self, data: &Cow<'static, [u8]>) -> EntityTag {
+        match data {
+            Cow::Borrowed(data) => self
+                .0
+                .entry((data.as_ptr() as usize, data.len()))
+                .or_insert_with(|| content_etag(data))
+                .clone(),
+            Cow::Owned(data) => content_etag(data),
+        }
+    }
+}
+
+/// Strong ETag derived from length and SHA-256 of the content, truncated
+/// to 128 bits.
+fn content_etag(data: &[u8]) -> EntityTag {
+    let mut tag = format!("{:x}:", data.len());
+    for b in &Sha256::digest(data)[..16] {
+        let _ = write!(tag, "{:02x}", b);
+    }
+    EntityTag::strong(tag)
+}
+
+/// Build response parameters for embedded asset.
+///
+/// Embedded assets have no modification time, ETag is derived from content.
+pub(crate) fn embedded_file(
+    name: &str,
+    data: Cow<'static, [u8]>,
+    flags: Flags,
+    etags: &mut EtagCache,
+) -> io::Result<(FileHead, FileBody)> {
+    let (content_type, content_disposition) = content_headers(Path::new(name))?;
+
+    let etag = if flags.contains(Flags::ETAG) {
+        Some(etags.get(&data))
+    } else {
+        None
+    };
+
+    let data = match data {
+        Cow::Borrowed(data) => Bytes::from_static(data),
+        Cow::Owned(data) => Bytes::from(data),
+    };
+
+    let head = FileHead {
+        status_code: StatusCode::OK,
+        content_type,
+        content_disposition,
+        flags,
+        encoding: None,
+        precompressed: None,
+        etag,
+        last_modified: None,
+        size: data.len() as u64,
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        bandwidth_limit: None,
+    };
+    Ok((head, FileBody::Bytes(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_file() {
+        let mut etags = EtagCache::default();
+        let (head, _) = embedded_file(
+            "css/main.css",
+            Cow::Borrowed(b"body {}"),
+            Flags::default(),
+            &mut etags,
+        )
+        .unwrap();
+        assert_eq!(head.content_type, mime::TEXT_CSS);
+        assert_eq!(head.size, 7);
+        assert!(head.last_modified.is_none());
+        assert_eq!(
+            head.etag.as_ref().unwrap().tag(),
+            "7:62368a1a29259b30bac235c0e75dc700"
+        );
+        assert_eq!(etags.0.len(), 1);
+
+        let (head2, _) = embedded_file(
+            "main.css",
+            Cow::Owned(b"body {}".to_vec()),
+            Flags::default(),
+            &mut etags,
+        )
+        .unwrap();
+        assert_eq!(head.etag, head2.etag);
+        assert_eq!(etags.0.len(), 1);
+
+        let (head, _) = embedded_file(
+            "main.css",
+            Cow::Borrowed(b"p {}"),
+            Flags::empty(),
+            &mut etags,
+        )
+        .unwrap();
+        assert!(head.etag.is_none());
+
+        assert!(
+            embedded_file("", Cow::Borrowed(b""), Flags::default(), &mut etags).is_err()
+        );
+    }
+}
@@ -3,7 +3,7 @@
 //! Static files support
 use std::cell::RefCell;
 use std::fmt::Write;
-use std::fs::{DirEntry, File};
+use std::fs::{DirEntry, File, Metadata};
 use std::future::Future;
 use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
@@ -15,13 +15,14 @@ use std::{cmp, io};
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
 use actix_web::dev::{
-    AppService, HttpServiceFactory, Payload, ResourceDef, ServiceRequest,
+    AppService, HttpServiceFactory, Payload, RequestHead, ResourceDef, ServiceRequest,
     ServiceResponse,
 };
-use actix_web::error::{Canceled, Error, ErrorInternalServerError};
+use actix_web::error::{BlockingError, Error};
 use actix_web::guard::Guard;
-use actix_web::http::header::{self, DispositionType};
-use actix_web::http::Method;
+use actix_web::http::header::{self, ContentDisposition, DispositionType, EntityTag};
+use actix_web::http::{ContentEncoding, Method};
+use actix_web::middleware::AcceptedEncodings;
 use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
 use bytes::Bytes;
 use futures::future::{ok, ready, Either, FutureExt, LocalBoxFuture, Ready};
@@ -31,13 +32,21 @@ use mime_guess::from_ext;
 use percent_encoding::{utf8_percent_encode, CONTROLS};
 use v_htmlescape::escape as escape_html_entity;
 
+mod cache;
+mod embedded;
 mod error;
 mod named;
 mod range;
+mod throttle;
 
+use self::cache::{CacheKey, MemoryCache};
+use self::embedded::{embedded_file, EtagCache};
 use self::error::{FilesError, UriSegmentError};
+use self::named::{FileBody, FileHead};
+pub use crate::embedded::Embedded;
 pub use crate::named::NamedFile;
 pub use crate::range::HttpRange;
+pub use crate::throttle::BandwidthLimit;
 
 type HttpService = BoxedService<ServiceRequest, ServiceResponse, Error>;
 type HttpNewService = BoxedNewService<(), ServiceRequest, ServiceResponse, Error, ()>;
@@ -50,15 +59,19 @@ pub fn file_extension_to_mime(ext: &str) -> mime::Mime {
     from_ext(ext).first_or_octet_stream()
 }
 
+/// Default size of chunks read from a file.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 65_536;
+
 #[doc(hidden)]
 /// A helper created from a `std::fs::File` which reads the file
 /// chunk-by-chunk on a `ThreadPool`.
 pub struct ChunkedReadFile {
     size: u64,
     offset: u64,
+    chunk_size: usize,
     file: Option<File>,
     fut: Option<
-        LocalBoxFuture<'static, Result<Result<(File, Bytes), io::Error>, Canceled>>,
+        LocalBoxFuture<'static, Result<Result<(File, Bytes), io::Error>, BlockingError>>,
     >,
     counter: u64,
 }
@@ -72,10 +85,10 @@ impl Stream for ChunkedReadFile {
     ) -> Poll<Option<Self::Item>> {
         if let Some(ref mut fut) = self.fut {
             return match Pin::new(fut).poll(cx) {
-                Poll::Ready(Err(_)) => Poll::Ready(Some(Err(ErrorInternalServerError(
-                    "Unexpected error",
-                )
-                .into()))),
+                Poll::Ready(Err(e)) => {
+                    self.fut.take();
+                    Poll::Ready(Some(Err(e.into())))
+                }
                 Poll::Ready(Ok(Ok((file, bytes)))) => {
                     self.fut.take();
                     self.file = Some(file);
@@ -91,6 +104,7 @@ impl Stream for ChunkedReadFile {
         let size = self.size;
         let offset = self.offset;
         let counter = self.counter;
+        let chunk_size = cmp::max(self.chunk_size, 1) as u64;
 
         if size == counter {
             Poll::Ready(None)
@@ -99,7 +113,8 @@ impl Stream for ChunkedReadFile {
             self.fut = Some(
                 web::block(move || {
                     let max_bytes: usize;
-                    max_bytes = cmp::min(size.saturating_sub(counter), 65_536) as usize;
+                    max_bytes =
+                        cmp::min(size.saturating_sub(counter), chunk_size) as usize;
                     let mut buf = Vec::with_capacity(max_bytes);
                     file.seek(io::SeekFrom::Start(offset))?;
                     let nbytes =
@@ -119,6 +134,19 @@ impl Stream for ChunkedReadFile {
 type DirectoryRenderer =
     dyn Fn(&Directory, &HttpRequest) -> Result<ServiceResponse, io::Error>;
 
+/// Order of directory listing entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirectorySort {
+    /// Keep order returned by the file system
+    Unsorted,
+    /// Sort by file name
+    Name,
+    /// Sort by file size, smallest first
+    Size,
+    /// Sort by modification time, oldest first
+    Modified,
+}
+
 /// A directory; responds with the generated directory listing.
 #[derive(Debug)]
 pub struct Directory {
@@ -126,19 +154,67 @@ pub struct Directory {
     pub base: PathBuf,
     /// Path of subdirectory to generate listing for
     pub path: PathBuf,
+    show_hidden: bool,
+    sort: DirectorySort,
+}
+
+/// Visible entry of a directory listing.
+#[derive(Debug)]
+pub struct DirectoryEntry {
+    /// File name
+    pub name: String,
+    /// Full path of the entry
+    pub path: PathBuf,
+    /// Entry metadata
+    pub metadata: Metadata,
 }
 
 impl Directory {
     /// Create a new directory
     pub fn new(base: PathBuf, path: PathBuf) -> Directory {
-        Directory { base, path }
+        Directory {
+            base,
+            path,
+            show_hidden: false,
+            sort: DirectorySort::Unsorted,
+        }
+    }
+
+    /// Visible entries of this directory, in configured order.
+    pub fn entries(&self) -> io::Result<Vec<DirectoryEntry>> {
+        let mut entries = Vec::new();
+        for entry in self.path.read_dir()? {
+            if !self.is_visible(&entry) {
+                continue;
+            }
+            let entry = entry?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            entries.push(DirectoryEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: entry.path(),
+                metadata,
+            });
+        }
+
+        match self.sort {
+            DirectorySort::Unsorted => (),
+            DirectorySort::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            DirectorySort::Size => entries.sort_by_key(|e| e.metadata.len()),
+            DirectorySort::Modified => {
+                entries.sort_by_key(|e| e.metadata.modified().ok())
+            }
+        }
+        Ok(entries)
     }
 
     /// Is this entry visible from this directory?
     pub fn is_visible(&self, entry: &io::Result<DirEntry>) -> bool {
         if let Ok(ref entry) = *entry {
             if let Some(name) = entry.file_name().to_str() {
-                if name.starts_with('.') {
+                if name.starts_with('.') && !self.show_hidden {
                     return false;
                 }
             }
@@ -161,7 +237,7 @@ macro_rules! encode_file_url {
 // " -- &quot;  & -- &amp;  ' -- &#x27;  < -- &lt;  > -- &gt;  / -- &#x2f;
 macro_rules! encode_file_name {
     ($entry:ident) => {
-        escape_html_entity(&$entry.file_name().to_string_lossy())
+        escape_html_entity(&$entry.name)
     };
 }
 
@@ -173,34 +249,24 @@ fn directory_listing(
     let mut body = String::new();
     let base = Path::new(req.path());
 
-    for entry in dir.path.read_dir()? {
-        if dir.is_visible(&entry) {
-            let entry = entry.unwrap();
-            let p = match entry.path().strip_prefix(&dir.path) {
-                Ok(p) => base.join(p),
-                Err(_) => continue,
-            };
+    for entry in dir.entries()? {
+        let p = base.join(&entry.name);
 
-            // if file is a directory, add '/' to the end of the name
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_dir() {
-                    let _ = write!(
-                        body,
-                        "<li><a href=\"{}\">{}/</a></li>",
-                        encode_file_url!(p),
-                        encode_file_name!(entry),
-                    );
-                } else {
-                    let _ = write!(
-                        body,
-                        "<li><a href=\"{}\">{}</a></li>",
-                        encode_file_url!(p),
-                        encode_file_name!(entry),
-                    );
-                }
-            } else {
-                continue;
-            }
+        // if file is a directory, add '/' to the end of the name
+        if entry.metadata.is_dir() {
+            let _ = write!(
+                body,
+                "<li><a href=\"{}\">{}/</a></li>",
+                encode_file_url!(p),
+                encode_file_name!(entry),
+            );
+        } else {
+            let _ = write!(
+                body,
+                "<li><a href=\"{}\">{}</a></li>",
+                encode_file_url!(p),
+                encode_file_name!(entry),
+            );
         }
     }
 
@@ -223,6 +289,12 @@ fn directory_listing(
 
 type MimeOverride = dyn Fn(&mime::Name) -> DispositionType;
 
+type EtagGenerator = dyn Fn(&Path, &Metadata) -> Option<EntityTag>;
+
+type DispositionOverride = dyn Fn(&Path, &mime::Mime) -> ContentDisposition;
+
+type PathFilter = dyn Fn(&Path, &RequestHead) -> bool;
+
 /// Static files handling
 ///
 /// `Files` service must be registered with `App::service()` method.
@@ -241,12 +313,25 @@ pub struct Files {
     directory: PathBuf,
     index: Option<String>,
     show_index: bool,
+    show_hidden: bool,
+    listing_sort: DirectorySort,
     redirect_to_slash: bool,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     renderer: Rc<DirectoryRenderer>,
     mime_override: Option<Rc<MimeOverride>>,
+    etag_generator: Option<Rc<EtagGenerator>>,
+    disposition_override: Option<Rc<DispositionOverride>>,
     file_flags: named::Flags,
+    chunk_size: usize,
+    bandwidth_limit: Option<u64>,
     guards: Option<Rc<Box<dyn Guard>>>,
+    hidden_files: bool,
+    follow_symlinks: bool,
+    restrict_to_directory: bool,
+    path_filter: Option<Rc<PathFilter>>,
+    precompressed: Vec<(ContentEncoding, String)>,
+    cache: Option<(u64, u64)>,
+    embedded: Option<Rc<dyn Embedded>>,
 }
 
 impl Clone for Files {
@@ -255,13 +340,26 @@ impl Clone for Files {
             directory: self.directory.clone(),
             index: self.index.clone(),
             show_index: self.show_index,
+            show_hidden: self.show_hidden,
+            listing_sort: self.listing_sort,
             redirect_to_slash: self.redirect_to_slash,
             default: self.default.clone(),
             renderer: self.renderer.clone(),
             file_flags: self.file_flags,
+            chunk_size: self.chunk_size,
+            bandwidth_limit: self.bandwidth_limit,
             path: self.path.clone(),
             mime_override: self.mime_override.clone(),
+            etag_generator: self.etag_generator.clone(),
+            disposition_override: self.disposition_override.clone(),
             guards: self.guards.clone(),
+            hidden_files: self.hidden_files,
+            follow_symlinks: self.follow_symlinks,
+            restrict_to_directory: self.restrict_to_directory,
+            path_filter: self.path_filter.clone(),
+            precompressed: self.precompressed.clone(),
+            cache: self.cache,
+            embedded: self.embedded.clone(),
         }
     }
 }
@@ -277,18 +375,62 @@ impl Files {
         if !dir.is_dir() {
             log::error!("Specified path is not a directory: {:?}", dir);
         }
+        Files::with_directory(path, dir)
+    }
 
+    fn with_directory(path: &str, dir: PathBuf) -> Files {
         Files {
             path: path.to_string(),
             directory: dir,
             index: None,
             show_index: false,
+            show_hidden: false,
+            listing_sort: DirectorySort::Unsorted,
             redirect_to_slash: false,
             default: Rc::new(RefCell::new(None)),
             renderer: Rc::new(directory_listing),
             mime_override: None,
+            etag_generator: None,
+            disposition_override: None,
             file_flags: named::Flags::default(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            bandwidth_limit: None,
             guards: None,
+            hidden_files: false,
+            follow_symlinks: true,
+            restrict_to_directory: false,
+            path_filter: None,
+            precompressed: Vec::new(),
+            cache: None,
+            embedded: None,
+        }
+    }
+
+    /// Create new `Files` instance for assets compiled into the binary.
+    ///
+    /// Assets are served with the same `ETag`, `Range` and precompressed
+    /// variants support as files on disk. Directory listing is not available,
+    /// requests for hidden or invalid paths are handled as not found.
+    ///
+    /// ```rust
+    /// use std::borrow::Cow;
+    /// use actix_web::App;
+    /// use actix_files::Files;
+    ///
+    /// fn asset(path: &str) -> Option<Cow<'static, [u8]>> {
+    ///     match path {
+    ///         "index.html" => Some(Cow::Borrowed(b"<h1>Hello</h1>")),
+    ///         _ => None,
+    ///     }
+    /// }
+    ///
+    /// let app = App::new()
+    ///     .service(Files::from_embedded("/", asset).index_file("index.html"));
+    /// ```
+    pub fn from_embedded<E: Embedded>(path: &str, assets: E) -> Files {
+        Files {
+            embedded: Some(Rc::new(assets)),
+            ..Files::with_directory(path, PathBuf::new())
         }
     }
 
@@ -308,7 +450,48 @@ impl Files {
         self
     }
 
+    /// Include hidden files (names starting with `.`) into files listing.
+    ///
+    /// Hidden files are excluded by default.
+    pub fn show_hidden_files(mut self) -> Self {
+        self.show_hidden = true;
+        self
+    }
+
+    /// Set order of files listing entries.
+    ///
+    /// By default entries are listed in file system order.
+    pub fn files_listing_sort(mut self, sort: DirectorySort) -> Self {
+        self.listing_sort = sort;
+        self
+    }
+
     /// Set custom directory renderer
+    ///
+    /// Renderer could use [`Directory::entries()`](struct.Directory.html#method.entries)
+    /// to get visible directory entries in configured order.
+    ///
+    /// ```rust
+    /// use actix_web::{dev::ServiceResponse, App, HttpResponse};
+    /// use actix_files::{DirectorySort, Files};
+    ///
+    /// let app = App::new().service(
+    ///     Files::new("/", ".")
+    ///         .show_files_listing()
+    ///         .files_listing_sort(DirectorySort::Name)
+    ///         .files_listing_renderer(|dir, req| {
+    ///             let names = dir
+    ///                 .entries()?
+    ///                 .into_iter()
+    ///                 .map(|entry| entry.name)
+    ///                 .collect::<Vec<_>>();
+    ///             Ok(ServiceResponse::new(
+    ///                 req.clone(),
+    ///                 HttpResponse::Ok().body(names.join("\n")),
+    ///             ))
+    ///         }),
+    /// );
+    /// ```
     pub fn files_listing_renderer<F>(mut self, f: F) -> Self
     where
         for<'r, 's> F: Fn(&'r Directory, &'s HttpRequest) -> Result<ServiceResponse, io::Error>
@@ -327,6 +510,56 @@ impl Files {
         self
     }
 
+    /// Specifies `ETag` generator for files on disk.
+    ///
+    /// Generator receives file path and metadata, `None` disables `ETag`
+    /// for the file. By default `ETag` is computed from file inode, size
+    /// and modification time.
+    ///
+    /// ```rust
+    /// use actix_web::http::header::EntityTag;
+    /// use actix_files::Files;
+    ///
+    /// let files = Files::new("/static", ".").etag_generator(|_path, md| {
+    ///     Some(EntityTag::weak(format!("{:x}", md.len())))
+    /// });
+    /// ```
+    pub fn etag_generator<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, &Metadata) -> Option<EntityTag> + 'static,
+    {
+        self.etag_generator = Some(Rc::new(f));
+        self
+    }
+
+    /// Specifies `Content-Disposition` callback.
+    ///
+    /// Callback receives file path and its content type and computes
+    /// `Content-Disposition` value for the file, it takes precedence
+    /// over `mime_override()`.
+    ///
+    /// ```rust
+    /// use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+    /// use actix_files::Files;
+    ///
+    /// let files = Files::new("/static", ".").content_disposition(|path, _mime| {
+    ///     ContentDisposition {
+    ///         disposition: DispositionType::Attachment,
+    ///         parameters: vec![DispositionParam::Filename(format!(
+    ///             "download-{}",
+    ///             path.file_name().unwrap().to_string_lossy()
+    ///         ))],
+    ///     }
+    /// });
+    /// ```
+    pub fn content_disposition<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, &mime::Mime) -> ContentDisposition + 'static,
+    {
+        self.disposition_override = Some(Rc::new(f));
+        self
+    }
+
     /// Set index file
     ///
     /// Shows specific index file for directory "/" instead of
@@ -354,6 +587,24 @@ impl Files {
         self
     }
 
+    /// Set size of chunks read from files, in bytes.
+    ///
+    /// Larger chunks reduce number of reads for large files at the cost
+    /// of memory per response. Default is 64 KiB.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Limit throughput of each response to `bytes_per_sec`.
+    ///
+    /// [`BandwidthLimit`](struct.BandwidthLimit.html) in request extensions
+    /// overrides this limit. By default throughput is not limited.
+    pub fn bandwidth_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.bandwidth_limit = Some(bytes_per_sec);
+        self
+    }
+
     /// Specifies custom guards to use for directory listings and files.
     ///
     /// Default behaviour allows GET and HEAD.
@@ -372,6 +623,98 @@ impl Files {
         self
     }
 
+    /// Allow access to hidden files, path segments starting with `.`.
+    ///
+    /// By default requests for hidden files are rejected with
+    /// `400 Bad Request`.
+    pub fn serve_hidden_files(mut self) -> Self {
+        self.hidden_files = true;
+        self
+    }
+
+    /// Specifies whether to follow symbolic links or not.
+    ///
+    /// If disabled, requests for paths containing a symbolic link below
+    /// the served directory are rejected with `403 Forbidden`.
+    ///
+    /// Default is true.
+    pub fn follow_symlinks(mut self, value: bool) -> Self {
+        self.follow_symlinks = value;
+        self
+    }
+
+    /// Specifies whether to reject files outside of the served directory.
+    ///
+    /// If enabled, resolved path of requested file must be inside of the
+    /// served directory, otherwise request is rejected with `403 Forbidden`.
+    /// This prevents symbolic links from escaping the directory.
+    ///
+    /// Default is false.
+    pub fn restrict_to_directory(mut self, value: bool) -> Self {
+        self.restrict_to_directory = value;
+        self
+    }
+
+    /// Specifies per-request path filter.
+    ///
+    /// Filter receives requested path relative to the served directory and
+    /// request head, rejected requests are responded with `403 Forbidden`.
+    ///
+    /// ```rust
+    /// use actix_files::Files;
+    ///
+    /// let files = Files::new("/static", ".").path_filter(|path, _| {
+    ///     path.extension().map(|ext| ext != "toml").unwrap_or(true)
+    /// });
+    /// ```
+    pub fn path_filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, &RequestHead) -> bool + 'static,
+    {
+        self.path_filter = Some(Rc::new(f));
+        self
+    }
+
+    /// Serve precompressed files.
+    ///
+    /// If client accepts `br` or `gzip` content encoding and a `<file>.br`
+    /// or `<file>.gz` sibling of requested file exists, the sibling is served
+    /// instead, with corresponding `Content-Encoding` header.
+    ///
+    /// By default precompressed files are not served.
+    pub fn use_precompressed(self) -> Self {
+        self.precompressed_variant(ContentEncoding::Br, ".br")
+            .precompressed_variant(ContentEncoding::Gzip, ".gz")
+    }
+
+    /// Add precompressed file variant.
+    ///
+    /// Variant is a file with `suffix` appended to requested file name, its
+    /// content is encoded with `encoding`. Variants are checked in order
+    /// they were added.
+    pub fn precompressed_variant<T: Into<String>>(
+        mut self,
+        encoding: ContentEncoding,
+        suffix: T,
+    ) -> Self {
+        self.precompressed.push((encoding, suffix.into()));
+        self.file_flags.insert(named::Flags::VARY_ENCODING);
+        self
+    }
+
+    /// Enable in-memory cache for small files.
+    ///
+    /// Files not larger than `max_file_size` bytes are kept in a per-worker
+    /// LRU cache, limited to `max_size` bytes in total, and served without
+    /// touching the filesystem. Cached file is checked once per second and
+    /// it is reloaded if its size or modification time changes.
+    ///
+    /// By default cache is disabled.
+    pub fn use_memory_cache(mut self, max_size: u64, max_file_size: u64) -> Self {
+        self.cache = Some((max_size, max_file_size));
+        self
+    }
+
     /// Sets default handler which is used when no matched file could be found.
     pub fn default_handler<F, U>(mut self, f: F) -> Self
     where
@@ -420,12 +763,28 @@ impl ServiceFactory for Files {
             directory: self.directory.clone(),
             index: self.index.clone(),
             show_index: self.show_index,
+            show_hidden: self.show_hidden,
+            listing_sort: self.listing_sort,
             redirect_to_slash: self.redirect_to_slash,
             default: None,
             renderer: self.renderer.clone(),
             mime_override: self.mime_override.clone(),
+            etag_generator: self.etag_generator.clone(),
+            disposition_override: self.disposition_override.clone(),
             file_flags: self.file_flags,
+            chunk_size: self.chunk_size,
+            bandwidth_limit: self.bandwidth_limit,
             guards: self.guards.clone(),
+            hidden_files: self.hidden_files,
+            follow_symlinks: self.follow_symlinks,
+            restrict_to_directory: self.restrict_to_directory,
+            path_filter: self.path_filter.clone(),
+            precompressed: self.precompressed.clone(),
+            cache: self.cache.map(|(max_size, max_file_size)| {
+                Rc::new(RefCell::new(MemoryCache::new(max_size, max_file_size)))
+            }),
+            embedded: self.embedded.clone(),
+            etags: EtagCache::default(),
         };
 
         if let Some(ref default) = *self.default.borrow() {
@@ -449,15 +808,251 @@ pub struct FilesService {
     directory: PathBuf,
     index: Option<String>,
     show_index: bool,
+    show_hidden: bool,
+    listing_sort: DirectorySort,
     redirect_to_slash: bool,
     default: Option<HttpService>,
     renderer: Rc<DirectoryRenderer>,
     mime_override: Option<Rc<MimeOverride>>,
+    etag_generator: Option<Rc<EtagGenerator>>,
+    disposition_override: Option<Rc<DispositionOverride>>,
     file_flags: named::Flags,
+    chunk_size: usize,
+    bandwidth_limit: Option<u64>,
     guards: Option<Rc<Box<dyn Guard>>>,
+    hidden_files: bool,
+    follow_symlinks: bool,
+    restrict_to_directory: bool,
+    path_filter: Option<Rc<PathFilter>>,
+    precompressed: Vec<(ContentEncoding, String)>,
+    cache: Option<Rc<RefCell<MemoryCache>>>,
+    embedded: Option<Rc<dyn Embedded>>,
+    etags: EtagCache,
 }
 
 impl FilesService {
+    /// Open file, precompressed variant of the file is preferred if client
+    /// accepts its encoding.
+    fn open_file(&self, path: PathBuf, req: &ServiceRequest) -> io::Result<NamedFile> {
+        if req.headers().contains_key(&header::ACCEPT_ENCODING) {
+            let accepted = AcceptedEncodings::from_headers(req.headers());
+            for (encoding, suffix) in &self.precompressed {
+                if !accepts_encoding(&accepted, *encoding) {
+                    continue;
+                }
+                let mut variant = path.clone().into_os_string();
+                variant.push(suffix);
+                let variant = PathBuf::from(variant);
+                if variant.is_file() && self.is_variant_allowed(&variant) {
+                    return NamedFile::from_file(File::open(variant)?, &path)
+                        .map(|file| file.set_precompressed(*encoding));
+                }
+            }
+        }
+        NamedFile::open(path)
+    }
+
+    /// Apply symlink and directory restrictions to precompressed variant.
+    fn is_variant_allowed(&self, variant: &Path) -> bool {
+        if !self.follow_symlinks {
+            match variant.symlink_metadata() {
+                Ok(ref md) if !md.file_type().is_symlink() => (),
+                _ => return false,
+            }
+        }
+        if self.restrict_to_directory {
+            match variant.canonicalize() {
+                Ok(path) => path.starts_with(&self.directory),
+                Err(_) => false,
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Memory cache key of the request, served file depends on requested
+    /// path and precompressed variants accepted by client.
+    fn cache_key(&self, req: &ServiceRequest) -> CacheKey {
+        let mut variants = 0;
+        if req.headers().contains_key(&header::ACCEPT_ENCODING) {
+            let accepted = AcceptedEncodings::from_headers(req.headers());
+            for (idx, (encoding, _)) in self.precompressed.iter().take(64).enumerate() {
+                if accepts_encoding(&accepted, *encoding) {
+                    variants |= 1 << idx;
+                }
+            }
+        }
+        (req.match_info().path().to_owned(), variants)
+    }
+
+    fn serve_file(
+        &self,
+        mut named_file: NamedFile,
+        key: Option<CacheKey>,
+        req: ServiceRequest,
+    ) -> Either<
+        Ready<Result<ServiceResponse, Error>>,
+        LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+    > {
+        if let Some(ref mime_override) = self.mime_override {
+            let new_disposition = mime_override(&named_file.content_type.type_());
+            named_file.content_disposition.disposition = new_disposition;
+        }
+
+        if let Some(ref disposition_override) = self.disposition_override {
+            named_file.content_disposition =
+                disposition_override(named_file.path(), &named_file.content_type);
+        }
+
+        named_file.flags = self.file_flags;
+        named_file.chunk_size = self.chunk_size;
+        named_file.bandwidth_limit = self.bandwidth_limit;
+        if let Some(ref etag_generator) = self.etag_generator {
+            match etag_generator(named_file.path(), &named_file.md) {
+                Some(etag) => named_file.custom_etag = Some(etag),
+                None => named_file.flags.remove(named::Flags::ETAG),
+            }
+        }
+        let (req, _) = req.into_parts();
+
+        let len = named_file.md.len();
+        let (cache, key) = match (&self.cache, key) {
+            (Some(cache), Some(key)) if cache.borrow().is_cacheable(len) => {
+                (cache.clone(), key)
+            }
+            _ => {
+                let head = named_file.head();
+                return Either::Left(ok(file_response(
+                    head,
+                    FileBody::File(named_file.file),
+                    req,
+                )));
+            }
+        };
+
+        // served file is revalidated by path of precompressed variant
+        let mut path = named_file.path().as_os_str().to_owned();
+        if let Some(encoding) = named_file.precompressed {
+            if let Some((_, suffix)) =
+                self.precompressed.iter().find(|(enc, _)| *enc == encoding)
+            {
+                path.push(suffix);
+            }
+        }
+        let path = PathBuf::from(path);
+
+        // read file on thread pool and store it in cache
+        let head = named_file.head();
+        let md = named_file.md.clone();
+        let mut file = named_file.file;
+        Either::Right(
+            web::block(move || {
+                let mut buf = Vec::with_capacity(md.len() as usize);
+                file.read_to_end(&mut buf)?;
+                Ok::<_, io::Error>((Bytes::from(buf), md))
+            })
+            .map(move |res| match res {
+                Ok(Ok((data, md))) => {
+                    cache.borrow_mut().insert(
+                        key,
+                        path,
+                        &md,
+                        head.clone(),
+                        data.clone(),
+                    );
+                    Ok(file_response(head, FileBody::Bytes(data), req))
+                }
+                Ok(Err(e)) => Ok(ServiceResponse::from_err(e, req)),
+                Err(e) => Ok(ServiceResponse::from_err(e, req)),
+            })
+            .boxed_local(),
+        )
+    }
+
+    fn serve_embedded(
+        &mut self,
+        assets: &dyn Embedded,
+        path: &Path,
+        req: ServiceRequest,
+    ) -> Either<
+        Ready<Result<ServiceResponse, Error>>,
+        LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+    > {
+        let mut name = path
+            .iter()
+            .map(|seg| seg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut found = if name.is_empty() || req.path().ends_with('/') {
+            None
+        } else {
+            assets.get(&name)
+        };
+
+        if found.is_none() {
+            if let Some(ref index) = self.index {
+                let index_name = if name.is_empty() {
+                    index.clone()
+                } else {
+                    format!("{}/{}", name, index)
+                };
+                found = assets.get(&index_name);
+                if found.is_some() {
+                    if self.redirect_to_slash && !req.path().ends_with('/') {
+                        let redirect_to = format!("{}/", req.path());
+                        return Either::Left(ok(req.into_response(
+                            HttpResponse::Found()
+                                .header(header::LOCATION, redirect_to)
+                                .body("")
+                                .into_body(),
+                        )));
+                    }
+                    name = index_name;
+                }
+            }
+        }
+
+        let mut data = match found {
+            Some(data) => data,
+            None => return self.handle_err(io::ErrorKind::NotFound.into(), req),
+        };
+
+        // precompressed variant is preferred if client accepts its encoding
+        let mut precompressed = None;
+        if req.headers().contains_key(&header::ACCEPT_ENCODING) {
+            let accepted = AcceptedEncodings::from_headers(req.headers());
+            for (encoding, suffix) in &self.precompressed {
+                if !accepts_encoding(&accepted, *encoding) {
+                    continue;
+                }
+                if let Some(variant) = assets.get(&format!("{}{}", name, suffix)) {
+                    data = variant;
+                    precompressed = Some(*encoding);
+                    break;
+                }
+            }
+        }
+
+        let flags = self.file_flags;
+        let (mut head, body) = match embedded_file(&name, data, flags, &mut self.etags) {
+            Ok(file) => file,
+            Err(e) => return self.handle_err(e, req),
+        };
+        if let Some(ref mime_override) = self.mime_override {
+            let new_disposition = mime_override(&head.content_type.type_());
+            head.content_disposition.disposition = new_disposition;
+        }
+        if let Some(ref disposition_override) = self.disposition_override {
+            head.content_disposition =
+                disposition_override(Path::new(&name), &head.content_type);
+        }
+        head.precompressed = precompressed;
+        head.bandwidth_limit = self.bandwidth_limit;
+
+        Either::Left(ok(file_response(head, body, req.into_parts().0)))
+    }
+
     fn handle_err(
         &mut self,
         e: io::Error,
@@ -508,10 +1103,39 @@ impl Service for FilesService {
             )));
         }
 
-        let real_path = match PathBufWrp::get_pathbuf(req.match_info().path()) {
-            Ok(item) => item,
-            Err(e) => return Either::Left(ok(req.error_response(e))),
-        };
+        let real_path =
+            match PathBufWrp::parse(req.match_info().path(), self.hidden_files) {
+                Ok(item) => item,
+                // embedded assets have no hidden files
+                Err(_) if self.embedded.is_some() => {
+                    return self.handle_err(io::ErrorKind::NotFound.into(), req);
+                }
+                Err(e) => return Either::Left(ok(req.error_response(e))),
+            };
+
+        if let Some(ref path_filter) = self.path_filter {
+            if !path_filter(&real_path.0, req.head()) {
+                return self.handle_err(io::ErrorKind::PermissionDenied.into(), req);
+            }
+        }
+
+        if let Some(assets) = self.embedded.clone() {
+            return self.serve_embedded(&*assets, &real_path.0, req);
+        }
+
+        // cached file is served without touching the filesystem
+        let key = self.cache.as_ref().map(|_| self.cache_key(&req));
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            let cached = cache.borrow_mut().get(key);
+            if let Some((head, data)) = cached {
+                let body = FileBody::Bytes(data);
+                return Either::Left(ok(file_response(head, body, req.into_parts().0)));
+            }
+        }
+
+        if !self.follow_symlinks && has_symlink(&self.directory, &real_path.0) {
+            return self.handle_err(io::ErrorKind::PermissionDenied.into(), req);
+        }
 
         // full filepath
         let path = match self.directory.join(&real_path.0).canonicalize() {
@@ -519,6 +1143,10 @@ impl Service for FilesService {
             Err(e) => return self.handle_err(e, req),
         };
 
+        if self.restrict_to_directory && !path.starts_with(&self.directory) {
+            return self.handle_err(io::ErrorKind::PermissionDenied.into(), req);
+        }
+
         if path.is_dir() {
             if let Some(ref redir_index) = self.index {
                 if self.redirect_to_slash && !req.path().ends_with('/') {
@@ -531,27 +1159,19 @@ impl Service for FilesService {
                     )));
                 }
 
+                if !self.follow_symlinks && has_symlink(&path, Path::new(redir_index)) {
+                    return self.handle_err(io::ErrorKind::PermissionDenied.into(), req);
+                }
                 let path = path.join(redir_index);
 
-                match NamedFile::open(path) {
-                    Ok(mut named_file) => {
-                        if let Some(ref mime_override) = self.mime_override {
-                            let new_disposition =
-                                mime_override(&named_file.content_type.type_());
-                            named_file.content_disposition.disposition = new_disposition;
-                        }
-
-                        named_file.flags = self.file_flags;
-                        let (req, _) = req.into_parts();
-                        Either::Left(ok(match named_file.into_response(&req) {
-                            Ok(item) => ServiceResponse::new(req, item),
-                            Err(e) => ServiceResponse::from_err(e, req),
-                        }))
-                    }
+                match self.open_file(path, &req) {
+                    Ok(named_file) => self.serve_file(named_file, key, req),
                     Err(e) => self.handle_err(e, req),
                 }
             } else if self.show_index {
-                let dir = Directory::new(self.directory.clone(), path);
+                let mut dir = Directory::new(self.directory.clone(), path);
+                dir.show_hidden = self.show_hidden;
+                dir.sort = self.listing_sort;
                 let (req, _) = req.into_parts();
                 let x = (self.renderer)(&dir, &req);
                 match x {
@@ -565,39 +1185,55 @@ impl Service for FilesService {
                 )))
             }
         } else {
-            match NamedFile::open(path) {
-                Ok(mut named_file) => {
-                    if let Some(ref mime_override) = self.mime_override {
-                        let new_disposition =
-                            mime_override(&named_file.content_type.type_());
-                        named_file.content_disposition.disposition = new_disposition;
-                    }
-
-                    named_file.flags = self.file_flags;
-                    let (req, _) = req.into_parts();
-                    match named_file.into_response(&req) {
-                        Ok(item) => {
-                            Either::Left(ok(ServiceResponse::new(req.clone(), item)))
-                        }
-                        Err(e) => Either::Left(ok(ServiceResponse::from_err(e, req))),
-                    }
-                }
+            match self.open_file(path, &req) {
+                Ok(named_file) => self.serve_file(named_file, key, req),
                 Err(e) => self.handle_err(e, req),
             }
         }
     }
 }
 
+/// Check if any component of `path` below `base` is a symbolic link.
+fn has_symlink(base: &Path, path: &Path) -> bool {
+    let mut current = base.to_path_buf();
+    for component in path.components() {
+        current.push(component);
+        if let Ok(md) = current.symlink_metadata() {
+            if md.file_type().is_symlink() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn file_response(head: FileHead, body: FileBody, req: HttpRequest) -> ServiceResponse {
+    match head.respond(body, &req) {
+        Ok(item) => ServiceResponse::new(req, item),
+        Err(e) => ServiceResponse::from_err(e, req),
+    }
+}
+
+/// Returns true if `Accept-Encoding` header allows `encoding`, explicit
+/// encoding takes precedence over `*`.
+fn accepts_encoding(accepted: &AcceptedEncodings, encoding: ContentEncoding) -> bool {
+    accepted.quality(encoding) > 0.0
+}
+
 #[derive(Debug)]
 struct PathBufWrp(PathBuf);
 
 impl PathBufWrp {
     fn get_pathbuf(path: &str) -> Result<Self, UriSegmentError> {
+        PathBufWrp::parse(path, false)
+    }
+
+    fn parse(path: &str, hidden_files: bool) -> Result<Self, UriSegmentError> {
         let mut buf = PathBuf::new();
         for segment in path.split('/') {
             if segment == ".." {
                 buf.pop();
-            } else if segment.starts_with('.') {
+            } else if segment.starts_with('.') && (!hidden_files || segment == ".") {
                 return Err(UriSegmentError::BadStart('.'));
             } else if segment.starts_with('*') {
                 return Err(UriSegmentError::BadStart('*'));
@@ -632,10 +1268,11 @@ impl FromRequest for PathBufWrp {
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
     use std::fs;
     use std::iter::FromIterator;
     use std::ops::Add;
-    use std::time::{Duration, SystemTime};
+    use std::time::{Duration, Instant, SystemTime};
 
     use super::*;
     use actix_web::guard;
@@ -645,7 +1282,8 @@ mod tests {
     use actix_web::http::{Method, StatusCode};
     use actix_web::middleware::Compress;
     use actix_web::test::{self, block_on, TestRequest};
-    use actix_web::{App, Responder};
+    use actix_web::{App, HttpMessage, Responder};
+    use futures::StreamExt;
 
     #[test]
     fn test_file_extension_to_mime() {
@@ -939,6 +1577,131 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_content_disposition_override() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(
+                    Files::new("/", ".")
+                        .mime_override(|_| DispositionType::Inline)
+                        .content_disposition(|path, mime| {
+                            assert_eq!(*mime, mime::IMAGE_PNG);
+                            ContentDisposition {
+                                disposition: DispositionType::Attachment,
+                                parameters: vec![DispositionParam::Filename(format!(
+                                    "download-{}",
+                                    path.file_name().unwrap().to_string_lossy()
+                                ))],
+                            }
+                        }),
+                ),
+            )
+            .await;
+
+            let request = TestRequest::get().uri("/tests/test.png").to_request();
+            let response = test::call_service(&mut srv, request).await;
+            assert_eq!(
+                response.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+                "attachment; filename=\"download-test.png\""
+            );
+        })
+    }
+
+    #[test]
+    fn test_etag_generator() {
+        block_on(async {
+            let mut srv = test::init_service(App::new().service(
+                Files::new("/", ".").etag_generator(|path, md| {
+                    if path.ends_with("test.png") {
+                        Some(EntityTag::strong(format!("png-{}", md.len())))
+                    } else {
+                        None
+                    }
+                }),
+            ))
+            .await;
+
+            let request = TestRequest::get().uri("/tests/test.png").to_request();
+            let response = test::call_service(&mut srv, request).await;
+            assert_eq!(response.headers().get(header::ETAG).unwrap(), "\"png-168\"");
+
+            let request = TestRequest::get()
+                .uri("/tests/test.png")
+                .header(header::IF_NONE_MATCH, "\"png-168\"")
+                .to_request();
+            let response = test::call_service(&mut srv, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+            let request = TestRequest::get().uri("/tests/test.binary").to_request();
+            let response = test::call_service(&mut srv, request).await;
+            assert!(response.headers().get(header::ETAG).is_none());
+            assert!(response.headers().get(header::LAST_MODIFIED).is_some());
+        })
+    }
+
+    #[test]
+    fn test_chunked_read_file_chunk_size() {
+        block_on(async {
+            let file = NamedFile::open("tests/test.binary")
+                .unwrap()
+                .set_chunk_size(30);
+            assert_eq!(file.head().chunk_size, 30);
+
+            let stream = ChunkedReadFile {
+                size: 100,
+                offset: 0,
+                chunk_size: file.chunk_size,
+                file: Some(file.file),
+                fut: None,
+                counter: 0,
+            };
+            let chunks = stream
+                .map(|chunk| chunk.unwrap().len())
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(chunks, vec![30, 30, 30, 10]);
+        })
+    }
+
+    #[test]
+    fn test_files_bandwidth_limit() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(
+                    Files::new("/", ".").chunk_size(50).bandwidth_limit(500),
+                ),
+            )
+            .await;
+            let data = Bytes::from(fs::read("tests/test.binary").unwrap());
+
+            // second chunk is sent after 100ms
+            let start = Instant::now();
+            let req = TestRequest::with_uri("/tests/test.binary").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(test::read_body(resp).await, data);
+            assert!(start.elapsed() >= Duration::from_millis(90));
+
+            let req = TestRequest::with_uri("/tests/test.binary").to_request();
+            req.extensions_mut().insert(BandwidthLimit::unlimited());
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(test::read_body(resp).await, data);
+        })
+    }
+
+    #[test]
+    fn test_named_file_set_etag() {
+        block_on(async {
+            let file = NamedFile::open("Cargo.toml")
+                .unwrap()
+                .use_etag(false)
+                .set_etag(EntityTag::weak("custom".to_owned()));
+
+            let req = TestRequest::default().to_http_request();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.headers().get(header::ETAG).unwrap(), "W/\"custom\"");
+        })
+    }
+
     #[test]
     fn test_named_file_ranges_status_code() {
         block_on(async {
@@ -1009,6 +1772,127 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_named_file_multiple_ranges() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new()
+                    .service(Files::new("/test", ".").index_file("tests/test.binary")),
+            )
+            .await;
+
+            let request = TestRequest::get()
+                .uri("/t%65st/tests/test.binary")
+                .header(header::RANGE, "bytes=0-1, -2")
+                .to_request();
+            let response = test::call_service(&mut srv, request).await;
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+            let ct = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned();
+            assert!(ct.starts_with("multipart/byteranges; boundary="));
+            let boundary = &ct["multipart/byteranges; boundary=".len()..];
+
+            let data = fs::read("tests/test.binary").unwrap();
+            let mut expected = Vec::new();
+            expected.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Type: application/octet-stream\r\n\
+                     Content-Range: bytes 0-1/100\r\n\r\n",
+                    boundary
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[..2]);
+            expected.extend_from_slice(
+                format!(
+                    "\r\n--{}\r\nContent-Type: application/octet-stream\r\n\
+                     Content-Range: bytes 98-99/100\r\n\r\n",
+                    boundary
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[98..]);
+            expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+            let bytes = test::read_body(response).await;
+            assert_eq!(bytes, Bytes::from(expected));
+        })
+    }
+
+    #[test]
+    fn test_named_file_ranges_limit() {
+        block_on(async {
+            let data = fs::read("tests/test.binary").unwrap();
+
+            // overlapping ranges are merged into single range
+            let req = TestRequest::default()
+                .header(header::RANGE, "bytes=0-5,3-9,10-19")
+                .to_http_request();
+            let file = NamedFile::open("tests/test.binary").unwrap();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_RANGE).unwrap(),
+                "bytes 0-19/100"
+            );
+            let bytes = test::read_body(ServiceResponse::new(req, resp)).await;
+            assert_eq!(bytes, Bytes::from(&data[..20]));
+
+            // too many ranges, full content is served
+            let ranges = (0..17)
+                .map(|i| format!("{}-{}", i * 3, i * 3))
+                .collect::<Vec<_>>()
+                .join(",");
+            let req = TestRequest::default()
+                .header(header::RANGE, format!("bytes={}", ranges))
+                .to_http_request();
+            let file = NamedFile::open("tests/test.binary").unwrap();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().get(header::CONTENT_RANGE).is_none());
+            let bytes = test::read_body(ServiceResponse::new(req, resp)).await;
+            assert_eq!(bytes, Bytes::from(data));
+        })
+    }
+
+    #[test]
+    fn test_named_file_if_range() {
+        block_on(async {
+            let file = NamedFile::open("tests/test.binary").unwrap();
+            let etag = file.etag().unwrap();
+            let lm = file.last_modified().unwrap();
+
+            let req = TestRequest::default()
+                .header(header::RANGE, "bytes=10-20")
+                .header(header::IF_RANGE, etag.to_string())
+                .to_http_request();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+            let file = NamedFile::open("tests/test.binary").unwrap();
+            let req = TestRequest::default()
+                .header(header::RANGE, "bytes=10-20")
+                .header(header::IF_RANGE, lm.to_string())
+                .to_http_request();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+            let file = NamedFile::open("tests/test.binary").unwrap();
+            let req = TestRequest::default()
+                .header(header::RANGE, "bytes=10-20")
+                .header(header::IF_RANGE, "\"miss_etag\"")
+                .to_http_request();
+            let resp = file.respond_to(&req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().get(header::CONTENT_RANGE).is_none());
+        })
+    }
+
     #[test]
     fn test_named_file_content_length_headers() {
         block_on(async {
@@ -1227,6 +2111,197 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_files_precompressed() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(Files::new("/", ".").use_precompressed()),
+            )
+            .await;
+
+            let request = TestRequest::get()
+                .uri("/tests/precompressed.txt")
+                .header(header::ACCEPT_ENCODING, "br;q=0, gzip")
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+            assert_eq!(res.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+            assert_eq!(
+                res.headers().get(header::CONTENT_TYPE).unwrap(),
+                "text/plain"
+            );
+            let bytes = test::read_body(res).await;
+            let data = Bytes::from(fs::read("tests/precompressed.txt.gz").unwrap());
+            assert_eq!(bytes, data);
+
+            let request = TestRequest::get()
+                .uri("/tests/precompressed.txt")
+                .header(header::ACCEPT_ENCODING, "br")
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+            assert_eq!(res.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+            let bytes = test::read_body(res).await;
+            let data = Bytes::from(fs::read("tests/precompressed.txt").unwrap());
+            assert_eq!(bytes, data);
+        })
+    }
+
+    #[test]
+    fn test_files_memory_cache() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(Files::new("/", ".").use_memory_cache(1024, 512)),
+            )
+            .await;
+
+            let data = Bytes::from(fs::read("tests/test.binary").unwrap());
+            for _ in 0..2 {
+                let request = TestRequest::get().uri("/tests/test.binary").to_request();
+                let res = test::call_service(&mut srv, request).await;
+                assert_eq!(res.status(), StatusCode::OK);
+                assert!(res.headers().contains_key(header::ETAG));
+                let bytes = test::read_body(res).await;
+                assert_eq!(bytes, data);
+            }
+
+            // range request is served from cache
+            let request = TestRequest::get()
+                .uri("/tests/test.binary")
+                .header(header::RANGE, "bytes=10-20")
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, data.slice(10, 21));
+
+            // large file is not cached
+            let request = TestRequest::get().uri("/Cargo.toml").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, Bytes::from(fs::read("Cargo.toml").unwrap()));
+        })
+    }
+
+    #[test]
+    fn test_files_memory_cache_hit() {
+        block_on(async {
+            let dir = std::env::temp_dir()
+                .join(format!("actix-files-cache-{}", rand::random::<u64>()));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("data.txt"), b"cached").unwrap();
+
+            let mut srv = test::init_service(
+                App::new().service(Files::new("/", &dir).use_memory_cache(1024, 512)),
+            )
+            .await;
+            let request = TestRequest::get().uri("/data.txt").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(test::read_body(res).await, Bytes::from_static(b"cached"));
+
+            // cache hit does not open the file
+            fs::remove_dir_all(&dir).unwrap();
+            let request = TestRequest::get().uri("/data.txt").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(test::read_body(res).await, Bytes::from_static(b"cached"));
+        })
+    }
+
+    fn test_asset(path: &str) -> Option<Cow<'static, [u8]>> {
+        match path {
+            "index.html" => Some(Cow::Borrowed(b"<h1>index</h1>")),
+            "docs/index.html" => Some(Cow::Borrowed(b"<h1>docs</h1>")),
+            "data.txt" => Some(Cow::Owned(b"0123456789".to_vec())),
+            "data.txt.gz" => Some(Cow::Borrowed(b"gzipped")),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_files_embedded() {
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(
+                    Files::from_embedded("/", test_asset)
+                        .index_file("index.html")
+                        .redirect_to_slash_directory()
+                        .use_precompressed(),
+                ),
+            )
+            .await;
+
+            let request = TestRequest::get().uri("/").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get(header::CONTENT_TYPE).unwrap(),
+                "text/html"
+            );
+            let etag = res.headers().get(header::ETAG).unwrap().clone();
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, Bytes::from_static(b"<h1>index</h1>"));
+
+            let request = TestRequest::get()
+                .uri("/")
+                .header(header::IF_NONE_MATCH, etag)
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+            let request = TestRequest::get().uri("/docs").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::FOUND);
+
+            let request = TestRequest::get().uri("/docs/").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, Bytes::from_static(b"<h1>docs</h1>"));
+
+            let request = TestRequest::get()
+                .uri("/data.txt")
+                .header(header::RANGE, "bytes=2-4")
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, Bytes::from_static(b"234"));
+
+            let request = TestRequest::get()
+                .uri("/data.txt")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+            let bytes = test::read_body(res).await;
+            assert_eq!(bytes, Bytes::from_static(b"gzipped"));
+
+            let request = TestRequest::get().uri("/missing.txt").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+            let request = TestRequest::get().uri("/.hidden").to_request();
+            let res = test::call_service(&mut srv, request).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        })
+    }
+
+    #[test]
+    fn test_accepts_encoding() {
+        let accepts = |raw: &str, encoding| {
+            accepts_encoding(&AcceptedEncodings::parse(raw), encoding)
+        };
+        assert!(accepts("gzip, deflate", ContentEncoding::Gzip));
+        assert!(accepts("*", ContentEncoding::Br));
+        assert!(!accepts("gzip;q=0", ContentEncoding::Gzip));
+        assert!(!accepts("deflate", ContentEncoding::Br));
+        assert!(!accepts("br;q=0, *", ContentEncoding::Br));
+        assert!(!accepts("*, br;q=0", ContentEncoding::Br));
+        assert!(accepts("br;q=0, *", ContentEncoding::Gzip));
+    }
+
     #[test]
     fn test_named_file_allowed_method() {
         block_on(async {
@@ -1272,6 +2347,161 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_files_listing_renderer() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("sub")).unwrap();
+        fs::write(root.path().join("sub/b.txt"), b"b").unwrap();
+        fs::write(root.path().join("sub/a.txt"), b"a").unwrap();
+        fs::write(root.path().join("sub/c d.bin"), b"c").unwrap();
+
+        block_on(async {
+            let mut srv = test::init_service(
+                App::new().service(
+                    Files::new("/", root.path())
+                        .show_files_listing()
+                        .files_listing_sort(DirectorySort::Name)
+                        .files_listing_renderer(|dir, req| {
+                            let names = dir
+                                .entries()?
+                                .into_iter()
+                                .map(|entry| entry.name)
+                                .collect::<Vec<_>>();
+                            Ok(ServiceResponse::new(
+                                req.clone(),
+                                HttpResponse::Ok().body(names.join(",")),
+                            ))
+                        }),
+                ),
+            )
+            .await;
+            let req = TestRequest::with_uri("/sub").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            let bytes = test::read_body(resp).await;
+            assert_eq!(bytes, Bytes::from_static(b"a.txt,b.txt,c d.bin"));
+        })
+    }
+
+    #[test]
+    fn test_directory_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_path_buf();
+        fs::create_dir(path.join("sub")).unwrap();
+        fs::write(path.join(".hidden"), b"1").unwrap();
+        fs::write(path.join("big"), b"1234").unwrap();
+        fs::write(path.join("a"), b"12").unwrap();
+
+        let mut dir = Directory::new(path.clone(), path.clone());
+        let names = |dir: &Directory| {
+            dir.entries()
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+
+        dir.sort = DirectorySort::Name;
+        assert_eq!(names(&dir), vec!["a", "big", "sub"]);
+
+        dir.show_hidden = true;
+        assert_eq!(names(&dir), vec![".hidden", "a", "big", "sub"]);
+
+        dir.show_hidden = false;
+        dir.sort = DirectorySort::Size;
+        let entries = names(&dir);
+        let pos = |name: &str| entries.iter().position(|n| n == name).unwrap();
+        assert!(pos("a") < pos("big"));
+    }
+
+    #[test]
+    fn test_path_filter() {
+        block_on(async {
+            let mut srv = test::init_service(App::new().service(
+                Files::new("/", ".").path_filter(|path, head| {
+                    assert_eq!(head.method, Method::GET);
+                    path.extension().map(|ext| ext != "toml").unwrap_or(true)
+                }),
+            ))
+            .await;
+
+            let req = TestRequest::with_uri("/Cargo.toml").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let req = TestRequest::with_uri("/tests/test.png").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_policy() {
+        let root = tempfile::tempdir().unwrap();
+        let base = root.path();
+        fs::create_dir(base.join("public")).unwrap();
+        fs::write(base.join("secret.txt"), b"secret").unwrap();
+        fs::write(base.join("public/file.txt"), b"file").unwrap();
+        fs::write(base.join("public/.env"), b"env").unwrap();
+        std::os::unix::fs::symlink(
+            base.join("secret.txt"),
+            base.join("public/link.txt"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            base.join("secret.txt"),
+            base.join("public/file.txt.gz"),
+        )
+        .unwrap();
+
+        block_on(async {
+            let public = base.join("public");
+            let status = |files: Files, uri: &'static str| async move {
+                let mut srv = test::init_service(App::new().service(files)).await;
+                let req = TestRequest::with_uri(uri).to_request();
+                test::call_service(&mut srv, req).await.status()
+            };
+
+            let files = || Files::new("/", &public);
+            assert_eq!(status(files(), "/link.txt").await, StatusCode::OK);
+            assert_eq!(status(files(), "/.env").await, StatusCode::BAD_REQUEST);
+
+            let files = || Files::new("/", &public).follow_symlinks(false);
+            assert_eq!(status(files(), "/link.txt").await, StatusCode::FORBIDDEN);
+            assert_eq!(status(files(), "/file.txt").await, StatusCode::OK);
+
+            let files = || Files::new("/", &public).restrict_to_directory(true);
+            assert_eq!(status(files(), "/link.txt").await, StatusCode::FORBIDDEN);
+            assert_eq!(status(files(), "/file.txt").await, StatusCode::OK);
+
+            let files = || Files::new("/", &public).serve_hidden_files();
+            assert_eq!(status(files(), "/.env").await, StatusCode::OK);
+            assert_eq!(
+                status(files(), "/./file.txt").await,
+                StatusCode::BAD_REQUEST
+            );
+
+            // precompressed variant is subject to the same restrictions
+            let body = |files: Files| async move {
+                let mut srv = test::init_service(App::new().service(files)).await;
+                let req = TestRequest::with_uri("/file.txt")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .to_request();
+                test::read_body(test::call_service(&mut srv, req).await).await
+            };
+            let files = || Files::new("/", &public).use_precompressed();
+            assert_eq!(body(files()).await, Bytes::from_static(b"secret"));
+            assert_eq!(
+                body(files().follow_symlinks(false)).await,
+                Bytes::from_static(b"file")
+            );
+            assert_eq!(
+                body(files().restrict_to_directory(true)).await,
+                Bytes::from_static(b"file")
+            );
+        });
+    }
+
     #[test]
     fn test_redirect_to_slash_directory() {
         block_on(async {
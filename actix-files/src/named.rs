@@ -2,7 +2,10 @@ use std::fs::{File, Metadata};
 use std::io;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::vec;
 
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
@@ -12,22 +15,29 @@ use mime;
 use mime_guess::from_path;
 
 use actix_http::body::SizedStream;
+use actix_web::dev::HttpResponseBuilder;
 use actix_web::http::header::{
     self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
 };
 use actix_web::http::{ContentEncoding, StatusCode};
 use actix_web::middleware::BodyEncoding;
 use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, Responder};
-use futures::future::{ready, Ready};
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, ready, Ready};
+use futures::stream::{self, LocalBoxStream, Stream, StreamExt};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 
 use crate::range::HttpRange;
-use crate::ChunkedReadFile;
+use crate::throttle::{BandwidthLimit, Throttled};
+use crate::{ChunkedReadFile, DEFAULT_CHUNK_SIZE};
 
 bitflags! {
     pub(crate) struct Flags: u8 {
         const ETAG = 0b0000_0001;
         const LAST_MD = 0b0000_0010;
         const CONTENT_DISPOSITION = 0b0000_0100;
+        const VARY_ENCODING = 0b0000_1000;
     }
 }
 
@@ -41,7 +51,7 @@ impl Default for Flags {
 #[derive(Debug)]
 pub struct NamedFile {
     path: PathBuf,
-    file: File,
+    pub(crate) file: File,
     modified: Option<SystemTime>,
     pub(crate) md: Metadata,
     pub(crate) flags: Flags,
@@ -49,6 +59,10 @@ pub struct NamedFile {
     pub(crate) content_type: mime::Mime,
     pub(crate) content_disposition: header::ContentDisposition,
     pub(crate) encoding: Option<ContentEncoding>,
+    pub(crate) precompressed: Option<ContentEncoding>,
+    pub(crate) custom_etag: Option<header::EntityTag>,
+    pub(crate) chunk_size: usize,
+    pub(crate) bandwidth_limit: Option<u64>,
 }
 
 impl NamedFile {
@@ -75,40 +89,7 @@ impl NamedFile {
     /// ```
     pub fn from_file<P: AsRef<Path>>(file: File, path: P) -> io::Result<NamedFile> {
         let path = path.as_ref().to_path_buf();
-
-        // Get the name of the file and use it to construct default Content-Type
-        // and Content-Disposition values
-        let (content_type, content_disposition) = {
-            let filename = match path.file_name() {
-                Some(name) => name.to_string_lossy(),
-                None => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Provided path has no filename",
-                    ));
-                }
-            };
-
-            let ct = from_path(&path).first_or_octet_stream();
-            let disposition_type = match ct.type_() {
-                mime::IMAGE | mime::TEXT | mime::VIDEO => DispositionType::Inline,
-                _ => DispositionType::Attachment,
-            };
-            let mut parameters =
-                vec![DispositionParam::Filename(String::from(filename.as_ref()))];
-            if !filename.is_ascii() {
-                parameters.push(DispositionParam::FilenameExt(ExtendedValue {
-                    charset: Charset::Ext(String::from("UTF-8")),
-                    language_tag: None,
-                    value: filename.into_owned().into_bytes(),
-                }))
-            }
-            let cd = ContentDisposition {
-                disposition: disposition_type,
-                parameters: parameters,
-            };
-            (ct, cd)
-        };
+        let (content_type, content_disposition) = content_headers(&path)?;
 
         let md = file.metadata()?;
         let modified = md.modified().ok();
@@ -121,6 +102,10 @@ impl NamedFile {
             md,
             modified,
             encoding,
+            precompressed: None,
+            custom_etag: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            bandwidth_limit: None,
             status_code: StatusCode::OK,
             flags: Flags::default(),
         })
@@ -208,6 +193,16 @@ impl NamedFile {
         self
     }
 
+    /// Mark file content as already encoded with `enc`.
+    ///
+    /// File is served as is, with `Content-Encoding` header set to `enc`,
+    /// and is not compressed on the fly.
+    #[inline]
+    pub fn set_precompressed(mut self, enc: ContentEncoding) -> Self {
+        self.precompressed = Some(enc);
+        self
+    }
+
     #[inline]
     ///Specifies whether to use ETag or not.
     ///
@@ -217,6 +212,35 @@ impl NamedFile {
         self
     }
 
+    /// Set size of chunks read from the file, in bytes.
+    ///
+    /// Default is 64 KiB.
+    #[inline]
+    pub fn set_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Limit response throughput to `bytes_per_sec`.
+    ///
+    /// [`BandwidthLimit`](struct.BandwidthLimit.html) in request extensions
+    /// overrides this limit. By default throughput is not limited.
+    #[inline]
+    pub fn set_bandwidth_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.bandwidth_limit = Some(bytes_per_sec);
+        self
+    }
+
+    /// Set custom `ETag` for serving this file.
+    ///
+    /// By default `ETag` is computed from file metadata.
+    #[inline]
+    pub fn set_etag(mut self, etag: header::EntityTag) -> Self {
+        self.custom_etag = Some(etag);
+        self.flags.insert(Flags::ETAG);
+        self
+    }
+
     #[inline]
     ///Specifies whether to use Last-Modified or not.
     ///
@@ -227,6 +251,10 @@ impl NamedFile {
     }
 
     pub(crate) fn etag(&self) -> Option<header::EntityTag> {
+        if let Some(ref etag) = self.custom_etag {
+            return Some(etag.clone());
+        }
+
         // This etag format is similar to Apache's.
         self.modified.as_ref().map(|mtime| {
             let ino = {
@@ -258,6 +286,141 @@ impl NamedFile {
     }
 
     pub fn into_response(self, req: &HttpRequest) -> Result<HttpResponse, Error> {
+        self.head().respond(FileBody::File(self.file), req)
+    }
+
+    /// Response parameters of this file.
+    pub(crate) fn head(&self) -> FileHead {
+        FileHead {
+            status_code: self.status_code,
+            content_type: self.content_type.clone(),
+            content_disposition: self.content_disposition.clone(),
+            flags: self.flags,
+            encoding: self.encoding,
+            precompressed: self.precompressed,
+            etag: if self.flags.contains(Flags::ETAG) {
+                self.etag()
+            } else {
+                None
+            },
+            last_modified: if self.flags.contains(Flags::LAST_MD) {
+                self.last_modified()
+            } else {
+                None
+            },
+            size: self.md.len(),
+            chunk_size: self.chunk_size,
+            bandwidth_limit: self.bandwidth_limit,
+        }
+    }
+}
+
+/// Get the name of the file and use it to construct default Content-Type
+/// and Content-Disposition values.
+pub(crate) fn content_headers(
+    path: &Path,
+) -> io::Result<(mime::Mime, ContentDisposition)> {
+    let filename = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Provided path has no filename",
+            ));
+        }
+    };
+
+    let ct = from_path(&path).first_or_octet_stream();
+    let disposition_type = match ct.type_() {
+        mime::IMAGE | mime::TEXT | mime::VIDEO => DispositionType::Inline,
+        _ => DispositionType::Attachment,
+    };
+    let mut parameters =
+        vec![DispositionParam::Filename(String::from(filename.as_ref()))];
+    if !filename.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext(String::from("UTF-8")),
+            language_tag: None,
+            value: filename.into_owned().into_bytes(),
+        }))
+    }
+    let cd = ContentDisposition {
+        disposition: disposition_type,
+        parameters,
+    };
+    Ok((ct, cd))
+}
+
+/// File content source.
+pub(crate) enum FileBody {
+    File(File),
+    Bytes(Bytes),
+}
+
+impl FileBody {
+    /// Stream `length` bytes of content starting at `offset`.
+    fn into_stream(
+        self,
+        offset: u64,
+        length: u64,
+        chunk_size: usize,
+    ) -> LocalBoxStream<'static, Result<Bytes, Error>> {
+        match self {
+            FileBody::File(file) => ChunkedReadFile {
+                offset,
+                chunk_size,
+                size: length,
+                file: Some(file),
+                fut: None,
+                counter: 0,
+            }
+            .boxed_local(),
+            FileBody::Bytes(bytes) => stream::once(ok(
+                bytes.slice(offset as usize, (offset + length) as usize)
+            ))
+            .boxed_local(),
+        }
+    }
+}
+
+/// Everything needed to respond with a file, except the file content.
+#[derive(Clone)]
+pub(crate) struct FileHead {
+    pub(crate) status_code: StatusCode,
+    pub(crate) content_type: mime::Mime,
+    pub(crate) content_disposition: header::ContentDisposition,
+    pub(crate) flags: Flags,
+    pub(crate) encoding: Option<ContentEncoding>,
+    pub(crate) precompressed: Option<ContentEncoding>,
+    pub(crate) etag: Option<header::EntityTag>,
+    pub(crate) last_modified: Option<header::HttpDate>,
+    pub(crate) size: u64,
+    pub(crate) chunk_size: usize,
+    pub(crate) bandwidth_limit: Option<u64>,
+}
+
+impl FileHead {
+    /// Apply bandwidth limit, limit from request extensions takes precedence.
+    fn throttle(
+        &self,
+        stream: LocalBoxStream<'static, Result<Bytes, Error>>,
+        req: &HttpRequest,
+    ) -> LocalBoxStream<'static, Result<Bytes, Error>> {
+        let limit = match req.extensions().get::<BandwidthLimit>() {
+            Some(limit) => limit.rate(),
+            None => self.bandwidth_limit,
+        };
+        match limit {
+            Some(rate) => Throttled::new(stream, rate).boxed_local(),
+            None => stream,
+        }
+    }
+
+    pub(crate) fn respond(
+        self,
+        body: FileBody,
+        req: &HttpRequest,
+    ) -> Result<HttpResponse, Error> {
         if self.status_code != StatusCode::OK {
             let mut resp = HttpResponse::build(self.status_code);
             resp.set(header::ContentType(self.content_type.clone()))
@@ -270,26 +433,13 @@ impl NamedFile {
             if let Some(current_encoding) = self.encoding {
                 resp.encoding(current_encoding);
             }
-            let reader = ChunkedReadFile {
-                size: self.md.len(),
-                offset: 0,
-                file: Some(self.file),
-                fut: None,
-                counter: 0,
-            };
-            return Ok(resp.streaming(reader));
+            set_precompressed(&mut resp, &self);
+            let reader = body.into_stream(0, self.size, self.chunk_size);
+            return Ok(resp.streaming(self.throttle(reader, req)));
         }
 
-        let etag = if self.flags.contains(Flags::ETAG) {
-            self.etag()
-        } else {
-            None
-        };
-        let last_modified = if self.flags.contains(Flags::LAST_MD) {
-            self.last_modified()
-        } else {
-            None
-        };
+        let etag = self.etag.clone();
+        let last_modified = self.last_modified;
 
         // check preconditions
         let precondition_failed = if !any_match(etag.as_ref(), req) {
@@ -325,6 +475,9 @@ impl NamedFile {
             false
         };
 
+        // range requests are served only if `If-Range` validator matches
+        let if_range = if_range_match(etag.as_ref(), last_modified, req);
+
         let mut resp = HttpResponse::build(self.status_code);
         resp.set(header::ContentType(self.content_type.clone()))
             .if_true(self.flags.contains(Flags::CONTENT_DISPOSITION), |res| {
@@ -337,6 +490,7 @@ impl NamedFile {
         if let Some(current_encoding) = self.encoding {
             resp.encoding(current_encoding);
         }
+        set_precompressed(&mut resp, &self);
 
         resp.if_some(last_modified, |lm, resp| {
             resp.set(header::LastModified(lm));
@@ -347,25 +501,34 @@ impl NamedFile {
 
         resp.header(header::ACCEPT_RANGES, "bytes");
 
-        let mut length = self.md.len();
+        let mut length = self.size;
         let mut offset = 0;
+        let mut multi_ranges = None;
 
         // check for range header
-        if let Some(ranges) = req.headers().get(&header::RANGE) {
+        if let Some(ranges) = req.headers().get(&header::RANGE).filter(|_| if_range) {
             if let Ok(rangesheader) = ranges.to_str() {
                 if let Ok(rangesvec) = HttpRange::parse(rangesheader, length) {
-                    length = rangesvec[0].length;
-                    offset = rangesvec[0].start;
-                    resp.encoding(ContentEncoding::Identity);
-                    resp.header(
-                        header::CONTENT_RANGE,
-                        format!(
-                            "bytes {}-{}/{}",
-                            offset,
-                            offset + length - 1,
-                            self.md.len()
-                        ),
-                    );
+                    let rangesvec = HttpRange::coalesce(rangesvec);
+                    if rangesvec.len() > MAX_RANGES {
+                        // too many ranges, serve full content
+                    } else if rangesvec.len() > 1 {
+                        resp.encoding(ContentEncoding::Identity);
+                        multi_ranges = Some(rangesvec);
+                    } else if let Some(range) = rangesvec.first() {
+                        length = range.length;
+                        offset = range.start;
+                        resp.encoding(ContentEncoding::Identity);
+                        resp.header(
+                            header::CONTENT_RANGE,
+                            format!(
+                                "bytes {}-{}/{}",
+                                offset,
+                                offset + length - 1,
+                                self.size
+                            ),
+                        );
+                    }
                 } else {
                     resp.header(header::CONTENT_RANGE, format!("bytes */{}", length));
                     return Ok(resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish());
@@ -381,18 +544,26 @@ impl NamedFile {
             return Ok(resp.status(StatusCode::NOT_MODIFIED).finish());
         }
 
-        let reader = ChunkedReadFile {
-            offset,
-            size: length,
-            file: Some(self.file),
-            fut: None,
-            counter: 0,
-        };
-        if offset != 0 || length != self.md.len() {
-            Ok(resp.status(StatusCode::PARTIAL_CONTENT).streaming(reader))
-        } else {
-            Ok(resp.body(SizedStream::new(length, reader)))
+        if let Some(ranges) = multi_ranges {
+            let boundary: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(BOUNDARY_LEN)
+                .collect();
+            let (size, body) = byteranges(body, &ranges, &self, &boundary);
+            let body = self.throttle(body, req);
+            return Ok(resp
+                .status(StatusCode::PARTIAL_CONTENT)
+                .content_type(format!("multipart/byteranges; boundary={}", boundary))
+                .body(SizedStream::new(size, body)));
         }
+
+        // single range is served same as full content, with known length
+        let reader =
+            self.throttle(body.into_stream(offset, length, self.chunk_size), req);
+        if offset != 0 || length != self.size {
+            resp.status(StatusCode::PARTIAL_CONTENT);
+        }
+        Ok(resp.body(SizedStream::new(length, reader)))
     }
 }
 
@@ -445,6 +616,146 @@ fn none_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
     }
 }
 
+/// Set `Content-Encoding` of already encoded content and disable compression.
+///
+/// `Vary` is set for identity content as well if precompressed variants
+/// could be served for the same path.
+fn set_precompressed(resp: &mut HttpResponseBuilder, head: &FileHead) {
+    if let Some(enc) = head.precompressed {
+        resp.encoding(ContentEncoding::Identity)
+            .header(header::CONTENT_ENCODING, enc.as_str());
+    }
+    if head.precompressed.is_some() || head.flags.contains(Flags::VARY_ENCODING) {
+        resp.header(header::VARY, "Accept-Encoding");
+    }
+}
+
+/// Returns true if `req` has no `If-Range` header or its validator matches
+/// current representation of the file.
+fn if_range_match(
+    etag: Option<&header::EntityTag>,
+    last_modified: Option<header::HttpDate>,
+    req: &HttpRequest,
+) -> bool {
+    match req.get_header::<header::IfRange>() {
+        None => true,
+        Some(header::IfRange::EntityTag(ref tag)) => {
+            etag.map(|etag| tag.strong_eq(etag)).unwrap_or(false)
+        }
+        Some(header::IfRange::Date(ref since)) => {
+            last_modified.map(|lm| lm == *since).unwrap_or(false)
+        }
+    }
+}
+
+const BOUNDARY_LEN: usize = 24;
+
+/// Maximum number of ranges served in `multipart/byteranges` response,
+/// full content is served for requests with more ranges.
+const MAX_RANGES: usize = 16;
+
+/// Build `multipart/byteranges` body for multiple ranges of a file.
+///
+/// Returns total size of the body and the body stream.
+fn byteranges(
+    body: FileBody,
+    ranges: &[HttpRange],
+    head: &FileHead,
+    boundary: &str,
+) -> (u64, LocalBoxStream<'static, Result<Bytes, Error>>) {
+    let mut total = 0;
+    let mut parts = Vec::with_capacity(ranges.len());
+
+    for (idx, range) in ranges.iter().enumerate() {
+        let part_head = format!(
+            "{}--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+            if idx == 0 { "" } else { "\r\n" },
+            boundary,
+            head.content_type,
+            range.start,
+            range.start + range.length - 1,
+            head.size
+        );
+        total += part_head.len() as u64 + range.length;
+        parts.push((Bytes::from(part_head), *range));
+    }
+    let tail = format!("\r\n--{}--\r\n", boundary);
+    total += tail.len() as u64;
+
+    let stream = ByteRanges {
+        parts: parts.into_iter(),
+        tail: Some(Bytes::from(tail)),
+        chunk_size: head.chunk_size,
+        body: Some(body),
+        reader: None,
+    };
+    (total, stream.boxed_local())
+}
+
+/// Stream of `multipart/byteranges` body.
+///
+/// Ranges are read one after another with the same file handle.
+struct ByteRanges {
+    parts: vec::IntoIter<(Bytes, HttpRange)>,
+    tail: Option<Bytes>,
+    chunk_size: usize,
+    body: Option<FileBody>,
+    reader: Option<ChunkedReadFile>,
+}
+
+impl Stream for ByteRanges {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(ref mut reader) = self.reader {
+            match Pin::new(&mut *reader).poll_next(cx) {
+                Poll::Ready(None) => {
+                    // range is done, reuse file for next range
+                    let file = reader.file.take();
+                    self.reader = None;
+                    self.body = file.map(FileBody::File);
+                }
+                res => return res,
+            }
+        }
+
+        let (part_head, range) = match self.parts.next() {
+            Some(part) => part,
+            None => return Poll::Ready(self.tail.take().map(Ok)),
+        };
+        match self.body.take() {
+            Some(FileBody::File(file)) => {
+                self.reader = Some(ChunkedReadFile {
+                    offset: range.start,
+                    chunk_size: self.chunk_size,
+                    size: range.length,
+                    file: Some(file),
+                    fut: None,
+                    counter: 0,
+                });
+                Poll::Ready(Some(Ok(part_head)))
+            }
+            Some(FileBody::Bytes(bytes)) => {
+                let end = (range.start + range.length) as usize;
+                let mut buf =
+                    BytesMut::with_capacity(part_head.len() + range.length as usize);
+                buf.extend_from_slice(&part_head);
+                buf.extend_from_slice(&bytes[range.start as usize..end]);
+                self.body = Some(FileBody::Bytes(bytes));
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            None => Poll::Ready(Some(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "File is not available",
+            )
+            .into()))),
+        }
+    }
+}
+
 impl Responder for NamedFile {
     type Error = Error;
     type Future = Ready<Result<HttpResponse, Error>>;
@@ -1,3 +1,5 @@
+use std::cmp;
+
 /// HTTP Range header representation.
 #[derive(Debug, Clone, Copy)]
 pub struct HttpRange {
@@ -9,7 +11,7 @@ static PREFIX: &str = "bytes=";
 const PREFIX_LEN: usize = 6;
 
 impl HttpRange {
-    /// Parses Range HTTP header string as per RFC 2616.
+    /// Parses Range HTTP header string as per RFC 7233.
     ///
     /// `header` is HTTP Range header (e.g. `bytes=bytes=0-9`).
     /// `size` is full size of response (file).
@@ -39,6 +41,10 @@ impl HttpRange {
                     // range start relative to the end of the file.
                     let mut length: i64 = end_str.parse().map_err(|_| ())?;
 
+                    if length <= 0 {
+                        no_overlap = true;
+                        return Ok(None);
+                    }
                     if length > size_sig {
                         length = size_sig;
                     }
@@ -91,6 +97,24 @@ impl HttpRange {
 
         Ok(ranges)
     }
+
+    /// Merge overlapping and adjacent ranges, result is ordered by start.
+    pub fn coalesce(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+        ranges.sort_by_key(|range| range.start);
+
+        let mut result: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match result.last_mut() {
+                Some(last) if range.start <= last.start + last.length => {
+                    let end =
+                        cmp::max(last.start + last.length, range.start + range.length);
+                    last.length = end - last.start;
+                }
+                _ => result.push(range),
+            }
+        }
+        result
+    }
 }
 
 #[cfg(test)]
@@ -113,6 +137,7 @@ mod tests {
             T("bytes=0-2,5-4", 10, vec![]),
             T("bytes=2-5,4-3", 10, vec![]),
             T("bytes=--5,4--3", 10, vec![]),
+            T("bytes=-0", 10, vec![]),
             T("bytes=A-", 10, vec![]),
             T("bytes=A- ", 10, vec![]),
             T("bytes=A-Z", 10, vec![]),
@@ -372,4 +397,19 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_coalesce() {
+        let ranges = HttpRange::parse("bytes=7-8,0-2,3-4,2-3,20-", 30).unwrap();
+        let ranges = HttpRange::coalesce(ranges);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!((ranges[0].start, ranges[0].length), (0, 5));
+        assert_eq!((ranges[1].start, ranges[1].length), (7, 2));
+        assert_eq!((ranges[2].start, ranges[2].length), (20, 10));
+
+        let ranges = HttpRange::parse("bytes=500-700,601-999", 10000).unwrap();
+        let ranges = HttpRange::coalesce(ranges);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!((ranges[0].start, ranges[0].length), (500, 500));
+    }
 }
@@ -0,0 +1,161 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::error::Error;
+use bytes::Bytes;
+use futures::stream::{LocalBoxStream, Stream};
+use futures::{ready, Future};
+use tokio_timer::{delay, Delay};
+
+/// Per-request bandwidth limit for static files.
+///
+/// Limit inserted into request extensions overrides limit configured
+/// with `Files::bandwidth_limit()` or `NamedFile::set_bandwidth_limit()`.
+///
+/// ```rust
+/// use actix_service::Service;
+/// use actix_web::{App, HttpMessage};
+/// use actix_files::{BandwidthLimit, Files};
+///
+/// let app = App::new()
+///     .wrap_fn(|req, srv| {
+///         if req.headers().contains_key("x-premium") {
+///             req.extensions_mut().insert(BandwidthLimit::unlimited());
+///         }
+///         srv.call(req)
+///     })
+///     .service(Files::new("/static", ".").bandwidth_limit(64 * 1024));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandwidthLimit(Option<u64>);
+
+impl BandwidthLimit {
+    /// Limit response throughput to `bytes_per_sec`.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        BandwidthLimit(Some(bytes_per_sec))
+    }
+
+    /// Do not limit response throughput.
+    pub fn unlimited() -> Self {
+        BandwidthLimit(None)
+    }
+
+    pub(crate) fn rate(self) -> Option<u64> {
+        self.0
+    }
+}
+
+/// Stream adapter that limits throughput of the inner stream.
+///
+/// Chunks larger than one second worth of data are split.
+pub(crate) struct Throttled {
+    stream: LocalBoxStream<'static, Result<Bytes, Error>>,
+    rate: u64,
+    start: Instant,
+    sent: u64,
+    pending: Option<Bytes>,
+    delay: Option<Delay>,
+}
+
+impl Throttled {
+    pub(crate) fn new(
+        stream: LocalBoxStream<'static, Result<Bytes, Error>>,
+        rate: u64,
+    ) -> Self {
+        Throttled {
+            stream,
+            rate: std::cmp::max(rate, 1),
+            start: Instant::now(),
+            sent: 0,
+            pending: None,
+            delay: None,
+        }
+    }
+
+    /// Time when all sent bytes fit into the rate.
+    fn due(&self) -> Instant {
+        let nanos = u128::from(self.sent) * 1_000_000_000 / u128::from(self.rate);
+        self.start + Duration::from_nanos(nanos as u64)
+    }
+}
+
+impl Stream for Throttled {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(ref mut delay) = self.delay {
+                ready!(Pin::new(delay).poll(cx));
+                self.delay = None;
+            }
+
+            let due = self.due();
+            if due > Instant::now() {
+                self.delay = Some(delay(due));
+                continue;
+            }
+
+            let mut chunk = match self.pending.take() {
+                Some(chunk) => chunk,
+                None => match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                    Some(Ok(chunk)) => chunk,
+                    item => return Poll::Ready(item),
+                },
+            };
+
+            if chunk.len() as u64 > self.rate {
+                self.pending = Some(chunk.split_off(self.rate as usize));
+            }
+            self.sent += chunk.len() as u64;
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream::{self, StreamExt};
+
+    use super::*;
+    use actix_web::test::block_on;
+
+    #[test]
+    fn test_throttled() {
+        block_on(async {
+            let data = stream::iter(vec![
+                Ok(Bytes::from(vec![b'a'; 20])),
+                Ok(Bytes::from_static(b"b")),
+            ])
+            .boxed_local();
+
+            let start = Instant::now();
+            let chunks = Throttled::new(data, 100)
+                .map(|chunk| chunk.unwrap().len())
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(chunks, vec![20, 1]);
+            // 20 bytes are sent before the last chunk
+            assert!(start.elapsed() >= Duration::from_millis(190));
+        })
+    }
+
+    #[test]
+    fn test_throttled_split() {
+        block_on(async {
+            let data =
+                stream::iter(vec![Ok(Bytes::from(vec![b'a'; 150]))]).boxed_local();
+            let mut throttled = Throttled::new(data, 100);
+
+            // first second worth of data is sent without delay
+            let chunk = throttled.next().await.unwrap().unwrap();
+            assert_eq!(chunk.len(), 100);
+            assert_eq!(throttled.pending.as_ref().unwrap().len(), 50);
+            assert_eq!(throttled.due(), throttled.start + Duration::from_secs(1));
+        })
+    }
+}
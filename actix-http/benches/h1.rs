@@ -0,0 +1,97 @@
+//! HTTP/1 request head and chunked payload parsing. Run with
+//! `cargo bench --bench h1`, add `--features simd` for vectorized scanning.
+use actix_codec::Decoder;
+use actix_http::h1::{Codec, Message};
+use actix_http::ServiceConfig;
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+const HEAD: &[u8] = b"GET /plaintext HTTP/1.1\r\n\
+    Host: localhost:8080\r\n\
+    User-Agent: Mozilla/5.0 (X11; Linux x86_64) Gecko/20130501 Firefox/30.0\r\n\
+    Accept: text/plain,text/html;q=0.9,application/xhtml+xml;q=0.9\r\n\
+    Accept-Language: en-US,en;q=0.5\r\n\
+    Cookie: uid=12345678901234567890; session=abcdefghijklmnopqrstuvwxyz\r\n\
+    Connection: keep-alive\r\n\r\n";
+
+fn chunked_request(chunks: usize, chunk: &[u8], ext: &str) -> BytesMut {
+    let mut buf = BytesMut::from(
+        &b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"[..],
+    );
+    for _ in 0..chunks {
+        buf.extend_from_slice(format!("{:x}{}\r\n", chunk.len(), ext).as_bytes());
+        buf.extend_from_slice(chunk);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(b"0\r\n\r\n");
+    buf
+}
+
+fn decode_all(buf: &BytesMut) -> usize {
+    let mut codec = Codec::new(ServiceConfig::default());
+    let mut buf = buf.clone();
+    let mut items = 0;
+    loop {
+        match codec.decode(&mut buf).unwrap() {
+            Some(Message::Chunk(None)) | None => return items,
+            Some(_) => items += 1,
+        }
+    }
+}
+
+fn bench_head(c: &mut Criterion) {
+    let mut pipelined = BytesMut::new();
+    for _ in 0..16 {
+        pipelined.extend_from_slice(HEAD);
+    }
+    let mut group = c.benchmark_group("request head");
+
+    group.throughput(Throughput::Bytes(pipelined.len() as u64));
+    group.bench_function("16 pipelined requests", |b| {
+        b.iter(|| {
+            let mut codec = Codec::new(ServiceConfig::default());
+            let mut buf = pipelined.clone();
+            while let Some(msg) = codec.decode(&mut buf).unwrap() {
+                criterion::black_box(msg);
+            }
+        })
+    });
+
+    let segments: Vec<_> = HEAD.chunks(16).collect();
+    group.throughput(Throughput::Bytes(HEAD.len() as u64));
+    group.bench_function("request head in 16 byte reads", |b| {
+        b.iter(|| {
+            let mut codec = Codec::new(ServiceConfig::default());
+            let mut buf = BytesMut::new();
+            for segment in &segments {
+                buf.extend_from_slice(segment);
+                if let Some(msg) = codec.decode(&mut buf).unwrap() {
+                    criterion::black_box(msg);
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_chunked(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunked payload");
+
+    for (name, size, ext) in &[
+        ("small chunks", 16, ""),
+        (
+            "small chunks with extension",
+            16,
+            ";name=a-long-extension-value",
+        ),
+        ("large chunks", 8192, ""),
+    ] {
+        let buf = chunked_request(256, &vec![b'x'; *size], ext);
+        group.throughput(Throughput::Bytes(buf.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| decode_all(&buf)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_head, bench_chunked);
+criterion_main!(benches);
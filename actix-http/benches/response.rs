@@ -0,0 +1,65 @@
+//! Building responses with many headers, as set by CORS and security
+//! headers middlewares. Run with `cargo bench --bench response`.
+use actix_http::Response;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const HEADERS: &[(&str, &str)] = &[
+    ("access-control-allow-origin", "https://example.com"),
+    ("access-control-allow-credentials", "true"),
+    (
+        "access-control-expose-headers",
+        "x-request-id, x-ratelimit-remaining",
+    ),
+    ("vary", "Origin"),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includeSubDomains",
+    ),
+    ("content-security-policy", "default-src 'self'"),
+    ("x-content-type-options", "nosniff"),
+    ("x-frame-options", "DENY"),
+    ("x-xss-protection", "1; mode=block"),
+    ("referrer-policy", "no-referrer"),
+    ("cache-control", "no-store"),
+    ("content-type", "application/json"),
+    ("x-request-id", "5f0c6a0e-7d4c-4c1a-9a53-5d2f0c1b9e7a"),
+    ("x-ratelimit-limit", "1000"),
+    ("x-ratelimit-remaining", "999"),
+    ("x-ratelimit-reset", "1572307200"),
+    ("x-response-time", "12ms"),
+    ("server-timing", "db;dur=5, app;dur=7"),
+];
+
+fn bench_headers(c: &mut Criterion) {
+    c.bench_function("header() one by one", |b| {
+        b.iter(|| {
+            let mut res = Response::Ok();
+            for (key, value) in HEADERS {
+                res.header(*key, *value);
+            }
+            res.finish()
+        })
+    });
+
+    c.bench_function("headers_reserve() and header()", |b| {
+        b.iter(|| {
+            let mut res = Response::Ok();
+            res.headers_reserve(HEADERS.len());
+            for (key, value) in HEADERS {
+                res.header(*key, *value);
+            }
+            res.finish()
+        })
+    });
+
+    c.bench_function("insert_headers()", |b| {
+        b.iter(|| {
+            Response::Ok()
+                .insert_headers(HEADERS.iter().cloned())
+                .finish()
+        })
+    });
+}
+
+criterion_group!(benches, bench_headers);
+criterion_main!(benches);
@@ -8,6 +8,7 @@ use futures::Stream;
 use pin_project::{pin_project, project};
 
 use crate::error::Error;
+use crate::header::{self, HeaderMap, HeaderName};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 /// Body size hint
@@ -29,13 +30,98 @@ impl BodySize {
             _ => false,
         }
     }
+
+    /// Lower and upper bounds of the size, upper bound is `None` for
+    /// stream of unknown size.
+    pub fn hint(&self) -> (u64, Option<u64>) {
+        match *self {
+            BodySize::None | BodySize::Empty => (0, Some(0)),
+            BodySize::Sized(len) => (len as u64, Some(len as u64)),
+            BodySize::Sized64(len) => (len, Some(len)),
+            BodySize::Stream => (0, None),
+        }
+    }
 }
 
 /// Type that provides this trait can be streamed to a peer.
 pub trait MessageBody {
     fn size(&self) -> BodySize;
 
+    /// Lower and upper bounds of the body length.
+    ///
+    /// Streaming body with exact bounds is sent with `Content-Length` header
+    /// instead of chunked transfer encoding. By default bounds are derived
+    /// from `size()`.
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.size().hint()
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>>;
+
+    /// Poll trailers of the body, it is called once after last chunk.
+    ///
+    /// Trailers are sent with HTTP/2 and with chunked transfer encoding of
+    /// HTTP/1.1, otherwise they are dropped. Fields that are not allowed in
+    /// trailers, i.e. `Content-Length` or `Host`, are dropped as well. Body
+    /// with trailers should not report exact size hint. By default body has
+    /// no trailers.
+    fn poll_trailers(
+        &mut self,
+        _: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
+
+/// Size of the body used for framing of the message.
+///
+/// Streaming body with exact size hint is sent as sized body, connection
+/// or stream is aborted if the body does not match the hint.
+pub(crate) fn framing_size<B: MessageBody + ?Sized>(body: &B) -> BodySize {
+    match body.size() {
+        BodySize::Stream => match body.size_hint() {
+            (lower, Some(upper)) if lower == upper => BodySize::Sized64(lower),
+            _ => BodySize::Stream,
+        },
+        size => size,
+    }
+}
+
+/// Check if header field can be sent in trailers.
+///
+/// Fields used for framing, routing, authentication, control of the
+/// response and processing of the payload are not allowed, see
+/// [RFC 7230 section 4.1.2](https://tools.ietf.org/html/rfc7230#section-4.1.2).
+pub(crate) fn trailer_allowed(name: &HeaderName) -> bool {
+    match *name {
+        header::AGE
+        | header::AUTHORIZATION
+        | header::CACHE_CONTROL
+        | header::CONNECTION
+        | header::CONTENT_ENCODING
+        | header::CONTENT_LENGTH
+        | header::CONTENT_RANGE
+        | header::CONTENT_TYPE
+        | header::DATE
+        | header::EXPECT
+        | header::EXPIRES
+        | header::HOST
+        | header::LOCATION
+        | header::MAX_FORWARDS
+        | header::PROXY_AUTHENTICATE
+        | header::PROXY_AUTHORIZATION
+        | header::RANGE
+        | header::RETRY_AFTER
+        | header::SET_COOKIE
+        | header::TE
+        | header::TRAILER
+        | header::TRANSFER_ENCODING
+        | header::UPGRADE
+        | header::VARY
+        | header::WARNING
+        | header::WWW_AUTHENTICATE => false,
+        _ => true,
+    }
 }
 
 impl MessageBody for () {
@@ -53,9 +139,20 @@ impl<T: MessageBody> MessageBody for Box<T> {
         self.as_ref().size()
     }
 
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.as_ref().size_hint()
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
         self.as_mut().poll_next(cx)
     }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        self.as_mut().poll_trailers(cx)
+    }
 }
 
 #[pin_project]
@@ -97,12 +194,29 @@ impl<B: MessageBody> MessageBody for ResponseBody<B> {
         }
     }
 
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match self {
+            ResponseBody::Body(ref body) => body.size_hint(),
+            ResponseBody::Other(ref body) => body.size_hint(),
+        }
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
         match self {
             ResponseBody::Body(ref mut body) => body.poll_next(cx),
             ResponseBody::Other(ref mut body) => body.poll_next(cx),
         }
     }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        match self {
+            ResponseBody::Body(ref mut body) => body.poll_trailers(cx),
+            ResponseBody::Other(ref mut body) => body.poll_trailers(cx),
+        }
+    }
 }
 
 impl<B: MessageBody> Stream for ResponseBody<B> {
@@ -152,6 +266,13 @@ impl MessageBody for Body {
         }
     }
 
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match self {
+            Body::Message(ref body) => body.size_hint(),
+            _ => self.size().hint(),
+        }
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
         match self {
             Body::None => Poll::Ready(None),
@@ -167,6 +288,16 @@ impl MessageBody for Body {
             Body::Message(ref mut body) => body.poll_next(cx),
         }
     }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        match self {
+            Body::Message(ref mut body) => body.poll_trailers(cx),
+            _ => Poll::Ready(Ok(None)),
+        }
+    }
 }
 
 impl PartialEq for Body {
@@ -553,6 +684,42 @@ mod tests {
         assert!(block_on(poll_fn(|cx| val.poll_next(cx))).is_none());
     }
 
+    #[test]
+    fn test_size_hint() {
+        struct Hinted(Option<Bytes>);
+
+        impl MessageBody for Hinted {
+            fn size(&self) -> BodySize {
+                BodySize::Stream
+            }
+
+            fn size_hint(&self) -> (u64, Option<u64>) {
+                (4, Some(4))
+            }
+
+            fn poll_next(
+                &mut self,
+                _: &mut Context,
+            ) -> Poll<Option<Result<Bytes, Error>>> {
+                Poll::Ready(self.0.take().map(Ok))
+            }
+        }
+
+        assert_eq!(BodySize::Sized(5).hint(), (5, Some(5)));
+        assert_eq!(BodySize::Stream.hint(), (0, None));
+        assert_eq!(framing_size(&Body::from("test")), BodySize::Sized(4));
+        assert_eq!(
+            framing_size(&Hinted(Some(Bytes::from_static(b"test")))),
+            BodySize::Sized64(4)
+        );
+
+        let mut body = Body::from_message(Hinted(None));
+        assert_eq!(body.size_hint(), (4, Some(4)));
+        assert!(block_on(poll_fn(|cx| body.poll_trailers(cx)))
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn test_body_eq() {
         assert!(Body::None == Body::None);
@@ -1,13 +1,14 @@
 use std::fmt;
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::Duration;
 
 use actix_codec::Framed;
 use actix_server_config::ServerConfig as SrvConfig;
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
 
 use crate::body::MessageBody;
-use crate::config::{KeepAlive, ServiceConfig};
+use crate::config::{FlushStrategy, HeaderCase, HeaderLimits, KeepAlive, ServiceConfig};
 use crate::error::Error;
 use crate::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::h2::H2Service;
@@ -15,6 +16,7 @@ use crate::helpers::{Data, DataFactory};
 use crate::request::Request;
 use crate::response::Response;
 use crate::service::HttpService;
+use crate::timer::DEFAULT_TICK;
 
 /// A http service builder
 ///
@@ -24,6 +26,11 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler<T>> {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_disconnect: u64,
+    limits: HeaderLimits,
+    header_case: HeaderCase,
+    timer_tick: u64,
+    write_watermarks: (usize, usize),
+    flush: FlushStrategy,
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
@@ -43,6 +50,11 @@ where
             keep_alive: KeepAlive::Timeout(5),
             client_timeout: 5000,
             client_disconnect: 0,
+            limits: HeaderLimits::default(),
+            header_case: HeaderCase::Lower,
+            timer_tick: DEFAULT_TICK,
+            write_watermarks: (4096, 32_768),
+            flush: FlushStrategy::Eager,
             expect: ExpectHandler,
             upgrade: None,
             on_connect: None,
@@ -105,6 +117,61 @@ where
         self
     }
 
+    /// Set limits of request head.
+    ///
+    /// Requests that exceed limits are responded with 431 (Request Header
+    /// Fields Too Large) or 414 (URI Too Long).
+    pub fn header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set case of response header names for HTTP/1 protocol.
+    ///
+    /// By default header names are written in lower case.
+    pub fn header_case(mut self, case: HeaderCase) -> Self {
+        self.header_case = case;
+        self
+    }
+
+    /// Set tick of connection timers in milliseconds.
+    ///
+    /// Keep-alive, client and disconnect timers of connections of the
+    /// service are driven by timer wheel shared on a worker, their deadlines are rounded
+    /// up to the tick. Larger tick reduces wakeups with many idle
+    /// connections.
+    ///
+    /// By default tick is set to 100 milliseconds.
+    pub fn timer_tick(mut self, val: u64) -> Self {
+        self.timer_tick = val;
+        self
+    }
+
+    /// Set low and high watermarks of HTTP/1 write buffer in bytes.
+    ///
+    /// Response body is not polled while write buffer holds more than high
+    /// watermark. By default watermarks are set to 4KiB and 32KiB.
+    ///
+    /// # Panics
+    ///
+    /// Panics if low watermark is zero or greater than high watermark.
+    pub fn write_watermarks(mut self, low: usize, high: usize) -> Self {
+        assert!(
+            0 < low && low <= high,
+            "Write watermarks must satisfy 0 < low <= high"
+        );
+        self.write_watermarks = (low, high);
+        self
+    }
+
+    /// Set flush strategy of HTTP/1 write buffer.
+    ///
+    /// By default buffer is flushed eagerly.
+    pub fn flush_strategy(mut self, flush: FlushStrategy) -> Self {
+        self.flush = flush;
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -122,6 +189,11 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            limits: self.limits,
+            header_case: self.header_case,
+            timer_tick: self.timer_tick,
+            write_watermarks: self.write_watermarks,
+            flush: self.flush,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect: self.on_connect,
@@ -149,6 +221,11 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            limits: self.limits,
+            header_case: self.header_case,
+            timer_tick: self.timer_tick,
+            write_watermarks: self.write_watermarks,
+            flush: self.flush,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect: self.on_connect,
@@ -178,11 +255,16 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
-        );
+            self.limits,
+        )
+        .with_header_case(self.header_case)
+        .with_timer_tick(Duration::from_millis(self.timer_tick))
+        .with_write_watermarks(self.write_watermarks.0, self.write_watermarks.1)
+        .with_flush_strategy(self.flush);
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -199,11 +281,16 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
-        );
+            self.limits,
+        )
+        .with_header_case(self.header_case)
+        .with_timer_tick(Duration::from_millis(self.timer_tick))
+        .with_write_watermarks(self.write_watermarks.0, self.write_watermarks.1)
+        .with_flush_strategy(self.flush);
         H2Service::with_config(cfg, service.into_factory()).on_connect(self.on_connect)
     }
 
@@ -217,11 +304,16 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
-        );
+            self.limits,
+        )
+        .with_header_case(self.header_case)
+        .with_timer_tick(Duration::from_millis(self.timer_tick))
+        .with_write_watermarks(self.write_watermarks.0, self.write_watermarks.1)
+        .with_flush_strategy(self.flush);
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -15,6 +15,7 @@ use crate::payload::Payload;
 
 use super::error::SendRequestError;
 use super::pool::{Acquired, Protocol};
+use super::proxy::ProxyForward;
 use super::{h1proto, h2proto};
 
 pub(crate) enum ConnectionType<Io> {
@@ -57,6 +58,7 @@ pub struct IoConnection<T> {
     io: Option<ConnectionType<T>>,
     created: time::Instant,
     pool: Option<Acquired<T>>,
+    forward: Option<ProxyForward>,
 }
 
 impl<T> fmt::Debug for IoConnection<T>
@@ -82,9 +84,16 @@ impl<T: AsyncRead + AsyncWrite + Unpin> IoConnection<T> {
             pool,
             created,
             io: Some(io),
+            forward: None,
         }
     }
 
+    /// Send requests to http proxy in absolute-form.
+    pub(crate) fn set_forward(mut self, forward: ProxyForward) -> Self {
+        self.forward = Some(forward);
+        self
+    }
+
     pub(crate) fn into_inner(self) -> (ConnectionType<T>, time::Instant) {
         (self.io.unwrap(), self.created)
     }
@@ -113,7 +122,11 @@ where
     ) -> Self::Future {
         match self.io.take().unwrap() {
             ConnectionType::H1(io) => {
-                h1proto::send_request(io, head.into(), body, self.created, self.pool)
+                let head = match self.forward {
+                    Some(ref forward) => forward.apply(head.into()),
+                    None => head.into(),
+                };
+                h1proto::send_request(io, head, body, self.created, self.pool)
                     .boxed_local()
             }
             ConnectionType::H2(io) => {
@@ -1,7 +1,11 @@
 use std::fmt;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -9,15 +13,21 @@ use actix_codec::{AsyncRead, AsyncWrite};
 use actix_connect::{
     default_connector, Connect as TcpConnect, Connection as TcpConnection,
 };
-use actix_service::{apply_fn, Service};
+use actix_service::Service;
 use actix_utils::timeout::{TimeoutError, TimeoutService};
 use futures::future::Ready;
-use http::Uri;
+use http::{Uri, Version};
 use tokio_net::tcp::TcpStream;
+#[cfg(unix)]
+use tokio_net::uds::UnixStream;
 
 use super::connection::Connection;
 use super::error::ConnectError;
-use super::pool::{ConnectionPool, Protocol};
+use super::pool::{ConnectionPool, PoolMetrics, Protocol};
+use super::proxy::{Proxy, ProxyConnector};
+use super::resolver::{HostResolver, Resolve};
+#[cfg(unix)]
+use super::unix::UnixConnector;
 use super::Connect;
 
 #[cfg(feature = "openssl")]
@@ -53,15 +63,55 @@ type SslConnector = ();
 pub struct Connector<T, U> {
     connector: T,
     timeout: Duration,
+    handshake_timeout: Option<Duration>,
     conn_lifetime: Duration,
     conn_keep_alive: Duration,
     disconnect_timeout: Duration,
     limit: usize,
+    limit_per_host: usize,
+    metrics: PoolMetrics,
+    proxy: Proxy,
+    resolver: HostResolver,
+    http2_prior_knowledge: bool,
+    #[allow(dead_code)]
+    max_http_version: Version,
     #[allow(dead_code)]
     ssl: SslConnector,
     _t: PhantomData<U>,
 }
 
+/// Create ssl connector that offers `protos` in ALPN negotiation.
+fn build_ssl(protos: Vec<Vec<u8>>) -> SslConnector {
+    #[cfg(feature = "openssl")]
+    {
+        use open_ssl::ssl::SslMethod;
+
+        let mut wire = Vec::new();
+        for proto in protos {
+            wire.push(proto.len() as u8);
+            wire.extend(proto);
+        }
+        let mut ssl = OpensslConnector::builder(SslMethod::tls()).unwrap();
+        let _ = ssl
+            .set_alpn_protos(&wire)
+            .map_err(|e| error!("Can not set alpn protocol: {:?}", e));
+        SslConnector::Openssl(ssl.build())
+    }
+    #[cfg(all(not(feature = "openssl"), feature = "rustls"))]
+    {
+        let mut config = ClientConfig::new();
+        config.set_protocols(&protos);
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        SslConnector::Rustls(Arc::new(config))
+    }
+    #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+    {
+        let _ = protos;
+    }
+}
+
 trait Io: AsyncRead + AsyncWrite + Unpin {}
 impl<T: AsyncRead + AsyncWrite + Unpin> Io for T {}
 
@@ -75,39 +125,21 @@ impl Connector<(), ()> {
             > + Clone,
         TcpStream,
     > {
-        let ssl = {
-            #[cfg(feature = "openssl")]
-            {
-                use open_ssl::ssl::SslMethod;
-
-                let mut ssl = OpensslConnector::builder(SslMethod::tls()).unwrap();
-                let _ = ssl
-                    .set_alpn_protos(b"\x02h2\x08http/1.1")
-                    .map_err(|e| error!("Can not set alpn protocol: {:?}", e));
-                SslConnector::Openssl(ssl.build())
-            }
-            #[cfg(all(not(feature = "openssl"), feature = "rustls"))]
-            {
-                let protos = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
-                let mut config = ClientConfig::new();
-                config.set_protocols(&protos);
-                config
-                    .root_store
-                    .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
-                SslConnector::Rustls(Arc::new(config))
-            }
-            #[cfg(not(any(feature = "openssl", feature = "rustls")))]
-            {}
-        };
-
         Connector {
-            ssl,
+            ssl: build_ssl(vec![b"h2".to_vec(), b"http/1.1".to_vec()]),
             connector: default_connector(),
             timeout: Duration::from_secs(1),
+            handshake_timeout: None,
             conn_lifetime: Duration::from_secs(75),
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Duration::from_millis(3000),
             limit: 100,
+            limit_per_host: 0,
+            metrics: PoolMetrics::default(),
+            proxy: Proxy::new(),
+            resolver: HostResolver::default(),
+            http2_prior_knowledge: false,
+            max_http_version: Version::HTTP_2,
             _t: PhantomData,
         }
     }
@@ -127,14 +159,36 @@ impl<T, U> Connector<T, U> {
         Connector {
             connector,
             timeout: self.timeout,
+            handshake_timeout: self.handshake_timeout,
             conn_lifetime: self.conn_lifetime,
             conn_keep_alive: self.conn_keep_alive,
             disconnect_timeout: self.disconnect_timeout,
             limit: self.limit,
+            limit_per_host: self.limit_per_host,
+            metrics: self.metrics,
+            proxy: self.proxy,
+            resolver: self.resolver,
+            http2_prior_knowledge: self.http2_prior_knowledge,
+            max_http_version: self.max_http_version,
             ssl: self.ssl,
             _t: PhantomData,
         }
     }
+
+    #[cfg(unix)]
+    /// Send requests to unix domain socket at `path`.
+    ///
+    /// All connections are opened to the socket, host of request uri is
+    /// only used for `Host` header.
+    ///
+    /// ```rust,ignore
+    /// use actix_http::client::Connector;
+    ///
+    /// let connector = Connector::new().unix("/var/run/docker.sock").finish();
+    /// ```
+    pub fn unix<P: AsRef<Path>>(self, path: P) -> Connector<UnixConnector, UnixStream> {
+        self.connector(UnixConnector::new(path))
+    }
 }
 
 impl<T, U> Connector<T, U>
@@ -154,6 +208,17 @@ where
         self
     }
 
+    /// TLS handshake timeout, i.e. max time to establish secure connection
+    /// after tcp connection is opened.
+    ///
+    /// Handshake timeout is reported as `ConnectError::HandshakeTimeout`.
+    /// By default it is the same as connection timeout, it is overridden
+    /// by `Connect::handshake_timeout` of the request.
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = Some(timeout);
+        self
+    }
+
     #[cfg(feature = "openssl")]
     /// Use custom `SslConnector` instance.
     pub fn ssl(mut self, connector: OpensslConnector) -> Self {
@@ -167,6 +232,29 @@ where
         self
     }
 
+    /// Set max http version negotiated for secure connections.
+    ///
+    /// Supported versions are HTTP/1.1 and HTTP/2. If version is HTTP/1.1,
+    /// only `http/1.1` protocol is offered in ALPN negotiation. By default
+    /// HTTP/2 is preferred.
+    ///
+    /// ALPN protocols are adjusted for every connection, custom connector
+    /// set with `ssl()` or `rustls()` is kept.
+    pub fn max_http_version(mut self, version: Version) -> Self {
+        self.max_http_version = version;
+        self
+    }
+
+    /// Use HTTP/2 for plain text connections without upgrade
+    /// negotiation, i.e. with prior knowledge.
+    ///
+    /// Server must support HTTP/2 over cleartext. Secure connections
+    /// still negotiate protocol with ALPN. Disabled by default.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
     /// Set total number of simultaneous connections per type of scheme.
     ///
     /// If limit is 0, the connector has no limit.
@@ -176,6 +264,16 @@ where
         self
     }
 
+    /// Set number of simultaneous connections to a single host.
+    ///
+    /// Requests to a host that reached the limit wait for a connection
+    /// without blocking requests to other hosts.
+    /// By default there is no per host limit.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.limit_per_host = limit;
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -210,6 +308,68 @@ where
         self
     }
 
+    /// Route connections through http or socks5 proxy.
+    ///
+    /// Plain `http` requests are sent to http proxy with absolute uri,
+    /// `https` and websocket connections are tunneled with `CONNECT`
+    /// request. By default proxy is not used.
+    ///
+    /// ```rust,ignore
+    /// use actix_http::client::{Connector, Proxy};
+    ///
+    /// let connector = Connector::new()
+    ///     .proxy(Proxy::from_env().no_proxy("localhost"))
+    ///     .finish();
+    /// ```
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Use custom host name resolver instead of dns.
+    pub fn resolver<R: Resolve + 'static>(mut self, resolver: R) -> Self {
+        self.resolver.set_resolver(resolver);
+        self
+    }
+
+    /// Connect to `addrs` instead of resolving `host`.
+    ///
+    /// Overrides take precedence over the resolver. If port of the address
+    /// is 0, port of the request uri is used. Connections to a host with
+    /// multiple addresses are spread between them in round-robin fashion.
+    ///
+    /// ```rust,ignore
+    /// use actix_http::client::Connector;
+    ///
+    /// let connector = Connector::new()
+    ///     .resolve_to("api.local", vec!["127.0.0.1:8080".parse().unwrap()])
+    ///     .finish();
+    /// ```
+    pub fn resolve_to<I>(mut self, host: &str, addrs: I) -> Self
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        self.resolver.add_override(host, addrs);
+        self
+    }
+
+    /// Get handle for observing connection pool usage.
+    ///
+    /// ```rust,ignore
+    /// use actix_http::client::Connector;
+    ///
+    /// let connector = Connector::new().limit_per_host(10);
+    /// let metrics = connector.metrics();
+    /// let connector = connector.finish();
+    ///
+    /// for host in metrics.snapshot() {
+    ///     println!("{}: idle {}, active {}", host.authority, host.idle, host.active);
+    /// }
+    /// ```
+    pub fn metrics(&self) -> PoolMetrics {
+        self.metrics.clone()
+    }
+
     /// Finish configuration process and create connector service.
     /// The Connector builder always concludes by calling `finish()` last in
     /// its combinator chain.
@@ -217,15 +377,21 @@ where
         self,
     ) -> impl Service<Request = Connect, Response = impl Connection, Error = ConnectError>
            + Clone {
+        let resolver = Rc::new(self.resolver);
+        let proxy = Rc::new(self.proxy);
+        let proto = if self.http2_prior_knowledge {
+            Protocol::Http2
+        } else {
+            Protocol::Http1
+        };
+
         #[cfg(not(any(feature = "openssl", feature = "rustls")))]
         {
             let connector = TimeoutService::new(
                 self.timeout,
-                apply_fn(self.connector, |msg: Connect, srv| {
-                    srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                })
-                .map_err(ConnectError::from)
-                .map(|stream| (stream.into_parts().0, Protocol::Http1)),
+                ProxyConnector::new(self.connector, proxy.clone(), resolver)
+                    .map_err(ConnectError::from)
+                    .map(move |stream| (stream.into_parts().0, proto)),
             )
             .map_err(|e| match e {
                 TimeoutError::Service(e) => e,
@@ -233,86 +399,87 @@ where
             });
 
             connect_impl::InnerConnector {
+                proxy,
                 tcp_pool: ConnectionPool::new(
                     connector,
                     self.conn_lifetime,
                     self.conn_keep_alive,
                     None,
                     self.limit,
+                    self.limit_per_host,
+                    &self.metrics,
                 ),
             }
         }
         #[cfg(any(feature = "openssl", feature = "rustls"))]
         {
+            #[cfg(feature = "rustls")]
             const H2: &[u8] = b"h2";
-            #[cfg(feature = "openssl")]
-            use actix_connect::ssl::OpensslConnector;
             #[cfg(feature = "rustls")]
             use actix_connect::ssl::RustlsConnector;
-            use actix_service::{boxed::service, pipeline};
+            use actix_service::boxed::service;
             #[cfg(feature = "rustls")]
             use rust_tls::Session;
 
-            let ssl_service = TimeoutService::new(
+            let tcp = TimeoutService::new(
                 self.timeout,
-                pipeline(
-                    apply_fn(self.connector.clone(), |msg: Connect, srv| {
-                        srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                    })
-                    .map_err(ConnectError::from),
+                ProxyConnector::new(
+                    self.connector.clone(),
+                    proxy.clone(),
+                    resolver.clone(),
                 )
-                .and_then(match self.ssl {
-                    #[cfg(feature = "openssl")]
-                    SslConnector::Openssl(ssl) => service(
-                        OpensslConnector::service(ssl)
-                            .map(|stream| {
-                                let sock = stream.into_parts().0;
-                                let h2 = sock
-                                    .ssl()
-                                    .selected_alpn_protocol()
-                                    .map(|protos| protos.windows(2).any(|w| w == H2))
-                                    .unwrap_or(false);
-                                if h2 {
-                                    (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
-                                } else {
-                                    (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
-                                }
-                            })
-                            .map_err(ConnectError::from),
-                    ),
-                    #[cfg(feature = "rustls")]
-                    SslConnector::Rustls(ssl) => service(
-                        RustlsConnector::service(ssl)
-                            .map_err(ConnectError::from)
-                            .map(|stream| {
-                                let sock = stream.into_parts().0;
-                                let h2 = sock
-                                    .get_ref()
-                                    .1
-                                    .get_alpn_protocol()
-                                    .map(|protos| protos.windows(2).any(|w| w == H2))
-                                    .unwrap_or(false);
-                                if h2 {
-                                    (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
-                                } else {
-                                    (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
-                                }
-                            }),
-                    ),
-                }),
+                .map_err(ConnectError::from),
             )
             .map_err(|e| match e {
                 TimeoutError::Service(e) => e,
                 TimeoutError::Timeout => ConnectError::Timeout,
             });
 
+            let http11_only = self.max_http_version != Version::HTTP_2;
+            let ssl_service = connect_impl::SslConnect::new(
+                tcp,
+                self.handshake_timeout.unwrap_or(self.timeout),
+                match self.ssl {
+                    #[cfg(feature = "openssl")]
+                    SslConnector::Openssl(ssl) => {
+                        service(connect_impl::OpensslHandshake {
+                            connector: ssl,
+                            http11_only,
+                        })
+                    }
+                    #[cfg(feature = "rustls")]
+                    SslConnector::Rustls(ssl) => service(
+                        RustlsConnector::service(if http11_only {
+                            let mut config = (*ssl).clone();
+                            config.set_protocols(&[b"http/1.1".to_vec()]);
+                            Arc::new(config)
+                        } else {
+                            ssl
+                        })
+                        .map_err(ConnectError::from)
+                        .map(|stream| {
+                            let sock = stream.into_parts().0;
+                            let h2 = sock
+                                .get_ref()
+                                .1
+                                .get_alpn_protocol()
+                                .map(|protos| protos.windows(2).any(|w| w == H2))
+                                .unwrap_or(false);
+                            if h2 {
+                                (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
+                            } else {
+                                (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
+                            }
+                        }),
+                    ),
+                },
+            );
+
             let tcp_service = TimeoutService::new(
                 self.timeout,
-                apply_fn(self.connector, |msg: Connect, srv| {
-                    srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                })
-                .map_err(ConnectError::from)
-                .map(|stream| (stream.into_parts().0, Protocol::Http1)),
+                ProxyConnector::new(self.connector, proxy.clone(), resolver)
+                    .map_err(ConnectError::from)
+                    .map(move |stream| (stream.into_parts().0, proto)),
             )
             .map_err(|e| match e {
                 TimeoutError::Service(e) => e,
@@ -320,12 +487,15 @@ where
             });
 
             connect_impl::InnerConnector {
+                proxy,
                 tcp_pool: ConnectionPool::new(
                     tcp_service,
                     self.conn_lifetime,
                     self.conn_keep_alive,
                     None,
                     self.limit,
+                    self.limit_per_host,
+                    &self.metrics,
                 ),
                 ssl_pool: ConnectionPool::new(
                     ssl_service,
@@ -333,6 +503,8 @@ where
                     self.conn_keep_alive,
                     Some(self.disconnect_timeout),
                     self.limit,
+                    self.limit_per_host,
+                    &self.metrics,
                 ),
             }
         }
@@ -343,7 +515,7 @@ where
 mod connect_impl {
     use std::task::{Context, Poll};
 
-    use futures::future::{err, Either, Ready};
+    use futures::future::{err, Either, FutureExt, Ready};
     use futures::ready;
 
     use super::*;
@@ -355,6 +527,7 @@ mod connect_impl {
         T: Service<Request = Connect, Response = (Io, Protocol), Error = ConnectError>
             + 'static,
     {
+        pub(crate) proxy: Rc<Proxy>,
         pub(crate) tcp_pool: ConnectionPool<T, Io>,
     }
 
@@ -366,6 +539,7 @@ mod connect_impl {
     {
         fn clone(&self) -> Self {
             InnerConnector {
+                proxy: self.proxy.clone(),
                 tcp_pool: self.tcp_pool.clone(),
             }
         }
@@ -394,7 +568,15 @@ mod connect_impl {
                 Some("https") | Some("wss") => {
                     Either::Right(err(ConnectError::SslIsNotSupported))
                 }
-                _ => Either::Left(self.tcp_pool.call(req)),
+                _ => match self.proxy.forward(&req.uri) {
+                    Some(forward) => Either::Left(
+                        self.tcp_pool
+                            .call(req)
+                            .map(|res| res.map(|conn| conn.set_forward(forward)))
+                            .boxed_local(),
+                    ),
+                    None => Either::Left(self.tcp_pool.call(req)),
+                },
             }
         }
     }
@@ -402,14 +584,126 @@ mod connect_impl {
 
 #[cfg(any(feature = "openssl", feature = "rustls"))]
 mod connect_impl {
+    use std::cell::RefCell;
     use std::marker::PhantomData;
 
-    use futures::future::Either;
+    use futures::future::{Either, FutureExt, LocalBoxFuture};
     use futures::ready;
+    use tokio_timer::Timeout;
 
     use super::*;
     use crate::client::connection::EitherConnection;
 
+    /// Performs TLS handshake with openssl connector, ALPN protocols of
+    /// the connector are replaced if only HTTP/1.1 is allowed.
+    #[cfg(feature = "openssl")]
+    pub(crate) struct OpensslHandshake {
+        pub(crate) connector: OpensslConnector,
+        pub(crate) http11_only: bool,
+    }
+
+    #[cfg(feature = "openssl")]
+    impl<U> Service for OpensslHandshake
+    where
+        U: AsyncRead + AsyncWrite + Unpin + fmt::Debug + 'static,
+    {
+        type Request = TcpConnection<Uri, U>;
+        type Response = (Box<dyn Io>, Protocol);
+        type Error = ConnectError;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, ConnectError>>;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: TcpConnection<Uri, U>) -> Self::Future {
+            let (io, uri) = req.into_parts();
+            let config = self.connector.configure();
+            let http11_only = self.http11_only;
+
+            async move {
+                let mut config = config.map_err(|e| ConnectError::SslError(e.into()))?;
+                if http11_only {
+                    config
+                        .set_alpn_protos(b"\x08http/1.1")
+                        .map_err(|e| ConnectError::SslError(e.into()))?;
+                }
+                let host = uri.host().unwrap_or("");
+                let sock = tokio_openssl::connect(config, host, io).await?;
+
+                let h2 = sock
+                    .ssl()
+                    .selected_alpn_protocol()
+                    .map(|protos| protos.windows(2).any(|w| w == b"h2"))
+                    .unwrap_or(false);
+                if h2 {
+                    Ok((Box::new(sock) as Box<dyn Io>, Protocol::Http2))
+                } else {
+                    Ok((Box::new(sock) as Box<dyn Io>, Protocol::Http1))
+                }
+            }
+            .boxed_local()
+        }
+    }
+
+    /// Opens tcp connection and performs TLS handshake, handshake timeout
+    /// of the request overrides timeout of the connector.
+    pub(crate) struct SslConnect<T, S> {
+        tcp: T,
+        ssl: Rc<RefCell<S>>,
+        timeout: Duration,
+    }
+
+    impl<T, S> SslConnect<T, S> {
+        pub(crate) fn new(tcp: T, timeout: Duration, ssl: S) -> Self {
+            SslConnect {
+                tcp,
+                ssl: Rc::new(RefCell::new(ssl)),
+                timeout,
+            }
+        }
+    }
+
+    impl<T, S, U> Service for SslConnect<T, S>
+    where
+        T: Service<Request = Connect, Error = ConnectError>,
+        T::Response: 'static,
+        T::Future: 'static,
+        S: Service<
+                Request = T::Response,
+                Response = (U, Protocol),
+                Error = ConnectError,
+            > + 'static,
+        S::Future: 'static,
+        U: 'static,
+    {
+        type Request = Connect;
+        type Response = (U, Protocol);
+        type Error = ConnectError;
+        type Future = LocalBoxFuture<'static, Result<(U, Protocol), ConnectError>>;
+
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+            ready!(self.tcp.poll_ready(cx))?;
+            self.ssl.borrow_mut().poll_ready(cx)
+        }
+
+        fn call(&mut self, req: Connect) -> Self::Future {
+            let timeout = req.handshake_timeout.unwrap_or(self.timeout);
+            let fut = self.tcp.call(req);
+            let ssl = self.ssl.clone();
+
+            async move {
+                let io = fut.await?;
+                let fut = ssl.borrow_mut().call(io);
+                match Timeout::new(fut, timeout).await {
+                    Ok(res) => res,
+                    Err(_) => Err(ConnectError::HandshakeTimeout),
+                }
+            }
+            .boxed_local()
+        }
+    }
+
     pub(crate) struct InnerConnector<T1, T2, Io1, Io2>
     where
         Io1: AsyncRead + AsyncWrite + Unpin + 'static,
@@ -417,6 +711,7 @@ mod connect_impl {
         T1: Service<Request = Connect, Response = (Io1, Protocol), Error = ConnectError>,
         T2: Service<Request = Connect, Response = (Io2, Protocol), Error = ConnectError>,
     {
+        pub(crate) proxy: Rc<Proxy>,
         pub(crate) tcp_pool: ConnectionPool<T1, Io1>,
         pub(crate) ssl_pool: ConnectionPool<T2, Io2>,
     }
@@ -432,6 +727,7 @@ mod connect_impl {
     {
         fn clone(&self) -> Self {
             InnerConnector {
+                proxy: self.proxy.clone(),
                 tcp_pool: self.tcp_pool.clone(),
                 ssl_pool: self.ssl_pool.clone(),
             }
@@ -465,10 +761,20 @@ mod connect_impl {
                     fut: self.ssl_pool.call(req),
                     _t: PhantomData,
                 }),
-                _ => Either::Left(InnerConnectorResponseA {
-                    fut: self.tcp_pool.call(req),
-                    _t: PhantomData,
-                }),
+                _ => {
+                    let forward = self.proxy.forward(&req.uri);
+                    let fut = self.tcp_pool.call(req);
+                    let fut = match forward {
+                        Some(forward) => fut
+                            .map(|res| res.map(|conn| conn.set_forward(forward)))
+                            .boxed_local(),
+                        None => fut,
+                    };
+                    Either::Left(InnerConnectorResponseA {
+                        fut,
+                        _t: PhantomData,
+                    })
+                }
             }
         }
     }
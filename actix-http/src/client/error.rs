@@ -38,6 +38,10 @@ pub enum ConnectError {
     #[display(fmt = "Timeout out while establishing connection")]
     Timeout,
 
+    /// TLS handshake took too long
+    #[display(fmt = "Timeout out while performing TLS handshake")]
+    HandshakeTimeout,
+
     /// Connector has been disconnected
     #[display(fmt = "Internal error: connector has been disconnected")]
     Disconnected,
@@ -119,7 +123,9 @@ pub enum SendRequestError {
 impl ResponseError for SendRequestError {
     fn error_response(&self) -> Response {
         match *self {
-            SendRequestError::Connect(ConnectError::Timeout) => {
+            SendRequestError::Timeout
+            | SendRequestError::Connect(ConnectError::Timeout)
+            | SendRequestError::Connect(ConnectError::HandshakeTimeout) => {
                 Response::GatewayTimeout()
             }
             SendRequestError::Connect(_) => Response::BadGateway(),
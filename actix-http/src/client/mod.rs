@@ -7,14 +7,24 @@ mod error;
 mod h1proto;
 mod h2proto;
 mod pool;
+mod proxy;
+mod resolver;
+#[cfg(unix)]
+mod unix;
 
 pub use self::connection::Connection;
 pub use self::connector::Connector;
 pub use self::error::{ConnectError, FreezeRequestError, InvalidUrl, SendRequestError};
-pub use self::pool::Protocol;
+pub use self::pool::{HostStats, PoolMetrics, Protocol};
+pub use self::proxy::Proxy;
+pub use self::resolver::Resolve;
+#[cfg(unix)]
+pub use self::unix::UnixConnector;
 
 #[derive(Clone)]
 pub struct Connect {
     pub uri: Uri,
     pub addr: Option<std::net::SocketAddr>,
+    /// TLS handshake timeout of the request, overrides connector setting
+    pub handshake_timeout: Option<std::time::Duration>,
 }
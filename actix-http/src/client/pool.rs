@@ -1,9 +1,10 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::fmt;
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
@@ -41,6 +42,62 @@ impl From<Authority> for Key {
     }
 }
 
+/// Connection pool usage of a single host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostStats {
+    /// Host and port of the remote peer.
+    pub authority: Authority,
+    /// Number of idle connections kept in the pool.
+    pub idle: usize,
+    /// Number of connections in use or being opened.
+    pub active: usize,
+    /// Number of requests waiting for a connection.
+    pub waiting: usize,
+}
+
+/// Observes connection pool usage.
+///
+/// Handle is obtained with `Connector::metrics()` and keeps working after
+/// the connector is finished.
+#[derive(Clone, Default)]
+pub struct PoolMetrics(Rc<RefCell<Vec<Weak<RefCell<dyn PoolStats>>>>>);
+
+impl PoolMetrics {
+    pub(crate) fn register(&self, pool: Rc<RefCell<dyn PoolStats>>) {
+        self.0.borrow_mut().push(Rc::downgrade(&pool));
+    }
+
+    /// Snapshot of pool usage per host, sorted by authority.
+    pub fn snapshot(&self) -> Vec<HostStats> {
+        let mut hosts = Vec::new();
+        self.0.borrow_mut().retain(|pool| {
+            if let Some(pool) = pool.upgrade() {
+                pool.borrow().stats(&mut hosts);
+                true
+            } else {
+                false
+            }
+        });
+        hosts.sort_by(|a: &HostStats, b: &HostStats| {
+            a.authority.as_str().cmp(b.authority.as_str())
+        });
+        hosts
+    }
+}
+
+impl fmt::Debug for PoolMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PoolMetrics")
+            .field(&self.snapshot())
+            .finish()
+    }
+}
+
+pub(crate) trait PoolStats {
+    /// Add usage of the pool to `hosts`.
+    fn stats(&self, hosts: &mut Vec<HostStats>);
+}
+
 /// Connections pool
 pub(crate) struct ConnectionPool<T, Io: 'static>(Rc<RefCell<T>>, Rc<RefCell<Inner<Io>>>);
 
@@ -56,21 +113,25 @@ where
         conn_keep_alive: Duration,
         disconnect_timeout: Option<Duration>,
         limit: usize,
+        limit_per_host: usize,
+        metrics: &PoolMetrics,
     ) -> Self {
-        ConnectionPool(
-            Rc::new(RefCell::new(connector)),
-            Rc::new(RefCell::new(Inner {
-                conn_lifetime,
-                conn_keep_alive,
-                disconnect_timeout,
-                limit,
-                acquired: 0,
-                waiters: Slab::new(),
-                waiters_queue: IndexSet::new(),
-                available: HashMap::new(),
-                waker: LocalWaker::new(),
-            })),
-        )
+        let inner = Rc::new(RefCell::new(Inner {
+            conn_lifetime,
+            conn_keep_alive,
+            disconnect_timeout,
+            limit,
+            limit_per_host,
+            acquired: 0,
+            acquired_per_host: HashMap::new(),
+            waiters: Slab::new(),
+            waiters_queue: IndexSet::new(),
+            available: HashMap::new(),
+            waker: LocalWaker::new(),
+        }));
+        metrics.register(inner.clone());
+
+        ConnectionPool(Rc::new(RefCell::new(connector)), inner)
     }
 }
 
@@ -236,7 +297,7 @@ where
     fn drop(&mut self) {
         if let Some(i) = self.inner.take() {
             let mut inner = i.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
             inner.check_availibility();
         }
     }
@@ -259,7 +320,9 @@ pub(crate) struct Inner<Io> {
     conn_keep_alive: Duration,
     disconnect_timeout: Option<Duration>,
     limit: usize,
+    limit_per_host: usize,
     acquired: usize,
+    acquired_per_host: HashMap<Key, usize>,
     available: HashMap<Key, VecDeque<AvailableConnection<Io>>>,
     waiters: Slab<
         Option<(
@@ -272,12 +335,31 @@ pub(crate) struct Inner<Io> {
 }
 
 impl<Io> Inner<Io> {
-    fn reserve(&mut self) {
+    fn reserve(&mut self, key: &Key) {
         self.acquired += 1;
+        *self.acquired_per_host.entry(key.clone()).or_insert(0) += 1;
     }
 
-    fn release(&mut self) {
+    fn release(&mut self, key: &Key) {
         self.acquired -= 1;
+        if let Some(num) = self.acquired_per_host.get_mut(key) {
+            *num -= 1;
+            if *num == 0 {
+                self.acquired_per_host.remove(key);
+            }
+        }
+    }
+
+    /// Check if global connections limit is reached.
+    fn is_exhausted(&self) -> bool {
+        self.limit > 0 && self.acquired >= self.limit
+    }
+
+    /// Check if connections limit of the host is reached.
+    fn is_host_exhausted(&self, key: &Key) -> bool {
+        self.limit_per_host > 0
+            && self.acquired_per_host.get(key).cloned().unwrap_or(0)
+                >= self.limit_per_host
     }
 
     fn release_waiter(&mut self, key: &Key, token: usize) {
@@ -311,11 +393,11 @@ where
 
     fn acquire(&mut self, key: &Key, cx: &mut Context) -> Acquire<Io> {
         // check limits
-        if self.limit > 0 && self.acquired >= self.limit {
+        if self.is_exhausted() || self.is_host_exhausted(key) {
             return Acquire::NotAvailable;
         }
 
-        self.reserve();
+        self.reserve(key);
 
         // check if open connection is available
         // cleanup stale connections at the same time
@@ -360,7 +442,7 @@ where
     }
 
     fn release_conn(&mut self, key: &Key, io: ConnectionType<Io>, created: Instant) {
-        self.acquired -= 1;
+        self.release(key);
         self.available
             .entry(key.clone())
             .or_insert_with(VecDeque::new)
@@ -372,8 +454,8 @@ where
         self.check_availibility();
     }
 
-    fn release_close(&mut self, io: ConnectionType<Io>) {
-        self.acquired -= 1;
+    fn release_close(&mut self, key: &Key, io: ConnectionType<Io>) {
+        self.release(key);
         if let Some(timeout) = self.disconnect_timeout {
             if let ConnectionType::H1(io) = io {
                 tokio_executor::current_thread::spawn(CloseConnection::new(io, timeout))
@@ -383,12 +465,41 @@ where
     }
 
     fn check_availibility(&self) {
-        if !self.waiters_queue.is_empty() && self.acquired < self.limit {
+        if !self.waiters_queue.is_empty() && !self.is_exhausted() {
             self.waker.wake();
         }
     }
 }
 
+impl<Io> PoolStats for Inner<Io> {
+    fn stats(&self, hosts: &mut Vec<HostStats>) {
+        let mut add = |key: &Key, idle, active, waiting| {
+            if let Some(host) = hosts.iter_mut().find(|h| h.authority == key.authority) {
+                host.idle += idle;
+                host.active += active;
+                host.waiting += waiting;
+            } else {
+                hosts.push(HostStats {
+                    authority: key.authority.clone(),
+                    idle,
+                    active,
+                    waiting,
+                });
+            }
+        };
+
+        for (key, conns) in &self.available {
+            add(key, conns.len(), 0, 0);
+        }
+        for (key, num) in &self.acquired_per_host {
+            add(key, 0, *num, 0);
+        }
+        for (key, _) in &self.waiters_queue {
+            add(key, 0, 0, 1);
+        }
+    }
+}
+
 struct CloseConnection<T> {
     io: T,
     timeout: Delay,
@@ -447,21 +558,30 @@ where
         let mut inner = this.inner.as_ref().borrow_mut();
         inner.waker.register(cx.waker());
 
-        // check waiters
+        // check waiters, requests for hosts that reached
+        // per host limit do not block other hosts
+        let mut idx = 0;
         loop {
             let (key, token) = {
-                if let Some((key, token)) = inner.waiters_queue.get_index(0) {
+                if let Some((key, token)) = inner.waiters_queue.get_index(idx) {
                     (key.clone(), *token)
                 } else {
                     break;
                 }
             };
             if inner.waiters.get(token).unwrap().is_none() {
+                let _ = inner.waiters_queue.shift_remove(&(key, token));
                 continue;
             }
 
             match inner.acquire(&key, cx) {
-                Acquire::NotAvailable => break,
+                Acquire::NotAvailable => {
+                    if inner.is_exhausted() {
+                        break;
+                    }
+                    idx += 1;
+                    continue;
+                }
                 Acquire::Acquired(io, created) => {
                     let tx = inner.waiters.get_mut(token).unwrap().take().unwrap().1;
                     if let Err(conn) = tx.send(Ok(IoConnection::new(
@@ -484,7 +604,7 @@ where
                     );
                 }
             }
-            let _ = inner.waiters_queue.swap_remove_index(0);
+            let _ = inner.waiters_queue.shift_remove(&(key, token));
         }
 
         Poll::Pending
@@ -535,7 +655,7 @@ where
     fn drop(&mut self) {
         if let Some(inner) = self.inner.take() {
             let mut inner = inner.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
             inner.check_availibility();
         }
     }
@@ -610,7 +730,7 @@ where
     pub(crate) fn close(&mut self, conn: IoConnection<T>) {
         if let Some(inner) = self.1.take() {
             let (io, _) = conn.into_inner();
-            inner.as_ref().borrow_mut().release_close(io);
+            inner.as_ref().borrow_mut().release_close(&self.0, io);
         }
     }
     pub(crate) fn release(&mut self, conn: IoConnection<T>) {
@@ -627,7 +747,7 @@ where
 impl<T> Drop for Acquired<T> {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
-            inner.as_ref().borrow_mut().release();
+            inner.as_ref().borrow_mut().release(&self.0);
         }
     }
 }
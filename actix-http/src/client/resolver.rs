@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::rc::Rc;
+
+use futures::future::{ok, FutureExt, LocalBoxFuture};
+
+/// Host name resolver of the `Connector`.
+///
+/// ```rust,ignore
+/// use std::io;
+/// use std::net::SocketAddr;
+/// use actix_http::client::{Connector, Resolve};
+/// use futures::future::{ok, FutureExt, LocalBoxFuture};
+///
+/// struct Discovery;
+///
+/// impl Resolve for Discovery {
+///     fn resolve(
+///         &self,
+///         host: &str,
+///         port: u16,
+///     ) -> LocalBoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+///         let addr = SocketAddr::from(([10, 0, 0, 1], port));
+///         ok(vec![addr]).boxed_local()
+///     }
+/// }
+///
+/// let connector = Connector::new().resolver(Discovery).finish();
+/// ```
+pub trait Resolve {
+    /// Resolve `host` to socket addresses, `port` is the port of request uri.
+    ///
+    /// Empty list fails connection with `ConnectError::NoRecords`.
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> LocalBoxFuture<'static, io::Result<Vec<SocketAddr>>>;
+}
+
+/// Static overrides and custom resolver.
+///
+/// If neither applies to a host, connector resolves it with dns.
+#[derive(Default)]
+pub(crate) struct HostResolver {
+    overrides: HashMap<String, Vec<SocketAddr>>,
+    resolver: Option<Rc<dyn Resolve>>,
+    next: RefCell<HashMap<String, usize>>,
+}
+
+impl HostResolver {
+    pub(crate) fn set_resolver<R: Resolve + 'static>(&mut self, resolver: R) {
+        self.resolver = Some(Rc::new(resolver));
+    }
+
+    pub(crate) fn add_override<I>(&mut self, host: &str, addrs: I)
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        self.overrides
+            .entry(host.to_ascii_lowercase())
+            .or_insert_with(Vec::new)
+            .extend(addrs);
+    }
+
+    /// Resolve host, returns `None` if host should be resolved with dns.
+    pub(crate) fn resolve(
+        this: &Rc<Self>,
+        host: &str,
+        port: u16,
+    ) -> Option<
+        LocalBoxFuture<'static, Result<Vec<SocketAddr>, actix_connect::ConnectError>>,
+    > {
+        let host = host.to_ascii_lowercase();
+
+        let fut = if let Some(addrs) = this.overrides.get(&host) {
+            let addrs = addrs
+                .iter()
+                .map(|addr| {
+                    if addr.port() == 0 {
+                        SocketAddr::new(addr.ip(), port)
+                    } else {
+                        *addr
+                    }
+                })
+                .collect();
+            ok(addrs).boxed_local()
+        } else if let Some(ref resolver) = this.resolver {
+            resolver.resolve(&host, port)
+        } else {
+            return None;
+        };
+
+        let this = this.clone();
+        Some(
+            async move {
+                let mut addrs = fut.await.map_err(actix_connect::ConnectError::Io)?;
+                if addrs.is_empty() {
+                    return Err(actix_connect::ConnectError::NoRecords);
+                }
+                this.rotate(host, &mut addrs);
+                Ok(addrs)
+            }
+            .boxed_local(),
+        )
+    }
+
+    /// Round-robin over host addresses, so connections are spread
+    /// between them.
+    fn rotate(&self, host: String, addrs: &mut Vec<SocketAddr>) {
+        let mut next = self.next.borrow_mut();
+        let idx = next.entry(host).or_insert(0);
+        let len = addrs.len();
+        addrs.rotate_left(*idx % len);
+        *idx = (*idx + 1) % len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_http_test::block_on;
+
+    struct TestResolver;
+
+    impl Resolve for TestResolver {
+        fn resolve(
+            &self,
+            host: &str,
+            port: u16,
+        ) -> LocalBoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+            let addrs = match host {
+                "empty.local" => vec![],
+                _ => vec![
+                    SocketAddr::from(([10, 0, 0, 1], port)),
+                    SocketAddr::from(([10, 0, 0, 2], port)),
+                ],
+            };
+            ok(addrs).boxed_local()
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut resolver = HostResolver::default();
+        resolver.add_override("Static.local", vec!["127.0.0.1:0".parse().unwrap()]);
+        resolver.add_override("port.local", vec!["127.0.0.1:8080".parse().unwrap()]);
+        let resolver = Rc::new(resolver);
+        assert!(HostResolver::resolve(&resolver, "rust-lang.org", 80).is_none());
+
+        block_on(async {
+            let addrs = HostResolver::resolve(&resolver, "static.local", 443)
+                .unwrap()
+                .await
+                .unwrap();
+            assert_eq!(addrs, vec!["127.0.0.1:443".parse().unwrap()]);
+
+            let addrs = HostResolver::resolve(&resolver, "port.local", 80)
+                .unwrap()
+                .await
+                .unwrap();
+            assert_eq!(addrs, vec!["127.0.0.1:8080".parse().unwrap()]);
+        })
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut resolver = HostResolver::default();
+        resolver.set_resolver(TestResolver);
+        let resolver = Rc::new(resolver);
+
+        block_on(async {
+            let first = |addrs: Vec<SocketAddr>| addrs[0].to_string();
+
+            let addrs = HostResolver::resolve(&resolver, "service.local", 80)
+                .unwrap()
+                .await;
+            assert_eq!(first(addrs.unwrap()), "10.0.0.1:80");
+            let addrs = HostResolver::resolve(&resolver, "service.local", 80)
+                .unwrap()
+                .await;
+            assert_eq!(first(addrs.unwrap()), "10.0.0.2:80");
+            let addrs = HostResolver::resolve(&resolver, "service.local", 80)
+                .unwrap()
+                .await;
+            assert_eq!(first(addrs.unwrap()), "10.0.0.1:80");
+
+            match HostResolver::resolve(&resolver, "empty.local", 80)
+                .unwrap()
+                .await
+            {
+                Err(actix_connect::ConnectError::NoRecords) => (),
+                _ => panic!(),
+            }
+        })
+    }
+}
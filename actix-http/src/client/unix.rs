@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_connect::{Connect as TcpConnect, Connection as TcpConnection};
+use actix_service::Service;
+use futures::future::{FutureExt, LocalBoxFuture};
+use http::Uri;
+use tokio_net::uds::UnixStream;
+
+/// Connector service that opens connections to unix domain socket.
+///
+/// Every request is sent to the same socket regardless of the host of
+/// request uri. It is used by `Connector::unix()`.
+#[derive(Clone, Debug)]
+pub struct UnixConnector {
+    path: Rc<PathBuf>,
+}
+
+impl UnixConnector {
+    /// Create connector for socket at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        UnixConnector {
+            path: Rc::new(path.as_ref().to_owned()),
+        }
+    }
+
+    /// Path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Service for UnixConnector {
+    type Request = TcpConnect<Uri>;
+    type Response = TcpConnection<Uri, UnixStream>;
+    type Error = actix_connect::ConnectError;
+    type Future =
+        LocalBoxFuture<'static, Result<Self::Response, actix_connect::ConnectError>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: TcpConnect<Uri>) -> Self::Future {
+        let path = self.path.clone();
+        let uri = format!("http://{}:{}/", req.host(), req.port())
+            .parse::<Uri>()
+            .unwrap_or_default();
+
+        async move {
+            let io = UnixStream::connect(path.as_ref()).await.map_err(|e| {
+                trace!("Unix socket connect error for {:?}: {:?}", path, e);
+                actix_connect::ConnectError::Io(e)
+            })?;
+            Ok(TcpConnection::new(io, uri))
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::net::UnixListener;
+
+    use actix_http_test::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_unix_connect() {
+        let path = std::env::temp_dir()
+            .join(format!("actix-http-unix-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let lst = UnixListener::bind(&path).unwrap();
+
+        let mut connector = UnixConnector::new(&path);
+        assert_eq!(connector.path(), path.as_path());
+
+        let uri = Uri::from_static("http://docker/info");
+        let conn = block_on(connector.call(TcpConnect::new(uri))).unwrap();
+        let (_, uri) = conn.into_parts();
+        assert_eq!(uri.host(), Some("docker"));
+        assert!(lst.accept().is_ok());
+
+        drop(lst);
+        let _ = fs::remove_file(&path);
+        let uri = Uri::from_static("http://docker/info");
+        assert!(block_on(connector.call(TcpConnect::new(uri))).is_err());
+    }
+}
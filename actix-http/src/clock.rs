@@ -0,0 +1,118 @@
+//! Clock for time based decisions, i.e. expiration of cookies or cached
+//! responses.
+//!
+//! Time could be advanced in tests with `MockClock`, so expiration is
+//! tested without sleeping. Mock clock is installed for current thread
+//! only, it affects code that runs on the thread of the test: client
+//! cookie store and cache, identity login and visit deadlines, request
+//! deadlines and server connection timers. Server started on its own
+//! thread, i.e. with `actix_web::test::start()`, uses real time.
+//!
+//! Connection timers, i.e. keep-alive and client timeout, are driven by
+//! the timer wheel, they fire on the next tick of the wheel once mock
+//! clock is advanced past their deadlines.
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant, SystemTime};
+
+thread_local! {
+    static OFFSET: Cell<Option<Duration>> = Cell::new(None);
+}
+
+fn offset() -> Duration {
+    OFFSET.with(|offset| offset.get().unwrap_or_default())
+}
+
+/// Current instant, advanced by mock clock of current thread.
+pub fn now() -> Instant {
+    Instant::now() + offset()
+}
+
+/// Current system time, advanced by mock clock of current thread.
+pub fn system_now() -> SystemTime {
+    SystemTime::now() + offset()
+}
+
+/// Mock clock of current thread.
+///
+/// Clock is installed on creation and removed once it is dropped, only one
+/// clock could be installed at a time.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_http::clock::{self, MockClock};
+///
+/// let clock = MockClock::new();
+/// let start = clock::now();
+///
+/// clock.advance(Duration::from_secs(3600));
+/// assert!(clock::now() - start >= Duration::from_secs(3600));
+/// ```
+pub struct MockClock {
+    // clock is bound to current thread
+    _t: PhantomData<*const ()>,
+}
+
+impl MockClock {
+    /// Install mock clock for current thread.
+    ///
+    /// Panics if mock clock is already installed.
+    pub fn new() -> Self {
+        OFFSET.with(|offset| {
+            assert!(offset.get().is_none(), "Mock clock is already installed");
+            offset.set(Some(Duration::from_secs(0)));
+        });
+        MockClock { _t: PhantomData }
+    }
+
+    /// Advance time by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        OFFSET.with(|offset| {
+            offset.set(Some(offset.get().unwrap_or_default() + duration))
+        });
+    }
+
+    /// Total time the clock has been advanced by.
+    pub fn advanced(&self) -> Duration {
+        offset()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+impl Drop for MockClock {
+    fn drop(&mut self) {
+        OFFSET.with(|offset| offset.set(None));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let start = SystemTime::now();
+        {
+            let clock = MockClock::new();
+            clock.advance(Duration::from_secs(60));
+            clock.advance(Duration::from_secs(60));
+            assert_eq!(clock.advanced(), Duration::from_secs(120));
+            assert!(system_now() >= start + Duration::from_secs(120));
+        }
+        assert!(system_now() < start + Duration::from_secs(120));
+
+        let _clock = MockClock::new();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mock_clock_installed() {
+        let _clock = MockClock::new();
+        let _clock2 = MockClock::new();
+    }
+}
@@ -1,16 +1,14 @@
 use std::cell::UnsafeCell;
-use std::fmt;
-use std::fmt::Write;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use bytes::BytesMut;
 use futures::{future, Future, FutureExt};
-use time;
-use tokio_timer::{delay, delay_for, Delay};
+use tokio_timer::delay_for;
 
-// "Sun, 06 Nov 1994 08:49:37 GMT".len()
-const DATE_VALUE_LENGTH: usize = 29;
+use crate::clock;
+use crate::header::{http_date_now, DATE_VALUE_LENGTH};
+use crate::timer::{Timer, TimerWheel, DEFAULT_TICK};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// Server keep-alive setting
@@ -39,6 +37,70 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Case of header names written by HTTP/1 encoder
+pub enum HeaderCase {
+    /// Lower case, i.e. `content-type`
+    Lower,
+    /// Upper camel case, i.e. `Content-Type`
+    Camel,
+    /// Case of names as inserted with `HeaderMap::insert_cased()`, other
+    /// headers are written in lower case
+    Preserve,
+}
+
+impl Default for HeaderCase {
+    fn default() -> Self {
+        HeaderCase::Lower
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Flush strategy of HTTP/1 write buffer
+pub enum FlushStrategy {
+    /// Write buffer is flushed whenever dispatcher produced data
+    Eager,
+    /// Response head and chunks of streaming body are kept in write buffer
+    /// until it reaches low watermark, the body is complete or data is kept
+    /// for 200ms. Fewer syscalls are made at the cost of latency of
+    /// streaming responses.
+    Corked,
+}
+
+impl Default for FlushStrategy {
+    fn default() -> Self {
+        FlushStrategy::Eager
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Limits of request head
+///
+/// Requests with too many headers or with too large header are responded
+/// with 431 (Request Header Fields Too Large), requests with too long uri
+/// with 414 (URI Too Long).
+pub struct HeaderLimits {
+    /// Maximum number of headers, by default 96.
+    pub max_headers: usize,
+    /// Maximum size of a header, name and value, by default 128KiB.
+    pub max_header_size: usize,
+    /// Maximum size of request head, by default 128KiB.
+    pub max_head_size: usize,
+    /// Maximum length of request uri, by default 128KiB.
+    pub max_uri_length: usize,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        HeaderLimits {
+            max_headers: 96,
+            max_header_size: 131_072,
+            max_head_size: 131_072,
+            max_uri_length: 131_072,
+        }
+    }
+}
+
 /// Http service configuration
 pub struct ServiceConfig(Rc<Inner>);
 
@@ -47,7 +109,13 @@ struct Inner {
     client_timeout: u64,
     client_disconnect: u64,
     ka_enabled: bool,
+    limits: HeaderLimits,
+    header_case: HeaderCase,
+    write_low: usize,
+    write_high: usize,
+    flush: FlushStrategy,
     timer: DateService,
+    wheel: TimerWheel,
 }
 
 impl Clone for ServiceConfig {
@@ -68,6 +136,21 @@ impl ServiceConfig {
         keep_alive: KeepAlive,
         client_timeout: u64,
         client_disconnect: u64,
+    ) -> ServiceConfig {
+        ServiceConfig::with_limits(
+            keep_alive,
+            client_timeout,
+            client_disconnect,
+            HeaderLimits::default(),
+        )
+    }
+
+    /// Create instance of `ServiceConfig` with limits of request head
+    pub fn with_limits(
+        keep_alive: KeepAlive,
+        client_timeout: u64,
+        client_disconnect: u64,
+        limits: HeaderLimits,
     ) -> ServiceConfig {
         let (keep_alive, ka_enabled) = match keep_alive {
             KeepAlive::Timeout(val) => (val as u64, true),
@@ -85,10 +168,93 @@ impl ServiceConfig {
             ka_enabled,
             client_timeout,
             client_disconnect,
+            limits,
+            header_case: HeaderCase::Lower,
+            write_low: 4096,
+            write_high: 32_768,
+            flush: FlushStrategy::Eager,
             timer: DateService::new(),
+            wheel: TimerWheel::new(Duration::from_millis(DEFAULT_TICK)),
         }))
     }
 
+    /// Set tick of the timer wheel.
+    ///
+    /// Keep-alive, slow request and disconnect timers of connections are
+    /// rounded up to the tick.
+    pub fn with_timer_tick(mut self, tick: Duration) -> ServiceConfig {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .wheel = TimerWheel::new(tick);
+        self
+    }
+
+    #[inline]
+    /// Tick of the timer wheel.
+    pub fn timer_tick(&self) -> Duration {
+        self.0.wheel.tick()
+    }
+
+    /// Set case of header names written by HTTP/1 encoder.
+    pub fn with_header_case(mut self, case: HeaderCase) -> ServiceConfig {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .header_case = case;
+        self
+    }
+
+    #[inline]
+    /// Case of header names written by HTTP/1 encoder.
+    pub fn header_case(&self) -> HeaderCase {
+        self.0.header_case
+    }
+
+    /// Set low and high watermarks of HTTP/1 write buffer.
+    ///
+    /// Buffer is grown by high watermark once its spare capacity is less
+    /// than low watermark. Response body is not polled while buffer holds
+    /// more than high watermark.
+    ///
+    /// # Panics
+    ///
+    /// Panics if low watermark is zero or greater than high watermark.
+    pub fn with_write_watermarks(mut self, low: usize, high: usize) -> ServiceConfig {
+        assert!(
+            0 < low && low <= high,
+            "Write watermarks must satisfy 0 < low <= high"
+        );
+        let inner = Rc::get_mut(&mut self.0).expect("Multiple copies exist");
+        inner.write_low = low;
+        inner.write_high = high;
+        self
+    }
+
+    /// Set flush strategy of HTTP/1 write buffer.
+    pub fn with_flush_strategy(mut self, flush: FlushStrategy) -> ServiceConfig {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .flush = flush;
+        self
+    }
+
+    #[inline]
+    /// Low and high watermarks of HTTP/1 write buffer.
+    pub fn write_watermarks(&self) -> (usize, usize) {
+        (self.0.write_low, self.0.write_high)
+    }
+
+    #[inline]
+    /// Flush strategy of HTTP/1 write buffer.
+    pub fn flush_strategy(&self) -> FlushStrategy {
+        self.0.flush
+    }
+
+    #[inline]
+    /// Limits of request head.
+    pub fn header_limits(&self) -> &HeaderLimits {
+        &self.0.limits
+    }
+
     #[inline]
     /// Keep alive duration if configured.
     pub fn keep_alive(&self) -> Option<Duration> {
@@ -103,12 +269,14 @@ impl ServiceConfig {
 
     #[inline]
     /// Client timeout for first request.
-    pub fn client_timer(&self) -> Option<Delay> {
+    pub fn client_timer(&self) -> Option<Timer> {
         let delay_time = self.0.client_timeout;
         if delay_time != 0 {
-            Some(delay(
-                self.0.timer.now() + Duration::from_millis(delay_time),
-            ))
+            Some(
+                self.0
+                    .wheel
+                    .timer(self.0.timer.now() + Duration::from_millis(delay_time)),
+            )
         } else {
             None
         }
@@ -136,9 +304,9 @@ impl ServiceConfig {
 
     #[inline]
     /// Return keep-alive timer delay is configured.
-    pub fn keep_alive_timer(&self) -> Option<Delay> {
+    pub fn keep_alive_timer(&self) -> Option<Timer> {
         if let Some(ka) = self.0.keep_alive {
-            Some(delay(self.0.timer.now() + ka))
+            Some(self.0.wheel.timer(self.0.timer.now() + ka))
         } else {
             None
         }
@@ -153,6 +321,11 @@ impl ServiceConfig {
         }
     }
 
+    /// Register timer of the connection in the timer wheel.
+    pub(crate) fn timer(&self, deadline: Instant) -> Timer {
+        self.0.wheel.timer(deadline)
+    }
+
     #[inline]
     pub(crate) fn now(&self) -> Instant {
         self.0.timer.now()
@@ -162,47 +335,13 @@ impl ServiceConfig {
     pub fn set_date(&self, dst: &mut BytesMut) {
         let mut buf: [u8; 39] = [0; 39];
         buf[..6].copy_from_slice(b"date: ");
-        self.0
-            .timer
-            .set_date(|date| buf[6..35].copy_from_slice(&date.bytes));
+        buf[6..35].copy_from_slice(&self.0.timer.date());
         buf[35..].copy_from_slice(b"\r\n\r\n");
         dst.extend_from_slice(&buf);
     }
 
     pub(crate) fn set_date_header(&self, dst: &mut BytesMut) {
-        self.0
-            .timer
-            .set_date(|date| dst.extend_from_slice(&date.bytes));
-    }
-}
-
-#[derive(Copy, Clone)]
-struct Date {
-    bytes: [u8; DATE_VALUE_LENGTH],
-    pos: usize,
-}
-
-impl Date {
-    fn new() -> Date {
-        let mut date = Date {
-            bytes: [0; DATE_VALUE_LENGTH],
-            pos: 0,
-        };
-        date.update();
-        date
-    }
-    fn update(&mut self) {
-        self.pos = 0;
-        write!(self, "{}", time::at_utc(time::get_time()).rfc822()).unwrap();
-    }
-}
-
-impl fmt::Write for Date {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        let len = s.len();
-        self.bytes[self.pos..self.pos + len].copy_from_slice(s.as_bytes());
-        self.pos += len;
-        Ok(())
+        dst.extend_from_slice(&self.0.timer.date());
     }
 }
 
@@ -210,7 +349,7 @@ impl fmt::Write for Date {
 struct DateService(Rc<DateServiceInner>);
 
 struct DateServiceInner {
-    current: UnsafeCell<Option<(Date, Instant)>>,
+    current: UnsafeCell<Option<(Instant, [u8; DATE_VALUE_LENGTH])>>,
 }
 
 impl DateServiceInner {
@@ -225,9 +364,7 @@ impl DateServiceInner {
     }
 
     fn update(&self) {
-        let now = Instant::now();
-        let date = Date::new();
-        *(unsafe { &mut *self.current.get() }) = Some((date, now));
+        *(unsafe { &mut *self.current.get() }) = Some((clock::now(), http_date_now()));
     }
 }
 
@@ -240,7 +377,7 @@ impl DateService {
         if unsafe { (&*self.0.current.get()).is_none() } {
             self.0.update();
 
-            // periodic date update
+            // periodic update
             let s = self.clone();
             tokio_executor::current_thread::spawn(
                 delay_for(Duration::from_millis(500)).then(move |_| {
@@ -253,12 +390,13 @@ impl DateService {
 
     fn now(&self) -> Instant {
         self.check_date();
-        unsafe { (&*self.0.current.get()).as_ref().unwrap().1 }
+        unsafe { (&*self.0.current.get()).unwrap().0 }
     }
 
-    fn set_date<F: FnMut(&Date)>(&self, mut f: F) {
+    /// Formatted date, refreshed together with `now()` by the periodic update.
+    fn date(&self) -> [u8; DATE_VALUE_LENGTH] {
         self.check_date();
-        f(&unsafe { (&*self.0.current.get()).as_ref().unwrap().0 })
+        unsafe { (&*self.0.current.get()).unwrap().1 }
     }
 }
 
@@ -284,7 +422,39 @@ mod tests {
             let mut buf2 = BytesMut::with_capacity(DATE_VALUE_LENGTH + 10);
             settings.set_date(&mut buf2);
             assert_eq!(buf1, buf2);
+
+            let mut buf3 = BytesMut::with_capacity(DATE_VALUE_LENGTH);
+            settings.set_date_header(&mut buf3);
+            assert_eq!(&buf1[6..35], &buf3[..]);
             future::ok::<_, ()>(())
         }));
     }
+
+    #[test]
+    fn test_write_buffer() {
+        let config = ServiceConfig::default();
+        assert_eq!(config.write_watermarks(), (4096, 32_768));
+        assert_eq!(config.flush_strategy(), FlushStrategy::Eager);
+
+        let config = ServiceConfig::default()
+            .with_write_watermarks(1024, 8192)
+            .with_flush_strategy(FlushStrategy::Corked);
+        assert_eq!(config.write_watermarks(), (1024, 8192));
+        assert_eq!(config.flush_strategy(), FlushStrategy::Corked);
+
+        let config = ServiceConfig::default().with_write_watermarks(1, 1);
+        assert_eq!(config.write_watermarks(), (1, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_write_watermarks_zero() {
+        let _ = ServiceConfig::default().with_write_watermarks(0, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_write_watermarks_inverted() {
+        let _ = ServiceConfig::default().with_write_watermarks(8192, 1024);
+    }
 }
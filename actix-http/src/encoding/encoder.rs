@@ -13,7 +13,7 @@ use flate2::write::{GzEncoder, ZlibEncoder};
 
 use crate::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::http::header::{ContentEncoding, CONTENT_ENCODING};
-use crate::http::{HeaderValue, HttpTryFrom, StatusCode};
+use crate::http::{HeaderMap, HeaderValue, HttpTryFrom, StatusCode};
 use crate::{Error, ResponseHead};
 
 use super::Writer;
@@ -32,6 +32,20 @@ impl<B: MessageBody> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        Encoder::response_with_level(encoding, None, head, body)
+    }
+
+    /// Encode response body with compression level, default level of
+    /// the encoding is used if level is `None`.
+    ///
+    /// Levels of `gzip` and `deflate` are in range 0-9, levels of `br`
+    /// are in range 0-11, higher levels are capped.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
         let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
@@ -57,7 +71,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
+            if let Some(enc) = ContentEncoder::encoder(encoding, level) {
                 update_head(encoding, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
@@ -96,6 +110,29 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
         }
     }
 
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        if self.encoder.is_none() && self.fut.is_none() {
+            match self.body {
+                EncoderBody::Bytes(ref b) => b.size_hint(),
+                EncoderBody::Stream(ref b) => b.size_hint(),
+                EncoderBody::BoxedStream(ref b) => b.size_hint(),
+            }
+        } else {
+            (0, None)
+        }
+    }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        match self.body {
+            EncoderBody::Bytes(_) => Poll::Ready(Ok(None)),
+            EncoderBody::Stream(ref mut b) => b.poll_trailers(cx),
+            EncoderBody::BoxedStream(ref mut b) => b.poll_trailers(cx),
+        }
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
         loop {
             if self.eof {
@@ -173,6 +210,13 @@ fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
     );
 }
 
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+fn flate2_level(level: Option<u32>) -> flate2::Compression {
+    level
+        .map(|level| flate2::Compression::new(std::cmp::min(level, 9)))
+        .unwrap_or_else(flate2::Compression::fast)
+}
+
 enum ContentEncoder {
     #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
     Deflate(ZlibEncoder<Writer>),
@@ -183,22 +227,23 @@ enum ContentEncoder {
 }
 
 impl ContentEncoder {
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
         match encoding {
             #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_level(level),
             ))),
             #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_level(level),
             ))),
             #[cfg(feature = "brotli")]
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
-            }
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.map(|level| std::cmp::min(level, 11)).unwrap_or(3),
+            ))),
             _ => None,
         }
     }
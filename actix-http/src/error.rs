@@ -7,12 +7,14 @@ use std::string::FromUtf8Error;
 use std::{fmt, io, result};
 
 use actix_utils::timeout::TimeoutError;
+use backtrace::Backtrace;
 use bytes::BytesMut;
 use derive_more::{Display, From};
 pub use futures::channel::oneshot::Canceled;
 use http::uri::InvalidUri;
 use http::{header, Error as HttpError, StatusCode};
 use httparse;
+use lazy_static::lazy_static;
 use serde::de::value::Error as DeError;
 use serde_json::error::Error as JsonError;
 use serde_urlencoded::ser::Error as FormError;
@@ -42,8 +44,20 @@ pub type Result<T, E = Error> = result::Result<T, E>;
 /// for it that can be used to create an http response from it this means that
 /// if you have access to an actix `Error` you can always get a
 /// `ResponseError` reference from it.
+///
+/// `source()` chain of the underlying error is preserved, backtrace is
+/// captured on creation if `RUST_BACKTRACE` environment variable is set.
+/// Symbols of backtrace are resolved only when it is rendered.
 pub struct Error {
     cause: Box<dyn ResponseError>,
+    backtrace: Option<Backtrace>,
+}
+
+lazy_static! {
+    static ref CAPTURE_BACKTRACE: bool = match std::env::var_os("RUST_BACKTRACE") {
+        Some(val) => val != "0",
+        None => false,
+    };
 }
 
 impl Error {
@@ -56,6 +70,47 @@ impl Error {
     pub fn as_error<T: ResponseError + 'static>(&self) -> Option<&T> {
         ResponseError::downcast_ref(self.cause.as_ref())
     }
+
+    /// Backtrace of the place where error was created.
+    ///
+    /// Backtrace is captured only if `RUST_BACKTRACE` environment variable
+    /// is set. Symbols are not resolved on capture, call `resolve()` on a
+    /// clone of backtrace before printing it.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    /// Constructs a detailed error response for debugging.
+    ///
+    /// Response has the status of `error_response()` and a plain text body
+    /// with the error, its whole `source()` chain and backtrace, if it was
+    /// captured. It should not be used in production, as it exposes
+    /// internals of the application.
+    pub fn debug_response(&self) -> Response {
+        let mut resp = self.cause.error_response();
+        let mut buf = BytesMut::new();
+        let _ = writeln!(Writer(&mut buf), "{}", self);
+
+        let mut source = std::error::Error::source(self);
+        if source.is_some() {
+            let _ = writeln!(Writer(&mut buf), "\nCaused by:");
+        }
+        while let Some(err) = source {
+            let _ = writeln!(Writer(&mut buf), "    {}", err);
+            source = err.source();
+        }
+        if let Some(ref backtrace) = self.backtrace {
+            let mut backtrace = backtrace.clone();
+            backtrace.resolve();
+            let _ = write!(Writer(&mut buf), "\nBacktrace:\n{:?}", backtrace);
+        }
+
+        resp.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        resp.set_body(Body::from(buf))
+    }
 }
 
 /// Error that can be converted to `Response`
@@ -79,6 +134,14 @@ pub trait ResponseError: fmt::Debug + fmt::Display {
         resp.set_body(Body::from(buf))
     }
 
+    /// The lower-level source of this error, if any.
+    ///
+    /// Used as `source()` of `Error`, so the chain of underlying errors is
+    /// not lost when error is converted to `Error`.
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+
     #[doc(hidden)]
     fn __private_get_type_id__(&self) -> TypeId
     where
@@ -122,12 +185,8 @@ impl std::error::Error for Error {
         "actix-http::Error"
     }
 
-    fn cause(&self) -> Option<&dyn std::error::Error> {
-        None
-    }
-
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        self.cause.error_source()
     }
 }
 
@@ -149,8 +208,14 @@ impl From<Error> for Response {
 /// `Error` for any error that implements `ResponseError`
 impl<T: ResponseError + 'static> From<T> for Error {
     fn from(err: T) -> Error {
+        let backtrace = if *CAPTURE_BACKTRACE {
+            Some(Backtrace::new_unresolved())
+        } else {
+            None
+        };
         Error {
             cause: Box::new(err),
+            backtrace,
         }
     }
 }
@@ -253,6 +318,12 @@ pub enum ParseError {
     /// A message head is too large to be reasonable.
     #[display(fmt = "Message head is too large")]
     TooLarge,
+    /// Request has too many headers or a header is too large.
+    #[display(fmt = "Request header fields are too large")]
+    HeaderFieldsTooLarge,
+    /// Request uri is too long.
+    #[display(fmt = "Request uri is too long")]
+    UriTooLong,
     /// A message reached EOF, but is not complete.
     #[display(fmt = "Message is incomplete")]
     Incomplete,
@@ -275,7 +346,27 @@ pub enum ParseError {
 /// Return `BadRequest` for `ParseError`
 impl ResponseError for ParseError {
     fn error_response(&self) -> Response {
-        Response::new(StatusCode::BAD_REQUEST)
+        match self {
+            ParseError::HeaderFieldsTooLarge => {
+                Response::new(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+            }
+            ParseError::UriTooLong => Response::new(StatusCode::URI_TOO_LONG),
+            _ => Response::new(StatusCode::BAD_REQUEST),
+        }
+    }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            ParseError::Utf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
     }
 }
 
@@ -381,6 +472,20 @@ impl ResponseError for PayloadError {
             _ => Response::new(StatusCode::BAD_REQUEST),
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PayloadError::Incomplete(Some(e)) | PayloadError::Io(e) => Some(e),
+            PayloadError::Http2Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 /// Return `BadRequest` for `cookie::ParseError`
@@ -1005,6 +1110,33 @@ mod tests {
         assert_eq!(format!("{}", e), desc);
     }
 
+    #[test]
+    fn test_error_source() {
+        let orig = io::Error::new(io::ErrorKind::Other, "other");
+        let e = Error::from(PayloadError::Io(orig));
+        let source = e.source().unwrap();
+        assert_eq!(format!("{}", source), "other");
+        assert!(source.source().is_none());
+
+        let e = Error::from(ParseError::Incomplete);
+        assert!(e.source().is_none());
+    }
+
+    #[test]
+    fn test_error_debug_response() {
+        let orig = io::Error::new(io::ErrorKind::Other, "other");
+        let e = Error::from(ParseError::Io(orig));
+        let resp = e.debug_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = std::str::from_utf8(resp.body().get_ref()).unwrap();
+        assert!(body.starts_with("IO error: other\n\nCaused by:\n    other\n"));
+
+        let resp = Error::from(ParseError::Incomplete).debug_response();
+        let body = std::str::from_utf8(resp.body().get_ref()).unwrap();
+        assert!(body.starts_with("Message is incomplete\n"));
+        assert!(!body.contains("Caused by:"));
+    }
+
     #[test]
     fn test_error_http_response() {
         let orig = io::Error::new(io::ErrorKind::Other, "other");
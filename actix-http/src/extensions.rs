@@ -1,12 +1,37 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 use std::fmt;
 
 use hashbrown::HashMap;
 
 #[derive(Default)]
 /// A type map of request extensions.
+///
+/// Extensions are used for passing values from middlewares to handlers.
+/// Values are keyed by their type, so a middleware should store private
+/// newtype instead of common types like `String`, to avoid collisions with
+/// values of other middlewares.
+///
+/// Extensions of a request are shared by all middlewares and handler of the
+/// request and are available until response is sent, they are cleared once
+/// request is dropped.
+///
+/// ```rust
+/// use actix_http::Extensions;
+///
+/// /// Value of authentication middleware
+/// struct UserId(u64);
+///
+/// let mut extensions = Extensions::new();
+/// extensions.insert(UserId(1));
+///
+/// let hits = extensions.get_or_insert_with(|| 0usize);
+/// *hits += 1;
+///
+/// assert_eq!(extensions.get::<UserId>().map(|id| id.0), Some(1));
+/// assert_eq!(extensions.get::<usize>(), Some(&1));
+/// ```
 pub struct Extensions {
-    map: HashMap<TypeId, Box<dyn Any>>,
+    map: HashMap<TypeId, (&'static str, Box<dyn Any>)>,
 }
 
 impl Extensions {
@@ -21,9 +46,25 @@ impl Extensions {
     /// Insert a type into this `Extensions`.
     ///
     /// If a extension of this type already existed, it will
-    /// be returned.
+    /// be replaced.
     pub fn insert<T: 'static>(&mut self, val: T) {
-        self.map.insert(TypeId::of::<T>(), Box::new(val));
+        self.map
+            .insert(TypeId::of::<T>(), (type_name::<T>(), Box::new(val)));
+    }
+
+    /// Get a mutable reference to a type, value is inserted with `f` if
+    /// container does not contain it.
+    pub fn get_or_insert_with<T: 'static, F>(&mut self, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        let (_, boxed) = self
+            .map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| (type_name::<T>(), Box::new(f())));
+        (&mut **boxed as &mut (dyn Any + 'static))
+            .downcast_mut()
+            .expect("Extensions entry of different type")
     }
 
     /// Check if container contains entry
@@ -35,21 +76,21 @@ impl Extensions {
     pub fn get<T: 'static>(&self) -> Option<&T> {
         self.map
             .get(&TypeId::of::<T>())
-            .and_then(|boxed| (&**boxed as &(dyn Any + 'static)).downcast_ref())
+            .and_then(|(_, boxed)| (&**boxed as &(dyn Any + 'static)).downcast_ref())
     }
 
     /// Get a mutable reference to a type previously inserted on this `Extensions`.
     pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
-        self.map
-            .get_mut(&TypeId::of::<T>())
-            .and_then(|boxed| (&mut **boxed as &mut (dyn Any + 'static)).downcast_mut())
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|(_, boxed)| {
+            (&mut **boxed as &mut (dyn Any + 'static)).downcast_mut()
+        })
     }
 
     /// Remove a type from this `Extensions`.
     ///
     /// If a extension of this type existed, it will be returned.
     pub fn remove<T: 'static>(&mut self) -> Option<T> {
-        self.map.remove(&TypeId::of::<T>()).and_then(|boxed| {
+        self.map.remove(&TypeId::of::<T>()).and_then(|(_, boxed)| {
             (boxed as Box<dyn Any + 'static>)
                 .downcast()
                 .ok()
@@ -62,11 +103,31 @@ impl Extensions {
     pub fn clear(&mut self) {
         self.map.clear();
     }
+
+    /// Number of stored extensions.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Check if container is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Names of stored types, in unspecified order.
+    ///
+    /// Names are intended for debugging only, they are not guaranteed to be
+    /// unique or stable.
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.map.values().map(|(name, _)| *name)
+    }
 }
 
 impl fmt::Debug for Extensions {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Extensions").finish()
+        f.debug_set().entries(self.type_names()).finish()
     }
 }
 
@@ -89,3 +150,32 @@ fn test_extensions() {
     assert_eq!(extensions.get::<bool>(), None);
     assert_eq!(extensions.get(), Some(&MyType(10)));
 }
+
+#[test]
+fn test_get_or_insert_with() {
+    let mut extensions = Extensions::new();
+
+    *extensions.get_or_insert_with(|| 1i32) += 1;
+    *extensions.get_or_insert_with(|| 10i32) += 1;
+    assert_eq!(extensions.get::<i32>(), Some(&3i32));
+    assert_eq!(extensions.len(), 1);
+
+    extensions.clear();
+    assert!(extensions.is_empty());
+}
+
+#[test]
+fn test_type_names() {
+    let mut extensions = Extensions::new();
+    extensions.insert(5i32);
+    extensions.insert(String::new());
+
+    let names: Vec<_> = extensions.type_names().collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"i32"));
+    assert!(names.iter().any(|name| name.ends_with("String")));
+
+    let debug = format!("{:?}", extensions);
+    assert!(debug.contains("i32"));
+    assert!(debug.contains("String"));
+}
@@ -14,6 +14,7 @@ use super::{Message, MessageType};
 use crate::body::BodySize;
 use crate::config::ServiceConfig;
 use crate::error::ParseError;
+use crate::header::HeaderMap;
 use crate::helpers;
 use crate::message::{ConnectionType, Head, ResponseHead};
 use crate::request::Request;
@@ -64,10 +65,11 @@ impl Codec {
         } else {
             Flags::empty()
         };
+        let decoder = decoder::MessageDecoder::new(*config.header_limits());
         Codec {
             config,
             flags,
-            decoder: decoder::MessageDecoder::default(),
+            decoder,
             payload: None,
             version: Version::HTTP_11,
             ctype: ConnectionType::Close,
@@ -109,6 +111,18 @@ impl Codec {
     pub fn config(&self) -> &ServiceConfig {
         &self.config
     }
+
+    /// Encode end of the payload with trailers.
+    ///
+    /// Trailers are dropped unless payload is sent with chunked transfer
+    /// encoding.
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        dst: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.encoder.encode_trailers(trailers, dst)
+    }
 }
 
 impl Decoder for Codec {
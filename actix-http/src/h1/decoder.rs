@@ -12,6 +12,7 @@ use http::{header, HttpTryFrom, Method, StatusCode, Uri, Version};
 use httparse;
 use log::{debug, error, trace};
 
+use crate::config::HeaderLimits;
 use crate::error::ParseError;
 use crate::header::HeaderMap;
 use crate::message::{ConnectionType, ResponseHead};
@@ -21,7 +22,7 @@ const MAX_BUFFER_SIZE: usize = 131_072;
 const MAX_HEADERS: usize = 96;
 
 /// Incoming messagd decoder
-pub(crate) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(crate) struct MessageDecoder<T: MessageType>(HeaderLimits, PhantomData<T>);
 
 #[derive(Debug)]
 /// Incoming request type
@@ -31,9 +32,15 @@ pub(crate) enum PayloadType {
     Stream(PayloadDecoder),
 }
 
+impl<T: MessageType> MessageDecoder<T> {
+    pub(crate) fn new(limits: HeaderLimits) -> Self {
+        MessageDecoder(limits, PhantomData)
+    }
+}
+
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder(HeaderLimits::default(), PhantomData)
     }
 }
 
@@ -42,7 +49,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, &self.0)
     }
 }
 
@@ -59,7 +66,10 @@ pub(crate) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &HeaderLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
@@ -186,32 +196,88 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        limits: &HeaderLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
-        let mut headers: [HeaderIndex; MAX_HEADERS] =
+        let mut headers_buf: [HeaderIndex; MAX_HEADERS] =
             unsafe { MaybeUninit::uninit().assume_init() };
+        let mut headers_vec = Vec::new();
+        let headers = if limits.max_headers > MAX_HEADERS {
+            headers_vec.resize(limits.max_headers, HeaderIndex::default());
+            &mut headers_vec[..]
+        } else {
+            &mut headers_buf[..limits.max_headers]
+        };
 
         let (len, method, uri, ver, h_len) = {
-            let mut parsed: [httparse::Header; MAX_HEADERS] =
+            let mut parsed_buf: [httparse::Header; MAX_HEADERS] =
                 unsafe { MaybeUninit::uninit().assume_init() };
+            let mut parsed_vec = Vec::new();
+            let parsed = if limits.max_headers > MAX_HEADERS {
+                parsed_vec.resize(limits.max_headers, httparse::EMPTY_HEADER);
+                &mut parsed_vec[..]
+            } else {
+                &mut parsed_buf[..limits.max_headers]
+            };
+
+            // partial head is not parsed until its end is received
+            if cfg!(feature = "simd") && !head_complete(src) {
+                if src.len() >= limits.max_head_size {
+                    debug!("Request head is larger than {}", limits.max_head_size);
+                    return Err(ParseError::HeaderFieldsTooLarge);
+                }
+                return Ok(None);
+            }
 
-            let mut req = httparse::Request::new(&mut parsed);
-            match req.parse(src)? {
+            let mut req = httparse::Request::new(parsed);
+            let status = match req.parse(src) {
+                Ok(status) => status,
+                Err(httparse::Error::TooManyHeaders) => {
+                    debug!("Request has more than {} headers", limits.max_headers);
+                    return Err(ParseError::HeaderFieldsTooLarge);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            match status {
                 httparse::Status::Complete(len) => {
+                    if len > limits.max_head_size {
+                        debug!("Request head is larger than {}", limits.max_head_size);
+                        return Err(ParseError::HeaderFieldsTooLarge);
+                    }
+                    for h in req.headers.iter() {
+                        if h.name.len() + h.value.len() > limits.max_header_size {
+                            debug!("Request header {} is too large", h.name);
+                            return Err(ParseError::HeaderFieldsTooLarge);
+                        }
+                    }
+                    let path = req.path.unwrap();
+                    if path.len() > limits.max_uri_length {
+                        debug!("Request uri is longer than {}", limits.max_uri_length);
+                        return Err(ParseError::UriTooLong);
+                    }
+
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
-                    let uri = Uri::try_from(req.path.unwrap())?;
+                    let uri = Uri::try_from(path)?;
                     let version = if req.version.unwrap() == 1 {
                         Version::HTTP_11
                     } else {
                         Version::HTTP_10
                     };
-                    HeaderIndex::record(src, req.headers, &mut headers);
+                    HeaderIndex::record(src, req.headers, headers);
 
                     (len, method, uri, version, req.headers.len())
                 }
-                httparse::Status::Partial => return Ok(None),
+                httparse::Status::Partial => {
+                    if src.len() >= limits.max_head_size {
+                        debug!("Request head is larger than {}", limits.max_head_size);
+                        return Err(ParseError::HeaderFieldsTooLarge);
+                    }
+                    return Ok(None);
+                }
             }
         };
 
@@ -261,7 +327,10 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        _: &HeaderLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -271,6 +340,11 @@ impl MessageType for ResponseHead {
             let mut parsed: [httparse::Header; MAX_HEADERS] =
                 unsafe { MaybeUninit::uninit().assume_init() };
 
+            // partial head is not parsed until its end is received
+            if cfg!(feature = "simd") && !head_complete(src) {
+                return Ok(None);
+            }
+
             let mut res = httparse::Response::new(&mut parsed);
             match res.parse(src)? {
                 httparse::Status::Complete(len) => {
@@ -318,7 +392,7 @@ impl MessageType for ResponseHead {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub(crate) struct HeaderIndex {
     pub(crate) name: (usize, usize),
     pub(crate) value: (usize, usize),
@@ -472,6 +546,36 @@ impl Decoder for PayloadDecoder {
     }
 }
 
+/// Position of the byte in the buffer, scan is vectorized with `simd` feature.
+#[cfg(feature = "simd")]
+#[inline]
+fn find_byte(b: u8, buf: &[u8]) -> Option<usize> {
+    memchr::memchr(b, buf)
+}
+
+/// Position of the byte in the buffer, scan is vectorized with `simd` feature.
+#[cfg(not(feature = "simd"))]
+#[inline]
+fn find_byte(b: u8, buf: &[u8]) -> Option<usize> {
+    buf.iter().position(|c| *c == b)
+}
+
+/// Check if the buffer contains an empty line that ends message head.
+///
+/// Line ends are found with `find_byte()`, so with `simd` feature head
+/// received in several reads is scanned instead of being parsed again.
+fn head_complete(buf: &[u8]) -> bool {
+    let mut pos = 0;
+    while let Some(idx) = find_byte(b'\n', &buf[pos..]) {
+        pos += idx + 1;
+        let rest = &buf[pos..];
+        if rest.starts_with(b"\n") || rest.starts_with(b"\r\n") {
+            return true;
+        }
+    }
+    false
+}
+
 macro_rules! byte (
     ($rdr:ident) => ({
         if $rdr.len() > 0 {
@@ -493,10 +597,9 @@ impl ChunkedState {
     ) -> Poll<Result<ChunkedState, io::Error>> {
         use self::ChunkedState::*;
         match *self {
-            Size => ChunkedState::read_size(body, size),
-            SizeLws => ChunkedState::read_size_lws(body),
-            Extension => ChunkedState::read_extension(body),
-            SizeLf => ChunkedState::read_size_lf(body, size),
+            Size | SizeLws | Extension | SizeLf => {
+                ChunkedState::read_size_line(self.clone(), body, size)
+            }
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
@@ -506,67 +609,102 @@ impl ChunkedState {
         }
     }
 
-    fn read_size(
+    /// Read buffered part of chunk size line.
+    ///
+    /// Bytes of the line are consumed at once, extensions are skipped with
+    /// a scan for the end of the line.
+    fn read_size_line(
+        mut state: ChunkedState,
         rdr: &mut BytesMut,
         size: &mut u64,
     ) -> Poll<Result<ChunkedState, io::Error>> {
-        let radix = 16;
-        match byte!(rdr) {
-            b @ b'0'..=b'9' => {
-                *size *= radix;
-                *size += u64::from(b - b'0');
-            }
-            b @ b'a'..=b'f' => {
-                *size *= radix;
-                *size += u64::from(b + 10 - b'a');
-            }
-            b @ b'A'..=b'F' => {
-                *size *= radix;
-                *size += u64::from(b + 10 - b'A');
+        if rdr.is_empty() {
+            return Poll::Pending;
+        }
+
+        let mut pos = 0;
+        while pos < rdr.len() {
+            if state == ChunkedState::Extension {
+                // no supported extensions
+                match find_byte(b'\r', &rdr[pos..]) {
+                    Some(idx) => {
+                        pos += idx + 1;
+                        state = ChunkedState::SizeLf;
+                        continue;
+                    }
+                    None => {
+                        pos = rdr.len();
+                        break;
+                    }
+                }
             }
-            b'\t' | b' ' => return Poll::Ready(Ok(ChunkedState::SizeLws)),
-            b';' => return Poll::Ready(Ok(ChunkedState::Extension)),
-            b'\r' => return Poll::Ready(Ok(ChunkedState::SizeLf)),
-            _ => {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Invalid chunk size line: Invalid Size",
-                )));
+
+            state = match state.read_size_byte(rdr[pos], size) {
+                Ok(state) => state,
+                Err(e) => return Poll::Ready(Err(e)),
+            };
+            pos += 1;
+            if state == ChunkedState::Body || state == ChunkedState::EndCr {
+                break;
             }
         }
-        Poll::Ready(Ok(ChunkedState::Size))
+        rdr.split_to(pos);
+        Poll::Ready(Ok(state))
     }
 
-    fn read_size_lws(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        trace!("read_size_lws");
-        match byte!(rdr) {
-            // LWS can follow the chunk size, but no more digits can come
-            b'\t' | b' ' => Poll::Ready(Ok(ChunkedState::SizeLws)),
-            b';' => Poll::Ready(Ok(ChunkedState::Extension)),
-            b'\r' => Poll::Ready(Ok(ChunkedState::SizeLf)),
-            _ => Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid chunk size linear white space",
-            ))),
-        }
-    }
-    fn read_extension(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        match byte!(rdr) {
-            b'\r' => Poll::Ready(Ok(ChunkedState::SizeLf)),
-            _ => Poll::Ready(Ok(ChunkedState::Extension)), // no supported extensions
-        }
-    }
-    fn read_size_lf(
-        rdr: &mut BytesMut,
-        size: &mut u64,
-    ) -> Poll<Result<ChunkedState, io::Error>> {
-        match byte!(rdr) {
-            b'\n' if *size > 0 => Poll::Ready(Ok(ChunkedState::Body)),
-            b'\n' if *size == 0 => Poll::Ready(Ok(ChunkedState::EndCr)),
-            _ => Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid chunk size LF",
-            ))),
+    fn read_size_byte(&self, b: u8, size: &mut u64) -> Result<ChunkedState, io::Error> {
+        use self::ChunkedState::*;
+        let radix = 16;
+        match *self {
+            Size => {
+                match b {
+                    b'0'..=b'9' => {
+                        *size *= radix;
+                        *size += u64::from(b - b'0');
+                    }
+                    b'a'..=b'f' => {
+                        *size *= radix;
+                        *size += u64::from(b + 10 - b'a');
+                    }
+                    b'A'..=b'F' => {
+                        *size *= radix;
+                        *size += u64::from(b + 10 - b'A');
+                    }
+                    b'\t' | b' ' => return Ok(SizeLws),
+                    b';' => return Ok(Extension),
+                    b'\r' => return Ok(SizeLf),
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "Invalid chunk size line: Invalid Size",
+                        ));
+                    }
+                }
+                Ok(Size)
+            }
+            SizeLws => match b {
+                // LWS can follow the chunk size, but no more digits can come
+                b'\t' | b' ' => Ok(SizeLws),
+                b';' => Ok(Extension),
+                b'\r' => Ok(SizeLf),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Invalid chunk size linear white space",
+                )),
+            },
+            Extension => match b {
+                b'\r' => Ok(SizeLf),
+                _ => Ok(Extension), // no supported extensions
+            },
+            SizeLf => match b {
+                b'\n' if *size > 0 => Ok(Body),
+                b'\n' if *size == 0 => Ok(EndCr),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Invalid chunk size LF",
+                )),
+            },
+            _ => unreachable!(),
         }
     }
 
@@ -714,6 +852,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_limits() {
+        let limits = HeaderLimits {
+            max_headers: 2,
+            max_header_size: 16,
+            max_head_size: 128,
+            max_uri_length: 8,
+        };
+        let mut reader = MessageDecoder::<Request>::new(limits);
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::HeaderFieldsTooLarge) => (),
+            _ => unreachable!("Parse error expected"),
+        }
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\nname: long-header-value\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::HeaderFieldsTooLarge) => (),
+            _ => unreachable!("Parse error expected"),
+        }
+
+        let mut buf = BytesMut::from("GET /test/long HTTP/1.1\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::UriTooLong) => (),
+            _ => unreachable!("Parse error expected"),
+        }
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n");
+        buf.extend_from_slice(&[b'a'; 128]);
+        match reader.decode(&mut buf) {
+            Err(ParseError::HeaderFieldsTooLarge) => (),
+            _ => unreachable!("Parse error expected"),
+        }
+
+        let limits = HeaderLimits {
+            max_headers: 128,
+            ..HeaderLimits::default()
+        };
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n");
+        for i in 0..100 {
+            buf.extend_from_slice(format!("x-header-{}: {}\r\n", i, i).as_bytes());
+        }
+        buf.extend_from_slice(b"\r\n");
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 100);
+    }
+
     #[test]
     fn test_parse_partial() {
         let mut buf = BytesMut::from("PUT /test HTTP/1");
@@ -728,6 +920,23 @@ mod tests {
         assert_eq!(req.path(), "/test");
     }
 
+    #[test]
+    fn test_head_complete() {
+        assert!(!head_complete(b"GET /test HTTP/1.1\r\n"));
+        assert!(!head_complete(b"GET /test HTTP/1.1\r\nHost: a\r\n\r"));
+        assert!(head_complete(b"GET /test HTTP/1.1\r\nHost: a\r\n\r\n"));
+        assert!(head_complete(b"GET /test HTTP/1.1\nHost: a\n\n"));
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nHost: a");
+        assert!(reader.decode(&mut buf).unwrap().is_none());
+        buf.extend(b"\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_none());
+        buf.extend(b"\r\n");
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().get("host").unwrap(), "a");
+    }
+
     #[test]
     fn test_parse_post() {
         let mut buf = BytesMut::from("POST /test2 HTTP/1.0\r\n\r\n");
@@ -1209,6 +1418,31 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_size_line() {
+        let mut pl = PayloadDecoder::chunked();
+
+        let mut buf = BytesMut::from(&b"1A \t;name=val"[..]);
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+        assert!(buf.is_empty());
+
+        buf.extend(b"ue;ext\r");
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+        buf.extend(b"\nabcdefghijklmnopqrstuvwxyz\r\n0;last\r\n\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"abcdefghijklmnopqrstuvwxyz"));
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+        assert!(buf.is_empty());
+
+        let mut pl = PayloadDecoder::chunked();
+        let mut buf = BytesMut::from(&b"4 x\r\ndata\r\n"[..]);
+        assert!(pl.decode(&mut buf).is_err());
+
+        let mut pl = PayloadDecoder::chunked();
+        let mut buf = BytesMut::from(&b"4\rdata\r\n"[..]);
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from(&"HTTP/1.0 200 Ok\r\n\r\ntest data"[..]);
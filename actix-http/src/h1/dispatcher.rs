@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{fmt, io, io::Write, net};
 
 use actix_codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed, FramedParts};
@@ -11,17 +11,18 @@ use actix_service::Service;
 use bitflags::bitflags;
 use bytes::{BufMut, BytesMut};
 use log::{error, trace};
-use tokio_timer::{delay, Delay};
 
-use crate::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::body::{framing_size, Body, BodySize, MessageBody, ResponseBody};
+use crate::clock;
 use crate::cloneable::CloneableService;
-use crate::config::ServiceConfig;
-use crate::error::{DispatchError, Error};
+use crate::config::{FlushStrategy, ServiceConfig};
+use crate::error::{DispatchError, Error, ResponseError};
 use crate::error::{ParseError, PayloadError};
 use crate::helpers::DataFactory;
 use crate::httpmessage::HttpMessage;
 use crate::request::Request;
 use crate::response::Response;
+use crate::timer::Timer;
 
 use super::codec::Codec;
 use super::payload::{Payload, PayloadSender, PayloadStatus};
@@ -30,6 +31,8 @@ use super::{Message, MessageType};
 const LW_BUFFER_SIZE: usize = 4096;
 const HW_BUFFER_SIZE: usize = 32_768;
 const MAX_PIPELINED_MESSAGES: usize = 16;
+/// Max time data of streaming response is kept in corked write buffer
+const CORK_TIMEOUT: Duration = Duration::from_millis(200);
 
 bitflags! {
     pub struct Flags: u8 {
@@ -95,7 +98,8 @@ where
     messages: VecDeque<DispatcherMessage>,
 
     ka_expire: Instant,
-    ka_timer: Option<Delay>,
+    ka_timer: Option<Timer>,
+    cork_timer: Option<Timer>,
 
     io: T,
     read_buf: BytesMut,
@@ -119,6 +123,7 @@ where
     ExpectCall(X::Future),
     ServiceCall(S::Future),
     SendPayload(ResponseBody<B>),
+    SendTrailers(ResponseBody<B>),
 }
 
 impl<S, B, X> State<S, B, X>
@@ -206,7 +211,7 @@ where
         codec: Codec,
         config: ServiceConfig,
         read_buf: BytesMut,
-        timeout: Option<Delay>,
+        timeout: Option<Timer>,
         service: CloneableService<S>,
         expect: CloneableService<X>,
         upgrade: Option<CloneableService<U>>,
@@ -230,7 +235,7 @@ where
 
         Dispatcher {
             inner: DispatcherState::Normal(InnerDispatcher {
-                write_buf: BytesMut::with_capacity(HW_BUFFER_SIZE),
+                write_buf: BytesMut::with_capacity(config.write_watermarks().1),
                 payload: None,
                 state: State::None,
                 error: None,
@@ -246,6 +251,7 @@ where
                 flags,
                 ka_expire,
                 ka_timer,
+                cork_timer: None,
             }),
         }
     }
@@ -270,7 +276,12 @@ where
         {
             false
         } else if let Some(ref info) = self.payload {
-            info.need_read(cx) == PayloadStatus::Read
+            match info.need_read(cx) {
+                PayloadStatus::Read => true,
+                PayloadStatus::Pause => false,
+                // payload is dropped, drain rest of the data
+                PayloadStatus::Dropped => !info.drain_exceeded(),
+            }
         } else {
             true
         }
@@ -285,6 +296,38 @@ where
         }
     }
 
+    /// Check if write buffer should be flushed.
+    ///
+    /// Corked buffer is kept while response is streamed, until it reaches
+    /// low watermark or its data is kept for `CORK_TIMEOUT`.
+    fn should_flush(&mut self, cx: &mut Context) -> bool {
+        let corked = match self.codec.config().flush_strategy() {
+            FlushStrategy::Eager => false,
+            FlushStrategy::Corked => match self.state {
+                State::SendPayload(_) | State::SendTrailers(_) => {
+                    let low = self.codec.config().write_watermarks().0;
+                    !self.write_buf.is_empty() && self.write_buf.len() < low
+                }
+                _ => false,
+            },
+        };
+        if !corked {
+            self.cork_timer = None;
+            return true;
+        }
+
+        let config = self.codec.config();
+        let timer = self
+            .cork_timer
+            .get_or_insert_with(|| config.timer(clock::now() + CORK_TIMEOUT));
+        if Pin::new(timer).poll(cx).is_ready() {
+            self.cork_timer = None;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Flush stream
     ///
     /// true - got whouldblock
@@ -331,8 +374,9 @@ where
         message: Response<()>,
         body: ResponseBody<B>,
     ) -> Result<State<S, B, X>, DispatchError> {
+        let size = framing_size(&body);
         self.codec
-            .encode(Message::Item((message, body.size())), &mut self.write_buf)
+            .encode(Message::Item((message, size)), &mut self.write_buf)
             .map_err(|err| {
                 if let Some(mut payload) = self.payload.take() {
                     payload.set_error(PayloadError::Incomplete(None));
@@ -341,12 +385,45 @@ where
             })?;
 
         self.flags.set(Flags::KEEPALIVE, self.codec.keepalive());
-        match body.size() {
+        match size {
             BodySize::None | BodySize::Empty => Ok(State::None),
             _ => Ok(State::SendPayload(body)),
         }
     }
 
+    /// Check if payload is dropped and it could not be drained,
+    /// in that case connection could not be reused.
+    ///
+    /// Rest of the payload that is already read from the connection is
+    /// discarded, connection is closed only if unread bytes of the payload
+    /// remain on the wire.
+    fn check_drain(&mut self) -> bool {
+        if !self
+            .payload
+            .as_ref()
+            .map(|pl| pl.drain_exceeded())
+            .unwrap_or(false)
+        {
+            return false;
+        }
+
+        loop {
+            match self.codec.decode(&mut self.read_buf) {
+                Ok(Some(Message::Chunk(Some(_)))) => (),
+                Ok(Some(Message::Chunk(None))) => {
+                    self.payload.take();
+                    return false;
+                }
+                _ => break,
+            }
+        }
+
+        trace!("Request payload is not drained, close connection");
+        self.payload.take();
+        self.flags.insert(Flags::READ_DISCONNECT);
+        true
+    }
+
     fn send_continue(&mut self) {
         self.write_buf
             .extend_from_slice(b"HTTP/1.1 100 Continue\r\n\r\n");
@@ -401,8 +478,9 @@ where
                     }
                 }
                 State::SendPayload(ref mut stream) => {
+                    let high = self.codec.config().write_watermarks().1;
                     loop {
-                        if self.write_buf.len() < HW_BUFFER_SIZE {
+                        if self.write_buf.len() < high {
                             match stream.poll_next(cx) {
                                 Poll::Ready(Some(Ok(item))) => {
                                     self.codec.encode(
@@ -412,11 +490,7 @@ where
                                     continue;
                                 }
                                 Poll::Ready(None) => {
-                                    self.codec.encode(
-                                        Message::Chunk(None),
-                                        &mut self.write_buf,
-                                    )?;
-                                    self.state = State::None;
+                                    self.state = State::SendTrailers(stream.take_body());
                                 }
                                 Poll::Ready(Some(Err(_))) => {
                                     return Err(DispatchError::Unknown)
@@ -430,6 +504,22 @@ where
                     }
                     continue;
                 }
+                State::SendTrailers(ref mut stream) => {
+                    match stream.poll_trailers(cx) {
+                        Poll::Ready(Ok(Some(trailers))) => {
+                            self.codec
+                                .encode_trailers(&trailers, &mut self.write_buf)?;
+                        }
+                        Poll::Ready(Ok(None)) => {
+                            self.codec
+                                .encode(Message::Chunk(None), &mut self.write_buf)?;
+                        }
+                        Poll::Ready(Err(_)) => return Err(DispatchError::Unknown),
+                        Poll::Pending => return Ok(PollResponse::DoNothing),
+                    }
+                    self.state = State::None;
+                    continue;
+                }
             };
 
             // set new state
@@ -502,7 +592,10 @@ where
         cx: &mut Context,
     ) -> Result<bool, DispatchError> {
         // limit a mount of non processed requests
-        if self.messages.len() >= MAX_PIPELINED_MESSAGES || !self.can_read(cx) {
+        if self.messages.len() >= MAX_PIPELINED_MESSAGES
+            || self.check_drain()
+            || !self.can_read(cx)
+        {
             return Ok(false);
         }
 
@@ -545,6 +638,9 @@ where
                         Message::Chunk(Some(chunk)) => {
                             if let Some(ref mut payload) = self.payload {
                                 payload.feed_data(chunk);
+                                if self.check_drain() {
+                                    break;
+                                }
                             } else {
                                 error!(
                                     "Internal server error: unexpected payload chunk"
@@ -583,9 +679,10 @@ where
                         payload.set_error(PayloadError::EncodingCorrupted);
                     }
 
-                    // Malformed requests should be responded with 400
+                    // Malformed requests should be responded with 400,
+                    // requests that exceed limits with 431 or 414
                     self.messages.push_back(DispatcherMessage::Error(
-                        Response::BadRequest().finish().drop_body(),
+                        e.error_response().drop_body(),
                     ));
                     self.flags.insert(Flags::READ_DISCONNECT);
                     self.error = Some(e.into());
@@ -608,7 +705,7 @@ where
             // shutdown timeout
             if self.flags.contains(Flags::SHUTDOWN) {
                 if let Some(interval) = self.codec.config().client_disconnect_timer() {
-                    self.ka_timer = Some(delay(interval));
+                    self.ka_timer = Some(self.codec.config().timer(interval));
                 } else {
                     self.flags.insert(Flags::READ_DISCONNECT);
                     if let Some(mut payload) = self.payload.take() {
@@ -748,9 +845,10 @@ where
                         }
                     };
 
+                    let (low, high) = inner.codec.config().write_watermarks();
                     loop {
-                        if inner.write_buf.remaining_mut() < LW_BUFFER_SIZE {
-                            inner.write_buf.reserve(HW_BUFFER_SIZE);
+                        if inner.write_buf.remaining_mut() < low {
+                            inner.write_buf.reserve(high);
                         }
                         let result = inner.poll_response(cx)?;
                         let drain = result == PollResponse::DrainWriteBuf;
@@ -779,7 +877,7 @@ where
                         // we didnt get WouldBlock from write operation,
                         // so data get written to kernel completely (OSX)
                         // and we have to write again otherwise response can get stuck
-                        if inner.poll_flush(cx)? || !drain {
+                        if !inner.should_flush(cx) || inner.poll_flush(cx)? || !drain {
                             break;
                         }
                     }
@@ -792,7 +890,10 @@ where
                     let is_empty = inner.state.is_empty();
 
                     // read half is closed and we do not processing any responses
-                    if inner.flags.contains(Flags::READ_DISCONNECT) && is_empty {
+                    if is_empty
+                        && (inner.flags.contains(Flags::READ_DISCONNECT)
+                            || inner.check_drain())
+                    {
                         inner.flags.insert(Flags::SHUTDOWN);
                     }
 
@@ -886,7 +987,10 @@ where
 #[cfg(test)]
 mod tests {
     use actix_service::IntoService;
-    use futures::future::{lazy, ok};
+    use bytes::Bytes;
+    use futures::channel::mpsc;
+    use futures::future::{lazy, ok, poll_fn};
+    use futures::stream;
 
     use super::*;
     use crate::error::Error;
@@ -921,4 +1025,95 @@ mod tests {
             ok::<_, ()>(())
         }));
     }
+
+    #[test]
+    fn test_corked_small_watermarks() {
+        let mut sys = actix_rt::System::new("test");
+        let _ = sys.block_on(lazy(|cx| {
+            let buf = TestBuffer::new("GET /test HTTP/1.1\r\n\r\n");
+            let config = ServiceConfig::default()
+                .with_write_watermarks(1, 1)
+                .with_flush_strategy(FlushStrategy::Corked);
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                config,
+                CloneableService::new(
+                    (|_| {
+                        let chunks = (0..3).map(|_| Ok::<_, Error>(Bytes::from("data")));
+                        ok::<_, Error>(Response::Ok().streaming(stream::iter(chunks)))
+                    })
+                    .into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+            );
+            assert!(Pin::new(&mut h1).poll(cx).is_pending());
+
+            if let DispatcherState::Normal(ref inner) = h1.inner {
+                assert!(inner.state.is_empty());
+                assert!(inner.write_buf.is_empty());
+                assert!(inner.io.write_buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+                assert!(inner.io.write_buf.ends_with(b"4\r\ndata\r\n0\r\n\r\n"));
+            }
+            ok::<_, ()>(())
+        }));
+    }
+
+    #[test]
+    fn test_corked_flush_timeout() {
+        let mut sys = actix_rt::System::new("test");
+        sys.block_on(async {
+            let (tx, rx) = mpsc::unbounded::<Result<Bytes, Error>>();
+            tx.unbounded_send(Ok(Bytes::from("data: 1\n\n"))).unwrap();
+            let mut rx = Some(rx);
+
+            let buf = TestBuffer::new("GET /events HTTP/1.1\r\n\r\n");
+            let config =
+                ServiceConfig::default().with_flush_strategy(FlushStrategy::Corked);
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                config,
+                CloneableService::new(
+                    (move |_| {
+                        ok::<_, Error>(Response::Ok().streaming(rx.take().unwrap()))
+                    })
+                    .into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+            );
+
+            // response head and first event are kept in write buffer
+            let start = Instant::now();
+            assert!(poll_fn(|cx| Poll::Ready(Pin::new(&mut h1).poll(cx)))
+                .await
+                .is_pending());
+            if let DispatcherState::Normal(ref inner) = h1.inner {
+                assert!(inner.io.write_buf.is_empty());
+                assert!(!inner.write_buf.is_empty());
+            }
+
+            // and flushed once they are kept for cork timeout
+            poll_fn(|cx| {
+                assert!(Pin::new(&mut h1).poll(cx).is_pending());
+                match h1.inner {
+                    DispatcherState::Normal(ref inner) if inner.write_buf.is_empty() => {
+                        Poll::Ready(())
+                    }
+                    _ => Poll::Pending,
+                }
+            })
+            .await;
+            assert!(Instant::now() - start >= CORK_TIMEOUT);
+
+            if let DispatcherState::Normal(ref inner) = h1.inner {
+                assert!(inner.io.write_buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+                assert!(inner.io.write_buf.ends_with(b"9\r\ndata: 1\n\n\r\n"));
+            }
+            drop(tx);
+        });
+    }
 }
@@ -8,8 +8,8 @@ use std::{cmp, fmt, io, mem};
 
 use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::body::BodySize;
-use crate::config::ServiceConfig;
+use crate::body::{trailer_allowed, BodySize};
+use crate::config::{HeaderCase, ServiceConfig};
 use crate::header::{map, ContentEncoding};
 use crate::helpers;
 use crate::http::header::{
@@ -26,6 +26,8 @@ const AVERAGE_HEADER_SIZE: usize = 30;
 pub(crate) struct MessageEncoder<T: MessageType> {
     pub length: BodySize,
     pub te: TransferEncoding,
+    // case of header names of the last message, used for trailers
+    case: HeaderCase,
     _t: PhantomData<T>,
 }
 
@@ -34,6 +36,7 @@ impl<T: MessageType> Default for MessageEncoder<T> {
         MessageEncoder {
             length: BodySize::None,
             te: TransferEncoding::empty(),
+            case: HeaderCase::Lower,
             _t: PhantomData,
         }
     }
@@ -64,7 +67,12 @@ pub(crate) trait MessageType: Sized {
     ) -> io::Result<()> {
         let chunked = self.chunked();
         let mut skip_len = length != BodySize::Stream;
-        let camel_case = self.camel_case();
+        let case = if self.camel_case() {
+            HeaderCase::Camel
+        } else {
+            config.header_case()
+        };
+        let camel_case = case == HeaderCase::Camel;
 
         // Content length
         if let Some(status) = self.status() {
@@ -155,7 +163,15 @@ pub(crate) trait MessageType: Sized {
                 }
                 _ => (),
             }
-            let k = key.as_str().as_bytes();
+            let k = if case == HeaderCase::Preserve {
+                extra_headers
+                    .original_case(key)
+                    .or_else(|| self.headers().original_case(key))
+                    .unwrap_or_else(|| key.as_str())
+            } else {
+                key.as_str()
+            }
+            .as_bytes();
             match value {
                 map::Value::One(ref val) => {
                     let v = val.as_ref();
@@ -288,18 +304,23 @@ impl MessageType for RequestHeadType {
     fn encode_status(&mut self, dst: &mut BytesMut) -> io::Result<()> {
         let head = self.as_ref();
         dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
-        write!(
-            Writer(dst),
-            "{} {} {}",
-            head.method,
-            head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
-            match head.version {
-                Version::HTTP_09 => "HTTP/0.9",
-                Version::HTTP_10 => "HTTP/1.0",
-                Version::HTTP_11 => "HTTP/1.1",
-                Version::HTTP_2 => "HTTP/2.0",
-            }
-        )
+        let version = match head.version {
+            Version::HTTP_09 => "HTTP/0.9",
+            Version::HTTP_10 => "HTTP/1.0",
+            Version::HTTP_11 => "HTTP/1.1",
+            Version::HTTP_2 => "HTTP/2.0",
+        };
+        if head.absolute_form() {
+            write!(Writer(dst), "{} {} {}", head.method, head.uri, version)
+        } else {
+            write!(
+                Writer(dst),
+                "{} {} {}",
+                head.method,
+                head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
+                version
+            )
+        }
         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
     }
 }
@@ -315,6 +336,14 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.te.encode_trailers(trailers, self.case, buf)
+    }
+
     pub fn encode(
         &mut self,
         dst: &mut BytesMut,
@@ -342,8 +371,13 @@ impl<T: MessageType> MessageEncoder<T> {
                 BodySize::None => TransferEncoding::empty(),
             };
         } else {
-            self.te = TransferEncoding::empty();
+            self.te = TransferEncoding::discard();
         }
+        self.case = if message.camel_case() {
+            HeaderCase::Camel
+        } else {
+            config.header_case()
+        };
 
         message.encode_status(dst)?;
         message.encode_headers(dst, version, length, ctype, config)
@@ -364,6 +398,8 @@ enum TransferEncodingKind {
     ///
     /// Enforces that the body is not longer than the Content-Length header.
     Length(u64),
+    /// An Encoder for response to HEAD request, payload is not sent.
+    Discard,
     /// An Encoder for when Content-Length is not known.
     ///
     /// Application decides when to stop writing.
@@ -378,6 +414,13 @@ impl TransferEncoding {
         }
     }
 
+    #[inline]
+    pub fn discard() -> TransferEncoding {
+        TransferEncoding {
+            kind: TransferEncodingKind::Discard,
+        }
+    }
+
     #[inline]
     pub fn eof() -> TransferEncoding {
         TransferEncoding {
@@ -427,20 +470,18 @@ impl TransferEncoding {
                 Ok(*eof)
             }
             TransferEncodingKind::Length(ref mut remaining) => {
-                if *remaining > 0 {
-                    if msg.is_empty() {
-                        return Ok(*remaining == 0);
-                    }
-                    let len = cmp::min(*remaining, msg.len() as u64);
-
-                    buf.extend_from_slice(&msg[..len as usize]);
-
-                    *remaining -= len as u64;
-                    Ok(*remaining == 0)
-                } else {
-                    Ok(true)
+                // longer payload would corrupt framing of the next message
+                if msg.len() as u64 > *remaining {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Payload is longer than Content-Length",
+                    ));
                 }
+                buf.extend_from_slice(msg);
+                *remaining -= msg.len() as u64;
+                Ok(*remaining == 0)
             }
+            TransferEncodingKind::Discard => Ok(true),
         }
     }
 
@@ -448,7 +489,7 @@ impl TransferEncoding {
     #[inline]
     pub fn encode_eof(&mut self, buf: &mut BytesMut) -> io::Result<()> {
         match self.kind {
-            TransferEncodingKind::Eof => Ok(()),
+            TransferEncodingKind::Eof | TransferEncodingKind::Discard => Ok(()),
             TransferEncodingKind::Length(rem) => {
                 if rem != 0 {
                     Err(io::Error::new(io::ErrorKind::UnexpectedEof, ""))
@@ -465,6 +506,48 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof with trailers, trailers are dropped unless encoding is
+    /// chunked. Fields that are not allowed in trailers are skipped.
+    #[inline]
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        case: HeaderCase,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) => {
+                if !*eof {
+                    *eof = true;
+                    buf.extend_from_slice(b"0\r\n");
+                    for (key, value) in trailers.iter() {
+                        if !trailer_allowed(key) {
+                            continue;
+                        }
+                        let k = match case {
+                            HeaderCase::Preserve => trailers.original_case(key),
+                            _ => None,
+                        }
+                        .unwrap_or_else(|| key.as_str())
+                        .as_bytes();
+
+                        let pos = buf.len();
+                        buf.extend_from_slice(k);
+                        if case == HeaderCase::Camel {
+                            write_camel_case(k, &mut buf[pos..]);
+                        }
+                        buf.extend_from_slice(b": ");
+                        buf.extend_from_slice(value.as_bytes());
+                        buf.extend_from_slice(b"\r\n");
+                    }
+                    buf.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 struct Writer<'a>(pub &'a mut BytesMut);
@@ -513,7 +596,7 @@ mod tests {
     //use std::rc::Rc;
 
     use super::*;
-    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
     use http::header::AUTHORIZATION;
 
     #[test]
@@ -530,6 +613,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+
+        trailers.insert(CONTENT_LENGTH, HeaderValue::from_static("4"));
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_trailers(&trailers, HeaderCase::Lower, &mut bytes)
+            .unwrap();
+        enc.encode_trailers(&trailers, HeaderCase::Lower, &mut bytes)
+            .unwrap();
+        assert_eq!(
+            bytes.take().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\ngrpc-status: 0\r\n\r\n")
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        enc.encode_trailers(&trailers, HeaderCase::Camel, &mut bytes)
+            .unwrap();
+        assert_eq!(
+            bytes.take().freeze(),
+            Bytes::from_static(b"0\r\nGrpc-Status: 0\r\n\r\n")
+        );
+
+        let mut trailers = HeaderMap::new();
+        trailers
+            .insert_cased("GRPC-status", HeaderValue::from_static("0"))
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        enc.encode_trailers(&trailers, HeaderCase::Preserve, &mut bytes)
+            .unwrap();
+        assert_eq!(
+            bytes.take().freeze(),
+            Bytes::from_static(b"0\r\nGRPC-status: 0\r\n\r\n")
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::length(4);
+        assert!(enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_trailers(&trailers, HeaderCase::Lower, &mut bytes)
+            .unwrap();
+        assert_eq!(bytes.take().freeze(), Bytes::from_static(b"test"));
+    }
+
+    #[test]
+    fn test_length_te() {
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::length(6);
+        assert!(!enc.encode(b"test", &mut bytes).unwrap());
+        assert!(enc.encode(b"test", &mut bytes).is_err());
+
+        let mut enc = TransferEncoding::length(6);
+        assert!(!enc.encode(b"test", &mut bytes).unwrap());
+        assert!(enc.encode_eof(&mut bytes).is_err());
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::discard();
+        assert!(enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_eof(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+    }
+
     #[test]
     fn test_camel_case() {
         let mut bytes = BytesMut::with_capacity(2048);
@@ -633,4 +786,69 @@ mod tests {
         assert!(data.contains("authorization: another authorization\r\n"));
         assert!(data.contains("date: date\r\n"));
     }
+
+    #[test]
+    fn test_absolute_form() {
+        let mut bytes = BytesMut::with_capacity(2048);
+        let mut head = RequestHead::default();
+        head.uri = "http://rust-lang.org:8080/path?q=1".parse().unwrap();
+
+        let mut req = RequestHeadType::Owned(head);
+        req.encode_status(&mut bytes).unwrap();
+        assert_eq!(&bytes.take()[..], &b"GET /path?q=1 HTTP/1.1"[..]);
+
+        let mut head = req.into_owned();
+        head.set_absolute_form();
+        let mut req = RequestHeadType::Owned(head);
+        req.encode_status(&mut bytes).unwrap();
+        assert_eq!(
+            &bytes.take()[..],
+            &b"GET http://rust-lang.org:8080/path?q=1 HTTP/1.1"[..]
+        );
+    }
+
+    #[test]
+    fn test_preserve_case() {
+        let mut bytes = BytesMut::with_capacity(2048);
+        let mut res = Response::Ok().finish().drop_body();
+        res.headers_mut()
+            .insert_cased("X-Legacy-ID", HeaderValue::from_static("1"))
+            .unwrap();
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("plain/text"));
+
+        let config = ServiceConfig::default().with_header_case(HeaderCase::Preserve);
+        let _ = res.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Empty,
+            ConnectionType::KeepAlive,
+            &config,
+        );
+        let data = String::from_utf8(Vec::from(bytes.take().freeze().as_ref())).unwrap();
+        assert!(data.contains("X-Legacy-ID: 1\r\n"));
+        assert!(data.contains("content-type: plain/text\r\n"));
+
+        let config = ServiceConfig::default().with_header_case(HeaderCase::Camel);
+        let _ = res.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Empty,
+            ConnectionType::KeepAlive,
+            &config,
+        );
+        let data = String::from_utf8(Vec::from(bytes.take().freeze().as_ref())).unwrap();
+        assert!(data.contains("X-Legacy-Id: 1\r\n"));
+        assert!(data.contains("Content-Type: plain/text\r\n"));
+
+        res.headers_mut().insert(
+            HeaderName::from_static("x-legacy-id"),
+            HeaderValue::from_static("2"),
+        );
+        assert_eq!(
+            res.headers()
+                .original_case(&HeaderName::from_static("x-legacy-id")),
+            None
+        );
+    }
 }
@@ -1,5 +1,5 @@
 //! Payload stream
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
@@ -11,6 +11,7 @@ use bytes::Bytes;
 use futures::Stream;
 
 use crate::error::PayloadError;
+use crate::payload::PayloadDrain;
 
 /// max buffer size 32k
 pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
@@ -32,6 +33,7 @@ pub enum PayloadStatus {
 #[derive(Debug)]
 pub struct Payload {
     inner: Rc<RefCell<Inner>>,
+    drain: Rc<Cell<PayloadDrain>>,
 }
 
 impl Payload {
@@ -45,12 +47,18 @@ impl Payload {
     /// * `Payload` - *Receiver* side of the stream
     pub fn create(eof: bool) -> (PayloadSender, Payload) {
         let shared = Rc::new(RefCell::new(Inner::new(eof)));
+        let drain = Rc::new(Cell::new(PayloadDrain::default()));
 
         (
             PayloadSender {
                 inner: Rc::downgrade(&shared),
+                drain: drain.clone(),
+                drained: 0,
+            },
+            Payload {
+                inner: shared,
+                drain,
             },
-            Payload { inner: shared },
         )
     }
 
@@ -59,6 +67,7 @@ impl Payload {
     pub fn empty() -> Payload {
         Payload {
             inner: Rc::new(RefCell::new(Inner::new(true))),
+            drain: Rc::new(Cell::new(PayloadDrain::default())),
         }
     }
 
@@ -74,6 +83,12 @@ impl Payload {
         self.inner.borrow().len() == 0
     }
 
+    /// Set policy for unread data once payload is dropped
+    #[inline]
+    pub fn set_drain(&self, drain: PayloadDrain) {
+        self.drain.set(drain);
+    }
+
     /// Put unused data back to payload
     #[inline]
     pub fn unread_data(&mut self, data: Bytes) {
@@ -103,6 +118,8 @@ impl Stream for Payload {
 /// Sender part of the payload stream
 pub struct PayloadSender {
     inner: Weak<RefCell<Inner>>,
+    drain: Rc<Cell<PayloadDrain>>,
+    drained: usize,
 }
 
 impl PayloadSender {
@@ -124,6 +141,21 @@ impl PayloadSender {
     pub fn feed_data(&mut self, data: Bytes) {
         if let Some(shared) = self.inner.upgrade() {
             shared.borrow_mut().feed_data(data)
+        } else {
+            self.drained += data.len();
+        }
+    }
+
+    /// Check if payload is dropped and rest of the data could not be
+    /// drained according to the drain policy.
+    #[inline]
+    pub fn drain_exceeded(&self) -> bool {
+        if self.inner.upgrade().is_some() {
+            return false;
+        }
+        match self.drain.get() {
+            PayloadDrain::Close => true,
+            PayloadDrain::Limit(limit) => self.drained > limit,
         }
     }
 
@@ -247,4 +279,22 @@ mod tests {
             ready(())
         });
     }
+
+    #[test]
+    fn test_drain() {
+        let (mut sender, payload) = Payload::create(false);
+        payload.set_drain(PayloadDrain::Limit(8));
+        sender.feed_data(Bytes::from("data"));
+        assert!(!sender.drain_exceeded());
+
+        drop(payload);
+        sender.feed_data(Bytes::from("12345678"));
+        assert!(!sender.drain_exceeded());
+        sender.feed_data(Bytes::from("9"));
+        assert!(sender.drain_exceeded());
+
+        let (sender, payload) = Payload::create(false);
+        drop(payload);
+        assert!(sender.drain_exceeded());
+    }
 }
@@ -1,8 +1,10 @@
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
 use std::time::Instant;
 use std::{fmt, mem, net};
 
@@ -19,18 +21,20 @@ use http::header::{
 };
 use http::HttpTryFrom;
 use log::{debug, error, trace};
-use tokio_timer::Delay;
 
-use crate::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::body::{
+    framing_size, trailer_allowed, Body, BodySize, MessageBody, ResponseBody,
+};
 use crate::cloneable::CloneableService;
-use crate::config::ServiceConfig;
+use crate::config::{HeaderLimits, ServiceConfig};
 use crate::error::{DispatchError, Error, ParseError, PayloadError, ResponseError};
 use crate::helpers::DataFactory;
 use crate::httpmessage::HttpMessage;
-use crate::message::ResponseHead;
+use crate::message::{ConnectionType, ResponseHead};
 use crate::payload::Payload;
 use crate::request::Request;
 use crate::response::Response;
+use crate::timer::Timer;
 
 const CHUNK_SIZE: usize = 16_384;
 
@@ -43,10 +47,30 @@ pub struct Dispatcher<T: IoStream, S: Service<Request = Request>, B: MessageBody
     config: ServiceConfig,
     peer_addr: Option<net::SocketAddr>,
     ka_expire: Instant,
-    ka_timer: Option<Delay>,
+    ka_timer: Option<Timer>,
+    goaway: Rc<GoAway>,
+    goaway_sent: bool,
     _t: PhantomData<B>,
 }
 
+/// Graceful shutdown of the connection, it is requested by response with
+/// `Connection: close`.
+#[derive(Default)]
+struct GoAway {
+    requested: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl GoAway {
+    fn request(&self) {
+        if !self.requested.replace(true) {
+            if let Some(waker) = self.waker.borrow_mut().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
 impl<T, S, B> Dispatcher<T, S, B>
 where
     T: IoStream,
@@ -61,7 +85,7 @@ where
         connection: Connection<T, Bytes>,
         on_connect: Option<Box<dyn DataFactory>>,
         config: ServiceConfig,
-        timeout: Option<Delay>,
+        timeout: Option<Timer>,
         peer_addr: Option<net::SocketAddr>,
     ) -> Self {
         // let keepalive = config.keep_alive_enabled();
@@ -88,6 +112,8 @@ where
             on_connect,
             ka_expire,
             ka_timer,
+            goaway: Rc::new(GoAway::default()),
+            goaway_sent: false,
             _t: PhantomData,
         }
     }
@@ -108,6 +134,12 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        // no new streams are accepted, in-flight streams are completed
+        if !this.goaway_sent && this.goaway.requested.get() {
+            this.connection.graceful_shutdown();
+            this.goaway_sent = true;
+        }
+
         loop {
             match Pin::new(&mut this.connection).poll_accept(cx) {
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
@@ -121,6 +153,18 @@ where
                     }
 
                     let (parts, body) = req.into_parts();
+                    if let Some(status) =
+                        exceeds_limits(&parts, this.config.header_limits())
+                    {
+                        let mut res = res;
+                        let mut head = http::Response::new(());
+                        *head.status_mut() = status;
+                        if let Err(e) = res.send_response(head, true) {
+                            trace!("Error sending h2 response: {:?}", e);
+                        }
+                        continue;
+                    }
+
                     let mut req = Request::with_payload(Payload::<
                         crate::payload::PayloadStream,
                     >::H2(
@@ -150,21 +194,68 @@ where
                             Some(res),
                         ),
                         config: this.config.clone(),
+                        goaway: this.goaway.clone(),
                         buffer: None,
+                        length: None,
+                        eof: false,
                         _t: PhantomData,
                     });
                 }
-                Poll::Pending => return Poll::Pending,
+                Poll::Pending => {
+                    if !this.goaway_sent {
+                        let mut waker = this.goaway.waker.borrow_mut();
+                        if !waker.as_ref().map_or(false, |w| w.will_wake(cx.waker())) {
+                            *waker = Some(cx.waker().clone());
+                        }
+                    }
+                    return Poll::Pending;
+                }
             }
         }
     }
 }
 
+/// Status of response for request that exceeds header limits.
+fn exceeds_limits(
+    parts: &http::request::Parts,
+    limits: &HeaderLimits,
+) -> Option<http::StatusCode> {
+    let uri_len = parts.uri.path_and_query().map_or(0, |p| p.as_str().len());
+    if uri_len > limits.max_uri_length {
+        debug!("Request uri is longer than {}", limits.max_uri_length);
+        return Some(http::StatusCode::URI_TOO_LONG);
+    }
+    if parts.headers.len() > limits.max_headers {
+        debug!("Request has more than {} headers", limits.max_headers);
+        return Some(http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    let mut size = uri_len;
+    for (name, value) in parts.headers.iter() {
+        let len = name.as_str().len() + value.len();
+        if len > limits.max_header_size {
+            debug!("Request header is larger than {}", limits.max_header_size);
+            return Some(http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+        }
+        size += len;
+    }
+    if size > limits.max_head_size {
+        debug!("Request head is larger than {}", limits.max_head_size);
+        return Some(http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+    None
+}
+
 #[pin_project::pin_project]
 struct ServiceResponse<F, I, E, B> {
     state: ServiceResponseState<F, B>,
     config: ServiceConfig,
+    goaway: Rc<GoAway>,
     buffer: Option<Bytes>,
+    // remaining length of sized payload
+    length: Option<u64>,
+    // payload is sent, trailers are polled
+    eof: bool,
     _t: PhantomData<(I, E)>,
 }
 
@@ -258,9 +349,12 @@ where
                 match unsafe { Pin::new_unchecked(call) }.poll(cx) {
                     Poll::Ready(Ok(res)) => {
                         let (res, body) = res.into().replace_body(());
+                        if res.head().connection_type() == ConnectionType::Close {
+                            this.goaway.request();
+                        }
 
                         let mut send = send.take().unwrap();
-                        let mut size = body.size();
+                        let mut size = framing_size(&body);
                         let h2_res =
                             self.as_mut().prepare_response(res.head(), &mut size);
                         this = self.as_mut().project();
@@ -276,6 +370,7 @@ where
                         if size.is_eof() {
                             Poll::Ready(())
                         } else {
+                            *this.length = size.hint().1;
                             *this.state =
                                 ServiceResponseState::SendPayload(stream, body);
                             self.poll(cx)
@@ -287,7 +382,7 @@ where
                         let (res, body) = res.replace_body(());
 
                         let mut send = send.take().unwrap();
-                        let mut size = body.size();
+                        let mut size = framing_size(&body);
                         let h2_res =
                             self.as_mut().prepare_response(res.head(), &mut size);
                         this = self.as_mut().project();
@@ -303,6 +398,7 @@ where
                         if size.is_eof() {
                             Poll::Ready(())
                         } else {
+                            *this.length = size.hint().1;
                             *this.state = ServiceResponseState::SendPayload(
                                 stream,
                                 body.into_body(),
@@ -337,16 +433,53 @@ where
                                 return Poll::Ready(());
                             }
                         }
+                    } else if *this.eof {
+                        let res = match body.poll_trailers(cx) {
+                            Poll::Pending => return Poll::Pending,
+                            Poll::Ready(Ok(Some(trailers))) => {
+                                let mut map =
+                                    http::HeaderMap::with_capacity(trailers.len());
+                                for (key, value) in trailers.iter() {
+                                    if trailer_allowed(key) {
+                                        map.append(key.clone(), value.clone());
+                                    }
+                                }
+                                stream.send_trailers(map)
+                            }
+                            Poll::Ready(Ok(None)) => {
+                                stream.send_data(Bytes::new(), true)
+                            }
+                            Poll::Ready(Err(e)) => {
+                                error!("Response payload trailers error: {:?}", e);
+                                return Poll::Ready(());
+                            }
+                        };
+                        if let Err(e) = res {
+                            warn!("{:?}", e);
+                        }
+                        return Poll::Ready(());
                     } else {
                         match body.poll_next(cx) {
                             Poll::Pending => return Poll::Pending,
                             Poll::Ready(None) => {
-                                if let Err(e) = stream.send_data(Bytes::new(), true) {
-                                    warn!("{:?}", e);
+                                if this.length.map_or(false, |len| len != 0) {
+                                    error!("Response payload is shorter than declared");
+                                    stream.send_reset(h2::Reason::INTERNAL_ERROR);
+                                    return Poll::Ready(());
                                 }
-                                return Poll::Ready(());
+                                *this.eof = true;
                             }
                             Poll::Ready(Some(Ok(chunk))) => {
+                                if let Some(ref mut len) = this.length {
+                                    if chunk.len() as u64 > *len {
+                                        error!(
+                                            "Response payload is longer than declared"
+                                        );
+                                        stream.send_reset(h2::Reason::INTERNAL_ERROR);
+                                        return Poll::Ready(());
+                                    }
+                                    *len -= chunk.len() as u64;
+                                }
                                 stream.reserve_capacity(std::cmp::min(
                                     chunk.len(),
                                     CHUNK_SIZE,
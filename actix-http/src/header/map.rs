@@ -1,17 +1,42 @@
+use std::cell::{Ref, RefCell, RefMut};
+
 use either::Either;
-use hashbrown::hash_map::{self, Entry};
+use hashbrown::hash_map::{self, DefaultHashBuilder};
 use hashbrown::HashMap;
-use http::header::{HeaderName, HeaderValue};
-use http::HttpTryFrom;
+use http::header::{HeaderName, HeaderValue, InvalidHeaderName};
+use http::{Error as HttpError, HttpTryFrom};
+
+use crate::error::ParseError;
+use crate::extensions::Extensions;
+use crate::header::{Header, IntoHeaderValue};
+use crate::httpmessage::HttpMessage;
+use crate::payload::Payload;
 
 /// A set of HTTP headers
 ///
-/// `HeaderMap` is an multimap of [`HeaderName`] to values.
+/// `HeaderMap` is an multimap of [`HeaderName`] to values. `insert()`
+/// replaces all values of the header, `append()` adds one more value.
+///
+/// ```rust
+/// use actix_http::http::{header, HeaderMap, HeaderValue};
+///
+/// let mut map = HeaderMap::new();
+/// map.typed_insert(header::ContentType::json()).unwrap();
+/// map.append(header::VARY, HeaderValue::from_static("origin"));
+/// map.append(header::VARY, HeaderValue::from_static("accept"));
+///
+/// assert_eq!(
+///     map.typed_get::<header::ContentType>(),
+///     Some(header::ContentType::json())
+/// );
+/// assert_eq!(map.get_all(header::VARY).count(), 2);
+/// ```
 ///
 /// [`HeaderName`]: struct.HeaderName.html
 #[derive(Debug, Clone)]
 pub struct HeaderMap {
     pub(crate) inner: HashMap<HeaderName, Value>,
+    cased: Option<HashMap<HeaderName, Box<str>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +60,13 @@ impl Value {
         }
     }
 
+    fn as_slice(&self) -> &[HeaderValue] {
+        match self {
+            Value::One(ref val) => std::slice::from_ref(val),
+            Value::Multi(ref vec) => vec,
+        }
+    }
+
     fn append(&mut self, val: HeaderValue) {
         match self {
             Value::One(_) => {
@@ -57,6 +89,7 @@ impl HeaderMap {
     pub fn new() -> Self {
         HeaderMap {
             inner: HashMap::new(),
+            cased: None,
         }
     }
 
@@ -71,6 +104,7 @@ impl HeaderMap {
     pub fn with_capacity(capacity: usize) -> HeaderMap {
         HeaderMap {
             inner: HashMap::with_capacity(capacity),
+            cased: None,
         }
     }
 
@@ -91,6 +125,7 @@ impl HeaderMap {
     /// for reuse.
     pub fn clear(&mut self) {
         self.inner.clear();
+        self.cased = None;
     }
 
     /// Returns the number of headers the map can hold without reallocating.
@@ -139,7 +174,7 @@ impl HeaderMap {
     ///
     /// The returned view does not incur any allocations and allows iterating
     /// the values associated with the key.  See [`GetAll`] for more details.
-    /// Returns `None` if there are no values associated with the key.
+    /// Iterator is empty if there are no values associated with the key.
     ///
     /// [`GetAll`]: struct.GetAll.html
     pub fn get_all<N: AsName>(&self, name: N) -> GetAll {
@@ -190,6 +225,15 @@ impl HeaderMap {
         Iter::new(self.inner.iter())
     }
 
+    /// An iterator visiting all keys with all of their associated values.
+    ///
+    /// The iteration order is arbitrary, but consistent across platforms for
+    /// the same crate version. Each key is yielded once, values are in order
+    /// they have been added.
+    pub fn iter_grouped(&self) -> IterGrouped {
+        IterGrouped(self.inner.iter())
+    }
+
     /// An iterator visiting all keys.
     ///
     /// The iteration order is arbitrary, but consistent across platforms for
@@ -201,26 +245,48 @@ impl HeaderMap {
 
     /// Inserts a key-value pair into the map.
     ///
-    /// If the map did not previously have this key present, then `None` is
-    /// returned.
-    ///
     /// If the map did have this key present, the new value is associated with
-    /// the key and all previous values are removed. **Note** that only a single
-    /// one of the previous values is returned. If there are multiple values
-    /// that have been previously associated with the key, then the first one is
-    /// returned. See `insert_mult` on `OccupiedEntry` for an API that returns
-    /// all values.
+    /// the key and all previous values are removed. Use `append` to add
+    /// value to the existing ones.
     ///
     /// The key is not updated, though; this matters for types that can be `==`
     /// without being identical.
     pub fn insert(&mut self, key: HeaderName, val: HeaderValue) {
+        if let Some(ref mut cased) = self.cased {
+            cased.remove(&key);
+        }
         let _ = self.inner.insert(key, Value::One(val));
     }
 
-    /// Inserts a key-value pair into the map.
+    /// Inserts a key-value pair into the map and remembers original case of
+    /// the name.
     ///
-    /// If the map did not previously have this key present, then `false` is
-    /// returned.
+    /// Header names are case-insensitive and are stored in lower case,
+    /// HTTP/1 encoder writes the name as inserted if it is configured with
+    /// `HeaderCase::Preserve`.
+    pub fn insert_cased(
+        &mut self,
+        name: &str,
+        val: HeaderValue,
+    ) -> Result<(), InvalidHeaderName> {
+        let key = HeaderName::from_bytes(name.as_bytes())?;
+        self.cased
+            .get_or_insert_with(HashMap::new)
+            .insert(key.clone(), name.into());
+        let _ = self.inner.insert(key, Value::One(val));
+        Ok(())
+    }
+
+    /// Returns original case of the name, if header is inserted with
+    /// `insert_cased()`.
+    pub fn original_case(&self, key: &HeaderName) -> Option<&str> {
+        self.cased
+            .as_ref()
+            .and_then(|cased| cased.get(key))
+            .map(|name| name.as_ref())
+    }
+
+    /// Appends a key-value pair to the map.
     ///
     /// If the map did have this key present, the new value is pushed to the end
     /// of the list of values currently associated with the key. The key is not
@@ -228,22 +294,70 @@ impl HeaderMap {
     /// identical.
     pub fn append(&mut self, key: HeaderName, value: HeaderValue) {
         match self.inner.entry(key) {
-            Entry::Occupied(mut entry) => entry.get_mut().append(value),
-            Entry::Vacant(entry) => {
+            hash_map::Entry::Occupied(mut entry) => entry.get_mut().append(value),
+            hash_map::Entry::Vacant(entry) => {
                 entry.insert(Value::One(value));
             }
         }
     }
 
+    /// Gets the given key's entry in the map for in-place manipulation.
+    pub fn entry(&mut self, key: HeaderName) -> Entry {
+        let cased = &mut self.cased;
+        match self.inner.entry(key) {
+            hash_map::Entry::Occupied(entry) => {
+                Entry::Occupied(OccupiedEntry(entry, cased))
+            }
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry(entry, cased)),
+        }
+    }
+
+    /// Returns typed header.
+    ///
+    /// Returns `None` if header is missing or could not be parsed, use
+    /// `typed_try_get` to get parse error.
+    pub fn typed_get<H: Header>(&self) -> Option<H> {
+        self.typed_try_get().unwrap_or(None)
+    }
+
+    /// Parses typed header, returns `Ok(None)` if header is missing.
+    pub fn typed_try_get<H: Header>(&self) -> Result<Option<H>, ParseError> {
+        if self.inner.contains_key(&H::name()) {
+            H::parse(&Headers::new(self)).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Inserts typed header, all previous values of the header are removed.
+    pub fn typed_insert<H: Header>(&mut self, header: H) -> Result<(), HttpError> {
+        let value = IntoHeaderValue::try_into(header).map_err(Into::into)?;
+        self.insert(H::name(), value);
+        Ok(())
+    }
+
+    /// Appends typed header to existing values of the header.
+    pub fn typed_append<H: Header>(&mut self, header: H) -> Result<(), HttpError> {
+        let value = IntoHeaderValue::try_into(header).map_err(Into::into)?;
+        self.append(H::name(), value);
+        Ok(())
+    }
+
     /// Removes all headers for a particular header name from the map.
     pub fn remove<N: AsName>(&mut self, key: N) {
         match key.as_name() {
             Either::Left(name) => {
                 let _ = self.inner.remove(name);
+                if let Some(ref mut cased) = self.cased {
+                    cased.remove(name);
+                }
             }
             Either::Right(s) => {
                 if let Ok(name) = HeaderName::try_from(s) {
                     let _ = self.inner.remove(&name);
+                    if let Some(ref mut cased) = self.cased {
+                        cased.remove(&name);
+                    }
                 }
             }
         }
@@ -285,6 +399,7 @@ impl<'a> AsName for &'a String {
     }
 }
 
+#[derive(Clone)]
 pub struct GetAll<'a> {
     idx: usize,
     item: Option<&'a Value>,
@@ -316,8 +431,19 @@ impl<'a> Iterator for GetAll<'a> {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match self.item {
+            Some(Value::One(_)) => 1,
+            Some(Value::Multi(vec)) => vec.len() - self.idx,
+            None => 0,
+        };
+        (len, Some(len))
+    }
 }
 
+impl<'a> ExactSizeIterator for GetAll<'a> {}
+
 pub struct Keys<'a>(hash_map::Keys<'a, HeaderName, Value>);
 
 impl<'a> Iterator for Keys<'a> {
@@ -382,3 +508,251 @@ impl<'a> Iterator for Iter<'a> {
         }
     }
 }
+
+/// Iterator over keys with all of their values, see `HeaderMap::iter_grouped()`.
+pub struct IterGrouped<'a>(hash_map::Iter<'a, HeaderName, Value>);
+
+impl<'a> Iterator for IterGrouped<'a> {
+    type Item = (&'a HeaderName, &'a [HeaderValue]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(name, value)| (name, value.as_slice()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// A view into a single entry of the header map, see `HeaderMap::entry()`.
+pub enum Entry<'a> {
+    /// Entry of the header with values.
+    Occupied(OccupiedEntry<'a>),
+    /// Entry of the missing header.
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the name of the header.
+    pub fn key(&self) -> &HeaderName {
+        match self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Inserts value if header is missing, returns reference to the first
+    /// value of the header.
+    pub fn or_insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        self.or_insert_with(|| value)
+    }
+
+    /// Inserts result of the function if header is missing, returns reference
+    /// to the first value of the header.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut HeaderValue
+    where
+        F: FnOnce() -> HeaderValue,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+}
+
+/// Entry of the header with values.
+pub struct OccupiedEntry<'a>(
+    hash_map::OccupiedEntry<'a, HeaderName, Value, DefaultHashBuilder>,
+    &'a mut Option<HashMap<HeaderName, Box<str>>>,
+);
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the name of the header.
+    pub fn key(&self) -> &HeaderName {
+        self.0.key()
+    }
+
+    /// Returns the first value of the header.
+    pub fn get(&self) -> &HeaderValue {
+        self.0.get().get()
+    }
+
+    /// Returns mutable reference to the first value of the header.
+    pub fn get_mut(&mut self) -> &mut HeaderValue {
+        self.0.get_mut().get_mut()
+    }
+
+    /// Converts entry into mutable reference to the first value of the
+    /// header.
+    pub fn into_mut(self) -> &'a mut HeaderValue {
+        self.0.into_mut().get_mut()
+    }
+
+    /// Returns all values of the header.
+    pub fn values(&self) -> &[HeaderValue] {
+        self.0.get().as_slice()
+    }
+
+    /// Replaces all values of the header with the new value.
+    pub fn insert(&mut self, value: HeaderValue) {
+        if let Some(cased) = self.1.as_mut() {
+            cased.remove(self.0.key());
+        }
+        let _ = self.0.insert(Value::One(value));
+    }
+
+    /// Appends value to existing values of the header.
+    pub fn append(&mut self, value: HeaderValue) {
+        self.0.get_mut().append(value)
+    }
+
+    /// Removes the header from the map, returns all its values.
+    pub fn remove(self) -> Vec<HeaderValue> {
+        if let Some(cased) = self.1.as_mut() {
+            cased.remove(self.0.key());
+        }
+        match self.0.remove() {
+            Value::One(val) => vec![val],
+            Value::Multi(vec) => vec,
+        }
+    }
+}
+
+/// Entry of the missing header.
+pub struct VacantEntry<'a>(
+    hash_map::VacantEntry<'a, HeaderName, Value, DefaultHashBuilder>,
+    &'a mut Option<HashMap<HeaderName, Box<str>>>,
+);
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the name of the header.
+    pub fn key(&self) -> &HeaderName {
+        self.0.key()
+    }
+
+    /// Inserts value of the header, returns mutable reference to it.
+    pub fn insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        if let Some(cased) = self.1.as_mut() {
+            cased.remove(self.0.key());
+        }
+        self.0.insert(Value::One(value)).get_mut()
+    }
+}
+
+/// Headers as a message, typed headers are parsed from `HttpMessage`.
+struct Headers<'a> {
+    headers: &'a HeaderMap,
+    extensions: RefCell<Extensions>,
+}
+
+impl<'a> Headers<'a> {
+    fn new(headers: &'a HeaderMap) -> Self {
+        Headers {
+            headers,
+            extensions: RefCell::new(Extensions::new()),
+        }
+    }
+}
+
+impl<'a> HttpMessage for Headers<'a> {
+    type Stream = ();
+
+    fn headers(&self) -> &HeaderMap {
+        self.headers
+    }
+
+    fn take_payload(&mut self) -> Payload<()> {
+        Payload::None
+    }
+
+    fn extensions(&self) -> Ref<Extensions> {
+        self.extensions.borrow()
+    }
+
+    fn extensions_mut(&self) -> RefMut<Extensions> {
+        self.extensions.borrow_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{self, ContentType};
+
+    #[test]
+    fn test_typed() {
+        let mut map = HeaderMap::new();
+        assert_eq!(map.typed_get::<ContentType>(), None);
+
+        map.typed_insert(ContentType::plaintext()).unwrap();
+        map.typed_insert(ContentType::json()).unwrap();
+        assert_eq!(map.get_all(header::CONTENT_TYPE).len(), 1);
+        assert_eq!(map.typed_get::<ContentType>(), Some(ContentType::json()));
+
+        map.insert(header::CONTENT_TYPE, HeaderValue::from_static("invalid"));
+        assert_eq!(map.typed_get::<ContentType>(), None);
+        assert!(map.typed_try_get::<ContentType>().is_err());
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map = HeaderMap::new();
+        let val = map
+            .entry(header::VARY)
+            .or_insert(HeaderValue::from_static("origin"));
+        assert_eq!(*val, "origin");
+
+        match map.entry(header::VARY) {
+            Entry::Occupied(mut entry) => {
+                entry.append(HeaderValue::from_static("accept"));
+                assert_eq!(entry.get(), "origin");
+                assert_eq!(entry.values().len(), 2);
+            }
+            Entry::Vacant(_) => panic!(),
+        }
+        assert_eq!(map.get_all(header::VARY).count(), 2);
+
+        match map.entry(header::VARY) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove().len(), 2),
+            Entry::Vacant(_) => panic!(),
+        }
+        assert!(!map.contains_key(header::VARY));
+    }
+
+    #[test]
+    fn test_entry_cased() {
+        let mut map = HeaderMap::new();
+        let name = HeaderName::from_static("x-request-id");
+        let value = HeaderValue::from_static("1");
+
+        map.insert_cased("X-Request-Id", value.clone()).unwrap();
+        if let Entry::Occupied(entry) = map.entry(name.clone()) {
+            entry.remove();
+        }
+        map.append(name.clone(), value.clone());
+        assert_eq!(map.original_case(&name), None);
+
+        map.insert_cased("X-Request-Id", value.clone()).unwrap();
+        if let Entry::Occupied(mut entry) = map.entry(name.clone()) {
+            entry.insert(value.clone());
+        }
+        assert_eq!(map.original_case(&name), None);
+        assert_eq!(map.get(&name), Some(&value));
+    }
+
+    #[test]
+    fn test_iter_grouped() {
+        let mut map = HeaderMap::new();
+        map.append(header::VARY, HeaderValue::from_static("origin"));
+        map.append(header::VARY, HeaderValue::from_static("accept"));
+        map.insert(header::HOST, HeaderValue::from_static("localhost"));
+        assert_eq!(map.iter().count(), 3);
+
+        let mut groups: Vec<_> = map.iter_grouped().collect();
+        groups.sort_by_key(|(name, _)| name.as_str());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, header::HOST);
+        assert_eq!(groups[1].1, &["origin", "accept"][..]);
+    }
+}
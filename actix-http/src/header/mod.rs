@@ -19,10 +19,11 @@ mod shared;
 pub use self::common::*;
 #[doc(hidden)]
 pub use self::shared::*;
+pub use self::shared::{fmt_http_date, http_date_now, DATE_VALUE_LENGTH};
 
 #[doc(hidden)]
 pub use self::map::GetAll;
-pub use self::map::HeaderMap;
+pub use self::map::{Entry, HeaderMap, IterGrouped, OccupiedEntry, VacantEntry};
 
 /// A trait for any object that will represent a header field and value.
 pub trait Header
@@ -391,6 +392,26 @@ impl From<http::HeaderMap> for HeaderMap {
     }
 }
 
+/// Convert HeaderMap to a http::HeaderMap
+impl From<HeaderMap> for http::HeaderMap {
+    fn from(map: HeaderMap) -> http::HeaderMap {
+        let mut new_map = http::HeaderMap::with_capacity(map.len());
+        for (h, v) in map.inner {
+            match v {
+                map::Value::One(v) => {
+                    new_map.append(h, v);
+                }
+                map::Value::Multi(vs) => {
+                    for v in vs {
+                        new_map.append(h.clone(), v);
+                    }
+                }
+            }
+        }
+        new_map
+    }
+}
+
 // This encode set is used for HTTP header values and is defined at
 // https://tools.ietf.org/html/rfc5987#section-3.2
 pub(crate) const HTTP_VALUE: &AsciiSet = &CONTROLS
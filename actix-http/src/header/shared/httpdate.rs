@@ -1,14 +1,72 @@
+use std::cell::Cell;
 use std::fmt::{self, Display};
 use std::io::Write;
 use std::str::FromStr;
+use std::thread::LocalKey;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use bytes::{BufMut, BytesMut};
+use bytes::Bytes;
 use http::header::{HeaderValue, InvalidHeaderValueBytes};
 
 use crate::error::ParseError;
 use crate::header::IntoHeaderValue;
 
+/// Length of formatted date, i.e. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub const DATE_VALUE_LENGTH: usize = 29;
+
+/// Second since epoch and its formatted value
+type Cached = Cell<(u64, [u8; DATE_VALUE_LENGTH])>;
+
+const EMPTY: (u64, [u8; DATE_VALUE_LENGTH]) = (u64::max_value(), [0; DATE_VALUE_LENGTH]);
+
+thread_local! {
+    static NOW: Cached = Cell::new(EMPTY);
+    static LAST: Cached = Cell::new(EMPTY);
+}
+
+/// Current date formatted for `Date` header.
+///
+/// Date is formatted once per second, formatted value is cached per thread.
+pub fn http_date_now() -> [u8; DATE_VALUE_LENGTH] {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(dur) => cached(&NOW, dur.as_secs()),
+        Err(_) => format_secs(0),
+    }
+}
+
+/// Format time as HTTP date, i.e. for `Last-Modified` header.
+///
+/// Sub-second part of the time is truncated. Formatted value of the last
+/// formatted second is cached per thread, so responses of the same file
+/// do not format modification time again. Times before unix epoch are
+/// formatted as epoch.
+pub fn fmt_http_date(time: SystemTime) -> [u8; DATE_VALUE_LENGTH] {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(dur) => cached(&LAST, dur.as_secs()),
+        Err(_) => format_secs(0),
+    }
+}
+
+fn cached(key: &'static LocalKey<Cached>, secs: u64) -> [u8; DATE_VALUE_LENGTH] {
+    key.with(|cell| {
+        let (cached, date) = cell.get();
+        if cached == secs {
+            date
+        } else {
+            let date = format_secs(secs);
+            cell.set((secs, date));
+            date
+        }
+    })
+}
+
+fn format_secs(secs: u64) -> [u8; DATE_VALUE_LENGTH] {
+    let mut buf = [0; DATE_VALUE_LENGTH];
+    let tm = time::at_utc(time::Timespec::new(secs as i64, 0));
+    write!(&mut buf[..], "{}", tm.rfc822()).unwrap();
+    buf
+}
+
 /// A timestamp with HTTP formatting and parsing
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct HttpDate(time::Tm);
@@ -61,9 +119,8 @@ impl IntoHeaderValue for HttpDate {
     type Error = InvalidHeaderValueBytes;
 
     fn try_into(self) -> Result<HeaderValue, Self::Error> {
-        let mut wrt = BytesMut::with_capacity(29).writer();
-        write!(wrt, "{}", self.0.rfc822()).unwrap();
-        HeaderValue::from_shared(wrt.get_mut().take().freeze())
+        let date = fmt_http_date(self.into());
+        HeaderValue::from_shared(Bytes::from(&date[..]))
     }
 }
 
@@ -80,7 +137,7 @@ impl From<HttpDate> for SystemTime {
 
 #[cfg(test)]
 mod tests {
-    use super::HttpDate;
+    use super::*;
     use time::Tm;
 
     const NOV_07: HttpDate = HttpDate(Tm {
@@ -115,4 +172,25 @@ mod tests {
         );
         assert!("this-is-no-date".parse::<HttpDate>().is_err());
     }
+
+    #[test]
+    fn test_fmt_http_date() {
+        let time = UNIX_EPOCH + Duration::from_millis(784_111_777_500);
+        assert_eq!(&fmt_http_date(time)[..], b"Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(&fmt_http_date(time)[..], b"Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            &fmt_http_date(time + Duration::from_secs(1))[..],
+            b"Sun, 06 Nov 1994 08:49:38 GMT"
+        );
+        assert_eq!(
+            &fmt_http_date(UNIX_EPOCH - Duration::from_secs(1))[..],
+            b"Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+
+        let value = HttpDate::from(time).try_into().unwrap();
+        assert_eq!(value, "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        let now = http_date_now();
+        assert_eq!(&now[26..], b"GMT");
+    }
 }
@@ -6,6 +6,8 @@ use http::header;
 use mime::Mime;
 
 use crate::cookie::Cookie;
+#[cfg(feature = "secure-cookies")]
+use crate::cookie::{CookieJar, Key};
 use crate::error::{ContentTypeError, CookieParseError, ParseError};
 use crate::extensions::Extensions;
 use crate::header::{Header, HeaderMap};
@@ -135,6 +137,110 @@ pub trait HttpMessage: Sized {
         }
         None
     }
+
+    /// Load request cookies and verify their signatures with `key`.
+    ///
+    /// Cookies that are not signed with the key are skipped. Returned
+    /// cookies contain the verified, unsigned value.
+    #[cfg(feature = "secure-cookies")]
+    fn signed_cookies(
+        &self,
+        key: &Key,
+    ) -> Result<Vec<Cookie<'static>>, CookieParseError> {
+        self.signed_cookies_rotated(std::slice::from_ref(key))
+    }
+
+    /// Load request cookies and verify their signatures with any of `keys`.
+    ///
+    /// Keys are tried in order, so the current key should be first,
+    /// followed by keys that are being rotated out.
+    #[cfg(feature = "secure-cookies")]
+    fn signed_cookies_rotated(
+        &self,
+        keys: &[Key],
+    ) -> Result<Vec<Cookie<'static>>, CookieParseError> {
+        let mut jar = cookie_jar(&*self.cookies()?);
+        Ok(names(&jar)
+            .into_iter()
+            .filter_map(|name| keys.iter().find_map(|key| jar.signed(key).get(&name)))
+            .collect())
+    }
+
+    /// Return request cookie verified with `key`.
+    #[cfg(feature = "secure-cookies")]
+    fn signed_cookie(&self, name: &str, key: &Key) -> Option<Cookie<'static>> {
+        self.signed_cookie_rotated(name, std::slice::from_ref(key))
+    }
+
+    /// Return request cookie verified with any of `keys`.
+    #[cfg(feature = "secure-cookies")]
+    fn signed_cookie_rotated(
+        &self,
+        name: &str,
+        keys: &[Key],
+    ) -> Option<Cookie<'static>> {
+        let mut jar = cookie_jar(&*self.cookies().ok()?);
+        keys.iter().find_map(|key| jar.signed(key).get(name))
+    }
+
+    /// Load request cookies and decrypt them with `key`.
+    ///
+    /// Cookies that can not be authenticated and decrypted with the key
+    /// are skipped. Returned cookies contain the plaintext value.
+    #[cfg(feature = "secure-cookies")]
+    fn private_cookies(
+        &self,
+        key: &Key,
+    ) -> Result<Vec<Cookie<'static>>, CookieParseError> {
+        self.private_cookies_rotated(std::slice::from_ref(key))
+    }
+
+    /// Load request cookies and decrypt them with any of `keys`.
+    ///
+    /// Keys are tried in order, so the current key should be first,
+    /// followed by keys that are being rotated out.
+    #[cfg(feature = "secure-cookies")]
+    fn private_cookies_rotated(
+        &self,
+        keys: &[Key],
+    ) -> Result<Vec<Cookie<'static>>, CookieParseError> {
+        let mut jar = cookie_jar(&*self.cookies()?);
+        Ok(names(&jar)
+            .into_iter()
+            .filter_map(|name| keys.iter().find_map(|key| jar.private(key).get(&name)))
+            .collect())
+    }
+
+    /// Return request cookie decrypted with `key`.
+    #[cfg(feature = "secure-cookies")]
+    fn private_cookie(&self, name: &str, key: &Key) -> Option<Cookie<'static>> {
+        self.private_cookie_rotated(name, std::slice::from_ref(key))
+    }
+
+    /// Return request cookie decrypted with any of `keys`.
+    #[cfg(feature = "secure-cookies")]
+    fn private_cookie_rotated(
+        &self,
+        name: &str,
+        keys: &[Key],
+    ) -> Option<Cookie<'static>> {
+        let mut jar = cookie_jar(&*self.cookies().ok()?);
+        keys.iter().find_map(|key| jar.private(key).get(name))
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+fn cookie_jar(cookies: &[Cookie<'static>]) -> CookieJar {
+    let mut jar = CookieJar::new();
+    for cookie in cookies {
+        jar.add_original(cookie.clone());
+    }
+    jar
+}
+
+#[cfg(feature = "secure-cookies")]
+fn names(jar: &CookieJar) -> Vec<String> {
+    jar.iter().map(|c| c.name().to_owned()).collect()
 }
 
 impl<'a, T> HttpMessage for &'a mut T
@@ -14,6 +14,7 @@ extern crate log;
 pub mod body;
 mod builder;
 pub mod client;
+pub mod clock;
 mod cloneable;
 mod config;
 pub mod encoding;
@@ -27,6 +28,7 @@ mod payload;
 mod request;
 mod response;
 mod service;
+mod timer;
 
 pub mod cookie;
 pub mod error;
@@ -36,15 +38,26 @@ pub mod test;
 pub mod ws;
 
 pub use self::builder::HttpServiceBuilder;
-pub use self::config::{KeepAlive, ServiceConfig};
+pub use self::config::{
+    FlushStrategy, HeaderCase, HeaderLimits, KeepAlive, ServiceConfig,
+};
 pub use self::error::{Error, ResponseError, Result};
 pub use self::extensions::Extensions;
 pub use self::httpmessage::HttpMessage;
 pub use self::message::{Message, RequestHead, RequestHeadType, ResponseHead};
-pub use self::payload::{Payload, PayloadStream};
+pub use self::payload::{IntoPayload, Payload, PayloadDrain, PayloadStream};
 pub use self::request::Request;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
+pub use self::timer::Timer;
+
+pub mod pool {
+    //! Per-thread pools of request and response heads
+
+    pub use crate::message::{
+        request_pool_stats, response_pool_stats, set_pool_capacity, PoolStats,
+    };
+}
 
 pub mod http {
     //! Various HTTP related types
@@ -62,6 +75,7 @@ pub mod http {
     pub mod header {
         pub use crate::header::*;
     }
+    pub use crate::config::{FlushStrategy, HeaderCase};
     pub use crate::header::ContentEncoding;
     pub use crate::message::ConnectionType;
 }
@@ -1,4 +1,4 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::net;
 use std::rc::Rc;
 
@@ -28,6 +28,7 @@ bitflags! {
         const EXPECT      = 0b0000_1000;
         const NO_CHUNKING = 0b0001_0000;
         const CAMEL_CASE  = 0b0010_0000;
+        const ABSOLUTE    = 0b0100_0000;
     }
 }
 
@@ -179,6 +180,17 @@ impl RequestHead {
     pub(crate) fn set_expect(&mut self) {
         self.flags.insert(Flags::EXPECT);
     }
+
+    #[inline]
+    /// Request line contains absolute uri, used for requests to http proxy
+    pub(crate) fn absolute_form(&self) -> bool {
+        self.flags.contains(Flags::ABSOLUTE)
+    }
+
+    #[inline]
+    pub(crate) fn set_absolute_form(&mut self) {
+        self.flags.insert(Flags::ABSOLUTE);
+    }
 }
 
 #[derive(Debug)]
@@ -194,6 +206,34 @@ impl RequestHeadType {
             RequestHeadType::Rc(_, headers) => headers.as_ref(),
         }
     }
+
+    /// Convert into owned head, shared head is copied if needed and
+    /// extra headers replace headers of the head.
+    pub(crate) fn into_owned(self) -> RequestHead {
+        match self {
+            RequestHeadType::Owned(head) => head,
+            RequestHeadType::Rc(head, extra_headers) => {
+                let mut head = Rc::try_unwrap(head).unwrap_or_else(|head| RequestHead {
+                    uri: head.uri.clone(),
+                    method: head.method.clone(),
+                    version: head.version,
+                    headers: head.headers.clone(),
+                    extensions: RefCell::new(Extensions::new()),
+                    peer_addr: head.peer_addr,
+                    flags: head.flags,
+                });
+                if let Some(extra_headers) = extra_headers {
+                    for name in extra_headers.keys() {
+                        head.headers.remove(name);
+                    }
+                    for (name, value) in extra_headers.iter() {
+                        head.headers.append(name.clone(), value.clone());
+                    }
+                }
+                head
+            }
+        }
+    }
 }
 
 impl AsRef<RequestHead> for RequestHeadType {
@@ -418,28 +458,88 @@ impl Drop for BoxedResponseHead {
     }
 }
 
+/// Default capacity of object pools
+pub(crate) const POOL_CAPACITY: usize = 128;
+
+/// Hit and miss counters of an object pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of objects taken from the pool
+    pub hits: u64,
+    /// Number of objects allocated because the pool was empty
+    pub misses: u64,
+}
+
+impl PoolStats {
+    #[inline]
+    fn record(cell: &Cell<PoolStats>, hit: bool) {
+        let mut stats = cell.get();
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        cell.set(stats);
+    }
+}
+
+/// Set capacity of request and response head pools of the current thread.
+///
+/// Pools keep up to 128 released heads by default, capacity of 0 disables
+/// pooling. Pools are per thread, so capacity has to be set on every
+/// worker, i.e. from application factory of `HttpServer`.
+pub fn set_pool_capacity(capacity: usize) {
+    RequestHead::pool().set_capacity(capacity);
+    RESPONSE_POOL.with(|p| p.set_capacity(capacity));
+}
+
+/// Counters of request head pool of the current thread.
+pub fn request_pool_stats() -> PoolStats {
+    RequestHead::pool().stats.get()
+}
+
+/// Counters of response head pool of the current thread.
+pub fn response_pool_stats() -> PoolStats {
+    RESPONSE_POOL.with(|p| p.stats.get())
+}
+
 #[doc(hidden)]
 /// Request's objects pool
-pub struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>);
+pub struct MessagePool<T: Head> {
+    pool: RefCell<Vec<Rc<T>>>,
+    capacity: Cell<usize>,
+    stats: Cell<PoolStats>,
+}
 
 #[doc(hidden)]
 #[allow(clippy::vec_box)]
 /// Request's objects pool
-pub struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>);
+pub struct BoxedResponsePool {
+    pool: RefCell<Vec<Box<ResponseHead>>>,
+    capacity: Cell<usize>,
+    stats: Cell<PoolStats>,
+}
 
 thread_local!(static REQUEST_POOL: &'static MessagePool<RequestHead> = MessagePool::<RequestHead>::create());
 thread_local!(static RESPONSE_POOL: &'static BoxedResponsePool = BoxedResponsePool::create());
 
 impl<T: Head> MessagePool<T> {
     fn create() -> &'static MessagePool<T> {
-        let pool = MessagePool(RefCell::new(Vec::with_capacity(128)));
+        let pool = MessagePool {
+            pool: RefCell::new(Vec::with_capacity(POOL_CAPACITY)),
+            capacity: Cell::new(POOL_CAPACITY),
+            stats: Cell::new(PoolStats::default()),
+        };
         Box::leak(Box::new(pool))
     }
 
     /// Get message from the pool
     #[inline]
     fn get_message(&'static self) -> Message<T> {
-        if let Some(mut msg) = self.0.borrow_mut().pop() {
+        let msg = self.pool.borrow_mut().pop();
+        PoolStats::record(&self.stats, msg.is_some());
+
+        if let Some(mut msg) = msg {
             if let Some(r) = Rc::get_mut(&mut msg) {
                 r.clear();
             }
@@ -454,23 +554,37 @@ impl<T: Head> MessagePool<T> {
     #[inline]
     /// Release request instance
     fn release(&self, msg: Rc<T>) {
-        let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
+        let v = &mut self.pool.borrow_mut();
+        if v.len() < self.capacity.get() {
             v.push(msg);
         }
     }
+
+    fn set_capacity(&self, capacity: usize) {
+        self.capacity.set(capacity);
+        let v = &mut self.pool.borrow_mut();
+        v.truncate(capacity);
+        v.shrink_to_fit();
+    }
 }
 
 impl BoxedResponsePool {
     fn create() -> &'static BoxedResponsePool {
-        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(128)));
+        let pool = BoxedResponsePool {
+            pool: RefCell::new(Vec::with_capacity(POOL_CAPACITY)),
+            capacity: Cell::new(POOL_CAPACITY),
+            stats: Cell::new(PoolStats::default()),
+        };
         Box::leak(Box::new(pool))
     }
 
     /// Get message from the pool
     #[inline]
     fn get_message(&'static self, status: StatusCode) -> BoxedResponseHead {
-        if let Some(mut head) = self.0.borrow_mut().pop() {
+        let head = self.pool.borrow_mut().pop();
+        PoolStats::record(&self.stats, head.is_some());
+
+        if let Some(mut head) = head {
             head.reason = None;
             head.status = status;
             head.headers.clear();
@@ -486,10 +600,17 @@ impl BoxedResponsePool {
     #[inline]
     /// Release request instance
     fn release(&self, msg: Box<ResponseHead>) {
-        let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
+        let v = &mut self.pool.borrow_mut();
+        if v.len() < self.capacity.get() {
             msg.extensions.borrow_mut().clear();
             v.push(msg);
         }
     }
+
+    fn set_capacity(&self, capacity: usize) {
+        self.capacity.set(capacity);
+        let v = &mut self.pool.borrow_mut();
+        v.truncate(capacity);
+        v.shrink_to_fit();
+    }
 }
@@ -48,6 +48,92 @@ impl<S> Payload<S> {
     pub fn take(&mut self) -> Payload<S> {
         std::mem::replace(self, Payload::None)
     }
+
+    /// Set policy for data that is not read by the time payload is dropped.
+    ///
+    /// Only *HTTP/1* payload is affected, *HTTP/2* streams are reset
+    /// independently of the connection.
+    pub fn set_drain(&self, drain: PayloadDrain) {
+        if let Payload::H1(ref pl) = *self {
+            pl.set_drain(drain);
+        }
+    }
+}
+
+/// Conversion of request body to `Payload`.
+///
+/// Used for conversion of `http::Request<B>` to `Request`, implement it for
+/// body types of other libraries to bridge them with actix payload.
+pub trait IntoPayload<S = PayloadStream> {
+    /// Convert body to payload
+    fn into_payload(self) -> Payload<S>;
+}
+
+impl<S> IntoPayload<S> for Payload<S> {
+    fn into_payload(self) -> Payload<S> {
+        self
+    }
+}
+
+impl<S> IntoPayload<S> for () {
+    fn into_payload(self) -> Payload<S> {
+        Payload::None
+    }
+}
+
+impl<S> IntoPayload<S> for Bytes {
+    fn into_payload(self) -> Payload<S> {
+        let mut payload = crate::h1::Payload::empty();
+        if !self.is_empty() {
+            payload.unread_data(self);
+        }
+        Payload::H1(payload)
+    }
+}
+
+impl<S> IntoPayload<S> for Vec<u8> {
+    fn into_payload(self) -> Payload<S> {
+        Bytes::from(self).into_payload()
+    }
+}
+
+impl<S> IntoPayload<S> for String {
+    fn into_payload(self) -> Payload<S> {
+        Bytes::from(self).into_payload()
+    }
+}
+
+impl<S> IntoPayload<S> for &'static str {
+    fn into_payload(self) -> Payload<S> {
+        Bytes::from_static(self.as_bytes()).into_payload()
+    }
+}
+
+impl<S> IntoPayload<S> for &'static [u8] {
+    fn into_payload(self) -> Payload<S> {
+        Bytes::from_static(self).into_payload()
+    }
+}
+
+/// Policy for request payload that is not read by the handler.
+///
+/// When response is sent before request payload is received completely,
+/// rest of the payload has to be read from the connection before next
+/// request, otherwise connection could not be kept alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadDrain {
+    /// Close connection once response is sent, if rest of the payload is
+    /// not read from the connection yet
+    Close,
+    /// Read and discard up to specified number of bytes, close connection
+    /// if payload is larger
+    Limit(usize),
+}
+
+impl Default for PayloadDrain {
+    fn default() -> Self {
+        PayloadDrain::Close
+    }
 }
 
 impl<S> Stream for Payload<S>
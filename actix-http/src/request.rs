@@ -7,7 +7,7 @@ use crate::extensions::Extensions;
 use crate::header::HeaderMap;
 use crate::httpmessage::HttpMessage;
 use crate::message::{Message, RequestHead};
-use crate::payload::{Payload, PayloadStream};
+use crate::payload::{IntoPayload, Payload, PayloadStream};
 
 /// Request
 pub struct Request<P = PayloadStream> {
@@ -164,6 +164,41 @@ impl<P> Request<P> {
     }
 }
 
+/// Convert `http::Request` to a `Request`
+///
+/// Extensions of `http` request are not preserved.
+impl<B, P> From<http::Request<B>> for Request<P>
+where
+    B: IntoPayload<P>,
+{
+    fn from(req: http::Request<B>) -> Self {
+        let (parts, body) = req.into_parts();
+        let mut req = Request::with_payload(body.into_payload());
+        let head = req.head_mut();
+        head.method = parts.method;
+        head.uri = parts.uri;
+        head.version = parts.version;
+        head.headers = parts.headers.into();
+        req
+    }
+}
+
+/// Convert `Request` to a `http::Request` with payload as a body
+///
+/// Extensions and peer address of the request are not preserved.
+impl<P> From<Request<P>> for http::Request<Payload<P>> {
+    fn from(req: Request<P>) -> Self {
+        let (mut head, payload) = req.into_parts();
+        let mut req = http::Request::new(payload);
+        *req.method_mut() = head.method.clone();
+        *req.uri_mut() = head.uri.clone();
+        *req.version_mut() = head.version;
+        *req.headers_mut() =
+            std::mem::replace(&mut head.headers, HeaderMap::new()).into();
+        req
+    }
+}
+
 impl<P> fmt::Debug for Request<P> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
@@ -206,4 +241,27 @@ mod tests {
         let s = format!("{:?}", req);
         assert!(s.contains("Request HTTP/1.1 GET:/index.html"));
     }
+
+    #[test]
+    fn test_http_request() {
+        let req = http::Request::put("/index.html?q=1")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .header(header::ACCEPT, "text/plain")
+            .header(header::ACCEPT, "text/html")
+            .body("data")
+            .unwrap();
+        let mut req: Request = req.into();
+        assert_eq!(req.method(), Method::PUT);
+        assert_eq!(req.uri().query(), Some("q=1"));
+        assert_eq!(req.headers().get_all(header::ACCEPT).count(), 2);
+        assert!(req.headers().contains_key(header::CONTENT_TYPE));
+        if let Payload::None = req.take_payload() {
+            panic!("payload is missing");
+        }
+
+        let req: http::Request<Payload> = req.into();
+        assert_eq!(req.method(), Method::PUT);
+        assert_eq!(req.uri().path(), "/index.html");
+        assert_eq!(req.headers().get_all(header::ACCEPT).iter().count(), 2);
+    }
 }
@@ -13,6 +13,8 @@ use serde::Serialize;
 use serde_json;
 
 use crate::body::{Body, BodyStream, MessageBody, ResponseBody};
+#[cfg(feature = "secure-cookies")]
+use crate::cookie::Key;
 use crate::cookie::{Cookie, CookieJar};
 use crate::error::Error;
 use crate::extensions::Extensions;
@@ -434,6 +436,68 @@ impl ResponseBuilder {
         self
     }
 
+    /// Reserve capacity for at least `additional` more headers.
+    ///
+    /// Header map of the response is grown once, instead of being rehashed
+    /// while headers are added one by one.
+    #[inline]
+    pub fn headers_reserve(&mut self, additional: usize) -> &mut Self {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            parts.headers.reserve(additional);
+        }
+        self
+    }
+
+    /// Set multiple headers, values of existing headers are replaced.
+    ///
+    /// Capacity for the headers is reserved up front, by lower bound of
+    /// iterator's size hint.
+    ///
+    /// ```rust
+    /// use actix_http::{http, Request, Response};
+    ///
+    /// fn index(req: Request) -> Response {
+    ///     Response::Ok()
+    ///         .insert_headers(vec![
+    ///             ("X-Frame-Options", "DENY"),
+    ///             ("X-Content-Type-Options", "nosniff"),
+    ///             ("Access-Control-Allow-Origin", "*"),
+    ///         ])
+    ///         .finish()
+    /// }
+    /// fn main() {}
+    /// ```
+    pub fn insert_headers<I, K, V>(&mut self, headers: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        HeaderName: HttpTryFrom<K>,
+        V: IntoHeaderValue,
+    {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            let headers = headers.into_iter();
+            parts.headers.reserve(headers.size_hint().0);
+
+            for (key, value) in headers {
+                match HeaderName::try_from(key) {
+                    Ok(key) => match value.try_into() {
+                        Ok(value) => {
+                            parts.headers.insert(key, value);
+                        }
+                        Err(e) => {
+                            self.err = Some(e.into());
+                            break;
+                        }
+                    },
+                    Err(e) => {
+                        self.err = Some(e.into());
+                        break;
+                    }
+                };
+            }
+        }
+        self
+    }
+
     /// Set the custom reason for the response.
     #[inline]
     pub fn reason(&mut self, reason: &'static str) -> &mut Self {
@@ -536,6 +600,54 @@ impl ResponseBuilder {
         self
     }
 
+    /// Set a signed cookie
+    ///
+    /// Cookie value is signed with `key`, its integrity could be verified
+    /// later with `HttpMessage::signed_cookie()`.
+    ///
+    /// ```rust
+    /// use actix_http::{http, Request, Response};
+    /// use actix_http::cookie::Key;
+    ///
+    /// fn index(req: Request, key: &Key) -> Response {
+    ///     Response::Ok()
+    ///         .signed_cookie(http::Cookie::new("name", "value"), key)
+    ///         .finish()
+    /// }
+    /// ```
+    #[cfg(feature = "secure-cookies")]
+    pub fn signed_cookie<'c>(&mut self, cookie: Cookie<'c>, key: &Key) -> &mut Self {
+        self.cookies
+            .get_or_insert_with(CookieJar::new)
+            .signed(key)
+            .add(cookie.into_owned());
+        self
+    }
+
+    /// Set a private cookie
+    ///
+    /// Cookie value is encrypted with `key`, it could be decrypted later
+    /// with `HttpMessage::private_cookie()`.
+    ///
+    /// ```rust
+    /// use actix_http::{http, Request, Response};
+    /// use actix_http::cookie::Key;
+    ///
+    /// fn index(req: Request, key: &Key) -> Response {
+    ///     Response::Ok()
+    ///         .private_cookie(http::Cookie::new("name", "value"), key)
+    ///         .finish()
+    /// }
+    /// ```
+    #[cfg(feature = "secure-cookies")]
+    pub fn private_cookie<'c>(&mut self, cookie: Cookie<'c>, key: &Key) -> &mut Self {
+        self.cookies
+            .get_or_insert_with(CookieJar::new)
+            .private(key)
+            .add(cookie.into_owned());
+        self
+    }
+
     /// Remove cookie
     ///
     /// ```rust
@@ -853,12 +965,60 @@ impl From<BytesMut> for Response {
     }
 }
 
+/// Convert `http::Response` to a `Response`
+///
+/// Extensions of `http` response are not preserved.
+impl<B: Into<Body>> From<http::Response<B>> for Response {
+    fn from(res: http::Response<B>) -> Self {
+        let (parts, body) = res.into_parts();
+        let mut res = Response::with_body(parts.status, body.into());
+        res.head.version = parts.version;
+        res.head.headers = parts.headers.into();
+        res
+    }
+}
+
+/// Convert `Response` to a `http::Response`
+///
+/// Extensions, reason phrase and connection type of the response are not
+/// preserved.
+impl<B> From<Response<B>> for http::Response<ResponseBody<B>> {
+    fn from(res: Response<B>) -> Self {
+        let Response { mut head, body, .. } = res;
+        let mut res = http::Response::new(body);
+        *res.status_mut() = head.status;
+        *res.version_mut() = head.version;
+        *res.headers_mut() =
+            std::mem::replace(&mut head.headers, HeaderMap::new()).into();
+        res
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::body::Body;
     use crate::http::header::{HeaderValue, CONTENT_TYPE, COOKIE, SET_COOKIE};
 
+    #[test]
+    fn test_http_response() {
+        let res = http::Response::builder()
+            .status(StatusCode::CREATED)
+            .header(SET_COOKIE, "c1=cookie1")
+            .header(SET_COOKIE, "c2=cookie2")
+            .body("data")
+            .unwrap();
+        let res: Response = res.into();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers().get_all(SET_COOKIE).count(), 2);
+        assert_eq!(res.body().get_ref(), b"data");
+
+        let res: http::Response<ResponseBody<Body>> = res.into();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers().get_all(SET_COOKIE).iter().count(), 2);
+        assert_eq!(res.body().get_ref(), b"data");
+    }
+
     #[test]
     fn test_debug() {
         let resp = Response::Ok()
@@ -904,6 +1064,48 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "secure-cookies")]
+    #[test]
+    fn test_secure_response_cookies() {
+        use crate::cookie::Key;
+        use crate::httpmessage::HttpMessage;
+
+        let old_key = Key::generate();
+        let key = Key::generate();
+
+        let resp = Response::Ok()
+            .signed_cookie(crate::http::Cookie::new("signed", "value1"), &old_key)
+            .private_cookie(crate::http::Cookie::new("private", "value2"), &key)
+            .finish();
+
+        let mut req = crate::test::TestRequest::default();
+        for cookie in resp.cookies() {
+            assert!(!cookie.value().starts_with("value"));
+            req.cookie(cookie.into_owned());
+        }
+        let req = req.finish();
+
+        assert!(req.signed_cookie("signed", &key).is_none());
+        assert_eq!(
+            req.signed_cookie_rotated("signed", &[key.clone(), old_key.clone()])
+                .unwrap()
+                .value(),
+            "value1"
+        );
+        assert!(req.private_cookie("private", &old_key).is_none());
+        assert_eq!(
+            req.private_cookie("private", &key).unwrap().value(),
+            "value2"
+        );
+
+        let cookies = req.private_cookies(&key).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name(), "private");
+        let cookies = req.signed_cookies(&old_key).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].value(), "value1");
+    }
+
     #[test]
     fn test_update_response_cookies() {
         let mut r = Response::Ok()
@@ -933,6 +1135,24 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn test_insert_headers() {
+        let resp = Response::Ok()
+            .header("X-TEST", "old")
+            .headers_reserve(8)
+            .insert_headers(vec![("X-TEST", "value"), ("X-FRAME-OPTIONS", "DENY")])
+            .finish();
+        assert!(resp.headers().capacity() >= 8);
+        assert_eq!(resp.headers().get_all("X-TEST").count(), 1);
+        assert_eq!(resp.headers().get("X-TEST").unwrap(), "value");
+        assert_eq!(resp.headers().get("X-FRAME-OPTIONS").unwrap(), "DENY");
+
+        let resp = Response::Ok()
+            .insert_headers(vec![("X-TEST", "value"), ("X TEST", "value")])
+            .finish();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[test]
     fn test_upgrade() {
         let resp = Response::build(StatusCode::OK)
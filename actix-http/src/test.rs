@@ -1,6 +1,7 @@
 //! Test Various helpers for Actix applications to use during testing.
 use std::fmt::Write as FmtWrite;
 use std::io::{self, Read, Write};
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::task::{Context, Poll};
@@ -8,11 +9,13 @@ use std::task::{Context, Poll};
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_server_config::IoStream;
 use bytes::{Buf, Bytes, BytesMut};
+use futures::Stream;
 use http::header::{self, HeaderName, HeaderValue};
 use http::{HttpTryFrom, Method, Uri, Version};
 use percent_encoding::percent_encode;
 
 use crate::cookie::{Cookie, CookieJar, USERINFO};
+use crate::error::PayloadError;
 use crate::header::HeaderMap;
 use crate::header::{Header, IntoHeaderValue};
 use crate::payload::Payload;
@@ -54,6 +57,7 @@ struct Inner {
     headers: HeaderMap,
     cookies: CookieJar,
     payload: Option<Payload>,
+    peer_addr: Option<SocketAddr>,
 }
 
 impl Default for TestRequest {
@@ -65,6 +69,7 @@ impl Default for TestRequest {
             headers: HeaderMap::new(),
             cookies: CookieJar::new(),
             payload: None,
+            peer_addr: None,
         }))
     }
 }
@@ -145,6 +150,21 @@ impl TestRequest {
         self
     }
 
+    /// Set streaming request payload
+    pub fn set_payload_stream<S>(&mut self, stream: S) -> &mut Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        parts(&mut self.0).payload = Some(Payload::Stream(Box::pin(stream)));
+        self
+    }
+
+    /// Set peer address of the request
+    pub fn peer_addr(&mut self, addr: SocketAddr) -> &mut Self {
+        parts(&mut self.0).peer_addr = Some(addr);
+        self
+    }
+
     pub fn take(&mut self) -> TestRequest {
         TestRequest(self.0.take())
     }
@@ -164,6 +184,7 @@ impl TestRequest {
         head.method = inner.method;
         head.version = inner.version;
         head.headers = inner.headers;
+        head.peer_addr = inner.peer_addr;
 
         let mut cookie = String::new();
         for c in inner.cookies.delta() {
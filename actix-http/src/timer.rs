@@ -0,0 +1,371 @@
+//! Hashed timer wheel for connection timers
+//!
+//! Keep-alive, slow request and shutdown timers of connections are
+//! registered in a wheel of the service config, instead of a timer per
+//! connection. Wheel is driven by single delay that runs only while there
+//! are armed timers. Deadlines are rounded up to the tick of the wheel,
+//! timer never fires before its deadline.
+//!
+//! Wheel reads time from `clock::now()`, so timers of connections served
+//! on the current thread fire on the next tick once `clock::MockClock`
+//! is advanced past their deadlines.
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, mem};
+
+use slab::Slab;
+use tokio_timer::delay_for;
+
+use crate::clock;
+
+/// Number of slots of the wheel
+const SLOTS: u64 = 512;
+
+/// Default tick of the wheel in milliseconds
+pub(crate) const DEFAULT_TICK: u64 = 100;
+
+#[derive(Clone)]
+pub(crate) struct TimerWheel(Rc<RefCell<Inner>>);
+
+struct Inner {
+    tick: Duration,
+    start: Instant,
+    /// Last processed tick
+    elapsed: u64,
+    /// Entries by deadline tick modulo number of slots, stale entries
+    /// are skipped by generation. Slots are allocated with first timer,
+    /// client connections do not use the wheel.
+    slots: Vec<Vec<(usize, u64)>>,
+    entries: Slab<Entry>,
+    generation: u64,
+    /// Number of timers that are not fired yet
+    armed: usize,
+    running: bool,
+}
+
+struct Entry {
+    deadline: u64,
+    generation: u64,
+    fired: bool,
+    waker: Option<Waker>,
+}
+
+impl TimerWheel {
+    pub(crate) fn new(tick: Duration) -> TimerWheel {
+        TimerWheel(Rc::new(RefCell::new(Inner {
+            tick: cmp::max(tick, Duration::from_millis(1)),
+            start: clock::now(),
+            elapsed: 0,
+            slots: Vec::new(),
+            entries: Slab::new(),
+            generation: 0,
+            armed: 0,
+            running: false,
+        })))
+    }
+
+    pub(crate) fn tick(&self) -> Duration {
+        self.0.borrow().tick
+    }
+
+    /// Register timer that fires at `deadline`.
+    pub(crate) fn timer(&self, deadline: Instant) -> Timer {
+        let key = self.0.borrow_mut().entries.insert(Entry {
+            deadline: 0,
+            generation: 0,
+            fired: true,
+            waker: None,
+        });
+        let mut timer = Timer {
+            wheel: self.clone(),
+            key,
+            deadline,
+        };
+        timer.reset(deadline);
+        timer
+    }
+
+    /// Spawn driver of the wheel if it is not running.
+    fn start(&self) {
+        let tick = {
+            let mut inner = self.0.borrow_mut();
+            if inner.running || inner.armed == 0 {
+                return;
+            }
+            inner.running = true;
+            inner.tick
+        };
+
+        let wheel = self.clone();
+        tokio_executor::current_thread::spawn(async move {
+            loop {
+                delay_for(tick).await;
+
+                let wakers = wheel.0.borrow_mut().advance(clock::now());
+                for waker in wakers {
+                    waker.wake();
+                }
+                if !wheel.0.borrow().running {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+impl Inner {
+    fn since_start(&self, at: Instant) -> u128 {
+        if at > self.start {
+            (at - self.start).as_nanos()
+        } else {
+            0
+        }
+    }
+
+    /// Tick that is elapsed at `at`.
+    fn tick_at(&self, at: Instant) -> u64 {
+        (self.since_start(at) / self.tick.as_nanos()) as u64
+    }
+
+    /// First tick that is elapsed after `deadline`.
+    fn deadline_tick(&self, deadline: Instant) -> u64 {
+        let tick = self.tick.as_nanos();
+        ((self.since_start(deadline) + tick - 1) / tick) as u64
+    }
+
+    fn schedule(&mut self, key: usize, deadline: Instant) {
+        // wheel is not processed while driver is stopped
+        if !self.running {
+            self.elapsed = self.tick_at(clock::now());
+        }
+
+        let deadline = self.deadline_tick(deadline);
+        self.generation += 1;
+        let generation = self.generation;
+
+        let entry = &mut self.entries[key];
+        if !entry.fired {
+            self.armed -= 1;
+        }
+        entry.deadline = deadline;
+        entry.generation = generation;
+        entry.fired = deadline <= self.elapsed;
+
+        if !entry.fired {
+            if self.slots.is_empty() {
+                self.slots = (0..SLOTS).map(|_| Vec::new()).collect();
+            }
+            self.armed += 1;
+            self.slots[(deadline % SLOTS) as usize].push((key, generation));
+        }
+    }
+
+    /// Fire timers that are elapsed at `now`, returns wakers of fired timers.
+    fn advance(&mut self, now: Instant) -> Vec<Waker> {
+        let now = self.tick_at(now);
+        let mut wakers = Vec::new();
+
+        while self.elapsed < now && self.armed > 0 {
+            self.elapsed += 1;
+            let elapsed = self.elapsed;
+            let slot = (elapsed % SLOTS) as usize;
+
+            let mut pending = mem::replace(&mut self.slots[slot], Vec::new());
+            let entries = &mut self.entries;
+            let armed = &mut self.armed;
+            pending.retain(|&(key, generation)| {
+                let entry = match entries.get_mut(key) {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                if entry.generation != generation || entry.fired {
+                    return false;
+                }
+                if entry.deadline > elapsed {
+                    // deadline is in one of next rounds
+                    return true;
+                }
+                entry.fired = true;
+                *armed -= 1;
+                if let Some(waker) = entry.waker.take() {
+                    wakers.push(waker);
+                }
+                false
+            });
+            self.slots[slot] = pending;
+        }
+        self.elapsed = cmp::max(self.elapsed, now);
+        self.running = self.armed > 0;
+        if !self.running {
+            // skipped slots could keep stale entries of reset timers
+            for slot in &mut self.slots {
+                slot.clear();
+            }
+        }
+        wakers
+    }
+}
+
+/// Timer of the connection.
+///
+/// Timer is registered in the timer wheel of the service config and
+/// resolves once deadline is elapsed.
+pub struct Timer {
+    wheel: TimerWheel,
+    key: usize,
+    deadline: Instant,
+}
+
+impl Timer {
+    /// Deadline of the timer.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Check if deadline of the timer is elapsed.
+    pub fn is_elapsed(&self) -> bool {
+        self.wheel.0.borrow().entries[self.key].fired
+    }
+
+    /// Reset timer to new deadline.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+        self.wheel.0.borrow_mut().schedule(self.key, deadline);
+        self.wheel.start();
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut inner = self.wheel.0.borrow_mut();
+        let entry = &mut inner.entries[self.key];
+
+        if entry.fired {
+            Poll::Ready(())
+        } else {
+            match entry.waker {
+                Some(ref waker) if waker.will_wake(cx.waker()) => (),
+                _ => entry.waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let mut inner = self.wheel.0.borrow_mut();
+        if !inner.entries.remove(self.key).fired {
+            inner.armed -= 1;
+        }
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("deadline", &self.deadline)
+            .field("elapsed", &self.is_elapsed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_rt::System;
+    use futures::future::poll_fn;
+
+    #[test]
+    fn test_advance() {
+        let wheel = TimerWheel::new(Duration::from_millis(10));
+        let start = wheel.0.borrow().start;
+        let mut inner = wheel.0.borrow_mut();
+
+        // keys are not registered with driver
+        inner.running = true;
+        let first = inner.entries.insert(Entry {
+            deadline: 0,
+            generation: 0,
+            fired: true,
+            waker: None,
+        });
+        let second = inner.entries.insert(Entry {
+            deadline: 0,
+            generation: 0,
+            fired: true,
+            waker: None,
+        });
+        inner.schedule(first, start + Duration::from_millis(25));
+        inner.schedule(second, start + Duration::from_millis(10 * SLOTS + 5));
+        assert_eq!(inner.armed, 2);
+        assert_eq!(inner.entries[first].deadline, 3);
+
+        inner.advance(start + Duration::from_millis(29));
+        assert!(!inner.entries[first].fired);
+
+        inner.advance(start + Duration::from_millis(30));
+        assert!(inner.entries[first].fired);
+        assert!(!inner.entries[second].fired);
+        assert_eq!(inner.armed, 1);
+
+        // second round of the wheel
+        inner.advance(start + Duration::from_millis(10 * SLOTS + 10));
+        assert!(inner.entries[second].fired);
+        assert_eq!(inner.armed, 0);
+        assert!(!inner.running);
+
+        // stale entry of reset timer is removed once driver stops
+        inner.running = true;
+        let now = start + Duration::from_millis(10 * SLOTS + 10);
+        inner.schedule(first, now + Duration::from_millis(200));
+        inner.schedule(first, now + Duration::from_millis(20));
+        inner.advance(now + Duration::from_millis(500));
+        assert!(inner.entries[first].fired);
+        assert!(!inner.running);
+        assert!(inner.slots.iter().all(|slot| slot.is_empty()));
+    }
+
+    #[test]
+    fn test_timer() {
+        System::new("test").block_on(async {
+            let wheel = TimerWheel::new(Duration::from_millis(5));
+            let now = Instant::now();
+
+            let mut first = wheel.timer(now + Duration::from_millis(20));
+            let second = wheel.timer(now + Duration::from_secs(60));
+            let mut third = wheel.timer(now + Duration::from_secs(60));
+            third.reset(now + Duration::from_millis(10));
+
+            (&mut first).await;
+            assert!(Instant::now() >= first.deadline());
+            assert!(third.is_elapsed());
+            assert!(!second.is_elapsed());
+
+            drop(second);
+            assert_eq!(wheel.0.borrow().armed, 0);
+
+            first.reset(now);
+            assert!(first.is_elapsed());
+            poll_fn(|cx| Pin::new(&mut first).poll(cx)).await;
+        })
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        System::new("test").block_on(async {
+            let clock = clock::MockClock::new();
+            let wheel = TimerWheel::new(Duration::from_millis(5));
+
+            let timer = wheel.timer(clock::now() + Duration::from_secs(60));
+            clock.advance(Duration::from_secs(61));
+            timer.await;
+        })
+    }
+}
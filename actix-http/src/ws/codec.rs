@@ -1,6 +1,8 @@
 use actix_codec::{Decoder, Encoder};
 use bytes::{Bytes, BytesMut};
 
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+use super::deflate::{DeflateContext, DeflateParams};
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
 use super::ProtocolError;
@@ -18,6 +20,8 @@ pub enum Message {
     Pong(String),
     /// Close message with optional reason
     Close(Option<CloseReason>),
+    /// Fragment of a continuation message
+    Continuation(Item),
     /// No-op. Useful for actix-net services
     Nop,
 }
@@ -35,13 +39,43 @@ pub enum Frame {
     Pong(String),
     /// Close message with optional reason
     Close(Option<CloseReason>),
+    /// Fragment of a continuation message, produced if continuations
+    /// are not aggregated
+    Continuation(Item),
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Fragment of a continuation message
+#[derive(Debug, PartialEq, Clone)]
+pub enum Item {
+    /// First fragment of a text message
+    FirstText(Bytes),
+    /// First fragment of a binary message
+    FirstBinary(Bytes),
+    /// Intermediate fragment
+    Continue(Bytes),
+    /// Last fragment
+    Last(Bytes),
+}
+
+/// State of a fragmented message being received
+#[derive(Debug, Clone)]
+struct Continuation {
+    opcode: OpCode,
+    compressed: bool,
+    size: usize,
+    buf: Option<BytesMut>,
+}
+
+#[derive(Debug, Clone)]
 /// WebSockets protocol codec
 pub struct Codec {
     max_size: usize,
+    max_message_size: Option<usize>,
     server: bool,
+    aggregate: bool,
+    continuation: Option<Continuation>,
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    deflate: Option<DeflateContext>,
 }
 
 impl Codec {
@@ -49,7 +83,12 @@ impl Codec {
     pub fn new() -> Codec {
         Codec {
             max_size: 65_536,
+            max_message_size: None,
             server: true,
+            aggregate: false,
+            continuation: None,
+            #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+            deflate: None,
         }
     }
 
@@ -61,6 +100,38 @@ impl Codec {
         self
     }
 
+    /// Set max size of a fragmented or decompressed message
+    ///
+    /// By default it is equal to max frame size
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = Some(size);
+        self
+    }
+
+    /// Aggregate continuation frames into complete text and binary frames.
+    ///
+    /// By default fragments are returned as `Frame::Continuation`.
+    /// Compressed fragmented messages are always aggregated.
+    pub fn aggregate_continuations(mut self, aggregate: bool) -> Self {
+        self.aggregate = aggregate;
+        self
+    }
+
+    /// Check if `permessage-deflate` is negotiated
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    fn is_deflate(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    #[cfg(not(any(feature = "flate2-zlib", feature = "flate2-rust")))]
+    fn is_deflate(&self) -> bool {
+        false
+    }
+
+    fn message_size(&self) -> usize {
+        self.max_message_size.unwrap_or(self.max_size)
+    }
+
     /// Set decoder to client mode.
     ///
     /// By default decoder works in server mode.
@@ -68,6 +139,131 @@ impl Codec {
         self.server = false;
         self
     }
+
+    /// Enable `permessage-deflate` compression with negotiated parameters.
+    ///
+    /// Text and binary messages are compressed, compressed frames are
+    /// decompressed up to max frame size. Has to be called after
+    /// `client_mode()`.
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    pub fn deflate(mut self, params: DeflateParams) -> Self {
+        self.deflate = Some(DeflateContext::new(params, self.server));
+        self
+    }
+
+    /// Write data message, compressed if deflate is enabled
+    fn write_data(
+        &mut self,
+        dst: &mut BytesMut,
+        data: Bytes,
+        op: OpCode,
+    ) -> Result<(), ProtocolError> {
+        #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+        {
+            if let Some(ref mut deflate) = self.deflate {
+                let data = deflate.compress(&data)?;
+                let pos = dst.len();
+                Parser::write_message(dst, data, op, true, !self.server);
+                // RSV1 bit marks compressed message
+                dst[pos] |= 0x40;
+                return Ok(());
+            }
+        }
+
+        Parser::write_message(dst, data, op, true, !self.server);
+        Ok(())
+    }
+
+    /// Decompress data frame payload if RSV1 bit is set
+    #[allow(unused_variables)]
+    fn read_data(
+        &mut self,
+        payload: Option<BytesMut>,
+        compressed: bool,
+    ) -> Result<Option<BytesMut>, ProtocolError> {
+        #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+        {
+            if compressed {
+                let max_size = self.message_size();
+                if let Some(ref mut deflate) = self.deflate {
+                    let data = payload.as_ref().map(|pl| &pl[..]).unwrap_or(&[]);
+                    return deflate.decompress(data, max_size).map(Some);
+                }
+            }
+        }
+
+        Ok(payload)
+    }
+
+    /// Handle fragment of a data message, returns frame if message
+    /// is complete or fragment has to be returned as is.
+    fn read_fragment(
+        &mut self,
+        opcode: OpCode,
+        finished: bool,
+        payload: Option<BytesMut>,
+        compressed: bool,
+    ) -> Result<Option<Frame>, ProtocolError> {
+        let payload = payload.unwrap_or_else(BytesMut::new);
+        let max_size = self.message_size();
+
+        let mut cont = match (opcode, self.continuation.take()) {
+            (OpCode::Continue, Some(cont)) => cont,
+            (OpCode::Continue, None) => {
+                return Err(ProtocolError::ContinuationNotStarted)
+            }
+            (_, Some(_)) => return Err(ProtocolError::ContinuationStarted),
+            (opcode, None) => {
+                let aggregate = self.aggregate || compressed;
+                Continuation {
+                    opcode,
+                    compressed,
+                    size: 0,
+                    buf: if aggregate {
+                        Some(BytesMut::new())
+                    } else {
+                        None
+                    },
+                }
+            }
+        };
+
+        cont.size += payload.len();
+        if cont.size > max_size {
+            return Err(ProtocolError::Overflow);
+        }
+
+        if let Some(mut buf) = cont.buf.take() {
+            buf.extend_from_slice(&payload);
+            if !finished {
+                cont.buf = Some(buf);
+                self.continuation = Some(cont);
+                return Ok(None);
+            }
+
+            let data = self.read_data(Some(buf), cont.compressed)?;
+            return Ok(Some(if cont.opcode == OpCode::Text {
+                Frame::Text(data)
+            } else {
+                Frame::Binary(data)
+            }));
+        }
+
+        let payload = payload.freeze();
+        let item = if finished {
+            Item::Last(payload)
+        } else if opcode == OpCode::Text {
+            Item::FirstText(payload)
+        } else if opcode == OpCode::Binary {
+            Item::FirstBinary(payload)
+        } else {
+            Item::Continue(payload)
+        };
+        if !finished {
+            self.continuation = Some(cont);
+        }
+        Ok(Some(Frame::Continuation(item)))
+    }
 }
 
 impl Encoder for Codec {
@@ -76,12 +272,8 @@ impl Encoder for Codec {
 
     fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Text(txt) => {
-                Parser::write_message(dst, txt, OpCode::Text, true, !self.server)
-            }
-            Message::Binary(bin) => {
-                Parser::write_message(dst, bin, OpCode::Binary, true, !self.server)
-            }
+            Message::Text(txt) => self.write_data(dst, txt.into(), OpCode::Text)?,
+            Message::Binary(bin) => self.write_data(dst, bin, OpCode::Binary)?,
             Message::Ping(txt) => {
                 Parser::write_message(dst, txt, OpCode::Ping, true, !self.server)
             }
@@ -89,6 +281,15 @@ impl Encoder for Codec {
                 Parser::write_message(dst, txt, OpCode::Pong, true, !self.server)
             }
             Message::Close(reason) => Parser::write_close(dst, reason, !self.server),
+            Message::Continuation(item) => {
+                let (data, op, fin) = match item {
+                    Item::FirstText(data) => (data, OpCode::Text, false),
+                    Item::FirstBinary(data) => (data, OpCode::Binary, false),
+                    Item::Continue(data) => (data, OpCode::Continue, false),
+                    Item::Last(data) => (data, OpCode::Continue, true),
+                };
+                Parser::write_message(dst, data, op, fin, !self.server)
+            }
             Message::Nop => (),
         }
         Ok(())
@@ -100,51 +301,195 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.server, self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
-                // continuation is not supported
-                if !finished {
+        loop {
+            // RSV bits of the first frame in buffer
+            let rsv = if src.is_empty() { 0 } else { src[0] & 0x70 };
+            let compressed = rsv & 0x40 != 0;
+
+            let (finished, opcode, payload) =
+                match Parser::parse(src, self.server, self.max_size)? {
+                    Some(frame) => frame,
+                    None => return Ok(None),
+                };
+
+            // RSV1 is allowed only for first frame of data message
+            // if compression is negotiated
+            if rsv & 0x30 != 0
+                || (compressed
+                    && !(self.is_deflate()
+                        && (opcode == OpCode::Text || opcode == OpCode::Binary)))
+            {
+                return Err(ProtocolError::ReservedBits);
+            }
+
+            let frame = match opcode {
+                OpCode::Bad => return Err(ProtocolError::BadOpCode),
+                // control frames can not be fragmented
+                OpCode::Close | OpCode::Ping | OpCode::Pong if !finished => {
                     return Err(ProtocolError::NoContinuation);
                 }
-
-                match opcode {
-                    OpCode::Continue => Err(ProtocolError::NoContinuation),
-                    OpCode::Bad => Err(ProtocolError::BadOpCode),
-                    OpCode::Close => {
-                        if let Some(ref pl) = payload {
-                            let close_reason = Parser::parse_close_payload(pl);
-                            Ok(Some(Frame::Close(close_reason)))
-                        } else {
-                            Ok(Some(Frame::Close(None)))
-                        }
-                    }
-                    OpCode::Ping => {
-                        if let Some(ref pl) = payload {
-                            Ok(Some(Frame::Ping(String::from_utf8_lossy(pl).into())))
-                        } else {
-                            Ok(Some(Frame::Ping(String::new())))
-                        }
+                OpCode::Close => {
+                    if let Some(ref pl) = payload {
+                        let close_reason = Parser::parse_close_payload(pl);
+                        Frame::Close(close_reason)
+                    } else {
+                        Frame::Close(None)
                     }
-                    OpCode::Pong => {
-                        if let Some(ref pl) = payload {
-                            Ok(Some(Frame::Pong(String::from_utf8_lossy(pl).into())))
-                        } else {
-                            Ok(Some(Frame::Pong(String::new())))
-                        }
+                }
+                OpCode::Ping => {
+                    if let Some(ref pl) = payload {
+                        Frame::Ping(String::from_utf8_lossy(pl).into())
+                    } else {
+                        Frame::Ping(String::new())
                     }
-                    OpCode::Binary => Ok(Some(Frame::Binary(payload))),
-                    OpCode::Text => {
-                        Ok(Some(Frame::Text(payload)))
-                        //let tmp = Vec::from(payload.as_ref());
-                        //match String::from_utf8(tmp) {
-                        //    Ok(s) => Ok(Some(Message::Text(s))),
-                        //    Err(_) => Err(ProtocolError::BadEncoding),
-                        //}
+                }
+                OpCode::Pong => {
+                    if let Some(ref pl) = payload {
+                        Frame::Pong(String::from_utf8_lossy(pl).into())
+                    } else {
+                        Frame::Pong(String::new())
                     }
                 }
-            }
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
+                OpCode::Binary if finished && self.continuation.is_none() => {
+                    Frame::Binary(self.read_data(payload, compressed)?)
+                }
+                OpCode::Text if finished && self.continuation.is_none() => {
+                    Frame::Text(self.read_data(payload, compressed)?)
+                }
+                _ => match self.read_fragment(opcode, finished, payload, compressed)? {
+                    Some(frame) => frame,
+                    // fragment is buffered, parse next frame
+                    None => continue,
+                },
+            };
+            return Ok(Some(frame));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragments(frames: &[(&'static str, OpCode, bool)]) -> BytesMut {
+        let mut buf = BytesMut::new();
+        for (data, op, fin) in frames {
+            Parser::write_message(&mut buf, *data, *op, *fin, true);
         }
+        buf
+    }
+
+    #[test]
+    fn test_continuation_aggregate() {
+        let mut codec = Codec::new().aggregate_continuations(true);
+        let mut buf = fragments(&[
+            ("hello ", OpCode::Text, false),
+            ("", OpCode::Ping, true),
+            ("wor", OpCode::Continue, false),
+            ("ld", OpCode::Continue, true),
+        ]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Ping(String::new()))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Some(BytesMut::from("hello world"))))
+        );
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+
+        let mut codec = Codec::new()
+            .aggregate_continuations(true)
+            .max_message_size(8);
+        let mut buf = fragments(&[
+            ("hello ", OpCode::Binary, false),
+            ("world", OpCode::Continue, true),
+        ]);
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::Overflow) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_reserved_bits() {
+        let mut codec = Codec::new();
+        let mut buf = fragments(&[("data", OpCode::Text, true)]);
+        buf[0] |= 0x40;
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::ReservedBits) => (),
+            _ => panic!(),
+        }
+
+        let mut buf = fragments(&[("data", OpCode::Binary, true)]);
+        buf[0] |= 0x20;
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::ReservedBits) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_continuation_fragments() {
+        let mut codec = Codec::new();
+        let mut buf = fragments(&[
+            ("a", OpCode::Binary, false),
+            ("b", OpCode::Continue, false),
+            ("c", OpCode::Continue, true),
+        ]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::FirstBinary(Bytes::from_static(
+                b"a"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Continue(Bytes::from_static(
+                b"b"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"c"))))
+        );
+
+        let mut buf = fragments(&[("a", OpCode::Continue, true)]);
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::ContinuationNotStarted) => (),
+            _ => panic!(),
+        }
+
+        let mut buf =
+            fragments(&[("a", OpCode::Text, false), ("b", OpCode::Text, true)]);
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::ContinuationStarted) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_encode_continuation() {
+        let mut codec = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Message::Continuation(Item::FirstText(Bytes::from_static(b"a"))),
+                &mut buf,
+            )
+            .unwrap();
+        codec
+            .encode(
+                Message::Continuation(Item::Last(Bytes::from_static(b"b"))),
+                &mut buf,
+            )
+            .unwrap();
+
+        let mut codec = Codec::new().aggregate_continuations(true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Some(BytesMut::from("ab"))))
+        );
     }
 }
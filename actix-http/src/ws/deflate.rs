@@ -0,0 +1,426 @@
+//! `permessage-deflate` extension, RFC 7692
+use std::io;
+
+use bytes::BytesMut;
+use flate2::{
+    Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status,
+};
+use http::header::{HeaderValue, SEC_WEBSOCKET_EXTENSIONS};
+
+use crate::message::RequestHead;
+
+use super::ProtocolError;
+
+/// Sync flush marker, removed from the end of compressed messages
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Server side `permessage-deflate` configuration.
+///
+/// Compression backend always uses 15 bits window, client offers that
+/// limit `server_max_window_bits` are declined.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    client_max_window_bits: Option<u8>,
+}
+
+impl DeflateConfig {
+    /// Reset compression context after every message.
+    ///
+    /// Reduces memory usage of idle connections at the cost of
+    /// compression ratio. Enabled anyway if client requests it.
+    pub fn server_no_context_takeover(mut self) -> Self {
+        self.server_no_context_takeover = true;
+        self
+    }
+
+    /// Ask client to reset its compression context after every message.
+    pub fn client_no_context_takeover(mut self) -> Self {
+        self.client_no_context_takeover = true;
+        self
+    }
+
+    /// Ask client to limit LZ77 window size, if client supports it.
+    ///
+    /// Value has to be between 8 and 15.
+    pub fn client_max_window_bits(mut self, bits: u8) -> Self {
+        assert!(
+            bits >= 8 && bits <= 15,
+            "Window bits must be between 8 and 15"
+        );
+        self.client_max_window_bits = Some(bits);
+        self
+    }
+
+    /// Select first acceptable `permessage-deflate` offer from
+    /// `Sec-WebSocket-Extensions` request headers.
+    pub fn negotiate(&self, req: &RequestHead) -> Option<DeflateParams> {
+        req.headers()
+            .get_all(SEC_WEBSOCKET_EXTENSIONS)
+            .filter_map(|hdr| hdr.to_str().ok())
+            .flat_map(|hdr| hdr.split(','))
+            .filter_map(|offer| self.accept(offer))
+            .next()
+    }
+
+    fn accept(&self, offer: &str) -> Option<DeflateParams> {
+        let mut params = offer.split(';').map(|p| p.trim());
+        if params.next() != Some("permessage-deflate") {
+            return None;
+        }
+
+        let mut server_no_context_takeover = false;
+        let mut client_window_bits = false;
+        let mut seen = Vec::new();
+
+        for param in params {
+            let mut kv = param.splitn(2, '=');
+            let name = kv.next().unwrap_or("").trim();
+            let value = kv.next().map(|v| v.trim().trim_matches('"'));
+
+            // duplicated parameters make offer invalid
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover", None) => {
+                    server_no_context_takeover = true
+                }
+                ("client_no_context_takeover", None) => (),
+                ("server_max_window_bits", Some(bits)) => {
+                    // window size of the compressor is fixed
+                    if window_bits(bits)? != 15 {
+                        return None;
+                    }
+                }
+                ("client_max_window_bits", None) => client_window_bits = true,
+                ("client_max_window_bits", Some(bits)) => {
+                    window_bits(bits)?;
+                    client_window_bits = true;
+                }
+                _ => return None,
+            }
+        }
+
+        Some(DeflateParams {
+            server_no_context_takeover: server_no_context_takeover
+                || self.server_no_context_takeover,
+            client_no_context_takeover: self.client_no_context_takeover,
+            client_max_window_bits: if client_window_bits {
+                self.client_max_window_bits
+            } else {
+                None
+            },
+        })
+    }
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    match value.parse::<u8>() {
+        Ok(bits) if bits >= 8 && bits <= 15 => Some(bits),
+        _ => None,
+    }
+}
+
+/// Negotiated `permessage-deflate` parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeflateParams {
+    /// Server resets compression context after every message
+    pub server_no_context_takeover: bool,
+    /// Client resets compression context after every message
+    pub client_no_context_takeover: bool,
+    /// LZ77 window size limit of client compressor
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// `Sec-WebSocket-Extensions` response header value
+    pub fn header_value(&self) -> HeaderValue {
+        let mut value = "permessage-deflate".to_string();
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = self.client_max_window_bits {
+            value.push_str(&format!("; client_max_window_bits={}", bits));
+        }
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+/// Compression state of the codec.
+///
+/// Compressor and decompressor are allocated on first use, cloned
+/// context starts with empty state.
+#[derive(Debug)]
+pub(crate) struct DeflateContext {
+    params: DeflateParams,
+    server: bool,
+    compress: Option<Compress>,
+    decompress: Option<Decompress>,
+}
+
+impl Clone for DeflateContext {
+    fn clone(&self) -> Self {
+        DeflateContext::new(self.params, self.server)
+    }
+}
+
+impl DeflateContext {
+    pub(crate) fn new(params: DeflateParams, server: bool) -> Self {
+        DeflateContext {
+            params,
+            server,
+            compress: None,
+            decompress: None,
+        }
+    }
+
+    fn reset_compress(&self) -> bool {
+        if self.server {
+            self.params.server_no_context_takeover
+        } else {
+            self.params.client_no_context_takeover
+        }
+    }
+
+    fn reset_decompress(&self) -> bool {
+        if self.server {
+            self.params.client_no_context_takeover
+        } else {
+            self.params.server_no_context_takeover
+        }
+    }
+
+    /// Compress message payload
+    pub(crate) fn compress(&mut self, data: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let reset = self.reset_compress();
+        let compress = self
+            .compress
+            .get_or_insert_with(|| Compress::new(Compression::default(), false));
+
+        let mut out = Vec::with_capacity(data.len() + 16);
+        let start = compress.total_in();
+        loop {
+            let consumed = (compress.total_in() - start) as usize;
+            compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+            // flush is complete if output buffer is not full
+            let consumed = (compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+            out.reserve(std::cmp::max(64, data.len() / 2));
+        }
+
+        if out.ends_with(&TAIL) {
+            out.truncate(out.len() - TAIL.len());
+        }
+        if reset {
+            compress.reset();
+        }
+        Ok(out)
+    }
+
+    /// Decompress message payload, decompressed size is limited
+    /// to `max_size`.
+    pub(crate) fn decompress(
+        &mut self,
+        data: &[u8],
+        max_size: usize,
+    ) -> Result<BytesMut, ProtocolError> {
+        let reset = self.reset_decompress();
+        let decompress = self
+            .decompress
+            .get_or_insert_with(|| Decompress::new(false));
+
+        let mut input = Vec::with_capacity(data.len() + TAIL.len());
+        input.extend_from_slice(data);
+        input.extend_from_slice(&TAIL);
+
+        let mut out =
+            Vec::with_capacity(std::cmp::min(data.len() * 2 + 64, max_size + 1));
+        let start = decompress.total_in();
+        loop {
+            let consumed = (decompress.total_in() - start) as usize;
+            let status = decompress
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            if out.len() > max_size {
+                return Err(ProtocolError::Overflow);
+            }
+
+            let consumed = (decompress.total_in() - start) as usize;
+            if status == Status::StreamEnd
+                || (consumed == input.len() && out.len() < out.capacity())
+            {
+                break;
+            }
+            out.reserve(std::cmp::max(64, data.len()));
+        }
+
+        if reset {
+            decompress.reset(false);
+        }
+        Ok(BytesMut::from(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_codec::{Decoder, Encoder};
+
+    use super::*;
+    use crate::test::TestRequest;
+    use crate::ws::{Codec, Frame, Message};
+
+    fn offer(hdr: &'static str) -> Option<DeflateParams> {
+        let req = TestRequest::default()
+            .header(SEC_WEBSOCKET_EXTENSIONS, hdr)
+            .finish();
+        DeflateConfig::default().negotiate(req.head())
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let params = offer("permessage-deflate").unwrap();
+        assert_eq!(params.header_value(), "permessage-deflate");
+
+        let params = offer(
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits",
+        )
+        .unwrap();
+        assert!(params.server_no_context_takeover);
+        assert_eq!(params.client_max_window_bits, None);
+
+        // first acceptable offer is selected
+        let params = offer(
+            "permessage-deflate; server_max_window_bits=10, \
+             permessage-deflate; server_max_window_bits=15",
+        )
+        .unwrap();
+        assert!(!params.server_no_context_takeover);
+
+        assert!(offer("x-webkit-deflate-frame").is_none());
+        assert!(offer("permessage-deflate; server_max_window_bits=10").is_none());
+        assert!(offer("permessage-deflate; client_max_window_bits=16").is_none());
+        assert!(offer("permessage-deflate; unknown").is_none());
+        assert!(offer(
+            "permessage-deflate; server_no_context_takeover; server_no_context_takeover"
+        )
+        .is_none());
+
+        let req = TestRequest::default()
+            .header(
+                SEC_WEBSOCKET_EXTENSIONS,
+                "permessage-deflate; client_max_window_bits",
+            )
+            .finish();
+        let config = DeflateConfig::default()
+            .client_no_context_takeover()
+            .client_max_window_bits(10);
+        let params = config.negotiate(req.head()).unwrap();
+        assert_eq!(
+            params.header_value(),
+            "permessage-deflate; client_no_context_takeover; client_max_window_bits=10"
+        );
+    }
+
+    #[test]
+    fn test_compress() {
+        let params = offer("permessage-deflate").unwrap();
+        let mut server = DeflateContext::new(params, true);
+        let mut client = DeflateContext::new(params, false);
+
+        let data: Vec<u8> = b"{\"message\": \"hello\"}"
+            .iter()
+            .cycle()
+            .take(2000)
+            .cloned()
+            .collect();
+        let compressed = server.compress(&data).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(
+            &client.decompress(&compressed, 65_536).unwrap()[..],
+            &data[..]
+        );
+
+        // context takeover, second message refers to the first one
+        let compressed2 = server.compress(&data).unwrap();
+        assert!(compressed2.len() < compressed.len());
+        assert_eq!(
+            &client.decompress(&compressed2, 65_536).unwrap()[..],
+            &data[..]
+        );
+
+        match client.decompress(&server.compress(&data).unwrap(), 100) {
+            Err(ProtocolError::Overflow) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_no_context_takeover() {
+        let params = offer("permessage-deflate; server_no_context_takeover").unwrap();
+        let mut server = DeflateContext::new(params, true);
+        let mut client = DeflateContext::new(params, false);
+
+        let data = b"hello world, hello world".to_vec();
+        let compressed = server.compress(&data).unwrap();
+        assert_eq!(server.compress(&data).unwrap(), compressed);
+
+        // fresh decompressor handles every message
+        assert_eq!(
+            &client.decompress(&compressed, 1024).unwrap()[..],
+            &data[..]
+        );
+        let mut client = DeflateContext::new(params, false);
+        assert_eq!(
+            &client.decompress(&compressed, 1024).unwrap()[..],
+            &data[..]
+        );
+    }
+
+    #[test]
+    fn test_codec() {
+        let params = offer("permessage-deflate").unwrap();
+        let mut server = Codec::new().deflate(params);
+        let mut client = Codec::new().client_mode().deflate(params);
+
+        let mut buf = BytesMut::new();
+        server
+            .encode(Message::Text("hello hello hello".to_string()), &mut buf)
+            .unwrap();
+        assert_eq!(buf[0] & 0x40, 0x40);
+        server
+            .encode(Message::Ping("ping".to_string()), &mut buf)
+            .unwrap();
+
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Some(BytesMut::from("hello hello hello"))))
+        );
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Ping("ping".to_string()))
+        );
+
+        // uncompressed frames are accepted
+        let mut buf = BytesMut::new();
+        Codec::new()
+            .encode(Message::Binary("data".into()), &mut buf)
+            .unwrap();
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Some(BytesMut::from("data"))))
+        );
+    }
+}
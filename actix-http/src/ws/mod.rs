@@ -13,12 +13,16 @@ use crate::message::RequestHead;
 use crate::response::{Response, ResponseBuilder};
 
 mod codec;
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+mod deflate;
 mod frame;
 mod mask;
 mod proto;
 mod transport;
 
-pub use self::codec::{Codec, Frame, Message};
+pub use self::codec::{Codec, Frame, Item, Message};
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+pub use self::deflate::{DeflateConfig, DeflateParams};
 pub use self::frame::Parser;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
 pub use self::transport::Transport;
@@ -47,9 +51,18 @@ pub enum ProtocolError {
     /// Continuation is not supported
     #[display(fmt = "Continuation is not supported.")]
     NoContinuation,
+    /// Received continuation frame without initial frame
+    #[display(fmt = "Continuation frame without initial frame.")]
+    ContinuationNotStarted,
+    /// Received new data frame while continuation is in progress
+    #[display(fmt = "Continuation is already started.")]
+    ContinuationStarted,
     /// Bad utf-8 encoding
     #[display(fmt = "Bad utf-8 encoding.")]
     BadEncoding,
+    /// Reserved bits are set without negotiated extension
+    #[display(fmt = "Reserved bits are set without negotiated extension.")]
+    ReservedBits,
     /// Io error
     #[display(fmt = "io error: {}", _0)]
     Io(io::Error),
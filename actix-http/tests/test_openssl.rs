@@ -1,5 +1,6 @@
 #![cfg(feature = "openssl")]
 use std::io;
+use std::task::{Context, Poll};
 
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_http_test::{block_on, TestServer};
@@ -16,6 +17,7 @@ use actix_http::error::{ErrorBadRequest, PayloadError};
 use actix_http::http::header::{self, HeaderName, HeaderValue};
 use actix_http::http::{Method, StatusCode, Version};
 use actix_http::httpmessage::HttpMessage;
+use actix_http::body::{BodySize, MessageBody};
 use actix_http::{body, Error, HttpService, Request, Response};
 
 async fn load_body<S>(stream: S) -> Result<BytesMut, PayloadError>
@@ -543,3 +545,133 @@ fn test_h2_on_connect() {
         assert!(response.status().is_success());
     })
 }
+
+#[test]
+fn test_h2_header_limits() {
+    block_on(async {
+        let openssl = ssl_acceptor().unwrap();
+
+        let srv = TestServer::start(move || {
+            pipeline_factory(
+                openssl
+                    .clone()
+                    .map_err(|e| println!("Openssl error: {}", e)),
+            )
+            .and_then(
+                HttpService::build()
+                    .header_limits(actix_http::HeaderLimits {
+                        max_headers: 8,
+                        max_header_size: 64,
+                        max_head_size: 1024,
+                        max_uri_length: 32,
+                    })
+                    .h2(|_| ok::<_, ()>(Response::Ok().finish()))
+                    .map_err(|_| ()),
+            )
+        });
+
+        let response = srv.sget("/").send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let mut req = srv.sget("/");
+        for idx in 0..10 {
+            req = req.header(format!("x-header-{}", idx).as_str(), "value");
+        }
+        let response = req.send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+        let response = srv
+            .sget("/")
+            .header("x-large", "v".repeat(64))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+        let response = srv.sget(&format!("/{}", "a".repeat(32))).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+    })
+}
+
+/// Streaming body with optional size hint and trailers
+struct TrailersBody {
+    chunk: Option<Bytes>,
+    hint: Option<u64>,
+}
+
+impl MessageBody for TrailersBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match self.hint {
+            Some(len) => (len, Some(len)),
+            None => (0, None),
+        }
+    }
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        Poll::Ready(self.chunk.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        &mut self,
+        _: &mut Context,
+    ) -> Poll<Result<Option<actix_http::http::HeaderMap>, Error>> {
+        let mut trailers = actix_http::http::HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+        // connection specific field is rejected by h2
+        trailers.insert(
+            header::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+        Poll::Ready(Ok(Some(trailers)))
+    }
+}
+
+#[test]
+fn test_h2_trailers() {
+    block_on(async {
+        let openssl = ssl_acceptor().unwrap();
+
+        let mut srv = TestServer::start(move || {
+            pipeline_factory(
+                openssl
+                    .clone()
+                    .map_err(|e| println!("Openssl error: {}", e)),
+            )
+            .and_then(
+                HttpService::build()
+                    .h2(|req: Request| {
+                        let hint = if req.path() == "/hint" {
+                            Some(10)
+                        } else {
+                            None
+                        };
+                        ok::<_, ()>(Response::Ok().message_body(TrailersBody {
+                            chunk: Some(Bytes::from_static(b"test")),
+                            hint,
+                        }))
+                    })
+                    .map_err(|_| ()),
+            )
+        });
+
+        let response = srv.sget("/").send().await.unwrap();
+        assert!(response.status().is_success());
+        let body = srv.load_body(response).await.unwrap();
+        assert_eq!(&body[..], b"test");
+
+        // body is shorter than size hint, stream is reset
+        let response = srv.sget("/hint").send().await.unwrap();
+        assert_eq!(
+            response.headers().get(header::CONTENT_LENGTH).unwrap(),
+            "10"
+        );
+        assert!(srv.load_body(response).await.is_err());
+    })
+}
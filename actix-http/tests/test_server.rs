@@ -1,4 +1,5 @@
 use std::io::{Read, Write};
+use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{net, thread};
 
@@ -11,9 +12,11 @@ use futures::stream::{once, StreamExt};
 use regex::Regex;
 use tokio_timer::delay_for;
 
+use actix_http::body::{BodySize, MessageBody};
 use actix_http::httpmessage::HttpMessage;
 use actix_http::{
-    body, error, http, http::header, Error, HttpService, KeepAlive, Request, Response,
+    body, error, http, http::header, Error, HeaderCase, HttpService, KeepAlive,
+    PayloadDrain, Request, Response,
 };
 
 #[test]
@@ -227,6 +230,50 @@ fn test_http1_keepalive() {
     })
 }
 
+#[test]
+fn test_http1_payload_drain() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::build().h1(|mut req: Request| {
+                // respond without reading the payload
+                let pl = req.take_payload();
+                if req.head().uri.path() == "/drain" {
+                    pl.set_drain(PayloadDrain::Limit(64));
+                }
+                future::ok::<_, ()>(Response::Ok().finish())
+            })
+        });
+
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(
+            b"POST /drain HTTP/1.1\r\ncontent-length: 10\r\n\r\n0123456789\
+              GET /test HTTP/1.1\r\nconnection: close\r\n\r\n",
+        );
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert_eq!(data.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+
+        // payload is already read, connection is kept alive
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(
+            b"POST /close HTTP/1.1\r\ncontent-length: 10\r\n\r\n0123456789\
+              GET /test HTTP/1.1\r\nconnection: close\r\n\r\n",
+        );
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert_eq!(data.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+
+        // rest of the payload is not received, connection is closed
+        // after first response
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ =
+            stream.write_all(b"POST /close HTTP/1.1\r\ncontent-length: 10\r\n\r\n01234");
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert_eq!(data.matches("HTTP/1.1 200 OK\r\n").count(), 1);
+    })
+}
+
 #[test]
 fn test_http1_keepalive_timeout() {
     block_on(async {
@@ -672,3 +719,88 @@ fn test_h1_on_connect() {
         assert!(response.status().is_success());
     })
 }
+
+/// Streaming body with optional size hint and trailers
+struct TrailersBody {
+    chunk: Option<Bytes>,
+    hint: Option<u64>,
+}
+
+impl TrailersBody {
+    fn new(hint: Option<u64>) -> Self {
+        TrailersBody {
+            chunk: Some(Bytes::from_static(b"test")),
+            hint,
+        }
+    }
+}
+
+impl MessageBody for TrailersBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match self.hint {
+            Some(len) => (len, Some(len)),
+            None => (0, None),
+        }
+    }
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        Poll::Ready(self.chunk.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        &mut self,
+        _: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert(
+            header::HeaderName::from_static("grpc-status"),
+            header::HeaderValue::from_static("0"),
+        );
+        trailers.insert(
+            header::CONTENT_LENGTH,
+            header::HeaderValue::from_static("4"),
+        );
+        Poll::Ready(Ok(Some(trailers)))
+    }
+}
+
+#[test]
+fn test_h1_trailers() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::build()
+                .header_case(HeaderCase::Camel)
+                .h1(|req: Request| {
+                    let hint = if req.path() == "/hint" {
+                        Some(10)
+                    } else {
+                        None
+                    };
+                    future::ok::<_, ()>(
+                        Response::Ok().message_body(TrailersBody::new(hint)),
+                    )
+                })
+        });
+
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert!(data.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(data.ends_with("\r\n4\r\ntest\r\n0\r\nGrpc-Status: 0\r\n\r\n"));
+
+        // body is shorter than size hint, connection is closed before
+        // next pipelined request
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(b"GET /hint HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert!(data.contains("Content-Length: 10\r\n"));
+        assert!(data.ends_with("\r\n\r\ntest"));
+        assert_eq!(data.matches("HTTP/1.1 200 OK").count(), 1);
+    })
+}
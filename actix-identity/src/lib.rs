@@ -58,9 +58,10 @@ use serde::{Deserialize, Serialize};
 use time::Duration;
 
 use actix_web::cookie::{Cookie, CookieJar, Key, SameSite};
-use actix_web::dev::{Extensions, Payload, ServiceRequest, ServiceResponse};
+use actix_web::dev::{clock, Extensions, Payload, ServiceRequest, ServiceResponse};
 use actix_web::error::{Error, Result};
 use actix_web::http::header::{self, HeaderValue};
+use actix_web::test::TestRequest;
 use actix_web::{FromRequest, HttpMessage, HttpRequest};
 
 /// The extractor type to obtain your identity from a request.
@@ -147,6 +148,36 @@ where
     }
 }
 
+/// Helper trait that allows to set identity of `TestRequest`.
+///
+/// Request is handled as if identity middleware has loaded `identity`
+/// from the request.
+///
+/// ```rust
+/// use actix_identity::{RequestIdentity, TestIdentity};
+/// use actix_web::test;
+///
+/// # fn main() {
+/// let req = test::TestRequest::default()
+///     .identity("alice")
+///     .to_http_request();
+/// assert_eq!(req.get_identity(), Some("alice".to_owned()));
+/// # }
+/// ```
+pub trait TestIdentity {
+    /// Set request identity.
+    fn identity<T: Into<String>>(self, identity: T) -> Self;
+}
+
+impl TestIdentity for TestRequest {
+    fn identity<T: Into<String>>(self, identity: T) -> Self {
+        let id = Some(identity.into());
+        self.extensions(move |extensions| {
+            extensions.insert(IdentityItem { id, changed: false })
+        })
+    }
+}
+
 /// Extractor implementation for Identity type.
 ///
 /// ```rust
@@ -414,7 +445,7 @@ impl CookieIdentityInner {
 
     fn parse(&self, cookie: Cookie) -> Option<CookieValue> {
         let value: CookieValue = serde_json::from_str(cookie.value()).ok()?;
-        let now = SystemTime::now();
+        let now = clock::system_now();
         if let Some(visit_deadline) = self.visit_deadline {
             if now.duration_since(value.visit_timestamp?).ok()?
                 > visit_deadline.to_std().ok()?
@@ -567,7 +598,7 @@ impl IdentityPolicy for CookieIdentityPolicy {
         res: &mut ServiceResponse<B>,
     ) -> Self::ResponseFuture {
         let _ = if changed {
-            let login_timestamp = SystemTime::now();
+            let login_timestamp = clock::system_now();
             self.0.set_cookie(
                 res,
                 id.map(|identity| CookieValue {
@@ -577,7 +608,7 @@ impl IdentityPolicy for CookieIdentityPolicy {
                 }),
             )
         } else if self.0.always_update_cookie() && id.is_some() {
-            let visit_timestamp = SystemTime::now();
+            let visit_timestamp = clock::system_now();
             let login_timestamp = if self.0.requires_oob_data() {
                 let CookieIdentityExtention {
                     login_timestamp: lt,
@@ -614,6 +645,20 @@ mod tests {
     const COOKIE_NAME: &'static str = "actix_auth";
     const COOKIE_LOGIN: &'static str = "test";
 
+    #[test]
+    fn test_identity_test_request() {
+        block_on(async {
+            let (req, mut payload) = TestRequest::default()
+                .identity(COOKIE_LOGIN)
+                .to_http_parts();
+            let id = Identity::from_request(&req, &mut payload).await.unwrap();
+            assert_eq!(id.identity(), Some(COOKIE_LOGIN.to_owned()));
+
+            id.forget();
+            assert!(req.get_identity().is_none());
+        })
+    }
+
     #[test]
     fn test_identity() {
         block_on(async {
@@ -1104,4 +1149,34 @@ mod tests {
             assert_logged_in(resp, Some(COOKIE_LOGIN)).await;
         })
     }
+
+    #[test]
+    fn test_identity_cookie_expires_with_mock_clock() {
+        block_on(async {
+            let clock = test::MockClock::new();
+            let mut srv =
+                create_identity_server(|c| c.visit_deadline(Duration::days(90))).await;
+            let cookie = login_cookie(COOKIE_LOGIN, None, Some(clock::system_now()));
+
+            clock.advance(Duration::days(89).to_std().unwrap());
+            let resp = test::call_service(
+                &mut srv,
+                TestRequest::with_uri("/")
+                    .cookie(cookie.clone())
+                    .to_request(),
+            )
+            .await;
+            assert_logged_in(resp, Some(COOKIE_LOGIN)).await;
+
+            clock.advance(Duration::days(2).to_std().unwrap());
+            let resp = test::call_service(
+                &mut srv,
+                TestRequest::with_uri("/")
+                    .cookie(cookie.clone())
+                    .to_request(),
+            )
+            .await;
+            assert_logged_in(resp, None).await;
+        })
+    }
 }
@@ -1,4 +1,6 @@
 //! Error and Result module
+use std::io;
+
 use actix_web::error::{ParseError, PayloadError};
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
@@ -17,6 +19,9 @@ pub enum MultipartError {
     #[display(fmt = "Multipart boundary is not found")]
     Boundary,
     /// Nested multipart is not supported
+    ///
+    /// Not returned by the parser, nested fields are parsed with
+    /// `Field::into_multipart()`.
     #[display(fmt = "Nested multipart is not supported")]
     Nested,
     /// Multipart stream is incomplete
@@ -31,12 +36,31 @@ pub enum MultipartError {
     /// Not consumed
     #[display(fmt = "Multipart stream is not consumed")]
     NotConsumed,
+    /// Multipart stream has more fields than allowed
+    #[display(fmt = "Multipart stream has too many fields")]
+    FieldsLimit,
+    /// Field is larger than allowed
+    #[display(fmt = "Multipart field is too large")]
+    FieldSizeLimit,
+    /// Io error while saving field to disk
+    #[display(fmt = "{}", _0)]
+    Io(io::Error),
 }
 
-/// Return `BadRequest` for `MultipartError`
+/// Return `PayloadTooLarge` for size limit errors, `InternalServerError`
+/// for io errors and `BadRequest` for other `MultipartError`s
 impl ResponseError for MultipartError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::new(StatusCode::BAD_REQUEST)
+        match *self {
+            MultipartError::FieldSizeLimit
+            | MultipartError::Payload(PayloadError::Overflow) => {
+                HttpResponse::new(StatusCode::PAYLOAD_TOO_LARGE)
+            }
+            MultipartError::Io(_) => {
+                HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            _ => HttpResponse::new(StatusCode::BAD_REQUEST),
+        }
     }
 }
 
@@ -48,5 +72,15 @@ mod tests {
     fn test_multipart_error() {
         let resp: HttpResponse = MultipartError::Boundary.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp: HttpResponse = MultipartError::FieldsLimit.error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp: HttpResponse = MultipartError::FieldSizeLimit.error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let resp: HttpResponse =
+            MultipartError::Payload(PayloadError::Overflow).error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 }
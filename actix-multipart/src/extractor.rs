@@ -32,10 +32,83 @@ use crate::server::Multipart;
 impl FromRequest for Multipart {
     type Error = Error;
     type Future = Ready<Result<Multipart, Error>>;
-    type Config = ();
+    type Config = MultipartConfig;
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        ok(Multipart::new(req.headers(), payload.take()))
+        let config = req
+            .app_data::<MultipartConfig>()
+            .cloned()
+            .unwrap_or_default();
+        ok(Multipart::with_config(
+            req.headers(),
+            payload.take(),
+            config,
+        ))
+    }
+}
+
+/// Multipart extractor configuration
+///
+/// By default number and size of fields are not limited.
+///
+/// ```rust
+/// use actix_web::{web, App, Error, HttpResponse};
+/// use actix_multipart::Multipart;
+///
+/// async fn index(payload: Multipart) -> Result<HttpResponse, Error> {
+///     Ok(HttpResponse::Ok().into())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .data(Multipart::configure(|cfg| {
+///                 cfg.max_fields(8)
+///                     .max_field_size(1024)
+///                     .max_file_size(10 * 1024 * 1024)
+///                     .total_limit(16 * 1024 * 1024)
+///             }))
+///             .route(web::post().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MultipartConfig {
+    pub(crate) max_fields: Option<usize>,
+    pub(crate) max_field_size: Option<u64>,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) total_limit: Option<u64>,
+}
+
+impl MultipartConfig {
+    /// Set max number of fields, extra field is reported as
+    /// `MultipartError::FieldsLimit`. Parts of nested streams are counted
+    /// as fields.
+    pub fn max_fields(mut self, max: usize) -> Self {
+        self.max_fields = Some(max);
+        self
+    }
+
+    /// Set max size of a field without file name, larger field is reported
+    /// as `MultipartError::FieldSizeLimit`. Nested `multipart/*` field is
+    /// not limited, limits apply to its parts.
+    pub fn max_field_size(mut self, max: u64) -> Self {
+        self.max_field_size = Some(max);
+        self
+    }
+
+    /// Set max size of a file field, larger field is reported
+    /// as `MultipartError::FieldSizeLimit`.
+    pub fn max_file_size(mut self, max: u64) -> Self {
+        self.max_file_size = Some(max);
+        self
+    }
+
+    /// Set max size of the whole multipart payload, larger payload is reported
+    /// as `MultipartError::Payload(PayloadError::Overflow)`.
+    pub fn total_limit(mut self, limit: u64) -> Self {
+        self.total_limit = Some(limit);
+        self
     }
 }
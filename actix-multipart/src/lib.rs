@@ -3,6 +3,9 @@
 mod error;
 mod extractor;
 mod server;
+mod tempfile;
 
 pub use self::error::MultipartError;
+pub use self::extractor::MultipartConfig;
 pub use self::server::{Field, Multipart};
+pub use self::tempfile::TempFile;
@@ -4,7 +4,7 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::{cmp, fmt};
+use std::{cmp, fmt, io};
 
 use bytes::{Bytes, BytesMut};
 use futures::stream::{LocalBoxStream, Stream, StreamExt};
@@ -19,15 +19,15 @@ use actix_web::http::header::{
 use actix_web::http::HttpTryFrom;
 
 use crate::error::MultipartError;
+use crate::extractor::MultipartConfig;
 
 const MAX_HEADERS: usize = 32;
 
 /// The server-side implementation of `multipart/form-data` requests.
 ///
-/// This will parse the incoming stream into `MultipartItem` instances via its
-/// Stream implementation.
-/// `MultipartItem::Field` contains multipart field. `MultipartItem::Multipart`
-/// is used for nested multipart streams.
+/// This will parse the incoming stream into `Field` instances via its
+/// Stream implementation. Nested `multipart/mixed` field could be parsed
+/// with `Field::into_multipart()`.
 pub struct Multipart {
     safety: Safety,
     error: Option<MultipartError>,
@@ -56,11 +56,38 @@ struct InnerMultipart {
     boundary: String,
     state: InnerState,
     item: InnerMultipartItem,
+    config: MultipartConfig,
+    /// Number of fields, shared with nested streams
+    fields: Rc<Cell<usize>>,
 }
 
 impl Multipart {
     /// Create multipart instance for boundary.
     pub fn new<S>(headers: &HeaderMap, stream: S) -> Multipart
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
+    {
+        Multipart::with_config(headers, stream, MultipartConfig::default())
+    }
+
+    /// Create multipart instance for boundary with limits from `config`.
+    pub fn with_config<S>(
+        headers: &HeaderMap,
+        stream: S,
+        config: MultipartConfig,
+    ) -> Multipart
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
+    {
+        Multipart::with_fields(headers, stream, config, Rc::new(Cell::new(0)))
+    }
+
+    fn with_fields<S>(
+        headers: &HeaderMap,
+        stream: S,
+        config: MultipartConfig,
+        fields: Rc<Cell<usize>>,
+    ) -> Multipart
     where
         S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
     {
@@ -70,9 +97,14 @@ impl Multipart {
                 safety: Safety::new(),
                 inner: Some(Rc::new(RefCell::new(InnerMultipart {
                     boundary,
-                    payload: PayloadRef::new(PayloadBuffer::new(Box::new(stream))),
+                    payload: PayloadRef::new(PayloadBuffer::with_limit(
+                        Box::new(stream),
+                        config.total_limit,
+                    )),
                     state: InnerState::FirstBoundary,
                     item: InnerMultipartItem::None,
+                    config,
+                    fields,
                 }))),
             },
             Err(err) => Multipart {
@@ -345,19 +377,43 @@ impl InnerMultipart {
 
             self.state = InnerState::Boundary;
 
-            // nested multipart stream
-            if mt.type_() == mime::MULTIPART {
-                Poll::Ready(Some(Err(MultipartError::Nested)))
-            } else {
-                let field = Rc::new(RefCell::new(InnerField::new(
-                    self.payload.clone(),
-                    self.boundary.clone(),
-                    &headers,
-                )?));
-                self.item = InnerMultipartItem::Field(Rc::clone(&field));
-
-                Poll::Ready(Some(Ok(Field::new(safety.clone(cx), headers, mt, field))))
+            self.fields.set(self.fields.get() + 1);
+            if let Some(max_fields) = self.config.max_fields {
+                if self.fields.get() > max_fields {
+                    return Poll::Ready(Some(Err(MultipartError::FieldsLimit)));
+                }
             }
+
+            let is_file = headers
+                .get(&header::CONTENT_DISPOSITION)
+                .and_then(|cd| ContentDisposition::from_raw(cd).ok())
+                .map(|cd| cd.get_filename().is_some())
+                .unwrap_or(false);
+            // parts of nested stream are limited on their own
+            let limit = if mt.type_() == mime::MULTIPART {
+                None
+            } else if is_file {
+                self.config.max_file_size
+            } else {
+                self.config.max_field_size
+            };
+
+            let field = Rc::new(RefCell::new(InnerField::new(
+                self.payload.clone(),
+                self.boundary.clone(),
+                &headers,
+                limit,
+            )?));
+            self.item = InnerMultipartItem::Field(Rc::clone(&field));
+
+            Poll::Ready(Some(Ok(Field::new(
+                safety.clone(cx),
+                headers,
+                mt,
+                field,
+                self.config.clone(),
+                self.fields.clone(),
+            ))))
         }
     }
 }
@@ -375,6 +431,8 @@ pub struct Field {
     headers: HeaderMap,
     inner: Rc<RefCell<InnerField>>,
     safety: Safety,
+    config: MultipartConfig,
+    fields: Rc<Cell<usize>>,
 }
 
 impl Field {
@@ -383,12 +441,16 @@ impl Field {
         headers: HeaderMap,
         ct: mime::Mime,
         inner: Rc<RefCell<InnerField>>,
+        config: MultipartConfig,
+        fields: Rc<Cell<usize>>,
     ) -> Self {
         Field {
             ct,
             headers,
             inner,
             safety,
+            config,
+            fields,
         }
     }
 
@@ -402,6 +464,51 @@ impl Field {
         &self.ct
     }
 
+    /// Check if field content is a nested multipart stream
+    pub fn is_multipart(&self) -> bool {
+        self.ct.type_() == mime::MULTIPART
+    }
+
+    /// Parse field content as a nested multipart stream.
+    ///
+    /// Legacy clients send multiple files under a single form name as
+    /// a `multipart/mixed` field (RFC 7578, section 4.3). Nested stream
+    /// has to be consumed and dropped before reading next field of the
+    /// parent stream. Limits of the parent stream apply to nested stream,
+    /// its parts are counted together with fields of the parent stream.
+    /// Size of the nested stream itself is limited only by total limit.
+    ///
+    /// ```rust
+    /// use actix_web::{Error, HttpResponse};
+    /// use actix_multipart::Multipart;
+    /// use futures::StreamExt;
+    ///
+    /// async fn upload(mut payload: Multipart) -> Result<HttpResponse, Error> {
+    ///     while let Some(field) = payload.next().await {
+    ///         let field = field?;
+    ///         if field.is_multipart() {
+    ///             let mut files = field.into_multipart();
+    ///             while let Some(file) = files.next().await {
+    ///                 println!("file: {:?}", file?.content_disposition());
+    ///             }
+    ///         }
+    ///     }
+    ///     Ok(HttpResponse::Ok().into())
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn into_multipart(self) -> Multipart {
+        let headers = self.headers.clone();
+        let config = self.config.clone();
+        let fields = self.fields.clone();
+        Multipart::with_fields(
+            &headers,
+            self.map(|res| res.map_err(into_payload_error)),
+            config,
+            fields,
+        )
+    }
+
     /// Get the content disposition of the field, if it exists
     pub fn content_disposition(&self) -> Option<ContentDisposition> {
         // RFC 7578: 'Each part MUST contain a Content-Disposition header field
@@ -447,11 +554,26 @@ impl fmt::Debug for Field {
     }
 }
 
+/// Convert error of a parent field into payload error of nested stream.
+fn into_payload_error(err: MultipartError) -> PayloadError {
+    match err {
+        MultipartError::Payload(err) => err,
+        MultipartError::Incomplete => PayloadError::Incomplete(None),
+        MultipartError::FieldSizeLimit => PayloadError::Overflow,
+        MultipartError::Io(err) => PayloadError::Io(err),
+        err => {
+            PayloadError::Io(io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+        }
+    }
+}
+
 struct InnerField {
     payload: Option<PayloadRef>,
     boundary: String,
     eof: bool,
     length: Option<u64>,
+    limit: Option<u64>,
+    size: u64,
 }
 
 impl InnerField {
@@ -459,6 +581,7 @@ impl InnerField {
         payload: PayloadRef,
         boundary: String,
         headers: &HeaderMap,
+        limit: Option<u64>,
     ) -> Result<InnerField, PayloadError> {
         let len = if let Some(len) = headers.get(&header::CONTENT_LENGTH) {
             if let Ok(s) = len.to_str() {
@@ -476,9 +599,11 @@ impl InnerField {
 
         Ok(InnerField {
             boundary,
+            limit,
             payload: Some(payload),
             eof: false,
             length: len,
+            size: 0,
         })
     }
 
@@ -603,7 +728,17 @@ impl InnerField {
 
                 match res {
                     Poll::Pending => return Poll::Pending,
-                    Poll::Ready(Some(Ok(bytes))) => return Poll::Ready(Some(Ok(bytes))),
+                    Poll::Ready(Some(Ok(bytes))) => {
+                        self.size += bytes.len() as u64;
+                        if let Some(limit) = self.limit {
+                            if self.size > limit {
+                                return Poll::Ready(Some(Err(
+                                    MultipartError::FieldSizeLimit,
+                                )));
+                            }
+                        }
+                        return Poll::Ready(Some(Ok(bytes)));
+                    }
                     Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
                     Poll::Ready(None) => self.eof = true,
                 }
@@ -721,25 +856,46 @@ struct PayloadBuffer {
     eof: bool,
     buf: BytesMut,
     stream: LocalBoxStream<'static, Result<Bytes, PayloadError>>,
+    limit: Option<u64>,
+    size: u64,
 }
 
 impl PayloadBuffer {
     /// Create new `PayloadBuffer` instance
+    #[cfg(test)]
     fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        PayloadBuffer::with_limit(stream, None)
+    }
+
+    /// Create new `PayloadBuffer` instance, which reads at most `limit` bytes
+    fn with_limit<S>(stream: S, limit: Option<u64>) -> Self
     where
         S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
     {
         PayloadBuffer {
+            limit,
             eof: false,
             buf: BytesMut::new(),
             stream: stream.boxed_local(),
+            size: 0,
         }
     }
 
     fn poll_stream(&mut self, cx: &mut Context) -> Result<(), PayloadError> {
         loop {
             match Pin::new(&mut self.stream).poll_next(cx) {
-                Poll::Ready(Some(Ok(data))) => self.buf.extend_from_slice(&data),
+                Poll::Ready(Some(Ok(data))) => {
+                    self.size += data.len() as u64;
+                    if let Some(limit) = self.limit {
+                        if self.size > limit {
+                            return Err(PayloadError::Overflow);
+                        }
+                    }
+                    self.buf.extend_from_slice(&data)
+                }
                 Poll::Ready(Some(Err(e))) => return Err(e),
                 Poll::Ready(None) => {
                     self.eof = true;
@@ -1028,6 +1184,176 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_multipart_limits() {
+        block_on(async {
+            let (bytes, headers) = create_simple_request_with_header();
+
+            // file field is too large
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes.clone())).unwrap();
+            let config = MultipartConfig::default().max_file_size(3);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+            let mut field = multipart.next().await.unwrap().unwrap();
+            match field.next().await {
+                Some(Err(MultipartError::FieldSizeLimit)) => (),
+                _ => unreachable!(),
+            }
+
+            // second field has no file name
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes.clone())).unwrap();
+            let config = MultipartConfig::default().max_field_size(3);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+            let mut field = multipart.next().await.unwrap().unwrap();
+            assert_eq!(field.next().await.unwrap().unwrap(), "test");
+            assert!(field.next().await.is_none());
+            drop(field);
+            let mut field = multipart.next().await.unwrap().unwrap();
+            match field.next().await {
+                Some(Err(MultipartError::FieldSizeLimit)) => (),
+                _ => unreachable!(),
+            }
+
+            // too many fields
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes.clone())).unwrap();
+            let config = MultipartConfig::default().max_fields(1);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+            assert!(multipart.next().await.unwrap().is_ok());
+            match multipart.next().await {
+                Some(Err(MultipartError::FieldsLimit)) => (),
+                _ => unreachable!(),
+            }
+
+            // payload is too large
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes)).unwrap();
+            let config = MultipartConfig::default().total_limit(10);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+            match multipart.next().await {
+                Some(Err(MultipartError::Payload(PayloadError::Overflow))) => (),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    fn create_nested_request() -> (Bytes, HeaderMap) {
+        let bytes = Bytes::from(
+            "--AaB03x\r\n\
+             Content-Disposition: form-data; name=\"submit-name\"\r\n\r\n\
+             Larry\r\n\
+             --AaB03x\r\n\
+             Content-Disposition: form-data; name=\"files\"\r\n\
+             Content-Type: multipart/mixed; boundary=BbC04y\r\n\r\n\
+             --BbC04y\r\n\
+             Content-Disposition: file; filename=\"file1.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             file1 content\r\n\
+             --BbC04y\r\n\
+             Content-Disposition: file; filename=\"file2.gif\"\r\n\
+             Content-Type: image/gif\r\n\r\n\
+             file2\r\n\
+             --BbC04y--\r\n\
+             --AaB03x--\r\n",
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("multipart/form-data; boundary=AaB03x"),
+        );
+        (bytes, headers)
+    }
+
+    #[test]
+    fn test_nested_multipart() {
+        block_on(async {
+            let (bytes, headers) = create_nested_request();
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes)).unwrap();
+            let mut multipart = Multipart::new(&headers, payload);
+
+            let mut field = multipart.next().await.unwrap().unwrap();
+            assert!(!field.is_multipart());
+            assert_eq!(field.next().await.unwrap().unwrap(), "Larry");
+            assert!(field.next().await.is_none());
+            drop(field);
+
+            let field = multipart.next().await.unwrap().unwrap();
+            assert!(field.is_multipart());
+            let mut files = field.into_multipart();
+            let expected = [("file1.txt", "file1 content"), ("file2.gif", "file2")];
+            for (name, content) in &expected {
+                let mut file = files.next().await.unwrap().unwrap();
+                let cd = file.content_disposition().unwrap();
+                assert_eq!(cd.get_filename(), Some(*name));
+                assert_eq!(file.next().await.unwrap().unwrap(), *content);
+                assert!(file.next().await.is_none());
+            }
+            assert!(files.next().await.is_none());
+            drop(files);
+
+            assert!(multipart.next().await.is_none());
+        })
+    }
+
+    #[test]
+    fn test_nested_multipart_limits() {
+        block_on(async {
+            let (bytes, headers) = create_nested_request();
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes)).unwrap();
+            let config = MultipartConfig::default().max_file_size(3);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+
+            let field = multipart.next().await.unwrap().unwrap();
+            drop(field);
+            let field = multipart.next().await.unwrap().unwrap();
+            let mut files = field.into_multipart();
+            let mut file = files.next().await.unwrap().unwrap();
+            match file.next().await {
+                Some(Err(MultipartError::FieldSizeLimit)) => (),
+                _ => unreachable!(),
+            }
+
+            // nested stream and its files are not limited by field size
+            let (bytes, headers) = create_nested_request();
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes)).unwrap();
+            let config = MultipartConfig::default().max_field_size(5);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+
+            let field = multipart.next().await.unwrap().unwrap();
+            drop(field);
+            let field = multipart.next().await.unwrap().unwrap();
+            let mut files = field.into_multipart();
+            let mut file = files.next().await.unwrap().unwrap();
+            assert_eq!(file.next().await.unwrap().unwrap(), "file1 content");
+            assert!(file.next().await.is_none());
+            drop(file);
+            drop(files);
+            assert!(multipart.next().await.is_none());
+
+            // nested parts are counted with fields of parent stream
+            let (bytes, headers) = create_nested_request();
+            let (sender, payload) = create_stream();
+            sender.send(Ok(bytes)).unwrap();
+            let config = MultipartConfig::default().max_fields(3);
+            let mut multipart = Multipart::with_config(&headers, payload, config);
+
+            let field = multipart.next().await.unwrap().unwrap();
+            drop(field);
+            let field = multipart.next().await.unwrap().unwrap();
+            let mut files = field.into_multipart();
+            let file = files.next().await.unwrap().unwrap();
+            drop(file);
+            match files.next().await {
+                Some(Err(MultipartError::FieldsLimit)) => (),
+                _ => unreachable!(),
+            }
+        })
+    }
+
     #[test]
     fn test_basic() {
         block_on(async {
@@ -0,0 +1,267 @@
+//! Saving multipart fields to disk
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use actix_web::web;
+use futures::StreamExt;
+
+use crate::error::MultipartError;
+use crate::server::Field;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Field content stored in a temporary file.
+///
+/// File is removed on a thread pool when `TempFile` is dropped, unless it
+/// is moved to a permanent location with `TempFile::persist()`.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    size: u64,
+    keep: bool,
+}
+
+impl TempFile {
+    /// Path of the temporary file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Move file to `path` on a thread pool, file is not removed afterwards.
+    ///
+    /// File is copied if it could not be renamed, i.e. `path` is on another
+    /// filesystem.
+    pub async fn persist<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Result<PathBuf, MultipartError> {
+        let from = self.path.clone();
+        let to = path.as_ref().to_path_buf();
+        let path = blocking(move || move_file(&from, &to).map(|_| to)).await?;
+        self.keep = true;
+        Ok(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.keep {
+            // function is queued on creation, result is not awaited
+            let path = mem::replace(&mut self.path, PathBuf::new());
+            let _ = web::block(move || fs::remove_file(path));
+        }
+    }
+}
+
+/// Rename file, or copy and remove it if rename fails.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = fs::copy(from, to) {
+        let _ = fs::remove_file(to);
+        return Err(e);
+    }
+    fs::remove_file(from)
+}
+
+impl Field {
+    /// Stream field content to a file at `path`, returns number of written
+    /// bytes.
+    ///
+    /// File is written on a thread pool. Existing file is truncated,
+    /// partially written file is removed on error.
+    pub async fn persist_to<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<u64, MultipartError> {
+        let path = path.as_ref().to_path_buf();
+        let path2 = path.clone();
+        let file = blocking(move || File::create(path2)).await?;
+
+        match self.write_to(file).await {
+            Ok(size) => Ok(size),
+            Err(e) => {
+                let _ = blocking(move || fs::remove_file(path)).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Stream field content to a new temporary file in system temporary
+    /// directory.
+    pub async fn to_temp_file(&mut self) -> Result<TempFile, MultipartError> {
+        self.to_temp_file_in(std::env::temp_dir()).await
+    }
+
+    /// Stream field content to a new temporary file in `dir`.
+    ///
+    /// ```rust
+    /// use actix_web::{Error, HttpResponse};
+    /// use actix_multipart::Multipart;
+    /// use futures::StreamExt;
+    ///
+    /// async fn upload(mut payload: Multipart) -> Result<HttpResponse, Error> {
+    ///     while let Some(field) = payload.next().await {
+    ///         let file = field?.to_temp_file_in("./uploads").await?;
+    ///         let size = file.size();
+    ///         file.persist(format!("./uploads/{}.bin", size)).await?;
+    ///     }
+    ///     Ok(HttpResponse::Ok().into())
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub async fn to_temp_file_in<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+    ) -> Result<TempFile, MultipartError> {
+        let dir = dir.as_ref().to_path_buf();
+        let (file, path) = blocking(move || create_temp(&dir)).await?;
+
+        // file is removed on drop if writing fails
+        let mut temp = TempFile {
+            path,
+            size: 0,
+            keep: false,
+        };
+        temp.size = self.write_to(file).await?;
+        Ok(temp)
+    }
+
+    async fn write_to(&mut self, file: File) -> Result<u64, MultipartError> {
+        let mut file = file;
+        let mut size = 0;
+        while let Some(chunk) = self.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            file = blocking(move || file.write_all(&chunk).map(|_| file)).await?;
+        }
+        blocking(move || file.sync_all()).await?;
+        Ok(size)
+    }
+}
+
+/// Create new file with unique name in `dir`.
+fn create_temp(dir: &Path) -> io::Result<(File, PathBuf)> {
+    loop {
+        let path = dir.join(format!(
+            "actix-multipart-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Run blocking io operation on a thread pool.
+async fn blocking<F, R>(f: F) -> Result<R, MultipartError>
+where
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    match web::block(f).await {
+        Ok(res) => res.map_err(MultipartError::Io),
+        Err(_) => Err(MultipartError::Io(io::Error::new(
+            io::ErrorKind::Other,
+            "Thread pool is gone",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::{self, HeaderMap};
+    use actix_web::test::block_on;
+    use bytes::Bytes;
+    use futures::stream;
+
+    use super::*;
+    use crate::server::Multipart;
+
+    fn multipart() -> Multipart {
+        let bytes = Bytes::from(
+            "--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"fn.txt\"\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nContent-Length: 4\r\n\r\n\
+             test\r\n\
+             --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n",
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static(
+                "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+            ),
+        );
+        Multipart::new(&headers, stream::iter(vec![Ok(bytes)]))
+    }
+
+    /// Create unique directory for a test.
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "actix-multipart-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_persist_to() {
+        block_on(async {
+            let dir = test_dir();
+            let path = dir.join("persist-to");
+            let mut payload = multipart();
+            let mut field = payload.next().await.unwrap().unwrap();
+            assert_eq!(field.persist_to(&path).await.unwrap(), 4);
+            assert_eq!(fs::read(&path).unwrap(), b"test");
+            fs::remove_dir_all(&dir).unwrap();
+        })
+    }
+
+    #[test]
+    fn test_temp_file() {
+        block_on(async {
+            let dir = test_dir();
+            let mut payload = multipart();
+            let mut field = payload.next().await.unwrap().unwrap();
+            let file = field.to_temp_file_in(&dir).await.unwrap();
+            assert_eq!(file.size(), 4);
+            assert_eq!(fs::read(file.path()).unwrap(), b"test");
+
+            // removed on drop, on a thread pool
+            let path = file.path().to_path_buf();
+            drop(file);
+            for _ in 0..100 {
+                if !path.exists() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            assert!(!path.exists());
+
+            // persisted
+            let mut payload = multipart();
+            let mut field = payload.next().await.unwrap().unwrap();
+            let file = field.to_temp_file().await.unwrap();
+            let temp = file.path().to_path_buf();
+            let path = file.persist(dir.join("persisted")).await.unwrap();
+            assert_eq!(fs::read(&path).unwrap(), b"test");
+            assert!(!temp.exists());
+            fs::remove_dir_all(&dir).unwrap();
+        })
+    }
+}
@@ -46,6 +46,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use actix_web::dev::{Extensions, Payload, ServiceRequest, ServiceResponse};
+use actix_web::test::TestRequest;
 use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
 use futures::future::{ok, Ready};
 use hashbrown::HashMap;
@@ -99,6 +100,37 @@ impl UserSession for ServiceRequest {
     }
 }
 
+/// Helper trait that allows to seed session data of `TestRequest`.
+///
+/// ```rust
+/// use actix_session::{TestSession, UserSession};
+/// use actix_web::test;
+///
+/// # fn main() {
+/// let mut req = test::TestRequest::default()
+///     .session("user", "alice")
+///     .to_http_request();
+///
+/// let user = req.get_session().get::<String>("user").unwrap();
+/// assert_eq!(user, Some("alice".to_owned()));
+/// # }
+/// ```
+pub trait TestSession {
+    /// Set session value, panics if value could not be serialized.
+    fn session<T: Serialize>(self, key: &str, value: T) -> Self;
+}
+
+impl TestSession for TestRequest {
+    fn session<T: Serialize>(self, key: &str, value: T) -> Self {
+        let key = key.to_owned();
+        let value = serde_json::to_string(&value).unwrap();
+        self.extensions(move |extensions| {
+            let session = Session::get_session(extensions);
+            session.0.borrow_mut().state.insert(key, value);
+        })
+    }
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum SessionStatus {
     Changed,
@@ -281,6 +313,25 @@ mod tests {
         assert_eq!(res, Some("value".to_string()));
     }
 
+    #[test]
+    fn test_session() {
+        let mut req = test::TestRequest::default()
+            .session("key", "value")
+            .session("counter", 1)
+            .to_srv_request();
+
+        let session = req.get_session();
+        assert_eq!(
+            session.get::<String>("key").unwrap(),
+            Some("value".to_owned())
+        );
+        assert_eq!(session.get::<i32>("counter").unwrap(), Some(1));
+
+        let mut res = req.into_response(HttpResponse::Ok().finish());
+        let (status, _) = Session::get_changes(&mut res);
+        assert_eq!(status, SessionStatus::Unchanged);
+    }
+
     #[test]
     fn purge_session() {
         let req = test::TestRequest::default().to_srv_request();
@@ -16,14 +16,25 @@
 //! - [options](attr.options.html)
 //! - [trace](attr.trace.html)
 //! - [patch](attr.patch.html)
+//! - [route](attr.route.html) - handler for multiple methods, specified with `method` attribute
 //!
 //! ### Attributes:
 //!
 //! - `"path"` - Raw literal string with path for which to register handle. Mandatory.
 //! - `guard="function_name"` - Registers function as guard using `actix_web::guard::fn_guard`
+//! - `wrap="Middleware"` - Registers middleware for the resource, expression is evaluated
+//!   in the scope of the handler. Could be specified multiple times.
+//! - `name="resource_name"` - Sets name of the resource, it could be used for url generation
+//! - `path_check=true` - Enables check of `Path<T>` extractors of the handler, see below
 //!
 //! ## Notes
 //!
+//! With `path_check=true` number of dynamic segments of the path is checked against
+//! `Path<T>` extractors of the handler at compile time, i.e. `Path<(u32, String)>` requires
+//! exactly two segments and `Path<u32>` requires one. Fields of structs are not checked.
+//! Check is disabled by default, macro does not know segments of enclosing scopes, so
+//! it should not be enabled for handlers that extract them.
+//!
 //! Function name can be specified as any expression that is going to be accessible to the generate
 //! code (e.g `my_guard` or `my_module::my_guard`)
 //!
@@ -43,6 +54,7 @@
 extern crate proc_macro;
 
 mod route;
+mod rt;
 
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
@@ -55,10 +67,13 @@ use syn::parse_macro_input;
 ///
 /// - `"path"` - Raw literal string with path for which to register handler. Mandatory.
 /// - `guard="function_name"` - Registers function as guard using `actix_web::guard::fn_guard`
+/// - `wrap="Middleware"` - Registers middleware for the resource
+/// - `name="resource_name"` - Sets name of the resource
+/// - `path_check=true` - Enables compile time check of `Path<T>` extractors
 #[proc_macro_attribute]
 pub fn get(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Get) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Get)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -73,7 +88,7 @@ pub fn get(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn post(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Post) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Post)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -88,7 +103,7 @@ pub fn post(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn put(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Put) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Put)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -103,7 +118,7 @@ pub fn put(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn delete(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Delete) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Delete)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -118,7 +133,7 @@ pub fn delete(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn head(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Head) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Head)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -133,7 +148,7 @@ pub fn head(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn connect(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Connect) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Connect)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -148,7 +163,7 @@ pub fn connect(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn options(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Options) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Options)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -163,7 +178,7 @@ pub fn options(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn trace(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Trace) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Trace)) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
@@ -178,9 +193,72 @@ pub fn trace(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn patch(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as syn::AttributeArgs);
-    let gen = match route::Route::new(args, input, route::GuardType::Patch) {
+    let gen = match route::Route::new(args, input, Some(route::GuardType::Patch)) {
+        Ok(gen) => gen,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    gen.generate()
+}
+
+/// Creates route handler for multiple http methods.
+///
+/// Syntax: `#[route("path", method = "HTTP_METHOD"[, attributes])]`
+///
+/// ## Attributes:
+///
+/// - `"path"` - Raw literal string with path for which to register handler. Mandatory.
+/// - `method="HTTP_METHOD"` - Registers http method to handle, i.e. `GET` or `POST`.
+///   Could be specified multiple times, at least one method is required.
+///
+/// Other attributes are the same as in [get](attr.get.html)
+#[proc_macro_attribute]
+pub fn route(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as syn::AttributeArgs);
+    let gen = match route::Route::new(args, input, None) {
         Ok(gen) => gen,
         Err(err) => return err.to_compile_error().into(),
     };
     gen.generate()
 }
+
+/// Marks async main function as the actix system entry-point.
+///
+/// Users do not need to depend on `actix-rt`, system of the `actix-web`
+/// version in use is started.
+///
+/// ## Usage
+///
+/// ```rust
+/// use actix_web::web;
+///
+/// #[actix_web::main]
+/// async fn main() {
+///     let sum = web::block(|| 2 + 2).await.unwrap();
+///     assert_eq!(sum, 4);
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(_: TokenStream, input: TokenStream) -> TokenStream {
+    match rt::main(input) {
+        Ok(gen) => gen,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Marks async test function to be executed by actix system.
+///
+/// ## Usage
+///
+/// ```rust
+/// #[actix_web::test]
+/// async fn my_test() {
+///     assert!(true);
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn test(_: TokenStream, input: TokenStream) -> TokenStream {
+    match rt::test(input) {
+        Ok(gen) => gen,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
@@ -48,6 +48,21 @@ impl GuardType {
             GuardType::Patch => "Patch",
         }
     }
+
+    fn from_method(method: &str) -> Option<Self> {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" => Some(GuardType::Get),
+            "POST" => Some(GuardType::Post),
+            "PUT" => Some(GuardType::Put),
+            "DELETE" => Some(GuardType::Delete),
+            "HEAD" => Some(GuardType::Head),
+            "CONNECT" => Some(GuardType::Connect),
+            "OPTIONS" => Some(GuardType::Options),
+            "TRACE" => Some(GuardType::Trace),
+            "PATCH" => Some(GuardType::Patch),
+            _ => None,
+        }
+    }
 }
 
 impl ToTokens for GuardType {
@@ -61,12 +76,20 @@ impl ToTokens for GuardType {
 struct Args {
     path: syn::LitStr,
     guards: Vec<Ident>,
+    wrappers: Vec<syn::Expr>,
+    resource_name: Option<syn::LitStr>,
+    methods: Vec<GuardType>,
+    path_check: bool,
 }
 
 impl Args {
     fn new(args: AttributeArgs) -> syn::Result<Self> {
         let mut path = None;
         let mut guards = Vec::new();
+        let mut wrappers = Vec::new();
+        let mut resource_name = None;
+        let mut methods = Vec::new();
+        let mut path_check = false;
         for arg in args {
             match arg {
                 NestedMeta::Lit(syn::Lit::Str(lit)) => match path {
@@ -90,10 +113,67 @@ impl Args {
                                 "Attribute guard expects literal string!",
                             ));
                         }
+                    } else if nv.path.is_ident("wrap") {
+                        if let syn::Lit::Str(lit) = nv.lit {
+                            wrappers.push(lit.parse()?);
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                nv.lit,
+                                "Attribute wrap expects literal string!",
+                            ));
+                        }
+                    } else if nv.path.is_ident("name") {
+                        if let syn::Lit::Str(lit) = nv.lit {
+                            if resource_name.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "Multiple names specified! Should be only one!",
+                                ));
+                            }
+                            resource_name = Some(lit);
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                nv.lit,
+                                "Attribute name expects literal string!",
+                            ));
+                        }
+                    } else if nv.path.is_ident("method") {
+                        if let syn::Lit::Str(lit) = nv.lit {
+                            let method = match GuardType::from_method(&lit.value()) {
+                                Some(method) => method,
+                                None => {
+                                    return Err(syn::Error::new_spanned(
+                                        lit,
+                                        "Unknown http method is specified",
+                                    ));
+                                }
+                            };
+                            if methods.contains(&method) {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "Http method is specified multiple times",
+                                ));
+                            }
+                            methods.push(method);
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                nv.lit,
+                                "Attribute method expects literal string!",
+                            ));
+                        }
+                    } else if nv.path.is_ident("path_check") {
+                        if let syn::Lit::Bool(lit) = nv.lit {
+                            path_check = lit.value;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                nv.lit,
+                                "Attribute path_check expects literal bool!",
+                            ));
+                        }
                     } else {
                         return Err(syn::Error::new_spanned(
                             nv.path,
-                            "Unknown attribute key is specified. Allowed: guard",
+                            "Unknown attribute key is specified. Allowed: guard, wrap, name, method, path_check",
                         ));
                     }
                 }
@@ -105,16 +185,142 @@ impl Args {
         Ok(Args {
             path: path.unwrap(),
             guards,
+            wrappers,
+            resource_name,
+            methods,
+            path_check,
         })
     }
 }
 
+/// Names of dynamic segments of the path, i.e. `{id}` or `{id:\d+}`.
+fn path_params(path: &syn::LitStr) -> syn::Result<Vec<String>> {
+    let path = path.value();
+    let mut params = Vec::new();
+    let mut param = String::new();
+    let mut depth = 0;
+
+    for ch in path.chars() {
+        match ch {
+            '{' => {
+                if depth > 0 {
+                    param.push(ch);
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let name = param.split(':').next().unwrap_or("").trim();
+                    params.push(name.to_owned());
+                    param.clear();
+                } else {
+                    param.push(ch);
+                }
+            }
+            _ if depth > 0 => param.push(ch),
+            _ => (),
+        }
+    }
+
+    if depth > 0 {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "Path has unclosed dynamic segment",
+        ));
+    }
+    Ok(params)
+}
+
+/// Type parameter of `Path<T>` extractor.
+fn path_extractor(typ: &syn::Type) -> Option<&syn::Type> {
+    if let syn::Type::Path(ref typ) = typ {
+        let segment = typ.path.segments.last()?;
+        if segment.ident != "Path" {
+            return None;
+        }
+        if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
+            if args.args.len() == 1 {
+                if let Some(syn::GenericArgument::Type(ref typ)) = args.args.first() {
+                    return Some(typ);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Number of path segments extracted by `T`, `None` if it is not known, i.e.
+/// for structs.
+fn extracted_segments(typ: &syn::Type) -> Option<usize> {
+    const SCALARS: &[&str] = &[
+        "String", "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8",
+        "i16", "i32", "i64", "i128", "isize", "f32", "f64",
+    ];
+
+    match typ {
+        syn::Type::Tuple(ref typ) => Some(typ.elems.len()),
+        syn::Type::Paren(ref typ) => extracted_segments(&typ.elem),
+        syn::Type::Path(ref typ) => typ.path.segments.last().and_then(|segment| {
+            if SCALARS.iter().any(|name| segment.ident == name) {
+                Some(1)
+            } else {
+                None
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Check that `Path<T>` parameters of the handler match dynamic segments
+/// of the path.
+fn check_path(path: &syn::LitStr, ast: &syn::ItemFn) -> syn::Result<()> {
+    let params = path_params(path)?;
+
+    for input in ast.sig.inputs.iter() {
+        let typ = match input {
+            syn::FnArg::Typed(ref pat) => pat.ty.as_ref(),
+            _ => continue,
+        };
+        let inner = match path_extractor(typ) {
+            Some(inner) => inner,
+            None => continue,
+        };
+
+        match extracted_segments(inner) {
+            Some(count) if count != params.len() => {
+                return Err(syn::Error::new_spanned(
+                    typ,
+                    format!(
+                        "Path extractor expects {} parameter(s), but path \"{}\" has {}: [{}]",
+                        count,
+                        path.value(),
+                        params.len(),
+                        params.join(", "),
+                    ),
+                ));
+            }
+            None if params.is_empty() => {
+                return Err(syn::Error::new_spanned(
+                    typ,
+                    format!(
+                        "Path extractor is used, but path \"{}\" has no parameters",
+                        path.value(),
+                    ),
+                ));
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
 pub struct Route {
     name: syn::Ident,
     args: Args,
     ast: syn::ItemFn,
     resource_type: ResourceType,
-    guard: GuardType,
+    methods: Vec<GuardType>,
 }
 
 fn guess_resource_type(typ: &syn::Type) -> ResourceType {
@@ -143,21 +349,49 @@ impl Route {
     pub fn new(
         args: AttributeArgs,
         input: TokenStream,
-        guard: GuardType,
+        guard: Option<GuardType>,
     ) -> syn::Result<Self> {
         if args.is_empty() {
+            let macro_name = match guard {
+                Some(ref guard) => guard.as_str().to_ascii_lowercase(),
+                None => "route".to_owned(),
+            };
             return Err(syn::Error::new(
                 Span::call_site(),
                 format!(
                     r#"invalid server definition, expected #[{}("<some path>")]"#,
-                    guard.as_str().to_ascii_lowercase()
+                    macro_name
                 ),
             ));
         }
         let ast: syn::ItemFn = syn::parse(input)?;
         let name = ast.sig.ident.clone();
 
-        let args = Args::new(args)?;
+        let mut args = Args::new(args)?;
+        if args.path_check {
+            check_path(&args.path, &ast)?;
+        }
+
+        let methods = match guard {
+            Some(guard) => {
+                if !args.methods.is_empty() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "Attribute method is allowed only in #[route(..)] macro",
+                    ));
+                }
+                vec![guard]
+            }
+            None => {
+                if args.methods.is_empty() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        r#"At least one method is required, expected #[route("<some path>", method = "GET")]"#,
+                    ));
+                }
+                std::mem::replace(&mut args.methods, Vec::new())
+            }
+        };
 
         let resource_type = if ast.sig.asyncness.is_some() {
             ResourceType::Async
@@ -178,16 +412,19 @@ impl Route {
             args,
             ast,
             resource_type,
-            guard,
+            methods,
         })
     }
 
     pub fn generate(&self) -> TokenStream {
         let name = &self.name;
-        let guard = &self.guard;
+        let guard = &self.methods[0];
+        let other_guards = &self.methods[1..];
         let ast = &self.ast;
         let path = &self.args.path;
         let extra_guards = &self.args.guards;
+        let wrappers = &self.args.wrappers;
+        let resource_name = self.args.resource_name.iter();
         let resource_type = &self.resource_type;
         let stream = quote! {
             #[allow(non_camel_case_types)]
@@ -198,9 +435,14 @@ impl Route {
                     #ast
 
                     let resource = actix_web::Resource::new(#path)
-                        .guard(actix_web::guard::#guard())
+                        #(.name(#resource_name))*
+                        .guard(
+                            actix_web::guard::Any(actix_web::guard::#guard())
+                                #(.or(actix_web::guard::#other_guards()))*
+                        )
                         #(.guard(actix_web::guard::fn_guard(#extra_guards)))*
-                        .#resource_type(#name);
+                        .#resource_type(#name)
+                        #(.wrap(#wrappers))*;
 
                     actix_web::dev::HttpServiceFactory::register(resource, config)
                 }
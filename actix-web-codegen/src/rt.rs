@@ -0,0 +1,61 @@
+use proc_macro::TokenStream;
+use quote::quote;
+
+/// Run `async fn main()` on actix system.
+pub fn main(input: TokenStream) -> syn::Result<TokenStream> {
+    let mut input: syn::ItemFn = syn::parse(input)?;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let body = &input.block;
+    let name = input.sig.ident.to_string();
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "the async keyword is missing from the function declaration",
+        ));
+    }
+    input.sig.asyncness = None;
+    let sig = &input.sig;
+
+    Ok((quote! {
+        #(#attrs)*
+        #vis #sig {
+            actix_web::rt::System::new(#name)
+                .block_on(async move { #body })
+        }
+    })
+    .into())
+}
+
+/// Run async test function on actix system.
+pub fn test(input: TokenStream) -> syn::Result<TokenStream> {
+    let input: syn::ItemFn = syn::parse(input)?;
+    let attrs = &input.attrs;
+    let name = &input.sig.ident;
+    let ret = &input.sig.output;
+    let body = &input.block;
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "the async keyword is missing from the function declaration",
+        ));
+    }
+    if let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("test")) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "second test attribute is supplied",
+        ));
+    }
+
+    Ok((quote! {
+        #[test]
+        #(#attrs)*
+        fn #name() #ret {
+            actix_web::rt::System::new("test")
+                .block_on(async { #body })
+        }
+    })
+    .into())
+}
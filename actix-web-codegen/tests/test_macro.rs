@@ -1,7 +1,9 @@
 use actix_http::HttpService;
 use actix_http_test::{block_on, TestServer};
-use actix_web::{http, web::Path, App, HttpResponse, Responder};
-use actix_web_codegen::{connect, delete, get, head, options, patch, post, put, trace};
+use actix_web::{http, web::Path, App, HttpRequest, HttpResponse, Responder};
+use actix_web_codegen::{
+    connect, delete, get, head, options, patch, post, put, route, trace,
+};
 use futures::{future, Future};
 
 #[get("/test")]
@@ -69,6 +71,30 @@ async fn get_param_test(_: Path<String>) -> impl Responder {
     HttpResponse::Ok()
 }
 
+#[get(
+    "/test/wrap",
+    wrap = "actix_web::middleware::DefaultHeaders::new().header(\"custom-key\", \"custom-value\")",
+    name = "wrap_test"
+)]
+async fn get_wrap(req: HttpRequest) -> impl Responder {
+    HttpResponse::Ok().body(req.url_for_static("wrap_test").unwrap().path().to_owned())
+}
+
+#[route("/multi", method = "GET", method = "POST", method = "HEAD")]
+async fn route_test() -> impl Responder {
+    HttpResponse::Ok()
+}
+
+#[get("/check/{id}/{name:[a-z]+}", path_check = true)]
+async fn get_checked_test(path: Path<(u32, String)>) -> impl Responder {
+    HttpResponse::Ok().body(format!("{} {}", path.0, path.1))
+}
+
+#[get("/unchecked")]
+async fn get_unchecked_test(_: Path<(u32,)>) -> impl Responder {
+    HttpResponse::Ok()
+}
+
 #[test]
 fn test_params() {
     block_on(async {
@@ -161,3 +187,75 @@ fn test_auto_async() {
         assert!(response.status().is_success());
     })
 }
+
+#[test]
+fn test_wrap_and_name() {
+    block_on(async {
+        let srv = TestServer::start(|| HttpService::new(App::new().service(get_wrap)));
+
+        let request = srv.request(http::Method::GET, srv.url("/test/wrap"));
+        let mut response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.headers().get("custom-key").unwrap(),
+            "custom-value"
+        );
+        let body = response.body().await.unwrap();
+        assert_eq!(&body[..], b"/test/wrap");
+    })
+}
+
+#[test]
+fn test_route() {
+    block_on(async {
+        let srv = TestServer::start(|| HttpService::new(App::new().service(route_test)));
+
+        let request = srv.request(http::Method::GET, srv.url("/multi"));
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let request = srv.request(http::Method::POST, srv.url("/multi"));
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let request = srv.request(http::Method::HEAD, srv.url("/multi"));
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let request = srv.request(http::Method::PUT, srv.url("/multi"));
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_client_error());
+    })
+}
+
+#[actix_web::test]
+async fn test_runtime() {
+    let srv = TestServer::start(|| HttpService::new(App::new().service(test)));
+
+    let request = srv.request(http::Method::GET, srv.url("/test"));
+    let response = request.send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
+#[test]
+fn test_path_check() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(
+                App::new()
+                    .service(get_checked_test)
+                    .service(actix_web::web::scope("/{id}").service(get_unchecked_test)),
+            )
+        });
+
+        let request = srv.request(http::Method::GET, srv.url("/check/1/name"));
+        let mut response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+        let body = response.body().await.unwrap();
+        assert_eq!(&body[..], b"1 name");
+
+        let request = srv.request(http::Method::GET, srv.url("/1/unchecked"));
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+    })
+}
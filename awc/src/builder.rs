@@ -3,11 +3,17 @@ use std::fmt;
 use std::rc::Rc;
 use std::time::Duration;
 
-use actix_http::client::{Connect, ConnectError, Connection, Connector};
-use actix_http::http::{header, HeaderMap, HeaderName, HttpTryFrom};
-use actix_service::Service;
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_connect::{Connect as TcpConnect, Connection as TcpConnection};
+use actix_http::client::{Connect, ConnectError, Connection, Connector, PoolMetrics};
+use actix_http::http::{header, HeaderMap, HeaderName, HttpTryFrom, Uri, Version};
+use actix_service::{boxed, Service};
 
-use crate::connect::ConnectorWrapper;
+use crate::connect::{Connect as ClientConnect, ConnectorWrapper};
+use crate::middleware::{
+    Cache, ConnectService, ConnectorService, CookieStore, MiddlewareConnector, Redirect,
+    Retry, Transform,
+};
 use crate::{Client, ClientConfig};
 
 /// An HTTP Client builder
@@ -15,10 +21,99 @@ use crate::{Client, ClientConfig};
 /// This type can be used to construct an instance of `Client` through a
 /// builder-like pattern.
 pub struct ClientBuilder {
-    config: ClientConfig,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
     default_headers: bool,
     allow_redirects: bool,
     max_redirects: usize,
+    same_origin_redirects: bool,
+    https_downgrade: bool,
+    retry: Option<Retry>,
+    cookie_store: Option<CookieStore>,
+    cache: Option<Cache>,
+    connector: ConnectorSource,
+    connector_config: ConnectorConfig,
+    middlewares: Vec<Box<dyn FnOnce(ConnectService) -> ConnectService>>,
+}
+
+type BuildConnector =
+    dyn FnOnce(&ConnectorConfig) -> (Box<dyn ClientConnect>, PoolMetrics);
+
+/// Connector of the client, it is built by `ClientBuilder::finish()`.
+enum ConnectorSource {
+    /// Connector builder, pool settings are applied before it is finished
+    Builder(Box<BuildConnector>),
+    /// Finished connector service, pool settings do not apply
+    Service(Box<dyn ClientConnect>),
+}
+
+/// Pool and timeout settings, unset values are left as configured
+/// in connector builder.
+#[derive(Default)]
+struct ConnectorConfig {
+    limit: Option<usize>,
+    limit_per_host: Option<usize>,
+    conn_keep_alive: Option<Duration>,
+    conn_lifetime: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    handshake_timeout: Option<Duration>,
+    max_http_version: Option<Version>,
+    http2_prior_knowledge: bool,
+}
+
+impl ConnectorConfig {
+    fn is_set(&self) -> bool {
+        self.limit.is_some()
+            || self.limit_per_host.is_some()
+            || self.conn_keep_alive.is_some()
+            || self.conn_lifetime.is_some()
+            || self.connect_timeout.is_some()
+            || self.handshake_timeout.is_some()
+            || self.max_http_version.is_some()
+            || self.http2_prior_knowledge
+    }
+
+    fn build<T, U>(
+        &self,
+        mut connector: Connector<T, U>,
+    ) -> (Box<dyn ClientConnect>, PoolMetrics)
+    where
+        U: AsyncRead + AsyncWrite + Unpin + fmt::Debug + 'static,
+        T: Service<
+                Request = TcpConnect<Uri>,
+                Response = TcpConnection<Uri, U>,
+                Error = actix_connect::ConnectError,
+            > + Clone
+            + 'static,
+    {
+        if let Some(limit) = self.limit {
+            connector = connector.limit(limit);
+        }
+        if let Some(limit) = self.limit_per_host {
+            connector = connector.limit_per_host(limit);
+        }
+        if let Some(dur) = self.conn_keep_alive {
+            connector = connector.conn_keep_alive(dur);
+        }
+        if let Some(dur) = self.conn_lifetime {
+            connector = connector.conn_lifetime(dur);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            connector = connector.timeout(timeout);
+        }
+        if let Some(timeout) = self.handshake_timeout {
+            connector = connector.handshake_timeout(timeout);
+        }
+        if let Some(version) = self.max_http_version {
+            connector = connector.max_http_version(version);
+        }
+        if self.http2_prior_knowledge {
+            connector = connector.http2_prior_knowledge();
+        }
+        let metrics = connector.metrics();
+        (Box::new(ConnectorWrapper(connector.finish())), metrics)
+    }
 }
 
 impl Default for ClientBuilder {
@@ -30,20 +125,29 @@ impl Default for ClientBuilder {
 impl ClientBuilder {
     pub fn new() -> Self {
         ClientBuilder {
+            headers: HeaderMap::new(),
+            timeout: Some(Duration::from_secs(5)),
+            read_timeout: None,
             default_headers: true,
             allow_redirects: true,
             max_redirects: 10,
-            config: ClientConfig {
-                headers: HeaderMap::new(),
-                timeout: Some(Duration::from_secs(5)),
-                connector: RefCell::new(Box::new(ConnectorWrapper(
-                    Connector::new().finish(),
-                ))),
-            },
+            same_origin_redirects: false,
+            https_downgrade: false,
+            retry: None,
+            cookie_store: None,
+            cache: None,
+            connector: ConnectorSource::Builder(Box::new(|cfg: &ConnectorConfig| {
+                cfg.build(Connector::new())
+            })),
+            connector_config: ConnectorConfig::default(),
+            middlewares: Vec::new(),
         }
     }
 
     /// Use custom connector service.
+    ///
+    /// Pool and connect timeout settings of the builder do not apply to
+    /// finished connector service, use `connector_builder()` instead.
     pub fn connector<T>(mut self, connector: T) -> Self
     where
         T: Service<Request = Connect, Error = ConnectError> + 'static,
@@ -51,7 +155,114 @@ impl ClientBuilder {
         <T::Response as Connection>::Future: 'static,
         T::Future: 'static,
     {
-        self.config.connector = RefCell::new(Box::new(ConnectorWrapper(connector)));
+        self.connector = ConnectorSource::Service(Box::new(ConnectorWrapper(connector)));
+        self
+    }
+
+    /// Use custom connector builder.
+    ///
+    /// Pool and connect timeout settings of the client builder are
+    /// applied to the connector, connector is finished by `finish()`.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use awc::{Client, Connector};
+    ///
+    /// let client = Client::build()
+    ///     .connector_builder(Connector::new().resolve_to("api.local", vec![]))
+    ///     .limit_per_host(10)
+    ///     .conn_keep_alive(Duration::from_secs(30))
+    ///     .finish();
+    /// ```
+    pub fn connector_builder<T, U>(mut self, connector: Connector<T, U>) -> Self
+    where
+        U: AsyncRead + AsyncWrite + Unpin + fmt::Debug + 'static,
+        T: Service<
+                Request = TcpConnect<Uri>,
+                Response = TcpConnection<Uri, U>,
+                Error = actix_connect::ConnectError,
+            > + Clone
+            + 'static,
+    {
+        self.connector =
+            ConnectorSource::Builder(Box::new(move |cfg: &ConnectorConfig| {
+                cfg.build(connector)
+            }));
+        self
+    }
+
+    /// Set total number of simultaneous connections per type of scheme.
+    ///
+    /// Pool and connect timeout settings apply to the default connector
+    /// or to the connector set with `connector_builder()`.
+    /// If limit is 0, the connector has no limit.
+    /// The default limit size is 100.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.connector_config.limit = Some(limit);
+        self
+    }
+
+    /// Set number of simultaneous connections to a single host.
+    ///
+    /// By default there is no per host limit.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.connector_config.limit_per_host = Some(limit);
+        self
+    }
+
+    /// Set idle timeout for pooled connections.
+    ///
+    /// Connection is closed if it is not reused within this period.
+    /// Default keep-alive period is 15 seconds.
+    pub fn conn_keep_alive(mut self, dur: Duration) -> Self {
+        self.connector_config.conn_keep_alive = Some(dur);
+        self
+    }
+
+    /// Set max lifetime of pooled connections.
+    ///
+    /// Connection is closed after this period regardless of keep-alive.
+    /// Default lifetime period is 75 seconds.
+    pub fn conn_lifetime(mut self, dur: Duration) -> Self {
+        self.connector_config.conn_lifetime = Some(dur);
+        self
+    }
+
+    /// Set connect timeout, i.e. max time to open tcp connection
+    /// including dns name resolution.
+    ///
+    /// Connect timeout failures are reported as `ConnectError::Timeout`.
+    /// Default connect timeout is 1 second.
+    pub fn connect_timeout(mut self, dur: Duration) -> Self {
+        self.connector_config.connect_timeout = Some(dur);
+        self
+    }
+
+    /// Set TLS handshake timeout.
+    ///
+    /// Handshake timeout failures are reported as
+    /// `ConnectError::HandshakeTimeout`. By default it is the same as
+    /// connect timeout.
+    pub fn handshake_timeout(mut self, dur: Duration) -> Self {
+        self.connector_config.handshake_timeout = Some(dur);
+        self
+    }
+
+    /// Set max http version negotiated for secure connections.
+    ///
+    /// With HTTP/1.1 server could not select HTTP/2 during TLS handshake.
+    /// By default HTTP/2 is preferred.
+    pub fn max_http_version(mut self, version: Version) -> Self {
+        self.connector_config.max_http_version = Some(version);
+        self
+    }
+
+    /// Use HTTP/2 for plain text connections without upgrade
+    /// negotiation.
+    ///
+    /// Useful for internal services that support HTTP/2 over cleartext.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.connector_config.http2_prior_knowledge = true;
         self
     }
 
@@ -60,13 +271,23 @@ impl ClientBuilder {
     /// Request timeout is the total time before a response must be received.
     /// Default value is 5 seconds.
     pub fn timeout(mut self, timeout: Duration) -> Self {
-        self.config.timeout = Some(timeout);
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set body read timeout.
+    ///
+    /// Reading response body fails with `PayloadError::Io` of
+    /// `TimedOut` kind if no data is received within this period.
+    /// By default body reads are not limited.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
         self
     }
 
     /// Disable request timeout.
     pub fn disable_timeout(mut self) -> Self {
-        self.config.timeout = None;
+        self.timeout = None;
         self
     }
 
@@ -86,6 +307,63 @@ impl ClientBuilder {
         self
     }
 
+    /// Follow redirects only within origin of the request.
+    ///
+    /// Redirects to other scheme, host or port are returned to the caller.
+    pub fn same_origin_redirects(mut self) -> Self {
+        self.same_origin_redirects = true;
+        self
+    }
+
+    /// Follow redirects from `https` to `http` urls.
+    ///
+    /// By default such redirects are returned to the caller.
+    pub fn allow_https_downgrade(mut self) -> Self {
+        self.https_downgrade = true;
+        self
+    }
+
+    /// Register a client middleware.
+    ///
+    /// Middleware wraps every outgoing request of the client, including
+    /// websocket handshakes. Like `App::wrap()`, middleware registered
+    /// last is executed first.
+    pub fn wrap<M>(mut self, mw: M) -> Self
+    where
+        M: Transform<ConnectService> + 'static,
+        M::Transform: 'static,
+        <M::Transform as Service>::Future: 'static,
+    {
+        self.middlewares
+            .push(Box::new(move |srv| boxed::service(mw.new_transform(srv))));
+        self
+    }
+
+    /// Set retry policy for all requests.
+    ///
+    /// Requests are not retried by default.
+    pub fn retry(mut self, policy: Retry) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Store cookies set by responses and send them with subsequent requests.
+    ///
+    /// Store could be shared by several clients. Cookies are not stored
+    /// by default.
+    pub fn cookie_store(mut self, store: CookieStore) -> Self {
+        self.cookie_store = Some(store);
+        self
+    }
+
+    /// Cache responses according to their `Cache-Control` headers.
+    ///
+    /// Responses are not cached by default.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Do not add default request headers.
     /// By default `Date` and `User-Agent` headers are set.
     pub fn no_default_headers(mut self) -> Self {
@@ -105,7 +383,7 @@ impl ClientBuilder {
         match HeaderName::try_from(key) {
             Ok(key) => match value.try_into() {
                 Ok(value) => {
-                    self.config.headers.append(key, value);
+                    self.headers.append(key, value);
                 }
                 Err(e) => log::error!("Header value error: {:?}", e),
             },
@@ -138,8 +416,50 @@ impl ClientBuilder {
     }
 
     /// Finish build process and create `Client` instance.
-    pub fn finish(self) -> Client {
-        Client(Rc::new(self.config))
+    pub fn finish(mut self) -> Client {
+        // inside of redirects, so every redirect sees stored cookies
+        if let Some(store) = self.cookie_store.take() {
+            self = self.wrap(store);
+        }
+        // registered even if disabled, so requests could override it
+        let retry = self.retry.take().unwrap_or_else(Retry::disabled);
+        self = self.wrap(retry);
+        // every redirect is cached by its own url
+        if let Some(cache) = self.cache.take() {
+            self = self.wrap(cache);
+        }
+
+        if self.allow_redirects && self.max_redirects > 0 {
+            let redirect = Redirect::new()
+                .max_redirects(self.max_redirects)
+                .same_origin_only(self.same_origin_redirects)
+                .allow_https_downgrade(self.https_downgrade);
+            self = self.wrap(redirect);
+        }
+
+        let (connector, metrics) = match self.connector {
+            ConnectorSource::Builder(build) => {
+                let (connector, metrics) = build(&self.connector_config);
+                (connector, Some(metrics))
+            }
+            ConnectorSource::Service(connector) => {
+                if self.connector_config.is_set() {
+                    log::warn!("Pool settings do not apply to custom connector service");
+                }
+                (connector, None)
+            }
+        };
+        let mut srv = boxed::service(ConnectorService(connector));
+        for mw in self.middlewares {
+            srv = mw(srv);
+        }
+        Client(Rc::new(ClientConfig {
+            connector: RefCell::new(Box::new(MiddlewareConnector::new(srv))),
+            headers: self.headers,
+            timeout: self.timeout,
+            read_timeout: self.read_timeout,
+            metrics,
+        }))
     }
 }
 
@@ -152,7 +472,6 @@ mod tests {
         let client = ClientBuilder::new().basic_auth("username", Some("password"));
         assert_eq!(
             client
-                .config
                 .headers
                 .get(header::AUTHORIZATION)
                 .unwrap()
@@ -164,7 +483,6 @@ mod tests {
         let client = ClientBuilder::new().basic_auth("username", None);
         assert_eq!(
             client
-                .config
                 .headers
                 .get(header::AUTHORIZATION)
                 .unwrap()
@@ -174,12 +492,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn client_pool_config() {
+        let client = ClientBuilder::new().finish();
+        assert!(client.pool_metrics().unwrap().snapshot().is_empty());
+
+        let client = ClientBuilder::new()
+            .connector(Connector::new().finish())
+            .finish();
+        assert!(client.pool_metrics().is_none());
+
+        // custom connector service is kept
+        let client = ClientBuilder::new()
+            .connector(Connector::new().finish())
+            .limit_per_host(2)
+            .conn_keep_alive(Duration::from_secs(5))
+            .finish();
+        assert!(client.pool_metrics().is_none());
+
+        let client = ClientBuilder::new()
+            .connector_builder(Connector::new().limit(1))
+            .limit_per_host(2)
+            .finish();
+        assert!(client.pool_metrics().is_some());
+    }
+
+    #[test]
+    fn client_connector_config() {
+        let builder = ClientBuilder::new();
+        assert!(!builder.connector_config.is_set());
+
+        let builder = builder.limit(10).connect_timeout(Duration::from_secs(3));
+        assert_eq!(builder.connector_config.limit, Some(10));
+        assert_eq!(builder.connector_config.limit_per_host, None);
+        assert!(builder.connector_config.is_set());
+    }
+
     #[test]
     fn client_bearer_auth() {
         let client = ClientBuilder::new().bearer_auth("someS3cr3tAutht0k3n");
         assert_eq!(
             client
-                .config
                 .headers
                 .get(header::AUTHORIZATION)
                 .unwrap()
@@ -1,6 +1,8 @@
+use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{fmt, io, net};
 
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
@@ -12,9 +14,12 @@ use actix_http::h1::ClientCodec;
 use actix_http::http::HeaderMap;
 use actix_http::{RequestHead, RequestHeadType, ResponseHead};
 use actix_service::Service;
-use futures::future::{FutureExt, LocalBoxFuture};
+use futures::future::{select, Either, FutureExt, LocalBoxFuture};
+use tokio_timer::delay_for;
 
+use crate::middleware::trace_connect;
 use crate::response::ClientResponse;
+use crate::sender::Timeouts;
 
 pub(crate) struct ConnectorWrapper<T>(pub T);
 
@@ -72,13 +77,16 @@ where
         addr: Option<net::SocketAddr>,
     ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
         // connect to the host
+        let timeout = Timeouts::connect(&head);
         let fut = self.0.call(ClientConnect {
             uri: head.uri.clone(),
             addr,
+            handshake_timeout: Timeouts::handshake(&head),
         });
 
         async move {
-            let connection = fut.await?;
+            let connection = connect(fut, timeout).await?;
+            trace_connect(&head);
 
             // send request
             connection
@@ -86,7 +94,7 @@ where
                 .await
                 .map(|(head, payload)| ClientResponse::new(head, payload))
         }
-            .boxed_local()
+        .boxed_local()
     }
 
     fn send_request_extra(
@@ -97,13 +105,16 @@ where
         addr: Option<net::SocketAddr>,
     ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
         // connect to the host
+        let timeout = Timeouts::connect(&head);
         let fut = self.0.call(ClientConnect {
             uri: head.uri.clone(),
             addr,
+            handshake_timeout: Timeouts::handshake(&head),
         });
 
         async move {
-            let connection = fut.await?;
+            let connection = connect(fut, timeout).await?;
+            trace_connect(&head);
 
             // send request
             let (head, payload) = connection
@@ -112,7 +123,7 @@ where
 
             Ok(ClientResponse::new(head, payload))
         }
-            .boxed_local()
+        .boxed_local()
     }
 
     fn open_tunnel(
@@ -124,13 +135,16 @@ where
         Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError>,
     > {
         // connect to the host
+        let timeout = Timeouts::connect(&head);
         let fut = self.0.call(ClientConnect {
             uri: head.uri.clone(),
             addr,
+            handshake_timeout: Timeouts::handshake(&head),
         });
 
         async move {
-            let connection = fut.await?;
+            let connection = connect(fut, timeout).await?;
+            trace_connect(&head);
 
             // send request
             let (head, framed) =
@@ -139,7 +153,7 @@ where
             let framed = framed.map_io(|io| BoxedSocket(Box::new(Socket(io))));
             Ok((head, framed))
         }
-            .boxed_local()
+        .boxed_local()
     }
 
     fn open_tunnel_extra(
@@ -152,13 +166,16 @@ where
         Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError>,
     > {
         // connect to the host
+        let timeout = Timeouts::connect(&head);
         let fut = self.0.call(ClientConnect {
             uri: head.uri.clone(),
             addr,
+            handshake_timeout: Timeouts::handshake(&head),
         });
 
         async move {
-            let connection = fut.await?;
+            let connection = connect(fut, timeout).await?;
+            trace_connect(&head);
 
             // send request
             let (head, framed) = connection
@@ -168,7 +185,21 @@ where
             let framed = framed.map_io(|io| BoxedSocket(Box::new(Socket(io))));
             Ok((head, framed))
         }
-            .boxed_local()
+        .boxed_local()
+    }
+}
+
+/// Wait for connection, fails if it is not established within `timeout`.
+async fn connect<F, C>(fut: F, timeout: Option<Duration>) -> Result<C, ConnectError>
+where
+    F: Future<Output = Result<C, ConnectError>>,
+{
+    match timeout {
+        None => fut.await,
+        Some(timeout) => match select(Box::pin(fut), delay_for(timeout)).await {
+            Either::Left((res, _)) => res,
+            Either::Right(_) => Err(ConnectError::Timeout),
+        },
     }
 }
 
@@ -41,6 +41,23 @@ pub enum WsClientError {
     SendRequest(SendRequestError),
 }
 
+/// Timeouts of the handshake are returned as `GatewayTimeout`, other
+/// errors as `BadGateway`
+impl ResponseError for WsClientError {
+    fn error_response(&self) -> Response {
+        match self {
+            WsClientError::SendRequest(SendRequestError::Timeout)
+            | WsClientError::SendRequest(SendRequestError::Connect(
+                ConnectError::Timeout,
+            ))
+            | WsClientError::SendRequest(SendRequestError::Connect(
+                ConnectError::HandshakeTimeout,
+            )) => Response::new(StatusCode::GATEWAY_TIMEOUT),
+            _ => Response::new(StatusCode::BAD_GATEWAY),
+        }
+    }
+}
+
 impl From<InvalidUrl> for WsClientError {
     fn from(err: InvalidUrl) -> Self {
         WsClientError::SendRequest(err.into())
@@ -13,6 +13,7 @@ use actix_http::http::{
 };
 use actix_http::{Error, RequestHead};
 
+use crate::multipart::Form;
 use crate::sender::{RequestSender, SendClientRequest};
 use crate::ClientConfig;
 
@@ -79,6 +80,17 @@ impl FrozenClientRequest {
         )
     }
 
+    /// Send a `multipart/form-data` body.
+    pub fn send_multipart(&self, form: Form) -> SendClientRequest {
+        RequestSender::Rc(self.head.clone(), None).send_multipart(
+            self.addr,
+            self.response_decompress,
+            self.timeout,
+            self.config.as_ref(),
+            form,
+        )
+    }
+
     /// Send a streaming body.
     pub fn send_stream<S, E>(&self, stream: S) -> SendClientRequest
     where
@@ -200,6 +212,21 @@ impl FrozenSendBuilder {
         )
     }
 
+    /// Complete request construction and send a `multipart/form-data` body.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        if let Some(e) = self.err {
+            return e.into();
+        }
+
+        RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_multipart(
+            self.req.addr,
+            self.req.response_decompress,
+            self.req.timeout,
+            self.req.config.as_ref(),
+            form,
+        )
+    }
+
     /// Complete request construction and send a streaming body.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
@@ -25,7 +25,10 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Duration;
 
-pub use actix_http::{client::Connector, cookie, http};
+#[cfg(unix)]
+pub use actix_http::client::UnixConnector;
+pub use actix_http::client::{Connector, HostStats, PoolMetrics, Proxy, Resolve};
+pub use actix_http::{cookie, http};
 
 use actix_http::http::{HeaderMap, HttpTryFrom, Method, Uri};
 use actix_http::RequestHead;
@@ -34,6 +37,8 @@ mod builder;
 mod connect;
 pub mod error;
 mod frozen;
+pub mod middleware;
+pub mod multipart;
 mod request;
 mod response;
 mod sender;
@@ -47,7 +52,7 @@ pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
 pub use self::sender::SendClientRequest;
 
-use self::connect::{Connect, ConnectorWrapper};
+use self::connect::Connect;
 
 /// An HTTP Client
 ///
@@ -77,17 +82,13 @@ pub(crate) struct ClientConfig {
     pub(crate) connector: RefCell<Box<dyn Connect>>,
     pub(crate) headers: HeaderMap,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) read_timeout: Option<Duration>,
+    pub(crate) metrics: Option<PoolMetrics>,
 }
 
 impl Default for Client {
     fn default() -> Self {
-        Client(Rc::new(ClientConfig {
-            connector: RefCell::new(Box::new(ConnectorWrapper(
-                Connector::new().finish(),
-            ))),
-            headers: HeaderMap::new(),
-            timeout: Some(Duration::from_secs(5)),
-        }))
+        ClientBuilder::new().finish()
     }
 }
 
@@ -102,6 +103,14 @@ impl Client {
         ClientBuilder::new()
     }
 
+    /// Connection pool usage of the client.
+    ///
+    /// Returns `None` if client uses custom connector, use
+    /// `Connector::metrics()` to observe it instead.
+    pub fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.0.metrics.clone()
+    }
+
     /// Construct HTTP request.
     pub fn request<U>(&self, method: Method, url: U) -> ClientRequest
     where
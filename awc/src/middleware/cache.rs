@@ -0,0 +1,749 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use actix_http::clock;
+use actix_http::error::PayloadError;
+use actix_http::http::header::{self, HeaderMap, HeaderName, HeaderValue, HttpDate};
+use actix_http::http::{Method, StatusCode, Version};
+use actix_http::{HttpMessage, Payload, ResponseHead};
+use actix_service::Service;
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, FutureExt, LocalBoxFuture};
+use futures::{stream, StreamExt};
+
+use super::{ConnectRequest, ConnectResponse, Transform};
+use crate::error::SendRequestError;
+use crate::response::ClientResponse;
+
+/// Storage of cached responses.
+///
+/// Responses are stored by url of the request.
+pub trait CacheStore {
+    /// Get response stored for `key`.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Store response for `key`, replacing previous one.
+    fn put(&self, key: &str, res: CachedResponse);
+
+    /// Remove response stored for `key`.
+    fn remove(&self, key: &str);
+}
+
+/// Response stored in the cache.
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    // request headers selected by `Vary`
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    request_time: SystemTime,
+    response_time: SystemTime,
+}
+
+impl CachedResponse {
+    /// Response status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Response body.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Time the response is received at.
+    pub fn response_time(&self) -> SystemTime {
+        self.response_time
+    }
+
+    /// Stored response matches request headers selected by `Vary`.
+    fn matches(&self, req: &mut ConnectRequest) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_header(req, name).as_ref() == value.as_ref())
+    }
+
+    /// Freshness lifetime, RFC 9111 section 4.2.1
+    fn freshness_lifetime(&self) -> Duration {
+        let cc = Directives::parse(&self.headers);
+        if let Some(max_age) = cc.max_age {
+            return max_age;
+        }
+        let date =
+            header_date(&self.headers, &header::DATE).unwrap_or(self.response_time);
+        if self.headers.contains_key(header::EXPIRES) {
+            // invalid date means the response is already expired
+            return header_date(&self.headers, &header::EXPIRES)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_else(|| Duration::from_secs(0));
+        }
+        // heuristic freshness, 10% of time since last modification
+        header_date(&self.headers, &header::LAST_MODIFIED)
+            .and_then(|modified| date.duration_since(modified).ok())
+            .map(|age| age / 10)
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    /// Current age, RFC 9111 section 4.2.3
+    fn current_age(&self, now: SystemTime) -> Duration {
+        let zero = Duration::from_secs(0);
+        let apparent_age = header_date(&self.headers, &header::DATE)
+            .and_then(|date| self.response_time.duration_since(date).ok())
+            .unwrap_or(zero);
+        let age_value = self
+            .headers
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(zero);
+        let response_delay = self
+            .response_time
+            .duration_since(self.request_time)
+            .unwrap_or(zero);
+        let resident_time = now.duration_since(self.response_time).unwrap_or(zero);
+
+        apparent_age.max(age_value + response_delay) + resident_time
+    }
+
+    fn has_validators(&self) -> bool {
+        self.headers.contains_key(header::ETAG)
+            || self.headers.contains_key(header::LAST_MODIFIED)
+    }
+
+    /// Update stored headers with headers of `304 Not Modified` response.
+    fn update(&mut self, headers: &HeaderMap, request_time: SystemTime) {
+        for name in headers.keys() {
+            if name == header::CONTENT_LENGTH {
+                continue;
+            }
+            self.headers.remove(name.clone());
+            for value in headers.get_all(name) {
+                self.headers.append(name.clone(), value.clone());
+            }
+        }
+        self.request_time = request_time;
+        self.response_time = clock::system_now();
+    }
+
+    fn to_response(&self, now: SystemTime) -> ClientResponse {
+        let mut head = ResponseHead::new(self.status);
+        head.version = self.version;
+        head.headers = self.headers.clone();
+        if let Ok(age) =
+            HeaderValue::from_str(&self.current_age(now).as_secs().to_string())
+        {
+            head.headers.insert(header::AGE, age);
+        }
+        let payload = if self.body.is_empty() {
+            Payload::None
+        } else {
+            let body = self.body.clone();
+            Payload::Stream(Box::pin(stream::once(ok::<_, PayloadError>(body))))
+        };
+        ClientResponse::new(head, payload)
+    }
+}
+
+/// In-memory cache store.
+///
+/// Once store is full, the least recently stored response is evicted.
+/// Store is shared between its clones.
+#[derive(Clone)]
+pub struct MemoryStore(Rc<RefCell<MemoryInner>>);
+
+struct MemoryInner {
+    capacity: usize,
+    entries: HashMap<String, CachedResponse>,
+    order: VecDeque<String>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::new(1024)
+    }
+}
+
+impl MemoryStore {
+    /// Create store for `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        MemoryStore(Rc::new(RefCell::new(MemoryInner {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        })))
+    }
+
+    /// Number of stored responses.
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// Store does not contain responses.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().entries.is_empty()
+    }
+
+    /// Remove all stored responses.
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.0.borrow().entries.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, res: CachedResponse) {
+        let mut inner = self.0.borrow_mut();
+        if inner.entries.insert(key.to_owned(), res).is_some() {
+            inner.order.retain(|k| k != key);
+        }
+        inner.order.push_back(key.to_owned());
+
+        while inner.entries.len() > inner.capacity {
+            match inner.order.pop_front() {
+                Some(key) => {
+                    inner.entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let mut inner = self.0.borrow_mut();
+        if inner.entries.remove(key).is_some() {
+            inner.order.retain(|k| k != key);
+        }
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Middleware that caches responses to `GET` requests, RFC 9111.
+///
+/// Client is a private cache, fresh responses are served from the store
+/// according to `Cache-Control`, `Expires` and `Last-Modified` headers.
+/// Stale responses with `ETag` or `Last-Modified` validators are
+/// revalidated with conditional request. Successful responses to unsafe
+/// methods invalidate stored response of the url.
+///
+/// Response body is read completely before it is stored, responses with
+/// body larger than max size are not cached.
+///
+/// ```rust
+/// use awc::middleware::{Cache, MemoryStore};
+///
+/// let store = MemoryStore::new(100);
+/// let client = awc::Client::build()
+///     .cache(Cache::with_store(store.clone()))
+///     .finish();
+/// assert!(store.is_empty());
+/// ```
+#[derive(Clone)]
+pub struct Cache {
+    store: Rc<dyn CacheStore>,
+    max_size: usize,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
+impl Cache {
+    /// Cache responses in `MemoryStore`.
+    pub fn new() -> Self {
+        Cache::with_store(MemoryStore::default())
+    }
+
+    /// Cache responses in custom store.
+    pub fn with_store<T: CacheStore + 'static>(store: T) -> Self {
+        Cache {
+            store: Rc::new(store),
+            max_size: 1_048_576,
+        }
+    }
+
+    /// Set max size of cached response body.
+    ///
+    /// By default max size is 1Mb.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+}
+
+impl<S> Transform<S> for Cache
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Transform = CacheService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        CacheService {
+            service,
+            cache: self,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct CacheService<S> {
+    service: S,
+    cache: Cache,
+}
+
+impl<S> Service for CacheService<S>
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ConnectRequest) -> Self::Future {
+        let key = req.head().uri.to_string();
+        let store = self.cache.store.clone();
+
+        let method = req.head().method.clone();
+        let client = match req {
+            ConnectRequest::Client(..) => true,
+            ConnectRequest::Tunnel(..) => false,
+        };
+
+        if client && !is_safe(&method) {
+            return self
+                .service
+                .call(req)
+                .map(move |res| {
+                    if let Ok(ConnectResponse::Client(ref res)) = res {
+                        if res.status().is_success() || res.status().is_redirection() {
+                            store.remove(&key);
+                        }
+                    }
+                    res
+                })
+                .boxed_local();
+        }
+        if !client || method != Method::GET {
+            return self.service.call(req).boxed_local();
+        }
+
+        let req_cc = Directives::parse_request(&mut req);
+        if req_cc.no_store
+            || request_header(&mut req, &header::IF_NONE_MATCH).is_some()
+            || request_header(&mut req, &header::IF_MODIFIED_SINCE).is_some()
+        {
+            return self.service.call(req).boxed_local();
+        }
+
+        let request_time = clock::system_now();
+        let cached = store.get(&key).filter(|cached| cached.matches(&mut req));
+
+        if let Some(ref cached) = cached {
+            let age = cached.current_age(request_time);
+            let lifetime = cached
+                .freshness_lifetime()
+                .checked_sub(req_cc.min_fresh.unwrap_or_default())
+                .unwrap_or_default();
+            let res_cc = Directives::parse(&cached.headers);
+
+            let max_stale = !res_cc.must_revalidate
+                && req_cc
+                    .max_stale
+                    .map(|stale| age < lifetime + stale)
+                    .unwrap_or(false);
+            let fresh = !req_cc.no_cache
+                && !res_cc.no_cache
+                && req_cc.max_age.map(|max| age <= max).unwrap_or(true)
+                && (lifetime > age || max_stale);
+            if fresh {
+                return ok(ConnectResponse::Client(cached.to_response(request_time)))
+                    .boxed_local();
+            }
+        }
+        if req_cc.only_if_cached {
+            let res = ClientResponse::new(
+                ResponseHead::new(StatusCode::GATEWAY_TIMEOUT),
+                Payload::None,
+            );
+            return ok(ConnectResponse::Client(res)).boxed_local();
+        }
+
+        // revalidate stale response
+        let cached = cached.filter(|cached| cached.has_validators());
+        if let Some(ref cached) = cached {
+            let headers = req.headers_mut();
+            if let Some(etag) = cached.headers.get(header::ETAG) {
+                headers.insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(modified) = cached.headers.get(header::LAST_MODIFIED) {
+                headers.insert(header::IF_MODIFIED_SINCE, modified.clone());
+            }
+        }
+
+        let vary_req = VaryRequest::new(&mut req);
+        let max_size = self.cache.max_size;
+        let fut = self.service.call(req);
+
+        async move {
+            let res = match fut.await? {
+                ConnectResponse::Client(res) => res,
+                res => return Ok(res),
+            };
+
+            if let Some(mut cached) = cached {
+                if res.status() == StatusCode::NOT_MODIFIED {
+                    cached.update(res.headers(), request_time);
+                    store.put(&key, cached.clone());
+                    return Ok(ConnectResponse::Client(
+                        cached.to_response(clock::system_now()),
+                    ));
+                }
+            }
+
+            let vary = match vary_req.select(res.headers()) {
+                Some(vary) if is_storable(&res, &req_cc) => vary,
+                _ => {
+                    store.remove(&key);
+                    return Ok(ConnectResponse::Client(res));
+                }
+            };
+
+            // response is stored once its body is read
+            match read_body(res, max_size).await {
+                Ok((res, body)) => {
+                    let cached = CachedResponse {
+                        status: res.status(),
+                        version: res.version(),
+                        headers: res.headers().clone(),
+                        body,
+                        vary,
+                        request_time,
+                        response_time: clock::system_now(),
+                    };
+                    store.put(&key, cached.clone());
+                    Ok(ConnectResponse::Client(
+                        cached.to_response(clock::system_now()),
+                    ))
+                }
+                Err(res) => {
+                    store.remove(&key);
+                    Ok(ConnectResponse::Client(res))
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Read response body, body that exceeds `max_size` or fails is returned
+/// with the response.
+async fn read_body(
+    mut res: ClientResponse,
+    max_size: usize,
+) -> Result<(ClientResponse, Bytes), ClientResponse> {
+    let mut payload = res.take_payload();
+    let mut buf = BytesMut::new();
+
+    while let Some(item) = payload.next().await {
+        match item {
+            Ok(chunk) if buf.len() + chunk.len() <= max_size => {
+                buf.extend_from_slice(&chunk);
+            }
+            item => {
+                let mut read = Vec::new();
+                if !buf.is_empty() {
+                    read.push(Ok(buf.freeze()));
+                }
+                read.push(item);
+                res.payload =
+                    Payload::Stream(Box::pin(stream::iter(read).chain(payload)));
+                return Err(res);
+            }
+        }
+    }
+    Ok((res, buf.freeze()))
+}
+
+/// Request headers that could be selected by `Vary` of the response.
+struct VaryRequest(HeaderMap);
+
+impl VaryRequest {
+    fn new(req: &mut ConnectRequest) -> Self {
+        let mut headers = req.head().headers.clone();
+        for (name, value) in req.headers_mut().iter() {
+            headers.insert(name.clone(), value.clone());
+        }
+        VaryRequest(headers)
+    }
+
+    /// Values of request headers listed in `Vary`, `None` if response
+    /// varies on every request.
+    fn select(
+        &self,
+        headers: &HeaderMap,
+    ) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+        let mut vary = Vec::new();
+        for value in headers.get_all(header::VARY) {
+            for name in value.to_str().ok()?.split(',') {
+                let name = name.trim();
+                if name == "*" {
+                    return None;
+                }
+                if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                    let value = self.0.get(&name).cloned();
+                    vary.push((name, value));
+                }
+            }
+        }
+        Some(vary)
+    }
+}
+
+/// Response could be stored, RFC 9111 section 3
+fn is_storable(res: &ClientResponse, req_cc: &Directives) -> bool {
+    let cc = Directives::parse(res.headers());
+    if req_cc.no_store || cc.no_store {
+        return false;
+    }
+    let explicit = cc.max_age.is_some() || res.headers().contains_key(header::EXPIRES);
+    let status = match res.status().as_u16() {
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501 => true,
+        _ => false,
+    };
+    let validators = res.headers().contains_key(header::ETAG)
+        || res.headers().contains_key(header::LAST_MODIFIED);
+
+    status && (explicit || validators)
+}
+
+fn is_safe(method: &Method) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE => true,
+        _ => false,
+    }
+}
+
+/// Request header, extra headers of shared head take precedence.
+fn request_header(req: &mut ConnectRequest, name: &HeaderName) -> Option<HeaderValue> {
+    req.headers_mut()
+        .get(name)
+        .cloned()
+        .or_else(|| req.head().headers.get(name).cloned())
+}
+
+fn header_date(headers: &HeaderMap, name: &HeaderName) -> Option<SystemTime> {
+    let value = headers.get(name)?.to_str().ok()?;
+    Some(value.parse::<HttpDate>().ok()?.into())
+}
+
+/// `Cache-Control` directives relevant for private cache.
+#[derive(Default, Debug)]
+struct Directives {
+    no_store: bool,
+    no_cache: bool,
+    must_revalidate: bool,
+    only_if_cached: bool,
+    max_age: Option<Duration>,
+    max_stale: Option<Duration>,
+    min_fresh: Option<Duration>,
+}
+
+impl Directives {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cc = Directives::default();
+
+        for value in headers.get_all(header::CACHE_CONTROL) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            for directive in value.split(',') {
+                let mut parts = directive.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+                let secs = parts
+                    .next()
+                    .and_then(|v| v.trim().trim_matches('"').parse::<u64>().ok())
+                    .map(Duration::from_secs);
+
+                match name.as_str() {
+                    "no-store" => cc.no_store = true,
+                    "no-cache" => cc.no_cache = true,
+                    "must-revalidate" => cc.must_revalidate = true,
+                    "only-if-cached" => cc.only_if_cached = true,
+                    "max-age" => cc.max_age = secs,
+                    // max-stale without value accepts any stale response
+                    "max-stale" => {
+                        cc.max_stale = Some(secs.unwrap_or_else(|| {
+                            Duration::from_secs(u32::max_value().into())
+                        }))
+                    }
+                    "min-fresh" => cc.min_fresh = secs,
+                    _ => (),
+                }
+            }
+        }
+        // legacy `Pragma: no-cache` applies if `Cache-Control` is missing
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            if let Some(pragma) = headers.get(header::PRAGMA) {
+                if pragma.as_bytes().eq_ignore_ascii_case(b"no-cache") {
+                    cc.no_cache = true;
+                }
+            }
+        }
+        cc
+    }
+
+    fn parse_request(req: &mut ConnectRequest) -> Self {
+        let headers = VaryRequest::new(req).0;
+        Directives::parse(&headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(
+        headers: &[(&'static str, &str)],
+        response_time: SystemTime,
+    ) -> CachedResponse {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(
+                HeaderName::from_static(*name),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        CachedResponse {
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers: map,
+            body: Bytes::from_static(b"body"),
+            vary: Vec::new(),
+            request_time: response_time,
+            response_time,
+        }
+    }
+
+    #[test]
+    fn test_directives() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache, Max-Age=\"60\", max-stale, private"),
+        );
+        let cc = Directives::parse(&headers);
+        assert!(cc.no_cache);
+        assert!(!cc.no_store);
+        assert_eq!(cc.max_age, Some(Duration::from_secs(60)));
+        assert!(cc.max_stale.is_some());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+        assert!(Directives::parse(&headers).no_cache);
+    }
+
+    #[test]
+    fn test_freshness() {
+        let now = SystemTime::now();
+        let date = HttpDate::from(now).to_string();
+
+        let res = cached(
+            &[("cache-control", "max-age=60"), ("date", date.as_str())],
+            now,
+        );
+        assert_eq!(res.freshness_lifetime(), Duration::from_secs(60));
+
+        let expires = HttpDate::from(now + Duration::from_secs(120)).to_string();
+        let res = cached(
+            &[("date", date.as_str()), ("expires", expires.as_str())],
+            now,
+        );
+        assert_eq!(res.freshness_lifetime(), Duration::from_secs(120));
+
+        let res = cached(&[("date", date.as_str()), ("expires", "0")], now);
+        assert_eq!(res.freshness_lifetime(), Duration::from_secs(0));
+
+        let modified = HttpDate::from(now - Duration::from_secs(1000)).to_string();
+        let res = cached(
+            &[
+                ("date", date.as_str()),
+                ("last-modified", modified.as_str()),
+            ],
+            now,
+        );
+        assert_eq!(res.freshness_lifetime(), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_age() {
+        let now = SystemTime::now();
+        let date = HttpDate::from(now - Duration::from_secs(10)).to_string();
+
+        let res = cached(&[("date", date.as_str()), ("age", "30")], now);
+        let age = res.current_age(now + Duration::from_secs(5));
+        assert_eq!(age.as_secs(), 35);
+
+        let res = cached(&[("date", date.as_str())], now);
+        let age = res.current_age(now);
+        assert_eq!(age.as_secs(), 10);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new(2);
+        let res = cached(&[], SystemTime::now());
+        store.put("a", res.clone());
+        store.put("b", res.clone());
+        store.put("a", res.clone());
+        store.put("c", res);
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b").is_none());
+        assert!(store.get("a").is_some());
+
+        store.remove("a");
+        assert_eq!(store.len(), 1);
+        store.clear();
+        assert!(store.is_empty());
+    }
+}
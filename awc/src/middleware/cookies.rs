@@ -0,0 +1,429 @@
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_http::clock;
+use actix_http::cookie::{Cookie, USERINFO};
+use actix_http::http::header::{self, HeaderMap, HeaderValue};
+use actix_http::http::Uri;
+use actix_service::Service;
+use futures::future::{FutureExt, LocalBoxFuture};
+use percent_encoding::percent_encode;
+
+use super::{ConnectRequest, ConnectResponse, Transform};
+use crate::error::SendRequestError;
+
+/// Client cookie store.
+///
+/// Middleware captures cookies set by responses and attaches them to
+/// subsequent requests according to their `Domain`, `Path`, `Secure`,
+/// `Expires` and `Max-Age` attributes. Cookies added to the request with
+/// `ClientRequest::cookie()` take precedence over stored cookies with
+/// the same name.
+///
+/// Store is shared between its clones, so it could be inspected while
+/// the client is in use.
+///
+/// ```rust
+/// use awc::middleware::CookieStore;
+///
+/// let store = CookieStore::new();
+/// let client = awc::Client::build()
+///     .cookie_store(store.clone())
+///     .finish();
+///
+/// let uri = "https://www.rust-lang.org/".parse().unwrap();
+/// assert!(store.cookies(&uri).is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub struct CookieStore(Rc<RefCell<Vec<StoredCookie>>>);
+
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.map(|t| t <= now).unwrap_or(false)
+    }
+
+    fn matches(&self, uri: &Uri, host: &str) -> bool {
+        let domain_match = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain_match
+            && path_match(uri.path(), &self.path)
+            && (!self.secure || is_secure(uri))
+    }
+}
+
+impl CookieStore {
+    /// Create empty cookie store.
+    pub fn new() -> Self {
+        CookieStore::default()
+    }
+
+    /// Cookies that are sent with request to `uri`.
+    pub fn cookies(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return Vec::new(),
+        };
+        let now = clock::system_now();
+        let mut cookies = self.0.borrow_mut();
+        cookies.retain(|c| !c.is_expired(now));
+
+        let mut matched: Vec<_> =
+            cookies.iter().filter(|c| c.matches(uri, &host)).collect();
+        // cookies with longer paths are listed first
+        matched.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+        matched.into_iter().map(|c| c.cookie.clone()).collect()
+    }
+
+    /// Store cookie as if it was set by response to `uri`.
+    ///
+    /// Cookie is ignored if its domain does not match `uri`.
+    pub fn insert(&self, cookie: Cookie<'_>, uri: &Uri) {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return,
+        };
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) if !domain.is_empty() => {
+                let domain = domain.to_ascii_lowercase();
+                if domain != host
+                    && (!domain.contains('.') || !domain_match(&host, &domain))
+                {
+                    return;
+                }
+                (domain, false)
+            }
+            _ => (host, true),
+        };
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(uri.path()).to_owned(),
+        };
+        let now = clock::system_now();
+        let expires = if let Some(age) = cookie.max_age() {
+            let secs = age.num_seconds();
+            if secs <= 0 {
+                Some(UNIX_EPOCH)
+            } else {
+                Some(now + Duration::from_secs(secs as u64))
+            }
+        } else if let Some(tm) = cookie.expires() {
+            let secs = tm.to_timespec().sec;
+            if secs <= 0 {
+                Some(UNIX_EPOCH)
+            } else {
+                Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+            }
+        } else {
+            None
+        };
+
+        let stored = StoredCookie {
+            secure: cookie.secure().unwrap_or(false),
+            cookie: cookie.into_owned(),
+            domain,
+            host_only,
+            path,
+            expires,
+        };
+
+        let mut cookies = self.0.borrow_mut();
+        cookies.retain(|c| {
+            c.cookie.name() != stored.cookie.name()
+                || c.domain != stored.domain
+                || c.path != stored.path
+        });
+        if !stored.is_expired(now) {
+            cookies.push(stored);
+        }
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    /// Capture `Set-Cookie` headers of response to `uri`.
+    fn capture(&self, uri: &Uri, headers: &HeaderMap) {
+        for value in headers.get_all(header::SET_COOKIE) {
+            if let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|s| Cookie::parse_encoded(s).ok())
+            {
+                self.insert(cookie, uri);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CookieStore")
+            .field("cookies", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+impl<S> Transform<S> for CookieStore
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Transform = CookieStoreService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        CookieStoreService {
+            service,
+            store: self,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct CookieStoreService<S> {
+    service: S,
+    store: CookieStore,
+}
+
+impl<S> Service for CookieStoreService<S>
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ConnectRequest) -> Self::Future {
+        let uri = req.head().uri.clone();
+        let cookies = self.store.cookies(&uri);
+
+        if !cookies.is_empty() {
+            // cookies of the request itself take precedence
+            let current = req.headers_mut().get(header::COOKIE).cloned();
+            let current = current
+                .or_else(|| req.head().headers.get(header::COOKIE).cloned())
+                .and_then(|value| value.to_str().ok().map(|s| s.to_owned()))
+                .unwrap_or_default();
+
+            let mut value = current.clone();
+            for cookie in cookies {
+                let name =
+                    percent_encode(cookie.name().as_bytes(), USERINFO).to_string();
+                if has_cookie(&current, &name) {
+                    continue;
+                }
+                let val = percent_encode(cookie.value().as_bytes(), USERINFO);
+                if !value.is_empty() {
+                    value.push_str("; ");
+                }
+                let _ = write!(&mut value, "{}={}", name, val);
+            }
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                req.headers_mut().insert(header::COOKIE, value);
+            }
+        }
+
+        let store = self.store.clone();
+        self.service
+            .call(req)
+            .map(move |res| {
+                match res {
+                    Ok(ConnectResponse::Client(ref res)) => {
+                        store.capture(&uri, res.headers())
+                    }
+                    Ok(ConnectResponse::Tunnel(ref head, _)) => {
+                        store.capture(&uri, &head.headers)
+                    }
+                    Err(_) => (),
+                }
+                res
+            })
+            .boxed_local()
+    }
+}
+
+fn has_cookie(header: &str, name: &str) -> bool {
+    header
+        .split(';')
+        .any(|pair| pair.split('=').next().map(|n| n.trim()) == Some(name))
+}
+
+fn is_secure(uri: &Uri) -> bool {
+    match uri.scheme_str() {
+        Some("https") | Some("wss") => true,
+        _ => false,
+    }
+}
+
+/// Host matches the cookie domain or its subdomain, RFC 6265 5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.parse::<IpAddr>().is_err()
+        && host.ends_with(domain)
+        && host[..host.len() - domain.len()].ends_with('.')
+}
+
+/// Request path matches the cookie path, RFC 6265 5.1.4
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/')
+                || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Default cookie path for the request path, RFC 6265 5.1.4
+fn default_path(path: &str) -> &str {
+    if !path.starts_with('/') {
+        return "/";
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(idx) => &path[..idx],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    fn names(store: &CookieStore, s: &str) -> Vec<String> {
+        store
+            .cookies(&uri(s))
+            .iter()
+            .map(|c| c.name().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_domain() {
+        let store = CookieStore::new();
+        let origin = uri("http://www.example.com/");
+        store.insert(Cookie::parse("host=1").unwrap(), &origin);
+        store.insert(
+            Cookie::parse("domain=1; Domain=.example.com").unwrap(),
+            &origin,
+        );
+        store.insert(
+            Cookie::parse("other=1; Domain=rust-lang.org").unwrap(),
+            &origin,
+        );
+        store.insert(Cookie::parse("tld=1; Domain=com").unwrap(), &origin);
+
+        assert_eq!(names(&store, "http://www.example.com/"), ["host", "domain"]);
+        assert_eq!(names(&store, "http://api.example.com/"), ["domain"]);
+        assert_eq!(names(&store, "http://example.com/"), ["domain"]);
+        assert!(names(&store, "http://notexample.com/").is_empty());
+        assert!(names(&store, "http://rust-lang.org/").is_empty());
+    }
+
+    #[test]
+    fn test_path() {
+        assert_eq!(default_path(""), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path("/login"), "/");
+        assert_eq!(default_path("/account/login"), "/account");
+        assert!(path_match("/account", "/account"));
+        assert!(path_match("/account/settings", "/account"));
+        assert!(path_match("/account/settings", "/"));
+        assert!(!path_match("/accounts", "/account"));
+
+        let store = CookieStore::new();
+        store.insert(
+            Cookie::parse("a=1").unwrap(),
+            &uri("http://example.com/account/login"),
+        );
+        store.insert(
+            Cookie::parse("b=1; Path=/").unwrap(),
+            &uri("http://example.com/account/login"),
+        );
+        assert_eq!(names(&store, "http://example.com/account/x"), ["a", "b"]);
+        assert_eq!(names(&store, "http://example.com/"), ["b"]);
+    }
+
+    #[test]
+    fn test_secure_and_expiry() {
+        let store = CookieStore::new();
+        let origin = uri("https://example.com/");
+        store.insert(Cookie::parse("secure=1; Secure").unwrap(), &origin);
+        store.insert(Cookie::parse("session=1").unwrap(), &origin);
+        store.insert(Cookie::parse("old=1; Max-Age=0").unwrap(), &origin);
+        store.insert(
+            Cookie::parse("past=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT").unwrap(),
+            &origin,
+        );
+        store.insert(Cookie::parse("age=1; Max-Age=3600").unwrap(), &origin);
+
+        assert_eq!(
+            names(&store, "https://example.com/"),
+            ["secure", "session", "age"]
+        );
+        assert_eq!(names(&store, "http://example.com/"), ["session", "age"]);
+
+        // cookie is replaced, then removed
+        store.insert(Cookie::parse("session=2").unwrap(), &origin);
+        let cookies = store.cookies(&origin);
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies[2].name_value(), ("session", "2"));
+        store.insert(Cookie::parse("session=; Max-Age=0").unwrap(), &origin);
+        assert_eq!(names(&store, "https://example.com/"), ["secure", "age"]);
+
+        store.clear();
+        assert!(store.cookies(&origin).is_empty());
+    }
+
+    #[test]
+    fn test_expiry_mock_clock() {
+        let clock = clock::MockClock::new();
+        let store = CookieStore::new();
+        let origin = uri("https://example.com/");
+        store.insert(Cookie::parse("age=1; Max-Age=3600").unwrap(), &origin);
+        assert_eq!(names(&store, "https://example.com/"), ["age"]);
+
+        clock.advance(Duration::from_secs(3601));
+        assert!(store.cookies(&origin).is_empty());
+    }
+
+    #[test]
+    fn test_has_cookie() {
+        assert!(has_cookie("a=1; b=2", "b"));
+        assert!(!has_cookie("a=1; b=2", "c"));
+        assert!(!has_cookie("", "a"));
+    }
+}
@@ -0,0 +1,394 @@
+//! Client middlewares
+//!
+//! Middleware wraps connector service of the client, so it sees every
+//! outgoing request including websocket handshakes.
+//!
+//! ```rust
+//! use actix_service::Service;
+//! use awc::middleware::{ConnectRequest, ConnectResponse, Transform};
+//! use awc::error::SendRequestError;
+//! use awc::http::header::{HeaderValue, AUTHORIZATION};
+//! use std::task::{Context, Poll};
+//!
+//! struct Auth;
+//!
+//! impl<S> Transform<S> for Auth
+//! where
+//!     S: Service<
+//!         Request = ConnectRequest,
+//!         Response = ConnectResponse,
+//!         Error = SendRequestError,
+//!     >,
+//! {
+//!     type Transform = AuthService<S>;
+//!
+//!     fn new_transform(self, service: S) -> Self::Transform {
+//!         AuthService(service)
+//!     }
+//! }
+//!
+//! struct AuthService<S>(S);
+//!
+//! impl<S> Service for AuthService<S>
+//! where
+//!     S: Service<
+//!         Request = ConnectRequest,
+//!         Response = ConnectResponse,
+//!         Error = SendRequestError,
+//!     >,
+//! {
+//!     type Request = ConnectRequest;
+//!     type Response = ConnectResponse;
+//!     type Error = SendRequestError;
+//!     type Future = S::Future;
+//!
+//!     fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+//!         self.0.poll_ready(cx)
+//!     }
+//!
+//!     fn call(&mut self, mut req: ConnectRequest) -> Self::Future {
+//!         req.headers_mut()
+//!             .insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+//!         self.0.call(req)
+//!     }
+//! }
+//!
+//! let client = awc::Client::build().wrap(Auth).finish();
+//! ```
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::{io, net};
+
+use actix_codec::Framed;
+use actix_http::body::Body;
+use actix_http::h1::ClientCodec;
+use actix_http::http::{ConnectionType, HeaderMap};
+use actix_http::{RequestHead, RequestHeadType, ResponseHead};
+use actix_service::boxed::BoxedService;
+use actix_service::Service;
+use futures::future::{poll_fn, FutureExt, LocalBoxFuture};
+
+use crate::connect::{BoxedSocket, Connect};
+use crate::error::SendRequestError;
+use crate::response::ClientResponse;
+use crate::sender::Timeouts;
+
+mod cache;
+mod cookies;
+mod redirect;
+mod retry;
+mod trace;
+
+pub use self::cache::{Cache, CacheStore, CachedResponse, MemoryStore};
+pub use self::cookies::CookieStore;
+pub use self::redirect::Redirect;
+pub use self::retry::Retry;
+pub(crate) use self::trace::trace_connect;
+pub use self::trace::{Timing, Trace, TraceHooks};
+
+/// Type of the service wrapped by client middlewares.
+pub type ConnectService =
+    BoxedService<ConnectRequest, ConnectResponse, SendRequestError>;
+
+/// Client middleware factory.
+///
+/// Unlike server middlewares, client middlewares are constructed
+/// synchronously while the client is built.
+pub trait Transform<S> {
+    /// Middleware service
+    type Transform: Service<
+        Request = ConnectRequest,
+        Response = ConnectResponse,
+        Error = SendRequestError,
+    >;
+
+    /// Wrap `service` with middleware.
+    fn new_transform(self, service: S) -> Self::Transform;
+}
+
+/// Outgoing request passed through client middlewares.
+pub enum ConnectRequest {
+    /// Regular request with body and optional socket address.
+    Client(RequestHeadType, Body, Option<net::SocketAddr>),
+    /// Request that upgrades connection, i.e. websocket handshake.
+    Tunnel(RequestHeadType, Option<net::SocketAddr>),
+}
+
+impl ConnectRequest {
+    /// Request head
+    pub fn head(&self) -> &RequestHead {
+        match self {
+            ConnectRequest::Client(head, _, _) | ConnectRequest::Tunnel(head, _) => {
+                head.as_ref()
+            }
+        }
+    }
+
+    /// Mutable reference to request headers.
+    ///
+    /// Frozen requests share their head, for them this method returns
+    /// extra headers that override headers of the shared head.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        let head = match self {
+            ConnectRequest::Client(head, _, _) | ConnectRequest::Tunnel(head, _) => head,
+        };
+        match head {
+            RequestHeadType::Owned(head) => &mut head.headers,
+            RequestHeadType::Rc(_, extra) => extra.get_or_insert_with(HeaderMap::new),
+        }
+    }
+}
+
+/// Response of the connector service.
+pub enum ConnectResponse {
+    /// Response to `ConnectRequest::Client`
+    Client(ClientResponse),
+    /// Response head and framed connection for `ConnectRequest::Tunnel`
+    Tunnel(ResponseHead, Framed<BoxedSocket, ClientCodec>),
+}
+
+impl ConnectResponse {
+    /// Unwrap client response.
+    ///
+    /// Returns error if middleware responded with a tunnel.
+    pub fn into_client_response(self) -> Result<ClientResponse, SendRequestError> {
+        match self {
+            ConnectResponse::Client(res) => Ok(res),
+            ConnectResponse::Tunnel(_, _) => {
+                Err(SendRequestError::Send(io::Error::new(
+                    io::ErrorKind::Other,
+                    "ClientResponse is expected, got tunnel response",
+                )))
+            }
+        }
+    }
+
+    /// Unwrap tunnel response.
+    ///
+    /// Returns error if middleware responded with a client response.
+    pub fn into_tunnel_response(
+        self,
+    ) -> Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError> {
+        match self {
+            ConnectResponse::Tunnel(head, framed) => Ok((head, framed)),
+            ConnectResponse::Client(_) => Err(SendRequestError::Send(io::Error::new(
+                io::ErrorKind::Other,
+                "Tunnel response is expected, got ClientResponse",
+            ))),
+        }
+    }
+}
+
+/// Innermost service, sends requests with client connector.
+pub(crate) struct ConnectorService(pub(crate) Box<dyn Connect>);
+
+impl Service for ConnectorService {
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: ConnectRequest) -> Self::Future {
+        match req {
+            ConnectRequest::Client(RequestHeadType::Owned(head), body, addr) => self
+                .0
+                .send_request(head, body, addr)
+                .map(|res| res.map(ConnectResponse::Client))
+                .boxed_local(),
+            ConnectRequest::Client(RequestHeadType::Rc(head, extra), body, addr) => self
+                .0
+                .send_request_extra(head, extra, body, addr)
+                .map(|res| res.map(ConnectResponse::Client))
+                .boxed_local(),
+            ConnectRequest::Tunnel(RequestHeadType::Owned(head), addr) => self
+                .0
+                .open_tunnel(head, addr)
+                .map(|res| {
+                    res.map(|(head, framed)| ConnectResponse::Tunnel(head, framed))
+                })
+                .boxed_local(),
+            ConnectRequest::Tunnel(RequestHeadType::Rc(head, extra), addr) => self
+                .0
+                .open_tunnel_extra(head, extra, addr)
+                .map(|res| {
+                    res.map(|(head, framed)| ConnectResponse::Tunnel(head, framed))
+                })
+                .boxed_local(),
+        }
+    }
+}
+
+/// Adapts middleware chain to the client connector interface.
+pub(crate) struct MiddlewareConnector(Rc<RefCell<ConnectService>>);
+
+impl MiddlewareConnector {
+    pub(crate) fn new(srv: ConnectService) -> Self {
+        MiddlewareConnector(Rc::new(RefCell::new(srv)))
+    }
+
+    /// Wait for readiness of the middleware chain and send request.
+    fn call(
+        &self,
+        req: ConnectRequest,
+    ) -> LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>> {
+        let srv = self.0.clone();
+        async move {
+            poll_fn(|cx| srv.borrow_mut().poll_ready(cx)).await?;
+            let fut = srv.borrow_mut().call(req);
+            fut.await
+        }
+        .boxed_local()
+    }
+}
+
+impl Connect for MiddlewareConnector {
+    fn send_request(
+        &mut self,
+        head: RequestHead,
+        body: Body,
+        addr: Option<net::SocketAddr>,
+    ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+        self.call(ConnectRequest::Client(head.into(), body, addr))
+            .map(|res| res.and_then(ConnectResponse::into_client_response))
+            .boxed_local()
+    }
+
+    fn send_request_extra(
+        &mut self,
+        head: Rc<RequestHead>,
+        extra_headers: Option<HeaderMap>,
+        body: Body,
+        addr: Option<net::SocketAddr>,
+    ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+        self.call(ConnectRequest::Client(
+            RequestHeadType::Rc(head, extra_headers),
+            body,
+            addr,
+        ))
+        .map(|res| res.and_then(ConnectResponse::into_client_response))
+        .boxed_local()
+    }
+
+    fn open_tunnel(
+        &mut self,
+        head: RequestHead,
+        addr: Option<net::SocketAddr>,
+    ) -> LocalBoxFuture<
+        'static,
+        Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError>,
+    > {
+        self.call(ConnectRequest::Tunnel(head.into(), addr))
+            .map(|res| res.and_then(ConnectResponse::into_tunnel_response))
+            .boxed_local()
+    }
+
+    fn open_tunnel_extra(
+        &mut self,
+        head: Rc<RequestHead>,
+        extra_headers: Option<HeaderMap>,
+        addr: Option<net::SocketAddr>,
+    ) -> LocalBoxFuture<
+        'static,
+        Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError>,
+    > {
+        self.call(ConnectRequest::Tunnel(
+            RequestHeadType::Rc(head, extra_headers),
+            addr,
+        ))
+        .map(|res| res.and_then(ConnectResponse::into_tunnel_response))
+        .boxed_local()
+    }
+}
+
+/// Copy request head, extra headers of shared head are merged.
+fn copy_head(head: &RequestHeadType) -> RequestHead {
+    let src = head.as_ref();
+    let mut copy = RequestHead::default();
+    copy.uri = src.uri.clone();
+    copy.method = src.method.clone();
+    copy.version = src.version;
+    copy.peer_addr = src.peer_addr;
+    copy.headers = src.headers.clone();
+    if let Some(extra) = head.extra_headers() {
+        for key in extra.keys() {
+            copy.headers.remove(key.clone());
+        }
+        for (key, value) in extra.iter() {
+            copy.headers.append(key.clone(), value.clone());
+        }
+    }
+    // per-request settings
+    if let Some(retry) = src.extensions().get::<Retry>() {
+        copy.extensions_mut().insert(retry.clone());
+    }
+    if let Some(timeouts) = src.extensions().get::<Timeouts>() {
+        copy.extensions_mut().insert(*timeouts);
+    }
+    copy.set_camel_case_headers(src.camel_case_headers());
+    copy.no_chunking(!src.chunked());
+    if let ConnectionType::Close = src.connection_type() {
+        copy.set_connection_type(ConnectionType::Close);
+    }
+    copy
+}
+
+/// Copy body if it could be sent again.
+fn copy_body(body: &Body) -> Option<Body> {
+    match body {
+        Body::None => Some(Body::None),
+        Body::Empty => Some(Body::Empty),
+        Body::Bytes(bytes) => Some(Body::Bytes(bytes.clone())),
+        Body::Message(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_service::boxed;
+    use futures::future::{ready, Ready};
+
+    use super::*;
+
+    struct NotReady(Rc<Cell<usize>>);
+
+    impl Service for NotReady {
+        type Request = ConnectRequest;
+        type Response = ConnectResponse;
+        type Error = SendRequestError;
+        type Future = Ready<Result<ConnectResponse, SendRequestError>>;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Err(SendRequestError::Timeout))
+        }
+
+        fn call(&mut self, _: ConnectRequest) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ready(Err(SendRequestError::TunnelNotSupported))
+        }
+    }
+
+    #[test]
+    fn test_middleware_poll_ready() {
+        actix_rt::System::new("test").block_on(async {
+            let calls = Rc::new(Cell::new(0));
+            let mut connector =
+                MiddlewareConnector::new(boxed::service(NotReady(calls.clone())));
+
+            let res = connector
+                .send_request(RequestHead::default(), Body::Empty, None)
+                .await;
+            match res {
+                Err(SendRequestError::Timeout) => (),
+                _ => panic!("poll_ready error is expected"),
+            }
+            assert_eq!(calls.get(), 0);
+        })
+    }
+}
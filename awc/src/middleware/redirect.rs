@@ -0,0 +1,332 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_http::body::Body;
+use actix_http::http::header::{self, HeaderMap};
+use actix_http::http::{Method, StatusCode, Uri};
+use actix_http::RequestHeadType;
+use actix_service::Service;
+use futures::future::{FutureExt, LocalBoxFuture};
+
+use super::{copy_body, copy_head, ConnectRequest, ConnectResponse, Transform};
+use crate::error::SendRequestError;
+
+/// Middleware that follows redirect responses.
+///
+/// `ClientBuilder` registers this middleware unless redirects are
+/// disabled with `ClientBuilder::disable_redirects()`.
+///
+/// * `303 See Other` is followed with `GET` request without body,
+///   `HEAD` requests stay `HEAD`.
+/// * `301` and `302` turn `POST` requests into `GET`, other methods and
+///   body are preserved.
+/// * `307` and `308` preserve method and body.
+///
+/// Streaming request body can not be sent twice, redirects that need
+/// to resend it are returned to the caller. Returned as well are the last
+/// response if max number of redirects is reached, and redirects that
+/// have no valid `Location` header or violate same-origin policy.
+/// Redirects from `https` to `http` are returned as well, unless
+/// downgrade is allowed with `allow_https_downgrade()`.
+///
+/// `Authorization` and `Cookie` headers are not forwarded to other origins.
+#[derive(Clone, Debug)]
+pub struct Redirect {
+    max_redirects: usize,
+    same_origin_only: bool,
+    https_downgrade: bool,
+}
+
+impl Default for Redirect {
+    fn default() -> Self {
+        Redirect::new()
+    }
+}
+
+impl Redirect {
+    /// Create redirect middleware, it follows up to 10 redirects.
+    pub fn new() -> Self {
+        Redirect {
+            max_redirects: 10,
+            same_origin_only: false,
+            https_downgrade: false,
+        }
+    }
+
+    /// Set max number of redirects.
+    pub fn max_redirects(mut self, num: usize) -> Self {
+        self.max_redirects = num;
+        self
+    }
+
+    /// Follow redirects only within origin of the initial request.
+    pub fn same_origin_only(mut self, val: bool) -> Self {
+        self.same_origin_only = val;
+        self
+    }
+
+    /// Follow redirects from `https` to `http` urls, disabled by default.
+    pub fn allow_https_downgrade(mut self, val: bool) -> Self {
+        self.https_downgrade = val;
+        self
+    }
+}
+
+impl<S> Transform<S> for Redirect
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Transform = RedirectService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        RedirectService {
+            service: Rc::new(RefCell::new(service)),
+            config: self,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct RedirectService<S> {
+    service: Rc<RefCell<S>>,
+    config: Redirect,
+}
+
+impl<S> Service for RedirectService<S>
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ConnectRequest) -> Self::Future {
+        let (mut head, mut body, mut addr) = match req {
+            ConnectRequest::Client(head, body, addr) => (head, body, addr),
+            req => return self.service.borrow_mut().call(req).boxed_local(),
+        };
+        let srv = self.service.clone();
+        let config = self.config.clone();
+
+        async move {
+            let origin = head.as_ref().uri.clone();
+            let mut redirects = 0;
+
+            loop {
+                // keep copy of the request while it could be redirected
+                let prev = if redirects < config.max_redirects {
+                    Some((copy_head(&head), copy_body(&body)))
+                } else {
+                    None
+                };
+
+                let fut = srv
+                    .borrow_mut()
+                    .call(ConnectRequest::Client(head, body, addr));
+                let res = fut.await?;
+
+                let (mut next, next_body) = match prev {
+                    Some(prev) => prev,
+                    None => return Ok(res),
+                };
+                let res = match res {
+                    ConnectResponse::Client(res) => res,
+                    res => return Ok(res),
+                };
+                let uri = match redirect_uri(&next.uri, res.status(), res.headers()) {
+                    Some(uri) => uri,
+                    None => return Ok(ConnectResponse::Client(res)),
+                };
+                if config.same_origin_only && !same_origin(&origin, &uri) {
+                    return Ok(ConnectResponse::Client(res));
+                }
+                if !config.https_downgrade && is_downgrade(&next.uri, &uri) {
+                    return Ok(ConnectResponse::Client(res));
+                }
+
+                // select method and body of the next request
+                let keep_body = match res.status() {
+                    StatusCode::SEE_OTHER => {
+                        if next.method != Method::HEAD {
+                            next.method = Method::GET;
+                        }
+                        false
+                    }
+                    StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND
+                        if next.method == Method::POST =>
+                    {
+                        next.method = Method::GET;
+                        false
+                    }
+                    _ => true,
+                };
+                body = if keep_body {
+                    match next_body {
+                        Some(body) => body,
+                        None => return Ok(ConnectResponse::Client(res)),
+                    }
+                } else {
+                    next.headers.remove(header::CONTENT_TYPE);
+                    next.headers.remove(header::CONTENT_LENGTH);
+                    next.headers.remove(header::TRANSFER_ENCODING);
+                    Body::Empty
+                };
+
+                if !same_origin(&next.uri, &uri) {
+                    next.headers.remove(header::AUTHORIZATION);
+                    next.headers.remove(header::COOKIE);
+                    next.headers.remove(header::HOST);
+                    addr = None;
+                }
+                next.uri = uri;
+
+                // response of the redirect is dropped with its connection
+                drop(res);
+                head = RequestHeadType::Owned(next);
+                redirects += 1;
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Location of the redirect response resolved against request uri.
+fn redirect_uri(base: &Uri, status: StatusCode, headers: &HeaderMap) -> Option<Uri> {
+    match status {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => (),
+        _ => return None,
+    }
+    let location = headers.get(header::LOCATION)?.to_str().ok()?;
+    resolve(base, location)
+}
+
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    // fragment is not sent to the server
+    let location = location.split('#').next().unwrap_or("");
+
+    if let Ok(uri) = location.parse::<Uri>() {
+        if uri.scheme_part().is_some() && uri.authority_part().is_some() {
+            return Some(uri);
+        }
+    }
+
+    let scheme = base.scheme_str()?;
+    let authority = base.authority_part()?;
+
+    let path = if location.starts_with("//") {
+        return format!("{}:{}", scheme, location).parse().ok();
+    } else if location.starts_with('/') {
+        location.to_owned()
+    } else if location.is_empty() || location.starts_with('?') {
+        format!("{}{}", base.path(), location)
+    } else {
+        let path = base.path();
+        let dir = path.rfind('/').map(|idx| &path[..=idx]).unwrap_or("/");
+        format!("{}{}", dir, location)
+    };
+    format!("{}://{}{}", scheme, authority, path).parse().ok()
+}
+
+fn same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme_str() == b.scheme_str() && a.authority_part() == b.authority_part()
+}
+
+/// Redirect from secure to plain text url.
+fn is_downgrade(from: &Uri, to: &Uri) -> bool {
+    let secure = |uri: &Uri| match uri.scheme_str() {
+        Some("https") | Some("wss") => true,
+        _ => false,
+    };
+    secure(from) && !secure(to)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::ConnectionType;
+    use actix_http::RequestHead;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base: Uri = "http://example.com/a/b?c=d".parse().unwrap();
+        let resolved = |loc| resolve(&base, loc).unwrap().to_string();
+
+        assert_eq!(resolved("https://other.org/x"), "https://other.org/x");
+        assert_eq!(resolved("//other.org/x"), "http://other.org/x");
+        assert_eq!(resolved("/x?y=z"), "http://example.com/x?y=z");
+        assert_eq!(resolved("x"), "http://example.com/a/x");
+        assert_eq!(resolved("?e=f"), "http://example.com/a/b?e=f");
+        assert_eq!(resolved("/x#frag"), "http://example.com/x");
+    }
+
+    #[test]
+    fn test_redirect_uri() {
+        let base: Uri = "http://example.com/".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(redirect_uri(&base, StatusCode::FOUND, &headers).is_none());
+
+        headers.insert(header::LOCATION, header::HeaderValue::from_static("/x"));
+        assert!(redirect_uri(&base, StatusCode::OK, &headers).is_none());
+        assert!(redirect_uri(&base, StatusCode::NOT_MODIFIED, &headers).is_none());
+        assert_eq!(
+            redirect_uri(&base, StatusCode::PERMANENT_REDIRECT, &headers).unwrap(),
+            "http://example.com/x"
+        );
+    }
+
+    #[test]
+    fn test_is_downgrade() {
+        let uri = |s: &str| s.parse::<Uri>().unwrap();
+        assert!(is_downgrade(&uri("https://a.com/"), &uri("http://a.com/")));
+        assert!(!is_downgrade(
+            &uri("https://a.com/"),
+            &uri("https://b.com/")
+        ));
+        assert!(!is_downgrade(&uri("http://a.com/"), &uri("https://a.com/")));
+        assert!(!is_downgrade(&uri("http://a.com/"), &uri("http://b.com/")));
+    }
+
+    #[test]
+    fn test_copy_head() {
+        let mut head = RequestHead::default();
+        head.uri = "http://example.com/".parse().unwrap();
+        head.headers.insert(
+            header::ACCEPT,
+            header::HeaderValue::from_static("text/html"),
+        );
+        head.set_connection_type(ConnectionType::Close);
+
+        let mut extra = HeaderMap::new();
+        extra.insert(header::ACCEPT, header::HeaderValue::from_static("*/*"));
+
+        let copy = copy_head(&RequestHeadType::Rc(Rc::new(head), Some(extra)));
+        assert_eq!(copy.uri, "http://example.com/");
+        assert_eq!(copy.headers.get(header::ACCEPT).unwrap(), "*/*");
+        assert!(copy.headers.get_all(header::ACCEPT).count() == 1);
+        assert!(match copy.connection_type() {
+            ConnectionType::Close => true,
+            _ => false,
+        });
+    }
+}
@@ -0,0 +1,332 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use actix_http::client::ConnectError;
+use actix_http::http::header::{self, HeaderMap, HttpDate};
+use actix_http::http::{Method, StatusCode};
+use actix_http::RequestHeadType;
+use actix_service::Service;
+use futures::future::{FutureExt, LocalBoxFuture};
+use rand::Rng;
+use tokio_timer::delay_for;
+
+use super::{copy_body, copy_head, ConnectRequest, ConnectResponse, Transform};
+use crate::error::SendRequestError;
+
+/// Middleware that retries failed requests.
+///
+/// Requests are retried if connection could not be established, requests
+/// with idempotent methods are retried on `5xx` and `429 Too Many Requests`
+/// responses as well. Delay between attempts grows exponentially, unless
+/// response provides `Retry-After` header. Response that asks to wait
+/// longer than max delay is returned to the caller.
+///
+/// Requests with streaming body are not retried. Request timeout covers
+/// all attempts.
+///
+/// Policy is set for all requests with `ClientBuilder::retry()` and
+/// could be overridden with `ClientRequest::retry()`.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use awc::middleware::Retry;
+///
+/// let client = awc::Client::build()
+///     .retry(
+///         Retry::new()
+///             .max_retries(5)
+///             .backoff(Duration::from_millis(50), Duration::from_secs(2)),
+///     )
+///     .finish();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Retry {
+    pub(crate) max_retries: usize,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry::new()
+    }
+}
+
+impl Retry {
+    /// Create retry policy.
+    ///
+    /// By default request is retried 3 times, delay starts at 100
+    /// milliseconds and does not exceed 10 seconds.
+    pub fn new() -> Self {
+        Retry {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+
+    /// Policy that does not retry requests.
+    pub fn disabled() -> Self {
+        Retry::new().max_retries(0)
+    }
+
+    /// Set max number of retries.
+    pub fn max_retries(mut self, num: usize) -> Self {
+        self.max_retries = num;
+        self
+    }
+
+    /// Set delay before the first retry and max delay between retries.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max;
+        self
+    }
+
+    /// Randomize delays, so clients do not retry simultaneously.
+    ///
+    /// With jitter delay is chosen between half and full backoff delay.
+    /// Enabled by default.
+    pub fn jitter(mut self, val: bool) -> Self {
+        self.jitter = val;
+        self
+    }
+
+    /// Backoff delay before retry number `attempt`, starting from 0.
+    pub(crate) fn delay(&self, attempt: usize) -> Duration {
+        let factor = 1u32 << (attempt.min(16) as u32);
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .map(|d| d.min(self.max_delay))
+            .unwrap_or(self.max_delay);
+
+        if self.jitter && delay > Duration::from_millis(1) {
+            let half = delay / 2;
+            let millis = half.as_millis() as u64;
+            half + Duration::from_millis(rand::thread_rng().gen_range(0, millis + 1))
+        } else {
+            delay
+        }
+    }
+}
+
+impl<S> Transform<S> for Retry
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Transform = RetryService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        RetryService {
+            service: Rc::new(RefCell::new(service)),
+            policy: self,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct RetryService<S> {
+    service: Rc<RefCell<S>>,
+    policy: Retry,
+}
+
+impl<S> Service for RetryService<S>
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ConnectRequest) -> Self::Future {
+        let policy = req
+            .head()
+            .extensions()
+            .get::<Retry>()
+            .cloned()
+            .unwrap_or_else(|| self.policy.clone());
+
+        let (mut head, mut body, addr) = match req {
+            ConnectRequest::Client(head, body, addr) => {
+                if policy.max_retries == 0 || copy_body(&body).is_none() {
+                    return self
+                        .service
+                        .borrow_mut()
+                        .call(ConnectRequest::Client(head, body, addr))
+                        .boxed_local();
+                }
+                (head, body, addr)
+            }
+            req => return self.service.borrow_mut().call(req).boxed_local(),
+        };
+        let srv = self.service.clone();
+
+        async move {
+            let idempotent = is_idempotent(&head.as_ref().method);
+            let mut attempt = 0;
+
+            loop {
+                let next = if attempt < policy.max_retries {
+                    copy_body(&body).map(|body| (copy_head(&head), body))
+                } else {
+                    None
+                };
+
+                let fut = srv
+                    .borrow_mut()
+                    .call(ConnectRequest::Client(head, body, addr));
+                let res = fut.await;
+
+                let (next_head, next_body) = match next {
+                    Some(next) => next,
+                    None => return res,
+                };
+                let delay = match res {
+                    Err(SendRequestError::Connect(ref err)) if is_transient(err) => {
+                        Some(policy.delay(attempt))
+                    }
+                    Ok(ConnectResponse::Client(ref res))
+                        if idempotent && is_retryable(res.status()) =>
+                    {
+                        match retry_after(res.headers()) {
+                            Some(delay) if delay > policy.max_delay => None,
+                            Some(delay) => Some(delay),
+                            None => Some(policy.delay(attempt)),
+                        }
+                    }
+                    _ => None,
+                };
+                let delay = match delay {
+                    Some(delay) => delay,
+                    None => return res,
+                };
+
+                // response of the failed attempt is dropped with its connection
+                drop(res);
+                delay_for(delay).await;
+
+                head = RequestHeadType::Owned(next_head);
+                body = next_body;
+                attempt += 1;
+            }
+        }
+        .boxed_local()
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::HEAD
+        | Method::OPTIONS
+        | Method::TRACE
+        | Method::PUT
+        | Method::DELETE => true,
+        _ => false,
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Request was not sent, and connecting again could succeed.
+fn is_transient(err: &ConnectError) -> bool {
+    match err {
+        ConnectError::Resolver(_)
+        | ConnectError::NoRecords
+        | ConnectError::Timeout
+        | ConnectError::HandshakeTimeout
+        | ConnectError::Disconnected
+        | ConnectError::Io(_) => true,
+        _ => false,
+    }
+}
+
+/// Parse `Retry-After` header, it contains either seconds or http date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date: SystemTime = value.parse::<HttpDate>().ok()?.into();
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or_else(|_| Duration::from_secs(0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = Retry::new()
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false);
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_secs(1));
+        assert_eq!(policy.delay(100), Duration::from_secs(1));
+
+        let policy = policy.jitter(true);
+        for attempt in 0..5 {
+            let delay = policy.delay(attempt);
+            let max = policy.clone().jitter(false).delay(attempt);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert!(retry_after(&headers).is_none());
+
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(0)));
+
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert!(retry_after(&headers).is_none());
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(is_transient(&ConnectError::Timeout));
+        assert!(!is_transient(&ConnectError::SslIsNotSupported));
+    }
+}
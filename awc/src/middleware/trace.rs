@@ -0,0 +1,346 @@
+use std::cell::Cell;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_http::error::PayloadError;
+use actix_http::{Payload, PayloadStream, RequestHead, RequestHeadType, ResponseHead};
+use actix_service::Service;
+use bytes::Bytes;
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::{ready, Stream};
+
+use super::{copy_head, ConnectRequest, ConnectResponse, Transform};
+use crate::error::SendRequestError;
+
+/// Client instrumentation hooks.
+///
+/// Hooks are called for every request passed through `Trace` middleware,
+/// all methods do nothing by default.
+pub trait TraceHooks {
+    /// Request is about to be sent.
+    ///
+    /// Headers could be added to the request, i.e. to propagate
+    /// distributed tracing context.
+    fn on_request_start(&self, _req: &mut RequestHead) {}
+
+    /// Connection is acquired, either opened or reused from the pool.
+    fn on_connect(&self, _req: &RequestHead, _timing: &Timing) {}
+
+    /// Response head is received.
+    fn on_first_byte(&self, _req: &RequestHead, _res: &ResponseHead, _timing: &Timing) {}
+
+    /// Request is finished.
+    ///
+    /// It is called once response body is read or dropped, and with error
+    /// if request or reading of response body failed.
+    fn on_complete(
+        &self,
+        _req: &RequestHead,
+        _timing: &Timing,
+        _err: Option<&dyn fmt::Display>,
+    ) {
+    }
+}
+
+/// Timing breakdown of the request.
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    start: Instant,
+    connected: Option<Instant>,
+    first_byte: Option<Instant>,
+    complete: Option<Instant>,
+}
+
+impl Timing {
+    fn new() -> Self {
+        Timing {
+            start: Instant::now(),
+            connected: None,
+            first_byte: None,
+            complete: None,
+        }
+    }
+
+    /// Time the request is started at.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Time spent on acquiring connection, including dns resolution,
+    /// TLS handshake and waiting for pooled connection.
+    pub fn connect(&self) -> Option<Duration> {
+        self.connected.map(|t| t - self.start)
+    }
+
+    /// Time between acquiring connection and receiving response head.
+    pub fn first_byte(&self) -> Option<Duration> {
+        self.first_byte
+            .map(|t| t - self.connected.unwrap_or(self.start))
+    }
+
+    /// Time spent on reading response body.
+    pub fn body(&self) -> Option<Duration> {
+        Some(self.complete? - self.first_byte?)
+    }
+
+    /// Total time of the request.
+    pub fn total(&self) -> Option<Duration> {
+        self.complete.map(|t| t - self.start)
+    }
+}
+
+/// Middleware that calls `TraceHooks` during request lifecycle.
+///
+/// Middleware registered with `ClientBuilder::wrap()` sees every attempt
+/// of retried and redirected requests as a separate request.
+///
+/// ```rust
+/// use actix_http::RequestHead;
+/// use awc::http::header::{HeaderName, HeaderValue};
+/// use awc::middleware::{Timing, Trace, TraceHooks};
+///
+/// struct Metrics;
+///
+/// impl TraceHooks for Metrics {
+///     fn on_request_start(&self, req: &mut RequestHead) {
+///         req.headers.insert(
+///             HeaderName::from_static("x-request-id"),
+///             HeaderValue::from_static("1"),
+///         );
+///     }
+///
+///     fn on_complete(
+///         &self,
+///         req: &RequestHead,
+///         timing: &Timing,
+///         err: Option<&dyn std::fmt::Display>,
+///     ) {
+///         println!("{} {}: {:?}", req.method, req.uri, timing.total());
+///     }
+/// }
+///
+/// let client = awc::Client::build().wrap(Trace::new(Metrics)).finish();
+/// ```
+#[derive(Clone)]
+pub struct Trace {
+    hooks: Rc<dyn TraceHooks>,
+}
+
+impl Trace {
+    /// Create middleware that calls `hooks`.
+    pub fn new<H: TraceHooks + 'static>(hooks: H) -> Self {
+        Trace {
+            hooks: Rc::new(hooks),
+        }
+    }
+}
+
+impl<S> Transform<S> for Trace
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Transform = TraceService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        TraceService {
+            service,
+            hooks: self.hooks,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct TraceService<S> {
+    service: S,
+    hooks: Rc<dyn TraceHooks>,
+}
+
+impl<S> Service for TraceService<S>
+where
+    S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        > + 'static,
+    S::Future: 'static,
+{
+    type Request = ConnectRequest;
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ConnectRequest) -> Self::Future {
+        let state = Rc::new(TraceState {
+            hooks: self.hooks.clone(),
+            timing: Cell::new(Timing::new()),
+        });
+
+        // head is shared with connector, so it is available for hooks
+        // after request is sent
+        let start = |head: RequestHeadType| {
+            let mut head = match head {
+                RequestHeadType::Owned(head) => head,
+                head => copy_head(&head),
+            };
+            state.hooks.on_request_start(&mut head);
+            head.extensions_mut().insert(Traced(state.clone()));
+            Rc::new(head)
+        };
+        let (head, fut) = match req {
+            ConnectRequest::Client(head, body, addr) => {
+                let head = start(head);
+                let req = RequestHeadType::Rc(head.clone(), None);
+                (
+                    head,
+                    self.service.call(ConnectRequest::Client(req, body, addr)),
+                )
+            }
+            ConnectRequest::Tunnel(head, addr) => {
+                let head = start(head);
+                let req = RequestHeadType::Rc(head.clone(), None);
+                (head, self.service.call(ConnectRequest::Tunnel(req, addr)))
+            }
+        };
+
+        async move {
+            match fut.await {
+                Ok(ConnectResponse::Client(res)) => {
+                    let timing = state.update(|t| t.first_byte = Some(Instant::now()));
+                    state.hooks.on_first_byte(&head, res.head(), &timing);
+
+                    let res = res.map_body(move |_, payload| {
+                        Payload::Stream(Box::pin(TracedPayload {
+                            stream: payload,
+                            head,
+                            state,
+                            done: false,
+                        }))
+                    });
+                    Ok(ConnectResponse::Client(res))
+                }
+                Ok(ConnectResponse::Tunnel(res, framed)) => {
+                    let timing = state.update(|t| t.first_byte = Some(Instant::now()));
+                    state.hooks.on_first_byte(&head, &res, &timing);
+                    state.complete(&head, None);
+                    Ok(ConnectResponse::Tunnel(res, framed))
+                }
+                Err(err) => {
+                    state.complete(&head, Some(&err));
+                    Err(err)
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Trace state of the request, stored in request head extensions.
+pub(crate) struct Traced(Rc<TraceState>);
+
+struct TraceState {
+    hooks: Rc<dyn TraceHooks>,
+    timing: Cell<Timing>,
+}
+
+impl TraceState {
+    fn update<F: FnOnce(&mut Timing)>(&self, f: F) -> Timing {
+        let mut timing = self.timing.get();
+        f(&mut timing);
+        self.timing.set(timing);
+        timing
+    }
+
+    fn complete(&self, head: &RequestHead, err: Option<&dyn fmt::Display>) {
+        let timing = self.update(|t| t.complete = Some(Instant::now()));
+        self.hooks.on_complete(head, &timing, err);
+    }
+}
+
+/// Notify hooks of traced request that connection is acquired.
+pub(crate) fn trace_connect(head: &RequestHead) {
+    let state = head.extensions().get::<Traced>().map(|t| t.0.clone());
+    if let Some(state) = state {
+        let timing = state.update(|t| t.connected = Some(Instant::now()));
+        state.hooks.on_connect(head, &timing);
+    }
+}
+
+/// Response payload that completes trace once it is read or dropped.
+struct TracedPayload {
+    stream: Payload<PayloadStream>,
+    head: Rc<RequestHead>,
+    state: Rc<TraceState>,
+    done: bool,
+}
+
+impl TracedPayload {
+    fn complete(&mut self, err: Option<&dyn fmt::Display>) {
+        if !self.done {
+            self.done = true;
+            self.state.complete(&self.head, err);
+        }
+    }
+}
+
+impl Stream for TracedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let item = ready!(Pin::new(&mut this.stream).poll_next(cx));
+        match item {
+            None => this.complete(None),
+            Some(Err(ref err)) => this.complete(Some(err)),
+            Some(Ok(_)) => (),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for TracedPayload {
+    fn drop(&mut self) {
+        self.complete(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing() {
+        let mut timing = Timing::new();
+        assert!(timing.connect().is_none());
+        assert!(timing.total().is_none());
+
+        let start = timing.start();
+        timing.connected = Some(start + Duration::from_millis(10));
+        timing.first_byte = Some(start + Duration::from_millis(25));
+        assert_eq!(timing.connect(), Some(Duration::from_millis(10)));
+        assert_eq!(timing.first_byte(), Some(Duration::from_millis(15)));
+        assert!(timing.body().is_none());
+
+        timing.complete = Some(start + Duration::from_millis(40));
+        assert_eq!(timing.body(), Some(Duration::from_millis(15)));
+        assert_eq!(timing.total(), Some(Duration::from_millis(40)));
+
+        // connection error
+        timing.connected = None;
+        timing.first_byte = None;
+        assert!(timing.first_byte().is_none());
+        assert!(timing.body().is_none());
+    }
+}
@@ -0,0 +1,415 @@
+//! Multipart form for `multipart/form-data` requests
+//!
+//! ```rust
+//! use awc::multipart::{Form, Part};
+//!
+//! let form = Form::new()
+//!     .text("name", "actix")
+//!     .part(
+//!         "avatar",
+//!         Part::bytes(&b"\x89PNG"[..])
+//!             .file_name("avatar.png")
+//!             .mime(mime::IMAGE_PNG),
+//!     );
+//!
+//! let client = awc::Client::new();
+//! let request = client
+//!     .post("http://www.rust-lang.org/upload")
+//!     .send_multipart(form);
+//! ```
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use actix_codec::AsyncRead;
+use actix_http::body::{BodySize, MessageBody};
+use actix_http::Error;
+use bytes::{Bytes, BytesMut};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+const CHUNK_SIZE: usize = 8192;
+
+/// `multipart/form-data` body builder.
+///
+/// If sizes of all parts are known, request is sent with `Content-Length`,
+/// otherwise chunked transfer encoding is used.
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+/// Single field of the multipart form.
+pub struct Part {
+    body: PartBody,
+    file_name: Option<String>,
+    mime: Option<mime::Mime>,
+}
+
+enum PartBody {
+    Bytes(Bytes),
+    Reader(Box<dyn AsyncRead + Unpin>, Option<u64>),
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Form::new()
+    }
+}
+
+impl Form {
+    /// Create empty form with random boundary.
+    pub fn new() -> Self {
+        let boundary: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(30)
+            .collect();
+        Form {
+            boundary: format!("------------------------{}", boundary),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Form boundary.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Add text field.
+    pub fn text<N, V>(self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.part(name, Part::text(value))
+    }
+
+    /// Add in-memory file.
+    pub fn file<N, F, B>(self, name: N, file_name: F, data: B) -> Self
+    where
+        N: Into<String>,
+        F: Into<String>,
+        B: Into<Bytes>,
+    {
+        self.part(name, Part::bytes(data).file_name(file_name))
+    }
+
+    /// Add form field.
+    pub fn part<N: Into<String>>(mut self, name: N, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Value of the `Content-Type` header.
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Convert form to request body.
+    pub(crate) fn into_body(self) -> FormBody {
+        let mut chunks = VecDeque::new();
+        let mut size = Some(0u64);
+
+        let mut push = |chunk: Chunk, size: &mut Option<u64>| {
+            *size = match (*size, chunk.size()) {
+                (Some(total), Some(len)) => Some(total + len),
+                _ => None,
+            };
+            chunks.push_back(chunk);
+        };
+
+        for (name, part) in self.parts {
+            let mut head = format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                self.boundary,
+                escape(&name)
+            );
+            if let Some(ref file_name) = part.file_name {
+                head.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+            }
+            head.push_str("\r\n");
+            let mime = part.mime.clone().or_else(|| {
+                if part.file_name.is_some() {
+                    Some(mime::APPLICATION_OCTET_STREAM)
+                } else {
+                    None
+                }
+            });
+            if let Some(mime) = mime {
+                head.push_str(&format!("Content-Type: {}\r\n", mime));
+            }
+            head.push_str("\r\n");
+
+            push(Chunk::Bytes(Bytes::from(head)), &mut size);
+            match part.body {
+                PartBody::Bytes(bytes) => push(Chunk::Bytes(bytes), &mut size),
+                PartBody::Reader(reader, len) => {
+                    push(Chunk::Reader(reader, len), &mut size)
+                }
+            }
+            push(Chunk::Bytes(Bytes::from_static(b"\r\n")), &mut size);
+        }
+        let tail = format!("--{}--\r\n", self.boundary);
+        push(Chunk::Bytes(Bytes::from(tail)), &mut size);
+
+        FormBody {
+            chunks,
+            size,
+            buf: BytesMut::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field(
+                "parts",
+                &self.parts.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Part {
+    /// Text field.
+    pub fn text<V: Into<String>>(value: V) -> Self {
+        Part::new(PartBody::Bytes(Bytes::from(value.into())))
+    }
+
+    /// In-memory field content.
+    pub fn bytes<B: Into<Bytes>>(data: B) -> Self {
+        Part::new(PartBody::Bytes(data.into()))
+    }
+
+    /// Field content is read from `reader`.
+    ///
+    /// Length of the content is not known, so request is sent with
+    /// chunked transfer encoding.
+    pub fn reader<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        Part::new(PartBody::Reader(Box::new(reader), None))
+    }
+
+    /// Field content of `length` bytes is read from `reader`.
+    ///
+    /// Reader must provide exactly `length` bytes.
+    pub fn reader_with_length<R>(reader: R, length: u64) -> Self
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        Part::new(PartBody::Reader(Box::new(reader), Some(length)))
+    }
+
+    fn new(body: PartBody) -> Self {
+        Part {
+            body,
+            file_name: None,
+            mime: None,
+        }
+    }
+
+    /// Set file name of the field.
+    pub fn file_name<F: Into<String>>(mut self, file_name: F) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Set content type of the field.
+    ///
+    /// Files are sent as `application/octet-stream` by default.
+    pub fn mime(mut self, mime: mime::Mime) -> Self {
+        self.mime = Some(mime);
+        self
+    }
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Reader(Box<dyn AsyncRead + Unpin>, Option<u64>),
+}
+
+impl Chunk {
+    fn size(&self) -> Option<u64> {
+        match self {
+            Chunk::Bytes(bytes) => Some(bytes.len() as u64),
+            Chunk::Reader(_, len) => *len,
+        }
+    }
+}
+
+/// Body of the multipart request.
+pub(crate) struct FormBody {
+    chunks: VecDeque<Chunk>,
+    size: Option<u64>,
+    buf: BytesMut,
+}
+
+impl MessageBody for FormBody {
+    fn size(&self) -> BodySize {
+        match self.size {
+            Some(size) => BodySize::Sized64(size),
+            None => BodySize::Stream,
+        }
+    }
+
+    fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        loop {
+            match self.chunks.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Chunk::Bytes(_)) => {
+                    if let Some(Chunk::Bytes(bytes)) = self.chunks.pop_front() {
+                        return Poll::Ready(Some(Ok(bytes)));
+                    }
+                }
+                Some(Chunk::Reader(reader, remaining)) => {
+                    let max = match *remaining {
+                        Some(0) => {
+                            self.chunks.pop_front();
+                            continue;
+                        }
+                        Some(len) if len < CHUNK_SIZE as u64 => len as usize,
+                        _ => CHUNK_SIZE,
+                    };
+                    self.buf.resize(max, 0);
+
+                    let n = match Pin::new(reader).poll_read(cx, &mut self.buf) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                        Poll::Ready(Ok(n)) => n,
+                    };
+                    if n == 0 {
+                        if remaining.is_some() {
+                            let err = io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "Multipart field is shorter than its length",
+                            );
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                        self.chunks.pop_front();
+                        continue;
+                    }
+                    if let Some(ref mut len) = remaining {
+                        *len -= n as u64;
+                    }
+                    return Poll::Ready(Some(Ok(self.buf.split_to(n).freeze())));
+                }
+            }
+        }
+    }
+}
+
+/// Quote field name, like browsers do.
+fn escape(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http_test::block_on;
+    use futures::future::poll_fn;
+
+    use super::*;
+
+    fn read_body(mut body: FormBody) -> Bytes {
+        block_on(async move {
+            let mut buf = BytesMut::new();
+            while let Some(chunk) = poll_fn(|cx| body.poll_next(cx)).await {
+                buf.extend_from_slice(&chunk.unwrap());
+            }
+            buf.freeze()
+        })
+    }
+
+    #[test]
+    fn test_form() {
+        let form = Form::new()
+            .text("name", "actix")
+            .file("file", "a\"b.txt", &b"data"[..])
+            .part("json", Part::text("{}").mime(mime::APPLICATION_JSON));
+        let boundary = form.boundary().to_owned();
+        assert!(form.content_type().ends_with(&boundary));
+
+        let body = form.into_body();
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nactix\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a%22b.txt\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\ndata\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"json\"\r\n\
+             Content-Type: application/json\r\n\r\n{{}}\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        match body.size() {
+            BodySize::Sized64(size) => assert_eq!(size, expected.len() as u64),
+            _ => panic!(),
+        }
+        assert_eq!(read_body(body), Bytes::from(expected));
+    }
+
+    /// Reader of `n` bytes.
+    struct Reader(usize);
+
+    impl AsyncRead for Reader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let n = self.0.min(buf.len());
+            for b in &mut buf[..n] {
+                *b = b'x';
+            }
+            self.0 -= n;
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    #[test]
+    fn test_reader() {
+        let len = CHUNK_SIZE * 2 + 10;
+        let form = Form::new().part("file", Part::reader(Reader(len)));
+        let head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\n",
+            form.boundary()
+        );
+        let tail = format!("\r\n--{}--\r\n", form.boundary());
+
+        let body = form.into_body();
+        assert!(body.size() == BodySize::Stream);
+        let bytes = read_body(body);
+        assert_eq!(bytes.len(), head.len() + len + tail.len());
+        assert!(bytes.starts_with(head.as_bytes()));
+        assert!(bytes.ends_with(tail.as_bytes()));
+
+        // reader is limited to its length
+        let body = Form::new()
+            .part("file", Part::reader_with_length(Reader(len), 10))
+            .into_body();
+        let size = match body.size() {
+            BodySize::Sized64(size) => size,
+            _ => panic!(),
+        };
+        assert_eq!(read_body(body).len() as u64, size);
+
+        let mut body = Form::new()
+            .part("file", Part::reader_with_length(Reader(5), 10))
+            .into_body();
+        let err = block_on(async move {
+            loop {
+                match poll_fn(|cx| body.poll_next(cx)).await {
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Some(e),
+                    None => return None,
+                }
+            }
+        });
+        assert!(err.is_some());
+    }
+}
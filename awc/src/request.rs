@@ -20,7 +20,9 @@ use actix_http::{Error, RequestHead};
 
 use crate::error::{FreezeRequestError, InvalidUrl};
 use crate::frozen::FrozenClientRequest;
-use crate::sender::{PrepForSendingError, RequestSender, SendClientRequest};
+use crate::middleware::Retry;
+use crate::multipart::Form;
+use crate::sender::{PrepForSendingError, RequestSender, SendClientRequest, Timeouts};
 use crate::ClientConfig;
 
 #[cfg(any(feature = "brotli", feature = "flate2-zlib", feature = "flate2-rust"))]
@@ -355,6 +357,44 @@ impl ClientRequest {
         self
     }
 
+    /// Set connect timeout for this request.
+    ///
+    /// Connect timeout limits time spent waiting for a connection,
+    /// including connection pool wait and TLS handshake. It fails the
+    /// request with `ConnectError::Timeout`. Connect timeout of the
+    /// connector applies too.
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        Timeouts::update(&self.head, |t| t.connect = Some(timeout));
+        self
+    }
+
+    /// Set TLS handshake timeout for this request.
+    ///
+    /// Overrides handshake timeout of the connector for new connections
+    /// opened for this request, it fails the request with
+    /// `ConnectError::HandshakeTimeout`.
+    pub fn handshake_timeout(self, timeout: Duration) -> Self {
+        Timeouts::update(&self.head, |t| t.handshake = Some(timeout));
+        self
+    }
+
+    /// Set body read timeout. Overrides client wide read timeout setting.
+    ///
+    /// Reading response body fails if no data is received within this
+    /// period.
+    pub fn read_timeout(self, timeout: Duration) -> Self {
+        Timeouts::update(&self.head, |t| t.read = Some(timeout));
+        self
+    }
+
+    /// Set retry policy. Overrides client wide retry policy.
+    ///
+    /// Use `Retry::disabled()` to send request only once.
+    pub fn retry(self, policy: Retry) -> Self {
+        self.head.extensions_mut().insert(policy);
+        self
+    }
+
     /// This method calls provided closure with builder reference if
     /// value is `true`.
     pub fn if_true<F>(self, value: bool, f: F) -> Self
@@ -474,6 +514,24 @@ impl ClientRequest {
         )
     }
 
+    /// Set a `multipart/form-data` body and generate `ClientRequest`.
+    ///
+    /// `Content-Type` header is replaced with boundary of the form.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(e) => return e.into(),
+        };
+
+        RequestSender::Owned(slf.head).send_multipart(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeout,
+            slf.config.as_ref(),
+            form,
+        )
+    }
+
     /// Set an streaming body and generate `ClientRequest`.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
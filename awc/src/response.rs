@@ -1,9 +1,17 @@
 use std::cell::{Ref, RefMut};
 use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::marker::PhantomData;
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use actix_codec::AsyncWrite;
+use actix_threadpool::{run, CpuFuture};
 use bytes::{Bytes, BytesMut};
 use futures::{ready, Future, Stream};
 
@@ -72,7 +80,10 @@ impl<S> ClientResponse<S> {
         &self.head
     }
 
-    /// Read the Request Version.
+    /// Read the Response Version.
+    ///
+    /// It is the protocol used for the request, i.e. `HTTP_2` if HTTP/2
+    /// was negotiated with the server.
     #[inline]
     pub fn version(&self) -> Version {
         self.head().version
@@ -123,6 +134,39 @@ where
     pub fn json<T: DeserializeOwned>(&mut self) -> JsonBody<S, T> {
         JsonBody::new(self)
     }
+
+    /// Stream http response's body to `writer`.
+    /// Return `CopyBody` future. It resolves to number of written bytes.
+    ///
+    /// Size of the body is not limited by default.
+    ///
+    /// ```rust,ignore
+    /// let mut file = tokio_fs::File::create("rust.html").await?;
+    /// let written = response
+    ///     .copy_to(&mut file)
+    ///     .limit(10 * 1024 * 1024)
+    ///     .progress(|written, total| println!("{} of {:?}", written, total))
+    ///     .await?;
+    /// ```
+    pub fn copy_to<'a, W>(&mut self, writer: W) -> CopyBody<'a, S>
+    where
+        W: AsyncWrite + Unpin + 'a,
+    {
+        CopyBody::new(self, Box::new(writer), None)
+    }
+
+    /// Save http response's body to file at `path`.
+    /// Return `CopyBody` future. It resolves to number of written bytes.
+    ///
+    /// File is created or truncated, and it is removed if the body could
+    /// not be saved. Existing file is kept if saving fails before the file
+    /// is created. File is created and written on a thread pool.
+    pub fn save_to<P: AsRef<Path>>(&mut self, path: P) -> CopyBody<'static, S> {
+        let path = path.as_ref().to_owned();
+        let created = Arc::new(AtomicBool::new(false));
+        let writer = FileWriter::Create(path.clone(), created.clone());
+        CopyBody::new(self, Box::new(writer), Some((path, created)))
+    }
 }
 
 impl<S> Stream for ClientResponse<S>
@@ -220,6 +264,240 @@ where
     }
 }
 
+/// Future that streams http message body to a writer.
+pub struct CopyBody<'a, S> {
+    stream: Payload<S>,
+    writer: Option<Box<dyn AsyncWrite + Unpin + 'a>>,
+    /// Saved file and whether it is created by the writer
+    file: Option<(PathBuf, Arc<AtomicBool>)>,
+    length: Option<u64>,
+    limit: Option<u64>,
+    written: u64,
+    chunk: Bytes,
+    eof: bool,
+    progress: Option<Box<dyn FnMut(u64, Option<u64>) + 'a>>,
+    err: Option<PayloadError>,
+    /// Error of failed copy, returned once saved file is removed
+    failed: Option<PayloadError>,
+    remove: Option<CpuFuture<io::Result<()>>>,
+}
+
+impl<'a, S> CopyBody<'a, S> {
+    fn new(
+        res: &mut ClientResponse<S>,
+        writer: Box<dyn AsyncWrite + Unpin + 'a>,
+        file: Option<(PathBuf, Arc<AtomicBool>)>,
+    ) -> Self {
+        let mut err = None;
+        let mut length = None;
+        if let Some(l) = res.headers().get(&CONTENT_LENGTH) {
+            match l.to_str().ok().and_then(|s| s.parse::<u64>().ok()) {
+                Some(l) => length = Some(l),
+                None => err = Some(PayloadError::UnknownLength),
+            }
+        }
+
+        CopyBody {
+            stream: res.take_payload(),
+            writer: Some(writer),
+            file,
+            length,
+            limit: None,
+            written: 0,
+            chunk: Bytes::new(),
+            eof: false,
+            progress: None,
+            err,
+            failed: None,
+            remove: None,
+        }
+    }
+
+    /// Change max size of payload. By default size is not limited.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Register progress callback.
+    ///
+    /// Callback is called after every written chunk with number of
+    /// written bytes and body size if response has `Content-Length`.
+    pub fn progress<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(u64, Option<u64>) + 'a,
+    {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    fn poll_copy(&mut self, cx: &mut Context) -> Poll<Result<u64, PayloadError>>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        if let Some(err) = self.err.take() {
+            return Poll::Ready(Err(err));
+        }
+        if let (Some(len), Some(limit)) = (self.length, self.limit) {
+            if len > limit {
+                return Poll::Ready(Err(PayloadError::Overflow));
+            }
+        }
+        let writer = self.writer.as_mut().unwrap();
+
+        loop {
+            if !self.chunk.is_empty() {
+                let n = ready!(Pin::new(&mut *writer).poll_write(cx, &self.chunk))
+                    .map_err(PayloadError::Io)?;
+                if n == 0 {
+                    return Poll::Ready(Err(PayloadError::Io(
+                        io::ErrorKind::WriteZero.into(),
+                    )));
+                }
+                self.chunk.advance(n);
+                self.written += n as u64;
+                if let Some(ref mut progress) = self.progress {
+                    progress(self.written, self.length);
+                }
+            } else if !self.eof {
+                match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                    Some(chunk) => {
+                        let chunk = chunk?;
+                        if let Some(limit) = self.limit {
+                            if self.written + chunk.len() as u64 > limit {
+                                return Poll::Ready(Err(PayloadError::Overflow));
+                            }
+                        }
+                        self.chunk = chunk;
+                    }
+                    None => self.eof = true,
+                }
+            } else {
+                ready!(Pin::new(&mut *writer).poll_flush(cx))
+                    .map_err(PayloadError::Io)?;
+                return Poll::Ready(Ok(self.written));
+            }
+        }
+    }
+}
+
+impl<'a, S> Future for CopyBody<'a, S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    type Output = Result<u64, PayloadError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(ref mut fut) = this.remove {
+            let _ = ready!(Pin::new(fut).poll(cx));
+            this.remove.take();
+            return Poll::Ready(Err(this.failed.take().unwrap()));
+        }
+
+        if this.failed.is_none() {
+            match ready!(this.poll_copy(cx)) {
+                Err(e) if this.file.is_some() => this.failed = Some(e),
+                res => return Poll::Ready(res),
+            }
+        }
+
+        // do not leave partially saved file, pending write has to
+        // complete before file is removed
+        if let Some(ref mut writer) = this.writer {
+            let _ = ready!(Pin::new(&mut **writer).poll_shutdown(cx));
+        }
+        this.writer.take();
+
+        let (path, created) = this.file.take().unwrap();
+        if created.load(Ordering::Acquire) {
+            this.remove = Some(run(move || fs::remove_file(path)));
+            Pin::new(this).poll(cx)
+        } else {
+            Poll::Ready(Err(this.failed.take().unwrap()))
+        }
+    }
+}
+
+/// Writes file on a thread pool.
+///
+/// Write completes once chunk is copied, error of the pending write is
+/// returned by next operation.
+enum FileWriter {
+    /// File is created with first operation, flag is set once it is created
+    Create(PathBuf, Arc<AtomicBool>),
+    Idle(File),
+    Busy(CpuFuture<io::Result<File>>),
+    Closed,
+}
+
+impl FileWriter {
+    /// Wait for pending operation and take file.
+    fn poll_file(&mut self, cx: &mut Context) -> Poll<io::Result<File>> {
+        loop {
+            match mem::replace(self, FileWriter::Closed) {
+                FileWriter::Create(path, created) => {
+                    *self = FileWriter::Busy(run(move || {
+                        let file = File::create(path)?;
+                        created.store(true, Ordering::Release);
+                        Ok(file)
+                    }))
+                }
+                FileWriter::Idle(file) => return Poll::Ready(Ok(file)),
+                FileWriter::Busy(mut fut) => match Pin::new(&mut fut).poll(cx) {
+                    Poll::Pending => {
+                        *self = FileWriter::Busy(fut);
+                        return Poll::Pending;
+                    }
+                    Poll::Ready(Ok(res)) => return Poll::Ready(res),
+                    Poll::Ready(Err(_)) => {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Thread pool is gone",
+                        )))
+                    }
+                },
+                FileWriter::Closed => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "File is closed",
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for FileWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let mut file = ready!(this.poll_file(cx))?;
+        let chunk = buf.to_vec();
+        *this = FileWriter::Busy(run(move || file.write_all(&chunk).map(|_| file)));
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let file = ready!(this.poll_file(cx))?;
+        *this = FileWriter::Idle(file);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let FileWriter::Create(..) = this {
+            *this = FileWriter::Closed;
+        }
+        this.poll_file(cx).map(|res| res.map(|_| ()))
+    }
+}
+
 /// Response's payload json parser, it resolves to a deserialized `T` value.
 ///
 /// Returns error:
@@ -358,6 +636,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use actix_http::test::TestBuffer;
     use actix_http_test::block_on;
     use serde::{Deserialize, Serialize};
 
@@ -395,6 +674,87 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_copy_to() {
+        block_on(async {
+            let mut res = TestResponse::default()
+                .set_payload(Bytes::from_static(b"test"))
+                .finish();
+            let mut buf = TestBuffer::empty();
+            let mut calls = Vec::new();
+            let written = res
+                .copy_to(&mut buf)
+                .progress(|written, total| calls.push((written, total)))
+                .await
+                .unwrap();
+            assert_eq!(written, 4);
+            assert_eq!(&buf.write_buf[..], b"test");
+            assert_eq!(calls, vec![(4, None)]);
+
+            let mut res =
+                TestResponse::with_header(header::CONTENT_LENGTH, "1000000").finish();
+            match res.copy_to(TestBuffer::empty()).limit(10).await {
+                Err(PayloadError::Overflow) => (),
+                _ => unreachable!("error"),
+            }
+
+            let mut res = TestResponse::default()
+                .set_payload(Bytes::from_static(b"11111111111111"))
+                .finish();
+            match res.copy_to(TestBuffer::empty()).limit(5).await {
+                Err(PayloadError::Overflow) => (),
+                _ => unreachable!("error"),
+            }
+        })
+    }
+
+    #[test]
+    fn test_save_to() {
+        block_on(async {
+            let path = std::env::temp_dir()
+                .join(format!("awc-save-to-{}", rand::random::<u64>()));
+
+            let mut res = TestResponse::default()
+                .set_payload(Bytes::from_static(b"test"))
+                .finish();
+            assert_eq!(res.save_to(&path).await.unwrap(), 4);
+            assert_eq!(fs::read(&path).unwrap(), b"test");
+
+            // file is removed on error
+            let mut res = TestResponse::default()
+                .set_payload(Bytes::from_static(b"11111111111111"))
+                .finish();
+            assert!(res.save_to(&path).limit(5).await.is_err());
+            assert!(!path.exists());
+
+            // payload error while write of previous chunk is pending
+            let payload = futures::stream::iter(vec![
+                Ok(Bytes::from_static(b"test")),
+                Err(PayloadError::Incomplete(None)),
+            ]);
+            let mut res = ClientResponse::new(
+                ResponseHead::new(StatusCode::OK),
+                Payload::Stream(payload),
+            );
+            match res.save_to(&path).await {
+                Err(PayloadError::Incomplete(_)) => (),
+                _ => unreachable!("error"),
+            }
+            assert!(!path.exists());
+
+            // file that is not created by the call is kept
+            fs::write(&path, b"existing").unwrap();
+            let mut res =
+                TestResponse::with_header(header::CONTENT_LENGTH, "1000000").finish();
+            match res.save_to(&path).limit(10).await {
+                Err(PayloadError::Overflow) => (),
+                _ => unreachable!("error"),
+            }
+            assert_eq!(fs::read(&path).unwrap(), b"existing");
+            fs::remove_file(&path).unwrap();
+        })
+    }
+
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct MyObject {
         name: String,
@@ -1,23 +1,26 @@
-use std::net;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 use std::time::Duration;
+use std::{io, net};
 
 use bytes::Bytes;
 use derive_more::From;
-use futures::{future::LocalBoxFuture, ready, Future, Stream};
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::{ready, Future, Stream};
 use serde::Serialize;
 use serde_json;
 use tokio_timer::{delay_for, Delay};
 
 use actix_http::body::{Body, BodyStream};
 use actix_http::encoding::Decoder;
+use actix_http::error::PayloadError;
 use actix_http::http::header::{self, ContentEncoding, IntoHeaderValue};
 use actix_http::http::{Error as HttpError, HeaderMap, HeaderName};
 use actix_http::{Error, Payload, PayloadStream, RequestHead};
 
 use crate::error::{FreezeRequestError, InvalidUrl, SendRequestError};
+use crate::multipart::Form;
 use crate::response::ClientResponse;
 use crate::ClientConfig;
 
@@ -45,6 +48,74 @@ impl Into<SendRequestError> for PrepForSendingError {
     }
 }
 
+/// Per-request timeouts, stored in request head extensions.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: Option<Duration>,
+    pub(crate) handshake: Option<Duration>,
+    pub(crate) read: Option<Duration>,
+}
+
+impl Timeouts {
+    pub(crate) fn update<F>(head: &RequestHead, f: F)
+    where
+        F: FnOnce(&mut Timeouts),
+    {
+        let mut timeouts = head
+            .extensions()
+            .get::<Timeouts>()
+            .cloned()
+            .unwrap_or_default();
+        f(&mut timeouts);
+        head.extensions_mut().insert(timeouts);
+    }
+
+    pub(crate) fn connect(head: &RequestHead) -> Option<Duration> {
+        head.extensions().get::<Timeouts>().and_then(|t| t.connect)
+    }
+
+    pub(crate) fn handshake(head: &RequestHead) -> Option<Duration> {
+        head.extensions()
+            .get::<Timeouts>()
+            .and_then(|t| t.handshake)
+    }
+
+    fn read(head: &RequestHead) -> Option<Duration> {
+        head.extensions().get::<Timeouts>().and_then(|t| t.read)
+    }
+}
+
+/// Fails response payload if no data is received within timeout.
+struct ReadTimeout {
+    stream: Payload<PayloadStream>,
+    timeout: Duration,
+    delay: Delay,
+}
+
+impl Stream for ReadTimeout {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(item) => {
+                this.delay = delay_for(this.timeout);
+                Poll::Ready(item)
+            }
+            Poll::Pending => match Pin::new(&mut this.delay).poll(cx) {
+                Poll::Ready(_) => {
+                    Poll::Ready(Some(Err(PayloadError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Timeout while reading response body",
+                    )))))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
+
 /// Future that sends request's payload and resolves to a server response.
 #[must_use = "futures do nothing unless polled"]
 pub enum SendClientRequest {
@@ -153,7 +224,13 @@ impl RequestSender {
     {
         let mut connector = config.connector.borrow_mut();
 
-        let fut = match self {
+        let read_timeout = match self {
+            RequestSender::Owned(ref head) => Timeouts::read(head),
+            RequestSender::Rc(ref head, _) => Timeouts::read(head),
+        }
+        .or(config.read_timeout);
+
+        let mut fut = match self {
             RequestSender::Owned(head) => {
                 connector.send_request(head, body.into(), addr)
             }
@@ -162,6 +239,22 @@ impl RequestSender {
             }
         };
 
+        if let Some(timeout) = read_timeout {
+            fut = fut
+                .map(move |res| {
+                    res.map(|res| {
+                        res.map_body(|_, payload| {
+                            Payload::Stream(Box::pin(ReadTimeout {
+                                stream: payload,
+                                timeout,
+                                delay: delay_for(timeout),
+                            }))
+                        })
+                    })
+                })
+                .boxed_local();
+        }
+
         SendClientRequest::new(
             fut,
             response_decompress,
@@ -226,6 +319,28 @@ impl RequestSender {
         )
     }
 
+    pub(crate) fn send_multipart(
+        mut self,
+        addr: Option<net::SocketAddr>,
+        response_decompress: bool,
+        timeout: Option<Duration>,
+        config: &ClientConfig,
+        form: Form,
+    ) -> SendClientRequest {
+        // boundary is generated by the form
+        if let Err(e) = self.set_header(header::CONTENT_TYPE, form.content_type()) {
+            return e.into();
+        }
+
+        self.send_body(
+            addr,
+            response_decompress,
+            timeout,
+            config,
+            Body::from_message(form.into_body()),
+        )
+    }
+
     pub(crate) fn send_stream<S, E>(
         self,
         addr: Option<net::SocketAddr>,
@@ -257,6 +372,27 @@ impl RequestSender {
         self.send_body(addr, response_decompress, timeout, config, Body::Empty)
     }
 
+    fn set_header<V>(&mut self, key: HeaderName, value: V) -> Result<(), HttpError>
+    where
+        V: IntoHeaderValue,
+    {
+        let value = match value.try_into() {
+            Ok(value) => value,
+            Err(e) => return Err(e.into()),
+        };
+        match self {
+            RequestSender::Owned(head) => {
+                head.headers.insert(key, value);
+            }
+            RequestSender::Rc(_, extra_headers) => {
+                extra_headers
+                    .get_or_insert(HeaderMap::new())
+                    .insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
     fn set_header_if_none<V>(
         &mut self,
         key: HeaderName,
@@ -0,0 +1,195 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_http::ws::{Frame, Message, ProtocolError};
+use futures::{ready, Sink, Stream};
+use tokio_timer::{delay_for, Delay};
+
+/// Websocket connection that keeps itself alive with ping messages.
+///
+/// Ping is sent if no frames are received within `interval`. If nothing is
+/// received within `timeout` after that, stream fails with
+/// `ProtocolError::Io` of `TimedOut` kind. Pings of the server are answered
+/// automatically, all frames are still returned by the stream.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use awc::ws::Heartbeat;
+///
+/// # async fn run() -> Result<(), awc::error::WsClientError> {
+/// let (_, framed) = awc::Client::new()
+///     .ws("ws://localhost:8080/ws")
+///     .connect()
+///     .await?;
+/// let framed = Heartbeat::new(framed, Duration::from_secs(5), Duration::from_secs(10));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Heartbeat<T> {
+    framed: T,
+    interval: Duration,
+    timeout: Duration,
+    ping: Option<Delay>,
+    deadline: Option<Delay>,
+    control: Option<Message>,
+}
+
+impl<T> Heartbeat<T>
+where
+    T: Stream<Item = Result<Frame, ProtocolError>>
+        + Sink<Message, Error = ProtocolError>
+        + Unpin,
+{
+    /// Send pings over `framed` connection.
+    pub fn new(framed: T, interval: Duration, timeout: Duration) -> Self {
+        Heartbeat {
+            framed,
+            interval,
+            timeout,
+            ping: Some(delay_for(interval)),
+            deadline: Some(delay_for(interval + timeout)),
+            control: None,
+        }
+    }
+
+    /// Answer pings of the server, but do not send pings.
+    pub(crate) fn pong_only(framed: T) -> Self {
+        Heartbeat {
+            framed,
+            interval: Duration::from_secs(0),
+            timeout: Duration::from_secs(0),
+            ping: None,
+            deadline: None,
+            control: None,
+        }
+    }
+
+    /// Reference to the underlying connection.
+    pub fn get_ref(&self) -> &T {
+        &self.framed
+    }
+
+    /// Mutable reference to the underlying connection.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.framed
+    }
+
+    /// Unwrap the underlying connection.
+    pub fn into_inner(self) -> T {
+        self.framed
+    }
+
+    /// Send queued ping or pong.
+    fn poll_control(&mut self, cx: &mut Context) -> Poll<Result<(), ProtocolError>> {
+        if self.control.is_some() {
+            ready!(Pin::new(&mut self.framed).poll_ready(cx))?;
+            if let Some(msg) = self.control.take() {
+                Pin::new(&mut self.framed).start_send(msg)?;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn send_control(&mut self, cx: &mut Context) -> Result<(), ProtocolError> {
+        if let Poll::Ready(Err(e)) = self.poll_control(cx) {
+            return Err(e);
+        }
+        match Pin::new(&mut self.framed).poll_flush(cx) {
+            Poll::Ready(Err(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> Stream for Heartbeat<T>
+where
+    T: Stream<Item = Result<Frame, ProtocolError>>
+        + Sink<Message, Error = ProtocolError>
+        + Unpin,
+{
+    type Item = Result<Frame, ProtocolError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.framed).poll_next(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                if this.ping.is_some() {
+                    this.ping = Some(delay_for(this.interval));
+                    this.deadline = Some(delay_for(this.interval + this.timeout));
+                }
+                if let Frame::Ping(ref msg) = frame {
+                    this.control = Some(Message::Pong(msg.clone()));
+                    if let Err(e) = this.send_control(cx) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            Poll::Ready(item) => return Poll::Ready(item),
+            Poll::Pending => (),
+        }
+
+        if let Some(ref mut deadline) = this.deadline {
+            if Pin::new(deadline).poll(cx).is_ready() {
+                return Poll::Ready(Some(Err(ProtocolError::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Websocket heartbeat timeout",
+                )))));
+            }
+        }
+        if let Some(ref mut ping) = this.ping {
+            if Pin::new(&mut *ping).poll(cx).is_ready() {
+                *ping = delay_for(this.interval);
+                let _ = Pin::new(ping).poll(cx);
+                // pending pong keeps connection alive as well
+                if this.control.is_none() {
+                    this.control = Some(Message::Ping(String::new()));
+                }
+            }
+        }
+        if let Err(e) = this.send_control(cx) {
+            return Poll::Ready(Some(Err(e)));
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Sink<Message> for Heartbeat<T>
+where
+    T: Stream<Item = Result<Frame, ProtocolError>>
+        + Sink<Message, Error = ProtocolError>
+        + Unpin,
+{
+    type Error = ProtocolError;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_control(cx))?;
+        Pin::new(&mut this.framed).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.get_mut().framed).start_send(item)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().framed).poll_flush(cx)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().framed).poll_close(cx)
+    }
+}
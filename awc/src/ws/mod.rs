@@ -2,6 +2,7 @@
 use std::fmt::Write as FmtWrite;
 use std::net::SocketAddr;
 use std::rc::Rc;
+use std::time::Duration;
 use std::{fmt, str};
 
 use actix_codec::Framed;
@@ -11,7 +12,7 @@ use percent_encoding::percent_encode;
 use tokio_timer::Timeout;
 
 use actix_http::cookie::USERINFO;
-pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message};
+pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message, ProtocolError};
 
 use crate::connect::BoxedSocket;
 use crate::error::{InvalidUrl, SendRequestError, WsClientError};
@@ -22,8 +23,15 @@ use crate::http::{
     ConnectionType, Error as HttpError, HttpTryFrom, Method, StatusCode, Uri, Version,
 };
 use crate::response::ClientResponse;
+use crate::sender::Timeouts;
 use crate::ClientConfig;
 
+mod heartbeat;
+mod reconnect;
+
+pub use self::heartbeat::Heartbeat;
+pub use self::reconnect::Reconnect;
+
 /// `WebSocket` connection
 pub struct WebsocketsRequest {
     pub(crate) head: RequestHead,
@@ -127,6 +135,25 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Set connect timeout for this request.
+    ///
+    /// Connect timeout limits time spent waiting for a connection,
+    /// including TLS handshake. It fails the handshake with
+    /// `ConnectError::Timeout`.
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        Timeouts::update(&self.head, |t| t.connect = Some(timeout));
+        self
+    }
+
+    /// Set TLS handshake timeout for this request.
+    ///
+    /// Overrides handshake timeout of the connector, it fails the
+    /// request with `ConnectError::HandshakeTimeout`.
+    pub fn handshake_timeout(self, timeout: Duration) -> Self {
+        Timeouts::update(&self.head, |t| t.handshake = Some(timeout));
+        self
+    }
+
     /// Append a header.
     ///
     /// Header gets appended to existing header.
@@ -421,6 +448,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_timeouts() {
+        let req = Client::new()
+            .ws("/")
+            .connect_timeout(Duration::from_secs(3))
+            .handshake_timeout(Duration::from_secs(1));
+        assert_eq!(Timeouts::connect(&req.head), Some(Duration::from_secs(3)));
+        assert_eq!(Timeouts::handshake(&req.head), Some(Duration::from_secs(1)));
+    }
+
     #[test]
     fn basic_auth() {
         let req = Client::new()
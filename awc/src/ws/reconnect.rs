@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_codec::Framed;
+use actix_http::ws::{Codec, Frame, Message, ProtocolError};
+use futures::{ready, Sink, Stream};
+use tokio_timer::{delay_for, Delay};
+
+use super::Heartbeat;
+use crate::connect::BoxedSocket;
+use crate::error::WsClientError;
+use crate::middleware::Retry;
+use crate::response::ClientResponse;
+
+type Connection = Heartbeat<Framed<BoxedSocket, Codec>>;
+
+/// Websocket connection that is re-established when it is closed or fails.
+///
+/// `connect` is called to open every connection. Delays between attempts
+/// and max number of consecutive failed attempts are defined by `Retry`
+/// policy, by default connection is re-established 3 times. Once retries
+/// are exhausted stream returns the last connect error and ends.
+///
+/// Connection errors are not returned by the stream, `Close` frames of the
+/// server are returned before connection is re-established. Closing the
+/// sink closes connection for good. Pings of the server are answered
+/// automatically.
+///
+/// ```rust
+/// use awc::middleware::Retry;
+/// use awc::ws::{Message, Reconnect};
+///
+/// let client = awc::Client::new();
+/// let conn = Reconnect::new(move || client.ws("ws://localhost:8080/feed").connect())
+///     .retry(Retry::new().max_retries(10))
+///     .on_connect(|| vec![Message::Text("subscribe".to_owned())]);
+/// ```
+pub struct Reconnect<F, Fut> {
+    connect: F,
+    policy: Retry,
+    heartbeat: Option<(Duration, Duration)>,
+    on_connect: Option<Box<dyn FnMut() -> Vec<Message>>>,
+    state: State<Fut>,
+    attempt: usize,
+    queue: VecDeque<Message>,
+}
+
+enum State<Fut> {
+    Disconnected,
+    Connecting(Pin<Box<Fut>>),
+    Waiting(Delay),
+    Connected(Connection),
+    Closed,
+}
+
+impl<F, Fut> Reconnect<F, Fut>
+where
+    F: FnMut() -> Fut + Unpin,
+    Fut: Future<
+        Output = Result<(ClientResponse, Framed<BoxedSocket, Codec>), WsClientError>,
+    >,
+{
+    /// Create connection, it is opened once stream is polled.
+    pub fn new(connect: F) -> Self {
+        Reconnect {
+            connect,
+            policy: Retry::new(),
+            heartbeat: None,
+            on_connect: None,
+            state: State::Disconnected,
+            attempt: 0,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Set reconnect policy.
+    pub fn retry(mut self, policy: Retry) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Keep connection alive with pings, see `Heartbeat`.
+    ///
+    /// Connection that timed out is re-established.
+    pub fn heartbeat(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    /// Set messages that are sent after every connect, i.e. to restore
+    /// subscriptions.
+    pub fn on_connect<C>(mut self, f: C) -> Self
+    where
+        C: FnMut() -> Vec<Message> + 'static,
+    {
+        self.on_connect = Some(Box::new(f));
+        self
+    }
+
+    /// Drop current connection and schedule reconnect.
+    fn reconnect(&mut self, err: Option<WsClientError>) -> Option<WsClientError> {
+        self.queue.clear();
+        if self.attempt >= self.policy.max_retries {
+            self.state = State::Closed;
+            return err;
+        }
+        if let Some(err) = err {
+            log::trace!("Websocket connect error: {}", err);
+        }
+        self.state = State::Waiting(delay_for(self.policy.delay(self.attempt)));
+        self.attempt += 1;
+        None
+    }
+
+    /// Drive connection until it is established.
+    fn poll_connect(&mut self, cx: &mut Context) -> Poll<Result<(), WsClientError>> {
+        loop {
+            match self.state {
+                State::Connected(_) => return Poll::Ready(Ok(())),
+                State::Closed => return Poll::Ready(Err(closed())),
+                State::Disconnected => {
+                    self.state = State::Connecting(Box::pin((self.connect)()));
+                }
+                State::Waiting(ref mut delay) => {
+                    ready!(Pin::new(delay).poll(cx));
+                    self.state = State::Disconnected;
+                }
+                State::Connecting(ref mut fut) => match ready!(fut.as_mut().poll(cx)) {
+                    Ok((_, framed)) => {
+                        let conn = match self.heartbeat {
+                            Some((interval, timeout)) => {
+                                Heartbeat::new(framed, interval, timeout)
+                            }
+                            None => Heartbeat::pong_only(framed),
+                        };
+                        if let Some(ref mut on_connect) = self.on_connect {
+                            self.queue.extend(on_connect());
+                        }
+                        self.attempt = 0;
+                        self.state = State::Connected(conn);
+                    }
+                    Err(err) => {
+                        if let Some(err) = self.reconnect(Some(err)) {
+                            return Poll::Ready(Err(err));
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Send messages queued by `on_connect`.
+    fn poll_queue(&mut self, cx: &mut Context) -> Poll<Result<(), ProtocolError>> {
+        if let State::Connected(ref mut conn) = self.state {
+            while !self.queue.is_empty() {
+                ready!(Pin::new(&mut *conn).poll_ready(cx))?;
+                if let Some(msg) = self.queue.pop_front() {
+                    Pin::new(&mut *conn).start_send(msg)?;
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<F, Fut> Stream for Reconnect<F, Fut>
+where
+    F: FnMut() -> Fut + Unpin,
+    Fut: Future<
+        Output = Result<(ClientResponse, Framed<BoxedSocket, Codec>), WsClientError>,
+    >,
+{
+    type Item = Result<Frame, WsClientError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let State::Closed = this.state {
+                return Poll::Ready(None);
+            }
+            if let Err(err) = ready!(this.poll_connect(cx)) {
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            let res = match this.poll_queue(cx) {
+                Poll::Ready(Err(e)) => Err(e),
+                _ => match this.state {
+                    State::Connected(ref mut conn) => {
+                        let _ = Pin::new(&mut *conn).poll_flush(cx);
+                        match ready!(Pin::new(conn).poll_next(cx)) {
+                            Some(Ok(frame)) => Ok(Some(frame)),
+                            Some(Err(e)) => Err(e),
+                            None => Ok(None),
+                        }
+                    }
+                    _ => Ok(None),
+                },
+            };
+
+            match res {
+                Ok(Some(Frame::Close(reason))) => {
+                    this.attempt = 0;
+                    this.reconnect(None);
+                    return Poll::Ready(Some(Ok(Frame::Close(reason))));
+                }
+                Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                Ok(None) => {
+                    this.attempt = 0;
+                    this.reconnect(None);
+                }
+                Err(e) => {
+                    log::trace!("Websocket connection error: {}", e);
+                    this.attempt = 0;
+                    this.reconnect(None);
+                }
+            }
+        }
+    }
+}
+
+impl<F, Fut> Sink<Message> for Reconnect<F, Fut>
+where
+    F: FnMut() -> Fut + Unpin,
+    Fut: Future<
+        Output = Result<(ClientResponse, Framed<BoxedSocket, Codec>), WsClientError>,
+    >,
+{
+    type Error = WsClientError;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_connect(cx))?;
+        ready!(this.poll_queue(cx))?;
+        match this.state {
+            State::Connected(ref mut conn) => {
+                Pin::new(conn).poll_ready(cx).map_err(Into::into)
+            }
+            _ => Poll::Ready(Err(closed())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match self.get_mut().state {
+            State::Connected(ref mut conn) => {
+                Pin::new(conn).start_send(item).map_err(Into::into)
+            }
+            _ => Err(closed()),
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut().state {
+            State::Connected(ref mut conn) => {
+                Pin::new(conn).poll_flush(cx).map_err(Into::into)
+            }
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if let State::Connected(ref mut conn) = this.state {
+            ready!(Pin::new(conn).poll_close(cx))?;
+        }
+        this.state = State::Closed;
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn closed() -> WsClientError {
+    WsClientError::Protocol(ProtocolError::Io(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "Websocket connection is closed",
+    )))
+}
@@ -15,7 +15,7 @@ use rand::Rng;
 use actix_http::HttpService;
 use actix_http_test::{block_on, TestServer};
 use actix_service::pipeline_factory;
-use actix_web::http::Cookie;
+use actix_web::http::{Cookie, Version};
 use actix_web::middleware::{BodyEncoding, Compress};
 use actix_web::{http::header, web, App, Error, HttpMessage, HttpRequest, HttpResponse};
 use awc::error::SendRequestError;
@@ -367,6 +367,48 @@ fn test_connection_wait_queue_force_close() {
     })
 }
 
+#[test]
+fn test_connection_pool_metrics() {
+    block_on(async {
+        let srv = TestServer::start(move || {
+            HttpService::new(App::new().service(
+                web::resource("/").route(web::to(|| HttpResponse::Ok().body(STR))),
+            ))
+        });
+
+        let client = awc::Client::build().limit_per_host(1).finish();
+        let metrics = client.pool_metrics().unwrap();
+
+        // req 1
+        let request = client.get(srv.url("/")).send();
+        let mut response = request.await.unwrap();
+        assert!(response.status().is_success());
+
+        let hosts = metrics.snapshot();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].active, 1);
+        assert_eq!(hosts[0].idle, 0);
+
+        // req 2 waits for host connection
+        let req2 = client.post(srv.url("/"));
+        let req2_fut = req2.send();
+
+        // read response 1
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+        let mut response = req2_fut.await.unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+        let hosts = metrics.snapshot();
+        assert_eq!(hosts[0].active, 0);
+        assert_eq!(hosts[0].idle, 1);
+        assert_eq!(hosts[0].waiting, 0);
+    })
+}
+
 #[test]
 fn test_with_query_parameter() {
     block_on(async {
@@ -856,3 +898,652 @@ fn client_bearer_auth() {
         assert!(response.status().is_success());
     })
 }
+
+#[test]
+fn test_client_middleware() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use actix_service::Service;
+    use awc::middleware::{ConnectRequest, ConnectResponse, Transform};
+
+    struct Counter(Rc<Cell<usize>>);
+
+    impl<S> Transform<S> for Counter
+    where
+        S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        >,
+    {
+        type Transform = CounterService<S>;
+
+        fn new_transform(self, service: S) -> Self::Transform {
+            CounterService(service, self.0)
+        }
+    }
+
+    struct CounterService<S>(S, Rc<Cell<usize>>);
+
+    impl<S> Service for CounterService<S>
+    where
+        S: Service<
+            Request = ConnectRequest,
+            Response = ConnectResponse,
+            Error = SendRequestError,
+        >,
+    {
+        type Request = ConnectRequest;
+        type Response = ConnectResponse;
+        type Error = SendRequestError;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, mut req: ConnectRequest) -> Self::Future {
+            self.1.set(self.1.get() + 1);
+            let value = self.1.get().to_string();
+            req.headers_mut().insert(
+                header::HeaderName::from_static("x-count"),
+                header::HeaderValue::from_str(&value).unwrap(),
+            );
+            self.0.call(req)
+        }
+    }
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                |req: HttpRequest| {
+                    HttpResponse::Ok().body(
+                        req.headers()
+                            .get("x-count")
+                            .map(|v| v.to_str().unwrap().to_owned())
+                            .unwrap_or_default(),
+                    )
+                },
+            ))))
+        });
+
+        let count = Rc::new(Cell::new(0));
+        let client = awc::Client::build().wrap(Counter(count.clone())).finish();
+
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"1"));
+
+        // frozen requests share head, middleware sets extra headers
+        let req = client.get(srv.url("/")).freeze().unwrap();
+        let mut response = req.send().await.unwrap();
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"2"));
+        assert_eq!(count.get(), 2);
+    })
+}
+
+#[test]
+fn test_client_redirects() {
+    use actix_web::http::StatusCode;
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(
+                App::new()
+                    .route(
+                        "/see-other",
+                        web::to(|| {
+                            HttpResponse::SeeOther()
+                                .header(header::LOCATION, "/target")
+                                .finish()
+                        }),
+                    )
+                    .route(
+                        "/temporary",
+                        web::to(|| {
+                            HttpResponse::TemporaryRedirect()
+                                .header(header::LOCATION, "target")
+                                .finish()
+                        }),
+                    )
+                    .route(
+                        "/loop",
+                        web::to(|| {
+                            HttpResponse::Found()
+                                .header(header::LOCATION, "/loop")
+                                .finish()
+                        }),
+                    )
+                    .route(
+                        "/target",
+                        web::to(|req: HttpRequest, body: Bytes| {
+                            HttpResponse::Ok().body(format!(
+                                "{} {}",
+                                req.method(),
+                                String::from_utf8_lossy(&body)
+                            ))
+                        }),
+                    ),
+            )
+        });
+
+        let client = awc::Client::default();
+
+        // 303 switches to GET without body
+        let mut response = client
+            .post(srv.url("/see-other"))
+            .send_body("data")
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"GET "));
+
+        // 307 preserves method and body
+        let mut response = client
+            .post(srv.url("/temporary"))
+            .send_body("data")
+            .await
+            .unwrap();
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"POST data"));
+
+        // last redirect is returned when max number is reached
+        let client = awc::Client::build().max_redirects(3).finish();
+        let response = client.get(srv.url("/loop")).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+
+        let client = awc::Client::build().disable_redirects().finish();
+        let response = client.get(srv.url("/see-other")).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    })
+}
+
+#[test]
+fn test_client_retry() {
+    use awc::middleware::Retry;
+
+    block_on(async {
+        let num = Arc::new(AtomicUsize::new(0));
+        let num2 = num.clone();
+
+        let srv = TestServer::start(move || {
+            let num2 = num2.clone();
+            HttpService::new(App::new().service(web::resource("/").to(move || {
+                // fail every request except each third one
+                if num2.fetch_add(1, Ordering::Relaxed) % 3 == 2 {
+                    HttpResponse::Ok().finish()
+                } else {
+                    HttpResponse::ServiceUnavailable()
+                        .header(header::RETRY_AFTER, "0")
+                        .finish()
+                }
+            })))
+        });
+
+        let client = awc::Client::build()
+            .retry(
+                Retry::new()
+                    .backoff(Duration::from_millis(1), Duration::from_millis(10)),
+            )
+            .finish();
+
+        let response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(num.load(Ordering::Relaxed), 3);
+
+        // non-idempotent requests are not retried
+        let response = client.post(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_server_error());
+        assert_eq!(num.load(Ordering::Relaxed), 4);
+
+        // per-request policy
+        let response = client
+            .get(srv.url("/"))
+            .retry(Retry::disabled())
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_server_error());
+        assert_eq!(num.load(Ordering::Relaxed), 5);
+
+        let response = client
+            .get(srv.url("/"))
+            .retry(Retry::new().max_retries(1).jitter(false))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(num.load(Ordering::Relaxed), 6);
+    })
+}
+
+#[test]
+fn test_client_cookie_store() {
+    use awc::middleware::CookieStore;
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(
+                App::new()
+                    .route(
+                        "/login",
+                        web::to(|| {
+                            HttpResponse::Found()
+                                .header(header::LOCATION, "/account/")
+                                .cookie(
+                                    Cookie::build("session", "id").path("/").finish(),
+                                )
+                                .finish()
+                        }),
+                    )
+                    .route(
+                        "/account/",
+                        web::to(|req: HttpRequest| match req.cookie("session") {
+                            Some(ref c) if c.value() == "id" => HttpResponse::Ok()
+                                .cookie(Cookie::new("visited", "1"))
+                                .body(
+                                    req.headers()
+                                        .get(header::COOKIE)
+                                        .unwrap()
+                                        .to_str()
+                                        .unwrap()
+                                        .to_owned(),
+                                ),
+                            _ => HttpResponse::Unauthorized().finish(),
+                        }),
+                    ),
+            )
+        });
+
+        let store = CookieStore::new();
+        let client = awc::Client::build().cookie_store(store.clone()).finish();
+
+        // cookie is stored by redirect response
+        let mut response = client.post(srv.url("/login")).send().await.unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"session=id"));
+
+        // cookie with default path is sent only to /account
+        let mut response = client
+            .get(srv.url("/account/"))
+            .cookie(Cookie::new("extra", "1"))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"extra=1; visited=1; session=id"));
+
+        let uri = srv.url("/").parse().unwrap();
+        assert_eq!(store.cookies(&uri).len(), 1);
+
+        store.clear();
+        let response = client.get(srv.url("/account/")).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+    })
+}
+
+#[test]
+fn test_client_multipart() {
+    use awc::multipart::{Form, Part};
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                |req: HttpRequest, body: Bytes| {
+                    let content_type = req.headers().get(header::CONTENT_TYPE).unwrap();
+                    assert!(content_type
+                        .to_str()
+                        .unwrap()
+                        .starts_with("multipart/form-data; boundary="));
+                    let sized = req.headers().contains_key(header::CONTENT_LENGTH);
+                    HttpResponse::Ok()
+                        .header("x-sized", if sized { "1" } else { "0" })
+                        .body(body)
+                },
+            ))))
+        });
+
+        let form =
+            Form::new()
+                .text("name", "actix")
+                .file("file", "data.txt", &b"data"[..]);
+        let boundary = form.boundary().to_owned();
+        let mut response = srv.post("/").send_multipart(form).await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("x-sized").unwrap(), "1");
+        let bytes = response.body().await.unwrap();
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nactix\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; \
+             filename=\"data.txt\"\r\nContent-Type: application/octet-stream\r\n\r\n\
+             data\r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(bytes, Bytes::from(expected));
+
+        // reader without length is sent chunked
+        let form = Form::new().part("file", Part::reader(&STR.as_bytes()[..]));
+        let mut response = srv.post("/").send_multipart(form).await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("x-sized").unwrap(), "0");
+        let bytes = response.body().await.unwrap();
+        assert!(bytes.len() > STR.len());
+    })
+}
+
+#[test]
+fn test_client_read_timeout() {
+    use awc::error::PayloadError;
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                || {
+                    let body = futures::stream::once(async {
+                        tokio_timer::delay_for(Duration::from_millis(300)).await;
+                        Ok::<_, Error>(Bytes::from_static(b"data"))
+                    });
+                    HttpResponse::Ok().streaming(Box::pin(body))
+                },
+            ))))
+        });
+
+        let client = awc::Client::build()
+            .read_timeout(Duration::from_millis(50))
+            .finish();
+
+        // response head is received, body read times out
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        match response.body().await {
+            Err(PayloadError::Io(ref e)) if e.kind() == std::io::ErrorKind::TimedOut => {
+                ()
+            }
+            res => panic!("{:?}", res),
+        }
+
+        // request setting overrides client setting
+        let mut response = client
+            .get(srv.url("/"))
+            .read_timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .unwrap();
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"data"));
+    })
+}
+
+#[test]
+fn test_client_resolve_to() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                |req: HttpRequest| {
+                    HttpResponse::Ok().body(
+                        req.headers()
+                            .get(header::HOST)
+                            .unwrap()
+                            .to_str()
+                            .unwrap()
+                            .to_owned(),
+                    )
+                },
+            ))))
+        });
+
+        let client = awc::Client::build()
+            .connector(
+                awc::Connector::new()
+                    .resolve_to("service.local", vec![srv.addr()])
+                    .finish(),
+            )
+            .finish();
+
+        let mut response = client.get("http://service.local/").send().await.unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"service.local"));
+    })
+}
+
+#[test]
+fn test_client_http2_prior_knowledge() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                |req: HttpRequest| {
+                    HttpResponse::Ok().body(format!("{:?}", req.version()))
+                },
+            ))))
+        });
+
+        let client = awc::Client::build().http2_prior_knowledge().finish();
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.version(), Version::HTTP_2);
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"HTTP/2.0"));
+
+        let mut response = srv.get("/").send().await.unwrap();
+        assert_eq!(response.version(), Version::HTTP_11);
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"HTTP/1.1"));
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn test_client_unix_socket() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+
+    let path =
+        std::env::temp_dir().join(format!("awc-unix-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let lst = UnixListener::bind(&path).unwrap();
+    std::thread::spawn(move || {
+        let (stream, _) = lst.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut host = String::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            if line.to_ascii_lowercase().starts_with("host:") {
+                host = line[5..].trim().to_owned();
+            }
+            line.clear();
+        }
+        let mut stream = reader.into_inner();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+            host.len(),
+            host
+        )
+        .unwrap();
+    });
+
+    block_on(async {
+        let client = awc::Client::build()
+            .connector(awc::Connector::new().unix(&path).finish())
+            .finish();
+
+        let mut response = client.get("http://docker/info").send().await.unwrap();
+        assert!(response.status().is_success());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"docker"));
+    });
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_client_trace_hooks() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use actix_http::RequestHead;
+    use awc::middleware::{Timing, Trace, TraceHooks};
+
+    #[derive(Clone, Default)]
+    struct Events(Rc<RefCell<Vec<String>>>);
+
+    impl TraceHooks for Events {
+        fn on_request_start(&self, req: &mut RequestHead) {
+            req.headers.insert(
+                header::HeaderName::from_static("x-trace-id"),
+                header::HeaderValue::from_static("42"),
+            );
+            self.0.borrow_mut().push("start".to_owned());
+        }
+
+        fn on_connect(&self, _: &RequestHead, timing: &Timing) {
+            assert!(timing.connect().is_some());
+            self.0.borrow_mut().push("connect".to_owned());
+        }
+
+        fn on_first_byte(
+            &self,
+            _: &RequestHead,
+            res: &actix_http::ResponseHead,
+            timing: &Timing,
+        ) {
+            assert!(timing.first_byte().is_some());
+            self.0
+                .borrow_mut()
+                .push(format!("first_byte {}", res.status));
+        }
+
+        fn on_complete(
+            &self,
+            _: &RequestHead,
+            timing: &Timing,
+            err: Option<&dyn std::fmt::Display>,
+        ) {
+            assert!(timing.total().is_some());
+            self.0
+                .borrow_mut()
+                .push(format!("complete {}", err.is_some()));
+        }
+    }
+
+    block_on(async {
+        let srv = TestServer::start(|| {
+            HttpService::new(App::new().service(web::resource("/").route(web::to(
+                |req: HttpRequest| {
+                    HttpResponse::Ok().body(
+                        req.headers()
+                            .get("x-trace-id")
+                            .unwrap()
+                            .to_str()
+                            .unwrap()
+                            .to_owned(),
+                    )
+                },
+            ))))
+        });
+
+        let events = Events::default();
+        let client = awc::Client::build()
+            .wrap(Trace::new(events.clone()))
+            .finish();
+
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(events.0.borrow().len(), 3);
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"42"));
+        assert_eq!(
+            *events.0.borrow(),
+            vec!["start", "connect", "first_byte 200 OK", "complete false"]
+        );
+
+        // connection error
+        events.0.borrow_mut().clear();
+        let res = client.get("http://localhost:1/").send().await;
+        assert!(res.is_err());
+        assert_eq!(*events.0.borrow(), vec!["start", "complete true"]);
+    })
+}
+
+#[test]
+fn test_client_cache() {
+    use awc::middleware::{Cache, MemoryStore};
+
+    block_on(async {
+        let num = Arc::new(AtomicUsize::new(0));
+        let num2 = num.clone();
+
+        let srv = TestServer::start(move || {
+            let num2 = num2.clone();
+            let num3 = num2.clone();
+            HttpService::new(
+                App::new()
+                    .route(
+                        "/fresh",
+                        web::to(move || {
+                            num2.fetch_add(1, Ordering::Relaxed);
+                            HttpResponse::Ok()
+                                .header(header::CACHE_CONTROL, "max-age=60")
+                                .body("fresh")
+                        }),
+                    )
+                    .route(
+                        "/etag",
+                        web::to(move |req: HttpRequest| {
+                            num3.fetch_add(1, Ordering::Relaxed);
+                            let etag = req.headers().get(header::IF_NONE_MATCH);
+                            match etag.and_then(|etag| etag.to_str().ok()) {
+                                Some("\"v1\"") => HttpResponse::NotModified()
+                                    .header(header::ETAG, "\"v1\"")
+                                    .finish(),
+                                _ => HttpResponse::Ok()
+                                    .header(header::ETAG, "\"v1\"")
+                                    .header(header::CACHE_CONTROL, "no-cache")
+                                    .body("etag"),
+                            }
+                        }),
+                    ),
+            )
+        });
+
+        let store = MemoryStore::new(10);
+        let client = awc::Client::build()
+            .cache(Cache::with_store(store.clone()))
+            .finish();
+
+        // second request is served from cache
+        for _ in 0..2 {
+            let mut response = client.get(srv.url("/fresh")).send().await.unwrap();
+            assert!(response.status().is_success());
+            let bytes = response.body().await.unwrap();
+            assert_eq!(bytes, Bytes::from_static(b"fresh"));
+        }
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+        let response = client.get(srv.url("/fresh")).send().await.unwrap();
+        assert!(response.headers().contains_key(header::AGE));
+
+        // unsafe request invalidates stored response
+        let response = client.post(srv.url("/fresh")).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert!(store.is_empty());
+        let _ = client.get(srv.url("/fresh")).send().await.unwrap();
+        assert_eq!(num.load(Ordering::Relaxed), 3);
+
+        // stored response is revalidated
+        for _ in 0..2 {
+            let mut response = client.get(srv.url("/etag")).send().await.unwrap();
+            assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+            let bytes = response.body().await.unwrap();
+            assert_eq!(bytes, Bytes::from_static(b"etag"));
+        }
+        assert_eq!(num.load(Ordering::Relaxed), 5);
+        assert_eq!(store.len(), 2);
+    })
+}
@@ -1,8 +1,13 @@
+use std::cell::Cell;
 use std::io;
+use std::rc::Rc;
+use std::time::Duration;
 
 use actix_codec::Framed;
 use actix_http::{body::BodySize, h1, ws, Error, HttpService, Request, Response};
 use actix_http_test::{block_on, TestServer};
+use awc::middleware::Retry;
+use awc::ws::{Heartbeat, Reconnect};
 use bytes::{Bytes, BytesMut};
 use futures::future::ok;
 use futures::{SinkExt, StreamExt};
@@ -81,3 +86,76 @@ fn test_simple() {
         assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
     })
 }
+
+fn ws_server() -> TestServer {
+    TestServer::start(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| async move {
+                let res = ws::handshake_response(req.head()).finish();
+                framed
+                    .send(h1::Message::Item((res.drop_body(), BodySize::None)))
+                    .await?;
+
+                let framed = framed.into_framed(ws::Codec::new());
+                ws::Transport::with(framed, ws_service).await
+            })
+            .finish(|_| ok::<_, Error>(Response::NotFound()))
+    })
+}
+
+#[test]
+fn test_heartbeat() {
+    block_on(async {
+        let mut srv = ws_server();
+
+        let framed = srv.ws().await.unwrap();
+        let mut framed =
+            Heartbeat::new(framed, Duration::from_millis(50), Duration::from_secs(5));
+
+        // server answers ping of the heartbeat
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Pong(String::new()));
+
+        framed
+            .send(ws::Message::Text("text".to_string()))
+            .await
+            .unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Some(BytesMut::from("text"))));
+    })
+}
+
+#[test]
+fn test_reconnect() {
+    block_on(async {
+        let srv = ws_server();
+        let url = srv.url("/");
+        let connects = Rc::new(Cell::new(0));
+
+        let client = awc::Client::new();
+        let counter = connects.clone();
+        let mut conn = Reconnect::new(move || {
+            counter.set(counter.get() + 1);
+            client.ws(url.as_str()).connect()
+        })
+        .retry(
+            Retry::new().backoff(Duration::from_millis(10), Duration::from_millis(10)),
+        )
+        .on_connect(|| vec![ws::Message::Text("subscribe".to_string())]);
+
+        let item = conn.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Some(BytesMut::from("subscribe"))));
+
+        // server closes connection, it is re-established
+        conn.send(ws::Message::Close(None)).await.unwrap();
+        let item = conn.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Close(None));
+
+        let item = conn.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Some(BytesMut::from("subscribe"))));
+        assert_eq!(connects.get(), 2);
+
+        conn.close().await.unwrap();
+        assert!(conn.next().await.is_none());
+    })
+}
@@ -0,0 +1,83 @@
+//! Request through a stack of middlewares, composed with `App::wrap()` or
+//! boxed with `middleware::Boxed`. Run with `cargo bench --bench middleware`.
+//!
+//! Heap allocations of a single request are counted by global allocator and
+//! printed for each app, app without middlewares is the baseline.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::middleware::{Boxed, DefaultHeaders};
+use actix_web::test::{block_on, call_service, init_service, TestRequest};
+use actix_web::{web, App, Error, HttpResponse};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn headers(n: usize) -> DefaultHeaders {
+    DefaultHeaders::new().header(format!("x-header-{}", n).as_str(), "value")
+}
+
+fn bench<S, B>(c: &mut Criterion, name: &str, mut srv: S)
+where
+    S: Service<Request = Request, Response = ServiceResponse<B>, Error = Error>,
+{
+    // warm up request and response pools
+    block_on(call_service(&mut srv, TestRequest::default().to_request()));
+
+    let req = TestRequest::default().to_request();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    block_on(call_service(&mut srv, req));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{}: {} allocations per request", name, allocations);
+
+    c.bench_function(name, |b| {
+        b.iter(|| block_on(call_service(&mut srv, TestRequest::default().to_request())))
+    });
+}
+
+fn bench_middleware(c: &mut Criterion) {
+    let srv = block_on(init_service(
+        App::new().route("/", web::get().to(|| HttpResponse::Ok())),
+    ));
+    bench(c, "no middlewares", srv);
+
+    let srv = block_on(init_service(
+        App::new()
+            .wrap(headers(0))
+            .wrap(headers(1))
+            .wrap(headers(2))
+            .wrap(headers(3))
+            .route("/", web::get().to(|| HttpResponse::Ok())),
+    ));
+    bench(c, "static stack", srv);
+
+    let srv = block_on(init_service(
+        App::new()
+            .wrap(Boxed::stack(
+                (0..4).map(|n| Boxed::new(headers(n))).collect(),
+            ))
+            .route("/", web::get().to(|| HttpResponse::Ok())),
+    ));
+    bench(c, "boxed stack", srv);
+}
+
+criterion_group!(benches, bench_middleware);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Routing of a request to the last registered resource, with apps of
+//! different size. Run with `cargo bench --bench router`.
+//!
+//! `linear` apps register no resources and match the same patterns in the
+//! default service one by one, the way routing worked before the trie. Both
+//! go through the same app service, so the difference is the routing cost.
+use std::rc::Rc;
+
+use actix_web::dev::{ResourceDef, ServiceRequest};
+use actix_web::test::{block_on, call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::future::ok;
+
+fn patterns(n: usize) -> Vec<String> {
+    (0..n)
+        .flat_map(|i| {
+            vec![
+                format!("/api/v1/resource{}", i),
+                format!("/api/v1/resource{}/{{id}}", i),
+            ]
+        })
+        .collect()
+}
+
+fn bench_routes(c: &mut Criterion) {
+    for n in &[10, 100, 500] {
+        let mut app = App::new();
+        for pattern in patterns(*n) {
+            app = app.route(&pattern, web::get().to(|| HttpResponse::Ok()));
+        }
+        let mut srv = block_on(init_service(app));
+
+        let rdefs: Rc<Vec<_>> =
+            Rc::new(patterns(*n).iter().map(|p| ResourceDef::new(p)).collect());
+        let mut linear = block_on(init_service(App::new().default_service(
+            move |mut req: ServiceRequest| {
+                let found = rdefs
+                    .iter()
+                    .any(|rdef| rdef.match_path(req.match_info_mut()));
+                assert!(found);
+                ok(req.into_response(HttpResponse::Ok()))
+            },
+        )));
+
+        let static_path = format!("/api/v1/resource{}", n - 1);
+        let dynamic_path = format!("/api/v1/resource{}/42", n - 1);
+
+        for (name, path) in &[("static", &static_path), ("dynamic", &dynamic_path)] {
+            c.bench_function(&format!("{} route, {} resources", name, n * 2), |b| {
+                b.iter(|| {
+                    let req = TestRequest::with_uri(path).to_request();
+                    block_on(call_service(&mut srv, req))
+                })
+            });
+            c.bench_function(
+                &format!("{} route, {} resources, linear", name, n * 2),
+                |b| {
+                    b.iter(|| {
+                        let req = TestRequest::with_uri(path).to_request();
+                        block_on(call_service(&mut linear, req))
+                    })
+                },
+            );
+        }
+    }
+}
+
+criterion_group!(benches, bench_routes);
+criterion_main!(benches);
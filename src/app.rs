@@ -7,23 +7,34 @@ use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use actix_http::body::{Body, MessageBody};
+use actix_http::{PayloadDrain, Request, ResponseHead};
+use actix_server_config::ServerConfig;
 use actix_service::boxed::{self, BoxedNewService};
 use actix_service::{
     apply, apply_fn_factory, IntoServiceFactory, ServiceFactory, Transform,
 };
-use futures::future::{FutureExt, LocalBoxFuture};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture};
 
-use crate::app_service::{AppEntry, AppInit, AppRoutingFactory};
+use crate::app_service::{
+    AppEntry, AppHooks, AppInit, AppInitService, AppRoutingFactory, ErrorRenderer,
+};
 use crate::config::{AppConfig, AppConfigInner, ServiceConfig};
-use crate::data::{Data, DataFactory};
+use crate::data::{Data, DataFactory, WorkerData};
 use crate::dev::ResourceDef;
 use crate::error::Error;
+use crate::extract::FromRequest;
+use crate::handler::Factory;
+use crate::info::ForwardedConfig;
+use crate::metrics::RouteMetrics;
+use crate::request::HttpRequest;
 use crate::resource::Resource;
+use crate::responder::Responder;
 use crate::route::Route;
 use crate::service::{
     AppServiceFactory, HttpServiceFactory, ServiceFactoryWrapper, ServiceRequest,
     ServiceResponse,
 };
+use crate::HttpResponse;
 
 type HttpNewService = BoxedNewService<(), ServiceRequest, ServiceResponse, Error, ()>;
 type FnDataFactory =
@@ -35,11 +46,14 @@ pub struct App<T, B> {
     endpoint: T,
     services: Vec<Box<dyn AppServiceFactory>>,
     default: Option<Rc<HttpNewService>>,
+    not_found: Option<Rc<HttpNewService>>,
     factory_ref: Rc<RefCell<Option<AppRoutingFactory>>>,
     data: Vec<Box<dyn DataFactory>>,
     data_factories: Vec<FnDataFactory>,
     config: AppConfigInner,
     external: Vec<ResourceDef>,
+    error_renderer: Option<Rc<ErrorRenderer>>,
+    hooks: AppHooks,
     _t: PhantomData<(B)>,
 }
 
@@ -53,9 +67,12 @@ impl App<AppEntry, Body> {
             data_factories: Vec::new(),
             services: Vec::new(),
             default: None,
+            not_found: None,
             factory_ref: fref,
             config: AppConfigInner::default(),
             external: Vec::new(),
+            error_renderer: None,
+            hooks: AppHooks::default(),
             _t: PhantomData,
         }
     }
@@ -110,6 +127,9 @@ where
     /// Set application data factory. This function is
     /// similar to `.data()` but it accepts data factory. Data object get
     /// constructed asynchronously during application initialization.
+    ///
+    /// Use `AsyncApp` if application configuration depends on async
+    /// initialization.
     pub fn data_factory<F, Out, D, E>(mut self, data: F) -> Self
     where
         F: Fn() -> Out + 'static,
@@ -138,6 +158,36 @@ where
         self
     }
 
+    /// Set worker data factory. Data object is constructed by factory
+    /// during application initialization on each worker thread and could
+    /// be accessed by using `WorkerData<T>` extractor.
+    ///
+    /// Worker data does not need to be `Send` or `Sync`, it is useful for
+    /// `Rc` based caches or thread-affine clients.
+    ///
+    /// ```rust
+    /// use std::cell::Cell;
+    /// use actix_web::{web, App};
+    ///
+    /// async fn index(counter: web::WorkerData<Cell<usize>>) -> String {
+    ///     counter.set(counter.get() + 1);
+    ///     format!("requests on this worker: {}", counter.get())
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .worker_data(|| Cell::new(0usize))
+    ///         .route("/", web::get().to(index));
+    /// }
+    /// ```
+    pub fn worker_data<F, D>(self, factory: F) -> Self
+    where
+        F: Fn() -> D + 'static,
+        D: 'static,
+    {
+        self.data_factory(move || ok::<_, ()>(WorkerData::new(factory())))
+    }
+
     /// Set application data. Application data could be accessed
     /// by using `Data<T>` extractor where `T` is data type.
     pub fn register_data<U: 'static>(mut self, data: Data<U>) -> Self {
@@ -293,6 +343,42 @@ where
         self
     }
 
+    /// Set handler for requests that do not match path of any resource.
+    ///
+    /// Unlike default service, handler is not called if path matches
+    /// a resource but resource guards do not, i.e. for wrong http method.
+    /// Such requests are still handled by default service. Scopes without
+    /// own default service use this handler as well.
+    ///
+    /// ```rust
+    /// use actix_web::{guard, web, App, HttpRequest, HttpResponse};
+    ///
+    /// async fn not_found(req: HttpRequest) -> HttpResponse {
+    ///     HttpResponse::NotFound().body(format!("{} is not found", req.path()))
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(
+    ///             web::resource("/index.html")
+    ///                 .guard(guard::Get())
+    ///                 .to(|| HttpResponse::Ok()))
+    ///         .default_service(
+    ///             web::route().to(|| HttpResponse::MethodNotAllowed()))
+    ///         .not_found(not_found);
+    /// }
+    /// ```
+    pub fn not_found<F, I, R, U>(mut self, handler: F) -> Self
+    where
+        F: Factory<I, R, U>,
+        I: FromRequest + 'static,
+        R: Future<Output = U> + 'static,
+        U: Responder + 'static,
+    {
+        self.not_found = Some(Rc::new(boxed::factory(Route::new().to(handler))));
+        self
+    }
+
     /// Register an external resource.
     ///
     /// External resources are useful for URL generation purposes only
@@ -326,6 +412,190 @@ where
         self
     }
 
+    /// Set application wide error renderer.
+    ///
+    /// Renderer is called for every error that reaches the application,
+    /// including errors of extractors, handlers and middlewares, and its
+    /// response is sent instead of the error response. It is useful for
+    /// rendering all errors in the same format.
+    ///
+    /// Errors of middlewares are rendered with the request released by the
+    /// middleware, error is returned as is if the request is still
+    /// referenced elsewhere, i.e. by a spawned future.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .error_renderer(|err, req| {
+    ///             let res = err.as_response_error().error_response();
+    ///             HttpResponse::build(res.status()).json(serde_json::json!({
+    ///                 "error": err.to_string(),
+    ///                 "path": req.path(),
+    ///             }))
+    ///         })
+    ///         .route("/index.html", web::get().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn error_renderer<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Error, &HttpRequest) -> HttpResponse + 'static,
+    {
+        self.error_renderer = Some(Rc::new(f));
+        self
+    }
+
+    /// Render detailed error responses.
+    ///
+    /// If enabled, responses of errors contain the error, its whole
+    /// `source()` chain and backtrace, see `Error::debug_response()`.
+    /// Backtrace is captured only if `RUST_BACKTRACE` environment variable
+    /// is set. By default error responses are generic.
+    ///
+    /// Error renderer set with `App::error_renderer()` takes precedence.
+    /// Debug responses expose internals of the application, they should
+    /// not be enabled in production.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .debug_errors(cfg!(debug_assertions))
+    ///         .route("/index.html", web::get().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn debug_errors(mut self, enabled: bool) -> Self {
+        self.config.debug_errors = enabled;
+        self
+    }
+
+    /// Configure forwarding headers used by `ConnectionInfo`.
+    ///
+    /// Trusted proxies and precedence of `Forwarded` and `X-Forwarded-*`
+    /// headers are defined by `ForwardedConfig`. By default forwarding
+    /// headers of all peers are trusted and `Forwarded` header takes
+    /// precedence.
+    pub fn forwarded(mut self, cfg: ForwardedConfig) -> Self {
+        self.config.forwarded = cfg;
+        self
+    }
+
+    /// Set policy for request payload that is not read by the handler.
+    ///
+    /// When response is sent before request payload is read completely,
+    /// i.e. upload is rejected by authentication middleware, rest of the
+    /// payload has to be read from the connection before it could be kept
+    /// alive. By default connection is closed, unless rest of the payload
+    /// is already read, `PayloadDrain::Limit(n)` reads and discards up to
+    /// `n` bytes. Policy could be overridden with `Resource::drain_payload()`.
+    ///
+    /// ```rust
+    /// use actix_web::{dev::PayloadDrain, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .drain_payload(PayloadDrain::Limit(16_384))
+    ///         .route("/index.html", web::post().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn drain_payload(mut self, drain: PayloadDrain) -> Self {
+        self.config.payload_drain = drain;
+        self
+    }
+
+    /// Record hit counts and latency histograms of resources.
+    ///
+    /// Metrics are recorded by the router, see `metrics` module. Same
+    /// `RouteMetrics` should be used by application factories of all
+    /// workers.
+    pub fn route_metrics(mut self, metrics: RouteMetrics) -> Self {
+        self.config.route_metrics = Some(metrics);
+        self
+    }
+
+    /// Set capacity of `HttpRequest` pool of the worker.
+    ///
+    /// Requests are returned to the pool when they are dropped, so
+    /// allocations are reused by following requests. Pool keeps up to 128
+    /// requests by default, capacity of 0 disables pooling. Hits and misses
+    /// of the pool are counted by `dev::http_request_pool_stats()`, pools of
+    /// request and response heads are configured with `dev::pool` functions.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .request_pool(16)
+    ///         .route("/index.html", web::get().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn request_pool(mut self, capacity: usize) -> Self {
+        self.config.request_pool = capacity;
+        self
+    }
+
+    /// Register hook that is called for every request before middlewares
+    /// and routing.
+    ///
+    /// Hooks run outside of the middleware stack, request processing
+    /// continues once future of the hook completes. Hooks are intended for
+    /// lightweight bookkeeping, i.e. request accounting or audit trails,
+    /// that does not change the request. Hook must not keep clones of the
+    /// request once its future completes, routing requires unique access to
+    /// the request, such request fails with *INTERNAL SERVER ERROR*.
+    ///
+    /// ```rust
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let in_flight = Rc::new(Cell::new(0usize));
+    ///     let (started, done) = (in_flight.clone(), in_flight.clone());
+    ///
+    ///     let app = App::new()
+    ///         .on_request(move |_| {
+    ///             started.set(started.get() + 1);
+    ///             async {}
+    ///         })
+    ///         .on_response(move |_, _| {
+    ///             done.set(done.get() - 1);
+    ///             async {}
+    ///         })
+    ///         .route("/index.html", web::get().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn on_request<F, R>(mut self, hook: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.hooks
+            .on_request
+            .push(Box::new(move |req| hook(req).boxed_local()));
+        self
+    }
+
+    /// Register hook that is called for every response after all
+    /// middlewares.
+    ///
+    /// Errors of the services are rendered before the hook is called, so
+    /// hook sees head of the response that is sent to the client. Response
+    /// is returned once future of the hook completes.
+    pub fn on_response<F, R>(mut self, hook: F) -> Self
+    where
+        F: Fn(&HttpRequest, &ResponseHead) -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.hooks
+            .on_response
+            .push(Box::new(move |req, head| hook(req, head).boxed_local()));
+        self
+    }
+
     /// Registers middleware, in the form of a middleware component (type),
     /// that runs during inbound and/or outbound processing in the request
     /// lifecycle (request -> response), modifying request/response as
@@ -385,9 +655,12 @@ where
             data_factories: self.data_factories,
             services: self.services,
             default: self.default,
+            not_found: self.not_found,
             factory_ref: self.factory_ref,
             config: self.config,
             external: self.external,
+            error_renderer: self.error_renderer,
+            hooks: self.hooks,
             _t: PhantomData,
         }
     }
@@ -447,9 +720,12 @@ where
             data_factories: self.data_factories,
             services: self.services,
             default: self.default,
+            not_found: self.not_found,
             factory_ref: self.factory_ref,
             config: self.config,
             external: self.external,
+            error_renderer: self.error_renderer,
+            hooks: self.hooks,
             _t: PhantomData,
         }
     }
@@ -467,6 +743,14 @@ where
     >,
 {
     fn into_factory(self) -> AppInit<T, B> {
+        let error_renderer = match self.error_renderer {
+            Some(renderer) => Some(renderer),
+            None if self.config.debug_errors => {
+                Some(Rc::new(|err: &Error, _: &HttpRequest| err.debug_response())
+                    as Rc<ErrorRenderer>)
+            }
+            None => None,
+        };
         AppInit {
             data: Rc::new(self.data),
             data_factories: Rc::new(self.data_factories),
@@ -474,12 +758,94 @@ where
             services: Rc::new(RefCell::new(self.services)),
             external: RefCell::new(self.external),
             default: self.default,
+            not_found: self.not_found,
             factory_ref: self.factory_ref,
             config: RefCell::new(AppConfig(Rc::new(self.config))),
+            error_renderer,
+            hooks: if self.hooks.is_empty() {
+                None
+            } else {
+                Some(Rc::new(self.hooks))
+            },
+        }
+    }
+}
+
+/// Application that is constructed asynchronously.
+///
+/// Wraps future that resolves to `App`, it is awaited during initialization
+/// of the worker, so per-worker state that requires async construction,
+/// i.e. database pools, could be created without blocking.
+///
+/// ```rust
+/// use actix_web::{web, App, AsyncApp, HttpResponse, HttpServer};
+///
+/// struct Pool;
+///
+/// async fn create_pool() -> Pool {
+///     Pool
+/// }
+///
+/// fn main() {
+///     let server = HttpServer::new(|| {
+///         AsyncApp::new(async {
+///             let pool = create_pool().await;
+///             App::new()
+///                 .data(pool)
+///                 .route("/", web::get().to(|| HttpResponse::Ok()))
+///         })
+///     });
+/// }
+/// ```
+pub struct AsyncApp<F> {
+    fut: RefCell<Option<F>>,
+}
+
+impl<F, T, B> AsyncApp<F>
+where
+    F: Future<Output = App<T, B>>,
+{
+    /// Create application from future.
+    pub fn new(fut: F) -> Self {
+        AsyncApp {
+            fut: RefCell::new(Some(fut)),
         }
     }
 }
 
+impl<F, T, B> ServiceFactory for AsyncApp<F>
+where
+    F: Future<Output = App<T, B>> + 'static,
+    B: MessageBody + 'static,
+    T: ServiceFactory<
+            Config = (),
+            Request = ServiceRequest,
+            Response = ServiceResponse<B>,
+            Error = Error,
+            InitError = (),
+        > + 'static,
+{
+    type Config = ServerConfig;
+    type Request = Request;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Service = AppInitService<T::Service, B>;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, ()>>;
+
+    fn new_service(&self, cfg: &ServerConfig) -> Self::Future {
+        let fut = if let Some(fut) = self.fut.borrow_mut().take() {
+            fut
+        } else {
+            log::error!("Async application is already initialized");
+            return err(()).boxed_local();
+        };
+        let cfg = cfg.clone();
+
+        async move { fut.await.into_factory().new_service(&cfg).await }.boxed_local()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::Service;
@@ -540,6 +906,58 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_not_found() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .data(10usize)
+                    .service(
+                        web::resource("/test")
+                            .guard(crate::guard::Get())
+                            .to(|| HttpResponse::Ok()),
+                    )
+                    .service(
+                        web::scope("/scope")
+                            .route("/test", web::get().to(|| HttpResponse::Ok())),
+                    )
+                    .default_service(|r: ServiceRequest| {
+                        ok(r.into_response(HttpResponse::MethodNotAllowed()))
+                    })
+                    .not_found(|req: HttpRequest, data: web::Data<usize>| {
+                        HttpResponse::NotFound().body(format!(
+                            "{} {}",
+                            req.path(),
+                            *data
+                        ))
+                    }),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/test").to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let req = TestRequest::with_uri("/test")
+                .method(Method::POST)
+                .to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+            let req = TestRequest::with_uri("/blah").to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            let body = read_body(resp).await;
+            assert_eq!(body, Bytes::from_static(b"/blah 10"));
+
+            let req = TestRequest::with_uri("/scope/blah").to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            let body = read_body(resp).await;
+            assert_eq!(body, Bytes::from_static(b"/scope/blah 10"));
+        })
+    }
+
     #[test]
     fn test_data_factory() {
         block_on(async {
@@ -711,4 +1129,234 @@ mod tests {
             assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
         })
     }
+
+    #[test]
+    fn test_error_renderer() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .error_renderer(|err, req| {
+                        let status = err.as_response_error().error_response().status();
+                        HttpResponse::build(status).body(format!(
+                            "{} {}",
+                            status.as_u16(),
+                            req.path()
+                        ))
+                    })
+                    .wrap_fn(|req, srv| {
+                        let fut = srv.call(req);
+                        async move {
+                            let res = fut.await?;
+                            if res.request().path() == "/mw" {
+                                Err(crate::error::ErrorForbidden("mw"))
+                            } else {
+                                Ok(res)
+                            }
+                        }
+                    })
+                    .route("/ok", web::get().to(|| HttpResponse::Ok().body("ok")))
+                    .route(
+                        "/path/{id}",
+                        web::get().to(|_: web::Path<u32>| HttpResponse::Ok()),
+                    )
+                    .route(
+                        "/handler",
+                        web::get().to(|| async {
+                            Err::<HttpResponse, _>(crate::error::ErrorConflict(
+                                "handler",
+                            ))
+                        }),
+                    )
+                    .route("/mw", web::get().to(|| HttpResponse::Ok())),
+            )
+            .await;
+
+            for (path, status, body) in &[
+                ("/ok", StatusCode::OK, "ok"),
+                ("/path/abc", StatusCode::NOT_FOUND, "404 /path/abc"),
+                ("/handler", StatusCode::CONFLICT, "409 /handler"),
+                ("/mw", StatusCode::FORBIDDEN, "403 /mw"),
+                ("/missing", StatusCode::NOT_FOUND, ""),
+            ] {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(&mut srv, req).await;
+                assert_eq!(resp.status(), *status);
+                let resp_body = read_body(resp).await;
+                assert_eq!(resp_body, Bytes::from(*body));
+            }
+        })
+    }
+
+    #[test]
+    fn test_error_renderer_request_pool() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .debug_errors(true)
+                    .route(
+                        "/path/{id}",
+                        web::get().to(|_: web::Path<u32>| HttpResponse::Ok()),
+                    )
+                    .route(
+                        "/handler",
+                        web::get().to(|| async {
+                            Err::<HttpResponse, _>(crate::error::ErrorConflict(""))
+                        }),
+                    ),
+            )
+            .await;
+
+            // request is not shared with renderer, so it returns to the pool
+            let start = crate::dev::http_request_pool_stats();
+            for path in &["/path/1", "/handler", "/path/abc", "/path/2"] {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(&mut srv, req).await;
+                assert_ne!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            let stats = crate::dev::http_request_pool_stats();
+            assert_eq!(stats.hits - start.hits, 3);
+        })
+    }
+
+    #[test]
+    fn test_hooks() {
+        block_on(async {
+            let log = Rc::new(RefCell::new(Vec::new()));
+            let (log1, log2, log3) = (log.clone(), log.clone(), log.clone());
+
+            let mut srv = init_service(
+                App::new()
+                    .on_request(move |req| {
+                        log1.borrow_mut().push(format!("request {}", req.path()));
+                        async {}
+                    })
+                    .on_response(move |req, head| {
+                        let log = log2.clone();
+                        let msg = format!("response {} {}", req.path(), head.status);
+                        async move { log.borrow_mut().push(msg) }
+                    })
+                    .wrap_fn(move |req, srv| {
+                        log3.borrow_mut().push("middleware".to_owned());
+                        let fut = srv.call(req);
+                        async move {
+                            let res = fut.await?;
+                            if res.request().path() == "/mw" {
+                                Err(crate::error::ErrorForbidden("mw"))
+                            } else {
+                                Ok(res)
+                            }
+                        }
+                    })
+                    .route("/ok", web::get().to(|| HttpResponse::Ok()))
+                    .route("/mw", web::get().to(|| HttpResponse::Ok())),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/ok").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let req = TestRequest::with_uri("/mw").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            assert_eq!(
+                *log.borrow(),
+                vec![
+                    "request /ok",
+                    "middleware",
+                    "response /ok 200 OK",
+                    "request /mw",
+                    "middleware",
+                    "response /mw 403 Forbidden",
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_hook_keeps_request() {
+        block_on(async {
+            let kept = Rc::new(RefCell::new(Vec::new()));
+            let kept2 = kept.clone();
+
+            let mut srv = init_service(
+                App::new()
+                    .on_request(move |req| {
+                        kept2.borrow_mut().push(req.request().clone());
+                        async {}
+                    })
+                    .route("/", web::get().to(|| HttpResponse::Ok())),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/").to_request();
+            let err = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                err.as_response_error().error_response().status(),
+                StatusCode::INTERNAL_SERVER_ERROR
+            );
+            assert_eq!(kept.borrow().len(), 1);
+        })
+    }
+
+    #[test]
+    fn test_debug_errors() {
+        use crate::error::{JsonPayloadError, PayloadError};
+
+        fn app(debug: bool) -> App<AppEntry, Body> {
+            App::new().debug_errors(debug).route(
+                "/",
+                web::get().to(|| async {
+                    Err::<HttpResponse, _>(JsonPayloadError::Payload(PayloadError::Io(
+                        std::io::Error::new(std::io::ErrorKind::Other, "reset"),
+                    )))
+                }),
+            )
+        }
+
+        block_on(async {
+            let mut srv = init_service(app(false)).await;
+            let req = TestRequest::with_uri("/").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body = read_body(resp).await;
+            assert_eq!(
+                body,
+                Bytes::from_static(b"Error that occur during reading payload: reset")
+            );
+
+            let mut srv = init_service(app(true)).await;
+            let req = TestRequest::with_uri("/").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body = read_body(resp).await;
+            assert!(body.starts_with(
+                b"Error that occur during reading payload: reset\n\n\
+                  Caused by:\n    reset\n    reset\n"
+            ));
+        })
+    }
+
+    #[test]
+    fn test_async_app() {
+        block_on(async {
+            let mut srv = init_service(AsyncApp::new(async {
+                let data = async { 10usize }.await;
+                App::new().data(data).route(
+                    "/",
+                    web::get().to(|data: web::Data<usize>| {
+                        HttpResponse::Ok().body(format!("{}", *data))
+                    }),
+                )
+            }))
+            .await;
+
+            let req = TestRequest::default().to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = read_body(resp).await;
+            assert_eq!(body, Bytes::from_static(b"10"));
+        })
+    }
 }
@@ -5,8 +5,8 @@ use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
-use actix_http::{Extensions, Request, Response};
-use actix_router::{Path, ResourceDef, ResourceInfo, Router, Url};
+use actix_http::{Extensions, HttpMessage, Request, Response, ResponseHead};
+use actix_router::{Path, ResourceDef, ResourceInfo, Url};
 use actix_server_config::ServerConfig;
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{service_fn, Service, ServiceFactory};
@@ -14,10 +14,11 @@ use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
 
 use crate::config::{AppConfig, AppService};
 use crate::data::DataFactory;
-use crate::error::Error;
+use crate::error::{self, Error};
 use crate::guard::Guard;
-use crate::request::{HttpRequest, HttpRequestPool};
+use crate::request::{HttpRequest, HttpRequestPool, RetainedRequest};
 use crate::rmap::ResourceMap;
+use crate::router::Router;
 use crate::service::{AppServiceFactory, ServiceRequest, ServiceResponse};
 
 type Guards = Vec<Box<dyn Guard>>;
@@ -26,6 +27,23 @@ type HttpNewService = BoxedNewService<(), ServiceRequest, ServiceResponse, Error
 type BoxedResponse = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
 type FnDataFactory =
     Box<dyn Fn() -> LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>;
+pub(crate) type ErrorRenderer = dyn Fn(&Error, &HttpRequest) -> Response;
+pub(crate) type RequestHook = dyn Fn(&ServiceRequest) -> LocalBoxFuture<'static, ()>;
+pub(crate) type ResponseHook =
+    dyn Fn(&HttpRequest, &ResponseHead) -> LocalBoxFuture<'static, ()>;
+
+/// Hooks registered with `App::on_request()` and `App::on_response()`
+#[derive(Default)]
+pub(crate) struct AppHooks {
+    pub(crate) on_request: Vec<Box<RequestHook>>,
+    pub(crate) on_response: Vec<Box<ResponseHook>>,
+}
+
+impl AppHooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.on_request.is_empty() && self.on_response.is_empty()
+    }
+}
 
 /// Service factory to convert `Request` to a `ServiceRequest<S>`.
 /// It also executes data factories.
@@ -47,6 +65,9 @@ where
     pub(crate) default: Option<Rc<HttpNewService>>,
     pub(crate) factory_ref: Rc<RefCell<Option<AppRoutingFactory>>>,
     pub(crate) external: RefCell<Vec<ResourceDef>>,
+    pub(crate) error_renderer: Option<Rc<ErrorRenderer>>,
+    pub(crate) hooks: Option<Rc<AppHooks>>,
+    pub(crate) not_found: Option<Rc<HttpNewService>>,
 }
 
 impl<T, B> ServiceFactory for AppInit<T, B>
@@ -74,6 +95,14 @@ where
                 ok(req.into_response(Response::NotFound().finish()))
             })))
         });
+        let default = if let Some(ref not_found) = self.not_found {
+            Rc::new(boxed::factory(NotFoundFactory {
+                default,
+                not_found: not_found.clone(),
+            }))
+        } else {
+            default
+        };
 
         // App config
         {
@@ -129,6 +158,8 @@ where
             data_factories_fut: self.data_factories.iter().map(|f| f()).collect(),
             config,
             rmap,
+            error_renderer: self.error_renderer.clone(),
+            hooks: self.hooks.clone(),
             _t: PhantomData,
         }
     }
@@ -147,6 +178,8 @@ where
     data: Rc<Vec<Box<dyn DataFactory>>>,
     data_factories: Vec<Box<dyn DataFactory>>,
     data_factories_fut: Vec<LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>,
+    error_renderer: Option<Rc<ErrorRenderer>>,
+    hooks: Option<Rc<AppHooks>>,
     _t: PhantomData<B>,
 }
 
@@ -195,11 +228,13 @@ where
             }
 
             Poll::Ready(Ok(AppInitService {
-                service: this.endpoint.take().unwrap(),
+                service: Rc::new(RefCell::new(this.endpoint.take().unwrap())),
                 rmap: this.rmap.clone(),
                 config: this.config.clone(),
                 data: Rc::new(data),
-                pool: HttpRequestPool::create(),
+                pool: HttpRequestPool::with_capacity(this.config.request_pool()),
+                error_renderer: this.error_renderer.clone(),
+                hooks: this.hooks.clone(),
             }))
         } else {
             Poll::Pending
@@ -212,11 +247,13 @@ pub struct AppInitService<T, B>
 where
     T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
 {
-    service: T,
+    service: Rc<RefCell<T>>,
     rmap: Rc<ResourceMap>,
     config: AppConfig,
     data: Rc<Extensions>,
     pool: &'static HttpRequestPool,
+    error_renderer: Option<Rc<ErrorRenderer>>,
+    hooks: Option<Rc<AppHooks>>,
 }
 
 impl<T, B> Service for AppInitService<T, B>
@@ -226,16 +263,17 @@ where
     type Request = Request;
     type Response = ServiceResponse<B>;
     type Error = T::Error;
-    type Future = T::Future;
+    type Future = AppInitServiceResponse<T, B>;
 
     fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.service.poll_ready(cx)
+        self.service.borrow_mut().poll_ready(cx)
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
         let (head, payload) = req.into_parts();
+        payload.set_drain(self.config.payload_drain());
 
-        let req = if let Some(mut req) = self.pool.get_request() {
+        let mut req = if let Some(mut req) = self.pool.get_request() {
             let inner = Rc::get_mut(&mut req.0).unwrap();
             inner.path.get_mut().update(&head.uri);
             inner.path.reset();
@@ -254,7 +292,173 @@ where
                 self.pool,
             )
         };
-        self.service.call(ServiceRequest::new(req))
+
+        // request is kept for responses of service errors once service
+        // releases it, services require unique access to it
+        let retained = if self.error_renderer.is_some()
+            || self
+                .hooks
+                .as_ref()
+                .map_or(false, |h| !h.on_response.is_empty())
+        {
+            Some(RetainedRequest::new(&mut req))
+        } else {
+            None
+        };
+        let req = ServiceRequest::new(req);
+
+        match self.hooks {
+            Some(ref hooks) if !hooks.on_request.is_empty() => AppInitServiceResponse {
+                fut: None,
+                req: Some(req),
+                res: None,
+                service: Some(self.service.clone()),
+                hooks: Some(hooks.clone()),
+                hook_fut: None,
+                hook_idx: 0,
+                renderer: self.error_renderer.clone(),
+                retained,
+            },
+            _ => AppInitServiceResponse {
+                fut: Some(self.service.borrow_mut().call(req)),
+                req: None,
+                res: None,
+                service: None,
+                hooks: self.hooks.clone(),
+                hook_fut: None,
+                hook_idx: 0,
+                renderer: self.error_renderer.clone(),
+                retained,
+            },
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct AppInitServiceResponse<T, B>
+where
+    T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    #[pin]
+    fut: Option<T::Future>,
+    // request waits for request hooks
+    req: Option<ServiceRequest>,
+    // response waits for response hooks
+    res: Option<ServiceResponse<B>>,
+    service: Option<Rc<RefCell<T>>>,
+    hooks: Option<Rc<AppHooks>>,
+    hook_fut: Option<LocalBoxFuture<'static, ()>>,
+    hook_idx: usize,
+    renderer: Option<Rc<ErrorRenderer>>,
+    retained: Option<RetainedRequest>,
+}
+
+impl<T, B> Future for AppInitServiceResponse<T, B>
+where
+    T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(ref mut fut) = this.hook_fut {
+                if fut.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                *this.hook_fut = None;
+            }
+
+            // request hooks
+            if let Some(req) = this.req.take() {
+                let hooks = this.hooks.as_ref().unwrap();
+                if *this.hook_idx < hooks.on_request.len() {
+                    *this.hook_fut = Some((hooks.on_request[*this.hook_idx])(&req));
+                    *this.hook_idx += 1;
+                    *this.req = Some(req);
+                } else if Rc::strong_count(&req.request().0) > 1 {
+                    log::error!("Request is referenced after on_request hook");
+                    drop(req);
+                    let err = error::ErrorInternalServerError("Request is shared");
+                    return Poll::Ready(Err(err));
+                } else {
+                    // readiness is checked again, service could be used by
+                    // other requests while hooks were running
+                    let mut service = this.service.as_ref().unwrap().borrow_mut();
+                    match service.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => (),
+                        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                        Poll::Pending => {
+                            drop(service);
+                            *this.req = Some(req);
+                            return Poll::Pending;
+                        }
+                    }
+                    *this.hook_idx = 0;
+                    let fut = service.call(req);
+                    drop(service);
+                    *this.service = None;
+                    this.fut.set(Some(fut));
+                }
+                continue;
+            }
+
+            // response hooks
+            if let Some(res) = this.res.take() {
+                let hooks = this.hooks.as_ref().unwrap();
+                if *this.hook_idx < hooks.on_response.len() {
+                    *this.hook_fut = Some((hooks.on_response[*this.hook_idx])(
+                        res.request(),
+                        res.response().head(),
+                    ));
+                    *this.hook_idx += 1;
+                    *this.res = Some(res);
+                    continue;
+                }
+                return Poll::Ready(Ok(res));
+            }
+
+            let res = match this.fut.as_mut().as_pin_mut().unwrap().poll(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.fut.set(None);
+
+            let res = match res {
+                Ok(res) => {
+                    let rendered = match (this.renderer.as_ref(), res.response().error())
+                    {
+                        (Some(renderer), Some(err)) => {
+                            Some(renderer(err, res.request()))
+                        }
+                        _ => None,
+                    };
+                    match rendered {
+                        Some(rendered) => res.into_response(rendered.into_body()),
+                        None => res,
+                    }
+                }
+                Err(err) => match this.retained.as_ref().and_then(|r| r.take()) {
+                    Some(req) => {
+                        let rendered = match this.renderer {
+                            Some(renderer) => renderer(&err, &req),
+                            None => err.into(),
+                        };
+                        ServiceResponse::new(req, rendered.into_body())
+                    }
+                    None => return Poll::Ready(Err(err)),
+                },
+            };
+
+            match this.hooks {
+                Some(ref hooks) if !hooks.on_response.is_empty() => {
+                    *this.res = Some(res)
+                }
+                _ => return Poll::Ready(Ok(res)),
+            }
+        }
     }
 }
 
@@ -402,6 +606,7 @@ impl Service for AppRouting {
             if let Some(ref guards) = guards {
                 for f in guards {
                     if !f.check(req.head()) {
+                        req.extensions_mut().insert(PathMatched);
                         return false;
                     }
                 }
@@ -409,7 +614,7 @@ impl Service for AppRouting {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(srv) = res {
             srv.call(req)
         } else if let Some(ref mut default) = self.default {
             default.call(req)
@@ -445,6 +650,63 @@ impl ServiceFactory for AppEntry {
     }
 }
 
+/// Request path matched a resource, but resource guards did not.
+pub(crate) struct PathMatched;
+
+/// Default service that calls `not_found` service if request path does not
+/// match any resource.
+struct NotFoundFactory {
+    default: Rc<HttpNewService>,
+    not_found: Rc<HttpNewService>,
+}
+
+impl ServiceFactory for NotFoundFactory {
+    type Config = ();
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Service = NotFoundService;
+    type Future = LocalBoxFuture<'static, Result<NotFoundService, ()>>;
+
+    fn new_service(&self, _: &()) -> Self::Future {
+        let default = self.default.new_service(&());
+        let not_found = self.not_found.new_service(&());
+
+        async move {
+            Ok(NotFoundService {
+                default: default.await?,
+                not_found: not_found.await?,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+struct NotFoundService {
+    default: HttpService,
+    not_found: HttpService,
+}
+
+impl Service for NotFoundService {
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = BoxedResponse;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if req.extensions().contains::<PathMatched>() {
+            self.default.call(req)
+        } else {
+            self.not_found.call(req)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::Service;
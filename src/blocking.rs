@@ -0,0 +1,241 @@
+//! Thread pool for blocking operations, see `web::block()`.
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::{env, thread};
+
+use futures::channel::oneshot;
+use futures::Future;
+use parking_lot::Mutex;
+
+use crate::error::BlockingError;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool configuration, applied when pool is started.
+struct Config {
+    threads: usize,
+    max_queued: Option<usize>,
+    started: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIG: Mutex<Config> = Mutex::new(Config {
+        threads: default_threads(),
+        max_queued: None,
+        started: false,
+    });
+    static ref POOL: Pool = {
+        let mut cfg = CONFIG.lock();
+        cfg.started = true;
+        Pool::start(&cfg)
+    };
+}
+
+/// Number of threads is read from `ACTIX_THREADPOOL` env variable, five
+/// threads per cpu otherwise.
+fn default_threads() -> usize {
+    env::var("ACTIX_THREADPOOL")
+        .ok()
+        .and_then(|val| val.parse().ok())
+        .unwrap_or_else(|| num_cpus::get() * 5)
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    active: AtomicUsize,
+}
+
+/// Decrements number of active functions, even if function panics.
+struct ActiveGuard<'a>(&'a Counters);
+
+impl<'a> Drop for ActiveGuard<'a> {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Pool {
+    tx: Mutex<mpsc::Sender<Job>>,
+    counters: Arc<Counters>,
+    threads: usize,
+    max_queued: Option<usize>,
+}
+
+impl Pool {
+    fn start(cfg: &Config) -> Pool {
+        let threads = std::cmp::max(cfg.threads, 1);
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let counters = Arc::new(Counters::default());
+
+        for idx in 0..threads {
+            let rx = rx.clone();
+            let counters = counters.clone();
+            thread::Builder::new()
+                .name(format!("actix-web-blocking-{}", idx))
+                .spawn(move || loop {
+                    let job = match rx.lock().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    counters.queued.fetch_sub(1, Ordering::SeqCst);
+                    counters.active.fetch_add(1, Ordering::SeqCst);
+                    let _guard = ActiveGuard(&counters);
+
+                    // panic of the function drops its result sender, so
+                    // caller gets `Canceled` error and thread keeps running
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                })
+                .expect("Can not start blocking thread");
+        }
+
+        Pool {
+            tx: Mutex::new(tx),
+            counters,
+            threads,
+            max_queued: cfg.max_queued,
+        }
+    }
+
+    fn execute(&self, job: Job) -> Result<(), BlockingError> {
+        let queued = self.counters.queued.fetch_add(1, Ordering::SeqCst);
+        if let Some(max) = self.max_queued {
+            if queued >= max {
+                self.counters.queued.fetch_sub(1, Ordering::SeqCst);
+                return Err(BlockingError::Saturated);
+            }
+        }
+        self.tx.lock().send(job).map_err(|_| {
+            self.counters.queued.fetch_sub(1, Ordering::SeqCst);
+            BlockingError::Canceled
+        })
+    }
+}
+
+/// Configuration could not be changed once pool is started.
+fn configure<F: FnOnce(&mut Config)>(f: F) {
+    let mut cfg = CONFIG.lock();
+    if cfg.started {
+        panic!("Blocking thread pool has to be configured before first use");
+    }
+    f(&mut cfg)
+}
+
+/// Set number of threads of the pool.
+pub(crate) fn set_threads(threads: usize) {
+    configure(|cfg| cfg.threads = threads)
+}
+
+/// Set max number of functions waiting for a free thread.
+pub(crate) fn set_max_queued(max: usize) {
+    configure(|cfg| cfg.max_queued = Some(max))
+}
+
+/// Execute function on the pool.
+pub(crate) fn run<F, R>(f: F) -> impl Future<Output = Result<R, BlockingError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let res = POOL.execute(Box::new(move || {
+        let _ = tx.send(f());
+    }));
+
+    async move {
+        res?;
+        rx.await.map_err(|_| BlockingError::Canceled)
+    }
+}
+
+/// Metrics of the blocking thread pool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockingMetrics {
+    /// Number of threads in the pool
+    pub threads: usize,
+    /// Number of functions that are being executed
+    pub active: usize,
+    /// Number of functions waiting for a free thread
+    pub queued: usize,
+    /// Max number of waiting functions, `None` if queue is not limited
+    pub max_queued: Option<usize>,
+}
+
+/// Current metrics of the pool.
+pub(crate) fn metrics() -> BlockingMetrics {
+    BlockingMetrics {
+        threads: POOL.threads,
+        active: POOL.counters.active.load(Ordering::SeqCst),
+        queued: POOL.counters.queued.load(Ordering::SeqCst),
+        max_queued: POOL.max_queued,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::block_on;
+
+    #[test]
+    fn test_saturated() {
+        block_on(async {
+            let pool = Pool::start(&Config {
+                threads: 1,
+                max_queued: Some(1),
+                started: false,
+            });
+            let (tx, rx) = mpsc::channel::<()>();
+            let (started_tx, started_rx) = mpsc::channel::<()>();
+
+            // occupy the only thread
+            pool.execute(Box::new(move || {
+                let _ = started_tx.send(());
+                let _ = rx.recv();
+            }))
+            .unwrap();
+            started_rx.recv().unwrap();
+            assert_eq!(pool.counters.active.load(Ordering::SeqCst), 1);
+
+            pool.execute(Box::new(|| ())).unwrap();
+            match pool.execute(Box::new(|| ())) {
+                Err(BlockingError::Saturated) => (),
+                _ => panic!("Pool should be saturated"),
+            }
+            assert_eq!(pool.counters.queued.load(Ordering::SeqCst), 1);
+            let _ = tx.send(());
+        })
+    }
+
+    #[test]
+    fn test_panic() {
+        block_on(async {
+            let pool = Pool::start(&Config {
+                threads: 1,
+                max_queued: None,
+                started: false,
+            });
+            pool.execute(Box::new(|| panic!("job panic"))).unwrap();
+
+            // thread survives the panic
+            let (tx, rx) = oneshot::channel();
+            pool.execute(Box::new(move || {
+                let _ = tx.send(2 + 2);
+            }))
+            .unwrap();
+            assert_eq!(rx.await.unwrap(), 4);
+            assert_eq!(pool.counters.active.load(Ordering::SeqCst), 0);
+        })
+    }
+
+    #[test]
+    fn test_run() {
+        block_on(async {
+            assert_eq!(run(|| 2 + 2).await.unwrap(), 4);
+            assert!(metrics().threads > 0);
+            assert!(panic::catch_unwind(|| set_threads(1)).is_err());
+        })
+    }
+}
@@ -0,0 +1,386 @@
+//! Compatibility with [tower](https://github.com/tower-rs/tower) services
+//! and layers.
+//!
+//! Requires `tower` feature.
+//!
+//! * `TowerLayer` - tower `Layer` as actix-web middleware
+//! * `TowerService` - actix service as tower `Service`
+//! * `ActixService` - tower `Service` as actix service
+//!
+//! Tower middlewares that require `Send` services or requests, i.e.
+//! `Buffer`, could not be used, actix services are not `Send`.
+use std::convert::Infallible;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use tower_layer::Layer;
+
+use crate::error::{Error, ErrorInternalServerError};
+use crate::service::{ServiceRequest, ServiceResponse};
+
+/// Error type of tower middlewares
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Tower `Layer` as actix-web middleware.
+///
+/// Layer wraps `LayerService`, a tower service which responds with result
+/// of inner actix service, so middleware has to be generic over response
+/// type, as most of tower middlewares are. Errors of the layer are
+/// rendered as *INTERNAL SERVER ERROR* response, use
+/// `TowerLayer::map_err()` for custom errors.
+///
+/// ```rust,ignore
+/// use std::time::Duration;
+/// use actix_web::compat::TowerLayer;
+/// use actix_web::{error, web, App, HttpResponse};
+/// use tower_timeout::TimeoutLayer;
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             TowerLayer::new(TimeoutLayer::new(Duration::from_secs(5)))
+///                 .map_err(|e| error::ErrorGatewayTimeout(e)),
+///         )
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+pub struct TowerLayer<L> {
+    layer: L,
+    map_err: Rc<dyn Fn(BoxError) -> Error>,
+}
+
+impl<L> TowerLayer<L> {
+    /// Create middleware from the layer.
+    pub fn new(layer: L) -> Self {
+        TowerLayer {
+            layer,
+            map_err: Rc::new(ErrorInternalServerError::<BoxError>),
+        }
+    }
+
+    /// Set function that converts errors of the layer.
+    pub fn map_err<F>(mut self, f: F) -> Self
+    where
+        F: Fn(BoxError) -> Error + 'static,
+    {
+        self.map_err = Rc::new(f);
+        self
+    }
+}
+
+impl<S, B, L> Transform<S> for TowerLayer<L>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    L: Layer<LayerService<S>>,
+    L::Service: tower_service::Service<
+        ServiceRequest,
+        Response = Result<ServiceResponse<B>, Error>,
+    >,
+    <L::Service as tower_service::Service<ServiceRequest>>::Error: Into<BoxError>,
+    <L::Service as tower_service::Service<ServiceRequest>>::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TowerMiddleware<L::Service>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TowerMiddleware {
+            service: self.layer.layer(LayerService { service, err: None }),
+            map_err: self.map_err.clone(),
+        })
+    }
+}
+
+/// Actix-web service wrapped by `TowerLayer`.
+///
+/// Service never fails, result of inner service is passed through the
+/// layer as response.
+pub struct LayerService<S> {
+    service: S,
+    err: Option<Error>,
+}
+
+impl<S, B> tower_service::Service<ServiceRequest> for LayerService<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = Result<ServiceResponse<B>, Error>;
+    type Error = Infallible;
+    type Future = ResultFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Infallible>> {
+        if self.err.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        match self.service.poll_ready(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
+            // error is returned as result of next call
+            Poll::Ready(Err(e)) => {
+                self.err = Some(e);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        match self.err.take() {
+            Some(e) => ResultFuture {
+                fut: None,
+                err: Some(e),
+            },
+            None => ResultFuture {
+                fut: Some(self.service.call(req)),
+                err: None,
+            },
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct ResultFuture<F> {
+    #[pin]
+    fut: Option<F>,
+    err: Option<Error>,
+}
+
+impl<F, T> Future for ResultFuture<F>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    type Output = Result<Result<T, Error>, Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(e) = this.err.take() {
+            return Poll::Ready(Ok(Err(e)));
+        }
+        this.fut.as_pin_mut().unwrap().poll(cx).map(Ok)
+    }
+}
+
+pub struct TowerMiddleware<T> {
+    service: T,
+    map_err: Rc<dyn Fn(BoxError) -> Error>,
+}
+
+impl<T, B> Service for TowerMiddleware<T>
+where
+    T: tower_service::Service<
+        ServiceRequest,
+        Response = Result<ServiceResponse<B>, Error>,
+    >,
+    T::Error: Into<BoxError>,
+    T::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let map_err = &self.map_err;
+        self.service.poll_ready(cx).map_err(|e| map_err(e.into()))
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let map_err = self.map_err.clone();
+        let fut = self.service.call(req);
+
+        async move {
+            match fut.await {
+                Ok(res) => res,
+                Err(e) => Err(map_err(e.into())),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Actix service as tower `Service`.
+pub struct TowerService<S>(S);
+
+impl<S> TowerService<S> {
+    /// Wrap actix service.
+    pub fn new(service: S) -> Self {
+        TowerService(service)
+    }
+
+    /// Unwrap actix service.
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S: Service> tower_service::Service<S::Request> for TowerService<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.0.call(req)
+    }
+}
+
+/// Tower `Service` as actix service.
+pub struct ActixService<T, R> {
+    service: T,
+    _t: PhantomData<R>,
+}
+
+impl<T, R> ActixService<T, R>
+where
+    T: tower_service::Service<R>,
+{
+    /// Wrap tower service.
+    pub fn new(service: T) -> Self {
+        ActixService {
+            service,
+            _t: PhantomData,
+        }
+    }
+
+    /// Unwrap tower service.
+    pub fn into_inner(self) -> T {
+        self.service
+    }
+}
+
+impl<T, R> Service for ActixService<T, R>
+where
+    T: tower_service::Service<R>,
+{
+    type Request = R;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, Either};
+
+    use super::*;
+    use crate::error::ErrorForbidden;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    /// Layer that rejects requests without the header
+    struct RejectLayer(&'static str);
+
+    struct Reject<S>(S, &'static str);
+
+    impl<S> Layer<S> for RejectLayer {
+        type Service = Reject<S>;
+
+        fn layer(&self, inner: S) -> Reject<S> {
+            Reject(inner, self.0)
+        }
+    }
+
+    impl<S> tower_service::Service<ServiceRequest> for Reject<S>
+    where
+        S: tower_service::Service<ServiceRequest>,
+        S::Error: Into<BoxError>,
+        S::Future: 'static,
+    {
+        type Response = S::Response;
+        type Error = BoxError;
+        type Future = Either<
+            LocalBoxFuture<'static, Result<S::Response, BoxError>>,
+            Ready<Result<S::Response, BoxError>>,
+        >;
+
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), BoxError>> {
+            self.0.poll_ready(cx).map_err(Into::into)
+        }
+
+        fn call(&mut self, req: ServiceRequest) -> Self::Future {
+            if req.headers().contains_key(self.1) {
+                let fut = self.0.call(req);
+                Either::Left(async move { fut.await.map_err(Into::into) }.boxed_local())
+            } else {
+                Either::Right(err("rejected".into()))
+            }
+        }
+    }
+
+    #[test]
+    fn test_tower_layer() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(TowerLayer::new(RejectLayer("x-allow")))
+                    .route("/", web::get().to(|| HttpResponse::Ok()))
+                    .service(
+                        web::scope("/admin")
+                            .wrap(
+                                TowerLayer::new(RejectLayer("x-admin"))
+                                    .map_err(|e| ErrorForbidden(e)),
+                            )
+                            .route("", web::get().to(|| HttpResponse::Ok())),
+                    ),
+            )
+            .await;
+
+            let req = TestRequest::with_header("x-allow", "1").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let req = TestRequest::default().to_request();
+            let res = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                res.as_response_error().error_response().status(),
+                StatusCode::INTERNAL_SERVER_ERROR
+            );
+
+            let req = TestRequest::with_uri("/admin")
+                .header("x-allow", "1")
+                .to_request();
+            let res = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                res.as_response_error().error_response().status(),
+                StatusCode::FORBIDDEN
+            );
+
+            let req = TestRequest::with_uri("/admin")
+                .header("x-allow", "1")
+                .header("x-admin", "1")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        })
+    }
+
+    #[test]
+    fn test_service_conversions() {
+        block_on(async {
+            let srv = actix_service::service_fn(|n: u32| ok::<_, ()>(n * 2));
+            let mut srv = ActixService::new(TowerService::new(srv));
+            assert_eq!(srv.call(2).await, Ok(4));
+        })
+    }
+}
@@ -1,13 +1,15 @@
 use std::net::SocketAddr;
 use std::rc::Rc;
 
-use actix_http::Extensions;
+use actix_http::{Extensions, PayloadDrain};
 use actix_router::ResourceDef;
 use actix_service::{boxed, IntoServiceFactory, ServiceFactory};
 
 use crate::data::{Data, DataFactory};
 use crate::error::Error;
 use crate::guard::Guard;
+use crate::info::ForwardedConfig;
+use crate::metrics::RouteMetrics;
 use crate::resource::Resource;
 use crate::rmap::ResourceMap;
 use crate::route::Route;
@@ -24,6 +26,7 @@ type HttpNewService =
 pub struct AppService {
     config: AppConfig,
     root: bool,
+    prefix: String,
     default: Rc<HttpNewService>,
     services: Vec<(
         ResourceDef,
@@ -46,6 +49,7 @@ impl AppService {
             default,
             service_data,
             root: true,
+            prefix: String::new(),
             services: Vec::new(),
         }
     }
@@ -75,10 +79,29 @@ impl AppService {
             default: self.default.clone(),
             services: Vec::new(),
             root: false,
+            prefix: self.prefix.clone(),
             service_data: self.service_data.clone(),
         }
     }
 
+    /// Clone config without application data, services registered with
+    /// it do not inherit application data.
+    pub(crate) fn clone_isolated_config(&self) -> Self {
+        AppService {
+            service_data: Rc::new(Vec::new()),
+            ..self.clone_config()
+        }
+    }
+
+    /// Pattern of the scope that is being configured
+    pub(crate) fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub(crate) fn set_prefix(&mut self, prefix: String) {
+        self.prefix = prefix;
+    }
+
     /// Service configuration
     pub fn config(&self) -> &AppConfig {
         &self.config
@@ -92,7 +115,7 @@ impl AppService {
     /// Set global route data
     pub fn set_service_data(&self, extensions: &mut Extensions) -> bool {
         for f in self.service_data.iter() {
-            f.create(extensions);
+            f.inherit(extensions);
         }
         !self.service_data.is_empty()
     }
@@ -151,12 +174,42 @@ impl AppConfig {
     pub fn local_addr(&self) -> SocketAddr {
         self.0.addr
     }
+
+    /// Returns true if detailed error responses are enabled with
+    /// `App::debug_errors()`
+    pub fn debug_errors(&self) -> bool {
+        self.0.debug_errors
+    }
+
+    /// Configuration of forwarding headers, see `App::forwarded()`
+    pub fn forwarded(&self) -> &ForwardedConfig {
+        &self.0.forwarded
+    }
+
+    /// Policy for unread request payload, see `App::drain_payload()`
+    pub fn payload_drain(&self) -> PayloadDrain {
+        self.0.payload_drain
+    }
+
+    pub(crate) fn route_metrics(&self) -> Option<&RouteMetrics> {
+        self.0.route_metrics.as_ref()
+    }
+
+    /// Capacity of `HttpRequest` pool, see `App::request_pool()`
+    pub fn request_pool(&self) -> usize {
+        self.0.request_pool
+    }
 }
 
 pub(crate) struct AppConfigInner {
     pub(crate) secure: bool,
     pub(crate) host: String,
     pub(crate) addr: SocketAddr,
+    pub(crate) debug_errors: bool,
+    pub(crate) forwarded: ForwardedConfig,
+    pub(crate) payload_drain: PayloadDrain,
+    pub(crate) route_metrics: Option<RouteMetrics>,
+    pub(crate) request_pool: usize,
 }
 
 impl Default for AppConfigInner {
@@ -165,6 +218,11 @@ impl Default for AppConfigInner {
             secure: false,
             addr: "127.0.0.1:8080".parse().unwrap(),
             host: "localhost:8080".to_owned(),
+            debug_errors: false,
+            forwarded: ForwardedConfig::default(),
+            payload_drain: PayloadDrain::default(),
+            route_metrics: None,
+            request_pool: 128,
         }
     }
 }
@@ -1,8 +1,13 @@
+use std::any::type_name;
+use std::fmt;
+use std::marker::PhantomData;
 use std::ops::Deref;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use actix_http::error::{Error, ErrorInternalServerError};
 use actix_http::Extensions;
+use arc_swap::ArcSwap;
 use futures::future::{err, ok, Ready};
 
 use crate::dev::Payload;
@@ -12,6 +17,9 @@ use crate::request::HttpRequest;
 /// Application data factory
 pub(crate) trait DataFactory {
     fn create(&self, extensions: &mut Extensions) -> bool;
+
+    /// Insert data that is inherited from the application.
+    fn inherit(&self, extensions: &mut Extensions) -> bool;
 }
 
 /// Application data.
@@ -34,7 +42,9 @@ pub(crate) trait DataFactory {
 /// use `web::Data::new()` and avoid double `Arc`.
 ///
 /// If route data is not set for a handler, using `Data<T>` extractor would
-/// cause *Internal Server Error* response.
+/// cause *Internal Server Error* response. Type of missing data is reported
+/// in the response if `App::debug_errors()` is enabled. Use `OptionalData<T>`
+/// extractor for data that may be missing.
 ///
 /// ```rust
 /// use std::sync::Mutex;
@@ -85,6 +95,26 @@ impl<T> Data<T> {
     }
 }
 
+impl<T: Default + 'static> Data<T> {
+    /// Get data configured for the request or default value of `T`.
+    ///
+    /// Default value is created on every call, it is not stored in
+    /// application data.
+    ///
+    /// ```rust
+    /// use actix_web::{web, HttpRequest};
+    ///
+    /// async fn index(req: HttpRequest) -> String {
+    ///     let greeting = web::Data::<String>::get_or_default(&req);
+    ///     format!("{}!", greeting)
+    /// }
+    /// ```
+    pub fn get_or_default(req: &HttpRequest) -> Data<T> {
+        req.get_app_data::<T>()
+            .unwrap_or_else(|| Data::new(T::default()))
+    }
+}
+
 impl<T> Deref for Data<T> {
     type Target = T;
 
@@ -110,8 +140,166 @@ impl<T: 'static> FromRequest for Data<T> {
             ok(st)
         } else {
             log::debug!(
-                "Failed to construct App-level Data extractor. \
+                "Failed to construct App-level Data extractor for type {}. \
+                 Request path: {:?}",
+                type_name::<T>(),
+                req.path()
+            );
+            if req.app_config().debug_errors() {
+                err(ErrorInternalServerError(format!(
+                    "App data of type `{}` is not configured, to configure use \
+                     App::data() or App::register_data()",
+                    type_name::<T>()
+                )))
+            } else {
+                err(ErrorInternalServerError(
+                    "App data is not configured, to configure use App::data()",
+                ))
+            }
+        }
+    }
+}
+
+/// Optional application data.
+///
+/// Unlike `Data<T>` extractor it does not fail if data of type `T` is not
+/// configured, contains `None` instead.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// struct Cache;
+///
+/// async fn index(cache: web::OptionalData<Cache>) -> &'static str {
+///     if cache.is_some() {
+///         "cached"
+///     } else {
+///         "not cached"
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/index.html", web::get().to(index));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct OptionalData<T>(Option<Data<T>>);
+
+impl<T> OptionalData<T> {
+    /// Unwrap into `Option<Data<T>>`
+    pub fn into_inner(self) -> Option<Data<T>> {
+        self.0
+    }
+}
+
+impl<T> Deref for OptionalData<T> {
+    type Target = Option<Data<T>>;
+
+    fn deref(&self) -> &Option<Data<T>> {
+        &self.0
+    }
+}
+
+impl<T> Clone for OptionalData<T> {
+    fn clone(&self) -> OptionalData<T> {
+        OptionalData(self.0.clone())
+    }
+}
+
+impl<T: 'static> FromRequest for OptionalData<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(OptionalData(req.get_app_data::<T>()))
+    }
+}
+
+/// Application data that could be replaced at runtime.
+///
+/// Contents are replaced atomically with `store()`, i.e. from a background
+/// task that reloads configuration. Handlers get a consistent snapshot with
+/// `load()`, reads do not take locks. Clones of `SwapData` share contents,
+/// so it should be constructed outside of the application factory and
+/// registered with `App::data()`.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpServer};
+///
+/// struct Config {
+///     greeting: String,
+/// }
+///
+/// async fn index(config: web::SwapData<Config>) -> String {
+///     config.load().greeting.clone()
+/// }
+///
+/// fn main() {
+///     let config = web::SwapData::new(Config { greeting: "Hello".to_owned() });
+///     let server_config = config.clone();
+///
+///     let server = HttpServer::new(move || {
+///         App::new()
+///             .data(server_config.clone())
+///             .route("/", web::get().to(index))
+///     });
+///
+///     // later, i.e. on configuration reload
+///     config.store(Config { greeting: "Hi".to_owned() });
+/// }
+/// ```
+pub struct SwapData<T>(Arc<ArcSwap<T>>);
+
+impl<T> SwapData<T> {
+    /// Create new `SwapData` instance.
+    pub fn new(state: T) -> SwapData<T> {
+        SwapData(Arc::new(ArcSwap::from(Arc::new(state))))
+    }
+
+    /// Snapshot of current contents.
+    pub fn load(&self) -> Arc<T> {
+        self.0.load_full()
+    }
+
+    /// Replace contents.
+    pub fn store(&self, state: T) {
+        self.0.store(Arc::new(state))
+    }
+
+    /// Replace contents and return previous ones.
+    pub fn swap(&self, state: T) -> Arc<T> {
+        self.0.swap(Arc::new(state))
+    }
+}
+
+impl<T> Clone for SwapData<T> {
+    fn clone(&self) -> SwapData<T> {
+        SwapData(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SwapData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SwapData").field(&self.load()).finish()
+    }
+}
+
+impl<T: 'static> FromRequest for SwapData<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(st) = req.app_data::<SwapData<T>>() {
+            ok(st.clone())
+        } else {
+            log::debug!(
+                "Failed to construct App-level SwapData extractor for type {}. \
                  Request path: {:?}",
+                type_name::<T>(),
                 req.path()
             );
             err(ErrorInternalServerError(
@@ -121,6 +309,72 @@ impl<T: 'static> FromRequest for Data<T> {
     }
 }
 
+/// Worker data.
+///
+/// Worker data is constructed on each worker thread by the factory
+/// registered with `App::worker_data()`. Internally it uses `Rc`, so data
+/// does not need to be `Send` or `Sync` and extraction does not require
+/// synchronization.
+///
+/// If worker data is not set for a handler, using `WorkerData<T>` extractor
+/// would cause *Internal Server Error* response.
+#[derive(Debug)]
+pub struct WorkerData<T>(Rc<T>);
+
+impl<T> WorkerData<T> {
+    /// Create new `WorkerData` instance.
+    pub fn new(state: T) -> WorkerData<T> {
+        WorkerData(Rc::new(state))
+    }
+
+    /// Get reference to inner worker data.
+    pub fn get_ref(&self) -> &T {
+        self.0.as_ref()
+    }
+
+    /// Convert to the internal Rc<T>
+    pub fn into_inner(self) -> Rc<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for WorkerData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref()
+    }
+}
+
+impl<T> Clone for WorkerData<T> {
+    fn clone(&self) -> WorkerData<T> {
+        WorkerData(self.0.clone())
+    }
+}
+
+impl<T: 'static> FromRequest for WorkerData<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(st) = req.app_data::<WorkerData<T>>() {
+            ok(st.clone())
+        } else {
+            log::debug!(
+                "Failed to construct WorkerData extractor for type {}. \
+                 Request path: {:?}",
+                type_name::<T>(),
+                req.path()
+            );
+            err(ErrorInternalServerError(
+                "Worker data is not configured, to configure use App::worker_data()",
+            ))
+        }
+    }
+}
+
 impl<T: 'static> DataFactory for Data<T> {
     fn create(&self, extensions: &mut Extensions) -> bool {
         if !extensions.contains::<Data<T>>() {
@@ -130,15 +384,41 @@ impl<T: 'static> DataFactory for Data<T> {
             false
         }
     }
+
+    fn inherit(&self, extensions: &mut Extensions) -> bool {
+        if self.create(extensions) {
+            extensions.insert(InheritedData::<T>(PhantomData));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Marker of application data that is inherited by scope or resource.
+pub(crate) struct InheritedData<T>(PhantomData<T>);
+
+/// Origin of application data, see `HttpRequest::app_data_scope()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataScope {
+    /// Data is set by the scope or resource that handles request, or it is
+    /// application data and request is not handled by a scope or resource
+    /// with its own data.
+    Local,
+    /// Application data that is inherited by the scope or resource that
+    /// handles request.
+    Inherited,
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+
     use actix_service::Service;
 
     use super::*;
     use crate::http::StatusCode;
-    use crate::test::{block_on, init_service, TestRequest};
+    use crate::test::{block_on, init_service, read_body, TestRequest};
     use crate::{web, App, HttpResponse};
 
     #[test]
@@ -238,4 +518,116 @@ mod tests {
             assert_eq!(resp.status(), StatusCode::OK);
         })
     }
+
+    #[test]
+    fn test_missing_data_diagnostics() {
+        block_on(async {
+            let mut srv = init_service(App::new().service(
+                web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()),
+            ))
+            .await;
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let body = read_body(resp).await;
+            assert!(!std::str::from_utf8(&body).unwrap().contains("usize"));
+
+            let mut srv = init_service(App::new().debug_errors(true).service(
+                web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()),
+            ))
+            .await;
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let body = read_body(resp).await;
+            assert!(std::str::from_utf8(&body)
+                .unwrap()
+                .starts_with("App data of type `usize` is not configured"));
+        })
+    }
+
+    #[test]
+    fn test_get_or_default() {
+        let req = TestRequest::default().data(10usize).to_http_request();
+        assert_eq!(*Data::<usize>::get_or_default(&req), 10);
+        assert_eq!(*Data::<u32>::get_or_default(&req), 0);
+    }
+
+    #[test]
+    fn test_optional_data_extractor() {
+        block_on(async {
+            let mut srv =
+                init_service(App::new().data(10usize).service(web::resource("/").to(
+                    |num: web::OptionalData<usize>, other: web::OptionalData<u32>| {
+                        assert_eq!(num.as_ref().map(|num| **num), Some(10));
+                        assert!(other.is_none());
+                        HttpResponse::Ok()
+                    },
+                )))
+                .await;
+
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    }
+
+    #[test]
+    fn test_swap_data_extractor() {
+        block_on(async {
+            let data = SwapData::new(1usize);
+            let mut srv = init_service(App::new().data(data.clone()).service(
+                web::resource("/").to(|data: web::SwapData<usize>| {
+                    HttpResponse::Ok().body(format!("{}", data.load()))
+                }),
+            ))
+            .await;
+
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(read_body(resp).await, "1");
+
+            assert_eq!(*data.swap(2), 1);
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(read_body(resp).await, "2");
+
+            let mut srv = init_service(App::new().service(
+                web::resource("/").to(|_: web::SwapData<usize>| HttpResponse::Ok()),
+            ))
+            .await;
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        })
+    }
+
+    #[test]
+    fn test_worker_data_extractor() {
+        block_on(async {
+            let mut srv =
+                init_service(App::new().worker_data(|| Cell::new(0usize)).service(
+                    web::resource("/").to(|data: web::WorkerData<Cell<usize>>| {
+                        data.set(data.get() + 1);
+                        HttpResponse::Ok().body(format!("{}", data.get()))
+                    }),
+                ))
+                .await;
+
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(read_body(resp).await, "1");
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(read_body(resp).await, "2");
+
+            let mut srv = init_service(App::new().service(
+                web::resource("/").to(|_: web::WorkerData<usize>| HttpResponse::Ok()),
+            ))
+            .await;
+            let req = TestRequest::default().to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        })
+    }
 }
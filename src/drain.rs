@@ -0,0 +1,316 @@
+//! Connection draining
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_http::http::ConnectionType;
+use actix_http::{Request, Response};
+use actix_server_config::ServerConfig;
+use actix_service::{IntoServiceFactory, Service, ServiceFactory};
+use parking_lot::Mutex;
+
+use crate::health::Readiness;
+
+/// Handle for draining of the running server.
+///
+/// In drain mode keep-alive is stopped, responses are sent with
+/// `Connection: close` header, HTTP/2 connections receive `GOAWAY` frame,
+/// and readiness of the server is flipped off (see
+/// `HttpServer::readiness()`). Server continues to accept connections, so
+/// the node could be drained before shutdown.
+///
+/// Requests are tracked only by servers with the handle set with
+/// `HttpServer::drain_handle()`.
+///
+/// ```rust
+/// use actix_web::dev::DrainHandle;
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+///
+/// async fn drain(handle: web::Data<DrainHandle>) -> HttpResponse {
+///     handle.drain();
+///     HttpResponse::Ok().body(format!("in-flight: {}", handle.in_flight()))
+/// }
+///
+/// fn main() {
+///     let handle = DrainHandle::new();
+///     let drain_handle = handle.clone();
+///
+///     let server = HttpServer::new(move || {
+///         App::new()
+///             .data(drain_handle.clone())
+///             .route("/admin/drain", web::post().to(drain))
+///     })
+///     .drain_handle(handle);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DrainHandle(Arc<DrainInner>);
+
+struct DrainInner {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    readiness: Mutex<Option<Readiness>>,
+}
+
+impl DrainHandle {
+    /// Create new handle.
+    pub fn new() -> DrainHandle {
+        DrainHandle(Arc::new(DrainInner {
+            draining: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            readiness: Mutex::new(None),
+        }))
+    }
+
+    /// Enter drain mode.
+    pub fn drain(&self) {
+        self.0.draining.store(true, Ordering::Release);
+        if let Some(ref readiness) = *self.0.readiness.lock() {
+            readiness.set_ready(false);
+        }
+    }
+
+    /// Leave drain mode.
+    pub fn resume(&self) {
+        self.0.draining.store(false, Ordering::Release);
+        if let Some(ref readiness) = *self.0.readiness.lock() {
+            readiness.set_ready(true);
+        }
+    }
+
+    /// Check if server is in drain mode.
+    pub fn is_draining(&self) -> bool {
+        self.0.draining.load(Ordering::Acquire)
+    }
+
+    /// Number of requests that are being processed by all workers.
+    ///
+    /// Request is counted until its response is ready, streaming of the
+    /// response body is not included.
+    pub fn in_flight(&self) -> usize {
+        self.0.in_flight.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_readiness(&self, readiness: Readiness) {
+        *self.0.readiness.lock() = Some(readiness);
+    }
+}
+
+impl Default for DrainHandle {
+    fn default() -> Self {
+        DrainHandle::new()
+    }
+}
+
+/// Service factory that tracks requests of the drain handle.
+///
+/// Service without handle passes requests through.
+pub(crate) struct DrainFactory<S, B> {
+    factory: S,
+    handle: Option<DrainHandle>,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> DrainFactory<S, B>
+where
+    S: ServiceFactory<Config = ServerConfig, Request = Request>,
+    S::Response: Into<Response<B>>,
+{
+    pub(crate) fn new<I: IntoServiceFactory<S>>(
+        factory: I,
+        handle: Option<DrainHandle>,
+    ) -> Self {
+        DrainFactory {
+            factory: factory.into_factory(),
+            handle,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, B> ServiceFactory for DrainFactory<S, B>
+where
+    S: ServiceFactory<Config = ServerConfig, Request = Request>,
+    S::Response: Into<Response<B>>,
+{
+    type Config = ServerConfig;
+    type Request = Request;
+    type Response = Response<B>;
+    type Error = S::Error;
+    type InitError = S::InitError;
+    type Service = DrainService<S::Service, B>;
+    type Future = DrainFactoryResponse<S::Future, B>;
+
+    fn new_service(&self, cfg: &ServerConfig) -> Self::Future {
+        DrainFactoryResponse {
+            fut: self.factory.new_service(cfg),
+            handle: self.handle.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct DrainFactoryResponse<F, B> {
+    #[pin]
+    fut: F,
+    handle: Option<DrainHandle>,
+    _t: PhantomData<B>,
+}
+
+impl<F, S, E, B> Future for DrainFactoryResponse<F, B>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<DrainService<S, B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(Ok(service)) => Poll::Ready(Ok(DrainService {
+                service,
+                handle: this.handle.take(),
+                _t: PhantomData,
+            })),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct DrainService<S, B> {
+    service: S,
+    handle: Option<DrainHandle>,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> Service for DrainService<S, B>
+where
+    S: Service<Request = Request>,
+    S::Response: Into<Response<B>>,
+{
+    type Request = Request;
+    type Response = Response<B>;
+    type Error = S::Error;
+    type Future = DrainServiceResponse<S::Future, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let guard = self.handle.as_ref().map(|handle| {
+            handle.0.in_flight.fetch_add(1, Ordering::AcqRel);
+            InFlight(handle.clone())
+        });
+        DrainServiceResponse {
+            fut: self.service.call(req),
+            guard,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Decrements in-flight counter, response future could be dropped
+/// before completion.
+struct InFlight(DrainHandle);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        (self.0).0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct DrainServiceResponse<F, B> {
+    #[pin]
+    fut: F,
+    guard: Option<InFlight>,
+    _t: PhantomData<B>,
+}
+
+impl<F, R, E, B> Future for DrainServiceResponse<F, B>
+where
+    F: Future<Output = Result<R, E>>,
+    R: Into<Response<B>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(Ok(res)) => {
+                let mut res: Response<B> = res.into();
+                if this.guard.as_ref().map_or(false, |g| g.0.is_draining()) {
+                    res.head_mut().set_connection_type(ConnectionType::Close);
+                }
+                Poll::Ready(Ok(res))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::Body;
+    use actix_service::service_fn;
+    use futures::future::ok;
+
+    use super::*;
+    use crate::test::block_on;
+
+    #[test]
+    fn test_drain() {
+        block_on(async {
+            let readiness = Readiness::new();
+            let handle = DrainHandle::new();
+            handle.set_readiness(readiness.clone());
+
+            let mut srv = DrainService::<_, Body> {
+                service: service_fn(|_: Request| ok::<_, ()>(Response::Ok().finish())),
+                handle: Some(handle.clone()),
+                _t: PhantomData,
+            };
+
+            let res = srv.call(Request::new()).await.unwrap();
+            assert_eq!(res.head().connection_type(), ConnectionType::KeepAlive);
+
+            let mut fut = srv.call(Request::new());
+            assert_eq!(handle.in_flight(), 1);
+
+            handle.drain();
+            assert!(handle.is_draining());
+            assert!(!readiness.is_ready());
+
+            let res = (&mut fut).await.unwrap();
+            assert_eq!(res.head().connection_type(), ConnectionType::Close);
+            drop(fut);
+            assert_eq!(handle.in_flight(), 0);
+
+            handle.resume();
+            assert!(!handle.is_draining());
+            assert!(readiness.is_ready());
+
+            // service without handle is not tracked
+            let mut srv = DrainService::<_, Body> {
+                service: service_fn(|_: Request| ok::<_, ()>(Response::Ok().finish())),
+                handle: None,
+                _t: PhantomData,
+            };
+            handle.drain();
+            let res = srv.call(Request::new()).await.unwrap();
+            assert_eq!(res.head().connection_type(), ConnectionType::KeepAlive);
+            assert_eq!(handle.in_flight(), 0);
+        })
+    }
+}
@@ -1,10 +1,11 @@
 //! Error and Result module
 pub use actix_http::error::*;
 use derive_more::{Display, From};
+use serde::Serialize;
 use serde_json::error::Error as JsonError;
 use url::ParseError as UrlParseError;
 
-use crate::http::StatusCode;
+use crate::http::{header, StatusCode};
 use crate::HttpResponse;
 use serde_urlencoded::de;
 
@@ -25,6 +26,129 @@ pub enum UrlGenerationError {
 /// `InternalServerError` for `UrlGeneratorError`
 impl ResponseError for UrlGenerationError {}
 
+/// Errors of blocking thread pool, see `web::block()`.
+#[derive(Debug, Display, PartialEq)]
+pub enum BlockingError {
+    /// Max number of queued functions is reached
+    #[display(fmt = "Blocking thread pool is saturated")]
+    Saturated,
+    /// Function panicked or thread pool is gone
+    #[display(fmt = "Blocking operation is canceled")]
+    Canceled,
+    /// Deadline of the request expired before function is executed
+    #[display(fmt = "Deadline of the request is exceeded")]
+    Timeout,
+}
+
+/// Return `ServiceUnavailable` for saturated pool, `GatewayTimeout` for
+/// expired deadline and `InternalServerError` otherwise
+impl ResponseError for BlockingError {
+    fn error_response(&self) -> HttpResponse {
+        match *self {
+            BlockingError::Saturated => {
+                HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE)
+            }
+            BlockingError::Canceled => {
+                HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            BlockingError::Timeout => HttpResponse::new(StatusCode::GATEWAY_TIMEOUT),
+        }
+    }
+}
+
+/// Deadline of the request is exceeded, see `web::Deadline`.
+#[derive(Debug, Display, PartialEq)]
+#[display(fmt = "Deadline of the request is exceeded")]
+pub struct DeadlineExceeded;
+
+/// Return `GatewayTimeout` for `DeadlineExceeded`
+impl ResponseError for DeadlineExceeded {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::new(StatusCode::GATEWAY_TIMEOUT)
+    }
+}
+
+/// Request payload exceeds limit of `middleware::BodyLimit`.
+#[derive(Debug, Display, PartialEq, Serialize)]
+#[display(
+    fmt = "Payload of {} bytes exceeds the limit of {} bytes",
+    length,
+    limit
+)]
+pub struct BodyLimitExceeded {
+    /// Length of the payload
+    pub length: u64,
+    /// Configured limit
+    pub limit: usize,
+}
+
+/// Return `PayloadTooLarge` with json body for `BodyLimitExceeded`
+impl ResponseError for BodyLimitExceeded {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::PayloadTooLarge().json(self)
+    }
+}
+
+/// Response body does not match its declared length or size limit, see
+/// `middleware::ResponseGuard`.
+#[derive(Debug, Display, PartialEq)]
+pub enum ResponseBodyError {
+    /// Body is longer than declared length or size limit
+    #[display(fmt = "Response body exceeds {} bytes", limit)]
+    Overflow { limit: u64 },
+    /// Body is shorter than declared length
+    #[display(
+        fmt = "Response body of {} bytes is shorter than declared {} bytes",
+        size,
+        declared
+    )]
+    Incomplete { size: u64, declared: u64 },
+}
+
+/// `InternalServerError` for `ResponseBodyError`
+impl ResponseError for ResponseBodyError {}
+
+/// Errors of `Content-Range` header of upload request, see
+/// `web::UploadRange`.
+#[derive(Debug, Display, PartialEq)]
+pub enum UploadRangeError {
+    /// Request does not contain `Content-Range` header
+    #[display(fmt = "Content-Range header is missing")]
+    Missing,
+    /// Header could not be parsed or unit is not `bytes`
+    #[display(fmt = "Content-Range header is invalid")]
+    Invalid,
+    /// Range is outside of complete length
+    #[display(
+        fmt = "Range {}-{} is not satisfiable for length {}",
+        first,
+        last,
+        length
+    )]
+    NotSatisfiable { first: u64, last: u64, length: u64 },
+    /// `Content-Length` of the request does not match the range
+    #[display(fmt = "Content-Length does not match Content-Range")]
+    LengthMismatch,
+    /// Payload is longer than the range
+    #[display(fmt = "Payload is longer than Content-Range")]
+    Overflow,
+}
+
+/// Return `RangeNotSatisfiable` for `NotSatisfiable` error, `BadRequest`
+/// otherwise.
+impl ResponseError for UploadRangeError {
+    fn error_response(&self) -> HttpResponse {
+        match *self {
+            UploadRangeError::NotSatisfiable { length, .. } => {
+                HttpResponse::build(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", length))
+                    .finish()
+            }
+            _ => HttpResponse::new(StatusCode::BAD_REQUEST),
+        }
+    }
+}
+
 /// A set of errors that can occur during parsing urlencoded payloads
 #[derive(Debug, Display, From)]
 pub enum UrlencodedError {
@@ -65,6 +189,19 @@ impl ResponseError for UrlencodedError {
             _ => HttpResponse::new(StatusCode::BAD_REQUEST),
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UrlencodedError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for UrlencodedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 /// A set of errors that can occur during parsing json payloads
@@ -94,6 +231,20 @@ impl ResponseError for JsonPayloadError {
             _ => HttpResponse::new(StatusCode::BAD_REQUEST),
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonPayloadError::Deserialize(e) => Some(e),
+            JsonPayloadError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for JsonPayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 /// A set of errors that can occur during parsing request paths
@@ -111,6 +262,18 @@ impl ResponseError for PathError {
             PathError::Deserialize(_) => HttpResponse::new(StatusCode::BAD_REQUEST),
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PathError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
+impl std::error::Error for PathError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 /// A set of errors that can occur during parsing query strings
@@ -130,6 +293,18 @@ impl ResponseError for QueryPayloadError {
             }
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QueryPayloadError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
+impl std::error::Error for QueryPayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 /// Error type returned when reading body as lines.
@@ -160,6 +335,64 @@ impl ResponseError for ReadlinesError {
             _ => HttpResponse::new(StatusCode::BAD_REQUEST),
         }
     }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadlinesError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for ReadlinesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
+}
+
+/// Error of `Json`, `Path`, `Query` or `Form` extractor, it is passed to
+/// the error handler of `ExtractorConfig`.
+#[derive(Debug, Display, From)]
+pub enum ExtractorError {
+    /// Json extractor error
+    #[display(fmt = "{}", _0)]
+    Json(JsonPayloadError),
+    /// Path extractor error
+    #[display(fmt = "{}", _0)]
+    Path(PathError),
+    /// Query extractor error
+    #[display(fmt = "{}", _0)]
+    Query(QueryPayloadError),
+    /// Form extractor error
+    #[display(fmt = "{}", _0)]
+    Form(UrlencodedError),
+}
+
+/// Return response of the extractor error, `NotFound` for `Path` errors
+impl ResponseError for ExtractorError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ExtractorError::Json(e) => e.error_response(),
+            ExtractorError::Path(_) => HttpResponse::new(StatusCode::NOT_FOUND),
+            ExtractorError::Query(e) => e.error_response(),
+            ExtractorError::Form(e) => e.error_response(),
+        }
+    }
+
+    fn error_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractorError::Json(e) => Some(e),
+            ExtractorError::Path(e) => Some(e),
+            ExtractorError::Query(e) => Some(e),
+            ExtractorError::Form(e) => Some(e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error_source()
+    }
 }
 
 #[cfg(test)]
@@ -201,4 +434,19 @@ mod tests {
         let resp: HttpResponse = ReadlinesError::EncodingError.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[test]
+    fn test_extractor_error() {
+        let resp: HttpResponse =
+            ExtractorError::from(JsonPayloadError::Overflow).error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let resp: HttpResponse = ExtractorError::from(PathError::Deserialize(
+            serde_urlencoded::from_str::<i32>("bad path").unwrap_err(),
+        ))
+        .error_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp: HttpResponse =
+            ExtractorError::from(UrlencodedError::ContentType).error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
 }
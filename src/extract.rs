@@ -191,7 +191,7 @@ impl FromRequest for () {
     }
 }
 
-macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
+macro_rules! tuple_from_req ({@impl $fut_type:ident, $config:ty, $(($n:tt, $T:ident)),+} => {
 
     /// FromRequest implementation for tuple
     #[doc(hidden)]
@@ -199,11 +199,11 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
     {
         type Error = Error;
         type Future = $fut_type<$($T),+>;
-        type Config = ($($T::Config),+);
+        type Config = $config;
 
         fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
             $fut_type {
-                items: <($(Option<$T>,)+)>::default(),
+                items: ($(None::<$T>,)+),
                 futs: ($($T::from_request(req, payload),)+),
             }
         }
@@ -245,6 +245,18 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
                 }
         }
     }
+};
+{$fut_type:ident, $(($n:tt, $T:ident)),+} => {
+    tuple_from_req!(@impl $fut_type, ($($T::Config),+), $(($n, $T)),+);
+};
+// tuples of more than 12 elements do not implement `Default`, configs are
+// split into tuple of first 12 configs and tuple of the rest
+{@large $fut_type:ident, [$(($n:tt, $T:ident)),+], [$(($n2:tt, $T2:ident)),+]} => {
+    tuple_from_req!(
+        @impl $fut_type,
+        (($($T::Config,)+), ($($T2::Config,)+)),
+        $(($n, $T)),+, $(($n2, $T2)),+
+    );
 });
 
 #[rustfmt::skip]
@@ -261,6 +273,12 @@ tuple_from_req!(TupleFromRequest7, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F
 tuple_from_req!(TupleFromRequest8, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H));
 tuple_from_req!(TupleFromRequest9, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I));
 tuple_from_req!(TupleFromRequest10, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J));
+tuple_from_req!(TupleFromRequest11, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K));
+tuple_from_req!(TupleFromRequest12, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L));
+tuple_from_req!(@large TupleFromRequest13, [(0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L)], [(12, M)]);
+tuple_from_req!(@large TupleFromRequest14, [(0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L)], [(12, M), (13, N)]);
+tuple_from_req!(@large TupleFromRequest15, [(0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L)], [(12, M), (13, N), (14, O)]);
+tuple_from_req!(@large TupleFromRequest16, [(0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L)], [(12, M), (13, N), (14, O), (15, P)]);
 }
 
 #[cfg(test)]
@@ -350,4 +368,12 @@ mod tests {
             block_on(Result::<Form<Info>, Error>::from_request(&req, &mut pl)).unwrap();
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_large_tuple_config() {
+        type Large = ((), (), (), (), (), (), (), (), (), (), (), (), Form<Info>);
+
+        let (_, (cfg,)) = Large::configure(|(first, (cfg,))| (first, (cfg.limit(10),)));
+        let _: FormConfig = cfg;
+    }
 }
@@ -11,11 +11,15 @@ use futures::ready;
 use pin_project::pin_project;
 
 use crate::extract::FromRequest;
+use crate::middleware::BodyLimit;
 use crate::request::HttpRequest;
 use crate::responder::Responder;
 use crate::service::{ServiceRequest, ServiceResponse};
 
 /// Async handler converter factory
+///
+/// Implemented for async functions of up to 16 arguments, every argument
+/// has to implement `FromRequest`.
 pub trait Factory<T, R, O>: Clone + 'static
 where
     R: Future<Output = O>,
@@ -209,11 +213,23 @@ where
 
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
         let (req, mut payload) = req.into_parts();
+
+        // limit is checked before extractors run
+        if let Err(e) = BodyLimit::check(&req) {
+            return ExtractResponse {
+                req,
+                err: Some(e.into()),
+                fut: None,
+                fut_s: None,
+                service: self.service.clone(),
+            };
+        }
         let fut = T::from_request(&req, &mut payload);
 
         ExtractResponse {
-            fut,
             req,
+            err: None,
+            fut: Some(fut),
             fut_s: None,
             service: self.service.clone(),
         }
@@ -224,8 +240,9 @@ where
 pub struct ExtractResponse<T: FromRequest, S: Service> {
     req: HttpRequest,
     service: S,
+    err: Option<Error>,
     #[pin]
-    fut: T::Future,
+    fut: Option<T::Future>,
     #[pin]
     fut_s: Option<S::Future>,
 }
@@ -247,7 +264,12 @@ where
             return fut.poll(cx).map_err(|_| panic!());
         }
 
-        match ready!(this.fut.poll(cx)) {
+        if let Some(e) = this.err.take() {
+            let req = ServiceRequest::new(this.req.clone());
+            return Poll::Ready(Err((e, req)));
+        }
+
+        match ready!(this.fut.as_pin_mut().unwrap().poll(cx)) {
             Err(e) => {
                 let req = ServiceRequest::new(this.req.clone());
                 Poll::Ready(Err((e.into(), req)))
@@ -288,4 +310,10 @@ factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G));
 factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H));
 factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I));
 factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L), (12, M));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L), (12, M), (13, N));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L), (12, M), (13, N), (14, O));
+factory_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L), (12, M), (13, N), (14, O), (15, P));
 }
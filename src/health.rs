@@ -0,0 +1,464 @@
+//! Health check service
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_http::clock;
+use actix_server::Server;
+use futures::future::{join_all, ready, FutureExt, LocalBoxFuture, Shared};
+use futures::StreamExt;
+use serde_json::{Map, Value};
+use tokio_timer::{delay_for, Timeout};
+
+use crate::config::AppService;
+use crate::dev::HttpServiceFactory;
+use crate::resource::Resource;
+use crate::{web, HttpResponse};
+
+/// Asynchronous check of a dependency, i.e. database ping or upstream probe.
+///
+/// Implemented for functions that return future of `Result<(), E>`.
+pub trait HealthCheck: 'static {
+    /// Error of the failed check, it is reported by readiness endpoint.
+    type Error: fmt::Display;
+
+    /// Future of the check.
+    type Future: Future<Output = Result<(), Self::Error>>;
+
+    /// Run the check.
+    fn check(&self) -> Self::Future;
+}
+
+impl<F, R, E> HealthCheck for F
+where
+    F: Fn() -> R + 'static,
+    R: Future<Output = Result<(), E>>,
+    E: fmt::Display,
+{
+    type Error = E;
+    type Future = R;
+
+    fn check(&self) -> R {
+        (self)()
+    }
+}
+
+/// Readiness of the server.
+///
+/// Handle could be shared by applications of all workers and by
+/// `HttpServer::readiness()`, which flips readiness off on shutdown signals.
+#[derive(Clone, Debug)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    /// Create ready handle.
+    pub fn new() -> Readiness {
+        Readiness(Arc::new(AtomicBool::new(true)))
+    }
+
+    /// Check if the server is ready.
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Set readiness of the server.
+    pub fn set_ready(&self, ready: bool) {
+        self.0.store(ready, Ordering::Release)
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Readiness::new()
+    }
+}
+
+type BoxedCheck = Box<dyn Fn() -> LocalBoxFuture<'static, Result<(), String>>>;
+
+/// Service with `/livez` and `/readyz` endpoints.
+///
+/// Liveness endpoint always responds with *200 OK*. Readiness endpoint runs
+/// registered checks concurrently and responds with *200 OK* if all of
+/// them succeed, otherwise with *503 Service Unavailable*. Results of the
+/// checks are reported in json body. Checks that do not complete within
+/// timeout are failed, results are cached for a second by default.
+/// Concurrent requests share the same run of a check.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpServer};
+///
+/// async fn ping_db() -> Result<(), String> {
+///     Ok(())
+/// }
+///
+/// fn main() {
+///     let readiness = web::Readiness::new();
+///     let ready = readiness.clone();
+///
+///     let server = HttpServer::new(move || {
+///         App::new().service(
+///             web::health_service()
+///                 .readiness(ready.clone())
+///                 .check("db", ping_db),
+///         )
+///     })
+///     .readiness(readiness, 5);
+/// }
+/// ```
+pub struct HealthService {
+    readiness: Readiness,
+    checks: Vec<(String, BoxedCheck)>,
+    timeout: Duration,
+    cache: Duration,
+}
+
+impl HealthService {
+    /// Create health service without checks.
+    pub fn new() -> HealthService {
+        HealthService {
+            readiness: Readiness::new(),
+            checks: Vec::new(),
+            timeout: Duration::from_secs(5),
+            cache: Duration::from_secs(1),
+        }
+    }
+
+    /// Set readiness handle, readiness endpoint fails if it is not ready.
+    pub fn readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// Register readiness check.
+    pub fn check<C>(mut self, name: &str, check: C) -> Self
+    where
+        C: HealthCheck,
+        C::Future: 'static,
+    {
+        let check: BoxedCheck = Box::new(move || {
+            check
+                .check()
+                .map(|res| res.map_err(|e| e.to_string()))
+                .boxed_local()
+        });
+        self.checks.push((name.to_owned(), check));
+        self
+    }
+
+    /// Set timeout of the checks.
+    ///
+    /// By default timeout is set to 5 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set time results of the checks are cached for.
+    ///
+    /// By default results are cached for 1 second.
+    pub fn cache(mut self, cache: Duration) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl Default for HealthService {
+    fn default() -> Self {
+        HealthService::new()
+    }
+}
+
+impl HttpServiceFactory for HealthService {
+    fn register(self, config: &mut AppService) {
+        let inner = Rc::new(HealthInner {
+            readiness: self.readiness,
+            checks: self
+                .checks
+                .into_iter()
+                .map(|(name, check)| Check {
+                    name,
+                    check,
+                    state: Rc::new(RefCell::new(CheckState::default())),
+                })
+                .collect(),
+            timeout: self.timeout,
+            cache: self.cache,
+        });
+
+        Resource::new("/livez")
+            .route(web::get().to(|| ready(HttpResponse::Ok().body("ok"))))
+            .register(config);
+        Resource::new("/readyz")
+            .route(web::get().to(move || {
+                let inner = inner.clone();
+                async move { inner.ready().await }
+            }))
+            .register(config);
+    }
+}
+
+struct Check {
+    name: String,
+    check: BoxedCheck,
+    state: Rc<RefCell<CheckState>>,
+}
+
+#[derive(Default)]
+struct CheckState {
+    cached: Option<(Instant, Result<(), String>)>,
+    running: Option<Shared<LocalBoxFuture<'static, Result<(), String>>>>,
+}
+
+struct HealthInner {
+    readiness: Readiness,
+    checks: Vec<Check>,
+    timeout: Duration,
+    cache: Duration,
+}
+
+impl HealthInner {
+    async fn ready(&self) -> HttpResponse {
+        if !self.readiness.is_ready() {
+            return HttpResponse::ServiceUnavailable()
+                .json(serde_json::json!({ "status": "shutting down" }));
+        }
+
+        let results = join_all(self.checks.iter().map(|c| self.run(c))).await;
+
+        let mut failed = false;
+        let mut checks = Map::new();
+        for (check, res) in self.checks.iter().zip(results) {
+            let status = match res {
+                Ok(()) => "ok".to_owned(),
+                Err(e) => {
+                    failed = true;
+                    e
+                }
+            };
+            checks.insert(check.name.clone(), Value::String(status));
+        }
+
+        if failed {
+            HttpResponse::ServiceUnavailable()
+                .json(serde_json::json!({ "status": "fail", "checks": checks }))
+        } else {
+            HttpResponse::Ok()
+                .json(serde_json::json!({ "status": "ok", "checks": checks }))
+        }
+    }
+
+    async fn run(&self, check: &Check) -> Result<(), String> {
+        let fut = {
+            let mut state = check.state.borrow_mut();
+            if let Some((at, res)) = &state.cached {
+                if clock::now() < *at + self.cache {
+                    return res.clone();
+                }
+            }
+
+            match state.running {
+                Some(ref fut) => fut.clone(),
+                None => {
+                    let timeout = Timeout::new((check.check)(), self.timeout);
+                    let weak = Rc::downgrade(&check.state);
+                    let fut = async move {
+                        let res = match timeout.await {
+                            Ok(res) => res,
+                            Err(_) => Err("timeout".to_owned()),
+                        };
+                        if let Some(state) = weak.upgrade() {
+                            let mut state = state.borrow_mut();
+                            state.cached = Some((clock::now(), res.clone()));
+                            state.running = None;
+                        }
+                        res
+                    }
+                    .boxed_local()
+                    .shared();
+                    state.running = Some(fut.clone());
+                    fut
+                }
+            }
+        };
+        fut.await
+    }
+}
+
+/// Flip readiness off on shutdown signals and stop the server after delay.
+///
+/// Immediate shutdown signals stop the server right away.
+pub(crate) fn shutdown_on_signals(srv: Server, readiness: Readiness, delay: Duration) {
+    actix_rt::spawn(async move {
+        let graceful = match signal().await {
+            Some(graceful) => graceful,
+            None => return,
+        };
+        if graceful {
+            log::info!("Shutdown signal received, server is not ready");
+            readiness.set_ready(false);
+            delay_for(delay).await;
+        }
+        srv.stop(graceful).await;
+    });
+}
+
+/// Wait for shutdown signal, returns `true` for graceful shutdown.
+#[cfg(unix)]
+async fn signal() -> Option<bool> {
+    use tokio_net::signal::unix::{signal, SignalKind};
+
+    let kinds = [
+        (SignalKind::interrupt(), false),
+        (SignalKind::terminate(), true),
+        (SignalKind::quit(), false),
+    ];
+    let mut signals = Vec::new();
+    for (kind, graceful) in kinds.iter() {
+        let graceful = *graceful;
+        match signal(*kind) {
+            Ok(mut stream) => signals.push(
+                async move {
+                    stream.next().await;
+                    graceful
+                }
+                .boxed_local(),
+            ),
+            Err(e) => log::error!("Can not initialize signal handler: {}", e),
+        }
+    }
+    if signals.is_empty() {
+        None
+    } else {
+        Some(futures::future::select_all(signals).await.0)
+    }
+}
+
+/// Wait for shutdown signal, returns `true` for graceful shutdown.
+#[cfg(not(unix))]
+async fn signal() -> Option<bool> {
+    match tokio_net::signal::ctrl_c() {
+        Ok(mut stream) => {
+            stream.next().await;
+            Some(false)
+        }
+        Err(e) => {
+            log::error!("Can not initialize signal handler: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_service::Service;
+    use futures::future::join;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::App;
+
+    #[test]
+    fn test_health() {
+        block_on(async {
+            let readiness = Readiness::new();
+            let calls = Rc::new(Cell::new(0));
+            let calls2 = calls.clone();
+
+            let mut srv = init_service(
+                App::new().service(
+                    web::scope("/health").service(
+                        web::health_service()
+                            .readiness(readiness.clone())
+                            .timeout(Duration::from_millis(50))
+                            .check("db", move || {
+                                calls2.set(calls2.get() + 1);
+                                ready(Ok::<_, String>(()))
+                            }),
+                    ),
+                ),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/health/livez").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            for _ in 0..2 {
+                let req = TestRequest::with_uri("/health/readyz").to_request();
+                let res = call_service(&mut srv, req).await;
+                assert_eq!(res.status(), StatusCode::OK);
+                assert_eq!(
+                    read_body(res).await,
+                    r#"{"checks":{"db":"ok"},"status":"ok"}"#
+                );
+            }
+            assert_eq!(calls.get(), 1);
+
+            readiness.set_ready(false);
+            let req = TestRequest::with_uri("/health/readyz").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+            let req = TestRequest::with_uri("/health/livez").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        })
+    }
+
+    #[test]
+    fn test_health_failed() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new().service(
+                    web::health_service()
+                        .timeout(Duration::from_millis(50))
+                        .check("db", || ready(Err::<(), _>("connection refused")))
+                        .check("upstream", || async {
+                            delay_for(Duration::from_millis(500)).await;
+                            Ok::<_, String>(())
+                        }),
+                ),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/readyz").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(
+                read_body(res).await,
+                r#"{"checks":{"db":"connection refused","upstream":"timeout"},"status":"fail"}"#
+            );
+        })
+    }
+
+    #[test]
+    fn test_health_single_run() {
+        block_on(async {
+            let calls = Rc::new(Cell::new(0));
+            let calls2 = calls.clone();
+
+            let health = web::health_service().check("db", move || {
+                calls2.set(calls2.get() + 1);
+                async {
+                    delay_for(Duration::from_millis(20)).await;
+                    Ok::<_, String>(())
+                }
+            });
+            let mut srv = init_service(App::new().service(health)).await;
+
+            let req1 = TestRequest::with_uri("/readyz").to_request();
+            let req2 = TestRequest::with_uri("/readyz").to_request();
+            let (res1, res2) = join(srv.call(req1), srv.call(req2)).await;
+            assert_eq!(res1.unwrap().status(), StatusCode::OK);
+            assert_eq!(res2.unwrap().status(), StatusCode::OK);
+            assert_eq!(calls.get(), 1);
+        })
+    }
+}
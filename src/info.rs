@@ -1,13 +1,221 @@
 use std::cell::Ref;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 
-use crate::dev::{AppConfig, RequestHead};
+use futures::future::{ok, Ready};
+
+use crate::dev::{AppConfig, Payload, RequestHead};
+use crate::error::Error;
+use crate::extract::FromRequest;
 use crate::http::header::{self, HeaderName};
+use crate::request::HttpRequest;
 
 const X_FORWARDED_FOR: &[u8] = b"x-forwarded-for";
 const X_FORWARDED_HOST: &[u8] = b"x-forwarded-host";
 const X_FORWARDED_PROTO: &[u8] = b"x-forwarded-proto";
 
+/// Order in which forwarding headers are used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForwardedHeaders {
+    /// `Forwarded` header, then `X-Forwarded-*` headers
+    ForwardedFirst,
+    /// `X-Forwarded-*` headers, then `Forwarded` header
+    XForwardedFirst,
+    /// `Forwarded` header only
+    ForwardedOnly,
+    /// `X-Forwarded-*` headers only
+    XForwardedOnly,
+}
+
+/// Configuration of forwarding headers used by `ConnectionInfo`.
+///
+/// By default forwarding headers of all peers are trusted. Once trusted
+/// proxies are configured, headers are used only if request comes from
+/// one of them, and proxies are skipped when client address is resolved.
+///
+/// ```rust
+/// use actix_web::dev::{ForwardedConfig, ForwardedHeaders};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .forwarded(
+///             ForwardedConfig::default()
+///                 .trusted_proxy("10.0.0.0/8")
+///                 .trusted_proxy("::1")
+///                 .headers(ForwardedHeaders::ForwardedOnly),
+///         )
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForwardedConfig {
+    trusted: Option<Arc<Vec<IpRange>>>,
+    headers: ForwardedHeaders,
+}
+
+impl Default for ForwardedConfig {
+    fn default() -> Self {
+        ForwardedConfig {
+            trusted: None,
+            headers: ForwardedHeaders::ForwardedFirst,
+        }
+    }
+}
+
+impl ForwardedConfig {
+    /// Trust forwarding headers of proxies from the range, i.e.
+    /// `"10.0.0.0/8"` or `"127.0.0.1"`.
+    ///
+    /// Panics if range can not be parsed.
+    pub fn trusted_proxy(mut self, range: &str) -> Self {
+        let range = IpRange::parse(range)
+            .unwrap_or_else(|| panic!("Can not parse ip range: {}", range));
+        let mut trusted = self
+            .trusted
+            .take()
+            .map(|trusted| (*trusted).clone())
+            .unwrap_or_default();
+        trusted.push(range);
+        self.trusted = Some(Arc::new(trusted));
+        self
+    }
+
+    /// Set which forwarding headers are used and their precedence.
+    ///
+    /// By default `Forwarded` header takes precedence over `X-Forwarded-*`
+    /// headers.
+    pub fn headers(mut self, headers: ForwardedHeaders) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    fn is_trusted(&self, addr: Option<IpAddr>) -> bool {
+        match self.trusted {
+            None => true,
+            Some(ref trusted) => match addr {
+                Some(addr) => trusted.iter().any(|range| range.contains(addr)),
+                None => false,
+            },
+        }
+    }
+
+    /// Index of client address in list of addresses, from client to the
+    /// last proxy. Trusted proxies are skipped from the end of the list.
+    fn client(&self, addrs: &[&str]) -> Option<usize> {
+        if addrs.is_empty() {
+            None
+        } else {
+            Some(
+                addrs
+                    .iter()
+                    .rposition(|addr| !self.is_trusted(parse_ip(addr)))
+                    .unwrap_or(0),
+            )
+        }
+    }
+}
+
+/// Network range, address and prefix length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IpRange(IpAddr, u8);
+
+impl IpRange {
+    fn parse(range: &str) -> Option<IpRange> {
+        let mut parts = range.trim().splitn(2, '/');
+        let addr: IpAddr = parts.next()?.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().ok()?,
+            None => max,
+        };
+        if prefix > max {
+            None
+        } else {
+            Some(IpRange(addr, prefix))
+        }
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        match (self.0, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.1))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.1))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Ip address of `for` value, i.e. `192.0.2.60`, `"[2001:db8::1]:4711"`.
+fn parse_ip(val: &str) -> Option<IpAddr> {
+    let val = val.trim().trim_matches('"');
+    val.parse::<IpAddr>()
+        .ok()
+        .or_else(|| val.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            val.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .ok()
+        })
+}
+
+/// Element of `Forwarded` header, RFC 7239.
+#[derive(Default)]
+struct ForwardedElement<'a> {
+    for_: Option<&'a str>,
+    proto: Option<&'a str>,
+    host: Option<&'a str>,
+}
+
+fn forwarded_elements(req: &RequestHead) -> Vec<ForwardedElement> {
+    let mut elements = Vec::new();
+    for hdr in req.headers.get_all(&header::FORWARDED) {
+        if let Ok(val) = hdr.to_str() {
+            for el in val.split(',') {
+                let mut element = ForwardedElement::default();
+                for pair in el.split(';') {
+                    let mut items = pair.trim().splitn(2, '=');
+                    if let (Some(name), Some(val)) = (items.next(), items.next()) {
+                        let val = val.trim().trim_matches('"');
+                        match &name.trim().to_lowercase() as &str {
+                            "for" => element.for_ = Some(val),
+                            "proto" => element.proto = Some(val),
+                            "host" => element.host = Some(val),
+                            _ => (),
+                        }
+                    }
+                }
+                elements.push(element);
+            }
+        }
+    }
+    elements
+}
+
+/// Last value of comma separated header, it is appended by the nearest
+/// proxy, preceding values could be sent by the client.
+fn last_value<'a>(req: &'a RequestHead, name: &[u8]) -> Option<&'a str> {
+    req.headers
+        .get_all(&HeaderName::from_lowercase(name).unwrap())
+        .filter_map(|h| h.to_str().ok())
+        .last()
+        .and_then(|h| h.rsplit(',').next())
+        .map(|v| v.trim())
+}
+
 /// `HttpRequest` connection information
+///
+/// It could be used as extractor.
 #[derive(Debug, Clone, Default)]
 pub struct ConnectionInfo {
     scheme: String,
@@ -25,107 +233,75 @@ impl ConnectionInfo {
         Ref::map(req.extensions(), |e| e.get().unwrap())
     }
 
-    #[allow(clippy::cognitive_complexity)]
     fn new(req: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
-        let mut host = None;
-        let mut scheme = None;
-        let mut remote = None;
-        let mut peer = None;
-
-        // load forwarded header
-        for hdr in req.headers.get_all(&header::FORWARDED) {
-            if let Ok(val) = hdr.to_str() {
-                for pair in val.split(';') {
-                    for el in pair.split(',') {
-                        let mut items = el.trim().splitn(2, '=');
-                        if let Some(name) = items.next() {
-                            if let Some(val) = items.next() {
-                                match &name.to_lowercase() as &str {
-                                    "for" => {
-                                        if remote.is_none() {
-                                            remote = Some(val.trim());
-                                        }
-                                    }
-                                    "proto" => {
-                                        if scheme.is_none() {
-                                            scheme = Some(val.trim());
-                                        }
-                                    }
-                                    "host" => {
-                                        if host.is_none() {
-                                            host = Some(val.trim());
-                                        }
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let fwd = cfg.forwarded();
+        let trusted = fwd.is_trusted(req.peer_addr.map(|addr| addr.ip()));
 
-        // scheme
-        if scheme.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    scheme = h.split(',').next().map(|v| v.trim());
-                }
-            }
-            if scheme.is_none() {
-                scheme = req.uri.scheme_part().map(|a| a.as_str());
-                if scheme.is_none() && cfg.secure() {
-                    scheme = Some("https")
-                }
+        let use_forwarded = trusted && fwd.headers != ForwardedHeaders::XForwardedOnly;
+        let use_x_forwarded = trusted && fwd.headers != ForwardedHeaders::ForwardedOnly;
+
+        // Forwarded header, values of the element added by the proxy
+        // that received request from the client
+        let mut forwarded = (None, None, None);
+        if use_forwarded {
+            let elements = forwarded_elements(req);
+            let addrs: Vec<_> =
+                elements.iter().map(|el| el.for_.unwrap_or("")).collect();
+            if let Some(el) = fwd.client(&addrs).and_then(|idx| elements.get(idx)) {
+                forwarded = (el.for_, el.proto, el.host);
             }
         }
 
-        // host
-        if host.is_none() {
-            if let Some(h) = req
+        // X-Forwarded-* headers
+        let mut x_forwarded = (None, None, None);
+        if use_x_forwarded {
+            let addrs: Vec<_> = req
                 .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    host = h.split(',').next().map(|v| v.trim());
-                }
-            }
-            if host.is_none() {
-                if let Some(h) = req.headers.get(&header::HOST) {
-                    host = h.to_str().ok();
-                }
-                if host.is_none() {
-                    host = req.uri.authority_part().map(|a| a.as_str());
-                    if host.is_none() {
-                        host = Some(cfg.host());
-                    }
-                }
-            }
+                .get_all(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+                .filter_map(|h| h.to_str().ok())
+                .flat_map(|h| h.split(','))
+                .map(|v| v.trim())
+                .collect();
+            x_forwarded = (
+                fwd.client(&addrs).and_then(|idx| addrs.get(idx).copied()),
+                last_value(req, X_FORWARDED_PROTO),
+                last_value(req, X_FORWARDED_HOST),
+            );
         }
 
-        // remote addr
-        if remote.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    remote = h.split(',').next().map(|v| v.trim());
-                }
-            }
-            if remote.is_none() {
-                // get peeraddr from socketaddr
-                peer = req.peer_addr.map(|addr| format!("{}", addr));
+        let (first, second) = match fwd.headers {
+            ForwardedHeaders::XForwardedFirst | ForwardedHeaders::XForwardedOnly => {
+                (x_forwarded, forwarded)
             }
-        }
+            _ => (forwarded, x_forwarded),
+        };
+
+        let remote = first.0.or(second.0);
+
+        let scheme = first
+            .1
+            .or(second.1)
+            .or_else(|| req.uri.scheme_part().map(|a| a.as_str()))
+            .or_else(|| if cfg.secure() { Some("https") } else { None });
+
+        let host = first
+            .2
+            .or(second.2)
+            .or_else(|| req.headers.get(&header::HOST).and_then(|h| h.to_str().ok()))
+            .or_else(|| req.uri.authority_part().map(|a| a.as_str()))
+            .unwrap_or_else(|| cfg.host());
+
+        // get peeraddr from socketaddr
+        let peer = if remote.is_none() {
+            req.peer_addr.map(|addr| format!("{}", addr))
+        } else {
+            None
+        };
 
         ConnectionInfo {
             peer,
             scheme: scheme.unwrap_or("http").to_owned(),
-            host: host.unwrap_or("localhost").to_owned(),
+            host: host.to_owned(),
             remote: remote.map(|s| s.to_owned()),
         }
     }
@@ -137,6 +313,8 @@ impl ConnectionInfo {
     /// - Forwarded
     /// - X-Forwarded-Proto
     /// - Uri
+    ///
+    /// Order of forwarding headers is configured with `App::forwarded()`.
     #[inline]
     pub fn scheme(&self) -> &str {
         &self.scheme
@@ -151,6 +329,8 @@ impl ConnectionInfo {
     /// - Host
     /// - Uri
     /// - Server hostname
+    ///
+    /// Order of forwarding headers is configured with `App::forwarded()`.
     pub fn host(&self) -> &str {
         &self.host
     }
@@ -163,10 +343,13 @@ impl ConnectionInfo {
     /// - X-Forwarded-For
     /// - peer name of opened socket
     ///
+    /// If trusted proxies are configured with `App::forwarded()`, the last
+    /// address that is not a trusted proxy is returned.
+    ///
     /// # Security
     /// Do not use this function for security purposes, unless you can ensure the Forwarded and
-    /// X-Forwarded-For headers cannot be spoofed by the client. If you want the client's socket
-    /// address explicitly, use
+    /// X-Forwarded-For headers cannot be spoofed by the client, i.e. trusted proxies are
+    /// configured. If you want the client's socket address explicitly, use
     /// [`HttpRequest::peer_addr()`](../web/struct.HttpRequest.html#method.peer_addr) instead.
     #[inline]
     pub fn remote(&self) -> Option<&str> {
@@ -180,10 +363,31 @@ impl ConnectionInfo {
     }
 }
 
+impl FromRequest for ConnectionInfo {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<ConnectionInfo, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req.connection_info().clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::TestRequest;
+    use crate::config::AppConfigInner;
+    use crate::test::{block_on, TestRequest};
+
+    fn info_with(req: TestRequest, cfg: ForwardedConfig) -> ConnectionInfo {
+        let req = req.to_http_request();
+        let config = AppConfig::new(AppConfigInner {
+            forwarded: cfg,
+            ..Default::default()
+        });
+        ConnectionInfo::new(req.head(), &config)
+    }
 
     #[test]
     fn test_forwarded() {
@@ -232,4 +436,129 @@ mod tests {
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
     }
+
+    #[test]
+    fn test_ip_range() {
+        let range = IpRange::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains("10.1.2.3".parse().unwrap()));
+        assert!(!range.contains("11.1.2.3".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+
+        let range = IpRange::parse("127.0.0.1").unwrap();
+        assert!(range.contains("127.0.0.1".parse().unwrap()));
+        assert!(!range.contains("127.0.0.2".parse().unwrap()));
+
+        let range = IpRange::parse("fd00::/8").unwrap();
+        assert!(range.contains("fd12::1".parse().unwrap()));
+        assert!(IpRange::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("1.2.3.4".parse().unwrap()));
+
+        assert!(IpRange::parse("10.0.0.0/33").is_none());
+        assert!(IpRange::parse("localhost").is_none());
+    }
+
+    #[test]
+    fn test_parse_ip() {
+        assert_eq!(parse_ip("192.0.2.60"), Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(
+            parse_ip("\"[2001:db8:cafe::17]:4711\""),
+            Some("2001:db8:cafe::17".parse().unwrap())
+        );
+        assert_eq!(
+            parse_ip("[2001:db8::1]"),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(parse_ip("_hidden"), None);
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let cfg = ForwardedConfig::default().trusted_proxy("10.0.0.0/8");
+
+        // untrusted peer, headers are ignored
+        let info = info_with(
+            TestRequest::default()
+                .peer_addr("192.0.2.1:8080".parse().unwrap())
+                .header(header::FORWARDED, "for=192.0.2.60;proto=https")
+                .header(X_FORWARDED_FOR, "192.0.2.61"),
+            cfg.clone(),
+        );
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.remote(), Some("192.0.2.1:8080"));
+
+        // trusted peer, trusted proxies are skipped
+        let info = info_with(
+            TestRequest::default()
+                .peer_addr("10.0.0.1:8080".parse().unwrap())
+                .header(
+                    header::FORWARDED,
+                    "for=198.51.100.1;proto=http, for=192.0.2.60;proto=https;host=rust-lang.org, for=10.0.0.2",
+                ),
+            cfg.clone(),
+        );
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        let info = info_with(
+            TestRequest::default()
+                .peer_addr("10.0.0.1:8080".parse().unwrap())
+                .header(X_FORWARDED_FOR, "198.51.100.1, 192.0.2.60, 10.0.0.2"),
+            cfg.clone(),
+        );
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        // values sent by the client are ignored
+        let info = info_with(
+            TestRequest::default()
+                .peer_addr("10.0.0.1:8080".parse().unwrap())
+                .header(X_FORWARDED_PROTO, "http, https")
+                .header(X_FORWARDED_HOST, "evil.org, rust-lang.org"),
+            cfg,
+        );
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+    }
+
+    #[test]
+    fn test_headers_precedence() {
+        let req = || {
+            TestRequest::default()
+                .header(header::FORWARDED, "for=192.0.2.60;host=forwarded.org")
+                .header(X_FORWARDED_FOR, "192.0.2.61")
+                .header(X_FORWARDED_HOST, "x-forwarded.org")
+        };
+
+        let info = info_with(req(), ForwardedConfig::default());
+        assert_eq!(info.host(), "forwarded.org");
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        let cfg = ForwardedConfig::default().headers(ForwardedHeaders::XForwardedFirst);
+        let info = info_with(req(), cfg);
+        assert_eq!(info.host(), "x-forwarded.org");
+        assert_eq!(info.remote(), Some("192.0.2.61"));
+
+        let cfg = ForwardedConfig::default().headers(ForwardedHeaders::ForwardedOnly);
+        let info = info_with(req().header(header::FORWARDED, "proto=https"), cfg);
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        let cfg = ForwardedConfig::default().headers(ForwardedHeaders::XForwardedOnly);
+        let info = info_with(
+            TestRequest::default().header(header::FORWARDED, "for=192.0.2.60"),
+            cfg,
+        );
+        assert_eq!(info.remote(), None);
+    }
+
+    #[test]
+    fn test_extractor() {
+        block_on(async {
+            let (req, mut pl) = TestRequest::default()
+                .header(header::HOST, "rust-lang.org")
+                .to_http_parts();
+            let info = ConnectionInfo::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(info.host(), "rust-lang.org");
+        })
+    }
 }
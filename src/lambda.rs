@@ -0,0 +1,514 @@
+//! AWS Lambda adapter
+//!
+//! Runs application with events of API Gateway REST and HTTP APIs (payload
+//! format `1.0` and `2.0`) and of Application Load Balancer. Adapter does
+//! not depend on particular lambda runtime, `LambdaEvent` is deserialized
+//! from payload of the invocation and `LambdaResponse` is serialized as its
+//! result.
+//!
+//! Requires `lambda` feature.
+//!
+//! ```rust
+//! use actix_web::lambda::{Lambda, LambdaEvent};
+//! use actix_web::{web, App, HttpResponse};
+//!
+//! async fn invoke(payload: &[u8]) -> Result<Vec<u8>, actix_web::Error> {
+//!     let mut lambda = Lambda::new(
+//!         App::new().route("/", web::get().to(|| HttpResponse::Ok())),
+//!     )
+//!     .await
+//!     .unwrap();
+//!
+//!     let event: LambdaEvent = serde_json::from_slice(payload)?;
+//!     let res = lambda.call(event).await?;
+//!     Ok(serde_json::to_vec(&res)?)
+//! }
+//! ```
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+use actix_http::body::MessageBody;
+use actix_http::{IntoPayload, Request, Response};
+use actix_server_config::ServerConfig;
+use actix_service::{IntoServiceFactory, Service, ServiceFactory};
+use bytes::{Bytes, BytesMut};
+use futures::future::poll_fn;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorBadRequest};
+use crate::http::header::{
+    HeaderName, HeaderValue, CONTENT_ENCODING, COOKIE, SET_COOKIE,
+};
+use crate::http::{Method, Uri};
+use crate::service::ServiceResponse;
+
+/// Event of API Gateway or Application Load Balancer.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LambdaEvent {
+    version: Option<String>,
+    http_method: Option<String>,
+    path: Option<String>,
+    raw_path: Option<String>,
+    raw_query_string: Option<String>,
+    query_string_parameters: Option<HashMap<String, String>>,
+    multi_value_query_string_parameters: Option<HashMap<String, Vec<String>>>,
+    headers: Option<HashMap<String, String>>,
+    multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    cookies: Option<Vec<String>>,
+    body: Option<String>,
+    is_base64_encoded: bool,
+    request_context: RequestContext,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct RequestContext {
+    identity: Option<Identity>,
+    http: Option<HttpContext>,
+    elb: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Identity {
+    source_ip: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct HttpContext {
+    method: Option<String>,
+    source_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    V1,
+    V2,
+    Alb { multi_value: bool },
+}
+
+impl LambdaEvent {
+    fn format(&self) -> Format {
+        if self.request_context.elb.is_some() {
+            Format::Alb {
+                multi_value: self.multi_value_headers.is_some(),
+            }
+        } else if self.version.as_ref().map(|v| v == "2.0").unwrap_or(false) {
+            Format::V2
+        } else {
+            Format::V1
+        }
+    }
+
+    fn method(&self) -> Result<Method, Error> {
+        let method = match self.request_context.http {
+            Some(ref http) if http.method.is_some() => http.method.as_ref(),
+            _ => self.http_method.as_ref(),
+        };
+        method
+            .and_then(|m| Method::from_bytes(m.as_bytes()).ok())
+            .ok_or_else(|| ErrorBadRequest("Invalid method of lambda event"))
+    }
+
+    fn uri(&self) -> Result<Uri, Error> {
+        let path = self
+            .raw_path
+            .as_ref()
+            .or_else(|| self.path.as_ref())
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+
+        let query = if let Some(ref query) = self.raw_query_string {
+            query.clone()
+        } else {
+            let mut params = Vec::new();
+            if let Some(ref multi) = self.multi_value_query_string_parameters {
+                for (key, values) in multi {
+                    for value in values {
+                        params.push((key.as_str(), value.as_str()));
+                    }
+                }
+            } else if let Some(ref single) = self.query_string_parameters {
+                for (key, value) in single {
+                    params.push((key.as_str(), value.as_str()));
+                }
+            }
+
+            if let Format::Alb { .. } = self.format() {
+                // load balancer passes query as it was received
+                params
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<_>>()
+                    .join("&")
+            } else {
+                serde_urlencoded::to_string(&params)
+                    .map_err(|_| ErrorBadRequest("Invalid query of lambda event"))?
+            }
+        };
+
+        let uri = if query.is_empty() {
+            path.to_owned()
+        } else {
+            format!("{}?{}", path, query)
+        };
+        uri.parse()
+            .map_err(|_| ErrorBadRequest("Invalid path of lambda event"))
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        let ip = match self.request_context.http {
+            Some(ref http) if http.source_ip.is_some() => http.source_ip.as_ref(),
+            _ => self
+                .request_context
+                .identity
+                .as_ref()
+                .and_then(|id| id.source_ip.as_ref()),
+        };
+        ip.and_then(|ip| ip.parse::<IpAddr>().ok())
+            .map(|ip| SocketAddr::new(ip, 0))
+    }
+
+    fn body(&mut self) -> Result<Bytes, Error> {
+        match self.body.take() {
+            Some(body) => {
+                if self.is_base64_encoded {
+                    base64::decode(&body)
+                        .map(Bytes::from)
+                        .map_err(|_| ErrorBadRequest("Invalid body of lambda event"))
+                } else {
+                    Ok(Bytes::from(body))
+                }
+            }
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    /// Convert event to a `Request`.
+    pub fn into_request(mut self) -> Result<Request, Error> {
+        let method = self.method()?;
+        let uri = self.uri()?;
+        let peer_addr = self.peer_addr();
+        let body = self.body()?;
+
+        let mut req = Request::with_payload(body.into_payload());
+        let head = req.head_mut();
+        head.method = method;
+        head.uri = uri;
+        head.peer_addr = peer_addr;
+
+        let headers = match self.multi_value_headers.take() {
+            Some(headers) => headers,
+            None => self
+                .headers
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, value)| (key, vec![value]))
+                .collect(),
+        };
+        for (key, values) in headers {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| ErrorBadRequest("Invalid header of lambda event"))?;
+            for value in values {
+                let value = HeaderValue::from_str(&value)
+                    .map_err(|_| ErrorBadRequest("Invalid header of lambda event"))?;
+                head.headers.append(name.clone(), value);
+            }
+        }
+
+        // payload format 2.0 passes cookies separately
+        if let Some(cookies) = self.cookies.take() {
+            if !cookies.is_empty() {
+                let value = HeaderValue::from_str(&cookies.join("; "))
+                    .map_err(|_| ErrorBadRequest("Invalid cookie of lambda event"))?;
+                head.headers.insert(COOKIE, value);
+            }
+        }
+
+        Ok(req)
+    }
+}
+
+/// Response to API Gateway or Application Load Balancer.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LambdaResponse {
+    /// Status code of the response
+    pub status_code: u16,
+    /// Status line, used by load balancer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_description: Option<String>,
+    /// Headers with single value, multiple values are comma separated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    /// Headers with multiple values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    /// `Set-Cookie` headers for payload format `2.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cookies: Option<Vec<String>>,
+    /// Body of the response, base64 encoded if it is binary
+    pub body: String,
+    /// Body is base64 encoded
+    pub is_base64_encoded: bool,
+}
+
+impl LambdaResponse {
+    fn new<B>(format: Format, res: &Response<B>, body: Bytes) -> LambdaResponse {
+        let status = res.status();
+        let mut headers: HashMap<String, Vec<String>> = HashMap::new();
+        let mut cookies = Vec::new();
+
+        for (name, value) in res.headers() {
+            let value = match value.to_str() {
+                Ok(value) => value.to_owned(),
+                Err(_) => continue,
+            };
+            if format == Format::V2 && name == SET_COOKIE {
+                cookies.push(value);
+            } else {
+                headers
+                    .entry(name.as_str().to_owned())
+                    .or_insert_with(Vec::new)
+                    .push(value);
+            }
+        }
+
+        // compressed or binary body has to be base64 encoded
+        let (body, is_base64_encoded) = match std::str::from_utf8(&body) {
+            Ok(s) if !res.headers().contains_key(CONTENT_ENCODING) => {
+                (s.to_owned(), false)
+            }
+            _ => (base64::encode(&body), true),
+        };
+
+        let mut response = LambdaResponse {
+            status_code: status.as_u16(),
+            body,
+            is_base64_encoded,
+            ..Default::default()
+        };
+        match format {
+            Format::V1 | Format::Alb { multi_value: true } => {
+                response.multi_value_headers = Some(headers);
+            }
+            Format::V2 | Format::Alb { multi_value: false } => {
+                response.headers = Some(
+                    headers
+                        .into_iter()
+                        .map(|(key, values)| (key, values.join(",")))
+                        .collect(),
+                );
+            }
+        }
+        if let Format::Alb { .. } = format {
+            response.status_description = Some(format!(
+                "{} {}",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("")
+            ));
+        }
+        if format == Format::V2 {
+            response.cookies = Some(cookies);
+        }
+        response
+    }
+}
+
+/// Application driven by lambda events.
+///
+/// Errors of the application are rendered as responses, the same way as
+/// by `HttpServer`.
+pub struct Lambda<S> {
+    service: S,
+}
+
+impl<S, B> Lambda<S>
+where
+    S: Service<Request = Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    /// Construct application service.
+    pub async fn new<F, T>(app: F) -> Result<Self, T::InitError>
+    where
+        F: IntoServiceFactory<T>,
+        T: ServiceFactory<
+            Config = ServerConfig,
+            Request = Request,
+            Response = ServiceResponse<B>,
+            Error = Error,
+            Service = S,
+        >,
+    {
+        let cfg = ServerConfig::new(([0, 0, 0, 0], 0).into());
+        let service = app.into_factory().new_service(&cfg).await?;
+        Ok(Lambda { service })
+    }
+
+    /// Handle lambda event.
+    ///
+    /// Fails if event could not be converted to a request or if body of
+    /// the response fails.
+    pub async fn call(&mut self, event: LambdaEvent) -> Result<LambdaResponse, Error> {
+        let format = event.format();
+        let req = event.into_request()?;
+
+        poll_fn(|cx| self.service.poll_ready(cx)).await?;
+        let mut res = match self.service.call(req).await {
+            Ok(res) => res.into(),
+            Err(e) => Response::from_error(e).into_body(),
+        };
+
+        let mut body = BytesMut::new();
+        let mut stream = res.take_body();
+        while let Some(chunk) = poll_fn(|cx| stream.poll_next(cx)).await {
+            body.extend_from_slice(&chunk?);
+        }
+
+        Ok(LambdaResponse::new(format, &res, body.freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::http::Cookie;
+    use crate::test::block_on;
+    use crate::{web, App, HttpRequest, HttpResponse};
+
+    fn config(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/echo",
+            web::post().to(|req: HttpRequest, body: Bytes| {
+                HttpResponse::Ok()
+                    .header("x-query", req.query_string())
+                    .header("x-peer", format!("{:?}", req.peer_addr()))
+                    .cookie(Cookie::new("c1", "1"))
+                    .cookie(Cookie::new("c2", "2"))
+                    .body(body)
+            }),
+        )
+        .route(
+            "/binary",
+            web::get().to(|| {
+                HttpResponse::Ok()
+                    .header(CONTENT_TYPE, "application/octet-stream")
+                    .body(vec![0u8, 255])
+            }),
+        );
+    }
+
+    #[test]
+    fn test_rest_api() {
+        block_on(async {
+            let mut lambda = Lambda::new(App::new().configure(config)).await.unwrap();
+
+            let event: LambdaEvent = serde_json::from_str(
+                r#"{
+                    "httpMethod": "POST",
+                    "path": "/echo",
+                    "queryStringParameters": {"q": "a b"},
+                    "multiValueQueryStringParameters": {"q": ["a b"]},
+                    "headers": {"Host": "example.com"},
+                    "multiValueHeaders": {"Host": ["example.com"]},
+                    "requestContext": {"identity": {"sourceIp": "10.0.0.1"}},
+                    "body": "aGVsbG8=",
+                    "isBase64Encoded": true
+                }"#,
+            )
+            .unwrap();
+            let res = lambda.call(event).await.unwrap();
+            assert_eq!(res.status_code, 200);
+            assert_eq!(res.body, "hello");
+            assert!(!res.is_base64_encoded);
+            assert!(res.status_description.is_none());
+
+            let headers = res.multi_value_headers.unwrap();
+            assert_eq!(headers["x-query"], vec!["q=a+b"]);
+            assert_eq!(headers["x-peer"], vec!["Some(10.0.0.1:0)"]);
+            assert_eq!(headers["set-cookie"].len(), 2);
+
+            let event: LambdaEvent =
+                serde_json::from_str(r#"{"httpMethod": "GET", "path": "/binary"}"#)
+                    .unwrap();
+            let res = lambda.call(event).await.unwrap();
+            assert_eq!(res.body, "AP8=");
+            assert!(res.is_base64_encoded);
+
+            let event: LambdaEvent =
+                serde_json::from_str(r#"{"httpMethod": "GET", "path": "/unknown"}"#)
+                    .unwrap();
+            let res = lambda.call(event).await.unwrap();
+            assert_eq!(res.status_code, 404);
+        })
+    }
+
+    #[test]
+    fn test_http_api() {
+        block_on(async {
+            let mut lambda = Lambda::new(App::new().configure(config)).await.unwrap();
+
+            let event: LambdaEvent = serde_json::from_str(
+                r#"{
+                    "version": "2.0",
+                    "rawPath": "/echo",
+                    "rawQueryString": "q=a%20b",
+                    "cookies": ["c3=3", "c4=4"],
+                    "headers": {"host": "example.com"},
+                    "requestContext": {
+                        "http": {"method": "POST", "sourceIp": "10.0.0.1"}
+                    },
+                    "body": "hello",
+                    "isBase64Encoded": false
+                }"#,
+            )
+            .unwrap();
+            let req: LambdaEvent = serde_json::from_str(
+                r#"{
+                    "version": "2.0",
+                    "rawPath": "/",
+                    "cookies": ["c3=3", "c4=4"],
+                    "requestContext": {"http": {"method": "GET"}}
+                }"#,
+            )
+            .unwrap();
+            let req = req.into_request().unwrap();
+            assert_eq!(req.headers().get(COOKIE).unwrap(), "c3=3; c4=4");
+
+            let res = lambda.call(event).await.unwrap();
+            assert_eq!(res.body, "hello");
+            assert_eq!(res.cookies.unwrap().len(), 2);
+
+            let headers = res.headers.unwrap();
+            assert_eq!(headers["x-query"], "q=a%20b");
+            assert!(!headers.contains_key("set-cookie"));
+        })
+    }
+
+    #[test]
+    fn test_alb() {
+        block_on(async {
+            let mut lambda = Lambda::new(App::new().configure(config)).await.unwrap();
+
+            let event: LambdaEvent = serde_json::from_str(
+                r#"{
+                    "httpMethod": "POST",
+                    "path": "/echo",
+                    "queryStringParameters": {"q": "a%20b"},
+                    "headers": {"host": "example.com"},
+                    "requestContext": {"elb": {"targetGroupArn": "arn"}},
+                    "body": "hello",
+                    "isBase64Encoded": false
+                }"#,
+            )
+            .unwrap();
+            let res = lambda.call(event).await.unwrap();
+            assert_eq!(res.status_description.unwrap(), "200 OK");
+            assert_eq!(res.headers.unwrap()["x-query"], "q=a%20b");
+        })
+    }
+}
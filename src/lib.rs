@@ -78,30 +78,55 @@
 //!   `c` compiler (default enabled)
 //! * `flate2-rust` - experimental rust based implementation for
 //!   `gzip`, `deflate` compression.
+//! * `askama` - enables `askama` templates rendering adapter
+//! * `tera` - enables `tera` templates rendering adapter
+//! * `digest` - enables `Digest` header verification middleware
+//! * `upload` - enables resumable uploads service
+//! * `tower` - enables `tower` services and layers compatibility
+//! * `lambda` - enables AWS Lambda adapter
+//! * `grpc-web` - enables gRPC-web translation middleware
+//! * `jwt` - enables JSON Web Token extractor, includes `client` feature
+//! * `simd` - enables vectorized scanning of partially received HTTP/1
+//!   message heads and of chunked payload
 //!
 #![allow(clippy::type_complexity, clippy::new_without_default)]
 
 mod app;
 mod app_service;
+mod blocking;
+#[cfg(feature = "tower")]
+pub mod compat;
 mod config;
 mod data;
+mod drain;
 pub mod error;
 mod extract;
 pub mod guard;
 mod handler;
+mod health;
 mod info;
+#[cfg(feature = "lambda")]
+pub mod lambda;
+pub mod metrics;
 pub mod middleware;
+#[cfg(feature = "client")]
+mod proxy;
 mod request;
 mod resource;
 mod responder;
 mod rmap;
 mod route;
+mod router;
 mod scope;
 mod server;
 mod service;
+pub mod template;
 pub mod test;
 mod types;
+#[cfg(feature = "upload")]
+pub mod upload;
 pub mod web;
+pub mod ws;
 
 #[allow(unused_imports)]
 #[macro_use]
@@ -109,12 +134,13 @@ extern crate actix_web_codegen;
 
 #[doc(hidden)]
 pub use actix_web_codegen::*;
+pub use actix_web_codegen::{main, test};
 
 // re-export for convenience
 pub use actix_http::Response as HttpResponse;
 pub use actix_http::{body, cookie, http, Error, HttpMessage, ResponseError, Result};
 
-pub use crate::app::App;
+pub use crate::app::{App, AsyncApp};
 pub use crate::extract::FromRequest;
 pub use crate::request::HttpRequest;
 pub use crate::resource::Resource;
@@ -135,9 +161,11 @@ pub mod dev {
     //! ```
 
     pub use crate::config::{AppConfig, AppService};
+    pub use crate::drain::DrainHandle;
     #[doc(hidden)]
     pub use crate::handler::Factory;
-    pub use crate::info::ConnectionInfo;
+    pub use crate::info::{ConnectionInfo, ForwardedConfig, ForwardedHeaders};
+    pub use crate::request::http_request_pool_stats;
     pub use crate::rmap::ResourceMap;
     pub use crate::service::{
         HttpServiceFactory, ServiceRequest, ServiceResponse, WebService,
@@ -148,10 +176,12 @@ pub mod dev {
     //pub use crate::types::readlines::Readlines;
 
     pub use actix_http::body::{Body, BodySize, MessageBody, ResponseBody, SizedStream};
+    pub use actix_http::clock;
     pub use actix_http::encoding::Decoder as Decompress;
+    pub use actix_http::pool;
     pub use actix_http::ResponseBuilder as HttpResponseBuilder;
     pub use actix_http::{
-        Extensions, Payload, PayloadStream, RequestHead, ResponseHead,
+        Extensions, Payload, PayloadDrain, PayloadStream, RequestHead, ResponseHead,
     };
     pub use actix_router::{Path, ResourceDef, ResourcePath, Url};
     pub use actix_server::Server;
@@ -166,6 +196,12 @@ pub mod dev {
     }
 }
 
+pub mod rt {
+    //! Actix runtime, started by `#[actix_web::main]` and
+    //! `#[actix_web::test]` macros.
+    pub use actix_rt::{spawn, Arbiter, System, SystemRunner};
+}
+
 #[cfg(feature = "client")]
 pub mod client {
     //! An HTTP Client
@@ -0,0 +1,225 @@
+//! Per-route latency metrics.
+//!
+//! Metrics are enabled with `App::route_metrics()`, resources record hit
+//! counts and latency histograms keyed by full route pattern, i.e.
+//! `/api/users/{id}`. Latency is measured from route matching to handler
+//! response, middlewares of the application, scopes and the resource are
+//! not included. Requests that do not match any resource are not recorded.
+//!
+//! `RouteMetrics` could be shared by application factories of all
+//! workers, recorded values are aggregated.
+//!
+//! ```rust
+//! use actix_web::metrics::RouteMetrics;
+//! use actix_web::{web, App, HttpResponse, HttpServer};
+//!
+//! fn main() {
+//!     let metrics = RouteMetrics::new();
+//!     let exported = metrics.clone();
+//!
+//!     let server = HttpServer::new(move || {
+//!         App::new()
+//!             .route_metrics(metrics.clone())
+//!             .route("/users/{id}", web::get().to(|| HttpResponse::Ok()))
+//!     });
+//!
+//!     for route in exported.snapshot() {
+//!         println!("{} {} {:?}", route.pattern, route.count, route.sum);
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_http::clock;
+use parking_lot::Mutex;
+
+/// Upper bounds of histogram buckets in microseconds
+const BUCKETS: [u64; 16] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+    500_000, 1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Registry of route metrics.
+#[derive(Clone, Default)]
+pub struct RouteMetrics {
+    routes: Arc<Mutex<HashMap<String, Arc<RouteStats>>>>,
+}
+
+impl RouteMetrics {
+    /// Construct empty registry.
+    pub fn new() -> RouteMetrics {
+        RouteMetrics::default()
+    }
+
+    /// Stats of the route pattern, shared by resources with same pattern.
+    pub(crate) fn route(&self, pattern: &str) -> Arc<RouteStats> {
+        self.routes
+            .lock()
+            .entry(pattern.to_owned())
+            .or_insert_with(|| Arc::new(RouteStats::default()))
+            .clone()
+    }
+
+    /// Snapshot of recorded metrics, sorted by route pattern.
+    pub fn snapshot(&self) -> Vec<RouteSnapshot> {
+        let mut routes: Vec<_> = self
+            .routes
+            .lock()
+            .iter()
+            .map(|(pattern, stats)| stats.snapshot(pattern))
+            .collect();
+        routes.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+        routes
+    }
+}
+
+/// Recorded metrics of a route.
+#[derive(Clone, Debug)]
+pub struct RouteSnapshot {
+    /// Route pattern
+    pub pattern: String,
+    /// Number of requests
+    pub count: u64,
+    /// Total time of requests
+    pub sum: Duration,
+    /// Number of requests per latency bucket, by upper bound of the bucket.
+    ///
+    /// Buckets are not cumulative, last bucket without upper bound counts
+    /// requests slower than 10 seconds.
+    pub buckets: Vec<(Option<Duration>, u64)>,
+}
+
+#[derive(Default)]
+pub(crate) struct RouteStats {
+    count: AtomicU64,
+    sum: AtomicU64,
+    buckets: [AtomicU64; 17],
+}
+
+impl RouteStats {
+    fn record(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        let idx = BUCKETS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(BUCKETS.len());
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(micros, Ordering::Relaxed);
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, pattern: &str) -> RouteSnapshot {
+        let bounds = BUCKETS
+            .iter()
+            .map(|bound| Some(Duration::from_micros(*bound)))
+            .chain(Some(None));
+
+        RouteSnapshot {
+            pattern: pattern.to_owned(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: Duration::from_micros(self.sum.load(Ordering::Relaxed)),
+            buckets: bounds
+                .zip(self.buckets.iter())
+                .map(|(bound, count)| (bound, count.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+}
+
+/// Future that records latency of the route if metrics are enabled.
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct Timed<F> {
+    #[pin]
+    fut: F,
+    stats: Option<(Arc<RouteStats>, Instant)>,
+}
+
+impl<F> Timed<F> {
+    pub(crate) fn new(fut: F, stats: Option<&Arc<RouteStats>>) -> Self {
+        Timed {
+            fut,
+            stats: stats.map(|stats| (stats.clone(), clock::now())),
+        }
+    }
+}
+
+impl<F: Future> Future for Timed<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(res) => {
+                if let Some((stats, start)) = this.stats.take() {
+                    stats.record(clock::now() - start);
+                }
+                Poll::Ready(res)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn test_record() {
+        let stats = RouteStats::default();
+        stats.record(Duration::from_micros(50));
+        stats.record(Duration::from_millis(3));
+        stats.record(Duration::from_secs(20));
+
+        let snapshot = stats.snapshot("/");
+        assert_eq!(snapshot.count, 3);
+        assert_eq!(snapshot.sum, Duration::from_micros(20_003_050));
+        assert_eq!(snapshot.buckets.len(), 17);
+        assert_eq!(snapshot.buckets[0], (Some(Duration::from_micros(100)), 1));
+        assert_eq!(snapshot.buckets[5], (Some(Duration::from_millis(5)), 1));
+        assert_eq!(snapshot.buckets[16], (None, 1));
+    }
+
+    #[test]
+    fn test_route_metrics() {
+        block_on(async {
+            let metrics = RouteMetrics::new();
+            let mut srv = init_service(
+                App::new()
+                    .route_metrics(metrics.clone())
+                    .route("/", web::get().to(|| HttpResponse::Ok()))
+                    .service(
+                        web::scope("/api").service(
+                            web::resource("/users/{id}")
+                                .route(web::get().to(|| HttpResponse::Ok())),
+                        ),
+                    ),
+            )
+            .await;
+
+            for path in &["/", "/api/users/1", "/api/users/2", "/missing"] {
+                let req = TestRequest::with_uri(path).to_request();
+                call_service(&mut srv, req).await;
+            }
+
+            let snapshot = metrics.snapshot();
+            assert_eq!(snapshot.len(), 2);
+            assert_eq!(snapshot[0].pattern, "/");
+            assert_eq!(snapshot[0].count, 1);
+            assert_eq!(snapshot[1].pattern, "/api/users/{id}");
+            assert_eq!(snapshot[1].count, 2);
+            assert_eq!(snapshot[1].buckets.iter().map(|(_, c)| c).sum::<u64>(), 2);
+        })
+    }
+}
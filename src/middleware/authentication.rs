@@ -0,0 +1,309 @@
+//! Middleware for request authentication
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, Ready};
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError};
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::types::auth::Authenticated;
+use crate::{HttpMessage, HttpResponse};
+
+/// `Middleware` for request authentication.
+///
+/// Middleware calls async function with the request, identity returned by
+/// the function is stored in request extensions and is available to
+/// handlers with `web::auth::Authenticated<T>` extractor. Function could
+/// use any extractor, i.e. `web::auth::Basic`, `web::auth::Jwt` or cookies,
+/// to validate credentials. Error of the function rejects the request, it
+/// is rendered as error response unless `Authentication::error_handler()`
+/// is set.
+///
+/// Middleware could be registered for the application or for a scope.
+///
+/// ```rust
+/// use actix_web::{error, middleware, web, App, Error, HttpRequest, HttpResponse};
+///
+/// struct User(String);
+///
+/// async fn api_key(req: HttpRequest) -> Result<User, Error> {
+///     match req.headers().get("x-api-key") {
+///         Some(key) if key == "secret" => Ok(User("service".to_owned())),
+///         _ => Err(error::ErrorUnauthorized("Invalid api key")),
+///     }
+/// }
+///
+/// async fn index(user: web::auth::Authenticated<User>) -> String {
+///     format!("Hello, {}!", user.0)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .route("/", web::get().to(|| HttpResponse::Ok()))
+///         .service(
+///             web::scope("/api")
+///                 .wrap(middleware::Authentication::new(api_key))
+///                 .route("/", web::get().to(index)),
+///         );
+/// }
+/// ```
+pub struct Authentication<F> {
+    inner: Rc<Inner<F>>,
+}
+
+struct Inner<F> {
+    validate: F,
+    optional: bool,
+    error_handler: Option<Box<dyn Fn(Error, &HttpRequest) -> HttpResponse>>,
+}
+
+impl<F> Authentication<F> {
+    /// Construct `Authentication` middleware with validation function.
+    pub fn new(validate: F) -> Self {
+        Authentication {
+            inner: Rc::new(Inner {
+                validate,
+                optional: false,
+                error_handler: None,
+            }),
+        }
+    }
+
+    /// Do not reject requests that are not authenticated, request is
+    /// passed to the service without identity.
+    pub fn optional(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .optional = true;
+        self
+    }
+
+    /// Set function that renders response for rejected requests.
+    pub fn error_handler<H>(mut self, handler: H) -> Self
+    where
+        H: Fn(Error, &HttpRequest) -> HttpResponse + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .error_handler = Some(Box::new(handler));
+        self
+    }
+}
+
+impl<S, B, F, Fut, T, E> Transform<S> for Authentication<F>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    F: Fn(HttpRequest) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    T: 'static,
+    E: Into<Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticationMiddleware<S, F>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuthenticationMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct AuthenticationMiddleware<S, F> {
+    service: Rc<RefCell<S>>,
+    inner: Rc<Inner<F>>,
+}
+
+impl<S, B, F, Fut, T, E> Service for AuthenticationMiddleware<S, F>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    F: Fn(HttpRequest) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    T: 'static,
+    E: Into<Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = AuthenticationFuture<S, F, Fut>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let (req, pl) = req.into_parts();
+
+        AuthenticationFuture {
+            validate: Some((self.inner.validate)(req.clone())),
+            fut: None,
+            parts: Some((req, pl)),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct AuthenticationFuture<S: Service, F, Fut> {
+    #[pin]
+    validate: Option<Fut>,
+    #[pin]
+    fut: Option<S::Future>,
+    parts: Option<(HttpRequest, Payload)>,
+    service: Rc<RefCell<S>>,
+    inner: Rc<Inner<F>>,
+}
+
+impl<S, B, F, Fut, T, E> Future for AuthenticationFuture<S, F, Fut>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    Fut: Future<Output = Result<T, E>>,
+    T: 'static,
+    E: Into<Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+            return fut.poll(cx);
+        }
+
+        let res = match this.validate.as_mut().as_pin_mut() {
+            Some(fut) => futures::ready!(fut.poll(cx)),
+            None => panic!("AuthenticationFuture polled after completion"),
+        };
+        this.validate.set(None);
+        let (req, pl) = this.parts.take().unwrap();
+
+        let identity = match res {
+            Ok(identity) => Some(identity),
+            Err(e) => {
+                let e: Error = e.into();
+                if this.inner.optional {
+                    log::debug!("Request is not authenticated: {}", e);
+                    None
+                } else if let Some(ref handler) = this.inner.error_handler {
+                    let res = handler(e, &req);
+                    return Poll::Ready(Ok(ServiceResponse::new(req, res.into_body())));
+                } else {
+                    return Poll::Ready(Err(e));
+                }
+            }
+        };
+
+        // validation function must not keep the request
+        let req = match ServiceRequest::from_parts(req, pl) {
+            Ok(req) => req,
+            Err(_) => {
+                log::error!("Request is kept by authentication function");
+                return Poll::Ready(Err(ErrorInternalServerError("Request is kept")));
+            }
+        };
+        if let Some(identity) = identity {
+            req.extensions_mut().insert(Authenticated::new(identity));
+        }
+
+        let fut = this.service.borrow_mut().call(req);
+        this.fut.set(Some(fut));
+        this.fut.as_pin_mut().unwrap().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::error::ErrorUnauthorized;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    async fn api_key(req: HttpRequest) -> Result<String, Error> {
+        match req.headers().get("x-api-key") {
+            Some(key) if key == "secret" => Ok("service".to_owned()),
+            _ => Err(ErrorUnauthorized("Invalid api key")),
+        }
+    }
+
+    async fn index(user: Option<Authenticated<String>>) -> String {
+        match user {
+            Some(user) => format!("Hello, {}!", *user),
+            None => "Hello!".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_authentication() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .service(
+                        web::scope("/api")
+                            .wrap(Authentication::new(api_key))
+                            .route("", web::get().to(index)),
+                    )
+                    .service(
+                        web::scope("/optional")
+                            .wrap(Authentication::new(api_key).optional())
+                            .route("", web::get().to(index)),
+                    )
+                    .service(
+                        web::scope("/custom")
+                            .wrap(Authentication::new(api_key).error_handler(|_, _| {
+                                HttpResponse::Forbidden().body("denied")
+                            }))
+                            .route("", web::get().to(index)),
+                    )
+                    .route("/", web::get().to(index)),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/api")
+                .header("x-api-key", "secret")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(read_body(res).await, Bytes::from_static(b"Hello, service!"));
+
+            let req = TestRequest::with_uri("/api").to_request();
+            let err = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                err.as_response_error().error_response().status(),
+                StatusCode::UNAUTHORIZED
+            );
+
+            let req = TestRequest::with_uri("/optional").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(read_body(res).await, Bytes::from_static(b"Hello!"));
+
+            let req = TestRequest::with_uri("/custom")
+                .header("x-api-key", "other")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+            assert_eq!(read_body(res).await, Bytes::from_static(b"denied"));
+
+            // handlers outside of the scope are not authenticated
+            let req = TestRequest::with_uri("/")
+                .header("x-api-key", "secret")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(read_body(res).await, Bytes::from_static(b"Hello!"));
+        })
+    }
+}
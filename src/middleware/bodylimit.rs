@@ -0,0 +1,292 @@
+//! Middleware for limiting size of request payload
+use std::cell::Cell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_http::error::PayloadError;
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+use futures::Stream;
+
+use crate::dev::Payload;
+use crate::error::{BodyLimitExceeded, Error};
+use crate::http::header::CONTENT_LENGTH;
+use crate::http::HeaderMap;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// `Middleware` for limiting size of request payload.
+///
+/// Limit is enforced before extractors of the handler run, so it applies
+/// to all handlers, including handlers that read raw `web::Payload`.
+/// Request with `Content-Length` larger than the limit is rejected with
+/// `BodyLimitExceeded` error, it is rendered as *PAYLOAD TOO LARGE*
+/// response with json body. Payload of the request is limited as well, so
+/// services that are not handlers, i.e. default service or `Files`, get
+/// `PayloadError::Overflow` once the limit is reached.
+///
+/// Middleware registered on a scope or a resource overrides limit of
+/// the application, the innermost middleware takes precedence.
+///
+/// ```rust
+/// use actix_web::{middleware, web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::BodyLimit::new(16_384))
+///         .route("/", web::post().to(|| HttpResponse::Ok()))
+///         .service(
+///             web::scope("/upload")
+///                 // uploads could be larger
+///                 .wrap(middleware::BodyLimit::new(10 * 1024 * 1024))
+///                 .route("", web::post().to(|| HttpResponse::Ok())),
+///         );
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct BodyLimit(usize);
+
+impl BodyLimit {
+    /// Construct `BodyLimit` middleware, limit is in bytes.
+    pub fn new(limit: usize) -> Self {
+        BodyLimit(limit)
+    }
+
+    /// Check `Content-Length` of the request against the limit configured
+    /// for the request, payload stream is limited by the middleware.
+    pub(crate) fn check(req: &HttpRequest) -> Result<(), BodyLimitExceeded> {
+        let limit = match req.extensions().get::<PayloadLimit>() {
+            Some(limit) => limit.0.get(),
+            None => return Ok(()),
+        };
+
+        match content_length(req.headers()) {
+            Some(length) if length > limit as u64 => {
+                Err(BodyLimitExceeded { length, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(&CONTENT_LENGTH)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse::<u64>().ok())
+}
+
+/// Limit of the request, it is shared with limited payload, so inner
+/// middleware could override limit of outer one.
+#[derive(Clone)]
+struct PayloadLimit(Rc<Cell<usize>>);
+
+impl<S, B> Transform<S> for BodyLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BodyLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(BodyLimitMiddleware {
+            service,
+            limit: *self,
+        })
+    }
+}
+
+pub struct BodyLimitMiddleware<S> {
+    service: S,
+    limit: BodyLimit,
+}
+
+impl<S, B> Service for BodyLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        // limit of outer middleware is overridden
+        let limit = req.extensions().get::<PayloadLimit>().cloned();
+        match limit {
+            Some(limit) => limit.0.set(self.limit.0),
+            None => {
+                let limit = PayloadLimit(Rc::new(Cell::new(self.limit.0)));
+                req.extensions_mut().insert(limit.clone());
+
+                let length = content_length(req.headers());
+                match req.take_payload() {
+                    Payload::None => (),
+                    payload => {
+                        req.set_payload(Payload::Stream(Box::pin(LimitedPayload {
+                            payload,
+                            limit: limit.0,
+                            length,
+                            size: 0,
+                            overflow: false,
+                        })))
+                    }
+                }
+            }
+        }
+        self.service.call(req)
+    }
+}
+
+struct LimitedPayload {
+    payload: Payload,
+    limit: Rc<Cell<usize>>,
+    length: Option<u64>,
+    size: usize,
+    overflow: bool,
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        if self.overflow {
+            return Poll::Ready(None);
+        }
+        let limit = self.limit.get();
+        if self.length.map_or(false, |length| length > limit as u64) {
+            self.overflow = true;
+            return Poll::Ready(Some(Err(PayloadError::Overflow)));
+        }
+
+        match Pin::new(&mut self.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.size += chunk.len();
+                if self.size > limit {
+                    self.overflow = true;
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn test_body_limit() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(BodyLimit::new(4))
+                    .route(
+                        "/",
+                        web::post().to(|body: Bytes| HttpResponse::Ok().body(body)),
+                    )
+                    .route(
+                        "/stream",
+                        web::post().to(|mut pl: web::Payload| async move {
+                            while let Some(chunk) = pl.next().await {
+                                chunk?;
+                            }
+                            Ok::<_, Error>(HttpResponse::Ok())
+                        }),
+                    )
+                    .service(web::scope("/upload").wrap(BodyLimit::new(16)).route(
+                        "",
+                        web::post().to(|body: Bytes| HttpResponse::Ok().body(body)),
+                    ))
+                    .default_service(|req: ServiceRequest| {
+                        let (req, mut pl) = req.into_parts();
+                        async move {
+                            let mut status = StatusCode::OK;
+                            while let Some(chunk) = pl.next().await {
+                                if chunk.is_err() {
+                                    status = StatusCode::PAYLOAD_TOO_LARGE;
+                                }
+                            }
+                            Ok::<_, Error>(ServiceResponse::new(
+                                req,
+                                HttpResponse::new(status),
+                            ))
+                        }
+                    }),
+            )
+            .await;
+
+            let req = TestRequest::post()
+                .uri("/")
+                .header(CONTENT_LENGTH, "4")
+                .set_payload("1234")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let req = TestRequest::post()
+                .uri("/")
+                .header(CONTENT_LENGTH, "8")
+                .set_payload("12345678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body = read_body(res).await;
+            assert_eq!(body, Bytes::from_static(b"{\"length\":8,\"limit\":4}"));
+
+            // payload without content-length
+            let req = TestRequest::post()
+                .uri("/stream")
+                .set_payload("12345678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let req = TestRequest::post()
+                .uri("/upload")
+                .header(CONTENT_LENGTH, "8")
+                .set_payload("12345678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            // services that are not handlers
+            let req = TestRequest::post()
+                .uri("/default")
+                .header(CONTENT_LENGTH, "8")
+                .set_payload("12345678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let req = TestRequest::post()
+                .uri("/default")
+                .set_payload("12345678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        })
+    }
+}
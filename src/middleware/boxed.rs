@@ -0,0 +1,135 @@
+//! `Middleware` with type erased service
+use std::rc::Rc;
+
+use actix_service::boxed::{self, BoxedService};
+use actix_service::{Service, Transform};
+use futures::future::{FutureExt, LocalBoxFuture};
+
+use crate::dev::{Body, ServiceRequest, ServiceResponse};
+use crate::error::Error;
+
+type BoxedHttpService<B> = BoxedService<ServiceRequest, ServiceResponse<B>, Error>;
+
+type BoxedTransform<B> =
+    dyn Fn(
+        BoxedHttpService<B>,
+    ) -> LocalBoxFuture<'static, Result<BoxedHttpService<B>, ()>>;
+
+/// `Middleware` with type erased service.
+///
+/// Middlewares registered with `App::wrap()` are composed statically and
+/// built-in middlewares do not box their response futures, though routing
+/// of the app still boxes response future of the matched service. `Boxed`
+/// is an escape hatch for middleware stacks that are assembled at runtime,
+/// response future of each boxed middleware is allocated per request.
+///
+/// ```rust
+/// use actix_web::middleware::{Boxed, DefaultHeaders, NormalizePath};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let mut stack = vec![Boxed::new(DefaultHeaders::new().header("X-Version", "0.2"))];
+///     if std::env::var("NORMALIZE_PATH").is_ok() {
+///         stack.push(Boxed::new(NormalizePath));
+///     }
+///
+///     let app = App::new()
+///         .wrap(Boxed::stack(stack))
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+pub struct Boxed<B = Body> {
+    transform: Box<BoxedTransform<B>>,
+}
+
+impl<B: 'static> Boxed<B> {
+    /// Box middleware.
+    pub fn new<T>(transform: T) -> Self
+    where
+        T: Transform<
+                BoxedHttpService<B>,
+                Request = ServiceRequest,
+                Response = ServiceResponse<B>,
+                Error = Error,
+                InitError = (),
+            > + 'static,
+        T::Transform: 'static,
+        T::Future: 'static,
+        <T::Transform as Service>::Future: 'static,
+    {
+        Boxed {
+            transform: Box::new(move |service: BoxedHttpService<B>| {
+                transform
+                    .new_transform(service)
+                    .map(|res| res.map(boxed::service))
+                    .boxed_local()
+            }),
+        }
+    }
+
+    /// Compose boxed middlewares.
+    ///
+    /// Middlewares are applied in order, same as with `App::wrap()`, last
+    /// middleware of the stack is called first.
+    pub fn stack(stack: Vec<Boxed<B>>) -> Self {
+        let stack = Rc::new(stack);
+
+        Boxed {
+            transform: Box::new(move |service: BoxedHttpService<B>| {
+                let stack = stack.clone();
+                async move {
+                    let mut service = service;
+                    for mw in stack.iter() {
+                        service = (mw.transform)(service).await?;
+                    }
+                    Ok(service)
+                }
+                .boxed_local()
+            }),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for Boxed<B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BoxedHttpService<B>;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        (self.transform)(boxed::service(service))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::middleware::DefaultHeaders;
+    use crate::test::{block_on, ok_service, TestRequest};
+
+    #[test]
+    fn test_stack() {
+        block_on(async {
+            let stack = Boxed::stack(vec![
+                Boxed::new(DefaultHeaders::new().header(CONTENT_TYPE, "0001")),
+                Boxed::new(DefaultHeaders::new().header(CONTENT_TYPE, "0002")),
+                Boxed::new(DefaultHeaders::new().header("X-Version", "0.2")),
+            ]);
+            let mut mw = stack.new_transform(ok_service()).await.unwrap();
+
+            let req = TestRequest::default().to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
+            assert_eq!(resp.headers().get("X-Version").unwrap(), "0.2");
+        })
+    }
+}
@@ -1,14 +1,14 @@
 //! `Middleware` for compressing response body.
-use std::cmp;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::str::FromStr;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
-use actix_http::body::MessageBody;
+use actix_http::body::{BodySize, MessageBody};
 use actix_http::encoding::Encoder;
-use actix_http::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use actix_http::http::header::{ContentEncoding, ACCEPT_ENCODING, CONTENT_TYPE};
+use actix_http::http::HeaderMap;
 use actix_http::{Error, Response, ResponseBuilder};
 use actix_service::{Service, Transform};
 use futures::future::{ok, Ready};
@@ -21,6 +21,11 @@ struct Enc(ContentEncoding);
 /// Helper trait that allows to set specific encoding for response.
 pub trait BodyEncoding {
     fn encoding(&mut self, encoding: ContentEncoding) -> &mut Self;
+
+    /// Disable compression of the response.
+    fn no_compress(&mut self) -> &mut Self {
+        self.encoding(ContentEncoding::Identity)
+    }
 }
 
 impl BodyEncoding for ResponseBuilder {
@@ -43,12 +48,36 @@ impl<B> BodyEncoding for Response<B> {
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// Encoding is negotiated with `AcceptedEncodings`, *NOT ACCEPTABLE*
+/// response is returned if client does not accept any encoding, including
+/// `identity`.
+///
+/// Compression level of each encoding, min size of compressed body and
+/// allowed content types are configurable. Body of unknown size is always
+/// compressed, response without `Content-Type` header is not compressed
+/// if content types are configured. Encoding that is set with
+/// `BodyEncoding` is not affected by min size and content types.
+///
+/// Content that is already compressed, i.e. images, video, audio and
+/// archives, and `text/event-stream` responses are not compressed, unless
+/// exactly same content type is added with `Compress::content_type()`.
+/// Use `BodyEncoding::no_compress()` for disabling compression of other
+/// responses.
+///
 /// ```rust
 /// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::http::ContentEncoding;
 ///
 /// fn main() {
 ///     let app = App::new()
-///         .wrap(middleware::Compress::default())
+///         .wrap(
+///             middleware::Compress::default()
+///                 .level(ContentEncoding::Gzip, 6)
+///                 .level(ContentEncoding::Br, 5)
+///                 .min_size(1024)
+///                 .content_type("text/*")
+///                 .content_type("application/json"),
+///         )
 ///         .service(
 ///             web::resource("/test")
 ///                 .route(web::get().to(|| HttpResponse::Ok()))
@@ -56,12 +85,129 @@ impl<B> BodyEncoding for Response<B> {
 ///         );
 /// }
 /// ```
-pub struct Compress(ContentEncoding);
+pub struct Compress {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    encoding: ContentEncoding,
+    levels: Vec<(ContentEncoding, u32)>,
+    min_size: u64,
+    content_types: Vec<String>,
+}
+
+impl Inner {
+    fn level(&self, encoding: ContentEncoding) -> Option<u32> {
+        self.levels
+            .iter()
+            .find(|(enc, _)| *enc == encoding)
+            .map(|(_, level)| *level)
+    }
+
+    /// Check if response matches min size and content types.
+    fn compressible<B: MessageBody>(&self, res: &Response<B>) -> bool {
+        let size = match res.body().size() {
+            BodySize::Sized(size) => Some(size as u64),
+            BodySize::Sized64(size) => Some(size),
+            _ => None,
+        };
+        if size.map(|size| size < self.min_size).unwrap_or(false) {
+            return false;
+        }
+        let mime = match res
+            .headers()
+            .get(&CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+        {
+            Some(val) => val.split(';').next().unwrap_or("").trim().to_lowercase(),
+            None => return self.content_types.is_empty(),
+        };
+
+        // exact match of configured content type overrides detection
+        if self.content_types.iter().any(|ct| *ct == mime) {
+            return true;
+        }
+        if is_precompressed(&mime) || mime == "text/event-stream" {
+            return false;
+        }
+        self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|ct| ct.ends_with("/*") && mime.starts_with(&ct[..ct.len() - 1]))
+    }
+}
+
+/// Check if content of the type is already compressed.
+fn is_precompressed(mime: &str) -> bool {
+    match mime {
+        "image/svg+xml" | "image/bmp" | "image/x-icon" | "image/vnd.microsoft.icon" => {
+            false
+        }
+        "application/zip"
+        | "application/gzip"
+        | "application/x-gzip"
+        | "application/x-bzip2"
+        | "application/x-xz"
+        | "application/x-7z-compressed"
+        | "application/x-rar-compressed"
+        | "application/vnd.rar"
+        | "application/zstd"
+        | "application/octet-stream+gzip"
+        | "font/woff"
+        | "font/woff2" => true,
+        _ => {
+            mime.starts_with("image/")
+                || mime.starts_with("video/")
+                || mime.starts_with("audio/")
+        }
+    }
+}
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress(encoding)
+        Compress {
+            inner: Rc::new(Inner {
+                encoding,
+                levels: Vec::new(),
+                min_size: 0,
+                content_types: Vec::new(),
+            }),
+        }
+    }
+
+    /// Set compression level of the encoding.
+    ///
+    /// Levels of `gzip` and `deflate` are in range 0-9, levels of `br` are
+    /// in range 0-11. By default fast compression is used.
+    pub fn level(mut self, encoding: ContentEncoding, level: u32) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        inner.levels.retain(|(enc, _)| *enc != encoding);
+        inner.levels.push((encoding, level));
+        self
+    }
+
+    /// Set min size of response body in bytes, smaller bodies are not
+    /// compressed.
+    pub fn min_size(mut self, size: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .min_size = size;
+        self
+    }
+
+    /// Add content type of responses that are compressed, i.e.
+    /// `application/json` or `text/*`.
+    ///
+    /// All responses are compressed if no content type is added.
+    pub fn content_type(mut self, mime: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .content_types
+            .push(mime.to_lowercase());
+        self
     }
 }
 
@@ -86,14 +232,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(CompressMiddleware {
             service,
-            encoding: self.0,
+            inner: self.inner.clone(),
         })
     }
 }
 
 pub struct CompressMiddleware<S> {
     service: S,
-    encoding: ContentEncoding,
+    inner: Rc<Inner>,
 }
 
 impl<S, B> Service for CompressMiddleware<S>
@@ -112,20 +258,26 @@ where
 
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
         // negotiate content-encoding
-        let encoding = if let Some(val) = req.headers().get(&ACCEPT_ENCODING) {
-            if let Ok(enc) = val.to_str() {
-                AcceptEncoding::parse(enc, self.encoding)
-            } else {
-                ContentEncoding::Identity
-            }
-        } else {
-            ContentEncoding::Identity
-        };
+        let encoding = AcceptedEncodings::from_headers(req.headers())
+            .negotiate(self.inner.encoding);
 
-        CompressResponse {
-            encoding,
-            fut: self.service.call(req),
-            _t: PhantomData,
+        match encoding {
+            Some(encoding) => CompressResponse {
+                encoding,
+                inner: self.inner.clone(),
+                fut: Some(self.service.call(req)),
+                res: None,
+                _t: PhantomData,
+            },
+            None => CompressResponse {
+                encoding: ContentEncoding::Identity,
+                inner: self.inner.clone(),
+                fut: None,
+                res: Some(
+                    req.into_response(Response::NotAcceptable().finish().into_body()),
+                ),
+                _t: PhantomData,
+            },
         }
     }
 }
@@ -138,8 +290,10 @@ where
     B: MessageBody,
 {
     #[pin]
-    fut: S::Future,
+    fut: Option<S::Future>,
     encoding: ContentEncoding,
+    inner: Rc<Inner>,
+    res: Option<ServiceResponse<Encoder<B>>>,
     _t: PhantomData<(B)>,
 }
 
@@ -153,89 +307,240 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let this = self.project();
 
-        match futures::ready!(this.fut.poll(cx)) {
+        if let Some(res) = this.res.take() {
+            return Poll::Ready(Ok(res));
+        }
+
+        match futures::ready!(this.fut.as_pin_mut().unwrap().poll(cx)) {
             Ok(resp) => {
                 let enc = if let Some(enc) = resp.response().extensions().get::<Enc>() {
                     enc.0
-                } else {
+                } else if this.inner.compressible(resp.response()) {
                     *this.encoding
+                } else {
+                    ContentEncoding::Identity
                 };
+                let level = this.inner.level(enc);
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_level(enc, level, head, body)
+                })))
             }
             Err(e) => Poll::Ready(Err(e)),
         }
     }
 }
 
-struct AcceptEncoding {
-    encoding: ContentEncoding,
-    quality: f64,
+/// Encodings accepted by the client, parsed from `Accept-Encoding` header.
+///
+/// Quality values are honored, coding that is not listed gets quality of
+/// `*`, `identity` is acceptable unless it is excluded with `q=0`, it is
+/// least preferred if it is not listed.
+///
+/// ```rust
+/// use actix_web::http::{header::ACCEPT_ENCODING, ContentEncoding};
+/// use actix_web::middleware::AcceptedEncodings;
+/// use actix_web::test::TestRequest;
+///
+/// let req = TestRequest::with_header(ACCEPT_ENCODING, "gzip;q=0.5, deflate")
+///     .to_http_request();
+/// let accepted = AcceptedEncodings::from_headers(req.headers());
+/// assert_eq!(
+///     accepted.negotiate(ContentEncoding::Gzip),
+///     Some(ContentEncoding::Gzip)
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptedEncodings {
+    /// Codings and their qualities, `None` if header is missing
+    items: Option<Vec<(String, f32)>>,
 }
 
-impl Eq for AcceptEncoding {}
+impl AcceptedEncodings {
+    /// Parse `Accept-Encoding` header value, invalid items are ignored.
+    pub fn parse(raw: &str) -> AcceptedEncodings {
+        let items = raw
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let coding = parts.next()?.trim().to_lowercase();
+                if coding.is_empty() {
+                    return None;
+                }
+                let mut quality = 1.0;
+                for param in parts {
+                    let param = param.trim();
+                    if param.starts_with("q=") || param.starts_with("Q=") {
+                        quality = param[2..]
+                            .trim()
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|q| *q >= 0.0 && *q <= 1.0)?;
+                    }
+                }
+                Some((coding, quality))
+            })
+            .collect();
+        AcceptedEncodings { items: Some(items) }
+    }
 
-impl Ord for AcceptEncoding {
-    fn cmp(&self, other: &AcceptEncoding) -> cmp::Ordering {
-        if self.quality > other.quality {
-            cmp::Ordering::Less
-        } else if self.quality < other.quality {
-            cmp::Ordering::Greater
+    /// Parse `Accept-Encoding` headers of the request.
+    pub fn from_headers(headers: &HeaderMap) -> AcceptedEncodings {
+        let values: Vec<_> = headers
+            .get_all(&ACCEPT_ENCODING)
+            .filter_map(|val| val.to_str().ok())
+            .collect();
+        if values.is_empty() {
+            AcceptedEncodings { items: None }
         } else {
-            cmp::Ordering::Equal
+            AcceptedEncodings::parse(&values.join(","))
         }
     }
-}
 
-impl PartialOrd for AcceptEncoding {
-    fn partial_cmp(&self, other: &AcceptEncoding) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+    /// Quality of the encoding, zero if encoding is not acceptable.
+    pub fn quality(&self, encoding: ContentEncoding) -> f32 {
+        if self.items.is_none() {
+            return 1.0;
+        }
+        let name = encoding.as_str();
+        self.listed(name)
+            .unwrap_or(if name == "identity" { 1.0 } else { 0.0 })
     }
-}
 
-impl PartialEq for AcceptEncoding {
-    fn eq(&self, other: &AcceptEncoding) -> bool {
-        self.quality == other.quality
+    /// Quality of the coding, either listed or of `*`.
+    fn listed(&self, name: &str) -> Option<f32> {
+        let items = self.items.as_ref()?;
+        items
+            .iter()
+            .find(|(coding, _)| coding == name)
+            .or_else(|| items.iter().find(|(coding, _)| coding == "*"))
+            .map(|(_, q)| *q)
     }
-}
 
-impl AcceptEncoding {
-    fn new(tag: &str) -> Option<AcceptEncoding> {
-        let parts: Vec<&str> = tag.split(';').collect();
-        let encoding = match parts.len() {
-            0 => return None,
-            _ => ContentEncoding::from(parts[0]),
-        };
-        let quality = match parts.len() {
-            1 => encoding.quality(),
-            _ => match f64::from_str(parts[1]) {
-                Ok(q) => q,
-                Err(_) => 0.0,
-            },
+    /// Select encoding of the response.
+    ///
+    /// Supported encoding with the highest quality is selected for
+    /// `ContentEncoding::Auto`, otherwise preferred encoding is selected if it
+    /// is acceptable. `Identity` is selected if it is listed with higher
+    /// quality or no compression is acceptable. Without `Accept-Encoding`
+    /// header response is not compressed. `None` is returned if no encoding
+    /// is acceptable, so *NOT ACCEPTABLE* response should be sent.
+    pub fn negotiate(&self, preferred: ContentEncoding) -> Option<ContentEncoding> {
+        if self.items.is_none() {
+            return Some(ContentEncoding::Identity);
+        }
+        let candidates: &[ContentEncoding] = match preferred {
+            ContentEncoding::Auto => &[
+                ContentEncoding::Br,
+                ContentEncoding::Gzip,
+                ContentEncoding::Deflate,
+            ],
+            ContentEncoding::Identity => &[],
+            ref enc => std::slice::from_ref(enc),
         };
-        Some(AcceptEncoding { encoding, quality })
-    }
 
-    /// Parse a raw Accept-Encoding header value into an ordered list.
-    pub fn parse(raw: &str, encoding: ContentEncoding) -> ContentEncoding {
-        let mut encodings: Vec<_> = raw
-            .replace(' ', "")
-            .split(',')
-            .map(|l| AcceptEncoding::new(l))
-            .collect();
-        encodings.sort();
-
-        for enc in encodings {
-            if let Some(enc) = enc {
-                if encoding == ContentEncoding::Auto {
-                    return enc.encoding;
-                } else if encoding == enc.encoding {
-                    return encoding;
-                }
+        let mut best = None;
+        let mut best_quality = 0.0;
+        for enc in candidates.iter().filter(|enc| is_supported(**enc)) {
+            let quality = self.quality(*enc);
+            if quality > best_quality {
+                best = Some(*enc);
+                best_quality = quality;
             }
         }
-        ContentEncoding::Identity
+
+        // identity that is not listed is acceptable, but least preferred
+        let identity = self.listed("identity");
+        match best {
+            Some(enc) if identity.map(|q| best_quality >= q).unwrap_or(true) => {
+                Some(enc)
+            }
+            _ if identity.map(|q| q > 0.0).unwrap_or(true) => {
+                Some(ContentEncoding::Identity)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Check if encoder of the encoding is available.
+fn is_supported(encoding: ContentEncoding) -> bool {
+    match encoding {
+        ContentEncoding::Br => cfg!(feature = "brotli"),
+        ContentEncoding::Gzip | ContentEncoding::Deflate => {
+            cfg!(any(feature = "flate2-zlib", feature = "flate2-rust"))
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn test_negotiate() {
+        let accepted = AcceptedEncodings::parse("gzip;q=0.5, br;q=0.8, identity;q=0.1");
+        assert_eq!(accepted.quality(ContentEncoding::Gzip), 0.5);
+        assert_eq!(accepted.quality(ContentEncoding::Deflate), 0.0);
+        assert_eq!(
+            accepted.negotiate(ContentEncoding::Auto),
+            Some(ContentEncoding::Br)
+        );
+        assert_eq!(
+            accepted.negotiate(ContentEncoding::Deflate),
+            Some(ContentEncoding::Identity)
+        );
+
+        let accepted = AcceptedEncodings::parse("gzip;q=0.5, identity");
+        assert_eq!(
+            accepted.negotiate(ContentEncoding::Gzip),
+            Some(ContentEncoding::Identity)
+        );
+
+        let accepted = AcceptedEncodings::parse("*;q=0.5, gzip;q=0");
+        assert_eq!(
+            accepted.negotiate(ContentEncoding::Auto),
+            Some(ContentEncoding::Br)
+        );
+        assert_eq!(accepted.quality(ContentEncoding::Identity), 0.5);
+
+        let accepted = AcceptedEncodings::parse("gzip;q=0, identity;q=0");
+        assert_eq!(accepted.negotiate(ContentEncoding::Gzip), None);
+        assert_eq!(accepted.negotiate(ContentEncoding::Identity), None);
+
+        let accepted = AcceptedEncodings::parse("deflate;q=abc, *;q=0");
+        assert_eq!(accepted.negotiate(ContentEncoding::Auto), None);
+
+        let accepted = AcceptedEncodings::from_headers(&HeaderMap::new());
+        assert_eq!(
+            accepted.negotiate(ContentEncoding::Auto),
+            Some(ContentEncoding::Identity)
+        );
+    }
+
+    #[test]
+    fn test_not_acceptable() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(Compress::default())
+                    .route("/", web::get().to(|| HttpResponse::Ok().body("test"))),
+            )
+            .await;
+
+            let req =
+                TestRequest::with_header(ACCEPT_ENCODING, "compress, identity;q=0")
+                    .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+
+            let req = TestRequest::with_header(ACCEPT_ENCODING, "compress").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        })
     }
 }
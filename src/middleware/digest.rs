@@ -0,0 +1,378 @@
+//! Verification of request body digest and digest of response body.
+//!
+//! Requires `digest` feature.
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_http::error::PayloadError;
+use actix_http::ResponseBuilder;
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures::future::{err, ok, Either, Ready};
+use futures::Stream;
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::dev::{Payload, ServiceRequest, ServiceResponse};
+use crate::error::{Error, ErrorBadRequest};
+use crate::http::header::HeaderValue;
+use crate::http::HeaderMap;
+use crate::{HttpMessage, HttpResponse};
+
+/// Name of `Digest` header
+pub const DIGEST: &str = "digest";
+
+/// Name of `Content-MD5` header
+pub const CONTENT_MD5: &str = "content-md5";
+
+/// Digest algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// `MD5`
+    Md5,
+    /// `SHA-256`
+    Sha256,
+    /// `SHA-512`
+    Sha512,
+}
+
+impl Algorithm {
+    /// Name of the algorithm used in `Digest` header.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+        }
+    }
+
+    /// Algorithm by name, case insensitive.
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        if name.eq_ignore_ascii_case("md5") {
+            Some(Algorithm::Md5)
+        } else if name.eq_ignore_ascii_case("sha-256") {
+            Some(Algorithm::Sha256)
+        } else if name.eq_ignore_ascii_case("sha-512") {
+            Some(Algorithm::Sha512)
+        } else {
+            None
+        }
+    }
+
+    /// Base64 encoded digest of `data`.
+    pub fn compute(self, data: &[u8]) -> String {
+        let mut hasher = Hasher::new(self);
+        hasher.update(data);
+        base64::encode(&hasher.finish())
+    }
+
+    /// Value of `Digest` header for `data`, i.e. `SHA-256=X48E9q...`.
+    pub fn header_value(self, data: &[u8]) -> HeaderValue {
+        let value = format!("{}={}", self.name(), self.compute(data));
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(h) => h.input(data),
+            Hasher::Sha256(h) => h.input(data),
+            Hasher::Sha512(h) => h.input(data),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(h) => h.result().to_vec(),
+            Hasher::Sha256(h) => h.result().to_vec(),
+            Hasher::Sha512(h) => h.result().to_vec(),
+        }
+    }
+}
+
+/// Helper trait for setting body of the response along with its `Digest`
+/// header.
+///
+/// ```rust
+/// use actix_web::middleware::digest::{Algorithm, DigestBody};
+/// use actix_web::HttpResponse;
+///
+/// fn index() -> HttpResponse {
+///     HttpResponse::Ok().digest_body(Algorithm::Sha256, "data")
+/// }
+/// ```
+pub trait DigestBody {
+    /// Set body and `Digest` header of the response
+    fn digest_body<B: Into<Bytes>>(
+        &mut self,
+        algorithm: Algorithm,
+        body: B,
+    ) -> HttpResponse;
+}
+
+impl DigestBody for ResponseBuilder {
+    fn digest_body<B: Into<Bytes>>(
+        &mut self,
+        algorithm: Algorithm,
+        body: B,
+    ) -> HttpResponse {
+        let body = body.into();
+        self.header(DIGEST, algorithm.header_value(&body))
+            .body(body)
+    }
+}
+
+/// Expected digests of the request body.
+fn expected_digests(headers: &HeaderMap) -> Result<Vec<(Algorithm, Vec<u8>)>, Error> {
+    let mut digests = Vec::new();
+
+    for value in headers.get_all(DIGEST) {
+        let value = value
+            .to_str()
+            .map_err(|_| ErrorBadRequest("Invalid Digest header"))?;
+        for item in value.split(',') {
+            let mut parts = item.trim().splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            // unsupported algorithms are ignored
+            if let Some(algorithm) = Algorithm::from_name(name) {
+                let hash = parts
+                    .next()
+                    .and_then(|val| base64::decode(val.trim()).ok())
+                    .ok_or_else(|| ErrorBadRequest("Invalid Digest header"))?;
+                digests.push((algorithm, hash));
+            }
+        }
+    }
+
+    if let Some(value) = headers.get(CONTENT_MD5) {
+        let hash = value
+            .to_str()
+            .ok()
+            .and_then(|val| base64::decode(val.trim()).ok())
+            .ok_or_else(|| ErrorBadRequest("Invalid Content-MD5 header"))?;
+        digests.push((Algorithm::Md5, hash));
+    }
+
+    Ok(digests)
+}
+
+/// `Middleware` for verifying `Digest` and `Content-MD5` headers of
+/// requests.
+///
+/// Digest is computed while payload is read, payload stream returns
+/// an error instead of the end of the payload if digest does not match,
+/// so extractors fail with *BAD REQUEST* response. Handlers that stream
+/// the payload have to treat data as unverified until payload is read
+/// completely. `MD5`, `SHA-256` and `SHA-512` algorithms are supported,
+/// other algorithms in `Digest` header are ignored.
+///
+/// ```rust
+/// use actix_web::middleware::digest::VerifyDigest;
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(VerifyDigest::new().required(true))
+///         .route("/upload", web::put().to(|_: web::Bytes| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct VerifyDigest {
+    required: bool,
+}
+
+impl VerifyDigest {
+    /// Construct `VerifyDigest` middleware, requests without digest are
+    /// accepted.
+    pub fn new() -> VerifyDigest {
+        VerifyDigest::default()
+    }
+
+    /// Reject requests without supported digest with *BAD REQUEST*
+    /// response.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+}
+
+impl<S, B> Transform<S> for VerifyDigest
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = VerifyDigestMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(VerifyDigestMiddleware {
+            service,
+            required: self.required,
+        })
+    }
+}
+
+pub struct VerifyDigestMiddleware<S> {
+    service: S,
+    required: bool,
+}
+
+impl<S, B> Service for VerifyDigestMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let digests = match expected_digests(req.headers()) {
+            Ok(digests) => digests,
+            Err(e) => return Either::Right(err(e)),
+        };
+        if digests.is_empty() {
+            if self.required {
+                return Either::Right(err(ErrorBadRequest("Digest is required")));
+            }
+            return Either::Left(self.service.call(req));
+        }
+
+        let payload = req.take_payload();
+        req.set_payload(Payload::Stream(Box::pin(DigestPayload {
+            payload,
+            digests: digests
+                .into_iter()
+                .map(|(algorithm, hash)| (Hasher::new(algorithm), hash))
+                .collect(),
+        })));
+        Either::Left(self.service.call(req))
+    }
+}
+
+struct DigestPayload {
+    payload: Payload,
+    digests: Vec<(Hasher, Vec<u8>)>,
+}
+
+impl Stream for DigestPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                for (hasher, _) in self.digests.iter_mut() {
+                    hasher.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                let digests = std::mem::replace(&mut self.digests, Vec::new());
+                for (hasher, expected) in digests {
+                    if hasher.finish() != expected {
+                        return Poll::Ready(Some(Err(PayloadError::Io(
+                            io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "Digest of the payload does not match",
+                            ),
+                        ))));
+                    }
+                }
+                Poll::Ready(None)
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    const HELLO_SHA256: &str = "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+    const HELLO_MD5: &str = "XUFAKrxLKna5cZ2REBfFkg==";
+
+    #[test]
+    fn test_compute() {
+        assert_eq!(Algorithm::Sha256.compute(b"hello"), HELLO_SHA256);
+        assert_eq!(Algorithm::Md5.compute(b"hello"), HELLO_MD5);
+        assert_eq!(Algorithm::from_name("sha-256"), Some(Algorithm::Sha256));
+        assert_eq!(Algorithm::from_name("UNIXsum"), None);
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        block_on(async {
+            let mut srv =
+                init_service(App::new().wrap(VerifyDigest::new().required(true)).route(
+                    "/",
+                    web::put().to(|body: Bytes| {
+                        HttpResponse::Ok().digest_body(Algorithm::Md5, body)
+                    }),
+                ))
+                .await;
+
+            let req = TestRequest::put()
+                .header(DIGEST, format!("UNIXsum=30637, SHA-256={}", HELLO_SHA256))
+                .set_payload("hello")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get(DIGEST).unwrap(),
+                &format!("MD5={}", HELLO_MD5)
+            );
+            assert_eq!(read_body(res).await, Bytes::from_static(b"hello"));
+
+            let req = TestRequest::put()
+                .header(CONTENT_MD5, HELLO_MD5)
+                .set_payload("hello")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let req = TestRequest::put()
+                .header(CONTENT_MD5, HELLO_MD5)
+                .set_payload("hello!")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+            let req = TestRequest::put().set_payload("hello").to_request();
+            let res = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                res.as_response_error().error_response().status(),
+                StatusCode::BAD_REQUEST
+            );
+        })
+    }
+}
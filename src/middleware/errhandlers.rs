@@ -1,9 +1,11 @@
 //! Custom handlers service for responses.
+use std::future::Future;
+use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use actix_service::{Service, Transform};
-use futures::future::{err, ok, Either, Future, FutureExt, LocalBoxFuture, Ready};
+use futures::future::{ok, LocalBoxFuture, Ready};
 use hashbrown::HashMap;
 
 use crate::dev::{ServiceRequest, ServiceResponse};
@@ -84,8 +86,6 @@ impl<B> ErrorHandlers<B> {
 impl<S, B> Transform<S> for ErrorHandlers<B>
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
@@ -111,36 +111,61 @@ pub struct ErrorHandlersMiddleware<S, B> {
 impl<S, B> Service for ErrorHandlersMiddleware<S, B>
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
     type Error = Error;
-    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Future = ErrorHandlersFuture<S, B>;
 
     fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
         self.service.poll_ready(cx)
     }
 
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
-        let handlers = self.handlers.clone();
-        let fut = self.service.call(req);
+        ErrorHandlersFuture {
+            fut: self.service.call(req),
+            handlers: self.handlers.clone(),
+            handler_fut: None,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct ErrorHandlersFuture<S: Service, B> {
+    #[pin]
+    fut: S::Future,
+    handlers: Rc<HashMap<StatusCode, Box<ErrorHandler<B>>>>,
+    handler_fut: Option<LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>>,
+}
 
-        async move {
-            let res = fut.await?;
+impl<S, B> Future for ErrorHandlersFuture<S, B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(fut) = this.handler_fut {
+            return fut.as_mut().poll(cx);
+        }
 
-            if let Some(handler) = handlers.get(&res.status()) {
-                match handler(res) {
-                    Ok(ErrorHandlerResponse::Response(res)) => Ok(res),
-                    Ok(ErrorHandlerResponse::Future(fut)) => fut.await,
-                    Err(e) => Err(e),
+        let res = futures::ready!(this.fut.poll(cx))?;
+        if let Some(handler) = this.handlers.get(&res.status()) {
+            match handler(res) {
+                Ok(ErrorHandlerResponse::Response(res)) => Poll::Ready(Ok(res)),
+                Ok(ErrorHandlerResponse::Future(mut fut)) => {
+                    let poll = fut.as_mut().poll(cx);
+                    *this.handler_fut = Some(fut);
+                    poll
                 }
-            } else {
-                Ok(res)
+                Err(e) => Poll::Ready(Err(e)),
             }
+        } else {
+            Poll::Ready(Ok(res))
         }
-            .boxed_local()
     }
 }
 
@@ -0,0 +1,459 @@
+//! One-shot flash messages.
+//!
+//! `Flash` middleware keeps messages of a response in a signed cookie and
+//! passes them to the next request, i.e. to a page that form handler
+//! redirects to. Messages are available with `FlashMessages` extractor,
+//! extracted messages are removed from the cookie.
+//!
+//! Requires `secure-cookies` feature.
+//!
+//! ```rust
+//! use actix_web::middleware::flash::{Flash, FlashMessages};
+//! use actix_web::{http, web, App, HttpResponse};
+//!
+//! async fn form(flash: FlashMessages) -> String {
+//!     flash
+//!         .iter()
+//!         .map(|msg| format!("{}: {}\n", msg.level(), msg.content()))
+//!         .collect()
+//! }
+//!
+//! async fn submit(flash: FlashMessages) -> HttpResponse {
+//!     flash.success("Saved");
+//!     HttpResponse::SeeOther()
+//!         .header(http::header::LOCATION, "/form")
+//!         .finish()
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .wrap(Flash::new(&[0; 32]))
+//!         .route("/form", web::get().to(form))
+//!         .route("/form", web::post().to(submit));
+//! }
+//! ```
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, Ready};
+use serde::{Deserialize, Serialize};
+
+use crate::cookie::{Cookie, CookieJar, Key};
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError};
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// Level of flash message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// `debug` level
+    Debug,
+    /// `info` level
+    Info,
+    /// `success` level
+    Success,
+    /// `warning` level
+    Warning,
+    /// `error` level
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Flash message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    level: Level,
+    content: String,
+}
+
+impl FlashMessage {
+    /// Construct flash message.
+    pub fn new<S: Into<String>>(level: Level, content: S) -> Self {
+        FlashMessage {
+            level,
+            content: content.into(),
+        }
+    }
+
+    /// Level of the message
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Content of the message
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+#[derive(Default)]
+struct FlashState {
+    incoming: Vec<FlashMessage>,
+    consumed: bool,
+    outgoing: Vec<FlashMessage>,
+}
+
+/// Flash messages of the request.
+///
+/// Extraction consumes incoming messages, they are not passed to next
+/// requests. Messages added with `FlashMessages::add()` are sent to the
+/// next request. Extractor fails if `Flash` middleware is not registered.
+pub struct FlashMessages {
+    req: HttpRequest,
+    incoming: Vec<FlashMessage>,
+}
+
+impl FlashMessages {
+    /// Iterate over incoming messages.
+    pub fn iter(&self) -> std::slice::Iter<FlashMessage> {
+        self.incoming.iter()
+    }
+
+    /// Number of incoming messages
+    pub fn len(&self) -> usize {
+        self.incoming.len()
+    }
+
+    /// Check if there are no incoming messages
+    pub fn is_empty(&self) -> bool {
+        self.incoming.is_empty()
+    }
+
+    /// Add message for the next request.
+    pub fn add(&self, msg: FlashMessage) {
+        if let Some(state) = self.req.extensions_mut().get_mut::<FlashState>() {
+            state.outgoing.push(msg);
+        }
+    }
+
+    /// Add message with `Info` level.
+    pub fn info<S: Into<String>>(&self, content: S) {
+        self.add(FlashMessage::new(Level::Info, content))
+    }
+
+    /// Add message with `Success` level.
+    pub fn success<S: Into<String>>(&self, content: S) {
+        self.add(FlashMessage::new(Level::Success, content))
+    }
+
+    /// Add message with `Warning` level.
+    pub fn warning<S: Into<String>>(&self, content: S) {
+        self.add(FlashMessage::new(Level::Warning, content))
+    }
+
+    /// Add message with `Error` level.
+    pub fn error<S: Into<String>>(&self, content: S) {
+        self.add(FlashMessage::new(Level::Error, content))
+    }
+}
+
+impl<'a> IntoIterator for &'a FlashMessages {
+    type Item = &'a FlashMessage;
+    type IntoIter = std::slice::Iter<'a, FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.incoming.iter()
+    }
+}
+
+impl fmt::Debug for FlashMessages {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.incoming.iter()).finish()
+    }
+}
+
+impl FromRequest for FlashMessages {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<FlashMessages, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let incoming = match req.extensions_mut().get_mut::<FlashState>() {
+            Some(state) => {
+                state.consumed = true;
+                state.incoming.clone()
+            }
+            None => {
+                log::error!("Flash middleware is not registered");
+                return err(ErrorInternalServerError(
+                    "Flash middleware is not registered",
+                ));
+            }
+        };
+        ok(FlashMessages {
+            req: req.clone(),
+            incoming,
+        })
+    }
+}
+
+/// `Middleware` for flash messages.
+///
+/// Messages are kept in a cookie signed with the key, key must be at least
+/// 32 bytes long.
+pub struct Flash {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    key: Key,
+    name: String,
+    path: String,
+    secure: bool,
+}
+
+impl Flash {
+    /// Construct `Flash` middleware with signing key.
+    ///
+    /// Panics if key length is less than 32 bytes.
+    pub fn new(key: &[u8]) -> Flash {
+        Flash {
+            inner: Rc::new(Inner {
+                key: Key::from_master(key),
+                name: "actix-flash".to_owned(),
+                path: "/".to_owned(),
+                secure: false,
+            }),
+        }
+    }
+
+    /// Set name of the cookie, by default it is `actix-flash`.
+    pub fn name(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .name = name.to_owned();
+        self
+    }
+
+    /// Set path of the cookie.
+    pub fn path(mut self, path: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .path = path.to_owned();
+        self
+    }
+
+    /// Send cookie only over secure connections.
+    pub fn secure(mut self, secure: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .secure = secure;
+        self
+    }
+}
+
+impl Inner {
+    fn load(&self, req: &ServiceRequest) -> Vec<FlashMessage> {
+        req.signed_cookie(&self.name, &self.key)
+            .and_then(|cookie| {
+                base64::decode_config(cookie.value(), base64::URL_SAFE_NO_PAD).ok()
+            })
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn store<B>(
+        &self,
+        res: &mut ServiceResponse<B>,
+        msgs: &[FlashMessage],
+    ) -> Result<(), Error> {
+        let value = if msgs.is_empty() {
+            String::new()
+        } else {
+            let data = serde_json::to_vec(msgs).map_err(ErrorInternalServerError)?;
+            base64::encode_config(&data, base64::URL_SAFE_NO_PAD)
+        };
+        let cookie = Cookie::build(self.name.clone(), value)
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(true)
+            .finish();
+
+        let mut jar = CookieJar::new();
+        if msgs.is_empty() {
+            jar.add_original(cookie.clone());
+            jar.remove(cookie);
+        } else {
+            jar.signed(&self.key).add(cookie);
+        }
+        for cookie in jar.delta() {
+            res.response_mut().add_cookie(cookie)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S, B> Transform<S> for Flash
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = FlashMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(FlashMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct FlashMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for FlashMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = FlashFuture<S, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let incoming = self.inner.load(&req);
+        req.extensions_mut().insert(FlashState {
+            incoming,
+            ..Default::default()
+        });
+
+        FlashFuture {
+            fut: self.service.call(req),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct FlashFuture<S: Service, B> {
+    #[pin]
+    fut: S::Future,
+    inner: Rc<Inner>,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> Future for FlashFuture<S, B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+
+        let state = res.request().extensions_mut().remove::<FlashState>();
+        if let Some(state) = state {
+            // unread messages are kept for the next request
+            let msgs = if !state.outgoing.is_empty() {
+                Some(state.outgoing)
+            } else if state.consumed && !state.incoming.is_empty() {
+                Some(Vec::new())
+            } else {
+                None
+            };
+            if let Some(msgs) = msgs {
+                if let Err(e) = this.inner.store(&mut res, &msgs) {
+                    return Poll::Ready(Ok(res.error_response(e)));
+                }
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn test_flash() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(Flash::new(&[0; 32]))
+                    .route(
+                        "/",
+                        web::get().to(|flash: FlashMessages| {
+                            flash
+                                .iter()
+                                .map(|msg| format!("{}:{};", msg.level(), msg.content()))
+                                .collect::<String>()
+                        }),
+                    )
+                    .route(
+                        "/",
+                        web::post().to(|flash: FlashMessages| {
+                            flash.success("saved");
+                            flash.warning("check");
+                            HttpResponse::SeeOther().finish()
+                        }),
+                    )
+                    .route("/other", web::get().to(|| HttpResponse::Ok())),
+            )
+            .await;
+
+            let req = TestRequest::post().uri("/").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::SEE_OTHER);
+            let cookie = res.response().cookies().next().unwrap().into_owned();
+
+            // messages are kept until they are read
+            let req = TestRequest::with_uri("/other")
+                .cookie(cookie.clone())
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert!(res.response().cookies().next().is_none());
+
+            let req = TestRequest::with_uri("/").cookie(cookie).to_request();
+            let res = call_service(&mut srv, req).await;
+            let removed = res.response().cookies().next().unwrap().into_owned();
+            assert_eq!(removed.value(), "");
+            assert_eq!(read_body(res).await, "success:saved;warning:check;");
+
+            // tampered cookie is ignored
+            let req = TestRequest::with_uri("/")
+                .cookie(Cookie::new("actix-flash", "W10"))
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(read_body(res).await, "");
+        })
+    }
+}
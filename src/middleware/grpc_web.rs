@@ -0,0 +1,740 @@
+//! gRPC-web translation middleware.
+//!
+//! The same handlers serve gRPC-web requests of browsers and native gRPC
+//! requests, so gRPC services can be mounted next to REST routes and
+//! dispatched by content type. Native gRPC requires HTTP/2, either
+//! negotiated with ALPN on a TLS listener or with prior knowledge.
+//!
+//! Requires `grpc-web` feature.
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_http::body::{BodySize, MessageBody, ResponseBody};
+use actix_http::error::PayloadError;
+use actix_service::{Service, Transform};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{ok, Ready};
+use futures::Stream;
+
+use crate::dev::{Payload, RequestHead, ServiceRequest, ServiceResponse};
+use crate::error::Error;
+use crate::guard::Guard;
+use crate::http::header::{
+    HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_METHOD, CONTENT_LENGTH, CONTENT_TYPE, ORIGIN, VARY,
+};
+use crate::http::{Method, StatusCode};
+use crate::{HttpMessage, HttpResponse};
+
+/// Name of `grpc-status` header
+pub const GRPC_STATUS: &str = "grpc-status";
+
+/// Name of `grpc-message` header
+pub const GRPC_MESSAGE: &str = "grpc-message";
+
+const GRPC_STATUS_DETAILS: &str = "grpc-status-details-bin";
+
+const ALLOW_HEADERS: &str =
+    "content-type, x-grpc-web, x-user-agent, grpc-timeout, authorization";
+const EXPOSE_HEADERS: &str = "grpc-status, grpc-message, grpc-status-details-bin";
+
+/// Flag of the frame with trailers
+const TRAILERS_FLAG: u8 = 0x80;
+
+/// Length-prefixed gRPC message frame.
+pub fn encode_message(data: &[u8]) -> Bytes {
+    encode_frame(0, data)
+}
+
+fn encode_frame(flag: u8, data: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(data.len() + 5);
+    buf.put_u8(flag);
+    buf.put_u32_be(data.len() as u32);
+    buf.put_slice(data);
+    buf.freeze()
+}
+
+/// Guard that matches gRPC-web requests.
+///
+/// ```rust
+/// use actix_web::middleware::grpc_web::GrpcWebGuard;
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .route("/", web::post().guard(GrpcWebGuard).to(|| HttpResponse::Ok()))
+///         .route("/", web::post().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+pub struct GrpcWebGuard;
+
+impl Guard for GrpcWebGuard {
+    fn check(&self, req: &RequestHead) -> bool {
+        req.headers
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .and_then(Mode::from_content_type)
+            .map(|mode| mode != Mode::Grpc)
+            .unwrap_or(false)
+    }
+}
+
+/// Guard that matches native gRPC and gRPC-web requests.
+///
+/// ```rust
+/// use actix_web::middleware::grpc_web::{GrpcGuard, GrpcWeb};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(GrpcWeb::new())
+///         .route("/", web::post().guard(GrpcGuard).to(|| HttpResponse::Ok()))
+///         .route("/", web::post().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+pub struct GrpcGuard;
+
+impl Guard for GrpcGuard {
+    fn check(&self, req: &RequestHead) -> bool {
+        req.headers
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .map(|val| Mode::from_content_type(val).is_some())
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Grpc,
+    Binary,
+    Text,
+}
+
+impl Mode {
+    fn from_content_type(ct: &str) -> Option<Mode> {
+        if ct.starts_with("application/grpc-web-text") {
+            Some(Mode::Text)
+        } else if ct.starts_with("application/grpc-web") {
+            Some(Mode::Binary)
+        } else if ct.starts_with("application/grpc") {
+            Some(Mode::Grpc)
+        } else {
+            None
+        }
+    }
+}
+
+/// `Middleware` for translating gRPC-web requests to gRPC.
+///
+/// Request content type `application/grpc-web[-text][+format]` is replaced
+/// with `application/grpc[+format]`, base64 encoded body of `-text` requests
+/// is decoded. Handlers respond with gRPC frames and set `grpc-status` and
+/// `grpc-message` headers, middleware moves them to the trailers frame at
+/// the end of the response body. Responses without `grpc-status` header get
+/// status derived from the response status.
+///
+/// Native gRPC requests with `application/grpc[+format]` content type are
+/// passed through unchanged, `grpc-*` headers of the response are sent as
+/// HTTP/2 trailers.
+///
+/// CORS preflight requests are answered by the middleware, any origin is
+/// allowed unless origins are listed with `GrpcWeb::allowed_origin()`.
+///
+/// ```rust
+/// use actix_web::middleware::grpc_web::{encode_message, GrpcWeb};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/helloworld.Greeter")
+///             .wrap(GrpcWeb::new().allowed_origin("https://example.com"))
+///             .route(
+///                 "/SayHello",
+///                 web::post().to(|| {
+///                     HttpResponse::Ok()
+///                         .content_type("application/grpc+proto")
+///                         .header("grpc-status", "0")
+///                         .body(encode_message(b"\x0a\x05hello"))
+///                 }),
+///             ),
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct GrpcWeb {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    origins: Vec<String>,
+}
+
+impl Inner {
+    fn allowed_origin(&self, headers: &HeaderMap) -> Option<HeaderValue> {
+        let origin = headers.get(ORIGIN)?;
+        if self.origins.is_empty() || self.origins.iter().any(|o| origin == o.as_str()) {
+            Some(origin.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl GrpcWeb {
+    /// Construct `GrpcWeb` middleware, requests of any origin are allowed.
+    pub fn new() -> GrpcWeb {
+        GrpcWeb::default()
+    }
+
+    /// Add allowed origin of cross-origin requests.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .origins
+            .push(origin.to_owned());
+        self
+    }
+}
+
+impl<S, B> Transform<S> for GrpcWeb
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<GrpcWebBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = GrpcWebMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(GrpcWebMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct GrpcWebMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for GrpcWebMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<GrpcWebBody<B>>;
+    type Error = Error;
+    type Future = GrpcWebFuture<S, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let origin = self.inner.allowed_origin(req.headers());
+
+        // preflight request
+        if req.method() == Method::OPTIONS
+            && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let mut res = HttpResponse::NoContent();
+            if let Some(origin) = origin {
+                res.header(ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+                    .header(ACCESS_CONTROL_ALLOW_METHODS, "POST, OPTIONS")
+                    .header(ACCESS_CONTROL_ALLOW_HEADERS, ALLOW_HEADERS)
+                    .header(ACCESS_CONTROL_EXPOSE_HEADERS, EXPOSE_HEADERS)
+                    .header(ACCESS_CONTROL_MAX_AGE, "86400");
+            }
+            let res = res.header(VARY, "Origin").finish().into_body();
+            return GrpcWebFuture {
+                fut: None,
+                res: Some(req.into_response(res)),
+                mode: None,
+                origin: None,
+            };
+        }
+
+        let mode = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .and_then(Mode::from_content_type);
+
+        // application/grpc-web-text+proto -> application/grpc+proto
+        if let Some(mode) = mode {
+            let ct = req.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap();
+            let ct = match mode {
+                Mode::Text => {
+                    Some(ct.replacen("application/grpc-web-text", "application/grpc", 1))
+                }
+                Mode::Binary => {
+                    Some(ct.replacen("application/grpc-web", "application/grpc", 1))
+                }
+                Mode::Grpc => None,
+            };
+            if let Some(ct) = ct {
+                let head = req.head_mut();
+                head.headers
+                    .insert(CONTENT_TYPE, HeaderValue::from_str(&ct).unwrap());
+            }
+            if mode == Mode::Text {
+                req.head_mut().headers.remove(CONTENT_LENGTH);
+                let payload = req.take_payload();
+                req.set_payload(Payload::Stream(Box::pin(TextPayload {
+                    payload,
+                    buf: BytesMut::new(),
+                })));
+            }
+        }
+
+        GrpcWebFuture {
+            fut: Some(self.service.call(req)),
+            res: None,
+            mode,
+            origin,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct GrpcWebFuture<S: Service, B> {
+    #[pin]
+    fut: Option<S::Future>,
+    res: Option<ServiceResponse<B>>,
+    mode: Option<Mode>,
+    origin: Option<HeaderValue>,
+}
+
+impl<S, B> Future for GrpcWebFuture<S, B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Output = Result<ServiceResponse<GrpcWebBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = match this.res.take() {
+            Some(res) => res,
+            None => futures::ready!(this.fut.as_pin_mut().unwrap().poll(cx))?,
+        };
+        let res = match *this.mode {
+            None => res
+                .map_body(|_, body| ResponseBody::Body(GrpcWebBody::passthrough(body))),
+            Some(Mode::Grpc) => grpc_response(res),
+            Some(mode) => grpc_web_response(res, mode, this.origin.take()),
+        };
+        Poll::Ready(Ok(res))
+    }
+}
+
+/// Native gRPC response, status is sent with HTTP trailers.
+fn grpc_response<B>(mut res: ServiceResponse<B>) -> ServiceResponse<GrpcWebBody<B>> {
+    let trailers = take_trailers(&mut res);
+    res.headers_mut().remove(CONTENT_LENGTH);
+    *res.response_mut().status_mut() = StatusCode::OK;
+
+    res.map_body(move |_, body| {
+        let mut body = GrpcWebBody::passthrough(body);
+        body.http_trailers = Some(trailers);
+        ResponseBody::Body(body)
+    })
+}
+
+/// gRPC-web response, status is sent with trailers frame in the body.
+fn grpc_web_response<B>(
+    mut res: ServiceResponse<B>,
+    mode: Mode,
+    origin: Option<HeaderValue>,
+) -> ServiceResponse<GrpcWebBody<B>> {
+    let fields = take_trailers(&mut res);
+    let mut trailers = BytesMut::new();
+    for name in &[GRPC_STATUS, GRPC_MESSAGE, GRPC_STATUS_DETAILS] {
+        for value in fields.get_all(*name) {
+            trailers.extend_from_slice(name.as_bytes());
+            trailers.extend_from_slice(b":");
+            trailers.extend_from_slice(value.as_bytes());
+            trailers.extend_from_slice(b"\r\n");
+        }
+    }
+
+    let headers = res.headers_mut();
+
+    let ct = headers
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .filter(|val| val.starts_with("application/grpc"))
+        .unwrap_or("application/grpc")
+        .to_owned();
+    let web = if mode == Mode::Text {
+        "application/grpc-web-text"
+    } else {
+        "application/grpc-web"
+    };
+    let ct = ct.replacen("application/grpc", web, 1);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(&ct).unwrap());
+    headers.remove(CONTENT_LENGTH);
+    if let Some(origin) = origin {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(
+            ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderValue::from_static(EXPOSE_HEADERS),
+        );
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+    *res.response_mut().status_mut() = StatusCode::OK;
+
+    let trailers = encode_frame(TRAILERS_FLAG, &trailers);
+    res.map_body(move |_, body| {
+        ResponseBody::Body(GrpcWebBody {
+            body,
+            trailers: Some(trailers),
+            http_trailers: None,
+            text: mode == Mode::Text,
+            buf: BytesMut::new(),
+            done: false,
+        })
+    })
+}
+
+/// Remove `grpc-*` status headers from response, status is derived from
+/// the response status if handler did not set it.
+fn take_trailers<B>(res: &mut ServiceResponse<B>) -> HeaderMap {
+    let status = res.status();
+    let headers = res.headers_mut();
+    let mut trailers = HeaderMap::new();
+    if !headers.contains_key(GRPC_STATUS) {
+        let code = grpc_code(status);
+        trailers.insert(
+            GRPC_STATUS.parse().unwrap(),
+            HeaderValue::from(u16::from(code)),
+        );
+        if code != 0 {
+            trailers.insert(
+                GRPC_MESSAGE.parse().unwrap(),
+                HeaderValue::from_static(status.canonical_reason().unwrap_or("")),
+            );
+        }
+    }
+    for name in &[GRPC_STATUS, GRPC_MESSAGE, GRPC_STATUS_DETAILS] {
+        for value in headers.get_all(*name) {
+            trailers.append(name.parse().unwrap(), value.clone());
+        }
+        headers.remove(*name);
+    }
+    trailers
+}
+
+/// gRPC status code for http status of response without `grpc-status`.
+fn grpc_code(status: StatusCode) -> u8 {
+    match status {
+        StatusCode::OK => 0,
+        StatusCode::BAD_REQUEST => 13,
+        StatusCode::UNAUTHORIZED => 16,
+        StatusCode::FORBIDDEN => 7,
+        StatusCode::NOT_FOUND => 12,
+        StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => 14,
+        _ => 2,
+    }
+}
+
+/// Decode base64 data, each group with padding ends a segment.
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>, base64::DecodeError> {
+    let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+    let mut start = 0;
+    for (idx, group) in data.chunks(4).enumerate() {
+        if group.contains(&b'=') {
+            let end = (idx + 1) * 4;
+            decoded.extend(base64::decode(&data[start..end])?);
+            start = end;
+        }
+    }
+    if start < data.len() {
+        decoded.extend(base64::decode(&data[start..])?);
+    }
+    Ok(decoded)
+}
+
+/// Request payload of `grpc-web-text` request.
+struct TextPayload {
+    payload: Payload,
+    buf: BytesMut,
+}
+
+impl Stream for TextPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.payload).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.buf.extend_from_slice(&chunk);
+                    let len = this.buf.len() - this.buf.len() % 4;
+                    if len == 0 {
+                        continue;
+                    }
+                    let data = this.buf.split_to(len);
+                    return Poll::Ready(Some(
+                        decode_base64(&data)
+                            .map(Bytes::from)
+                            .map_err(|_| invalid_payload()),
+                    ));
+                }
+                Poll::Ready(None) => {
+                    if this.buf.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    this.buf.clear();
+                    return Poll::Ready(Some(Err(invalid_payload())));
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+fn invalid_payload() -> PayloadError {
+    PayloadError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Invalid base64 payload",
+    ))
+}
+
+/// Response body translated by `GrpcWeb` middleware.
+pub struct GrpcWebBody<B> {
+    body: ResponseBody<B>,
+    trailers: Option<Bytes>,
+    http_trailers: Option<HeaderMap>,
+    text: bool,
+    buf: BytesMut,
+    done: bool,
+}
+
+impl<B> GrpcWebBody<B> {
+    fn passthrough(body: ResponseBody<B>) -> Self {
+        GrpcWebBody {
+            body,
+            trailers: None,
+            http_trailers: None,
+            text: false,
+            buf: BytesMut::new(),
+            done: false,
+        }
+    }
+
+    /// Encode chunk, base64 groups are not split between chunks.
+    fn encode(&mut self, chunk: Bytes, last: bool) -> Bytes {
+        if !self.text {
+            return chunk;
+        }
+        self.buf.extend_from_slice(&chunk);
+        let len = if last {
+            self.buf.len()
+        } else {
+            self.buf.len() - self.buf.len() % 3
+        };
+        let data = self.buf.split_to(len);
+        Bytes::from(base64::encode(&data))
+    }
+}
+
+impl<B: MessageBody> MessageBody for GrpcWebBody<B> {
+    fn size(&self) -> BodySize {
+        if self.trailers.is_none() && self.http_trailers.is_none() && !self.text {
+            self.body.size()
+        } else {
+            BodySize::Stream
+        }
+    }
+
+    fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.body.poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                Poll::Ready(Some(Ok(self.encode(chunk, false))))
+            }
+            Poll::Ready(None) => {
+                self.done = true;
+                match self.trailers.take() {
+                    Some(trailers) => Poll::Ready(Some(Ok(self.encode(trailers, true)))),
+                    None => Poll::Ready(None),
+                }
+            }
+            res => res,
+        }
+    }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        match self.http_trailers.take() {
+            Some(trailers) => Poll::Ready(Ok(Some(trailers))),
+            None => self.body.poll_trailers(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::poll_fn;
+
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    fn echo(body: Bytes) -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type("application/grpc+proto")
+            .header(GRPC_STATUS, "0")
+            .body(body)
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"aGk=aGk=").unwrap(), b"hihi");
+        assert_eq!(decode_base64(b"aGVsbG8=").unwrap(), b"hello");
+        assert!(decode_base64(b"a===").is_err());
+    }
+
+    #[test]
+    fn test_grpc_web() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(GrpcWeb::new().allowed_origin("https://example.com"))
+                    .route("/echo", web::post().to(echo))
+                    .route(
+                        "/missing",
+                        web::post().to(|| HttpResponse::NotFound().finish()),
+                    ),
+            )
+            .await;
+
+            let msg = encode_message(b"hello");
+            let req = TestRequest::post()
+                .uri("/echo")
+                .header(CONTENT_TYPE, "application/grpc-web+proto")
+                .header(ORIGIN, "https://example.com")
+                .set_payload(msg.clone())
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "application/grpc-web+proto"
+            );
+            assert_eq!(
+                res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+                "https://example.com"
+            );
+            assert!(res.headers().get(GRPC_STATUS).is_none());
+            let mut expected = BytesMut::from(&msg[..]);
+            expected.extend_from_slice(&encode_frame(0x80, b"grpc-status:0\r\n"));
+            assert_eq!(read_body(res).await, expected.freeze());
+
+            let req = TestRequest::post()
+                .uri("/echo")
+                .header(CONTENT_TYPE, "application/grpc-web-text")
+                .set_payload(base64::encode(&msg))
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "application/grpc-web-text+proto"
+            );
+            let body = base64::decode(&read_body(res).await).unwrap();
+            assert_eq!(&body[..msg.len()], &msg[..]);
+
+            let req = TestRequest::post()
+                .uri("/missing")
+                .header(CONTENT_TYPE, "application/grpc-web")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                read_body(res).await,
+                encode_frame(0x80, b"grpc-status:12\r\ngrpc-message:Not Found\r\n")
+            );
+
+            let req = TestRequest::with_uri("/echo")
+                .method(Method::OPTIONS)
+                .header(ORIGIN, "https://example.com")
+                .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+            assert_eq!(
+                res.headers().get(ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+                ALLOW_HEADERS
+            );
+
+            let req = TestRequest::with_uri("/echo")
+                .method(Method::OPTIONS)
+                .header(ORIGIN, "https://other.com")
+                .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        })
+    }
+
+    #[test]
+    fn test_grpc() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(GrpcWeb::new())
+                    .route("/echo", web::post().guard(GrpcGuard).to(echo))
+                    .route("/echo", web::post().to(|| HttpResponse::Accepted())),
+            )
+            .await;
+
+            let msg = encode_message(b"hello");
+            let req = TestRequest::post()
+                .uri("/echo")
+                .header(CONTENT_TYPE, "application/grpc+proto")
+                .set_payload(msg.clone())
+                .to_request();
+            let mut res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "application/grpc+proto"
+            );
+            assert!(res.headers().get(GRPC_STATUS).is_none());
+
+            let mut body = res.take_body();
+            assert_eq!(body.size(), BodySize::Stream);
+            let chunk = poll_fn(|cx| body.poll_next(cx)).await.unwrap().unwrap();
+            assert_eq!(chunk, msg);
+            assert!(poll_fn(|cx| body.poll_next(cx)).await.is_none());
+            let trailers = poll_fn(|cx| body.poll_trailers(cx)).await.unwrap().unwrap();
+            assert_eq!(trailers.get(GRPC_STATUS).unwrap(), "0");
+
+            // rest request on the same path
+            let req = TestRequest::post().uri("/echo").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::ACCEPTED);
+        })
+    }
+}
@@ -0,0 +1,584 @@
+//! Request identity service.
+//!
+//! `IdentityService` middleware loads identity of the request with an
+//! identity policy and stores changed identity in the response. Cookie
+//! identity policy is implemented by `CookieIdentityPolicy`, other
+//! policies could be added by implementing `IdentityPolicy` trait.
+//!
+//! Current identity is accessible with `Identity` extractor.
+//!
+//! Requires `secure-cookies` feature.
+//!
+//! ```rust
+//! use actix_web::middleware::identity::{CookieIdentityPolicy, Identity, IdentityService};
+//! use actix_web::{web, App, HttpResponse};
+//!
+//! async fn index(id: Identity) -> String {
+//!     // access request identity
+//!     if let Some(id) = id.identity() {
+//!         format!("Welcome! {}", id)
+//!     } else {
+//!         "Welcome Anonymous!".to_owned()
+//!     }
+//! }
+//!
+//! async fn login(id: Identity) -> HttpResponse {
+//!     id.remember("User1".to_owned()); // <- remember identity
+//!     HttpResponse::Ok().finish()
+//! }
+//!
+//! async fn logout(id: Identity) -> HttpResponse {
+//!     id.forget(); // <- remove identity
+//!     HttpResponse::Ok().finish()
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .wrap(IdentityService::new(
+//!             // <- create identity middleware
+//!             CookieIdentityPolicy::new(&[0; 32]) // <- create cookie identity policy
+//!                 .name("auth-cookie")
+//!                 .secure(false),
+//!         ))
+//!         .route("/index.html", web::get().to(index))
+//!         .route("/login.html", web::post().to(login))
+//!         .route("/logout.html", web::post().to(logout));
+//! }
+//! ```
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, Ready};
+
+use crate::cookie::{Cookie, CookieJar, Key, SameSite};
+use crate::dev::{Extensions, Payload};
+use crate::error::Error;
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// The extractor type to obtain your identity from a request.
+///
+/// ```rust
+/// use actix_web::middleware::identity::Identity;
+/// use actix_web::HttpResponse;
+///
+/// async fn index(id: Identity) -> String {
+///     // access request identity
+///     if let Some(id) = id.identity() {
+///         format!("Welcome! {}", id)
+///     } else {
+///         "Welcome Anonymous!".to_owned()
+///     }
+/// }
+///
+/// async fn login(id: Identity) -> HttpResponse {
+///     id.remember("User1".to_owned()); // <- remember identity
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// async fn logout(id: Identity) -> HttpResponse {
+///     id.forget(); // <- remove identity
+///     HttpResponse::Ok().finish()
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone)]
+pub struct Identity(HttpRequest);
+
+impl Identity {
+    /// Return the claimed identity of the user associated request or
+    /// ``None`` if no identity can be found associated with the request.
+    pub fn identity(&self) -> Option<String> {
+        Identity::get_identity(&self.0.extensions())
+    }
+
+    /// Remember identity.
+    pub fn remember(&self, identity: String) {
+        if let Some(id) = self.0.extensions_mut().get_mut::<IdentityItem>() {
+            id.id = Some(identity);
+            id.changed = true;
+        }
+    }
+
+    /// This method is used to 'forget' the current identity on subsequent
+    /// requests.
+    pub fn forget(&self) {
+        if let Some(id) = self.0.extensions_mut().get_mut::<IdentityItem>() {
+            id.id = None;
+            id.changed = true;
+        }
+    }
+
+    fn get_identity(extensions: &Extensions) -> Option<String> {
+        if let Some(id) = extensions.get::<IdentityItem>() {
+            id.id.clone()
+        } else {
+            None
+        }
+    }
+}
+
+struct IdentityItem {
+    id: Option<String>,
+    changed: bool,
+}
+
+/// Helper trait that allows to get Identity.
+///
+/// It could be used in middleware but identity policy must be set before any
+/// other middleware that needs identity. RequestIdentity is implemented both
+/// for `ServiceRequest` and `HttpRequest`.
+pub trait RequestIdentity {
+    /// Identity of the request
+    fn get_identity(&self) -> Option<String>;
+}
+
+impl<T> RequestIdentity for T
+where
+    T: HttpMessage,
+{
+    fn get_identity(&self) -> Option<String> {
+        Identity::get_identity(&self.extensions())
+    }
+}
+
+/// Extractor implementation for Identity type.
+impl FromRequest for Identity {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Identity, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(Identity(req.clone()))
+    }
+}
+
+/// Identity policy definition.
+pub trait IdentityPolicy: Sized + 'static {
+    /// The return type of the middleware
+    type Future: Future<Output = Result<Option<String>, Error>>;
+
+    /// The return type of the middleware
+    type ResponseFuture: Future<Output = Result<(), Error>>;
+
+    /// Parse the session from request and load data from a service identity.
+    fn from_request(&self, request: &mut ServiceRequest) -> Self::Future;
+
+    /// Write changes to response
+    fn to_response<B>(
+        &self,
+        identity: Option<String>,
+        changed: bool,
+        response: &mut ServiceResponse<B>,
+    ) -> Self::ResponseFuture;
+}
+
+/// Request identity middleware
+///
+/// ```rust
+/// use actix_web::App;
+/// use actix_web::middleware::identity::{CookieIdentityPolicy, IdentityService};
+///
+/// fn main() {
+///     let app = App::new().wrap(IdentityService::new(
+///         // <- create identity middleware
+///         CookieIdentityPolicy::new(&[0; 32])    // <- create cookie session backend
+///               .name("auth-cookie")
+///               .secure(false),
+///     ));
+/// }
+/// ```
+pub struct IdentityService<T> {
+    backend: Rc<T>,
+}
+
+impl<T> IdentityService<T> {
+    /// Create new identity service with specified backend.
+    pub fn new(backend: T) -> Self {
+        IdentityService {
+            backend: Rc::new(backend),
+        }
+    }
+}
+
+impl<S, T, B> Transform<S> for IdentityService<T>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    T: IdentityPolicy,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdentityServiceMiddleware<S, T>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdentityServiceMiddleware {
+            backend: self.backend.clone(),
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct IdentityServiceMiddleware<S, T> {
+    backend: Rc<T>,
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, T, B> Service for IdentityServiceMiddleware<S, T>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    T: IdentityPolicy,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = IdentityFuture<S, T, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        IdentityFuture {
+            id_fut: Some(self.backend.from_request(&mut req)),
+            fut: None,
+            res_fut: None,
+            req: Some(req),
+            res: None,
+            service: self.service.clone(),
+            backend: self.backend.clone(),
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct IdentityFuture<S: Service, T: IdentityPolicy, B> {
+    #[pin]
+    id_fut: Option<T::Future>,
+    #[pin]
+    fut: Option<S::Future>,
+    #[pin]
+    res_fut: Option<T::ResponseFuture>,
+    req: Option<ServiceRequest>,
+    res: Option<ServiceResponse<B>>,
+    service: Rc<RefCell<S>>,
+    backend: Rc<T>,
+}
+
+impl<S, T, B> Future for IdentityFuture<S, T, B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    T: IdentityPolicy,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(fut) = this.id_fut.as_mut().as_pin_mut() {
+                let id = futures::ready!(fut.poll(cx));
+                this.id_fut.set(None);
+                let req = this.req.take().unwrap();
+
+                match id {
+                    Ok(id) => {
+                        req.extensions_mut()
+                            .insert(IdentityItem { id, changed: false });
+                        let fut = this.service.borrow_mut().call(req);
+                        this.fut.set(Some(fut));
+                    }
+                    Err(e) => return Poll::Ready(Ok(req.error_response(e))),
+                }
+            } else if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+                let mut res = futures::ready!(fut.poll(cx))?;
+                this.fut.set(None);
+                let id = res.request().extensions_mut().remove::<IdentityItem>();
+
+                if let Some(id) = id {
+                    let fut = this.backend.to_response(id.id, id.changed, &mut res);
+                    this.res_fut.set(Some(fut));
+                    *this.res = Some(res);
+                } else {
+                    return Poll::Ready(Ok(res));
+                }
+            } else {
+                let fut = this.res_fut.as_mut().as_pin_mut();
+                let result = futures::ready!(fut.unwrap().poll(cx));
+                let res = this.res.take().unwrap();
+                return match result {
+                    Ok(_) => Poll::Ready(Ok(res)),
+                    Err(e) => Poll::Ready(Ok(res.error_response(e))),
+                };
+            }
+        }
+    }
+}
+
+struct CookieIdentityInner {
+    key: Key,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    max_age: Option<i64>,
+    same_site: Option<SameSite>,
+}
+
+impl CookieIdentityInner {
+    fn new(key: &[u8]) -> CookieIdentityInner {
+        CookieIdentityInner {
+            key: Key::from_master(key),
+            name: "actix-identity".to_owned(),
+            path: "/".to_owned(),
+            domain: None,
+            secure: true,
+            max_age: None,
+            same_site: None,
+        }
+    }
+
+    fn set_cookie<B>(
+        &self,
+        resp: &mut ServiceResponse<B>,
+        id: Option<String>,
+    ) -> Result<(), Error> {
+        let some = id.is_some();
+        let mut cookie = Cookie::build(self.name.clone(), id.unwrap_or_default())
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(true);
+
+        if let Some(ref domain) = self.domain {
+            cookie = cookie.domain(domain.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            cookie = cookie.max_age(max_age);
+        }
+        if let Some(same_site) = self.same_site {
+            cookie = cookie.same_site(same_site);
+        }
+        let cookie = cookie.finish();
+
+        let mut jar = CookieJar::new();
+        if some {
+            jar.private(&self.key).add(cookie);
+        } else {
+            jar.add_original(cookie.clone());
+            jar.private(&self.key).remove(cookie);
+        }
+
+        for cookie in jar.delta() {
+            resp.response_mut().add_cookie(cookie)?;
+        }
+        Ok(())
+    }
+
+    fn load(&self, req: &ServiceRequest) -> Option<String> {
+        req.private_cookie(&self.name, &self.key)
+            .map(|cookie| cookie.value().to_owned())
+    }
+}
+
+/// Use cookies for request identity storage.
+///
+/// The constructors take a key as an argument.
+/// This is the private key for cookie - when this value is changed,
+/// all identities are lost. The constructors will panic if the key is less
+/// than 32 bytes in length.
+///
+/// ```rust
+/// use actix_web::middleware::identity::{CookieIdentityPolicy, IdentityService};
+/// use actix_web::App;
+///
+/// fn main() {
+///     let app = App::new().wrap(IdentityService::new(
+///         // <- create identity middleware
+///         CookieIdentityPolicy::new(&[0; 32])  // <- construct cookie policy
+///                .domain("www.rust-lang.org")
+///                .name("actix_auth")
+///                .path("/")
+///                .secure(true),
+///     ));
+/// }
+/// ```
+pub struct CookieIdentityPolicy(Rc<CookieIdentityInner>);
+
+impl CookieIdentityPolicy {
+    /// Construct new `CookieIdentityPolicy` instance.
+    ///
+    /// Panics if key length is less than 32 bytes.
+    pub fn new(key: &[u8]) -> CookieIdentityPolicy {
+        CookieIdentityPolicy(Rc::new(CookieIdentityInner::new(key)))
+    }
+
+    fn inner_mut(&mut self) -> &mut CookieIdentityInner {
+        Rc::get_mut(&mut self.0).expect("Multiple copies exist")
+    }
+
+    /// Sets the `path` field in the session cookie being built.
+    pub fn path<S: Into<String>>(mut self, value: S) -> CookieIdentityPolicy {
+        self.inner_mut().path = value.into();
+        self
+    }
+
+    /// Sets the `name` field in the session cookie being built.
+    pub fn name<S: Into<String>>(mut self, value: S) -> CookieIdentityPolicy {
+        self.inner_mut().name = value.into();
+        self
+    }
+
+    /// Sets the `domain` field in the session cookie being built.
+    pub fn domain<S: Into<String>>(mut self, value: S) -> CookieIdentityPolicy {
+        self.inner_mut().domain = Some(value.into());
+        self
+    }
+
+    /// Sets the `secure` field in the session cookie being built.
+    ///
+    /// If the `secure` field is set, a cookie will only be transmitted when the
+    /// connection is secure - i.e. `https`
+    pub fn secure(mut self, value: bool) -> CookieIdentityPolicy {
+        self.inner_mut().secure = value;
+        self
+    }
+
+    /// Sets the `max-age` field in the session cookie being built, in seconds.
+    pub fn max_age(mut self, seconds: i64) -> CookieIdentityPolicy {
+        self.inner_mut().max_age = Some(seconds);
+        self
+    }
+
+    /// Sets the `same_site` field in the session cookie being built.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.inner_mut().same_site = Some(same_site);
+        self
+    }
+}
+
+impl IdentityPolicy for CookieIdentityPolicy {
+    type Future = Ready<Result<Option<String>, Error>>;
+    type ResponseFuture = Ready<Result<(), Error>>;
+
+    fn from_request(&self, req: &mut ServiceRequest) -> Self::Future {
+        ok(self.0.load(req))
+    }
+
+    fn to_response<B>(
+        &self,
+        id: Option<String>,
+        changed: bool,
+        res: &mut ServiceResponse<B>,
+    ) -> Self::ResponseFuture {
+        if changed {
+            if let Err(e) = self.0.set_cookie(res, id) {
+                return err(e);
+            }
+        }
+        ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    const COOKIE_KEY_MASTER: [u8; 32] = [0; 32];
+    const COOKIE_NAME: &str = "actix_auth";
+    const COOKIE_LOGIN: &str = "test";
+
+    #[test]
+    fn test_identity() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(IdentityService::new(
+                        CookieIdentityPolicy::new(&COOKIE_KEY_MASTER)
+                            .domain("www.rust-lang.org")
+                            .name(COOKIE_NAME)
+                            .path("/")
+                            .secure(true),
+                    ))
+                    .service(web::resource("/index").to(|id: Identity| {
+                        if id.identity().is_some() {
+                            HttpResponse::Created()
+                        } else {
+                            HttpResponse::Ok()
+                        }
+                    }))
+                    .service(web::resource("/login").to(|id: Identity| {
+                        id.remember(COOKIE_LOGIN.to_string());
+                        HttpResponse::Ok()
+                    }))
+                    .service(web::resource("/logout").to(|id: Identity| {
+                        if id.identity().is_some() {
+                            id.forget();
+                            HttpResponse::Ok()
+                        } else {
+                            HttpResponse::BadRequest()
+                        }
+                    })),
+            )
+            .await;
+
+            let resp =
+                call_service(&mut srv, TestRequest::with_uri("/index").to_request())
+                    .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp =
+                call_service(&mut srv, TestRequest::with_uri("/login").to_request())
+                    .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let c = resp.response().cookies().next().unwrap().into_owned();
+            assert_eq!(c.name(), COOKIE_NAME);
+            assert!(c.secure().unwrap_or(false));
+            assert!(c.http_only().unwrap_or(false));
+
+            let resp = call_service(
+                &mut srv,
+                TestRequest::with_uri("/index")
+                    .cookie(c.clone())
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            let resp = call_service(
+                &mut srv,
+                TestRequest::with_uri("/logout")
+                    .cookie(c.clone())
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().contains_key(header::SET_COOKIE));
+            let removed = resp.response().cookies().next().unwrap().into_owned();
+            assert_eq!(removed.value(), "");
+
+            // cookie encrypted with other key is ignored
+            let resp = call_service(
+                &mut srv,
+                TestRequest::with_uri("/index")
+                    .cookie(Cookie::new(COOKIE_NAME, COOKIE_LOGIN))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    }
+}
@@ -17,7 +17,7 @@ use time;
 
 use crate::dev::{BodySize, MessageBody, ResponseBody};
 use crate::error::{Error, Result};
-use crate::http::{HeaderName, HttpTryFrom, StatusCode};
+use crate::http::{HeaderMap, HeaderName, HttpTryFrom, StatusCode};
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::HttpResponse;
 
@@ -263,6 +263,10 @@ impl<B: MessageBody> MessageBody for StreamLog<B> {
         self.body.size()
     }
 
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.body.size_hint()
+    }
+
     fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
         match self.body.poll_next(cx) {
             Poll::Ready(Some(Ok(chunk))) => {
@@ -272,6 +276,13 @@ impl<B: MessageBody> MessageBody for StreamLog<B> {
             val => val,
         }
     }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        self.body.poll_trailers(cx)
+    }
 }
 
 /// A formatting style for the `Logger`, consisting of multiple
@@ -1,14 +1,32 @@
 //! Middlewares
 mod compress;
-pub use self::compress::{BodyEncoding, Compress};
+pub use self::compress::{AcceptedEncodings, BodyEncoding, Compress};
 
+mod authentication;
+mod bodylimit;
+mod boxed;
 mod condition;
 mod defaultheaders;
+#[cfg(feature = "digest")]
+pub mod digest;
 pub mod errhandlers;
+#[cfg(feature = "secure-cookies")]
+pub mod flash;
+#[cfg(feature = "grpc-web")]
+pub mod grpc_web;
+#[cfg(feature = "secure-cookies")]
+pub mod identity;
 mod logger;
 mod normalize;
+mod responseguard;
+mod timeout;
 
+pub use self::authentication::Authentication;
+pub use self::bodylimit::BodyLimit;
+pub use self::boxed::Boxed;
 pub use self::condition::Condition;
 pub use self::defaultheaders::DefaultHeaders;
 pub use self::logger::Logger;
 pub use self::normalize::NormalizePath;
+pub use self::responseguard::ResponseGuard;
+pub use self::timeout::Timeout;
@@ -0,0 +1,363 @@
+//! Middleware for guarding size of response body
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_http::body::{BodySize, MessageBody, ResponseBody};
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+
+use crate::error::{Error, ResponseBodyError};
+use crate::http::header::CONTENT_LENGTH;
+use crate::http::{HeaderMap, Method};
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpResponse;
+
+/// `Middleware` for guarding size of response body.
+///
+/// Body of the response is checked against its declared length, either
+/// size of the body or `Content-Length` header set for a streaming body,
+/// and against optional max size. Body that is longer is aborted with
+/// `ResponseBodyError`, so connection is closed instead of sending data
+/// that corrupts next response on keep-alive connection. Body that is
+/// shorter than declared length is always aborted. Response that declares
+/// length over max size is replaced with *INTERNAL SERVER ERROR*.
+///
+/// Middleware should be registered last, so it checks body that is sent
+/// to the client.
+///
+/// ```rust
+/// use actix_web::{middleware, web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::ResponseGuard::new().max_size(10 * 1024 * 1024))
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ResponseGuard {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    max_size: Option<u64>,
+    truncate: bool,
+}
+
+impl ResponseGuard {
+    /// Construct `ResponseGuard` middleware, only declared length is checked.
+    pub fn new() -> ResponseGuard {
+        ResponseGuard::default()
+    }
+
+    /// Set max size of response body in bytes.
+    pub fn max_size(mut self, size: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = Some(size);
+        self
+    }
+
+    /// Truncate body that is longer than declared length or max size
+    /// instead of aborting it.
+    pub fn truncate(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .truncate = true;
+        self
+    }
+}
+
+impl<S, B> Transform<S> for ResponseGuard
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<GuardedBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseGuardMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ResponseGuardMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct ResponseGuardMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for ResponseGuardMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<GuardedBody<B>>;
+    type Error = Error;
+    type Future = ResponseGuardFuture<S, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        ResponseGuardFuture {
+            // body of response to HEAD request is not sent
+            head: req.method() == Method::HEAD,
+            fut: self.service.call(req),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct ResponseGuardFuture<S: Service, B> {
+    #[pin]
+    fut: S::Future,
+    inner: Rc<Inner>,
+    head: bool,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> Future for ResponseGuardFuture<S, B>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Output = Result<ServiceResponse<GuardedBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+        let head = *this.head;
+
+        let mut declared = match res.response().body().size() {
+            BodySize::Sized(len) => Some(len as u64),
+            BodySize::Sized64(len) => Some(len),
+            BodySize::Stream => res
+                .headers()
+                .get(&CONTENT_LENGTH)
+                .and_then(|val| val.to_str().ok())
+                .and_then(|val| val.parse::<u64>().ok()),
+            BodySize::None | BodySize::Empty => None,
+        };
+
+        if let (Some(len), Some(max)) = (declared, this.inner.max_size) {
+            if len > max && !head {
+                log::error!("Response declares {} bytes, limit is {} bytes", len, max);
+                res = res.into_response(
+                    HttpResponse::InternalServerError().finish().into_body(),
+                );
+                declared = None;
+            }
+        }
+
+        let limit = if head {
+            None
+        } else {
+            match (declared, this.inner.max_size) {
+                (Some(declared), Some(max)) => Some(std::cmp::min(declared, max)),
+                (declared, max) => declared.or(max),
+            }
+        };
+        let truncate = this.inner.truncate;
+
+        Poll::Ready(Ok(res.map_body(move |_, body| {
+            ResponseBody::Body(GuardedBody {
+                body,
+                declared: if head { None } else { declared },
+                limit,
+                size: 0,
+                truncate,
+                done: false,
+            })
+        })))
+    }
+}
+
+/// Response body checked by `ResponseGuard`.
+pub struct GuardedBody<B> {
+    body: ResponseBody<B>,
+    declared: Option<u64>,
+    limit: Option<u64>,
+    size: u64,
+    truncate: bool,
+    done: bool,
+}
+
+impl<B: MessageBody> MessageBody for GuardedBody<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.body.poll_next(cx) {
+            Poll::Ready(Some(Ok(mut chunk))) => {
+                let len = chunk.len() as u64;
+                if let Some(limit) = self.limit {
+                    if self.size + len > limit {
+                        self.done = true;
+                        if !self.truncate {
+                            log::error!("Response body exceeds {} bytes", limit);
+                            return Poll::Ready(Some(Err(
+                                ResponseBodyError::Overflow { limit }.into(),
+                            )));
+                        }
+                        if self.size == limit {
+                            return Poll::Ready(None);
+                        }
+                        chunk.truncate((limit - self.size) as usize);
+                        self.size = limit;
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                }
+                self.size += len;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                self.done = true;
+                match self.declared {
+                    Some(declared) if self.size < declared => {
+                        log::error!(
+                            "Response body of {} bytes is shorter than declared {} bytes",
+                            self.size,
+                            declared
+                        );
+                        Poll::Ready(Some(Err(ResponseBodyError::Incomplete {
+                            size: self.size,
+                            declared,
+                        }
+                        .into())))
+                    }
+                    _ => Poll::Ready(None),
+                }
+            }
+            res => res,
+        }
+    }
+
+    fn poll_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        self.body.poll_trailers(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream::{once, StreamExt};
+
+    use super::*;
+    use crate::dev::{Body, SizedStream};
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, load_stream, TestRequest};
+    use crate::{web, App};
+
+    fn stream() -> impl futures::Stream<Item = Result<Bytes, Error>> {
+        once(ok(Bytes::from_static(b"12345678")))
+    }
+
+    #[test]
+    fn test_response_guard() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(ResponseGuard::new().max_size(16))
+                    .route(
+                        "/",
+                        web::get().to(|| {
+                            HttpResponse::Ok()
+                                .no_chunking()
+                                .content_length(8)
+                                .streaming(stream())
+                        }),
+                    )
+                    .route(
+                        "/long",
+                        web::get().to(|| {
+                            HttpResponse::Ok()
+                                .no_chunking()
+                                .content_length(4)
+                                .streaming(stream())
+                        }),
+                    )
+                    .route(
+                        "/short",
+                        web::get().to(|| {
+                            HttpResponse::Ok()
+                                .body(Body::from_message(SizedStream::new(10, stream())))
+                        }),
+                    )
+                    .route(
+                        "/large",
+                        web::get().to(|| HttpResponse::Ok().body(vec![0u8; 32])),
+                    )
+                    .route(
+                        "/stream",
+                        web::get().to(|| {
+                            HttpResponse::Ok()
+                                .streaming(stream().chain(stream()).chain(stream()))
+                        }),
+                    ),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/").to_request();
+            let mut res = call_service(&mut srv, req).await;
+            let body = load_stream(res.take_body()).await.unwrap();
+            assert_eq!(body, Bytes::from_static(b"12345678"));
+
+            for uri in &["/long", "/short", "/stream"] {
+                let req = TestRequest::with_uri(uri).to_request();
+                let mut res = call_service(&mut srv, req).await;
+                assert_eq!(res.status(), StatusCode::OK);
+                assert!(load_stream(res.take_body()).await.is_err());
+            }
+
+            let req = TestRequest::with_uri("/large").to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        })
+    }
+
+    #[test]
+    fn test_truncate() {
+        block_on(async {
+            let mut srv =
+                init_service(App::new().wrap(ResponseGuard::new().truncate()).route(
+                    "/",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .no_chunking()
+                            .content_length(4)
+                            .streaming(stream())
+                    }),
+                ))
+                .await;
+
+            let req = TestRequest::with_uri("/").to_request();
+            let mut res = call_service(&mut srv, req).await;
+            let body = load_stream(res.take_body()).await.unwrap();
+            assert_eq!(body, Bytes::from_static(b"1234"));
+        })
+    }
+}
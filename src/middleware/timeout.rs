@@ -0,0 +1,212 @@
+//! Middleware for request deadlines
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, Ready};
+
+use crate::error::DeadlineExceeded;
+use crate::http::header::HeaderName;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::types::deadline::REQUEST_TIMEOUT;
+use crate::web::Deadline;
+use crate::{Error, HttpMessage};
+
+/// `Middleware` for limiting time of request processing.
+///
+/// Middleware sets `Deadline` of the request, it is available to inner
+/// middlewares and handlers with `web::Deadline` extractor. Once deadline
+/// expires, processing is dropped and `DeadlineExceeded` error is returned,
+/// it is rendered as *GATEWAY TIMEOUT* response.
+///
+/// Timeout requested by the client or upstream service with
+/// `X-Request-Timeout` header, in milliseconds, is used if it is shorter.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{middleware, web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Timeout::new(Duration::from_secs(30)))
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Timeout {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    timeout: Duration,
+    header: Option<HeaderName>,
+}
+
+impl Timeout {
+    /// Construct `Timeout` middleware.
+    pub fn new(timeout: Duration) -> Timeout {
+        Timeout {
+            inner: Rc::new(Inner {
+                timeout,
+                header: Some(HeaderName::from_static(REQUEST_TIMEOUT)),
+            }),
+        }
+    }
+
+    /// Set name of request header with requested timeout, in milliseconds.
+    ///
+    /// Panics if header name is not valid.
+    pub fn header(mut self, name: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("Can not create header name: {}", name));
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = Some(name);
+        self
+    }
+
+    /// Ignore timeout requested with header.
+    pub fn ignore_header(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = None;
+        self
+    }
+}
+
+impl<S, B> Transform<S> for Timeout
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TimeoutMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TimeoutMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct TimeoutMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for TimeoutMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = TimeoutFuture<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let mut deadline = Deadline::after(self.inner.timeout);
+        if let Some(ref name) = self.inner.header {
+            if let Some(requested) = Deadline::from_header(req.headers(), name) {
+                deadline = std::cmp::min(deadline, requested);
+            }
+        }
+        deadline.insert(&mut req.extensions_mut());
+
+        // deadline of outer middleware could be sooner
+        let deadline = req
+            .extensions()
+            .get::<Deadline>()
+            .cloned()
+            .unwrap_or(deadline);
+
+        TimeoutFuture {
+            expired: deadline.is_expired(),
+            fut: tokio_timer::Timeout::new(self.service.call(req), deadline.remaining()),
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct TimeoutFuture<S: Service> {
+    #[pin]
+    fut: tokio_timer::Timeout<S::Future>,
+    expired: bool,
+}
+
+impl<S, B> Future for TimeoutFuture<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        if *this.expired {
+            return Poll::Ready(Err(DeadlineExceeded.into()));
+        }
+        match futures::ready!(this.fut.poll(cx)) {
+            Ok(res) => Poll::Ready(res),
+            Err(_) => Poll::Ready(Err(DeadlineExceeded.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_timer::delay_for;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn test_timeout() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .wrap(Timeout::new(Duration::from_millis(200)))
+                    .route(
+                        "/slow",
+                        web::get().to(|| async {
+                            delay_for(Duration::from_millis(500)).await;
+                            HttpResponse::Ok()
+                        }),
+                    )
+                    .route(
+                        "/deadline",
+                        web::get().to(|deadline: Deadline| async move {
+                            assert!(deadline.remaining() <= Duration::from_millis(50));
+                            HttpResponse::Ok()
+                        }),
+                    ),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/slow").to_request();
+            let err = srv.call(req).await.err().unwrap();
+            assert_eq!(
+                err.as_response_error().error_response().status(),
+                StatusCode::GATEWAY_TIMEOUT
+            );
+
+            let req = TestRequest::with_uri("/deadline")
+                .header(REQUEST_TIMEOUT, "50")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        })
+    }
+}
@@ -0,0 +1,445 @@
+//! Reverse proxy
+use awc::error::{ConnectError, SendRequestError, WsClientError};
+use awc::ws::Frame;
+use awc::Client;
+use futures::future::{select, Either, FutureExt};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+
+use crate::dev::SizedStream;
+use crate::error::Error;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::StatusCode;
+use crate::request::HttpRequest;
+use crate::types::Payload;
+use crate::ws::{self, Message};
+use crate::HttpResponse;
+
+/// Reverse proxy that forwards requests to the upstream server.
+///
+/// Path and query of the incoming request are appended to the upstream
+/// url, hop-by-hop headers are stripped in both directions and
+/// `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers
+/// are added. Request and response bodies are streamed, websocket
+/// handshakes are forwarded and messages are relayed in both directions.
+///
+/// Connection failures are returned as `502 Bad Gateway`, timeouts as
+/// `504 Gateway Timeout`.
+///
+/// ```rust
+/// use actix_web::{web, App, Error, HttpRequest, HttpResponse};
+/// use actix_web::web::Proxy;
+///
+/// async fn api(
+///     req: HttpRequest,
+///     body: web::Payload,
+///     proxy: web::Data<Proxy>,
+/// ) -> Result<HttpResponse, Error> {
+///     if !req.headers().contains_key("authorization") {
+///         return Ok(HttpResponse::Unauthorized().finish());
+///     }
+///     proxy.forward(&req, body).await
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .data(Proxy::new("http://127.0.0.1:8081"))
+///         .route("/api/{tail:.*}", web::to(api));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Proxy {
+    upstream: String,
+    client: Client,
+}
+
+impl Proxy {
+    /// Create proxy for `upstream` server, i.e. `http://127.0.0.1:8081`.
+    ///
+    /// Default client does not follow redirects and does not add default
+    /// headers.
+    pub fn new(upstream: &str) -> Self {
+        Proxy {
+            upstream: upstream.trim_end_matches('/').to_owned(),
+            client: Client::build()
+                .disable_redirects()
+                .no_default_headers()
+                .finish(),
+        }
+    }
+
+    /// Set client that is used for upstream requests.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Forward request to the upstream and return upstream response.
+    pub async fn forward(
+        &self,
+        req: &HttpRequest,
+        payload: Payload,
+    ) -> Result<HttpResponse, Error> {
+        let path = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let url = format!("{}{}", self.upstream, path);
+
+        if is_websocket(req) {
+            return self.forward_ws(req, payload, &url).await;
+        }
+
+        let mut request = self
+            .client
+            .request(req.method().clone(), url.as_str())
+            .no_decompress();
+        for (name, value) in forward_headers(req.headers()) {
+            request = request.header(name.clone(), value.clone());
+        }
+        for (name, value) in forwarded_for(req) {
+            request = request.set_header(name, value);
+        }
+
+        let length = content_length(req.headers());
+        let res = if let Some(len) = length {
+            let stream = payload.map_err(Error::from);
+            request.send_body(SizedStream::new(len, stream)).await
+        } else if req.headers().contains_key(header::TRANSFER_ENCODING) {
+            request.send_stream(payload).await
+        } else {
+            request.send().await
+        };
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                log::debug!("Upstream request to {} failed: {}", url, err);
+                return Ok(HttpResponse::new(error_status(&err)));
+            }
+        };
+
+        let mut builder = HttpResponse::build(res.status());
+        for (name, value) in forward_headers(res.headers()) {
+            builder.header(name.clone(), value.clone());
+        }
+        let status = res.status();
+        if status == StatusCode::NO_CONTENT || status == StatusCode::NOT_MODIFIED {
+            return Ok(builder.finish());
+        }
+        match content_length(res.headers()) {
+            Some(len) => {
+                let stream = res.map_err(Error::from);
+                Ok(builder.body(SizedStream::new(len, stream)))
+            }
+            None => Ok(builder.streaming(res)),
+        }
+    }
+
+    async fn forward_ws(
+        &self,
+        req: &HttpRequest,
+        payload: Payload,
+        url: &str,
+    ) -> Result<HttpResponse, Error> {
+        let mut request = self.client.ws(url);
+        for (name, value) in forward_headers(req.headers()) {
+            if !is_ws_handshake(name) {
+                request = request.header(name.clone(), value.clone());
+            }
+        }
+        for (name, value) in forwarded_for(req) {
+            request = request.set_header(name, value);
+        }
+
+        let (res, framed) = match request.connect().await {
+            Ok(res) => res,
+            Err(WsClientError::InvalidResponseStatus(status)) => {
+                return Ok(HttpResponse::new(status));
+            }
+            Err(WsClientError::SendRequest(ref err)) => {
+                log::debug!("Upstream websocket handshake to {} failed: {}", url, err);
+                return Ok(HttpResponse::new(error_status(err)));
+            }
+            Err(err) => {
+                log::debug!("Upstream websocket handshake to {} failed: {}", url, err);
+                return Ok(HttpResponse::new(StatusCode::BAD_GATEWAY));
+            }
+        };
+
+        let (mut response, mut session, mut msgs) = ws::start(req, payload)?;
+        if let Some(proto) = res.headers().get(header::SEC_WEBSOCKET_PROTOCOL) {
+            response
+                .headers_mut()
+                .insert(header::SEC_WEBSOCKET_PROTOCOL, proto.clone());
+        }
+
+        let (mut sink, mut stream) = framed.split();
+        let to_upstream = async move {
+            while let Some(Ok(msg)) = msgs.next().await {
+                if sink.send(msg).await.is_err() {
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        };
+        let to_client = async move {
+            while let Some(Ok(frame)) = stream.next().await {
+                match frame_message(frame) {
+                    Some(msg) => {
+                        if session.send(msg).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        };
+
+        // session ends once upstream connection is closed
+        actix_rt::spawn(async move {
+            let to_upstream = to_upstream.boxed_local();
+            let to_client = to_client.boxed_local();
+            if let Either::Left((_, to_client)) = select(to_upstream, to_client).await {
+                to_client.await;
+            }
+        });
+        Ok(response)
+    }
+}
+
+/// Headers that are forwarded, without hop-by-hop headers.
+fn forward_headers(
+    headers: &HeaderMap,
+) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+    // headers listed in `Connection` are hop-by-hop as well
+    let connection: Vec<String> = headers
+        .get_all(header::CONNECTION)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_ascii_lowercase())
+        .collect();
+
+    headers.iter().filter(move |(name, _)| {
+        !is_hop_by_hop(name)
+            && *name != header::HOST
+            && *name != header::CONTENT_LENGTH
+            && !connection.iter().any(|c| c == name.as_str())
+    })
+}
+
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    match name.as_str() {
+        "connection"
+        | "keep-alive"
+        | "proxy-authenticate"
+        | "proxy-authorization"
+        | "proxy-connection"
+        | "te"
+        | "trailer"
+        | "transfer-encoding"
+        | "upgrade" => true,
+        _ => false,
+    }
+}
+
+/// Handshake headers that are set by the client.
+fn is_ws_handshake(name: &HeaderName) -> bool {
+    *name == header::SEC_WEBSOCKET_KEY
+        || *name == header::SEC_WEBSOCKET_VERSION
+        || *name == header::SEC_WEBSOCKET_EXTENSIONS
+        || *name == header::SEC_WEBSOCKET_ACCEPT
+}
+
+fn is_websocket(req: &HttpRequest) -> bool {
+    req.head().upgrade()
+        && req
+            .headers()
+            .get(header::UPGRADE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.eq_ignore_ascii_case("websocket"))
+            .unwrap_or(false)
+}
+
+/// `X-Forwarded-*` headers of the request.
+fn forwarded_for(req: &HttpRequest) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+
+    if let Some(addr) = req.peer_addr() {
+        let value = match req.headers().get("x-forwarded-for") {
+            Some(prev) => format!("{}, {}", prev.to_str().unwrap_or(""), addr.ip()),
+            None => addr.ip().to_string(),
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.push((HeaderName::from_static("x-forwarded-for"), value));
+        }
+    }
+    let info = req.connection_info();
+    if let Ok(value) = HeaderValue::from_str(info.scheme()) {
+        headers.push((HeaderName::from_static("x-forwarded-proto"), value));
+    }
+    if let Ok(value) = HeaderValue::from_str(info.host()) {
+        headers.push((HeaderName::from_static("x-forwarded-host"), value));
+    }
+    headers
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+fn error_status(err: &SendRequestError) -> StatusCode {
+    match err {
+        SendRequestError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        SendRequestError::Connect(ConnectError::Timeout)
+        | SendRequestError::Connect(ConnectError::HandshakeTimeout) => {
+            StatusCode::GATEWAY_TIMEOUT
+        }
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Convert upstream frame to message for the client.
+fn frame_message(frame: Frame) -> Option<Message> {
+    Some(match frame {
+        Frame::Text(data) => {
+            let data = data.unwrap_or_default();
+            Message::Text(std::str::from_utf8(&data).ok()?.to_owned())
+        }
+        Frame::Binary(data) => Message::Binary(data.unwrap_or_default().freeze()),
+        Frame::Ping(msg) => Message::Ping(msg),
+        Frame::Pong(msg) => Message::Pong(msg),
+        Frame::Close(reason) => Message::Close(reason),
+        Frame::Continuation(item) => Message::Continuation(item),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::HttpService;
+    use actix_http_test::TestServer;
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[test]
+    fn test_forward() {
+        block_on(async {
+            let srv = TestServer::start(|| {
+                HttpService::new(App::new().route(
+                    "/{tail:.*}",
+                    web::to(|req: HttpRequest, body: Bytes| async move {
+                        let mut res = HttpResponse::Created();
+                        res.header("keep-alive", "timeout=5")
+                            .header("x-path", req.uri().to_string());
+                        for name in &["te", "x-hop", "x-forwarded-host"] {
+                            if let Some(v) = req.headers().get(*name) {
+                                res.header(
+                                    format!("x-echo-{}", name).as_str(),
+                                    v.clone(),
+                                );
+                            }
+                        }
+                        res.body(body)
+                    }),
+                ))
+            });
+
+            let mut app = init_service(
+                App::new().route("/{tail:.*}", web::proxy_to(&srv.url("/"))),
+            )
+            .await;
+
+            let req = TestRequest::post()
+                .uri("/api/items?id=1")
+                .header(header::HOST, "example.com")
+                .header(header::TE, "trailers")
+                .header(header::CONNECTION, "x-hop")
+                .header("x-hop", "1")
+                .set_payload(Bytes::from_static(b"payload"))
+                .to_request();
+            let res = call_service(&mut app, req).await;
+            assert_eq!(res.status(), StatusCode::CREATED);
+            assert_eq!(
+                res.headers().get("x-path").unwrap(),
+                &HeaderValue::from_static("/api/items?id=1")
+            );
+            assert_eq!(
+                res.headers().get("x-echo-x-forwarded-host").unwrap(),
+                &HeaderValue::from_static("example.com")
+            );
+            assert!(!res.headers().contains_key("x-echo-te"));
+            assert!(!res.headers().contains_key("x-echo-x-hop"));
+            assert!(!res.headers().contains_key("keep-alive"));
+            assert_eq!(read_body(res).await, Bytes::from_static(b"payload"));
+        })
+    }
+
+    #[test]
+    fn test_forward_error() {
+        block_on(async {
+            let addr = TestServer::unused_addr();
+            let mut app = init_service(
+                App::new().route("/", web::proxy_to(&format!("http://{}", addr))),
+            )
+            .await;
+
+            let res = call_service(&mut app, TestRequest::get().to_request()).await;
+            assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        })
+    }
+
+    #[test]
+    fn test_error_status() {
+        assert_eq!(
+            error_status(&SendRequestError::Timeout),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            error_status(&ConnectError::HandshakeTimeout.into()),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            error_status(&ConnectError::Disconnected.into()),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[test]
+    fn test_forward_ws() {
+        block_on(async {
+            let upstream = TestServer::start(|| {
+                HttpService::new(App::new().route(
+                    "/ws",
+                    web::get().to(|req: HttpRequest, body: Payload| async move {
+                        let (res, mut session, mut msgs) = ws::start(&req, body)?;
+                        actix_rt::spawn(async move {
+                            while let Some(Ok(msg)) = msgs.next().await {
+                                if let Message::Text(text) = msg {
+                                    let _ = session.text(text + "!").await;
+                                }
+                            }
+                        });
+                        Ok::<_, Error>(res)
+                    }),
+                ))
+            });
+            let url = upstream.url("/");
+            let srv = TestServer::start(move || {
+                HttpService::new(App::new().route("/ws", web::proxy_to(&url)))
+            });
+
+            let mut framed = srv.ws_at("/ws").await.unwrap();
+            framed
+                .send(Message::Text("hello".to_owned()))
+                .await
+                .unwrap();
+            let frame = framed.next().await.unwrap().unwrap();
+            assert_eq!(frame, Frame::Text(Some("hello!".into())));
+        })
+    }
+}
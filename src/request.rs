@@ -1,14 +1,15 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::rc::Rc;
 use std::{fmt, net};
 
 use actix_http::http::{HeaderMap, Method, Uri, Version};
+use actix_http::pool::PoolStats;
 use actix_http::{Error, Extensions, HttpMessage, Message, Payload, RequestHead};
 use actix_router::{Path, Url};
 use futures::future::{ok, Ready};
 
 use crate::config::AppConfig;
-use crate::data::Data;
+use crate::data::{Data, DataScope, InheritedData};
 use crate::error::UrlGenerationError;
 use crate::extract::FromRequest;
 use crate::info::ConnectionInfo;
@@ -26,6 +27,7 @@ pub(crate) struct HttpRequestInner {
     rmap: Rc<ResourceMap>,
     config: AppConfig,
     pool: &'static HttpRequestPool,
+    retain: Option<Rc<RefCell<Option<HttpRequest>>>>,
 }
 
 impl HttpRequest {
@@ -47,6 +49,7 @@ impl HttpRequest {
             config,
             app_data,
             pool,
+            retain: None,
         }))
     }
 }
@@ -124,6 +127,12 @@ impl HttpRequest {
     }
 
     /// Request extensions
+    ///
+    /// Extensions are shared by all middlewares and the handler of the
+    /// request, values inserted by a middleware before calling next service
+    /// are available to handler and values inserted by handler are available
+    /// to middlewares once response is returned. See `Extensions` for
+    /// details.
     #[inline]
     pub fn extensions(&self) -> Ref<Extensions> {
         self.head().extensions()
@@ -226,6 +235,20 @@ impl HttpRequest {
             None
         }
     }
+
+    /// Check whether application data of type `T` is set by the scope or
+    /// resource that handles request or is inherited from the application.
+    ///
+    /// Returns `None` if data is not configured.
+    pub fn app_data_scope<T: 'static>(&self) -> Option<DataScope> {
+        if !self.0.app_data.contains::<Data<T>>() {
+            None
+        } else if self.0.app_data.contains::<InheritedData<T>>() {
+            Some(DataScope::Inherited)
+        } else {
+            Some(DataScope::Local)
+        }
+    }
 }
 
 impl HttpMessage for HttpRequest {
@@ -258,8 +281,19 @@ impl HttpMessage for HttpRequest {
 impl Drop for HttpRequest {
     fn drop(&mut self) {
         if Rc::strong_count(&self.0) == 1 {
-            let v = &mut self.0.pool.0.borrow_mut();
-            if v.len() < 128 {
+            // release unread payload, so connection could be drained
+            let retain = Rc::get_mut(&mut self.0).and_then(|inner| {
+                inner.payload = Payload::None;
+                inner.retain.take()
+            });
+            if let Some(slot) = retain {
+                if Rc::strong_count(&slot) > 1 {
+                    *slot.borrow_mut() = Some(HttpRequest(self.0.clone()));
+                    return;
+                }
+            }
+            let v = &mut self.0.pool.pool.borrow_mut();
+            if v.len() < self.0.pool.capacity {
                 self.extensions_mut().clear();
                 v.push(self.0.clone());
             }
@@ -321,27 +355,150 @@ impl fmt::Debug for HttpRequest {
     }
 }
 
+/// Owned head of the request, it is kept for responses of service errors
+/// as request itself could not be kept, services require unique access
+/// to it.
+pub(crate) struct ErrorHead {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    peer_addr: Option<net::SocketAddr>,
+    path: Option<Path<Url>>,
+    rmap: Rc<ResourceMap>,
+    config: AppConfig,
+    data: Rc<Extensions>,
+    pool: &'static HttpRequestPool,
+}
+
+impl ErrorHead {
+    fn new(
+        head: &RequestHead,
+        rmap: Rc<ResourceMap>,
+        config: AppConfig,
+        data: Rc<Extensions>,
+        pool: &'static HttpRequestPool,
+    ) -> ErrorHead {
+        ErrorHead {
+            method: head.method.clone(),
+            uri: head.uri.clone(),
+            version: head.version,
+            headers: head.headers.clone(),
+            peer_addr: head.peer_addr,
+            path: None,
+            rmap,
+            config,
+            data,
+            pool,
+        }
+    }
+
+    /// Head of already routed request, match info is preserved.
+    pub(crate) fn from_request(req: &HttpRequest) -> ErrorHead {
+        let inner = &req.0;
+        let mut head = ErrorHead::new(
+            &inner.head,
+            inner.rmap.clone(),
+            inner.config.clone(),
+            inner.app_data.clone(),
+            inner.pool,
+        );
+        head.path = Some(inner.path.clone());
+        head
+    }
+
+    pub(crate) fn into_request(self) -> HttpRequest {
+        let mut head: Message<RequestHead> = Message::new();
+        head.method = self.method;
+        head.uri = self.uri;
+        head.version = self.version;
+        head.headers = self.headers;
+        head.peer_addr = self.peer_addr;
+
+        let path = match self.path {
+            Some(path) => path,
+            None => Path::new(Url::new(head.uri.clone())),
+        };
+        HttpRequest::new(
+            path,
+            head,
+            Payload::None,
+            self.rmap,
+            self.config,
+            self.data,
+            self.pool,
+        )
+    }
+}
+
+/// Request released by the application service.
+///
+/// Service errors are rendered with the request, it is kept once the last
+/// reference to it is dropped, no copy of the head is made.
+pub(crate) struct RetainedRequest(Rc<RefCell<Option<HttpRequest>>>);
+
+impl RetainedRequest {
+    /// Panics if multiple references of http request exists.
+    pub(crate) fn new(req: &mut HttpRequest) -> RetainedRequest {
+        let slot = Rc::new(RefCell::new(None));
+        Rc::get_mut(&mut req.0).unwrap().retain = Some(slot.clone());
+        RetainedRequest(slot)
+    }
+
+    /// Request, if it is not referenced anymore.
+    pub(crate) fn take(&self) -> Option<HttpRequest> {
+        self.0.borrow_mut().take()
+    }
+}
+
+thread_local!(static POOL_STATS: Cell<PoolStats> = Cell::new(PoolStats::default()));
+
+/// Counters of `HttpRequest` pools of the current worker.
+///
+/// Counters are aggregated for all applications of the worker, see
+/// `App::request_pool()`.
+pub fn http_request_pool_stats() -> PoolStats {
+    POOL_STATS.with(|stats| stats.get())
+}
+
 /// Request's objects pool
-pub(crate) struct HttpRequestPool(RefCell<Vec<Rc<HttpRequestInner>>>);
+pub(crate) struct HttpRequestPool {
+    pool: RefCell<Vec<Rc<HttpRequestInner>>>,
+    capacity: usize,
+}
 
 impl HttpRequestPool {
     pub(crate) fn create() -> &'static HttpRequestPool {
-        let pool = HttpRequestPool(RefCell::new(Vec::with_capacity(128)));
+        HttpRequestPool::with_capacity(128)
+    }
+
+    pub(crate) fn with_capacity(capacity: usize) -> &'static HttpRequestPool {
+        let pool = HttpRequestPool {
+            pool: RefCell::new(Vec::with_capacity(capacity)),
+            capacity,
+        };
         Box::leak(Box::new(pool))
     }
 
     /// Get message from the pool
     #[inline]
     pub(crate) fn get_request(&self) -> Option<HttpRequest> {
-        if let Some(inner) = self.0.borrow_mut().pop() {
-            Some(HttpRequest(inner))
-        } else {
-            None
-        }
+        let inner = self.pool.borrow_mut().pop();
+
+        POOL_STATS.with(|stats| {
+            let mut s = stats.get();
+            if inner.is_some() {
+                s.hits += 1;
+            } else {
+                s.misses += 1;
+            }
+            stats.set(s);
+        });
+        inner.map(HttpRequest)
     }
 
     pub(crate) fn clear(&self) {
-        self.0.borrow_mut().clear()
+        self.pool.borrow_mut().clear()
     }
 }
 
@@ -537,4 +694,29 @@ mod tests {
             assert!(tracker.borrow().dropped);
         })
     }
+
+    #[test]
+    fn test_request_pool() {
+        block_on(async {
+            for (capacity, hits) in &[(128, 1), (0, 0)] {
+                let mut srv = init_service(
+                    App::new()
+                        .request_pool(*capacity)
+                        .service(web::resource("/").to(|| HttpResponse::Ok())),
+                )
+                .await;
+
+                let start = http_request_pool_stats();
+                for _ in 0..2 {
+                    let req = TestRequest::default().to_request();
+                    let resp = call_service(&mut srv, req).await;
+                    assert_eq!(resp.status(), StatusCode::OK);
+                }
+
+                let stats = http_request_pool_stats();
+                assert_eq!(stats.hits - start.hits, *hits);
+                assert_eq!(stats.misses - start.misses, 2 - *hits);
+            }
+        })
+    }
 }
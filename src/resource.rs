@@ -3,9 +3,10 @@ use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use actix_http::{Error, Extensions, Response};
+use actix_http::{Error, Extensions, PayloadDrain, Response};
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{
     apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
@@ -18,6 +19,7 @@ use crate::dev::{insert_slash, AppService, HttpServiceFactory, ResourceDef};
 use crate::extract::FromRequest;
 use crate::guard::Guard;
 use crate::handler::Factory;
+use crate::metrics::{RouteStats, Timed};
 use crate::responder::Responder;
 use crate::route::{CreateRouteService, Route, RouteService};
 use crate::service::{ServiceRequest, ServiceResponse};
@@ -53,9 +55,11 @@ pub struct Resource<T = ResourceEndpoint> {
     name: Option<String>,
     routes: Vec<Route>,
     data: Option<Extensions>,
+    drain: Option<PayloadDrain>,
     guards: Vec<Box<dyn Guard>>,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     factory_ref: Rc<RefCell<Option<ResourceFactory>>>,
+    metrics: Option<Arc<RouteStats>>,
 }
 
 impl Resource {
@@ -70,7 +74,9 @@ impl Resource {
             factory_ref: fref,
             guards: Vec::new(),
             data: None,
+            drain: None,
             default: Rc::new(RefCell::new(None)),
+            metrics: None,
         }
     }
 }
@@ -210,6 +216,29 @@ where
         self
     }
 
+    /// Set policy for request payload that is not read by the handler,
+    /// overrides policy set with `App::drain_payload()`.
+    ///
+    /// Policy is applied after resource middlewares, requests rejected by
+    /// these middlewares use policy of the application.
+    ///
+    /// ```rust
+    /// use actix_web::{dev::PayloadDrain, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/upload")
+    ///             // drain up to 64kb of unread payload, so connection is kept alive
+    ///             .drain_payload(PayloadDrain::Limit(65_536))
+    ///             .route(web::post().to(|| HttpResponse::Forbidden())),
+    ///     );
+    /// }
+    /// ```
+    pub fn drain_payload(mut self, drain: PayloadDrain) -> Self {
+        self.drain = Some(drain);
+        self
+    }
+
     /// Register a new route and add handler. This route matches all requests.
     ///
     /// ```rust
@@ -277,7 +306,9 @@ where
             routes: self.routes,
             default: self.default,
             data: self.data,
+            drain: self.drain,
             factory_ref: self.factory_ref,
+            metrics: self.metrics,
         }
     }
 
@@ -339,7 +370,9 @@ where
             routes: self.routes,
             default: self.default,
             data: self.data,
+            drain: self.drain,
             factory_ref: self.factory_ref,
+            metrics: self.metrics,
         }
     }
 
@@ -392,6 +425,10 @@ where
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
         }
+        if let Some(metrics) = config.config().route_metrics() {
+            let pattern = format!("{}{}", config.prefix(), rdef.pattern());
+            self.metrics = Some(metrics.route(&pattern));
+        }
         // custom app data storage
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
@@ -414,7 +451,9 @@ where
         *self.factory_ref.borrow_mut() = Some(ResourceFactory {
             routes: self.routes,
             data: self.data.map(Rc::new),
+            drain: self.drain,
             default: self.default,
+            metrics: self.metrics,
         });
 
         self.endpoint
@@ -424,7 +463,9 @@ where
 pub struct ResourceFactory {
     routes: Vec<Route>,
     data: Option<Rc<Extensions>>,
+    drain: Option<PayloadDrain>,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
+    metrics: Option<Arc<RouteStats>>,
 }
 
 impl ServiceFactory for ResourceFactory {
@@ -450,8 +491,10 @@ impl ServiceFactory for ResourceFactory {
                 .map(|route| CreateRouteServiceItem::Future(route.new_service(&())))
                 .collect(),
             data: self.data.clone(),
+            drain: self.drain,
             default: None,
             default_fut,
+            metrics: self.metrics.clone(),
         }
     }
 }
@@ -464,8 +507,10 @@ enum CreateRouteServiceItem {
 pub struct CreateResourceService {
     fut: Vec<CreateRouteServiceItem>,
     data: Option<Rc<Extensions>>,
+    drain: Option<PayloadDrain>,
     default: Option<HttpService>,
     default_fut: Option<LocalBoxFuture<'static, Result<HttpService, ()>>>,
+    metrics: Option<Arc<RouteStats>>,
 }
 
 impl Future for CreateResourceService {
@@ -508,7 +553,9 @@ impl Future for CreateResourceService {
             Poll::Ready(Ok(ResourceService {
                 routes,
                 data: self.data.clone(),
+                drain: self.drain,
                 default: self.default.take(),
+                metrics: self.metrics.clone(),
             }))
         } else {
             Poll::Pending
@@ -519,7 +566,9 @@ impl Future for CreateResourceService {
 pub struct ResourceService {
     routes: Vec<RouteService>,
     data: Option<Rc<Extensions>>,
+    drain: Option<PayloadDrain>,
     default: Option<HttpService>,
+    metrics: Option<Arc<RouteStats>>,
 }
 
 impl Service for ResourceService {
@@ -528,7 +577,7 @@ impl Service for ResourceService {
     type Error = Error;
     type Future = Either<
         Ready<Result<ServiceResponse, Error>>,
-        LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+        Timed<LocalBoxFuture<'static, Result<ServiceResponse, Error>>>,
     >;
 
     fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
@@ -536,16 +585,22 @@ impl Service for ResourceService {
     }
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        if let Some(drain) = self.drain {
+            req.set_payload_drain(drain);
+        }
         for route in self.routes.iter_mut() {
             if route.check(&mut req) {
                 if let Some(ref data) = self.data {
                     req.set_data_container(data.clone());
                 }
-                return Either::Right(route.call(req));
+                return Either::Right(Timed::new(
+                    route.call(req),
+                    self.metrics.as_ref(),
+                ));
             }
         }
         if let Some(ref mut default) = self.default {
-            Either::Right(default.call(req))
+            Either::Right(Timed::new(default.call(req), None))
         } else {
             let req = req.into_parts().0;
             Either::Left(ok(ServiceResponse::new(
@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use actix_http::body::Body;
 use actix_http::error::InternalError;
 use actix_http::http::{
     header::IntoHeaderValue, Error as HttpError, HeaderMap, HeaderName, HttpTryFrom,
@@ -131,6 +132,16 @@ impl Responder for ResponseBuilder {
     }
 }
 
+impl<B: Into<Body>> Responder for http::Response<B> {
+    type Error = Error;
+    type Future = Ready<Result<Response, Error>>;
+
+    #[inline]
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(self.into())
+    }
+}
+
 impl Responder for () {
     type Error = Error;
     type Future = Ready<Result<Response, Error>>;
@@ -466,7 +477,7 @@ pub(crate) mod tests {
     use super::*;
     use crate::dev::{Body, ResponseBody};
     use crate::http::{header::CONTENT_TYPE, HeaderValue, StatusCode};
-    use crate::test::{block_on, init_service, TestRequest};
+    use crate::test::{block_on, init_service, read_body, TestRequest};
     use crate::{error, web, App, HttpResponse};
 
     #[test]
@@ -489,13 +500,8 @@ pub(crate) mod tests {
             let req = TestRequest::with_uri("/some").to_request();
             let resp = srv.call(req).await.unwrap();
             assert_eq!(resp.status(), StatusCode::OK);
-            match resp.response().body() {
-                ResponseBody::Body(Body::Bytes(ref b)) => {
-                    let bytes: Bytes = b.clone().into();
-                    assert_eq!(bytes, Bytes::from_static(b"some"));
-                }
-                _ => panic!(),
-            }
+            let bytes = read_body(resp).await;
+            assert_eq!(bytes, Bytes::from_static(b"some"));
         })
     }
 
@@ -587,6 +593,16 @@ pub(crate) mod tests {
                 HeaderValue::from_static("application/octet-stream")
             );
 
+            let resp: HttpResponse = http::Response::builder()
+                .status(StatusCode::CREATED)
+                .body("test")
+                .unwrap()
+                .respond_to(&req)
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            assert_eq!(resp.body().bin_ref(), b"test");
+
             // InternalError
             let resp: HttpResponse =
                 error::InternalError::new("err", StatusCode::BAD_REQUEST)
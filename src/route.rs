@@ -223,6 +223,11 @@ impl Route {
     ///     );
     /// }
     /// ```
+    ///
+    /// Handler could have up to 16 arguments and each of them has to implement
+    /// `FromRequest`, otherwise `Factory` trait bound is not satisfied. Tuple
+    /// of extractors is an extractor too, so handlers with more arguments
+    /// could group them, i.e. `(path, query): (web::Path<u32>, web::Query<Info>)`.
     pub fn to<F, T, R, U>(mut self, handler: F) -> Self
     where
         F: Factory<T, R, U>,
@@ -349,7 +354,7 @@ mod tests {
 
     use crate::http::{Method, StatusCode};
     use crate::test::{block_on, call_service, init_service, read_body, TestRequest};
-    use crate::{error, web, App, Error, HttpResponse};
+    use crate::{error, web, App, Error, HttpRequest, HttpResponse};
 
     #[derive(Serialize, PartialEq, Debug)]
     struct MyObject {
@@ -431,4 +436,67 @@ mod tests {
             assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
         })
     }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn sixteen_args(
+        req: HttpRequest,
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        _: (),
+        path: web::Path<(String,)>,
+    ) -> String {
+        format!("{} {}", req.path(), path.0)
+    }
+
+    #[test]
+    fn test_many_extractors() {
+        block_on(async {
+            let mut srv =
+                init_service(App::new().route("/{name}", web::get().to(sixteen_args)))
+                    .await;
+
+            let req = TestRequest::with_uri("/test").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let body = read_body(resp).await;
+            assert_eq!(body, Bytes::from_static(b"/test test"));
+        })
+    }
+
+    #[test]
+    fn test_block_fn() {
+        block_on(async {
+            let mut srv = init_service(App::new().route(
+                "/{id}",
+                web::get().to(web::block_fn(|id: web::Path<u32>| {
+                    std::thread::sleep(Duration::from_millis(10));
+                    format!("id: {}", id)
+                })),
+            ))
+            .await;
+
+            let req = TestRequest::with_uri("/10").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let body = read_body(resp).await;
+            assert_eq!(body, Bytes::from_static(b"id: 10"));
+
+            let req = TestRequest::with_uri("/abc").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        })
+    }
 }
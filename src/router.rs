@@ -0,0 +1,227 @@
+//! Prefix trie router
+//!
+//! Literal prefixes of resource patterns, up to the first dynamic segment,
+//! are compiled into a radix trie. Request path is walked through the trie
+//! once, and only resources whose prefix matches are checked with
+//! `ResourceDef::match_path()`, in order of registration. Static patterns
+//! are compared as strings, regex is used only for dynamic segments.
+use actix_router::ResourceDef;
+
+use crate::service::ServiceRequest;
+
+/// Router of app and scope services
+pub(crate) struct Router<T, U> {
+    routes: Vec<(ResourceDef, T, Option<U>)>,
+    root: Node,
+}
+
+impl<T, U> Router<T, U> {
+    pub(crate) fn build() -> RouterBuilder<T, U> {
+        RouterBuilder { routes: Vec::new() }
+    }
+
+    /// Find first resource that matches request path and passes check.
+    ///
+    /// Path segments of the matched resource are added to request's
+    /// match info.
+    pub(crate) fn recognize_mut_checked<F>(
+        &mut self,
+        req: &mut ServiceRequest,
+        check: F,
+    ) -> Option<&mut T>
+    where
+        F: Fn(&ServiceRequest, &Option<U>) -> bool,
+    {
+        let candidates = self.root.find(req.match_info().path().as_bytes());
+
+        for idx in candidates {
+            let (ref rdef, _, ref guards) = self.routes[*idx];
+
+            if guards.is_none() {
+                if rdef.match_path(req.match_info_mut()) && check(req, guards) {
+                    return Some(&mut self.routes[*idx].1);
+                }
+            } else {
+                // guards could fail after segments are added, restore path
+                let path = req.match_info().clone();
+                if rdef.match_path(req.match_info_mut()) {
+                    if check(req, guards) {
+                        return Some(&mut self.routes[*idx].1);
+                    }
+                    *req.match_info_mut() = path;
+                }
+            }
+        }
+        None
+    }
+}
+
+pub(crate) struct RouterBuilder<T, U> {
+    routes: Vec<(ResourceDef, T, Option<U>)>,
+}
+
+impl<T, U> RouterBuilder<T, U> {
+    /// Register resource, returns entry for setting guards.
+    pub(crate) fn rdef(
+        &mut self,
+        rdef: ResourceDef,
+        resource: T,
+    ) -> &mut (ResourceDef, T, Option<U>) {
+        self.routes.push((rdef, resource, None));
+        self.routes.last_mut().unwrap()
+    }
+
+    /// Compile trie of registered resources.
+    pub(crate) fn finish(self) -> Router<T, U> {
+        let mut root = Node::new(Vec::new());
+        for (idx, (rdef, _, _)) in self.routes.iter().enumerate() {
+            root.insert(literal_prefix(rdef.pattern()).as_bytes(), idx);
+        }
+        root.finish(&[]);
+
+        Router {
+            root,
+            routes: self.routes,
+        }
+    }
+}
+
+/// Literal part of the pattern before first dynamic segment.
+///
+/// Trailing slash is not included, prefix patterns match path without it.
+fn literal_prefix(pattern: &str) -> &str {
+    let prefix = match pattern.find('{') {
+        Some(idx) => &pattern[..idx],
+        None => pattern,
+    };
+    prefix.trim_end_matches('/')
+}
+
+struct Node {
+    prefix: Vec<u8>,
+    children: Vec<Node>,
+    /// Resources whose literal prefix is a prefix of the node's path,
+    /// in order of registration
+    routes: Vec<usize>,
+}
+
+impl Node {
+    fn new(prefix: Vec<u8>) -> Node {
+        Node {
+            prefix,
+            children: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, key: &[u8], route: usize) {
+        if key.is_empty() {
+            self.routes.push(route);
+            return;
+        }
+
+        for child in &mut self.children {
+            let common = child
+                .prefix
+                .iter()
+                .zip(key)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if common == 0 {
+                continue;
+            }
+
+            if common < child.prefix.len() {
+                let mut node = Node::new(key[..common].to_vec());
+                std::mem::swap(child, &mut node);
+                node.prefix.drain(..common);
+                child.children.push(node);
+            }
+            child.insert(&key[common..], route);
+            return;
+        }
+
+        let mut node = Node::new(key.to_vec());
+        node.routes.push(route);
+        self.children.push(node);
+    }
+
+    /// Merge routes of parent nodes into children.
+    fn finish(&mut self, parent: &[usize]) {
+        self.routes.extend_from_slice(parent);
+        self.routes.sort_unstable();
+
+        for child in &mut self.children {
+            child.finish(&self.routes);
+        }
+    }
+
+    /// Routes of the deepest node that is a prefix of the path.
+    fn find(&self, mut path: &[u8]) -> &[usize] {
+        let mut node = self;
+
+        'walk: loop {
+            for child in &node.children {
+                if path.starts_with(&child.prefix) {
+                    path = &path[child.prefix.len()..];
+                    node = child;
+                    continue 'walk;
+                }
+            }
+            return &node.routes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn test_recognize() {
+        let mut router = Router::<usize, ()>::build();
+        router.rdef(ResourceDef::new("/name"), 0);
+        router.rdef(ResourceDef::new("/name/{val}"), 1);
+        router.rdef(ResourceDef::new("/name/{val}/index.html"), 2);
+        router.rdef(ResourceDef::new("/file/{file}.{ext}"), 3);
+        router.rdef(ResourceDef::new("/v{val}/{val2}/index.html"), 4);
+        router.rdef(ResourceDef::prefix("/app"), 5);
+        router.rdef(ResourceDef::new("/nam"), 6);
+        let mut router = router.finish();
+
+        let cases = [
+            ("/name", Some(0)),
+            ("/name/value", Some(1)),
+            ("/name/value2/index.html", Some(2)),
+            ("/file/file.gz", Some(3)),
+            ("/vtest/ttt/index.html", Some(4)),
+            ("/app/index.html", Some(5)),
+            ("/nam", Some(6)),
+            ("/missing", None),
+        ];
+        for (path, idx) in cases.iter() {
+            let mut req = TestRequest::with_uri(path).to_srv_request();
+            let res = router.recognize_mut_checked(&mut req, |_, _| true);
+            assert_eq!(res.map(|idx| *idx), *idx, "{}", path);
+        }
+
+        let mut req = TestRequest::with_uri("/name/value2/index.html").to_srv_request();
+        router.recognize_mut_checked(&mut req, |_, _| true);
+        assert_eq!(req.match_info().get("val").unwrap(), "value2");
+    }
+
+    #[test]
+    fn test_recognize_checked() {
+        let mut router = Router::<usize, bool>::build();
+        router.rdef(ResourceDef::new("/user/{id}"), 0).2 = Some(false);
+        router.rdef(ResourceDef::new("/user/{name}"), 1).2 = Some(true);
+        let mut router = router.finish();
+
+        let mut req = TestRequest::with_uri("/user/1").to_srv_request();
+        let res = router.recognize_mut_checked(&mut req, |_, guard| guard.unwrap());
+        assert_eq!(*res.unwrap(), 1);
+        assert_eq!(req.match_info().get("name").unwrap(), "1");
+        assert!(req.match_info().get("id").is_none());
+    }
+}
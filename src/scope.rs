@@ -4,22 +4,25 @@ use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
-use actix_http::{Extensions, Response};
-use actix_router::{ResourceDef, ResourceInfo, Router};
+use actix_http::{Extensions, HttpMessage, Response};
+use actix_router::{ResourceDef, ResourceInfo};
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{
     apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
 };
 use futures::future::{ok, Either, Future, LocalBoxFuture, Ready};
 
+use crate::app_service::PathMatched;
 use crate::config::ServiceConfig;
 use crate::data::Data;
 use crate::dev::{AppService, HttpServiceFactory};
-use crate::error::Error;
+use crate::error::{Error, ResponseError};
 use crate::guard::Guard;
+use crate::request::{ErrorHead, HttpRequest};
 use crate::resource::Resource;
 use crate::rmap::ResourceMap;
 use crate::route::Route;
+use crate::router::Router;
 use crate::service::{
     AppServiceFactory, ServiceFactoryWrapper, ServiceRequest, ServiceResponse,
 };
@@ -28,6 +31,7 @@ type Guards = Vec<Box<dyn Guard>>;
 type HttpService = BoxedService<ServiceRequest, ServiceResponse, Error>;
 type HttpNewService = BoxedNewService<(), ServiceRequest, ServiceResponse, Error, ()>;
 type BoxedResponse = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+type ErrorMapper = dyn Fn(&Error, &HttpRequest) -> Option<Response>;
 
 /// Resources scope.
 ///
@@ -67,6 +71,8 @@ pub struct Scope<T = ScopeEndpoint> {
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     external: Vec<ResourceDef>,
     factory_ref: Rc<RefCell<Option<ScopeFactory>>>,
+    error_mappers: Vec<Box<ErrorMapper>>,
+    isolate_data: bool,
 }
 
 impl Scope {
@@ -82,6 +88,8 @@ impl Scope {
             default: Rc::new(RefCell::new(None)),
             external: Vec::new(),
             factory_ref: fref,
+            error_mappers: Vec::new(),
+            isolate_data: false,
         }
     }
 }
@@ -124,6 +132,12 @@ where
     /// Set or override application data. Application data could be accessed
     /// by using `Data<T>` extractor where `T` is data type.
     ///
+    /// Data of the scope shadows application data of the same type,
+    /// application data of other types is inherited unless
+    /// `Scope::isolate_data()` is used. Nested scopes and resources with
+    /// their own data inherit application data, but not data of this scope.
+    /// Use `HttpRequest::app_data_scope()` to check origin of the data.
+    ///
     /// ```rust
     /// use std::cell::Cell;
     /// use actix_web::{web, App};
@@ -163,6 +177,29 @@ where
         self
     }
 
+    /// Do not inherit application data.
+    ///
+    /// Services of the scope can access only data of the scope, nested
+    /// scopes and resources do not inherit application data either.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().data(10usize).service(
+    ///         web::scope("/app")
+    ///             .isolate_data()
+    ///             .data(20u32)
+    ///             // `Data<usize>` is not available here
+    ///             .route("/", web::get().to(|_: web::Data<u32>| HttpResponse::Ok())),
+    ///     );
+    /// }
+    /// ```
+    pub fn isolate_data(mut self) -> Self {
+        self.isolate_data = true;
+        self
+    }
+
     /// Run external configuration as part of the scope building
     /// process
     ///
@@ -302,6 +339,51 @@ where
         self
     }
 
+    /// Convert errors of type `E` to custom responses.
+    ///
+    /// Mapper is called for errors of handlers, extractors and middlewares of
+    /// the scope and its nested services that could be downcasted to `E`,
+    /// its response is sent instead of the error response. Mappers are tried
+    /// in the order of registration, errors of other types are not affected.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse, ResponseError};
+    ///
+    /// #[derive(Debug, derive_more::Display)]
+    /// #[display(fmt = "Item {} not found", _0)]
+    /// struct NotFound(u32);
+    ///
+    /// impl ResponseError for NotFound {}
+    ///
+    /// async fn index(id: web::Path<u32>) -> Result<HttpResponse, NotFound> {
+    ///     Err(NotFound(*id))
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .map_err(|err: &NotFound, req| {
+    ///                 HttpResponse::NotFound().json(serde_json::json!({
+    ///                     "error": err.to_string(),
+    ///                     "path": req.path(),
+    ///                 }))
+    ///             })
+    ///             .route("/items/{id}", web::get().to(index)),
+    ///     );
+    /// }
+    /// ```
+    pub fn map_err<E, F>(mut self, f: F) -> Self
+    where
+        E: ResponseError + 'static,
+        F: Fn(&E, &HttpRequest) -> Response + 'static,
+    {
+        self.error_mappers
+            .push(Box::new(move |err: &Error, req: &HttpRequest| {
+                err.as_error::<E>().map(|err| f(err, req))
+            }));
+        self
+    }
+
     /// Registers middleware, in the form of a middleware component (type),
     /// that runs during inbound processing in the request
     /// lifecycle (request -> response), modifying request as
@@ -341,6 +423,8 @@ where
             default: self.default,
             external: self.external,
             factory_ref: self.factory_ref,
+            error_mappers: self.error_mappers,
+            isolate_data: self.isolate_data,
         }
     }
 
@@ -401,6 +485,8 @@ where
             default: self.default,
             external: self.external,
             factory_ref: self.factory_ref,
+            error_mappers: self.error_mappers,
+            isolate_data: self.isolate_data,
         }
     }
 }
@@ -422,7 +508,16 @@ where
         }
 
         // register nested services
-        let mut cfg = config.clone_config();
+        let mut cfg = if self.isolate_data {
+            config.clone_isolated_config()
+        } else {
+            config.clone_config()
+        };
+        cfg.set_prefix(format!(
+            "{}{}",
+            config.prefix(),
+            ResourceDef::root_prefix(&self.rdef).pattern()
+        ));
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
@@ -435,7 +530,11 @@ where
         }
 
         // custom app data storage
-        if let Some(ref mut ext) = self.data {
+        if self.isolate_data {
+            if self.data.is_none() {
+                self.data = Some(Extensions::new());
+            }
+        } else if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
         }
 
@@ -463,12 +562,65 @@ where
         };
 
         // register final service
-        config.register_service(
-            ResourceDef::root_prefix(&self.rdef),
-            guards,
-            self.endpoint,
-            Some(Rc::new(rmap)),
-        )
+        if self.error_mappers.is_empty() {
+            config.register_service(
+                ResourceDef::root_prefix(&self.rdef),
+                guards,
+                self.endpoint,
+                Some(Rc::new(rmap)),
+            )
+        } else {
+            let mappers = Rc::new(self.error_mappers);
+            config.register_service(
+                ResourceDef::root_prefix(&self.rdef),
+                guards,
+                apply_fn_factory(self.endpoint, move |req: ServiceRequest, srv| {
+                    map_errors(mappers.clone(), req, srv)
+                }),
+                Some(Rc::new(rmap)),
+            )
+        }
+    }
+}
+
+/// Call scope service and convert its errors with error mappers.
+fn map_errors<S>(
+    mappers: Rc<Vec<Box<ErrorMapper>>>,
+    req: ServiceRequest,
+    srv: &mut S,
+) -> impl Future<Output = Result<ServiceResponse, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error>,
+{
+    // owned head, request is not shared while scope services run
+    let head = ErrorHead::from_request(req.request());
+    let fut = srv.call(req);
+
+    async move {
+        let map = |err: &Error, req: &HttpRequest| {
+            mappers.iter().find_map(|mapper| mapper(err, req))
+        };
+
+        match fut.await {
+            Ok(res) => {
+                drop(head);
+                let mapped = match res.response().error() {
+                    Some(err) => map(err, res.request()),
+                    None => None,
+                };
+                match mapped {
+                    Some(mapped) => Ok(res.into_response(mapped)),
+                    None => Ok(res),
+                }
+            }
+            Err(err) => {
+                let req = head.into_request();
+                match map(&err, &req) {
+                    Some(mapped) => Ok(ServiceResponse::new(req, mapped)),
+                    None => Err(err),
+                }
+            }
+        }
     }
 }
 
@@ -614,6 +766,7 @@ impl Service for ScopeService {
             if let Some(ref guards) = guards {
                 for f in guards {
                     if !f.check(req.head()) {
+                        req.extensions_mut().insert(PathMatched);
                         return false;
                     }
                 }
@@ -621,7 +774,7 @@ impl Service for ScopeService {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(srv) = res {
             if let Some(ref data) = self.data {
                 req.set_data_container(data.clone());
             }
@@ -667,7 +820,6 @@ mod tests {
     use futures::future::ok;
     use futures::Future;
 
-    use crate::dev::{Body, ResponseBody};
     use crate::http::{header, HeaderValue, Method, StatusCode};
     use crate::middleware::DefaultHeaders;
     use crate::service::{ServiceRequest, ServiceResponse};
@@ -858,13 +1010,8 @@ mod tests {
             let resp = srv.call(req).await.unwrap();
             assert_eq!(resp.status(), StatusCode::OK);
 
-            match resp.response().body() {
-                ResponseBody::Body(Body::Bytes(ref b)) => {
-                    let bytes: Bytes = b.clone().into();
-                    assert_eq!(bytes, Bytes::from_static(b"project: project1"));
-                }
-                _ => panic!(),
-            }
+            let bytes = read_body(resp).await;
+            assert_eq!(bytes, Bytes::from_static(b"project: project1"));
 
             let req = TestRequest::with_uri("/aa-project1/path1").to_request();
             let resp = srv.call(req).await.unwrap();
@@ -979,13 +1126,8 @@ mod tests {
             let resp = srv.call(req).await.unwrap();
             assert_eq!(resp.status(), StatusCode::CREATED);
 
-            match resp.response().body() {
-                ResponseBody::Body(Body::Bytes(ref b)) => {
-                    let bytes: Bytes = b.clone().into();
-                    assert_eq!(bytes, Bytes::from_static(b"project: project_1"));
-                }
-                _ => panic!(),
-            }
+            let bytes = read_body(resp).await;
+            assert_eq!(bytes, Bytes::from_static(b"project: project_1"));
         })
     }
 
@@ -1011,13 +1153,8 @@ mod tests {
             let resp = srv.call(req).await.unwrap();
             assert_eq!(resp.status(), StatusCode::CREATED);
 
-            match resp.response().body() {
-                ResponseBody::Body(Body::Bytes(ref b)) => {
-                    let bytes: Bytes = b.clone().into();
-                    assert_eq!(bytes, Bytes::from_static(b"project: test - 1"));
-                }
-                _ => panic!(),
-            }
+            let bytes = read_body(resp).await;
+            assert_eq!(bytes, Bytes::from_static(b"project: test - 1"));
 
             let req = TestRequest::with_uri("/app/test/1/path2").to_request();
             let resp = srv.call(req).await.unwrap();
@@ -1298,4 +1435,154 @@ mod tests {
             );
         })
     }
+
+    #[test]
+    fn test_map_err() {
+        #[derive(Debug, derive_more::Display)]
+        #[display(fmt = "my error")]
+        struct MyError;
+
+        impl crate::ResponseError for MyError {}
+
+        block_on(async {
+            let mut srv = init_service(
+                App::new().service(
+                    web::scope("/app")
+                        .map_err(|_: &MyError, req| {
+                            HttpResponse::Conflict()
+                                .body(format!("mapped {}", req.path()))
+                        })
+                        .wrap_fn(|req, srv| {
+                            let fut = srv.call(req);
+                            async move {
+                                let res = fut.await?;
+                                if res.request().path() == "/app/mw" {
+                                    Err(MyError.into())
+                                } else {
+                                    Ok(res)
+                                }
+                            }
+                        })
+                        .route(
+                            "/handler",
+                            web::get().to(|| async { Err::<HttpResponse, _>(MyError) }),
+                        )
+                        .route(
+                            "/other",
+                            web::get().to(|| async {
+                                Err::<HttpResponse, _>(crate::error::ErrorBadRequest(
+                                    "other",
+                                ))
+                            }),
+                        )
+                        .route("/mw", web::get().to(|| HttpResponse::Ok()))
+                        .service(web::scope("/nested").route(
+                            "/",
+                            web::get().to(|| async { Err::<HttpResponse, _>(MyError) }),
+                        )),
+                ),
+            )
+            .await;
+
+            for (path, status, body) in &[
+                ("/app/handler", StatusCode::CONFLICT, "mapped /app/handler"),
+                ("/app/mw", StatusCode::CONFLICT, "mapped /app/mw"),
+                ("/app/nested/", StatusCode::CONFLICT, "mapped /app/nested/"),
+                ("/app/other", StatusCode::BAD_REQUEST, "other"),
+            ] {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(&mut srv, req).await;
+                assert_eq!(resp.status(), *status);
+                let resp_body = read_body(resp).await;
+                assert_eq!(resp_body, Bytes::from(*body));
+            }
+        })
+    }
+
+    #[test]
+    fn test_data_scope() {
+        use crate::web::DataScope;
+
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .data(10usize)
+                    .data(1u32)
+                    .service(web::scope("/app").data(2u32).route(
+                        "/",
+                        web::get().to(|req: HttpRequest| {
+                            assert_eq!(
+                                req.app_data_scope::<usize>(),
+                                Some(DataScope::Inherited)
+                            );
+                            assert_eq!(
+                                req.app_data_scope::<u32>(),
+                                Some(DataScope::Local)
+                            );
+                            assert_eq!(req.app_data::<u32>(), Some(&2));
+                            assert_eq!(req.app_data_scope::<u8>(), None);
+                            HttpResponse::Ok()
+                        }),
+                    ))
+                    .route(
+                        "/",
+                        web::get().to(|req: HttpRequest| {
+                            assert_eq!(
+                                req.app_data_scope::<usize>(),
+                                Some(DataScope::Local)
+                            );
+                            HttpResponse::Ok()
+                        }),
+                    ),
+            )
+            .await;
+
+            for path in &["/app/", "/"] {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(&mut srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+            }
+        })
+    }
+
+    #[test]
+    fn test_isolate_data() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new().data(10usize).service(
+                    web::scope("/app")
+                        .isolate_data()
+                        .route(
+                            "/",
+                            web::get().to(|req: HttpRequest| {
+                                assert!(req.app_data::<usize>().is_none());
+                                HttpResponse::Ok()
+                            }),
+                        )
+                        .service(web::resource("/res").data(1u32).to(
+                            |req: HttpRequest| {
+                                assert!(req.app_data::<usize>().is_none());
+                                assert_eq!(req.app_data::<u32>(), Some(&1));
+                                HttpResponse::Ok()
+                            },
+                        ))
+                        .route(
+                            "/data",
+                            web::get().to(|_: web::Data<usize>| HttpResponse::Ok()),
+                        ),
+                ),
+            )
+            .await;
+
+            for (path, status) in &[
+                ("/app/", StatusCode::OK),
+                ("/app/res", StatusCode::OK),
+                ("/app/data", StatusCode::INTERNAL_SERVER_ERROR),
+            ] {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(&mut srv, req).await;
+                assert_eq!(resp.status(), *status);
+            }
+        })
+    }
 }
@@ -1,14 +1,21 @@
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fmt, io, net};
 
-use actix_http::{body::MessageBody, Error, HttpService, KeepAlive, Request, Response};
+use actix_http::{
+    body::MessageBody, Error, FlushStrategy, HeaderCase, HeaderLimits, HttpService,
+    KeepAlive, Request, Response,
+};
 use actix_rt::System;
 use actix_server::{Server, ServerBuilder};
 use actix_server_config::ServerConfig;
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
 use parking_lot::Mutex;
 
+use crate::drain::{DrainFactory, DrainHandle};
+use crate::health::{shutdown_on_signals, Readiness};
+
 use net2::TcpBuilder;
 
 #[cfg(feature = "openssl")]
@@ -25,6 +32,12 @@ struct Config {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_shutdown: u64,
+    limits: HeaderLimits,
+    header_case: HeaderCase,
+    timer_tick: u64,
+    write_watermarks: (usize, usize),
+    flush: FlushStrategy,
+    drain: Option<DrainHandle>,
 }
 
 /// An HTTP Server.
@@ -64,6 +77,8 @@ where
     backlog: i32,
     sockets: Vec<Socket>,
     builder: ServerBuilder,
+    signals: bool,
+    readiness: Option<(Readiness, u64)>,
     _t: PhantomData<(S, B)>,
 }
 
@@ -87,10 +102,18 @@ where
                 keep_alive: KeepAlive::Timeout(5),
                 client_timeout: 5000,
                 client_shutdown: 5000,
+                limits: HeaderLimits::default(),
+                header_case: HeaderCase::Lower,
+                timer_tick: 100,
+                write_watermarks: (4096, 32_768),
+                flush: FlushStrategy::Eager,
+                drain: None,
             })),
             backlog: 1024,
             sockets: Vec::new(),
             builder: ServerBuilder::default(),
+            signals: true,
+            readiness: None,
             _t: PhantomData,
         }
     }
@@ -104,6 +127,38 @@ where
         self
     }
 
+    /// Set number of threads of blocking thread pool, see `web::block()`.
+    ///
+    /// Pool is shared by all servers of the process and it is started on
+    /// first use, so this method has to be called before that.
+    ///
+    /// By default pool uses `ACTIX_THREADPOOL` env variable or five threads
+    /// per logical cpu.
+    ///
+    /// # Panics
+    ///
+    /// Panics if blocking thread pool is already started.
+    pub fn blocking_threads(self, num: usize) -> Self {
+        crate::blocking::set_threads(num);
+        self
+    }
+
+    /// Set max number of functions waiting for a free thread of blocking
+    /// thread pool.
+    ///
+    /// Once limit is reached, `web::block()` returns
+    /// `BlockingError::Saturated` error. By default queue is not limited.
+    /// Pool is shared by all servers of the process, see
+    /// `HttpServer::blocking_threads()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if blocking thread pool is already started.
+    pub fn blocking_queue(self, max: usize) -> Self {
+        crate::blocking::set_max_queued(max);
+        self
+    }
+
     /// Set the maximum number of pending connections.
     ///
     /// This refers to the number of clients that can be waiting to be served.
@@ -177,6 +232,95 @@ where
         self
     }
 
+    /// Set maximum number of request headers.
+    ///
+    /// Requests with more headers are responded with 431 (Request Header
+    /// Fields Too Large). By default limit is set to 96 headers.
+    pub fn max_headers(self, num: usize) -> Self {
+        self.config.lock().limits.max_headers = num;
+        self
+    }
+
+    /// Set maximum size of a request header, name and value, in bytes.
+    ///
+    /// Requests with larger header are responded with 431 (Request Header
+    /// Fields Too Large). By default limit is set to 128KiB.
+    pub fn max_header_size(self, size: usize) -> Self {
+        self.config.lock().limits.max_header_size = size;
+        self
+    }
+
+    /// Set maximum size of request head, request line and headers, in bytes.
+    ///
+    /// Requests with larger head are responded with 431 (Request Header
+    /// Fields Too Large). By default limit is set to 128KiB.
+    pub fn max_head_size(self, size: usize) -> Self {
+        self.config.lock().limits.max_head_size = size;
+        self
+    }
+
+    /// Set maximum length of request uri.
+    ///
+    /// Requests with longer uri are responded with 414 (URI Too Long).
+    /// By default limit is set to 128KiB.
+    pub fn max_uri_length(self, len: usize) -> Self {
+        self.config.lock().limits.max_uri_length = len;
+        self
+    }
+
+    /// Set case of response header names for HTTP/1 connections.
+    ///
+    /// `HeaderCase::Preserve` writes names of headers inserted with
+    /// `HeaderMap::insert_cased()` as inserted. By default header names are
+    /// written in lower case.
+    pub fn header_case(self, case: HeaderCase) -> Self {
+        self.config.lock().header_case = case;
+        self
+    }
+
+    /// Set tick of connection timers in milliseconds.
+    ///
+    /// Keep-alive, client and shutdown timeouts of connections are driven by
+    /// timer wheel of the worker and rounded up to the tick, larger tick
+    /// reduces timer wakeups with many idle keep-alive connections.
+    /// By default tick is set to 100 milliseconds.
+    pub fn timer_tick(self, val: u64) -> Self {
+        self.config.lock().timer_tick = val;
+        self
+    }
+
+    /// Set low and high watermarks of HTTP/1 write buffer in bytes.
+    ///
+    /// Write buffer is grown by high watermark once its spare capacity is
+    /// less than low watermark, response body is not polled while buffer
+    /// holds more than high watermark. By default watermarks are set to
+    /// 4KiB and 32KiB.
+    ///
+    /// # Panics
+    ///
+    /// Panics if low watermark is zero or greater than high watermark.
+    pub fn write_watermarks(self, low: usize, high: usize) -> Self {
+        assert!(
+            0 < low && low <= high,
+            "Write watermarks must satisfy 0 < low <= high"
+        );
+        self.config.lock().write_watermarks = (low, high);
+        self
+    }
+
+    /// Set flush strategy of HTTP/1 write buffer.
+    ///
+    /// `FlushStrategy::Eager` writes responses to the socket as soon as
+    /// they are produced. `FlushStrategy::Corked` keeps response head and
+    /// chunks of streaming body in write buffer until it reaches low
+    /// watermark, the body is complete or data is kept for 200ms, which
+    /// saves syscalls for bodies with many small chunks at the cost of
+    /// latency. By default buffer is flushed eagerly.
+    pub fn flush_strategy(self, flush: FlushStrategy) -> Self {
+        self.config.lock().flush = flush;
+        self
+    }
+
     /// Set server host name.
     ///
     /// Host name is used by application router as a hostname for url
@@ -196,6 +340,28 @@ where
     /// Disable signal handling
     pub fn disable_signals(mut self) -> Self {
         self.builder = self.builder.disable_signals();
+        self.signals = false;
+        self
+    }
+
+    /// Set drain handle of the server, see `dev::DrainHandle`.
+    ///
+    /// In-flight requests are tracked only once handle is set. Drain mode
+    /// flips readiness off if it is set with `readiness()`.
+    pub fn drain_handle(self, handle: DrainHandle) -> Self {
+        self.config.lock().drain = Some(handle);
+        self
+    }
+
+    /// Flip readiness off on shutdown signals.
+    ///
+    /// On graceful shutdown signal readiness is flipped off first, server
+    /// stops accepting connections `delay` seconds later, so load balancers
+    /// could notice that the server is not ready. See `web::health_service()`.
+    ///
+    /// Has no effect if signal handling is disabled.
+    pub fn readiness(mut self, readiness: Readiness, delay: u64) -> Self {
+        self.readiness = Some((readiness, delay));
         self
     }
 
@@ -247,7 +413,12 @@ where
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
-                    .finish(factory())
+                    .header_limits(c.limits)
+                    .header_case(c.header_case)
+                    .timer_tick(c.timer_tick)
+                    .write_watermarks(c.write_watermarks.0, c.write_watermarks.1)
+                    .flush_strategy(c.flush)
+                    .finish(DrainFactory::<S, B>::new(factory(), c.drain.clone()))
             },
         )?;
         Ok(self)
@@ -292,8 +463,13 @@ where
                     HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
+                        .header_limits(c.limits)
+                        .header_case(c.header_case)
+                        .timer_tick(c.timer_tick)
+                        .write_watermarks(c.write_watermarks.0, c.write_watermarks.1)
+                        .flush_strategy(c.flush)
                         .client_disconnect(c.client_shutdown)
-                        .finish(factory())
+                        .finish(DrainFactory::<S, B>::new(factory(), c.drain.clone()))
                         .map_err(SslError::Service)
                         .map_init_err(|_| ()),
                 )
@@ -344,8 +520,13 @@ where
                     HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
+                        .header_limits(c.limits)
+                        .header_case(c.header_case)
+                        .timer_tick(c.timer_tick)
+                        .write_watermarks(c.write_watermarks.0, c.write_watermarks.1)
+                        .flush_strategy(c.flush)
                         .client_disconnect(c.client_shutdown)
-                        .finish(factory())
+                        .finish(DrainFactory::<S, B>::new(factory(), c.drain.clone()))
                         .map_err(SslError::Service)
                         .map_init_err(|_| ()),
                 )
@@ -462,7 +643,12 @@ where
             HttpService::build()
                 .keep_alive(c.keep_alive)
                 .client_timeout(c.client_timeout)
-                .finish(factory())
+                .header_limits(c.limits)
+                .header_case(c.header_case)
+                .timer_tick(c.timer_tick)
+                .write_watermarks(c.write_watermarks.0, c.write_watermarks.1)
+                .flush_strategy(c.flush)
+                .finish(DrainFactory::<S, B>::new(factory(), c.drain.clone()))
         })?;
         Ok(self)
     }
@@ -493,7 +679,12 @@ where
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
-                    .finish(factory())
+                    .header_limits(c.limits)
+                    .header_case(c.header_case)
+                    .timer_tick(c.timer_tick)
+                    .write_watermarks(c.write_watermarks.0, c.write_watermarks.1)
+                    .flush_strategy(c.flush)
+                    .finish(DrainFactory::<S, B>::new(factory(), c.drain.clone()))
             },
         )?;
         Ok(self)
@@ -535,7 +726,19 @@ where
     /// }
     /// ```
     pub fn start(self) -> Server {
-        self.builder.start()
+        if let Some((ref readiness, _)) = self.readiness {
+            if let Some(ref drain) = self.config.lock().drain {
+                drain.set_readiness(readiness.clone());
+            }
+        }
+        match self.readiness {
+            Some((readiness, delay)) if self.signals => {
+                let srv = self.builder.disable_signals().start();
+                shutdown_on_signals(srv.clone(), readiness, Duration::from_secs(delay));
+                srv
+            }
+            _ => self.builder.start(),
+        }
     }
 
     /// Spawn new thread and start listening for incoming connections.
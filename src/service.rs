@@ -5,8 +5,8 @@ use std::{fmt, net};
 use actix_http::body::{Body, MessageBody, ResponseBody};
 use actix_http::http::{HeaderMap, Method, StatusCode, Uri, Version};
 use actix_http::{
-    Error, Extensions, HttpMessage, Payload, PayloadStream, RequestHead, Response,
-    ResponseHead,
+    Error, Extensions, HttpMessage, Payload, PayloadDrain, PayloadStream, RequestHead,
+    Response, ResponseHead,
 };
 use actix_router::{Path, Resource, ResourceDef, Url};
 use actix_service::{IntoServiceFactory, ServiceFactory};
@@ -230,6 +230,15 @@ impl ServiceRequest {
         Rc::get_mut(&mut (self.0).0).unwrap().payload = payload;
     }
 
+    /// Set policy for request payload that is not read by the time
+    /// response is sent.
+    ///
+    /// Middleware that responds without reading the payload, i.e. on
+    /// authentication failure, could use it to keep connection alive.
+    pub fn set_payload_drain(&self, drain: PayloadDrain) {
+        (self.0).0.payload.set_drain(drain);
+    }
+
     #[doc(hidden)]
     /// Set new app data container
     pub fn set_data_container(&mut self, extensions: Rc<Extensions>) {
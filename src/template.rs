@@ -0,0 +1,239 @@
+//! Template rendering support
+//!
+//! Any type that implements [`Render`](trait.Render.html) trait could be
+//! returned directly from a handler.
+//!
+//! ```rust
+//! use actix_web::{web, App, Error};
+//! use actix_web::template::Render;
+//!
+//! struct Hello {
+//!     name: String,
+//! }
+//!
+//! impl Render for Hello {
+//!     fn render(&self) -> Result<(String, mime::Mime), Error> {
+//!         Ok((format!("<h1>Hello {}!</h1>", self.name), mime::TEXT_HTML_UTF_8))
+//!     }
+//! }
+//!
+//! async fn index(name: web::Path<String>) -> Hello {
+//!     Hello { name: name.into_inner() }
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/{name}", web::get().to(index));
+//! }
+//! ```
+//!
+//! Adapters for popular template engines are available behind `askama`
+//! and `tera` features.
+use actix_http::http::StatusCode;
+use actix_http::{Error, Response};
+use futures::future::{err, ok, Ready};
+use mime::Mime;
+
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+#[cfg(feature = "askama")]
+pub use self::askama_impl::Askama;
+#[cfg(feature = "tera")]
+pub use self::tera_impl::Tera;
+
+/// Trait implemented by types that could be rendered to a response body.
+pub trait Render {
+    /// Render template, returns body and its mime type.
+    fn render(&self) -> Result<(String, Mime), Error>;
+}
+
+impl<T: Render> Responder for T {
+    type Error = Error;
+    type Future = Ready<Result<Response, Error>>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        match self.render() {
+            Ok((body, mime)) => ok(Response::build(StatusCode::OK)
+                .content_type(mime.to_string())
+                .body(body)),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Guess template mime type from template name extension.
+#[cfg(any(feature = "tera", test))]
+fn mime_from_name(name: &str) -> Mime {
+    match name.rsplit('.').next() {
+        Some("html") | Some("htm") => mime::TEXT_HTML_UTF_8,
+        Some("css") => mime::TEXT_CSS_UTF_8,
+        Some("js") => mime::APPLICATION_JAVASCRIPT_UTF_8,
+        Some("json") => mime::APPLICATION_JSON,
+        Some("xml") => mime::TEXT_XML,
+        _ => mime::TEXT_PLAIN_UTF_8,
+    }
+}
+
+#[cfg(feature = "askama")]
+mod askama_impl {
+    use actix_http::Error;
+    use mime::Mime;
+
+    use super::Render;
+    use crate::error::ErrorInternalServerError;
+
+    /// Askama template adapter
+    ///
+    /// ```rust,ignore
+    /// use actix_web::template::Askama;
+    /// use askama::Template;
+    ///
+    /// #[derive(Template)]
+    /// #[template(path = "hello.html")]
+    /// struct Hello<'a> {
+    ///     name: &'a str,
+    /// }
+    ///
+    /// async fn index() -> Askama<Hello<'static>> {
+    ///     Askama::new(Hello { name: "world" })
+    /// }
+    /// ```
+    pub struct Askama<T> {
+        tmpl: T,
+        mime: Mime,
+    }
+
+    impl<T: askama::Template> Askama<T> {
+        /// Create adapter, template is rendered as `text/html`.
+        pub fn new(tmpl: T) -> Self {
+            Askama {
+                tmpl,
+                mime: mime::TEXT_HTML_UTF_8,
+            }
+        }
+
+        /// Set mime type of rendered template.
+        pub fn mime(mut self, mime: Mime) -> Self {
+            self.mime = mime;
+            self
+        }
+    }
+
+    impl<T: askama::Template> Render for Askama<T> {
+        fn render(&self) -> Result<(String, Mime), Error> {
+            let body = self.tmpl.render().map_err(ErrorInternalServerError)?;
+            Ok((body, self.mime.clone()))
+        }
+    }
+}
+
+#[cfg(feature = "tera")]
+mod tera_impl {
+    use std::sync::Arc;
+
+    use actix_http::Error;
+    use mime::Mime;
+
+    use super::{mime_from_name, Render};
+    use crate::error::ErrorInternalServerError;
+
+    /// Tera template adapter
+    ///
+    /// Mime type is guessed from template name extension.
+    ///
+    /// ```rust,ignore
+    /// use std::sync::Arc;
+    /// use actix_web::{web, template::Tera};
+    ///
+    /// async fn index(tera: web::Data<Arc<tera::Tera>>) -> Tera {
+    ///     let mut ctx = tera::Context::new();
+    ///     ctx.insert("name", "world");
+    ///     Tera::new(tera.get_ref().clone(), "hello.html", ctx)
+    /// }
+    /// ```
+    pub struct Tera {
+        tera: Arc<tera::Tera>,
+        name: String,
+        ctx: tera::Context,
+    }
+
+    impl Tera {
+        /// Create adapter for template `name` rendered with `ctx` context.
+        pub fn new<N: Into<String>>(
+            tera: Arc<tera::Tera>,
+            name: N,
+            ctx: tera::Context,
+        ) -> Self {
+            Tera {
+                tera,
+                ctx,
+                name: name.into(),
+            }
+        }
+    }
+
+    impl Render for Tera {
+        fn render(&self) -> Result<(String, Mime), Error> {
+            let body = self
+                .tera
+                .render(&self.name, &self.ctx)
+                .map_err(ErrorInternalServerError)?;
+            Ok((body, mime_from_name(&self.name)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::Service;
+
+    use super::*;
+    use crate::http::{header::CONTENT_TYPE, HeaderValue};
+    use crate::responder::tests::BodyTest;
+    use crate::test::{block_on, init_service, TestRequest};
+    use crate::{error, web, App};
+
+    struct Hello(&'static str);
+
+    impl Render for Hello {
+        fn render(&self) -> Result<(String, Mime), Error> {
+            if self.0.is_empty() {
+                Err(error::ErrorBadRequest("empty"))
+            } else {
+                Ok((format!("<p>{}</p>", self.0), mime::TEXT_HTML_UTF_8))
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_responder() {
+        block_on(async {
+            let mut srv = init_service(
+                App::new()
+                    .service(web::resource("/ok").to(|| async { Hello("test") }))
+                    .service(web::resource("/err").to(|| async { Hello("") })),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/ok").to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(CONTENT_TYPE).unwrap(),
+                HeaderValue::from_static("text/html; charset=utf-8")
+            );
+            assert_eq!(resp.response().body().bin_ref(), b"<p>test</p>");
+
+            let req = TestRequest::with_uri("/err").to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    }
+
+    #[test]
+    fn test_mime_from_name() {
+        assert_eq!(mime_from_name("index.html"), mime::TEXT_HTML_UTF_8);
+        assert_eq!(mime_from_name("data.json"), mime::APPLICATION_JSON);
+        assert_eq!(mime_from_name("README"), mime::TEXT_PLAIN_UTF_8);
+    }
+}
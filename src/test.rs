@@ -1,20 +1,44 @@
 //! Various helpers for Actix applications to use during testing.
+use std::fmt;
 use std::rc::Rc;
-
-use actix_http::http::header::{ContentType, Header, HeaderName, IntoHeaderValue};
+#[cfg(feature = "client")]
+use std::sync::mpsc;
+#[cfg(feature = "client")]
+use std::{net, thread, time};
+
+#[cfg(feature = "client")]
+use actix_codec::Framed;
+use actix_http::error::PayloadError;
+use actix_http::http::header::{
+    ContentType, Header, HeaderName, IntoHeaderValue, CONTENT_TYPE,
+};
 use actix_http::http::{HttpTryFrom, Method, StatusCode, Uri, Version};
 use actix_http::test::TestRequest as HttpTestRequest;
+use actix_http::ws::{CloseCode, Frame as WsFrame, Message as WsMessage};
 use actix_http::{cookie::Cookie, Extensions, Request};
 use actix_router::{Path, ResourceDef, Url};
+#[cfg(feature = "client")]
+use actix_rt::System;
 use actix_server_config::ServerConfig;
 use actix_service::{IntoService, IntoServiceFactory, Service, ServiceFactory};
+#[cfg(feature = "client")]
+use awc::error::WsClientError;
+#[cfg(feature = "client")]
+use awc::{ws, BoxedSocket, Client, ClientRequest, Connector};
 use bytes::{Bytes, BytesMut};
 use futures::future::{ok, Future, FutureExt};
 use futures::stream::{Stream, StreamExt};
+use futures::{Sink, SinkExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json;
 
+#[cfg(feature = "openssl")]
+use open_ssl::ssl::SslAcceptorBuilder;
+#[cfg(feature = "rustls")]
+use rust_tls::ServerConfig as RustlsServerConfig;
+
+pub use actix_http::clock::MockClock;
 pub use actix_http::test::TestBuffer;
 pub use actix_testing::{block_fn, block_on, run_on};
 
@@ -24,7 +48,9 @@ use crate::dev::{Body, MessageBody, Payload};
 use crate::request::HttpRequestPool;
 use crate::rmap::ResourceMap;
 use crate::service::{ServiceRequest, ServiceResponse};
-use crate::{Error, HttpRequest, HttpResponse};
+#[cfg(feature = "client")]
+use crate::HttpServer;
+use crate::{App, Error, HttpRequest, HttpResponse, Resource, Scope};
 
 /// Create service that always responds with `HttpResponse::Ok()`
 pub fn ok_service(
@@ -84,6 +110,83 @@ where
     srv.new_service(&cfg).await.unwrap()
 }
 
+/// Construct service for a single resource.
+///
+/// This is a shortcut for `init_service(App::new().service(resource))`,
+/// resource is registered in an empty `App` and the application service
+/// is built as usual. App data, middlewares and default service of the
+/// real application are not present, requests that do not match the
+/// resource get `404 Not Found`.
+///
+/// ```rust
+/// use actix_web::{test, web, HttpResponse, http::StatusCode};
+///
+/// #[test]
+/// fn test_init_resource() {
+///     test::block_on(async {
+///         let mut srv = test::init_resource(
+///             web::resource("/test").to(|| async { HttpResponse::Ok() }),
+///         ).await;
+///
+///         let req = test::TestRequest::with_uri("/test").to_request();
+///         let resp = test::call_service(&mut srv, req).await;
+///         assert_eq!(resp.status(), StatusCode::OK);
+///     })
+/// }
+/// ```
+pub async fn init_resource<T>(
+    resource: Resource<T>,
+) -> impl Service<Request = Request, Response = ServiceResponse<Body>, Error = Error>
+where
+    T: ServiceFactory<
+            Config = (),
+            Request = ServiceRequest,
+            Response = ServiceResponse,
+            Error = Error,
+            InitError = (),
+        > + 'static,
+{
+    init_service(App::new().service(resource)).await
+}
+
+/// Construct service for a single scope.
+///
+/// This is a shortcut for `init_service(App::new().service(scope))`, see
+/// `init_resource()`. Default service of the scope handles unmatched
+/// requests under scope prefix, other requests get `404 Not Found`.
+///
+/// ```rust
+/// use actix_web::{test, web, HttpResponse, http::StatusCode};
+///
+/// #[test]
+/// fn test_init_scope() {
+///     test::block_on(async {
+///         let mut srv = test::init_scope(
+///             web::scope("/app")
+///                 .route("/test", web::get().to(|| async { HttpResponse::Ok() })),
+///         ).await;
+///
+///         let req = test::TestRequest::with_uri("/app/test").to_request();
+///         let resp = test::call_service(&mut srv, req).await;
+///         assert_eq!(resp.status(), StatusCode::OK);
+///     })
+/// }
+/// ```
+pub async fn init_scope<T>(
+    scope: Scope<T>,
+) -> impl Service<Request = Request, Response = ServiceResponse<Body>, Error = Error>
+where
+    T: ServiceFactory<
+            Config = (),
+            Request = ServiceRequest,
+            Response = ServiceResponse,
+            Error = Error,
+            InitError = (),
+        > + 'static,
+{
+    init_service(App::new().service(scope)).await
+}
+
 /// Calls service and waits for response future completion.
 ///
 /// ```rust
@@ -256,6 +359,65 @@ where
         .unwrap_or_else(|_| panic!("read_response_json failed during deserialization"))
 }
 
+/// Helper function that returns a deserialized response body of a
+/// `ServiceResponse`, streaming bodies are read completely.
+///
+/// ```rust
+/// use actix_web::{test, web, App, HttpResponse};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct Person {
+///     id: String,
+///     name: String,
+/// }
+///
+/// #[test]
+/// fn test_get_person() {
+///     let mut app = test::init_service(App::new().route(
+///         "/people",
+///         web::get().to(|| async {
+///             HttpResponse::Ok().json(Person {
+///                 id: "12345".to_owned(),
+///                 name: "User name".to_owned(),
+///             })
+///         }),
+///     ))
+///     .await;
+///
+///     let req = test::TestRequest::get().uri("/people").to_request();
+///     let resp = test::call_service(&mut app, req).await;
+///     assert!(resp.status().is_success());
+///
+///     let person: Person = test::read_body_json(resp).await;
+///     assert_eq!(person.id, "12345");
+/// }
+/// ```
+pub async fn read_body_json<B, T>(res: ServiceResponse<B>) -> T
+where
+    B: MessageBody,
+    T: DeserializeOwned,
+{
+    let body = read_body(res).await;
+
+    serde_json::from_slice(&body)
+        .unwrap_or_else(|_| panic!("read_body_json failed during deserialization"))
+}
+
+/// Helper function that calls the service and returns deserialized
+/// response body.
+///
+/// It is equivalent of `call_service()` followed by `read_body_json()`.
+pub async fn call_and_read_body_json<S, B, T>(app: &mut S, req: Request) -> T
+where
+    S: Service<Request = Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+    T: DeserializeOwned,
+{
+    let res = call_service(app, req).await;
+    read_body_json(res).await
+}
+
 /// Test `Request` builder.
 ///
 /// For unit testing, actix provides a request builder type and a simple handler runner. TestRequest implements a builder-like pattern.
@@ -296,6 +458,7 @@ pub struct TestRequest {
     config: AppConfigInner,
     path: Path<Url>,
     app_data: Extensions,
+    extensions: Vec<Box<dyn FnOnce(&mut Extensions)>>,
 }
 
 impl Default for TestRequest {
@@ -306,6 +469,7 @@ impl Default for TestRequest {
             config: AppConfigInner::default(),
             path: Path::new(Url::new(Uri::default())),
             app_data: Extensions::new(),
+            extensions: Vec::new(),
         }
     }
 }
@@ -396,6 +560,12 @@ impl TestRequest {
         self
     }
 
+    /// Set peer address of the request
+    pub fn peer_addr(mut self, addr: net::SocketAddr) -> Self {
+        self.req.peer_addr(addr);
+        self
+    }
+
     /// Set request path pattern parameter
     pub fn param(mut self, name: &'static str, value: &'static str) -> Self {
         self.path.add_static(name, value);
@@ -408,6 +578,37 @@ impl TestRequest {
         self
     }
 
+    /// Set streaming request payload.
+    ///
+    /// Handler receives payload chunk by chunk, as they are yielded by
+    /// `stream`. Delays between chunks and errors of the stream simulate
+    /// slow or broken clients. `Content-Length` header is not set.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use actix_web::error::PayloadError;
+    /// use actix_web::test::TestRequest;
+    /// use bytes::Bytes;
+    /// use futures::stream::{self, StreamExt};
+    ///
+    /// let chunks = stream::iter(vec!["first", "second"]).then(|chunk| async move {
+    ///     tokio_timer::delay_for(Duration::from_millis(10)).await;
+    ///     Ok::<_, PayloadError>(Bytes::from(chunk))
+    /// });
+    /// let broken = stream::iter(vec![Err(PayloadError::Incomplete(None))]);
+    ///
+    /// let req = TestRequest::post()
+    ///     .set_payload_stream(chunks.chain(broken))
+    ///     .to_srv_request();
+    /// ```
+    pub fn set_payload_stream<S>(mut self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        self.req.set_payload_stream(stream);
+        self
+    }
+
     /// Serialize `data` to a URL encoded form and set it as the request payload. The `Content-Type`
     /// header is set to `application/x-www-form-urlencoded`.
     pub fn set_form<T: Serialize>(mut self, data: &T) -> Self {
@@ -428,6 +629,15 @@ impl TestRequest {
         self
     }
 
+    /// Encode `form` as `multipart/form-data` and set it as the request
+    /// payload. The `Content-Type` header is set with form boundary.
+    pub fn set_multipart(mut self, form: MultipartForm) -> Self {
+        let (content_type, body) = form.encode();
+        self.req.set_payload(body);
+        self.req.header(CONTENT_TYPE, content_type);
+        self
+    }
+
     /// Set application data. This is equivalent of `App::data()` method
     /// for testing purpose.
     pub fn data<T: 'static>(mut self, data: T) -> Self {
@@ -435,6 +645,18 @@ impl TestRequest {
         self
     }
 
+    /// Modify request extensions.
+    ///
+    /// Closure is called once request is created, it could be used to seed
+    /// state that is normally set by middleware, i.e. session data.
+    pub fn extensions<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Extensions) + 'static,
+    {
+        self.extensions.push(Box::new(f));
+        self
+    }
+
     #[cfg(test)]
     /// Set request config
     pub(crate) fn rmap(mut self, rmap: ResourceMap) -> Self {
@@ -444,13 +666,20 @@ impl TestRequest {
 
     /// Complete request creation and generate `Request` instance
     pub fn to_request(mut self) -> Request {
-        self.req.finish()
+        let req = self.req.finish();
+        for f in self.extensions {
+            f(&mut req.head().extensions_mut());
+        }
+        req
     }
 
     /// Complete request creation and generate `ServiceRequest` instance
     pub fn to_srv_request(mut self) -> ServiceRequest {
         let (head, payload) = self.req.finish().into_parts();
         self.path.get_mut().update(&head.uri);
+        for f in self.extensions {
+            f(&mut head.extensions_mut());
+        }
 
         ServiceRequest::new(HttpRequest::new(
             self.path,
@@ -472,6 +701,9 @@ impl TestRequest {
     pub fn to_http_request(mut self) -> HttpRequest {
         let (head, payload) = self.req.finish().into_parts();
         self.path.get_mut().update(&head.uri);
+        for f in self.extensions {
+            f(&mut head.extensions_mut());
+        }
 
         HttpRequest::new(
             self.path,
@@ -488,6 +720,9 @@ impl TestRequest {
     pub fn to_http_parts(mut self) -> (HttpRequest, Payload) {
         let (head, payload) = self.req.finish().into_parts();
         self.path.get_mut().update(&head.uri);
+        for f in self.extensions {
+            f(&mut head.extensions_mut());
+        }
 
         let req = HttpRequest::new(
             self.path,
@@ -503,6 +738,559 @@ impl TestRequest {
     }
 }
 
+/// `multipart/form-data` payload builder for `TestRequest::set_multipart()`.
+///
+/// ```rust
+/// use actix_web::test::{MultipartForm, TestRequest};
+///
+/// let req = TestRequest::post()
+///     .uri("/upload")
+///     .set_multipart(
+///         MultipartForm::new()
+///             .text("name", "actix")
+///             .file("avatar", "avatar.png", mime::IMAGE_PNG, &b"\x89PNG"[..]),
+///     )
+///     .to_request();
+/// ```
+#[derive(Debug, Default)]
+pub struct MultipartForm {
+    parts: Vec<MultipartPart>,
+}
+
+#[derive(Debug)]
+struct MultipartPart {
+    name: String,
+    file_name: Option<String>,
+    mime: Option<mime::Mime>,
+    data: Bytes,
+}
+
+impl MultipartForm {
+    /// Create empty form
+    pub fn new() -> Self {
+        MultipartForm::default()
+    }
+
+    /// Add text field
+    pub fn text<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.parts.push(MultipartPart {
+            name: name.into(),
+            file_name: None,
+            mime: None,
+            data: Bytes::from(value.into()),
+        });
+        self
+    }
+
+    /// Add file field
+    pub fn file<N, F, B>(
+        mut self,
+        name: N,
+        file_name: F,
+        mime: mime::Mime,
+        data: B,
+    ) -> Self
+    where
+        N: Into<String>,
+        F: Into<String>,
+        B: Into<Bytes>,
+    {
+        self.parts.push(MultipartPart {
+            name: name.into(),
+            file_name: Some(file_name.into()),
+            mime: Some(mime),
+            data: data.into(),
+        });
+        self
+    }
+
+    /// Returns `Content-Type` header value and encoded body.
+    fn encode(self) -> (String, Bytes) {
+        // boundary must not occur in any part
+        let mut boundary = "------------------------actixtestboundary".to_owned();
+        let mut n = 0;
+        while self
+            .parts
+            .iter()
+            .any(|p| contains(&p.data, boundary.as_bytes()))
+        {
+            n += 1;
+            boundary = format!("------------------------actixtestboundary{}", n);
+        }
+
+        let mut body = BytesMut::new();
+        for part in self.parts {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                    boundary,
+                    escape(&part.name)
+                )
+                .as_bytes(),
+            );
+            if let Some(ref file_name) = part.file_name {
+                body.extend_from_slice(
+                    format!("; filename=\"{}\"", escape(file_name)).as_bytes(),
+                );
+            }
+            body.extend_from_slice(b"\r\n");
+            if let Some(ref mime) = part.mime {
+                body.extend_from_slice(format!("Content-Type: {}\r\n", mime).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        (
+            format!("multipart/form-data; boundary={}", boundary),
+            body.freeze(),
+        )
+    }
+}
+
+fn contains(data: &[u8], pattern: &[u8]) -> bool {
+    data.windows(pattern.len()).any(|w| w == pattern)
+}
+
+/// Quote field name, like browsers do.
+fn escape(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Send websocket message, panics if connection fails.
+///
+/// Websocket helpers work with any framed connection, i.e. returned by
+/// `TestServer::ws_at()`.
+///
+/// ```rust,ignore
+/// use actix_web::{test, web, App};
+/// use actix_web::web::ws::{CloseCode, Message};
+///
+/// let srv = test::start(|| App::new().route("/ws", web::get().to(echo)));
+///
+/// let mut framed = srv.ws_at("/ws").await.unwrap();
+/// test::ws_send(&mut framed, Message::Text("hello".to_owned())).await;
+/// test::ws_expect_text(&mut framed, "hello").await;
+///
+/// test::ws_send(&mut framed, Message::Close(Some(CloseCode::Normal.into()))).await;
+/// test::ws_expect_close(&mut framed, Some(CloseCode::Normal)).await;
+/// ```
+pub async fn ws_send<T>(framed: &mut T, msg: WsMessage)
+where
+    T: Sink<WsMessage> + Unpin,
+    T::Error: fmt::Debug,
+{
+    framed
+        .send(msg)
+        .await
+        .unwrap_or_else(|e| panic!("Can not send websocket message: {:?}", e));
+}
+
+/// Receive next websocket frame, panics if connection is closed or
+/// fails.
+pub async fn ws_next<T, E>(framed: &mut T) -> WsFrame
+where
+    T: Stream<Item = Result<WsFrame, E>> + Unpin,
+    E: fmt::Debug,
+{
+    match framed.next().await {
+        Some(Ok(frame)) => frame,
+        Some(Err(e)) => panic!("Websocket connection error: {:?}", e),
+        None => panic!("Websocket connection is closed"),
+    }
+}
+
+/// Receive next websocket frame and check that it is text frame with
+/// `text` content.
+pub async fn ws_expect_text<T, E>(framed: &mut T, text: &str)
+where
+    T: Stream<Item = Result<WsFrame, E>> + Unpin,
+    E: fmt::Debug,
+{
+    match ws_next(framed).await {
+        WsFrame::Text(data) => assert_eq!(
+            data.as_ref().map(|b| &b[..]).unwrap_or(b""),
+            text.as_bytes(),
+            "Unexpected websocket text frame"
+        ),
+        frame => panic!("Expected websocket text frame, got {:?}", frame),
+    }
+}
+
+/// Receive next websocket frame and check that it is binary frame with
+/// `data` content.
+pub async fn ws_expect_binary<T, E>(framed: &mut T, data: &[u8])
+where
+    T: Stream<Item = Result<WsFrame, E>> + Unpin,
+    E: fmt::Debug,
+{
+    match ws_next(framed).await {
+        WsFrame::Binary(bin) => assert_eq!(
+            bin.as_ref().map(|b| &b[..]).unwrap_or(b""),
+            data,
+            "Unexpected websocket binary frame"
+        ),
+        frame => panic!("Expected websocket binary frame, got {:?}", frame),
+    }
+}
+
+/// Receive next websocket frame and check that it is close frame with
+/// `code` close code.
+pub async fn ws_expect_close<T, E>(framed: &mut T, code: Option<CloseCode>)
+where
+    T: Stream<Item = Result<WsFrame, E>> + Unpin,
+    E: fmt::Debug,
+{
+    match ws_next(framed).await {
+        WsFrame::Close(reason) => assert_eq!(
+            reason.map(|r| r.code),
+            code,
+            "Unexpected websocket close code"
+        ),
+        frame => panic!("Expected websocket close frame, got {:?}", frame),
+    }
+}
+
+/// Start test server with default configuration.
+///
+/// Server runs in a separate thread, it is stopped once `TestServer` is
+/// dropped. Application factory is called for the server worker.
+///
+/// ```rust
+/// use actix_web::{test, web, App, HttpResponse};
+///
+/// #[test]
+/// fn test_example() {
+///     test::block_on(async {
+///         let srv = test::start(
+///             || App::new().service(
+///                 web::resource("/").to(|| async { HttpResponse::Ok().finish() }))
+///         );
+///
+///         let response = srv.get("/").send().await.unwrap();
+///         assert!(response.status().is_success());
+///     })
+/// }
+/// ```
+#[cfg(feature = "client")]
+pub fn start<F, I, S, B>(factory: F) -> TestServer
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S>,
+    S: ServiceFactory<Config = ServerConfig, Request = Request> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: fmt::Debug,
+    S::Response: Into<HttpResponse<B>> + 'static,
+    <S::Service as Service>::Future: 'static,
+    B: MessageBody + 'static,
+{
+    start_with(TestServerConfig::default(), factory)
+}
+
+/// Start test server with custom configuration.
+///
+/// ```rust,ignore
+/// use actix_web::{test, web, App, HttpResponse};
+/// use open_ssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+///
+/// let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+/// builder.set_private_key_file("key.pem", SslFiletype::PEM).unwrap();
+/// builder.set_certificate_chain_file("cert.pem").unwrap();
+///
+/// let srv = test::start_with(test::config().openssl(builder), || {
+///     App::new().route("/", web::to(|| async { HttpResponse::Ok().finish() }))
+/// });
+/// assert!(srv.url("/").starts_with("https://"));
+/// ```
+#[cfg(feature = "client")]
+pub fn start_with<F, I, S, B>(cfg: TestServerConfig, factory: F) -> TestServer
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S>,
+    S: ServiceFactory<Config = ServerConfig, Request = Request> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: fmt::Debug,
+    S::Response: Into<HttpResponse<B>> + 'static,
+    <S::Service as Service>::Future: 'static,
+    B: MessageBody + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let tls = cfg.tls();
+    let client_timeout = cfg.client_timeout;
+
+    // run server in separate thread
+    thread::spawn(move || {
+        let sys = System::new("actix-test-server");
+        let tcp = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = tcp.local_addr().unwrap();
+
+        let srv = HttpServer::new(factory)
+            .workers(1)
+            .disable_signals()
+            .client_timeout(client_timeout);
+        let srv = match cfg.stream {
+            StreamType::Tcp => srv.listen(tcp),
+            #[cfg(feature = "openssl")]
+            StreamType::Openssl(builder) => srv.listen_openssl(tcp, builder),
+            #[cfg(feature = "rustls")]
+            StreamType::Rustls(config) => srv.listen_rustls(tcp, config),
+        };
+        srv.unwrap().start();
+
+        tx.send((System::current(), local_addr)).unwrap();
+        sys.run()
+    });
+
+    let (system, addr) = rx.recv().unwrap();
+
+    let client = {
+        let connector = {
+            #[cfg(feature = "openssl")]
+            {
+                use open_ssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+                // self-signed certificates of test servers are accepted
+                let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+                builder.set_verify(SslVerifyMode::NONE);
+                let _ = builder
+                    .set_alpn_protos(b"\x02h2\x08http/1.1")
+                    .map_err(|e| log::error!("Can not set alpn protocol: {:?}", e));
+                Connector::new()
+                    .conn_lifetime(time::Duration::from_secs(0))
+                    .timeout(time::Duration::from_millis(3000))
+                    .ssl(builder.build())
+                    .finish()
+            }
+            #[cfg(all(feature = "rustls", not(feature = "openssl")))]
+            {
+                use rust_tls::ClientConfig;
+                use std::sync::Arc;
+
+                // self-signed certificates of test servers are accepted
+                let mut config = ClientConfig::new();
+                config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+                config.dangerous().set_certificate_verifier(Arc::new(
+                    danger::NoCertificateVerification,
+                ));
+                Connector::new()
+                    .conn_lifetime(time::Duration::from_secs(0))
+                    .timeout(time::Duration::from_millis(3000))
+                    .rustls(Arc::new(config))
+                    .finish()
+            }
+            #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+            {
+                Connector::new()
+                    .conn_lifetime(time::Duration::from_secs(0))
+                    .timeout(time::Duration::from_millis(3000))
+                    .finish()
+            }
+        };
+
+        Client::build().connector(connector).finish()
+    };
+
+    TestServer {
+        addr,
+        client,
+        system,
+        tls,
+    }
+}
+
+#[cfg(all(feature = "client", feature = "rustls", not(feature = "openssl")))]
+mod danger {
+    pub(super) struct NoCertificateVerification;
+
+    impl rust_tls::ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _roots: &rust_tls::RootCertStore,
+            _presented_certs: &[rust_tls::Certificate],
+            _dns_name: webpki::DNSNameRef<'_>,
+            _ocsp: &[u8],
+        ) -> Result<rust_tls::ServerCertVerified, rust_tls::TLSError> {
+            Ok(rust_tls::ServerCertVerified::assertion())
+        }
+    }
+}
+
+/// Create default test server configuration.
+#[cfg(feature = "client")]
+pub fn config() -> TestServerConfig {
+    TestServerConfig::default()
+}
+
+/// Test server configuration.
+#[cfg(feature = "client")]
+pub struct TestServerConfig {
+    stream: StreamType,
+    client_timeout: u64,
+}
+
+#[cfg(feature = "client")]
+enum StreamType {
+    Tcp,
+    #[cfg(feature = "openssl")]
+    Openssl(SslAcceptorBuilder),
+    #[cfg(feature = "rustls")]
+    Rustls(RustlsServerConfig),
+}
+
+#[cfg(feature = "client")]
+impl Default for TestServerConfig {
+    fn default() -> Self {
+        TestServerConfig {
+            stream: StreamType::Tcp,
+            client_timeout: 5000,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl TestServerConfig {
+    /// Accept tls connections with openssl acceptor.
+    ///
+    /// Alpn protocols are set to "h2" and "http/1.1" like for
+    /// `HttpServer::listen_openssl()`.
+    #[cfg(feature = "openssl")]
+    pub fn openssl(mut self, builder: SslAcceptorBuilder) -> Self {
+        self.stream = StreamType::Openssl(builder);
+        self
+    }
+
+    /// Accept tls connections with rustls config.
+    ///
+    /// Alpn protocols are set to "h2" and "http/1.1" like for
+    /// `HttpServer::listen_rustls()`.
+    #[cfg(feature = "rustls")]
+    pub fn rustls(mut self, config: RustlsServerConfig) -> Self {
+        self.stream = StreamType::Rustls(config);
+        self
+    }
+
+    /// Set server client timeout in milliseconds for first request.
+    pub fn client_timeout(mut self, val: u64) -> Self {
+        self.client_timeout = val;
+        self
+    }
+
+    fn tls(&self) -> bool {
+        match self.stream {
+            StreamType::Tcp => false,
+            #[cfg(feature = "openssl")]
+            StreamType::Openssl(_) => true,
+            #[cfg(feature = "rustls")]
+            StreamType::Rustls(_) => true,
+        }
+    }
+}
+
+/// Test server controller
+///
+/// Server is stopped once controller is dropped.
+#[cfg(feature = "client")]
+pub struct TestServer {
+    addr: net::SocketAddr,
+    client: Client,
+    system: System,
+    tls: bool,
+}
+
+#[cfg(feature = "client")]
+impl TestServer {
+    /// Server address
+    pub fn addr(&self) -> net::SocketAddr {
+        self.addr
+    }
+
+    /// Construct test server url, scheme is `https` for tls servers
+    pub fn url(&self, uri: &str) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        if uri.starts_with('/') {
+            format!("{}://localhost:{}{}", scheme, self.addr.port(), uri)
+        } else {
+            format!("{}://localhost:{}/{}", scheme, self.addr.port(), uri)
+        }
+    }
+
+    /// Create `GET` request
+    pub fn get<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.get(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `POST` request
+    pub fn post<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.post(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `HEAD` request
+    pub fn head<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.head(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `PUT` request
+    pub fn put<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.put(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `PATCH` request
+    pub fn patch<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.patch(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `DELETE` request
+    pub fn delete<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.delete(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create `OPTIONS` request
+    pub fn options<S: AsRef<str>>(&self, path: S) -> ClientRequest {
+        self.client.options(self.url(path.as_ref()).as_str())
+    }
+
+    /// Create request with custom method
+    pub fn request<S: AsRef<str>>(&self, method: Method, path: S) -> ClientRequest {
+        self.client
+            .request(method, self.url(path.as_ref()).as_str())
+    }
+
+    /// Connect to websocket server at a given path
+    pub async fn ws_at(
+        &self,
+        path: &str,
+    ) -> Result<Framed<BoxedSocket, ws::Codec>, WsClientError> {
+        let url = self.url(path);
+        let connect = self.client.ws(url).connect();
+        connect.await.map(|(_, framed)| framed)
+    }
+
+    /// Connect to websocket server at `/`
+    pub async fn ws(&self) -> Result<Framed<BoxedSocket, ws::Codec>, WsClientError> {
+        self.ws_at("/").await
+    }
+
+    /// Stop http server
+    fn stop(&mut self) {
+        self.system.stop();
+    }
+}
+
+#[cfg(feature = "client")]
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_http::httpmessage::HttpMessage;
@@ -536,6 +1324,66 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_extensions() {
+        let req = TestRequest::default()
+            .extensions(|ext| ext.insert(10u32))
+            .extensions(|ext| *ext.get_mut::<u32>().unwrap() += 1)
+            .to_http_request();
+        assert_eq!(req.extensions().get::<u32>(), Some(&11));
+
+        let req = TestRequest::default()
+            .extensions(|ext| ext.insert("ext"))
+            .to_srv_request();
+        assert_eq!(req.extensions().get::<&str>(), Some(&"ext"));
+    }
+
+    #[test]
+    fn test_init_resource() {
+        block_on(async {
+            let mut srv = init_resource(web::resource("/test").data(10usize).route(
+                web::get().to(|data: web::Data<usize>| async move {
+                    HttpResponse::Ok().json(*data.get_ref())
+                }),
+            ))
+            .await;
+
+            let req = TestRequest::with_uri("/test").to_request();
+            let data: usize = call_and_read_body_json(&mut srv, req).await;
+            assert_eq!(data, 10);
+
+            let req = TestRequest::post().uri("/test").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+            let req = TestRequest::with_uri("/other").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        })
+    }
+
+    #[test]
+    fn test_init_scope() {
+        block_on(async {
+            let mut srv = init_scope(
+                web::scope("/app")
+                    .route("/test", web::get().to(|| async { HttpResponse::Ok() }))
+                    .default_service(|r: ServiceRequest| {
+                        ok(r.into_response(HttpResponse::Conflict()))
+                    }),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/app/test").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let req = TestRequest::with_uri("/app/other").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT);
+        })
+    }
+
     #[test]
     fn test_request_methods() {
         block_on(async {
@@ -628,6 +1476,46 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_body_json() {
+        block_on(async {
+            let mut app = init_service(
+                App::new()
+                    .route(
+                        "/people",
+                        web::post().to(|person: web::Json<Person>| async {
+                            HttpResponse::Ok().json(person.into_inner())
+                        }),
+                    )
+                    .route(
+                        "/stream",
+                        web::get().to(|| async {
+                            HttpResponse::Ok().streaming(futures::stream::iter(vec![
+                                Ok::<_, Error>(Bytes::from_static(b"{\"id\":\"1\",")),
+                                Ok(Bytes::from_static(b"\"name\":\"User\"}")),
+                            ]))
+                        }),
+                    ),
+            )
+            .await;
+
+            let req = TestRequest::post()
+                .uri("/people")
+                .set_json(&Person {
+                    id: "12345".to_owned(),
+                    name: "User name".to_owned(),
+                })
+                .to_request();
+            let result: Person = call_and_read_body_json(&mut app, req).await;
+            assert_eq!(&result.id, "12345");
+
+            let req = TestRequest::with_uri("/stream").to_request();
+            let resp = call_service(&mut app, req).await;
+            let result: Person = read_body_json(resp).await;
+            assert_eq!(&result.name, "User");
+        })
+    }
+
     #[test]
     fn test_request_response_form() {
         block_on(async {
@@ -686,6 +1574,197 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_multipart() {
+        block_on(async {
+            let form = MultipartForm::new().text("name", "actix").file(
+                "file",
+                "a\"b.txt",
+                mime::TEXT_PLAIN,
+                "------------------------actixtestboundary",
+            );
+            let (req, mut payload) =
+                TestRequest::post().set_multipart(form).to_http_parts();
+            assert_eq!(
+                req.headers().get(header::CONTENT_TYPE).unwrap(),
+                "multipart/form-data; boundary=------------------------actixtestboundary1"
+            );
+
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                body.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(
+                body.freeze(),
+                Bytes::from_static(
+                    b"--------------------------actixtestboundary1\r\n\
+                      Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                      actix\r\n\
+                      --------------------------actixtestboundary1\r\n\
+                      Content-Disposition: form-data; name=\"file\"; filename=\"a%22b.txt\"\r\n\
+                      Content-Type: text/plain\r\n\r\n\
+                      ------------------------actixtestboundary\r\n\
+                      --------------------------actixtestboundary1--\r\n"
+                )
+            );
+        })
+    }
+
+    #[test]
+    fn test_payload_stream() {
+        block_on(async {
+            let mut app = init_service(App::new().service(web::resource("/").to(
+                |mut body: web::Payload| async move {
+                    let mut chunks = Vec::new();
+                    while let Some(chunk) = body.next().await {
+                        chunks.push(chunk?);
+                    }
+                    Ok::<_, Error>(HttpResponse::Ok().json(chunks.len()))
+                },
+            )))
+            .await;
+
+            let chunks = futures::stream::iter(vec![
+                Ok(Bytes::from_static(b"first")),
+                Ok(Bytes::from_static(b"second")),
+            ]);
+            let req = TestRequest::post().set_payload_stream(chunks).to_request();
+            let count: usize = call_and_read_body_json(&mut app, req).await;
+            assert_eq!(count, 2);
+
+            let chunks = futures::stream::iter(vec![
+                Ok(Bytes::from_static(b"first")),
+                Err(PayloadError::Incomplete(None)),
+            ]);
+            let req = TestRequest::post().set_payload_stream(chunks).to_request();
+            let resp = call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_start() {
+        block_on(async {
+            let srv = start(|| {
+                App::new().service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("test") }),
+                )
+            });
+            assert!(srv.url("/").starts_with("http://"));
+
+            let mut response = srv.get("/").send().await.unwrap();
+            assert!(response.status().is_success());
+            let body = response.body().await.unwrap();
+            assert_eq!(body, Bytes::from_static(b"test"));
+        })
+    }
+
+    #[cfg(all(feature = "client", feature = "openssl"))]
+    #[test]
+    fn test_start_openssl() {
+        use open_ssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+
+        block_on(async {
+            let mut builder =
+                SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+            builder
+                .set_private_key_file("tests/key.pem", SslFiletype::PEM)
+                .unwrap();
+            builder
+                .set_certificate_chain_file("tests/cert.pem")
+                .unwrap();
+
+            let srv = start_with(config().openssl(builder), || {
+                App::new().service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("tls") }),
+                )
+            });
+            assert!(srv.url("/").starts_with("https://"));
+
+            let mut response = srv.get("/").send().await.unwrap();
+            assert!(response.status().is_success());
+            assert_eq!(response.version(), Version::HTTP_2);
+            let body = response.body().await.unwrap();
+            assert_eq!(body, Bytes::from_static(b"tls"));
+        })
+    }
+
+    #[cfg(all(feature = "client", feature = "rustls"))]
+    #[test]
+    fn test_start_rustls() {
+        use rust_tls::internal::pemfile::{certs, pkcs8_private_keys};
+        use rust_tls::{NoClientAuth, ServerConfig};
+        use std::fs::File;
+        use std::io::BufReader;
+
+        block_on(async {
+            let mut tls = ServerConfig::new(NoClientAuth::new());
+            let cert_file = &mut BufReader::new(File::open("tests/cert.pem").unwrap());
+            let key_file = &mut BufReader::new(File::open("tests/key.pem").unwrap());
+            let cert_chain = certs(cert_file).unwrap();
+            let mut keys = pkcs8_private_keys(key_file).unwrap();
+            tls.set_single_cert(cert_chain, keys.remove(0)).unwrap();
+
+            let srv = start_with(config().rustls(tls), || {
+                App::new().service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("tls") }),
+                )
+            });
+            assert!(srv.url("/").starts_with("https://"));
+
+            let mut response = srv.get("/").send().await.unwrap();
+            assert!(response.status().is_success());
+            assert_eq!(response.version(), Version::HTTP_2);
+            let body = response.body().await.unwrap();
+            assert_eq!(body, Bytes::from_static(b"tls"));
+        })
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_ws() {
+        use crate::ws::{self, CloseCode, Message};
+
+        async fn echo(
+            req: HttpRequest,
+            body: web::Payload,
+        ) -> Result<HttpResponse, Error> {
+            let (res, mut session, mut msgs) = ws::start(&req, body)?;
+            actix_rt::spawn(async move {
+                while let Some(Ok(msg)) = msgs.next().await {
+                    let res = match msg {
+                        Message::Text(text) => session.text(text).await,
+                        Message::Binary(bin) => session.binary(bin).await,
+                        Message::Close(reason) => {
+                            let _ = session.close(reason).await;
+                            return;
+                        }
+                        _ => Ok(()),
+                    };
+                    if res.is_err() {
+                        return;
+                    }
+                }
+            });
+            Ok(res)
+        }
+
+        block_on(async {
+            let srv = start(|| App::new().route("/ws", web::get().to(echo)));
+
+            let mut framed = srv.ws_at("/ws").await.unwrap();
+            ws_send(&mut framed, Message::Text("text".to_owned())).await;
+            ws_expect_text(&mut framed, "text").await;
+
+            ws_send(&mut framed, Message::Binary(Bytes::from_static(b"bin"))).await;
+            ws_expect_binary(&mut framed, b"bin").await;
+
+            ws_send(&mut framed, Message::Close(Some(CloseCode::Normal.into()))).await;
+            ws_expect_close(&mut framed, Some(CloseCode::Normal)).await;
+        })
+    }
+
     #[test]
     fn test_async_with_block() {
         block_on(async {
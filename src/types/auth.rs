@@ -0,0 +1,502 @@
+//! `Authorization` header extractors
+use std::fmt;
+use std::ops;
+use std::rc::Rc;
+
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorUnauthorized, ResponseError};
+use crate::extract::FromRequest;
+use crate::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use crate::http::StatusCode;
+use crate::request::HttpRequest;
+use crate::HttpResponse;
+
+#[cfg(feature = "jwt")]
+pub mod jwt;
+#[cfg(feature = "jwt")]
+pub use self::jwt::{Jwt, JwtConfig};
+
+/// Configuration of `Basic` and `Bearer` extractors.
+///
+/// Realm and scope are included in `WWW-Authenticate` challenge of
+/// *UNAUTHORIZED* responses.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// async fn index(auth: web::auth::Basic) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .data(web::auth::AuthConfig::default().realm("Restricted area"))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AuthConfig {
+    realm: Option<String>,
+    scope: Option<String>,
+}
+
+impl AuthConfig {
+    /// Set realm of the challenge.
+    pub fn realm(mut self, realm: &str) -> Self {
+        self.realm = Some(realm.to_owned());
+        self
+    }
+
+    /// Set scope of `Bearer` challenge.
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_owned());
+        self
+    }
+}
+
+/// Authentication scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// `Basic` scheme
+    Basic,
+    /// `Bearer` scheme
+    Bearer,
+}
+
+/// Kind of authentication error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthErrorKind {
+    /// `Authorization` header is missing or uses other scheme
+    Missing,
+    /// `Authorization` header is malformed
+    Invalid,
+    /// Credentials or token are rejected
+    Rejected,
+    /// Token does not have required scope
+    InsufficientScope,
+}
+
+/// Authentication error, rendered as response with `WWW-Authenticate`
+/// challenge.
+///
+/// *FORBIDDEN* response is used for `InsufficientScope` errors,
+/// *UNAUTHORIZED* for other kinds.
+///
+/// ```rust
+/// use actix_web::web::auth::{AuthError, AuthErrorKind, Basic};
+/// use actix_web::{HttpRequest, HttpResponse};
+///
+/// async fn index(req: HttpRequest, auth: Basic) -> Result<HttpResponse, AuthError> {
+///     if auth.user_id() == "admin" && auth.password() == Some("secret") {
+///         Ok(HttpResponse::Ok().finish())
+///     } else {
+///         Err(AuthError::basic(&req, AuthErrorKind::Rejected))
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AuthError {
+    scheme: Scheme,
+    kind: AuthErrorKind,
+    config: AuthConfig,
+}
+
+impl AuthError {
+    /// Error of `Basic` scheme, realm is taken from `AuthConfig` of the
+    /// request.
+    pub fn basic(req: &HttpRequest, kind: AuthErrorKind) -> AuthError {
+        AuthError::new(Scheme::Basic, req, kind)
+    }
+
+    /// Error of `Bearer` scheme, realm and scope are taken from `AuthConfig`
+    /// of the request.
+    pub fn bearer(req: &HttpRequest, kind: AuthErrorKind) -> AuthError {
+        AuthError::new(Scheme::Bearer, req, kind)
+    }
+
+    fn new(scheme: Scheme, req: &HttpRequest, kind: AuthErrorKind) -> AuthError {
+        AuthError {
+            scheme,
+            kind,
+            config: req.app_data::<AuthConfig>().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Scheme of the challenge
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    /// Kind of the error
+    pub fn kind(&self) -> AuthErrorKind {
+        self.kind
+    }
+
+    /// Value of `WWW-Authenticate` header
+    pub fn challenge(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(ref realm) = self.config.realm {
+            params.push(format!("realm=\"{}\"", quote(realm)));
+        }
+        match self.scheme {
+            Scheme::Basic => {
+                params.push("charset=\"UTF-8\"".to_owned());
+                format!("Basic {}", params.join(", "))
+            }
+            Scheme::Bearer => {
+                if let Some(ref scope) = self.config.scope {
+                    params.push(format!("scope=\"{}\"", quote(scope)));
+                }
+                let error = match self.kind {
+                    AuthErrorKind::Missing => None,
+                    AuthErrorKind::Invalid => Some("invalid_request"),
+                    AuthErrorKind::Rejected => Some("invalid_token"),
+                    AuthErrorKind::InsufficientScope => Some("insufficient_scope"),
+                };
+                if let Some(error) = error {
+                    params.push(format!("error=\"{}\"", error));
+                }
+                if params.is_empty() {
+                    "Bearer".to_owned()
+                } else {
+                    format!("Bearer {}", params.join(", "))
+                }
+            }
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            AuthErrorKind::Missing => write!(f, "Authorization header is missing"),
+            AuthErrorKind::Invalid => write!(f, "Authorization header is invalid"),
+            AuthErrorKind::Rejected => write!(f, "Credentials are rejected"),
+            AuthErrorKind::InsufficientScope => write!(f, "Insufficient scope"),
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn error_response(&self) -> HttpResponse {
+        let status = if self.kind == AuthErrorKind::InsufficientScope {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::UNAUTHORIZED
+        };
+        HttpResponse::build(status)
+            .header(WWW_AUTHENTICATE, self.challenge())
+            .finish()
+    }
+}
+
+/// Credentials of the request with `Authorization` header of the scheme.
+fn credentials<'a>(req: &'a HttpRequest, scheme: &str) -> Option<Result<&'a str, ()>> {
+    let value = req.headers().get(AUTHORIZATION)?;
+    let value = match value.to_str() {
+        Ok(value) => value.trim(),
+        Err(_) => return Some(Err(())),
+    };
+    if value.len() < scheme.len() || !value[..scheme.len()].eq_ignore_ascii_case(scheme)
+    {
+        return None;
+    }
+    let rest = &value[scheme.len()..];
+    if rest.is_empty() {
+        return Some(Err(()));
+    }
+    if !rest.starts_with(' ') {
+        // other scheme with the same prefix
+        return None;
+    }
+    Some(Ok(rest.trim_start()))
+}
+
+/// Credentials of `Basic` authentication scheme.
+///
+/// Missing or invalid `Authorization` header is rejected with
+/// *UNAUTHORIZED* response, validation of credentials is up to the
+/// handler. Realm of the challenge is configured with `AuthConfig`.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// async fn index(auth: web::auth::Basic) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basic {
+    user_id: String,
+    password: Option<String>,
+}
+
+impl Basic {
+    /// Parse `Authorization` header of the request.
+    pub fn parse(req: &HttpRequest) -> Result<Basic, AuthError> {
+        let credentials = match credentials(req, "Basic") {
+            Some(Ok(credentials)) => credentials,
+            Some(Err(_)) => return Err(AuthError::basic(req, AuthErrorKind::Invalid)),
+            None => return Err(AuthError::basic(req, AuthErrorKind::Missing)),
+        };
+
+        let decoded = base64::decode(credentials)
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .ok_or_else(|| AuthError::basic(req, AuthErrorKind::Invalid))?;
+        let mut parts = decoded.splitn(2, ':');
+        let user_id = parts.next().unwrap_or("").to_owned();
+        let password = parts.next().map(|p| p.to_owned());
+
+        Ok(Basic { user_id, password })
+    }
+
+    /// User id
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Password, if it is provided
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_ref().map(|p| p.as_str())
+    }
+}
+
+impl FromRequest for Basic {
+    type Config = AuthConfig;
+    type Error = AuthError;
+    type Future = Ready<Result<Basic, AuthError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match Basic::parse(req) {
+            Ok(auth) => ok(auth),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Token of `Bearer` authentication scheme.
+///
+/// Missing or invalid `Authorization` header is rejected with
+/// *UNAUTHORIZED* response, validation of the token is up to the handler.
+/// Realm and scope of the challenge are configured with `AuthConfig`.
+///
+/// ```rust
+/// use actix_web::web::auth::{AuthError, AuthErrorKind, Bearer};
+/// use actix_web::{web, App, HttpRequest, HttpResponse};
+///
+/// async fn index(req: HttpRequest, auth: Bearer) -> Result<HttpResponse, AuthError> {
+///     if auth.token() == "mF_9.B5f-4.1JqM" {
+///         Ok(HttpResponse::Ok().finish())
+///     } else {
+///         Err(AuthError::bearer(&req, AuthErrorKind::Rejected))
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bearer {
+    token: String,
+}
+
+impl Bearer {
+    /// Parse `Authorization` header of the request.
+    pub fn parse(req: &HttpRequest) -> Result<Bearer, AuthError> {
+        let token = match credentials(req, "Bearer") {
+            Some(Ok(token)) => token,
+            Some(Err(_)) => return Err(AuthError::bearer(req, AuthErrorKind::Invalid)),
+            None => return Err(AuthError::bearer(req, AuthErrorKind::Missing)),
+        };
+
+        // b64token = 1*( ALPHA / DIGIT / "-" / "." / "_" / "~" / "+" / "/" ) *"="
+        let valid = token.trim_end_matches('=').bytes().all(|b| {
+            b.is_ascii_alphanumeric()
+                || b == b'-'
+                || b == b'.'
+                || b == b'_'
+                || b == b'~'
+                || b == b'+'
+                || b == b'/'
+        });
+        if token.is_empty() || !valid {
+            return Err(AuthError::bearer(req, AuthErrorKind::Invalid));
+        }
+
+        Ok(Bearer {
+            token: token.to_owned(),
+        })
+    }
+
+    /// Token
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl FromRequest for Bearer {
+    type Config = AuthConfig;
+    type Error = AuthError;
+    type Future = Ready<Result<Bearer, AuthError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match Bearer::parse(req) {
+            Ok(auth) => ok(auth),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Identity of the request authenticated by `middleware::Authentication`.
+///
+/// Extractor fails with *UNAUTHORIZED* response if request is not
+/// authenticated, use `Option<Authenticated<T>>` for optional
+/// authentication.
+///
+/// ```rust
+/// use actix_web::web;
+///
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn index(user: web::auth::Authenticated<User>) -> String {
+///     format!("Hello, {}!", user.name)
+/// }
+/// # fn main() {}
+/// ```
+pub struct Authenticated<T>(Rc<T>);
+
+impl<T> Authenticated<T> {
+    pub(crate) fn new(identity: T) -> Self {
+        Authenticated(Rc::new(identity))
+    }
+
+    /// Shared reference to the identity
+    pub fn into_inner(self) -> Rc<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for Authenticated<T> {
+    fn clone(&self) -> Self {
+        Authenticated(self.0.clone())
+    }
+}
+
+impl<T> ops::Deref for Authenticated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Authenticated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Authenticated({:?})", self.0)
+    }
+}
+
+impl<T: 'static> FromRequest for Authenticated<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Authenticated<T>, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<Authenticated<T>>() {
+            Some(identity) => ok(identity.clone()),
+            None => err(ErrorUnauthorized("Request is not authenticated")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{block_on, TestRequest};
+
+    #[test]
+    fn test_basic() {
+        let req = TestRequest::with_header(
+            AUTHORIZATION,
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+        )
+        .to_http_request();
+        let auth = Basic::parse(&req).unwrap();
+        assert_eq!(auth.user_id(), "Aladdin");
+        assert_eq!(auth.password(), Some("open sesame"));
+
+        let req = TestRequest::with_header(AUTHORIZATION, "basic QWxhZGRpbg==")
+            .to_http_request();
+        let auth = Basic::parse(&req).unwrap();
+        assert_eq!(auth.user_id(), "Aladdin");
+        assert_eq!(auth.password(), None);
+
+        let req = TestRequest::with_header(AUTHORIZATION, "Basic !!!").to_http_request();
+        assert_eq!(
+            Basic::parse(&req).unwrap_err().kind(),
+            AuthErrorKind::Invalid
+        );
+
+        let req = TestRequest::with_header(AUTHORIZATION, "Bearer abc")
+            .data(AuthConfig::default().realm("Admin \"area\""))
+            .to_http_request();
+        let e = Basic::parse(&req).unwrap_err();
+        assert_eq!(e.kind(), AuthErrorKind::Missing);
+        let res = e.error_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Basic realm=\"Admin \\\"area\\\"\", charset=\"UTF-8\""
+        );
+    }
+
+    #[test]
+    fn test_bearer() {
+        block_on(async {
+            let (req, mut pl) =
+                TestRequest::with_header(AUTHORIZATION, "Bearer mF_9.B5f-4.1JqM")
+                    .to_http_parts();
+            let auth = Bearer::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(auth.token(), "mF_9.B5f-4.1JqM");
+
+            let (req, mut pl) = TestRequest::default()
+                .data(AuthConfig::default().realm("example").scope("read"))
+                .to_http_parts();
+            let e = Bearer::from_request(&req, &mut pl).await.unwrap_err();
+            assert_eq!(e.kind(), AuthErrorKind::Missing);
+            assert_eq!(e.challenge(), "Bearer realm=\"example\", scope=\"read\"");
+
+            let req =
+                TestRequest::with_header(AUTHORIZATION, "Bearer a b").to_http_request();
+            let e = Bearer::parse(&req).unwrap_err();
+            assert_eq!(e.challenge(), "Bearer error=\"invalid_request\"");
+
+            let req =
+                TestRequest::with_header(AUTHORIZATION, "Bearer").to_http_request();
+            assert_eq!(
+                Bearer::parse(&req).unwrap_err().kind(),
+                AuthErrorKind::Invalid
+            );
+
+            let e = AuthError::bearer(&req, AuthErrorKind::InsufficientScope);
+            assert_eq!(e.error_response().status(), StatusCode::FORBIDDEN);
+        })
+    }
+}
@@ -0,0 +1,347 @@
+//! Content-Range uploads
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use actix_http::error::PayloadError;
+use bytes::Bytes;
+use futures::future::{err, ok, Ready};
+use futures::io::{AsyncWrite, AsyncWriteExt};
+use futures::{Stream, StreamExt};
+
+use crate::dev::Payload;
+use crate::error::{Error, UploadRangeError};
+use crate::extract::FromRequest;
+use crate::http::header::{ContentRangeSpec, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use crate::http::StatusCode;
+use crate::request::HttpRequest;
+use crate::HttpResponse;
+
+/// Range of uploaded data from `Content-Range` header of `PUT` or `PATCH`
+/// request.
+///
+/// Header is validated against complete length and `Content-Length` of the
+/// request, invalid header is rejected with `UploadRangeError`. Request
+/// with `bytes */<length>` range and empty body queries state of the upload,
+/// it is reported by `UploadRange::is_empty()`. Server that did not receive
+/// complete upload responds with `UploadRange::resume_incomplete()`, so
+/// client continues with the rest of the data.
+///
+/// ```rust
+/// use std::fs::OpenOptions;
+/// use actix_web::{web, App, Error, HttpResponse};
+///
+/// async fn upload(
+///     range: web::UploadRange,
+///     mut payload: web::Payload,
+/// ) -> Result<HttpResponse, Error> {
+///     let file = web::block(|| {
+///         OpenOptions::new().create(true).write(true).open("upload.bin")
+///     })
+///     .await??;
+///     let (file, written) = range.write_file(&mut payload, file).await?;
+///
+///     if range.is_last() && written == range.len() {
+///         Ok(HttpResponse::Created().finish())
+///     } else {
+///         let received = web::block(move || file.metadata()).await??.len();
+///         Ok(web::UploadRange::resume_incomplete(received))
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/upload", web::put().to(upload));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadRange {
+    range: Option<(u64, u64)>,
+    total: Option<u64>,
+}
+
+impl UploadRange {
+    /// Parse and validate `Content-Range` header of the request.
+    pub fn parse(req: &HttpRequest) -> Result<UploadRange, UploadRangeError> {
+        let spec = req
+            .headers()
+            .get(&CONTENT_RANGE)
+            .ok_or(UploadRangeError::Missing)?
+            .to_str()
+            .ok()
+            .and_then(|val| val.parse::<ContentRangeSpec>().ok())
+            .ok_or(UploadRangeError::Invalid)?;
+        let (range, total) = match spec {
+            ContentRangeSpec::Bytes {
+                range,
+                instance_length,
+            } => (range, instance_length),
+            ContentRangeSpec::Unregistered { .. } => {
+                return Err(UploadRangeError::Invalid)
+            }
+        };
+
+        if let Some((first, last)) = range {
+            if first > last {
+                return Err(UploadRangeError::Invalid);
+            }
+        }
+        if let (Some((first, last)), Some(length)) = (range, total) {
+            if last >= length {
+                return Err(UploadRangeError::NotSatisfiable {
+                    first,
+                    last,
+                    length,
+                });
+            }
+        }
+
+        let upload = UploadRange { range, total };
+        let length = req
+            .headers()
+            .get(&CONTENT_LENGTH)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<u64>().ok());
+        match length {
+            Some(length) if length != upload.len() => {
+                Err(UploadRangeError::LengthMismatch)
+            }
+            _ => Ok(upload),
+        }
+    }
+
+    /// First and last byte of the range, `None` for `bytes */<length>`.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        self.range
+    }
+
+    /// Offset of the first byte of the range.
+    pub fn start(&self) -> u64 {
+        self.range.map(|(first, _)| first).unwrap_or(0)
+    }
+
+    /// Number of bytes in the range, it saturates at `u64::MAX` for range
+    /// of all offsets.
+    pub fn len(&self) -> u64 {
+        self.range
+            .map(|(first, last)| (last - first).saturating_add(1))
+            .unwrap_or(0)
+    }
+
+    /// Check if range does not contain data, request queries state of
+    /// the upload.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_none()
+    }
+
+    /// Complete length of the upload, if it is known.
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Check if range ends with the last byte of the upload.
+    pub fn is_last(&self) -> bool {
+        match (self.range, self.total) {
+            (Some((_, last)), Some(total)) => last + 1 == total,
+            _ => false,
+        }
+    }
+
+    /// Write payload of the request to the writer, returns number of
+    /// written bytes.
+    ///
+    /// Writer has to be positioned at the start of the range. Payload that
+    /// is longer than the range fails with `UploadRangeError::Overflow`,
+    /// data of the range is written up to that point. Number of written
+    /// bytes is less than `len()` if payload is shorter.
+    pub async fn write<S, W>(
+        &self,
+        payload: &mut S,
+        writer: &mut W,
+    ) -> Result<u64, Error>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut written = 0;
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk?;
+            if written + chunk.len() as u64 > self.len() {
+                return Err(UploadRangeError::Overflow.into());
+            }
+            writer.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        Ok(written)
+    }
+
+    /// Write payload of the request to the file at the start of the range,
+    /// see `UploadRange::write()`.
+    ///
+    /// File operations are executed on blocking thread pool, file is
+    /// returned along with number of written bytes.
+    pub async fn write_file<S>(
+        &self,
+        payload: &mut S,
+        file: File,
+    ) -> Result<(File, u64), Error>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        let start = self.start();
+        let mut file = crate::blocking::run(move || -> io::Result<File> {
+            let mut file = file;
+            file.seek(SeekFrom::Start(start))?;
+            Ok(file)
+        })
+        .await??;
+
+        let mut written = 0;
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk?;
+            if written + chunk.len() as u64 > self.len() {
+                return Err(UploadRangeError::Overflow.into());
+            }
+            written += chunk.len() as u64;
+            file = crate::blocking::run(move || -> io::Result<File> {
+                let mut file = file;
+                file.write_all(&chunk)?;
+                Ok(file)
+            })
+            .await??;
+        }
+        Ok((file, written))
+    }
+
+    /// *308 Resume Incomplete* response, `Range` header contains range of
+    /// received bytes.
+    pub fn resume_incomplete(received: u64) -> HttpResponse {
+        let mut res = HttpResponse::build(StatusCode::PERMANENT_REDIRECT);
+        if received > 0 {
+            res.header(RANGE, format!("bytes=0-{}", received - 1));
+        }
+        res.finish()
+    }
+}
+
+impl FromRequest for UploadRange {
+    type Config = ();
+    type Error = UploadRangeError;
+    type Future = Ready<Result<UploadRange, UploadRangeError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match UploadRange::parse(req) {
+            Ok(range) => ok(range),
+            Err(e) => err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::test::{block_on, TestRequest};
+
+    #[test]
+    fn test_parse() {
+        let req = TestRequest::with_header(CONTENT_RANGE, "bytes 0-3/8")
+            .header(CONTENT_LENGTH, "4")
+            .to_http_request();
+        let range = UploadRange::parse(&req).unwrap();
+        assert_eq!(range.range(), Some((0, 3)));
+        assert_eq!(range.len(), 4);
+        assert_eq!(range.total(), Some(8));
+        assert!(!range.is_last());
+
+        let req =
+            TestRequest::with_header(CONTENT_RANGE, "bytes 4-7/*").to_http_request();
+        let range = UploadRange::parse(&req).unwrap();
+        assert_eq!(range.start(), 4);
+        assert!(!range.is_last());
+
+        let req = TestRequest::with_header(CONTENT_RANGE, "bytes */8").to_http_request();
+        let range = UploadRange::parse(&req).unwrap();
+        assert!(range.is_empty());
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(UploadRange::parse(&req), Err(UploadRangeError::Missing));
+
+        let req =
+            TestRequest::with_header(CONTENT_RANGE, "rows 0-3/8").to_http_request();
+        assert_eq!(UploadRange::parse(&req), Err(UploadRangeError::Invalid));
+
+        let req =
+            TestRequest::with_header(CONTENT_RANGE, "bytes 4-8/8").to_http_request();
+        assert_eq!(
+            UploadRange::parse(&req),
+            Err(UploadRangeError::NotSatisfiable {
+                first: 4,
+                last: 8,
+                length: 8
+            })
+        );
+
+        let req = TestRequest::with_header(CONTENT_RANGE, "bytes 0-3/8")
+            .header(CONTENT_LENGTH, "8")
+            .to_http_request();
+        assert_eq!(
+            UploadRange::parse(&req),
+            Err(UploadRangeError::LengthMismatch)
+        );
+
+        let req =
+            TestRequest::with_header(CONTENT_RANGE, "bytes 4-3/*").to_http_request();
+        assert!(UploadRange::parse(&req).is_err());
+
+        let req = TestRequest::with_header(
+            CONTENT_RANGE,
+            format!("bytes 0-{}/*", u64::max_value()),
+        )
+        .to_http_request();
+        assert_eq!(UploadRange::parse(&req).unwrap().len(), u64::max_value());
+    }
+
+    #[test]
+    fn test_write() {
+        block_on(async {
+            let (req, mut pl) = TestRequest::with_header(CONTENT_RANGE, "bytes 4-7/8")
+                .set_payload("5678")
+                .to_http_parts();
+            let range = UploadRange::from_request(&req, &mut pl).await.unwrap();
+            assert!(range.is_last());
+
+            let mut buf = Vec::new();
+            assert_eq!(range.write(&mut pl, &mut buf).await.unwrap(), 4);
+            assert_eq!(buf, b"5678");
+
+            let (_, mut pl) =
+                TestRequest::default().set_payload("56789").to_http_parts();
+            assert!(range.write(&mut pl, &mut Vec::<u8>::new()).await.is_err());
+
+            let path = std::env::temp_dir().join(format!(
+                "actix-web-upload-range-{:016x}",
+                rand::random::<u64>()
+            ));
+            let file = File::create(&path).unwrap();
+            let (_, mut pl) = TestRequest::default().set_payload("5678").to_http_parts();
+            let (_, written) = range.write_file(&mut pl, file).await.unwrap();
+            assert_eq!(written, 4);
+
+            let mut data = Vec::new();
+            File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+            assert_eq!(data, b"\0\0\0\05678");
+            std::fs::remove_file(&path).unwrap();
+        })
+    }
+
+    #[test]
+    fn test_resume_incomplete() {
+        let res = UploadRange::resume_incomplete(4);
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers().get(RANGE).unwrap(), "bytes=0-3");
+
+        let res = UploadRange::resume_incomplete(0);
+        assert!(res.headers().get(RANGE).is_none());
+    }
+}
@@ -0,0 +1,255 @@
+//! Request deadline
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use actix_http::clock;
+use actix_http::{Error, Extensions};
+use futures::future::{err, ok, Ready};
+use tokio_timer::Timeout;
+
+use crate::dev::Payload;
+use crate::error::{BlockingError, DeadlineExceeded};
+use crate::extract::FromRequest;
+use crate::http::header::HeaderName;
+use crate::http::HeaderMap;
+use crate::request::HttpRequest;
+
+/// Default header of propagated request timeout, in milliseconds.
+pub(crate) const REQUEST_TIMEOUT: &str = "x-request-timeout";
+
+/// Deadline of a request.
+///
+/// Deadline is stored in request extensions, it is set by `Timeout`
+/// middleware, see [`Timeout`](../middleware/struct.Timeout.html). Handlers
+/// could use it for limiting time of downstream calls, so work is not
+/// continued once response is not awaited anymore.
+///
+/// Extractor fails with *INTERNAL SERVER ERROR* if deadline is not set,
+/// `Option<Deadline>` could be used instead.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{middleware, web, App, Error, HttpResponse};
+///
+/// async fn index(deadline: web::Deadline) -> Result<HttpResponse, Error> {
+///     let sum = deadline.block(|| 2 + 2).await?;
+///     Ok(HttpResponse::Ok().body(format!("{}", sum)))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Timeout::new(Duration::from_secs(5)))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Create deadline at the instant.
+    pub fn new(instant: Instant) -> Deadline {
+        Deadline(instant)
+    }
+
+    /// Create deadline that expires after `timeout`.
+    pub fn after(timeout: Duration) -> Deadline {
+        Deadline(clock::now() + timeout)
+    }
+
+    /// Deadline of the request, if it is set.
+    pub fn of(req: &HttpRequest) -> Option<Deadline> {
+        req.extensions().get::<Deadline>().cloned()
+    }
+
+    /// Store deadline in request extensions.
+    ///
+    /// Deadline that is already stored is kept if it is sooner, deadline
+    /// could not be extended.
+    pub fn insert(self, extensions: &mut Extensions) {
+        let deadline = extensions.get_or_insert_with(|| self);
+        if self < *deadline {
+            *deadline = self;
+        }
+    }
+
+    /// Instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Time remaining until the deadline, zero if it is expired.
+    pub fn remaining(&self) -> Duration {
+        let now = clock::now();
+        if self.0 > now {
+            self.0 - now
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+
+    /// Check if deadline is expired.
+    pub fn is_expired(&self) -> bool {
+        self.0 <= clock::now()
+    }
+
+    /// Run future with remaining time of the deadline.
+    pub async fn timeout<F, T>(&self, fut: F) -> Result<T, DeadlineExceeded>
+    where
+        F: Future<Output = T>,
+    {
+        if self.is_expired() {
+            return Err(DeadlineExceeded);
+        }
+        Timeout::new(fut, self.remaining())
+            .await
+            .map_err(|_| DeadlineExceeded)
+    }
+
+    /// Execute blocking function on a thread pool, see `web::block()`.
+    ///
+    /// Function is not executed if the deadline expires while it is waiting
+    /// for a free thread, `BlockingError::Timeout` is returned instead.
+    pub async fn block<F, R>(&self, f: F) -> Result<R, BlockingError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let deadline = *self;
+        let res = crate::blocking::run(move || {
+            if deadline.is_expired() {
+                None
+            } else {
+                Some(f())
+            }
+        })
+        .await?;
+        res.ok_or(BlockingError::Timeout)
+    }
+
+    /// Set timeout of client request to the remaining time and propagate
+    /// it to upstream service with `X-Request-Timeout` header.
+    #[cfg(feature = "client")]
+    pub fn propagate(&self, req: awc::ClientRequest) -> awc::ClientRequest {
+        let remaining = self.remaining();
+        req.timeout(remaining).set_header(
+            HeaderName::from_static(REQUEST_TIMEOUT),
+            remaining.as_millis().to_string(),
+        )
+    }
+
+    /// Deadline from request timeout header, in milliseconds.
+    ///
+    /// Header is sent by the client, timeout that overflows `Instant` is
+    /// ignored.
+    pub(crate) fn from_header(
+        headers: &HeaderMap,
+        name: &HeaderName,
+    ) -> Option<Deadline> {
+        headers
+            .get(name)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.trim().parse().ok())
+            .and_then(|ms| clock::now().checked_add(Duration::from_millis(ms)))
+            .map(Deadline)
+    }
+}
+
+impl FromRequest for Deadline {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Deadline, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match Deadline::of(req) {
+            Some(deadline) => ok(deadline),
+            None => {
+                log::debug!("Deadline is not set, use middleware::Timeout");
+                err(crate::error::ErrorInternalServerError(
+                    "Deadline is not set",
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev::clock::MockClock;
+    use crate::test::{block_on, TestRequest};
+
+    #[test]
+    fn test_insert() {
+        let mut extensions = Extensions::new();
+        let deadline = Deadline::after(Duration::from_secs(10));
+
+        deadline.insert(&mut extensions);
+        Deadline::after(Duration::from_secs(20)).insert(&mut extensions);
+        assert_eq!(extensions.get::<Deadline>(), Some(&deadline));
+
+        let sooner = Deadline::after(Duration::from_secs(1));
+        sooner.insert(&mut extensions);
+        assert_eq!(extensions.get::<Deadline>(), Some(&sooner));
+    }
+
+    #[test]
+    fn test_remaining() {
+        let clock = MockClock::new();
+        let deadline = Deadline::after(Duration::from_secs(10));
+        assert!(!deadline.is_expired());
+        assert!(deadline.remaining() <= Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(11));
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_timeout_and_block() {
+        block_on(async {
+            let deadline = Deadline::after(Duration::from_secs(10));
+            assert_eq!(deadline.timeout(async { 1 }).await, Ok(1));
+            assert_eq!(deadline.block(|| 2).await, Ok(2));
+
+            let expired = Deadline::new(clock::now());
+            assert_eq!(expired.timeout(async { 1 }).await, Err(DeadlineExceeded));
+            assert_eq!(expired.block(|| 2).await, Err(BlockingError::Timeout));
+        })
+    }
+
+    #[test]
+    fn test_extractor() {
+        block_on(async {
+            let (req, mut pl) = TestRequest::default().to_http_parts();
+            assert!(Deadline::from_request(&req, &mut pl).await.is_err());
+
+            let deadline = Deadline::after(Duration::from_secs(1));
+            let (req, mut pl) = TestRequest::default()
+                .extensions(move |ext| deadline.insert(ext))
+                .to_http_parts();
+            assert_eq!(
+                Deadline::from_request(&req, &mut pl).await.unwrap(),
+                deadline
+            );
+
+            let req =
+                TestRequest::with_header(REQUEST_TIMEOUT, "1500").to_http_request();
+            let deadline = Deadline::from_header(
+                req.headers(),
+                &HeaderName::from_static(REQUEST_TIMEOUT),
+            )
+            .unwrap();
+            assert!(deadline.remaining() <= Duration::from_millis(1500));
+
+            let req =
+                TestRequest::with_header(REQUEST_TIMEOUT, u64::max_value().to_string())
+                    .to_http_request();
+            assert!(Deadline::from_header(
+                req.headers(),
+                &HeaderName::from_static(REQUEST_TIMEOUT),
+            )
+            .is_none());
+        })
+    }
+}
@@ -0,0 +1,155 @@
+//! Common configuration of extractors
+use std::sync::Arc;
+
+use crate::error::{Error, ExtractorError};
+use crate::request::HttpRequest;
+
+type ErrorHandler = Arc<dyn Fn(ExtractorError, &HttpRequest) -> Error + Send + Sync>;
+
+/// Common configuration of `Json`, `Path`, `Query` and `Form` extractors.
+///
+/// Error handler receives errors of all these extractors, so format of error
+/// responses could be defined in one place. Error handler of extractor
+/// specific configuration, i.e. `JsonConfig::error_handler()`, takes
+/// precedence.
+///
+/// ```rust
+/// use actix_web::{error, web, App, HttpResponse};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// async fn index(path: web::Path<u32>, info: web::Json<Info>) -> String {
+///     format!("Welcome {} {}!", info.username, path)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .data(web::ExtractorConfig::default().error_handler(|err, req| {
+///             let res = HttpResponse::BadRequest().json(serde_json::json!({
+///                 "error": err.to_string(),
+///                 "path": req.path(),
+///             }));
+///             error::InternalError::from_response(err, res).into()
+///         }))
+///         .route("/users/{id}", web::post().to(index));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ExtractorConfig {
+    ehandler: Option<ErrorHandler>,
+}
+
+impl ExtractorConfig {
+    /// Set custom error handler
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ExtractorError, &HttpRequest) -> Error + Send + Sync + 'static,
+    {
+        self.ehandler = Some(Arc::new(f));
+        self
+    }
+
+    /// Error handler configured for the request.
+    pub(crate) fn error_handler_for(req: &HttpRequest) -> Option<ErrorHandler> {
+        req.app_data::<ExtractorConfig>()
+            .and_then(|cfg| cfg.ehandler.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::dev::ServiceResponse;
+    use crate::test::{block_on, read_body, TestRequest};
+    use crate::types::{Form, Json, JsonConfig, Path, Query};
+    use crate::{error, http, FromRequest, HttpResponse};
+
+    #[derive(Deserialize, Debug)]
+    struct Info {
+        id: u32,
+    }
+
+    fn config() -> ExtractorConfig {
+        ExtractorConfig::default().error_handler(|err, req| {
+            let kind = match err {
+                ExtractorError::Json(_) => "json",
+                ExtractorError::Path(_) => "path",
+                ExtractorError::Query(_) => "query",
+                ExtractorError::Form(_) => "form",
+            };
+            let res = HttpResponse::Conflict().body(format!("{} {}", kind, req.path()));
+            error::InternalError::from_response(err, res).into()
+        })
+    }
+
+    async fn error_body(err: Error) -> (http::StatusCode, String) {
+        let res: HttpResponse = err.into();
+        let status = res.status();
+        let req = TestRequest::default().to_http_request();
+        let body = read_body(ServiceResponse::new(req, res)).await;
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn test_error_handler() {
+        block_on(async {
+            let (req, mut pl) = TestRequest::with_uri("/abc/?id=x")
+                .data(config())
+                .to_http_parts();
+
+            let err = Path::<(u32,)>::from_request(&req, &mut pl)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error_body(err).await,
+                (http::StatusCode::CONFLICT, "path /abc/".to_owned())
+            );
+
+            let err = Query::<Info>::from_request(&req, &mut pl)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error_body(err).await,
+                (http::StatusCode::CONFLICT, "query /abc/".to_owned())
+            );
+
+            let err = Json::<Info>::from_request(&req, &mut pl).await.unwrap_err();
+            assert_eq!(
+                error_body(err).await,
+                (http::StatusCode::CONFLICT, "json /abc/".to_owned())
+            );
+
+            let err = Form::<Info>::from_request(&req, &mut pl).await.unwrap_err();
+            assert_eq!(
+                error_body(err).await,
+                (http::StatusCode::CONFLICT, "form /abc/".to_owned())
+            );
+        })
+    }
+
+    #[test]
+    fn test_extractor_config_precedence() {
+        block_on(async {
+            let (req, mut pl) = TestRequest::with_uri("/abc/")
+                .data(config())
+                .data(JsonConfig::default().error_handler(|err, _| {
+                    error::InternalError::from_response(
+                        err,
+                        HttpResponse::Gone().finish(),
+                    )
+                    .into()
+                }))
+                .to_http_parts();
+
+            let err = Json::<Info>::from_request(&req, &mut pl).await.unwrap_err();
+            let res: HttpResponse = err.into();
+            assert_eq!(res.status(), http::StatusCode::GONE);
+        })
+    }
+}
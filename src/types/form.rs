@@ -15,7 +15,7 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::dev::Decompress;
-use crate::error::UrlencodedError;
+use crate::error::{ExtractorError, UrlencodedError};
 use crate::extract::FromRequest;
 use crate::http::{
     header::{ContentType, CONTENT_LENGTH},
@@ -23,6 +23,7 @@ use crate::http::{
 };
 use crate::request::HttpRequest;
 use crate::responder::Responder;
+use crate::types::{ExtractorConfig, QueryConfig};
 
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
@@ -123,6 +124,7 @@ where
             .app_data::<FormConfig>()
             .map(|c| (c.limit, c.ehandler.clone()))
             .unwrap_or((16384, None));
+        let ehandler = ExtractorConfig::error_handler_for(req);
 
         UrlEncoded::new(req, payload)
             .limit(limit)
@@ -130,6 +132,8 @@ where
                 Err(e) => {
                     if let Some(err) = err {
                         Err((*err)(e, &req2))
+                    } else if let Some(ehandler) = ehandler {
+                        Err((*ehandler)(ExtractorError::Form(e), &req2))
                     } else {
                         Err(e.into())
                     }
@@ -239,11 +243,14 @@ impl Default for FormConfig {
 /// * content type is not `application/x-www-form-urlencoded`
 /// * content-length is greater than 32k
 ///
+/// Parsing options of `QueryConfig` are used, if it is registered
+/// as application data.
 pub struct UrlEncoded<U> {
     stream: Option<Decompress<Payload>>,
     limit: usize,
     length: Option<usize>,
     encoding: &'static Encoding,
+    config: QueryConfig,
     err: Option<UrlencodedError>,
     fut: Option<LocalBoxFuture<'static, Result<U, UrlencodedError>>>,
 }
@@ -273,9 +280,11 @@ impl<U> UrlEncoded<U> {
             }
         };
 
+        let config = req.app_data::<QueryConfig>().cloned().unwrap_or_default();
         let payload = Decompress::from_headers(payload.take(), req.headers());
         UrlEncoded {
             encoding,
+            config,
             stream: Some(payload),
             limit: 32_768,
             length: len,
@@ -292,6 +301,7 @@ impl<U> UrlEncoded<U> {
             err: Some(e),
             length: None,
             encoding: UTF_8,
+            config: QueryConfig::default(),
         }
     }
 
@@ -327,6 +337,7 @@ where
 
         // future
         let encoding = self.encoding;
+        let config = self.config.clone();
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
@@ -346,14 +357,16 @@ where
                 }
 
                 if encoding == UTF_8 {
-                    serde_urlencoded::from_bytes::<U>(&body)
+                    config
+                        .deserialize::<U>(&String::from_utf8_lossy(&body))
                         .map_err(|_| UrlencodedError::Parse)
                 } else {
                     let body = encoding
                         .decode_without_bom_handling_and_without_replacement(&body)
                         .map(|s| s.into_owned())
                         .ok_or(UrlencodedError::Parse)?;
-                    serde_urlencoded::from_str::<U>(&body)
+                    config
+                        .deserialize::<U>(&body)
                         .map_err(|_| UrlencodedError::Parse)
                 }
             }
@@ -486,6 +499,23 @@ mod tests {
                     counter: 123
                 }
             );
+
+            let (req, mut pl) = TestRequest::with_header(
+                CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .data(QueryConfig::default().semicolon_separator(true))
+            .set_payload(Bytes::from_static(b"hello=a+b;counter=123"))
+            .to_http_parts();
+
+            let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+            assert_eq!(
+                info,
+                Info {
+                    hello: "a b".to_owned(),
+                    counter: 123
+                }
+            );
         })
     }
 
@@ -17,10 +17,11 @@ use actix_http::http::{header::CONTENT_LENGTH, StatusCode};
 use actix_http::{HttpMessage, Payload, Response};
 
 use crate::dev::Decompress;
-use crate::error::{Error, JsonPayloadError};
+use crate::error::{Error, ExtractorError, JsonPayloadError};
 use crate::extract::FromRequest;
 use crate::request::HttpRequest;
 use crate::responder::Responder;
+use crate::types::ExtractorConfig;
 
 /// Json helper
 ///
@@ -183,6 +184,7 @@ where
             .app_data::<Self::Config>()
             .map(|c| (c.limit, c.ehandler.clone(), c.content_type.clone()))
             .unwrap_or((32768, None, None));
+        let ehandler = ExtractorConfig::error_handler_for(req);
 
         JsonBody::new(req, payload, ctype)
             .limit(limit)
@@ -195,6 +197,8 @@ where
                     );
                     if let Some(err) = err {
                         Err((*err)(e, &req2))
+                    } else if let Some(ehandler) = ehandler {
+                        Err((*ehandler)(ExtractorError::Json(e), &req2))
                     } else {
                         Err(e.into())
                     }
@@ -1,5 +1,9 @@
 //! Helper types
 
+pub mod auth;
+mod content_range;
+pub(crate) mod deadline;
+mod extractor;
 pub(crate) mod form;
 pub(crate) mod json;
 mod path;
@@ -7,8 +11,11 @@ pub(crate) mod payload;
 mod query;
 pub(crate) mod readlines;
 
+pub use self::content_range::UploadRange;
+pub use self::deadline::Deadline;
+pub use self::extractor::ExtractorConfig;
 pub use self::form::{Form, FormConfig};
 pub use self::json::{Json, JsonConfig};
 pub use self::path::{Path, PathConfig};
-pub use self::payload::{Payload, PayloadConfig};
+pub use self::payload::{Payload, PayloadConfig, SavedFile};
 pub use self::query::{Query, QueryConfig};
@@ -1,16 +1,20 @@
 //! Path extractor
 
+use std::borrow::Cow;
 use std::sync::Arc;
 use std::{fmt, ops};
 
 use actix_http::error::{Error, ErrorNotFound};
 use actix_router::PathDeserializer;
 use futures::future::{ready, Ready};
-use serde::de;
+use percent_encoding::percent_decode_str;
+use serde::de::{self, Deserializer, Error as _, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
 
 use crate::dev::Payload;
-use crate::error::PathError;
+use crate::error::{ExtractorError, PathError};
 use crate::request::HttpRequest;
+use crate::types::ExtractorConfig;
 use crate::FromRequest;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -165,13 +169,17 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let error_handler = req
+        let (error_handler, decoding) = req
             .app_data::<Self::Config>()
-            .map(|c| c.ehandler.clone())
-            .unwrap_or(None);
+            .map(|c| (c.ehandler.clone(), c.decoding))
+            .unwrap_or((None, None));
 
         ready(
-            de::Deserialize::deserialize(PathDeserializer::new(req.match_info()))
+            decoding
+                .map(|decoding| decoding.deserialize(req))
+                .unwrap_or_else(|| {
+                    de::Deserialize::deserialize(PathDeserializer::new(req.match_info()))
+                })
                 .map(|inner| Path { inner })
                 .map_err(move |e| {
                     log::debug!(
@@ -182,6 +190,11 @@ where
                     if let Some(error_handler) = error_handler {
                         let e = PathError::Deserialize(e);
                         (error_handler)(e, req)
+                    } else if let Some(ehandler) =
+                        ExtractorConfig::error_handler_for(req)
+                    {
+                        let e = ExtractorError::Path(PathError::Deserialize(e));
+                        (ehandler)(e, req)
                     } else {
                         ErrorNotFound(e)
                     }
@@ -192,6 +205,12 @@ where
 
 /// Path extractor configuration
 ///
+/// By default segments are extracted as matched by the router, it decodes
+/// percent-encoded sequences except `%2F` and `%2B`. Decoding options
+/// change this, if any of them is set, `%2B` is decoded to `+`. Sequences
+/// are not decoded for paths with encoded `%`, they could not be told apart
+/// from `%252F` and `%252B` after routing.
+///
 /// ```rust
 /// use actix_web::web::PathConfig;
 /// use actix_web::{error, web, App, FromRequest, HttpResponse};
@@ -227,6 +246,7 @@ where
 #[derive(Clone)]
 pub struct PathConfig {
     ehandler: Option<Arc<dyn Fn(PathError, &HttpRequest) -> Error + Send + Sync>>,
+    decoding: Option<Decoding>,
 }
 
 impl PathConfig {
@@ -238,11 +258,302 @@ impl PathConfig {
         self.ehandler = Some(Arc::new(f));
         self
     }
+
+    /// Decode `%2F` into `/`.
+    ///
+    /// Decoded separator is a part of the segment, it should not be
+    /// enabled for routes that serve files.
+    pub fn decode_slash(mut self, enabled: bool) -> Self {
+        self.decoding.get_or_insert_with(Decoding::default).slash = enabled;
+        self
+    }
+
+    /// Decode `+` as space.
+    pub fn plus_as_space(mut self, enabled: bool) -> Self {
+        self.decoding.get_or_insert_with(Decoding::default).plus = enabled;
+        self
+    }
+
+    /// Fail extraction if decoded path is not valid UTF-8.
+    ///
+    /// Otherwise router keeps such path percent-encoded.
+    pub fn strict_utf8(mut self, enabled: bool) -> Self {
+        self.decoding
+            .get_or_insert_with(Decoding::default)
+            .strict_utf8 = enabled;
+        self
+    }
 }
 
 impl Default for PathConfig {
     fn default() -> Self {
-        PathConfig { ehandler: None }
+        PathConfig {
+            ehandler: None,
+            decoding: None,
+        }
+    }
+}
+
+/// Decoding policy of path segments
+#[derive(Clone, Copy, Default)]
+struct Decoding {
+    slash: bool,
+    plus: bool,
+    strict_utf8: bool,
+}
+
+impl Decoding {
+    fn deserialize<T>(&self, req: &HttpRequest) -> Result<T, de::value::Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let raw = req.uri().path();
+        if self.strict_utf8 && percent_decode_str(raw).decode_utf8().is_err() {
+            return Err(de::value::Error::custom(format!(
+                "invalid utf-8 sequence in {:?}",
+                raw
+            )));
+        }
+
+        let escapes = !raw.contains("%25");
+        let segments = req
+            .match_info()
+            .iter()
+            .map(|(name, value)| (name, self.decode(value, escapes)))
+            .collect::<Vec<_>>();
+        de::Deserialize::deserialize(Segments(&segments))
+    }
+
+    /// Decode sequences that router keeps encoded, `%2F` and `%2B` are
+    /// decoded only if `escapes` is set.
+    fn decode<'a>(&self, value: &'a str, escapes: bool) -> Cow<'a, str> {
+        let special = |c: char| c == '%' || c == '+';
+        if !value.contains(special) {
+            return Cow::Borrowed(value);
+        }
+
+        let mut decoded = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(idx) = rest.find(special) {
+            decoded.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            let (ch, len) = match rest.get(..3) {
+                _ if rest.starts_with('+') => (if self.plus { ' ' } else { '+' }, 1),
+                Some(seq)
+                    if escapes && self.slash && seq.eq_ignore_ascii_case("%2F") =>
+                {
+                    ('/', 3)
+                }
+                Some(seq) if escapes && seq.eq_ignore_ascii_case("%2B") => ('+', 3),
+                _ => ('%', 1),
+            };
+            decoded.push(ch);
+            rest = &rest[len..];
+        }
+        decoded.push_str(rest);
+        Cow::Owned(decoded)
+    }
+}
+
+/// Deserializer of decoded segments
+struct Segments<'a>(&'a [(&'a str, Cow<'a, str>)]);
+
+impl<'a> Segments<'a> {
+    fn single(&self) -> Result<Segment<'a>, de::value::Error> {
+        if self.0.len() == 1 {
+            Ok(Segment(&self.0[0].1))
+        } else {
+            Err(de::value::Error::custom(format!(
+                "wrong number of parameters: {} expected 1",
+                self.0.len()
+            )))
+        }
+    }
+}
+
+macro_rules! single_value {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            self.single()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de, 'a> Deserializer<'de> for Segments<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(de::value::MapDeserializer::new(
+            self.0.iter().map(|(name, value)| (*name, Segment(value))),
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(de::value::SeqDeserializer::new(
+            self.0.iter().map(|(_, value)| Segment(value)),
+        ))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.0.len() != len {
+            Err(de::value::Error::custom(format!(
+                "wrong number of parameters: {} expected {}",
+                self.0.len(),
+                len
+            )))
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    single_value! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16
+        deserialize_i32 deserialize_i64 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_option deserialize_unit
+        deserialize_identifier deserialize_ignored_any
+    }
+}
+
+/// Deserializer of a single decoded segment
+struct Segment<'a>(&'a str);
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.parse::<$ty>() {
+                Ok(val) => visitor.$visit(val),
+                Err(_) => Err(de::value::Error::custom(format!(
+                    "can not parse {:?} to a {}",
+                    self.0,
+                    stringify!($ty)
+                ))),
+            }
+        }
+    )*};
+}
+
+impl<'de, 'a> Deserializer<'de> for Segment<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+        deserialize_char => visit_char(char),
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, de::value::Error> for Segment<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
     }
 }
 
@@ -362,6 +673,67 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_decoding() {
+        block_on(async {
+            let resource = ResourceDef::new("/{key}/{value}");
+
+            let mut req = TestRequest::with_uri("/a%2Fb/c+d").to_srv_request();
+            resource.match_path(req.match_info_mut());
+            let (req, mut pl) = req.into_parts();
+            let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(s.key, "a%2Fb");
+
+            let mut req = TestRequest::with_uri("/a%2Fb/c+d")
+                .data(PathConfig::default().decode_slash(true).plus_as_space(true))
+                .to_srv_request();
+            resource.match_path(req.match_info_mut());
+            let (req, mut pl) = req.into_parts();
+            let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(s.key, "a/b");
+            assert_eq!(s.value, "c d");
+            let s = Path::<(String, String)>::from_request(&req, &mut pl)
+                .await
+                .unwrap();
+            assert_eq!(s.0, "a/b");
+
+            let mut req = TestRequest::with_uri("/a%2Fb/32")
+                .data(PathConfig::default().strict_utf8(true))
+                .to_srv_request();
+            resource.match_path(req.match_info_mut());
+            let (req, mut pl) = req.into_parts();
+            let s = Path::<Test2>::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(s.key, "a%2Fb");
+            assert_eq!(s.value, 32);
+
+            // encoded `%` is decoded by router, sequences are ambiguous
+            let mut req = TestRequest::with_uri("/..%252F..%2F/%2541")
+                .data(PathConfig::default().decode_slash(true))
+                .to_srv_request();
+            resource.match_path(req.match_info_mut());
+            let (req, mut pl) = req.into_parts();
+            let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(s.key, "..%2F..%2F");
+            assert_eq!(s.value, "%41");
+
+            let mut req = TestRequest::with_uri("/%FF/32")
+                .data(PathConfig::default().strict_utf8(true))
+                .to_srv_request();
+            resource.match_path(req.match_info_mut());
+            let (req, mut pl) = req.into_parts();
+            assert!(Path::<Test2>::from_request(&req, &mut pl).await.is_err());
+        });
+
+        let decoding = Decoding {
+            slash: true,
+            ..Decoding::default()
+        };
+        assert_eq!(decoding.decode("100%", true), "100%");
+        assert_eq!(decoding.decode("a%2fb%2Bc+d", true), "a/b+c+d");
+        assert_eq!(decoding.decode("a%2fb%2Bc+d", false), "a%2fb%2Bc+d");
+        assert_eq!(decoding.decode("%E2%82", true), "%E2%82");
+    }
+
     #[test]
     fn test_custom_err_handler() {
         block_on(async {
@@ -1,5 +1,8 @@
 //! Payload/Bytes/String extractors
+use std::fs::{self, File};
 use std::future::Future;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::str;
 use std::task::{Context, Poll};
@@ -46,11 +49,101 @@ use crate::request::HttpRequest;
 /// ```
 pub struct Payload(pub crate::dev::Payload);
 
+/// Size of buffered payload that is written to a file at once
+const SINK_BUFFER: usize = 65_536;
+
 impl Payload {
     /// Deconstruct to a inner value
     pub fn into_inner(self) -> crate::dev::Payload {
         self.0
     }
+
+    /// Stream payload to a file, returns size and CRC-32 checksum of the
+    /// payload.
+    ///
+    /// File is created or truncated. Chunks of the payload are written on
+    /// blocking thread pool without copying, up to 64Kb of payload is
+    /// buffered in memory. Partially written file is removed on error.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, Error, HttpResponse};
+    ///
+    /// async fn upload(body: web::Payload) -> Result<HttpResponse, Error> {
+    ///     let saved = body.sink_to_file("/tmp/upload.bin").await?;
+    ///     Ok(HttpResponse::Ok().body(format!("{} {:08x}", saved.size, saved.checksum)))
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new().route("/upload", web::put().to(upload));
+    /// }
+    /// ```
+    pub async fn sink_to_file<P: Into<PathBuf>>(
+        mut self,
+        path: P,
+    ) -> Result<SavedFile, Error> {
+        let path = path.into();
+        let file = {
+            let path = path.clone();
+            crate::blocking::run(move || File::create(path)).await??
+        };
+
+        match self.write_file(file).await {
+            Ok((size, checksum)) => Ok(SavedFile {
+                path,
+                size,
+                checksum,
+            }),
+            Err(e) => {
+                let _ = crate::blocking::run(move || fs::remove_file(path)).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn write_file(&mut self, file: File) -> Result<(u64, u32), Error> {
+        let mut state = (file, crc32fast::Hasher::new());
+        let mut chunks = Vec::new();
+        let mut buffered = 0;
+        let mut size = 0;
+
+        loop {
+            let chunk = self.next().await.transpose()?;
+            let eof = chunk.is_none();
+            if let Some(chunk) = chunk {
+                buffered += chunk.len();
+                size += chunk.len() as u64;
+                chunks.push(chunk);
+            }
+
+            if buffered >= SINK_BUFFER || (eof && buffered > 0) {
+                let data = std::mem::take(&mut chunks);
+                buffered = 0;
+                state = crate::blocking::run(move || -> io::Result<_> {
+                    let (mut file, mut crc) = state;
+                    for chunk in data {
+                        file.write_all(&chunk)?;
+                        crc.update(&chunk);
+                    }
+                    Ok((file, crc))
+                })
+                .await??;
+            }
+            if eof {
+                return Ok((size, state.1.finalize()));
+            }
+        }
+    }
+}
+
+/// Payload saved with `Payload::sink_to_file()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedFile {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Size of the payload
+    pub size: u64,
+    /// CRC-32 (IEEE) checksum of the payload
+    pub checksum: u32,
 }
 
 impl Stream for Payload {
@@ -470,4 +563,25 @@ mod tests {
             _ => unreachable!("error"),
         }
     }
+
+    #[test]
+    fn test_sink_to_file() {
+        block_on(async {
+            let path = std::env::temp_dir()
+                .join(format!("actix-web-sink-{}", rand::random::<u64>()));
+            let (_, pl) = TestRequest::default()
+                .set_payload(Bytes::from("0123456789".repeat(10_000)))
+                .to_http_parts();
+
+            let saved = Payload(pl).sink_to_file(&path).await.unwrap();
+            assert_eq!(saved.path, path);
+            assert_eq!(saved.size, 100_000);
+            assert_eq!(saved.checksum, 0x1cc5_b887);
+            assert_eq!(
+                std::fs::read(&path).unwrap(),
+                "0123456789".repeat(10_000).as_bytes()
+            );
+            std::fs::remove_file(&path).unwrap();
+        })
+    }
 }
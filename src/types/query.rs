@@ -5,13 +5,15 @@ use std::{fmt, ops};
 
 use actix_http::error::Error;
 use futures::future::{err, ok, Ready};
-use serde::de;
+use percent_encoding::percent_decode_str;
+use serde::de::{self, Error as _};
 use serde_urlencoded;
 
 use crate::dev::Payload;
-use crate::error::QueryPayloadError;
+use crate::error::{ExtractorError, QueryPayloadError};
 use crate::extract::FromRequest;
 use crate::request::HttpRequest;
+use crate::types::ExtractorConfig;
 
 /// Extract typed information from the request's query.
 ///
@@ -143,7 +145,9 @@ where
             .map(|c| c.ehandler.clone())
             .unwrap_or(None);
 
-        serde_urlencoded::from_str::<T>(req.query_string())
+        req.app_data::<Self::Config>()
+            .map(|cfg| cfg.deserialize::<T>(req.query_string()))
+            .unwrap_or_else(|| serde_urlencoded::from_str::<T>(req.query_string()))
             .map(|val| ok(Query(val)))
             .unwrap_or_else(move |e| {
                 let e = QueryPayloadError::Deserialize(e);
@@ -156,6 +160,8 @@ where
 
                 let e = if let Some(error_handler) = error_handler {
                     (error_handler)(e, req)
+                } else if let Some(ehandler) = ExtractorConfig::error_handler_for(req) {
+                    (ehandler)(ExtractorError::Query(e), req)
                 } else {
                     e.into()
                 };
@@ -167,6 +173,8 @@ where
 
 /// Query extractor configuration
 ///
+/// Parsing options are also used by `Form` extractor and `UrlEncoded` future.
+///
 /// ## Example
 ///
 /// ```rust
@@ -201,6 +209,9 @@ where
 pub struct QueryConfig {
     ehandler:
         Option<Arc<dyn Fn(QueryPayloadError, &HttpRequest) -> Error + Send + Sync>>,
+    semicolon: bool,
+    plus_as_space: bool,
+    strict_utf8: bool,
 }
 
 impl QueryConfig {
@@ -212,11 +223,63 @@ impl QueryConfig {
         self.ehandler = Some(Arc::new(f));
         self
     }
+
+    /// Accept `;` as separator of pairs, in addition to `&`.
+    ///
+    /// By default `;` is a part of the key or value.
+    pub fn semicolon_separator(mut self, enabled: bool) -> Self {
+        self.semicolon = enabled;
+        self
+    }
+
+    /// Decode `+` as space.
+    ///
+    /// By default `+` is decoded as space, if disabled `+` is kept as is.
+    pub fn plus_as_space(mut self, enabled: bool) -> Self {
+        self.plus_as_space = enabled;
+        self
+    }
+
+    /// Reject percent-encoded keys and values that are not valid UTF-8.
+    ///
+    /// By default invalid sequences are replaced with `U+FFFD`.
+    pub fn strict_utf8(mut self, enabled: bool) -> Self {
+        self.strict_utf8 = enabled;
+        self
+    }
+
+    /// Deserialize query string with configured options.
+    pub(crate) fn deserialize<T>(&self, query: &str) -> Result<T, de::value::Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        if self.strict_utf8 && percent_decode_str(query).decode_utf8().is_err() {
+            return Err(de::value::Error::custom("invalid utf-8 sequence"));
+        }
+        if !self.semicolon && self.plus_as_space {
+            return serde_urlencoded::from_str(query);
+        }
+
+        let mut normalized = String::with_capacity(query.len());
+        for ch in query.chars() {
+            match ch {
+                ';' if self.semicolon => normalized.push('&'),
+                '+' if !self.plus_as_space => normalized.push_str("%2B"),
+                _ => normalized.push(ch),
+            }
+        }
+        serde_urlencoded::from_str(&normalized)
+    }
 }
 
 impl Default for QueryConfig {
     fn default() -> Self {
-        QueryConfig { ehandler: None }
+        QueryConfig {
+            ehandler: None,
+            semicolon: false,
+            plus_as_space: true,
+            strict_utf8: false,
+        }
     }
 }
 
@@ -272,6 +335,40 @@ mod tests {
         })
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Search {
+        q: String,
+        page: u32,
+    }
+
+    #[test]
+    fn test_config() {
+        let cfg = QueryConfig::default();
+        let s: Search = cfg.deserialize("q=a+b;c&page=1").unwrap();
+        assert_eq!(s.q, "a b;c");
+        let s: Search = cfg.deserialize("q=%FF&page=1").unwrap();
+        assert_eq!(s.q, "\u{FFFD}");
+
+        let cfg = QueryConfig::default()
+            .semicolon_separator(true)
+            .plus_as_space(false)
+            .strict_utf8(true);
+        let s: Search = cfg.deserialize("q=a+b%20c;page=2").unwrap();
+        assert_eq!(s.q, "a+b c");
+        assert_eq!(s.page, 2);
+        assert!(cfg.deserialize::<Search>("q=%FF&page=1").is_err());
+
+        block_on(async {
+            let req = TestRequest::with_uri("/?q=x+y;page=3")
+                .data(QueryConfig::default().semicolon_separator(true))
+                .to_srv_request();
+            let (req, mut pl) = req.into_parts();
+            let s = Query::<Search>::from_request(&req, &mut pl).await.unwrap();
+            assert_eq!(s.q, "x y");
+            assert_eq!(s.page, 3);
+        })
+    }
+
     #[test]
     fn test_custom_error_responder() {
         block_on(async {
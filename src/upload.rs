@@ -0,0 +1,965 @@
+//! Resumable uploads
+//!
+//! Server side of [tus](https://tus.io/protocols/resumable-upload.html)
+//! resumable upload protocol, version `1.0.0` with `creation`, `expiration`
+//! and `termination` extensions.
+//!
+//! Requires `upload` feature.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use actix_web::upload::{FileStorage, Uploads};
+//! use actix_web::App;
+//!
+//! fn main() {
+//!     let app = App::new().service(
+//!         Uploads::new(FileStorage::new("/var/lib/uploads"))
+//!             .max_size(1024 * 1024 * 1024)
+//!             .expiration(Duration::from_secs(24 * 60 * 60))
+//!             .scope("/files"),
+//!     );
+//! }
+//! ```
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use actix_http::ResponseBuilder;
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio_timer::delay_for;
+
+use crate::error::{Error, ErrorConflict, ErrorNotFound};
+use crate::http::header::{HttpDate, CACHE_CONTROL, CONTENT_TYPE, LOCATION};
+use crate::http::{Method, StatusCode};
+use crate::request::HttpRequest;
+use crate::scope::Scope;
+use crate::{web, HttpResponse};
+
+/// Supported version of the protocol
+pub const TUS_VERSION: &str = "1.0.0";
+
+const TUS_RESUMABLE: &str = "tus-resumable";
+const TUS_VERSION_HEADER: &str = "tus-version";
+const TUS_EXTENSION: &str = "tus-extension";
+const TUS_MAX_SIZE: &str = "tus-max-size";
+const UPLOAD_OFFSET: &str = "upload-offset";
+const UPLOAD_LENGTH: &str = "upload-length";
+const UPLOAD_DEFER_LENGTH: &str = "upload-defer-length";
+const UPLOAD_METADATA: &str = "upload-metadata";
+const UPLOAD_EXPIRES: &str = "upload-expires";
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+/// State of an upload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadInfo {
+    /// Number of received bytes
+    pub offset: u64,
+    /// Length of the upload, `None` if it is not known yet
+    pub length: Option<u64>,
+    /// Value of `Upload-Metadata` header sent on creation
+    pub metadata: Option<String>,
+    /// Unfinished upload is removed after this time
+    pub expires: Option<SystemTime>,
+}
+
+impl UploadInfo {
+    /// Check if all data of the upload is received.
+    pub fn is_complete(&self) -> bool {
+        self.length == Some(self.offset)
+    }
+
+    /// Check if unfinished upload is expired.
+    pub fn is_expired(&self) -> bool {
+        !self.is_complete()
+            && self
+                .expires
+                .map(|expires| expires <= SystemTime::now())
+                .unwrap_or(false)
+    }
+}
+
+/// Storage of uploads.
+///
+/// Storage has to be shared by all workers of the server, `MemoryStorage`
+/// and `FileStorage` are available.
+pub trait UploadStorage: 'static {
+    /// Create new upload, returns id of the upload.
+    ///
+    /// Id must consist of ascii letters and digits.
+    fn create(&self, info: UploadInfo)
+        -> LocalBoxFuture<'static, Result<String, Error>>;
+
+    /// State of the upload, `None` if upload does not exist.
+    fn info(
+        &self,
+        id: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<UploadInfo>, Error>>;
+
+    /// Append data to the upload, returns new offset.
+    ///
+    /// Storage must fail with *CONFLICT* error if offset of the upload is not
+    /// `offset`, so concurrent requests do not corrupt the upload.
+    fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        data: Bytes,
+    ) -> LocalBoxFuture<'static, Result<u64, Error>>;
+
+    /// Set length of the upload that is created with deferred length.
+    fn set_length(
+        &self,
+        id: &str,
+        length: u64,
+    ) -> LocalBoxFuture<'static, Result<(), Error>>;
+
+    /// Remove the upload and its data.
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, Result<(), Error>>;
+
+    /// Ids of unfinished uploads that are expired, they are removed by
+    /// periodic sweep of `Uploads`.
+    ///
+    /// Default implementation returns no ids, so expired uploads are removed
+    /// only when they are accessed.
+    fn expired(&self) -> LocalBoxFuture<'static, Result<Vec<String>, Error>> {
+        ok(Vec::new()).boxed_local()
+    }
+}
+
+fn new_id() -> String {
+    let mut rng = rand::thread_rng();
+    format!("{:016x}{:016x}", rng.gen::<u64>(), rng.gen::<u64>())
+}
+
+/// Storage that keeps uploads in memory.
+///
+/// Clones share uploads, so storage could be created once and cloned to
+/// application factory of each worker.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    uploads: Arc<Mutex<HashMap<String, (UploadInfo, BytesMut)>>>,
+}
+
+impl MemoryStorage {
+    /// Create empty storage.
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    /// Received data of the upload.
+    pub fn data(&self, id: &str) -> Option<Bytes> {
+        self.uploads
+            .lock()
+            .get(id)
+            .map(|(_, data)| Bytes::from(&data[..]))
+    }
+}
+
+impl UploadStorage for MemoryStorage {
+    fn create(
+        &self,
+        info: UploadInfo,
+    ) -> LocalBoxFuture<'static, Result<String, Error>> {
+        let id = new_id();
+        self.uploads
+            .lock()
+            .insert(id.clone(), (info, BytesMut::new()));
+        ok(id).boxed_local()
+    }
+
+    fn info(
+        &self,
+        id: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<UploadInfo>, Error>> {
+        ok(self.uploads.lock().get(id).map(|(info, _)| info.clone())).boxed_local()
+    }
+
+    fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        data: Bytes,
+    ) -> LocalBoxFuture<'static, Result<u64, Error>> {
+        let res = match self.uploads.lock().get_mut(id) {
+            Some((info, buf)) if info.offset == offset => {
+                buf.extend_from_slice(&data);
+                info.offset += data.len() as u64;
+                Ok(info.offset)
+            }
+            Some(_) => Err(ErrorConflict("Offset of the upload is changed")),
+            None => Err(ErrorNotFound("Upload is not found")),
+        };
+        ready(res).boxed_local()
+    }
+
+    fn set_length(
+        &self,
+        id: &str,
+        length: u64,
+    ) -> LocalBoxFuture<'static, Result<(), Error>> {
+        if let Some((info, _)) = self.uploads.lock().get_mut(id) {
+            info.length = Some(length);
+        }
+        ok(()).boxed_local()
+    }
+
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, Result<(), Error>> {
+        self.uploads.lock().remove(id);
+        ok(()).boxed_local()
+    }
+
+    fn expired(&self) -> LocalBoxFuture<'static, Result<Vec<String>, Error>> {
+        let ids = self
+            .uploads
+            .lock()
+            .iter()
+            .filter(|(_, (info, _))| info.is_expired())
+            .map(|(id, _)| id.clone())
+            .collect();
+        ok(ids).boxed_local()
+    }
+}
+
+/// Storage that keeps uploads in a directory.
+///
+/// Data of the upload is stored in a file named by id of the upload, its
+/// state is stored next to it in `<id>.info` json file. File operations are
+/// executed on blocking thread pool.
+///
+/// Appends to an upload are serialized by clones of the storage, so the
+/// directory must not be shared by several processes.
+#[derive(Clone)]
+pub struct FileStorage {
+    dir: Arc<PathBuf>,
+    locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl FileStorage {
+    /// Create storage in the directory, directory must exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> FileStorage {
+        FileStorage {
+            dir: Arc::new(dir.into()),
+            locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Path of the file with data of the upload.
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.info", id))
+    }
+}
+
+fn read_info(path: &Path) -> io::Result<UploadInfo> {
+    let data = fs::read(path)?;
+    serde_json::from_slice(&data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_info(path: &Path, info: &UploadInfo) -> io::Result<()> {
+    let data = serde_json::to_vec(info)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, data)
+}
+
+/// Append data to the file if its length is `offset`.
+fn append_at(path: &Path, offset: u64, data: &[u8]) -> io::Result<Option<u64>> {
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    if file.metadata()?.len() != offset {
+        return Ok(None);
+    }
+    file.write_all(data)?;
+    Ok(Some(offset + data.len() as u64))
+}
+
+fn blocking<F, R>(f: F) -> LocalBoxFuture<'static, Result<R, Error>>
+where
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    async move { Ok(crate::blocking::run(f).await??) }.boxed_local()
+}
+
+impl UploadStorage for FileStorage {
+    fn create(
+        &self,
+        info: UploadInfo,
+    ) -> LocalBoxFuture<'static, Result<String, Error>> {
+        let id = new_id();
+        let path = self.path(&id);
+        let info_path = self.info_path(&id);
+        blocking(move || {
+            File::create(path)?;
+            write_info(&info_path, &info)?;
+            Ok(id)
+        })
+    }
+
+    fn info(
+        &self,
+        id: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<UploadInfo>, Error>> {
+        let path = self.path(id);
+        let info_path = self.info_path(id);
+        blocking(move || {
+            let mut info = match read_info(&info_path) {
+                Ok(info) => info,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            info.offset = fs::metadata(path)?.len();
+            Ok(Some(info))
+        })
+    }
+
+    fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        data: Bytes,
+    ) -> LocalBoxFuture<'static, Result<u64, Error>> {
+        let path = self.path(id);
+        let locks = self.locks.clone();
+        let id = id.to_owned();
+        let fut = blocking(move || {
+            // offset check and write of concurrent requests must not interleave
+            let lock = locks.lock().entry(id.clone()).or_default().clone();
+            let res = {
+                let _guard = lock.lock();
+                append_at(&path, offset, &data)
+            };
+
+            let mut locks = locks.lock();
+            if Arc::strong_count(&lock) == 2 {
+                locks.remove(&id);
+            }
+            res
+        });
+        async move {
+            fut.await?
+                .ok_or_else(|| ErrorConflict("Offset of the upload is changed"))
+        }
+        .boxed_local()
+    }
+
+    fn set_length(
+        &self,
+        id: &str,
+        length: u64,
+    ) -> LocalBoxFuture<'static, Result<(), Error>> {
+        let info_path = self.info_path(id);
+        blocking(move || {
+            let mut info = read_info(&info_path)?;
+            info.length = Some(length);
+            write_info(&info_path, &info)
+        })
+    }
+
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, Result<(), Error>> {
+        let path = self.path(id);
+        let info_path = self.info_path(id);
+        blocking(move || {
+            for path in &[info_path, path] {
+                match fs::remove_file(path) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                    res => res?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn expired(&self) -> LocalBoxFuture<'static, Result<Vec<String>, Error>> {
+        let dir = self.dir.clone();
+        blocking(move || {
+            let mut ids = Vec::new();
+            for entry in fs::read_dir(dir.as_path())? {
+                let path = entry?.path();
+                if path.extension().map_or(true, |ext| ext != "info") {
+                    continue;
+                }
+                let mut info = match read_info(&path) {
+                    Ok(info) => info,
+                    Err(_) => continue,
+                };
+                let data = path.with_extension("");
+                info.offset = fs::metadata(&data).map(|md| md.len()).unwrap_or(0);
+                if info.is_expired() {
+                    if let Some(id) = data.file_name().and_then(|name| name.to_str()) {
+                        ids.push(id.to_owned());
+                    }
+                }
+            }
+            Ok(ids)
+        })
+    }
+}
+
+/// Resumable uploads service.
+///
+/// `Uploads::scope()` creates scope that handles protocol requests:
+///
+/// * `OPTIONS /` - capabilities of the server
+/// * `POST /` - creation of an upload, `Location` of the upload is returned
+/// * `HEAD /{id}` - offset of the upload
+/// * `PATCH /{id}` - data of the upload at `Upload-Offset`
+/// * `DELETE /{id}` - termination of the upload
+///
+/// Unfinished upload is removed once it is accessed after its expiration,
+/// abandoned uploads are removed by periodic sweep, see
+/// `Uploads::sweep_interval()`. Completed uploads are kept in the storage,
+/// application is responsible for processing and removing them.
+#[derive(Clone)]
+pub struct Uploads {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    storage: Box<dyn UploadStorage>,
+    max_size: Option<u64>,
+    expiration: Option<Duration>,
+    sweep_interval: Duration,
+    sweeping: Cell<bool>,
+}
+
+impl Uploads {
+    /// Create uploads service with the storage.
+    pub fn new<S: UploadStorage>(storage: S) -> Uploads {
+        Uploads {
+            inner: Rc::new(Inner {
+                storage: Box::new(storage),
+                max_size: None,
+                expiration: None,
+                sweep_interval: Duration::from_secs(300),
+                sweeping: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Set max size of an upload in bytes.
+    pub fn max_size(mut self, size: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = Some(size);
+        self
+    }
+
+    /// Set time after which unfinished upload expires.
+    pub fn expiration(mut self, expiration: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .expiration = Some(expiration);
+        self
+    }
+
+    /// Set interval of sweep that removes expired uploads from the storage,
+    /// by default it is 5 minutes.
+    ///
+    /// Sweep runs in each worker once it handles first request, uploads are
+    /// not swept if expiration is not set.
+    pub fn sweep_interval(mut self, interval: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .sweep_interval = interval;
+        self
+    }
+
+    /// Create scope that handles uploads at the path.
+    pub fn scope(self, path: &str) -> Scope {
+        let inner = self.inner;
+        let (i1, i2, i3, i4, i5) = (
+            inner.clone(),
+            inner.clone(),
+            inner.clone(),
+            inner.clone(),
+            inner,
+        );
+
+        web::scope(path)
+            .service(
+                web::resource("")
+                    .route(web::method(Method::OPTIONS).to(move || options(&i1)))
+                    .route(
+                        web::post().to(move |req: HttpRequest| create(i2.clone(), req)),
+                    ),
+            )
+            .service(
+                web::resource("/{id}")
+                    .route(web::head().to(move |req: HttpRequest| head(i3.clone(), req)))
+                    .route(web::patch().to(
+                        move |req: HttpRequest, payload: web::Payload| {
+                            patch(i4.clone(), req, payload)
+                        },
+                    ))
+                    .route(
+                        web::delete()
+                            .to(move |req: HttpRequest| delete(i5.clone(), req)),
+                    ),
+            )
+    }
+}
+
+/// Start periodic sweep of expired uploads, it stops once uploads service
+/// is dropped.
+fn start_sweep(inner: &Rc<Inner>) {
+    if inner.expiration.is_none() || inner.sweeping.replace(true) {
+        return;
+    }
+
+    let interval = inner.sweep_interval;
+    let inner: Weak<Inner> = Rc::downgrade(inner);
+    actix_rt::spawn(async move {
+        loop {
+            delay_for(interval).await;
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let ids = match inner.storage.expired().await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log::error!("Can not list expired uploads: {}", e);
+                    continue;
+                }
+            };
+            for id in ids {
+                if let Err(e) = inner.storage.remove(&id).await {
+                    log::error!("Can not remove expired upload {}: {}", id, e);
+                }
+            }
+        }
+    });
+}
+
+fn response(status: StatusCode) -> ResponseBuilder {
+    let mut res = HttpResponse::build(status);
+    res.header(TUS_RESUMABLE, TUS_VERSION);
+    res
+}
+
+/// Response for request of unsupported version of the protocol.
+fn check_version(req: &HttpRequest) -> Option<HttpResponse> {
+    match req.headers().get(TUS_RESUMABLE) {
+        Some(val) if val == TUS_VERSION => None,
+        _ => Some(
+            response(StatusCode::PRECONDITION_FAILED)
+                .header(TUS_VERSION_HEADER, TUS_VERSION)
+                .finish(),
+        ),
+    }
+}
+
+fn header_u64(req: &HttpRequest, name: &str) -> Option<u64> {
+    req.headers()
+        .get(name)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse().ok())
+}
+
+/// Upload of the request, expired upload is removed.
+async fn load(
+    inner: &Inner,
+    req: &HttpRequest,
+) -> Result<Option<(String, UploadInfo)>, Error> {
+    let id = req.match_info().get("id").unwrap_or("");
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Ok(None);
+    }
+
+    match inner.storage.info(id).await? {
+        Some(ref info) if info.is_expired() => {
+            inner.storage.remove(id).await?;
+            Ok(None)
+        }
+        info => Ok(info.map(|info| (id.to_owned(), info))),
+    }
+}
+
+fn options(inner: &Inner) -> HttpResponse {
+    let mut res = response(StatusCode::NO_CONTENT);
+    res.header(TUS_VERSION_HEADER, TUS_VERSION).header(
+        TUS_EXTENSION,
+        if inner.expiration.is_some() {
+            "creation,expiration,termination"
+        } else {
+            "creation,termination"
+        },
+    );
+    if let Some(max_size) = inner.max_size {
+        res.header(TUS_MAX_SIZE, max_size.to_string());
+    }
+    res.finish()
+}
+
+async fn create(inner: Rc<Inner>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    start_sweep(&inner);
+    if let Some(res) = check_version(&req) {
+        return Ok(res);
+    }
+
+    let length = header_u64(&req, UPLOAD_LENGTH);
+    let deferred = req
+        .headers()
+        .get(UPLOAD_DEFER_LENGTH)
+        .map(|val| val == "1")
+        .unwrap_or(false);
+    if length.is_none() && !deferred {
+        return Ok(response(StatusCode::BAD_REQUEST).finish());
+    }
+    if let (Some(length), Some(max_size)) = (length, inner.max_size) {
+        if length > max_size {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE).finish());
+        }
+    }
+
+    let expires = inner.expiration.map(|exp| SystemTime::now() + exp);
+    let info = UploadInfo {
+        offset: 0,
+        length,
+        metadata: req
+            .headers()
+            .get(UPLOAD_METADATA)
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_owned()),
+        expires,
+    };
+    let id = inner.storage.create(info).await?;
+
+    let mut res = response(StatusCode::CREATED);
+    res.header(
+        LOCATION,
+        format!("{}/{}", req.path().trim_end_matches('/'), id),
+    );
+    if let Some(expires) = expires {
+        res.header(UPLOAD_EXPIRES, HttpDate::from(expires));
+    }
+    Ok(res.finish())
+}
+
+async fn head(inner: Rc<Inner>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    start_sweep(&inner);
+    if let Some(res) = check_version(&req) {
+        return Ok(res);
+    }
+    let info = match load(&inner, &req).await? {
+        Some((_, info)) => info,
+        None => return Ok(response(StatusCode::NOT_FOUND).finish()),
+    };
+
+    let mut res = response(StatusCode::OK);
+    res.header(UPLOAD_OFFSET, info.offset.to_string())
+        .header(CACHE_CONTROL, "no-store");
+    match info.length {
+        Some(length) => res.header(UPLOAD_LENGTH, length.to_string()),
+        None => res.header(UPLOAD_DEFER_LENGTH, "1"),
+    };
+    if let Some(metadata) = info.metadata {
+        res.header(UPLOAD_METADATA, metadata);
+    }
+    if let Some(expires) = info.expires {
+        res.header(UPLOAD_EXPIRES, HttpDate::from(expires));
+    }
+    Ok(res.finish())
+}
+
+async fn patch(
+    inner: Rc<Inner>,
+    req: HttpRequest,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    start_sweep(&inner);
+    if let Some(res) = check_version(&req) {
+        return Ok(res);
+    }
+    match req.headers().get(CONTENT_TYPE) {
+        Some(val) if val == OFFSET_OCTET_STREAM => (),
+        _ => return Ok(response(StatusCode::UNSUPPORTED_MEDIA_TYPE).finish()),
+    }
+    let mut offset = match header_u64(&req, UPLOAD_OFFSET) {
+        Some(offset) => offset,
+        None => return Ok(response(StatusCode::BAD_REQUEST).finish()),
+    };
+    let (id, mut info) = match load(&inner, &req).await? {
+        Some(upload) => upload,
+        None => return Ok(response(StatusCode::NOT_FOUND).finish()),
+    };
+
+    // length of deferred upload could be set with any request
+    if info.length.is_none() {
+        if let Some(length) = header_u64(&req, UPLOAD_LENGTH) {
+            if inner.max_size.map(|max| length > max).unwrap_or(false) {
+                return Ok(response(StatusCode::PAYLOAD_TOO_LARGE).finish());
+            }
+            inner.storage.set_length(&id, length).await?;
+            info.length = Some(length);
+        }
+    }
+    if offset != info.offset {
+        return Ok(response(StatusCode::CONFLICT).finish());
+    }
+
+    let limit = info.length.or(inner.max_size);
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if limit
+            .map(|limit| offset + chunk.len() as u64 > limit)
+            .unwrap_or(false)
+        {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE)
+                .header(UPLOAD_OFFSET, offset.to_string())
+                .finish());
+        }
+        offset = inner.storage.append(&id, offset, chunk).await?;
+    }
+
+    let mut res = response(StatusCode::NO_CONTENT);
+    res.header(UPLOAD_OFFSET, offset.to_string());
+    if let Some(expires) = info.expires {
+        res.header(UPLOAD_EXPIRES, HttpDate::from(expires));
+    }
+    Ok(res.finish())
+}
+
+async fn delete(inner: Rc<Inner>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    start_sweep(&inner);
+    if let Some(res) = check_version(&req) {
+        return Ok(res);
+    }
+    match load(&inner, &req).await? {
+        Some((id, _)) => {
+            inner.storage.remove(&id).await?;
+            Ok(response(StatusCode::NO_CONTENT).finish())
+        }
+        None => Ok(response(StatusCode::NOT_FOUND).finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{block_on, call_service, init_service, TestRequest};
+    use crate::App;
+
+    fn header<'a>(res: &'a crate::dev::ServiceResponse, name: &str) -> &'a str {
+        res.headers().get(name).unwrap().to_str().unwrap()
+    }
+
+    fn upload_storage<S: UploadStorage + Clone>(
+        storage: S,
+        data: impl Fn(&str) -> Bytes,
+    ) {
+        block_on(async {
+            let mut srv = init_service(
+                App::new().service(
+                    Uploads::new(storage.clone())
+                        .max_size(16)
+                        .expiration(Duration::from_secs(60))
+                        .scope("/files"),
+                ),
+            )
+            .await;
+
+            let req = TestRequest::with_uri("/files")
+                .method(Method::OPTIONS)
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+            assert_eq!(header(&res, TUS_MAX_SIZE), "16");
+
+            // version is required
+            let req = TestRequest::post()
+                .uri("/files")
+                .header(UPLOAD_LENGTH, "8")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+            let req = TestRequest::post()
+                .uri("/files")
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(UPLOAD_LENGTH, "32")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let req = TestRequest::post()
+                .uri("/files")
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(UPLOAD_LENGTH, "8")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::CREATED);
+            let location = header(&res, "location").to_owned();
+            let id = location.trim_start_matches("/files/").to_owned();
+
+            let req = TestRequest::default()
+                .method(Method::PATCH)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(CONTENT_TYPE, OFFSET_OCTET_STREAM)
+                .header(UPLOAD_OFFSET, "0")
+                .set_payload("1234")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+            assert_eq!(header(&res, UPLOAD_OFFSET), "4");
+
+            // offset does not match
+            let req = TestRequest::default()
+                .method(Method::PATCH)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(CONTENT_TYPE, OFFSET_OCTET_STREAM)
+                .header(UPLOAD_OFFSET, "2")
+                .set_payload("1234")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::CONFLICT);
+
+            let req = TestRequest::default()
+                .method(Method::HEAD)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(header(&res, UPLOAD_OFFSET), "4");
+            assert_eq!(header(&res, UPLOAD_LENGTH), "8");
+
+            let req = TestRequest::default()
+                .method(Method::PATCH)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(CONTENT_TYPE, OFFSET_OCTET_STREAM)
+                .header(UPLOAD_OFFSET, "4")
+                .set_payload("5678")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+            assert_eq!(header(&res, UPLOAD_OFFSET), "8");
+            assert_eq!(data(&id), Bytes::from_static(b"12345678"));
+
+            let req = TestRequest::delete()
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+            let req = TestRequest::default()
+                .method(Method::HEAD)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        })
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let storage = MemoryStorage::new();
+        let data = storage.clone();
+        upload_storage(storage, move |id| data.data(id).unwrap());
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let dir = std::env::temp_dir().join(format!("actix-upload-{}", new_id()));
+        fs::create_dir(&dir).unwrap();
+        let storage = FileStorage::new(&dir);
+        let data = storage.clone();
+        upload_storage(storage.clone(), move |id| {
+            Bytes::from(fs::read(data.path(id)).unwrap())
+        });
+
+        block_on(async {
+            let id = storage
+                .create(UploadInfo {
+                    offset: 0,
+                    length: Some(8),
+                    metadata: None,
+                    expires: Some(SystemTime::now()),
+                })
+                .await
+                .unwrap();
+            assert_eq!(storage.expired().await.unwrap(), vec![id]);
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expiration() {
+        block_on(async {
+            let storage = MemoryStorage::new();
+            let mut srv = init_service(
+                App::new().service(
+                    Uploads::new(storage.clone())
+                        .expiration(Duration::from_secs(0))
+                        .scope("/files"),
+                ),
+            )
+            .await;
+
+            let req = TestRequest::post()
+                .uri("/files")
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(UPLOAD_DEFER_LENGTH, "1")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::CREATED);
+            let location = header(&res, "location").to_owned();
+
+            let req = TestRequest::default()
+                .method(Method::HEAD)
+                .uri(&location)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+            assert!(storage.uploads.lock().is_empty());
+        })
+    }
+
+    #[test]
+    fn test_sweep() {
+        block_on(async {
+            let storage = MemoryStorage::new();
+            let mut srv = init_service(
+                App::new().service(
+                    Uploads::new(storage.clone())
+                        .expiration(Duration::from_secs(0))
+                        .sweep_interval(Duration::from_millis(10))
+                        .scope("/files"),
+                ),
+            )
+            .await;
+
+            let req = TestRequest::post()
+                .uri("/files")
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(UPLOAD_DEFER_LENGTH, "1")
+                .to_request();
+            let res = call_service(&mut srv, req).await;
+            assert_eq!(res.status(), StatusCode::CREATED);
+            assert_eq!(storage.uploads.lock().len(), 1);
+
+            // upload is removed without access
+            delay_for(Duration::from_millis(100)).await;
+            assert!(storage.uploads.lock().is_empty());
+        })
+    }
+}
@@ -1,12 +1,13 @@
 //! Essentials helper functions and types for application registration.
 use actix_http::http::Method;
+use futures::future::{FutureExt, LocalBoxFuture};
 use futures::Future;
 
 pub use actix_http::Response as HttpResponse;
 pub use bytes::{Bytes, BytesMut};
 pub use futures::channel::oneshot::Canceled;
 
-use crate::error::Error;
+use crate::error::{BlockingError, Error};
 use crate::extract::FromRequest;
 use crate::handler::Factory;
 use crate::resource::Resource;
@@ -15,10 +16,15 @@ use crate::route::Route;
 use crate::scope::Scope;
 use crate::service::WebService;
 
+pub use crate::blocking::BlockingMetrics;
 pub use crate::config::ServiceConfig;
-pub use crate::data::Data;
+pub use crate::data::{Data, DataScope, OptionalData, SwapData, WorkerData};
+pub use crate::health::{HealthCheck, HealthService, Readiness};
+#[cfg(feature = "client")]
+pub use crate::proxy::Proxy;
 pub use crate::request::HttpRequest;
 pub use crate::types::*;
+pub use crate::ws;
 
 /// Create resource for a specific path.
 ///
@@ -273,12 +279,100 @@ pub fn service(path: &str) -> WebService {
     WebService::new(path)
 }
 
+/// Create service with `/livez` and `/readyz` health endpoints.
+///
+/// Readiness endpoint aggregates registered checks, see `HealthService`.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::health_service()
+///             .check("db", || async { Ok::<_, String>(()) })
+///     );
+/// }
+/// ```
+pub fn health_service() -> HealthService {
+    HealthService::new()
+}
+
+/// Create route that forwards requests to the `upstream` server.
+///
+/// Path and query of the request are appended to the upstream url,
+/// websocket connections are relayed as well. See `Proxy` for details.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/api")
+///             .route("/{tail:.*}", web::proxy_to("http://127.0.0.1:8081"))
+///     );
+/// }
+/// ```
+#[cfg(feature = "client")]
+pub fn proxy_to(upstream: &str) -> Route {
+    let proxy = Proxy::new(upstream);
+    Route::new().to(move |req: HttpRequest, payload: Payload| {
+        let proxy = proxy.clone();
+        async move { proxy.forward(&req, payload).await }
+    })
+}
+
 /// Execute blocking function on a thread pool, returns future that resolves
 /// to result of the function execution.
-pub fn block<F, R>(f: F) -> impl Future<Output = Result<R, Canceled>>
+///
+/// Pool is shared by all servers of the process, its size is configured with
+/// `HttpServer::blocking_threads()` or `ACTIX_THREADPOOL` env variable. If
+/// queue of the pool is limited with `HttpServer::blocking_queue()` and limit
+/// is reached, `BlockingError::Saturated` is returned, it is rendered as
+/// *SERVICE UNAVAILABLE* response.
+pub fn block<F, R>(f: F) -> impl Future<Output = Result<R, BlockingError>>
 where
     F: FnOnce() -> R + Send + 'static,
     R: Send + 'static,
 {
-    actix_threadpool::run(f)
+    crate::blocking::run(f)
+}
+
+/// Current metrics of blocking thread pool, i.e. number of queued functions.
+pub fn block_metrics() -> BlockingMetrics {
+    crate::blocking::metrics()
+}
+
+/// Create handler that executes synchronous function on a thread pool.
+///
+/// Function receives result of extractor `T` and its result is returned
+/// as response, so blocking code does not stall worker threads. Extractors
+/// have to be `Send`, tuple could be used for multiple extractors.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// fn index(info: web::Path<(u32, String)>) -> String {
+///     // blocking computation
+///     format!("Welcome {}! id: {}", info.1, info.0)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/{id}/{name}/index.html")
+///             .route(web::get().to(web::block_fn(index)))
+///     );
+/// }
+/// ```
+pub fn block_fn<F, T, R>(
+    f: F,
+) -> impl Fn(T) -> LocalBoxFuture<'static, Result<R, BlockingError>> + Clone + 'static
+where
+    F: Fn(T) -> R + Clone + Send + 'static,
+    T: FromRequest + Send + 'static,
+    R: Responder + Send + 'static,
+{
+    move |param| {
+        let f = f.clone();
+        crate::blocking::run(move || f(param)).boxed_local()
+    }
 }
@@ -0,0 +1,910 @@
+//! WebSocket support without actors
+//!
+//! [`start()`](fn.start.html) performs websocket handshake and returns
+//! handshake response together with a [`Session`](struct.Session.html)
+//! for sending messages and a [`MessageStream`](struct.MessageStream.html)
+//! of incoming messages. Response has to be returned from the handler,
+//! messages could be handled in a spawned task.
+//!
+//! ```rust
+//! use actix_web::{web, App, Error, HttpRequest, HttpResponse};
+//! use actix_web::web::ws::{self, Message};
+//! use futures::StreamExt;
+//!
+//! async fn echo(req: HttpRequest, body: web::Payload) -> Result<HttpResponse, Error> {
+//!     let (res, mut session, mut msgs) = ws::start(&req, body)?;
+//!
+//!     actix_rt::spawn(async move {
+//!         while let Some(Ok(msg)) = msgs.next().await {
+//!             let res = match msg {
+//!                 Message::Ping(msg) => session.pong(&msg).await,
+//!                 Message::Text(text) => session.text(text).await,
+//!                 Message::Binary(bin) => session.binary(bin).await,
+//!                 Message::Close(reason) => {
+//!                     let _ = session.close(reason).await;
+//!                     return;
+//!                 }
+//!                 _ => Ok(()),
+//!             };
+//!             if res.is_err() {
+//!                 return;
+//!             }
+//!         }
+//!     });
+//!
+//!     Ok(res)
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/ws", web::get().to(echo));
+//! }
+//! ```
+//!
+//! Session options are configured with [`WsConfig`](struct.WsConfig.html),
+//! stored as application or resource data.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{handshake, Codec, Frame};
+use bytes::{Bytes, BytesMut};
+use derive_more::Display;
+use futures::future::poll_fn;
+use futures::{Future, Stream};
+use tokio_timer::{delay, Delay};
+
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+pub use actix_http::ws::DeflateConfig;
+pub use actix_http::ws::{
+    CloseCode, CloseReason, HandshakeError, Item, Message, ProtocolError,
+};
+
+use crate::error::{Error, ResponseError};
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+use crate::http::header;
+use crate::request::HttpRequest;
+use crate::types::Payload;
+use crate::HttpResponse;
+
+/// Websocket session configuration
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, App, HttpResponse};
+/// use actix_web::web::ws::WsConfig;
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/ws")
+///             .data(
+///                 WsConfig::default()
+///                     .heartbeat(Duration::from_secs(5), Duration::from_secs(10))
+///                     .max_message_size(1024 * 1024),
+///             )
+///             .route(web::get().to(|| HttpResponse::Ok())),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WsConfig {
+    heartbeat: Option<(Duration, Duration)>,
+    max_frame_size: usize,
+    max_message_size: usize,
+    aggregate: bool,
+    buffer_capacity: usize,
+    buffer_policy: BufferPolicy,
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    deflate: Option<DeflateConfig>,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        WsConfig {
+            heartbeat: None,
+            max_frame_size: 65_536,
+            max_message_size: 65_536,
+            aggregate: true,
+            buffer_capacity: 16,
+            buffer_policy: BufferPolicy::Wait,
+            #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+            deflate: None,
+        }
+    }
+}
+
+impl WsConfig {
+    /// Set max frame size, by default 64kb.
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        self.max_frame_size = size;
+        self
+    }
+
+    /// Set max size of aggregated or decompressed message, by default 64kb.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Aggregate continuation frames into complete messages.
+    ///
+    /// Enabled by default. If disabled, fragments are received as
+    /// `Message::Continuation`.
+    pub fn aggregate_continuations(mut self, aggregate: bool) -> Self {
+        self.aggregate = aggregate;
+        self
+    }
+
+    /// Set outgoing buffer capacity in messages and policy applied when
+    /// buffer is full.
+    ///
+    /// By default 16 messages are buffered and `Session::send()` waits
+    /// for free space.
+    pub fn buffer(mut self, capacity: usize, policy: BufferPolicy) -> Self {
+        self.buffer_capacity = std::cmp::max(capacity, 1);
+        self.buffer_policy = policy;
+        self
+    }
+
+    /// Send ping frame every `interval` and close session if nothing is
+    /// received from the peer for `timeout`.
+    ///
+    /// Any received frame counts as a sign of life, not only pong frames.
+    /// By default heartbeat is disabled.
+    pub fn heartbeat(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    /// Enable `permessage-deflate` compression if client offers it.
+    ///
+    /// By default compression is disabled.
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    pub fn deflate(mut self, config: DeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+}
+
+/// Do websocket handshake and start websocket session.
+///
+/// Returns handshake response, session handle and stream of incoming
+/// messages. If handshake fails, error response is returned.
+///
+/// Session is configured with `WsConfig` from application data,
+/// default configuration is used if it is not set.
+pub fn start(
+    req: &HttpRequest,
+    payload: Payload,
+) -> Result<(HttpResponse, Session, MessageStream), Error> {
+    match req.app_data::<WsConfig>() {
+        Some(config) => start_with_config(req, payload, config),
+        None => start_with_config(req, payload, &WsConfig::default()),
+    }
+}
+
+/// Do websocket handshake and start websocket session with explicit
+/// configuration.
+pub fn start_with_config(
+    req: &HttpRequest,
+    payload: Payload,
+    config: &WsConfig,
+) -> Result<(HttpResponse, Session, MessageStream), Error> {
+    let mut res = handshake(req.head())?;
+
+    let codec = Codec::new()
+        .max_size(config.max_frame_size)
+        .max_message_size(config.max_message_size)
+        .aggregate_continuations(config.aggregate);
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    let codec = match config.deflate.and_then(|cfg| cfg.negotiate(req.head())) {
+        Some(params) => {
+            res.header(header::SEC_WEBSOCKET_EXTENSIONS, params.header_value());
+            codec.deflate(params)
+        }
+        None => codec,
+    };
+
+    let channel = Rc::new(RefCell::new(Channel {
+        queue: VecDeque::new(),
+        capacity: config.buffer_capacity,
+        policy: config.buffer_policy,
+        closed: false,
+        disconnected: false,
+        sessions: 1,
+        rx_task: None,
+        tx_tasks: Vec::new(),
+    }));
+    let last_seen = Rc::new(Cell::new(Instant::now()));
+    let session = Session {
+        channel: channel.clone(),
+    };
+    let body = ResponseStream {
+        channel,
+        codec: codec.clone(),
+        buf: BytesMut::new(),
+        eof: false,
+        heartbeat: config.heartbeat.map(|(interval, timeout)| Heartbeat {
+            interval,
+            timeout,
+            last_seen: last_seen.clone(),
+            delay: delay(Instant::now() + interval),
+        }),
+    };
+
+    let msgs = MessageStream::new(payload, codec, last_seen, session.clone());
+    Ok((res.streaming(body), session, msgs))
+}
+
+/// Error returned from `Session` methods if session is closed or
+/// connection is gone.
+#[derive(Debug, Display)]
+#[display(fmt = "Websocket session is closed")]
+pub struct Closed;
+
+impl ResponseError for Closed {}
+
+/// Policy applied to messages sent while outgoing buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferPolicy {
+    /// Wait until peer reads buffered messages
+    Wait,
+    /// Drop the oldest buffered message
+    DropOldest,
+    /// Close session with `CloseCode::Policy`
+    Close,
+}
+
+/// Outgoing messages shared by sessions and response stream
+struct Channel {
+    queue: VecDeque<Message>,
+    capacity: usize,
+    policy: BufferPolicy,
+    closed: bool,
+    disconnected: bool,
+    sessions: usize,
+    rx_task: Option<Waker>,
+    tx_tasks: Vec<Waker>,
+}
+
+impl Channel {
+    fn push(&mut self, msg: Message) {
+        if let Message::Close(_) = msg {
+            self.closed = true;
+        }
+        self.queue.push_back(msg);
+        if let Some(task) = self.rx_task.take() {
+            task.wake();
+        }
+    }
+
+    fn wake_senders(&mut self) {
+        for task in self.tx_tasks.drain(..) {
+            task.wake();
+        }
+    }
+}
+
+/// Handle for sending messages to the peer.
+///
+/// Session could be cloned and moved to other tasks of the same thread.
+/// Close frame ends the session, messages sent afterwards are rejected.
+/// Response is also finished once all sessions and the message stream
+/// are dropped.
+///
+/// Outgoing messages are buffered until the peer reads them, buffer
+/// capacity and overflow policy are set with `WsConfig::buffer()`.
+pub struct Session {
+    channel: Rc<RefCell<Channel>>,
+}
+
+impl Clone for Session {
+    fn clone(&self) -> Self {
+        self.channel.borrow_mut().sessions += 1;
+        Session {
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut channel = self.channel.borrow_mut();
+        channel.sessions -= 1;
+        if channel.sessions == 0 {
+            if let Some(task) = channel.rx_task.take() {
+                task.wake();
+            }
+        }
+    }
+}
+
+impl Session {
+    /// Send message to the peer.
+    ///
+    /// If outgoing buffer is full, `BufferPolicy::Wait` policy waits
+    /// until there is free space. Close frames are never delayed.
+    pub async fn send(&mut self, msg: Message) -> Result<(), Closed> {
+        if let Message::Close(_) = msg {
+            let mut channel = self.channel.borrow_mut();
+            if channel.closed || channel.disconnected {
+                return Err(Closed);
+            }
+            channel.push(msg);
+            return Ok(());
+        }
+
+        poll_fn(|cx| self.poll_ready(cx)).await?;
+        self.channel.borrow_mut().push(msg);
+        Ok(())
+    }
+
+    fn poll_ready(&self, cx: &mut Context) -> Poll<Result<(), Closed>> {
+        let mut channel = self.channel.borrow_mut();
+        if channel.closed || channel.disconnected {
+            return Poll::Ready(Err(Closed));
+        }
+        if channel.queue.len() < channel.capacity {
+            return Poll::Ready(Ok(()));
+        }
+
+        match channel.policy {
+            BufferPolicy::Wait => {
+                // sender is registered once, no matter how often it is polled
+                let waker = cx.waker();
+                if !channel.tx_tasks.iter().any(|task| task.will_wake(waker)) {
+                    channel.tx_tasks.push(waker.clone());
+                }
+                Poll::Pending
+            }
+            BufferPolicy::DropOldest => {
+                channel.queue.pop_front();
+                Poll::Ready(Ok(()))
+            }
+            BufferPolicy::Close => {
+                log::debug!("Websocket send buffer is full, closing session");
+                channel.queue.clear();
+                channel.push(Message::Close(Some(CloseReason {
+                    code: CloseCode::Policy,
+                    description: Some("Send buffer overflow".to_string()),
+                })));
+                Poll::Ready(Err(Closed))
+            }
+        }
+    }
+
+    /// Send text frame
+    pub async fn text<T: Into<String>>(&mut self, text: T) -> Result<(), Closed> {
+        self.send(Message::Text(text.into())).await
+    }
+
+    /// Send binary frame
+    pub async fn binary<B: Into<Bytes>>(&mut self, data: B) -> Result<(), Closed> {
+        self.send(Message::Binary(data.into())).await
+    }
+
+    /// Send ping frame
+    pub async fn ping(&mut self, msg: &str) -> Result<(), Closed> {
+        self.send(Message::Ping(msg.to_string())).await
+    }
+
+    /// Send pong frame
+    pub async fn pong(&mut self, msg: &str) -> Result<(), Closed> {
+        self.send(Message::Pong(msg.to_string())).await
+    }
+
+    /// Send close frame and end the session.
+    pub async fn close(mut self, reason: Option<CloseReason>) -> Result<(), Closed> {
+        self.send(Message::Close(reason)).await
+    }
+
+    /// Check if session is closed
+    pub fn is_closed(&self) -> bool {
+        let channel = self.channel.borrow();
+        channel.closed || channel.disconnected
+    }
+
+    /// Number of buffered outgoing messages
+    pub fn buffered(&self) -> usize {
+        self.channel.borrow().queue.len()
+    }
+
+    /// Send close frame without waiting
+    fn close_now(&self, reason: CloseReason) {
+        let mut channel = self.channel.borrow_mut();
+        if !channel.closed && !channel.disconnected {
+            channel.push(Message::Close(Some(reason)));
+        }
+    }
+}
+
+struct Heartbeat {
+    interval: Duration,
+    timeout: Duration,
+    last_seen: Rc<Cell<Instant>>,
+    delay: Delay,
+}
+
+/// Response body, encodes messages sent via `Session`.
+struct ResponseStream {
+    channel: Rc<RefCell<Channel>>,
+    codec: Codec,
+    buf: BytesMut,
+    eof: bool,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl Stream for ResponseStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if let Some(ref mut hb) = this.heartbeat {
+            while !this.eof && Pin::new(&mut hb.delay).poll(cx).is_ready() {
+                if hb.last_seen.get().elapsed() >= hb.timeout {
+                    log::debug!("Websocket heartbeat timeout, closing session");
+                    let reason = CloseReason {
+                        code: CloseCode::Policy,
+                        description: Some("Heartbeat timeout".to_string()),
+                    };
+                    this.codec
+                        .encode(Message::Close(Some(reason)), &mut this.buf)?;
+                    this.eof = true;
+                } else {
+                    this.codec
+                        .encode(Message::Ping(String::new()), &mut this.buf)?;
+                    hb.delay = delay(Instant::now() + hb.interval);
+                }
+            }
+        }
+
+        {
+            let mut channel = this.channel.borrow_mut();
+            let len = channel.queue.len();
+            while !this.eof {
+                match channel.queue.pop_front() {
+                    Some(msg) => {
+                        if let Message::Close(_) = msg {
+                            this.eof = true;
+                        }
+                        this.codec.encode(msg, &mut this.buf)?;
+                    }
+                    None if channel.sessions == 0 => {
+                        // all sessions are dropped
+                        this.eof = true;
+                    }
+                    None => {
+                        channel.rx_task = Some(cx.waker().clone());
+                        break;
+                    }
+                }
+            }
+            if channel.queue.len() < len {
+                channel.wake_senders();
+            }
+            if this.eof {
+                channel.closed = true;
+            }
+        }
+
+        if !this.buf.is_empty() {
+            Poll::Ready(Some(Ok(this.buf.take().freeze())))
+        } else if this.eof {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for ResponseStream {
+    fn drop(&mut self) {
+        let mut channel = self.channel.borrow_mut();
+        channel.disconnected = true;
+        channel.queue.clear();
+        channel.wake_senders();
+    }
+}
+
+/// Stream of messages received from the peer.
+///
+/// Protocol errors close the session with appropriate close code,
+/// stream ends after an error.
+pub struct MessageStream {
+    payload: Payload,
+    codec: Codec,
+    buf: BytesMut,
+    closed: bool,
+    failed: bool,
+    last_seen: Rc<Cell<Instant>>,
+    session: Session,
+}
+
+impl MessageStream {
+    fn new(
+        payload: Payload,
+        codec: Codec,
+        last_seen: Rc<Cell<Instant>>,
+        session: Session,
+    ) -> Self {
+        MessageStream {
+            payload,
+            codec,
+            last_seen,
+            session,
+            buf: BytesMut::new(),
+            closed: false,
+            failed: false,
+        }
+    }
+
+    fn poll_message(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Message, ProtocolError>>> {
+        if !self.closed {
+            loop {
+                match Pin::new(&mut self.payload).poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        self.buf.extend_from_slice(&chunk[..]);
+                    }
+                    Poll::Ready(None) => {
+                        self.closed = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                    Poll::Ready(Some(Err(e))) => {
+                        return Poll::Ready(Some(Err(ProtocolError::Io(
+                            io::Error::new(io::ErrorKind::Other, format!("{}", e)),
+                        ))));
+                    }
+                }
+            }
+        }
+
+        match self.codec.decode(&mut self.buf)? {
+            None => {
+                if self.closed {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Pending
+                }
+            }
+            Some(frm) => {
+                self.last_seen.set(Instant::now());
+                let msg = match frm {
+                    Frame::Text(data) => {
+                        if let Some(data) = data {
+                            Message::Text(
+                                std::str::from_utf8(&data)
+                                    .map_err(|_| ProtocolError::BadEncoding)?
+                                    .to_string(),
+                            )
+                        } else {
+                            Message::Text(String::new())
+                        }
+                    }
+                    Frame::Binary(data) => Message::Binary(
+                        data.map(|b| b.freeze()).unwrap_or_else(Bytes::new),
+                    ),
+                    Frame::Ping(s) => Message::Ping(s),
+                    Frame::Pong(s) => Message::Pong(s),
+                    Frame::Close(reason) => Message::Close(reason),
+                    Frame::Continuation(item) => Message::Continuation(item),
+                };
+                Poll::Ready(Some(Ok(msg)))
+            }
+        }
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = Result<Message, ProtocolError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        if self.failed {
+            return Poll::Ready(None);
+        }
+
+        let res = self.poll_message(cx);
+        if let Poll::Ready(Some(Err(ref e))) = res {
+            self.failed = true;
+
+            let code = match e {
+                ProtocolError::Io(_) => None,
+                ProtocolError::Overflow => Some(CloseCode::Size),
+                ProtocolError::BadEncoding => Some(CloseCode::Invalid),
+                _ => Some(CloseCode::Protocol),
+            };
+            if let Some(code) = code {
+                log::debug!("Websocket protocol error: {}", e);
+                self.session.close_now(CloseReason {
+                    code,
+                    description: Some(e.to_string()),
+                });
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use actix_http::ws::{OpCode, Parser};
+
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::test::{block_on, TestRequest};
+
+    fn ws_request() -> TestRequest {
+        TestRequest::default()
+            .header(header::UPGRADE, "websocket")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "13")
+    }
+
+    fn client_frame(msg: Message) -> Bytes {
+        let mut buf = BytesMut::new();
+        Codec::new().client_mode().encode(msg, &mut buf).unwrap();
+        buf.freeze()
+    }
+
+    #[test]
+    fn test_handshake_error() {
+        let (req, payload) = TestRequest::default().to_http_parts();
+        match start(&req, Payload(payload)) {
+            Err(e) => assert_eq!(
+                e.as_response_error().error_response().status(),
+                StatusCode::BAD_REQUEST
+            ),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        block_on(async {
+            let (req, payload) = ws_request()
+                .set_payload(client_frame(Message::Text("hello".to_string())))
+                .to_http_parts();
+            let (mut res, mut session, mut msgs) =
+                start(&req, Payload(payload)).unwrap();
+            assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+            assert_eq!(
+                msgs.next().await.unwrap().unwrap(),
+                Message::Text("hello".to_string())
+            );
+            assert!(msgs.next().await.is_none());
+
+            session.text("hi").await.unwrap();
+            session.clone().close(None).await.unwrap();
+            assert!(session.is_closed());
+            assert!(session.text("late").await.is_err());
+
+            let mut body = res.take_body();
+            let mut buf = BytesMut::from(body.next().await.unwrap().unwrap());
+            let mut codec = Codec::new().client_mode();
+            assert_eq!(
+                codec.decode(&mut buf).unwrap(),
+                Some(Frame::Text(Some(BytesMut::from("hi"))))
+            );
+            assert_eq!(codec.decode(&mut buf).unwrap(), Some(Frame::Close(None)));
+            assert!(body.next().await.is_none());
+        })
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        block_on(async {
+            let (req, payload) = ws_request().to_http_parts();
+            let config = WsConfig::default()
+                .heartbeat(Duration::from_millis(50), Duration::from_millis(120));
+            let (mut res, session, _msgs) =
+                start_with_config(&req, Payload(payload), &config).unwrap();
+
+            let mut body = res.take_body();
+            let mut codec = Codec::new().client_mode();
+            for _ in 0..2 {
+                let mut buf = BytesMut::from(body.next().await.unwrap().unwrap());
+                assert_eq!(
+                    codec.decode(&mut buf).unwrap(),
+                    Some(Frame::Ping(String::new()))
+                );
+            }
+
+            // no response from the peer
+            let mut buf = BytesMut::from(body.next().await.unwrap().unwrap());
+            match codec.decode(&mut buf).unwrap() {
+                Some(Frame::Close(Some(reason))) => {
+                    assert_eq!(reason.code, CloseCode::Policy)
+                }
+                _ => panic!(),
+            }
+            assert!(body.next().await.is_none());
+            assert!(session.is_closed());
+        })
+    }
+
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    #[test]
+    fn test_deflate() {
+        block_on(async {
+            let params = DeflateConfig::default()
+                .negotiate(
+                    TestRequest::default()
+                        .header(header::SEC_WEBSOCKET_EXTENSIONS, "permessage-deflate")
+                        .to_http_request()
+                        .head(),
+                )
+                .unwrap();
+            let mut client = Codec::new().client_mode().deflate(params);
+            let mut frame = BytesMut::new();
+            client
+                .encode(Message::Text("hello".to_string()), &mut frame)
+                .unwrap();
+
+            let (req, payload) = ws_request()
+                .header(
+                    header::SEC_WEBSOCKET_EXTENSIONS,
+                    "permessage-deflate; client_max_window_bits",
+                )
+                .set_payload(frame.freeze())
+                .to_http_parts();
+            let config = WsConfig::default().deflate(DeflateConfig::default());
+            let (mut res, mut session, mut msgs) =
+                start_with_config(&req, Payload(payload), &config).unwrap();
+            assert_eq!(
+                res.headers().get(header::SEC_WEBSOCKET_EXTENSIONS).unwrap(),
+                "permessage-deflate"
+            );
+            assert_eq!(
+                msgs.next().await.unwrap().unwrap(),
+                Message::Text("hello".to_string())
+            );
+
+            session.text("hi").await.unwrap();
+            let mut buf = BytesMut::from(res.take_body().next().await.unwrap().unwrap());
+            assert_eq!(buf[0] & 0x40, 0x40);
+            assert_eq!(
+                client.decode(&mut buf).unwrap(),
+                Some(Frame::Text(Some(BytesMut::from("hi"))))
+            );
+
+            // not negotiated without config
+            let (req, payload) = ws_request()
+                .header(header::SEC_WEBSOCKET_EXTENSIONS, "permessage-deflate")
+                .to_http_parts();
+            let (res, _, _) = start(&req, Payload(payload)).unwrap();
+            assert!(!res.headers().contains_key(header::SEC_WEBSOCKET_EXTENSIONS));
+        })
+    }
+
+    #[test]
+    fn test_message_limits() {
+        block_on(async {
+            let mut frames = BytesMut::new();
+            for (data, op, fin) in &[
+                ("hello ", OpCode::Text, false),
+                ("world", OpCode::Continue, true),
+            ] {
+                Parser::write_message(&mut frames, *data, *op, *fin, true);
+            }
+
+            // continuations are aggregated
+            let (req, payload) =
+                ws_request().set_payload(frames.clone()).to_http_parts();
+            let (_, _, mut msgs) = start(&req, Payload(payload)).unwrap();
+            assert_eq!(
+                msgs.next().await.unwrap().unwrap(),
+                Message::Text("hello world".to_string())
+            );
+
+            // fragments
+            let (req, payload) =
+                ws_request().set_payload(frames.clone()).to_http_parts();
+            let config = WsConfig::default().aggregate_continuations(false);
+            let (_, _, mut msgs) =
+                start_with_config(&req, Payload(payload), &config).unwrap();
+            assert_eq!(
+                msgs.next().await.unwrap().unwrap(),
+                Message::Continuation(Item::FirstText(Bytes::from_static(b"hello ")))
+            );
+
+            // message is too large, session is closed
+            let (req, payload) = ws_request().set_payload(frames).to_http_parts();
+            let config = WsConfig::default().max_message_size(8);
+            let (mut res, session, mut msgs) =
+                start_with_config(&req, Payload(payload), &config).unwrap();
+            match msgs.next().await {
+                Some(Err(ProtocolError::Overflow)) => (),
+                _ => panic!(),
+            }
+            assert!(msgs.next().await.is_none());
+            assert!(session.is_closed());
+
+            let mut buf = BytesMut::from(res.take_body().next().await.unwrap().unwrap());
+            match Codec::new().client_mode().decode(&mut buf).unwrap() {
+                Some(Frame::Close(Some(reason))) => {
+                    assert_eq!(reason.code, CloseCode::Size)
+                }
+                _ => panic!(),
+            }
+        })
+    }
+
+    #[test]
+    fn test_buffer_policy() {
+        block_on(async {
+            let with_policy = |policy| {
+                let (req, payload) = ws_request().to_http_parts();
+                let config = WsConfig::default().buffer(1, policy);
+                start_with_config(&req, Payload(payload), &config).unwrap()
+            };
+            let mut codec = Codec::new().client_mode();
+
+            // wait for free space
+            let (mut res, mut session, _msgs) = with_policy(BufferPolicy::Wait);
+            session.text("1").await.unwrap();
+            let mut body = res.take_body();
+            let channel = session.channel.clone();
+            {
+                let mut send = Box::pin(session.text("2"));
+                for _ in 0..3 {
+                    assert!(futures::poll!(send.as_mut()).is_pending());
+                }
+                assert_eq!(channel.borrow().tx_tasks.len(), 1);
+                let mut buf = BytesMut::from(body.next().await.unwrap().unwrap());
+                assert_eq!(
+                    codec.decode(&mut buf).unwrap(),
+                    Some(Frame::Text(Some(BytesMut::from("1"))))
+                );
+                send.await.unwrap();
+            }
+            assert_eq!(session.buffered(), 1);
+
+            // peer is gone
+            drop(body);
+            drop(res);
+            assert!(session.text("3").await.is_err());
+
+            // drop oldest
+            let (mut res, mut session, _msgs) = with_policy(BufferPolicy::DropOldest);
+            session.text("1").await.unwrap();
+            session.text("2").await.unwrap();
+            let mut buf = BytesMut::from(res.take_body().next().await.unwrap().unwrap());
+            assert_eq!(
+                codec.decode(&mut buf).unwrap(),
+                Some(Frame::Text(Some(BytesMut::from("2"))))
+            );
+            assert!(buf.is_empty());
+
+            // close
+            let (mut res, mut session, _msgs) = with_policy(BufferPolicy::Close);
+            session.text("1").await.unwrap();
+            assert!(session.text("2").await.is_err());
+            assert!(session.is_closed());
+            let mut buf = BytesMut::from(res.take_body().next().await.unwrap().unwrap());
+            match codec.decode(&mut buf).unwrap() {
+                Some(Frame::Close(Some(reason))) => {
+                    assert_eq!(reason.code, CloseCode::Policy)
+                }
+                _ => panic!(),
+            }
+        })
+    }
+}
@@ -58,6 +58,48 @@ fn test_body() {
     })
 }
 
+#[test]
+fn test_drain_payload() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            h1::H1Service::new(
+                App::new()
+                    .drain_payload(dev::PayloadDrain::Limit(64))
+                    .service(
+                        web::resource("/upload")
+                            .route(web::post().to(|| HttpResponse::Forbidden())),
+                    )
+                    .service(
+                        web::resource("/close")
+                            .drain_payload(dev::PayloadDrain::Close)
+                            .route(web::post().to(|| HttpResponse::Forbidden())),
+                    )
+                    .service(web::resource("/").to(|| HttpResponse::Ok())),
+            )
+        });
+
+        let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(
+            b"POST /upload HTTP/1.1\r\ncontent-length: 10\r\n\r\n0123456789\
+              GET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+        );
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert!(data.contains("HTTP/1.1 200 OK\r\n"));
+
+        let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(
+            b"POST /close HTTP/1.1\r\ncontent-length: 10\r\n\r\n0123456789\
+              GET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+        );
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert!(!data.contains("HTTP/1.1 200 OK\r\n"));
+    })
+}
+
 #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
 #[test]
 fn test_body_gzip() {
@@ -126,6 +168,101 @@ fn test_body_gzip2() {
     })
 }
 
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+#[test]
+fn test_body_gzip_options() {
+    block_on(async {
+        let srv = TestServer::start(|| {
+            h1::H1Service::new(
+                App::new()
+                    .wrap(
+                        Compress::new(ContentEncoding::Gzip)
+                            .level(ContentEncoding::Gzip, 9)
+                            .min_size(64)
+                            .content_type("text/*"),
+                    )
+                    .service(web::resource("/").route(web::to(|| {
+                        Response::Ok().content_type("text/plain").body(STR)
+                    })))
+                    .service(web::resource("/small").route(web::to(|| {
+                        Response::Ok().content_type("text/plain").body("small")
+                    })))
+                    .service(web::resource("/image").route(web::to(|| {
+                        Response::Ok().content_type("image/png").body(STR)
+                    }))),
+            )
+        });
+
+        let mut response = srv
+            .get("/")
+            .no_decompress()
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let bytes = response.body().await.unwrap();
+        let mut e = GzDecoder::new(&bytes[..]);
+        let mut dec = Vec::new();
+        e.read_to_end(&mut dec).unwrap();
+        assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+
+        for (path, body) in &[("/small", "small"), ("/image", STR)] {
+            let mut response = srv
+                .get(*path)
+                .no_decompress()
+                .header(ACCEPT_ENCODING, "gzip")
+                .send()
+                .await
+                .unwrap();
+            assert!(response.headers().get(CONTENT_ENCODING).is_none());
+            let bytes = response.body().await.unwrap();
+            assert_eq!(bytes, Bytes::from(*body));
+        }
+    })
+}
+
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+#[test]
+fn test_body_no_compress() {
+    block_on(async {
+        let srv =
+            TestServer::start(|| {
+                h1::H1Service::new(
+                    App::new()
+                        .wrap(Compress::new(ContentEncoding::Gzip))
+                        .service(web::resource("/image").route(web::to(|| {
+                            Response::Ok().content_type("image/jpeg").body(STR)
+                        })))
+                        .service(web::resource("/events").route(web::to(|| {
+                            Response::Ok().content_type("text/event-stream").streaming(
+                                once(ok::<_, Error>(Bytes::from_static(STR.as_ref()))),
+                            )
+                        })))
+                        .service(web::resource("/text").route(web::to(|| {
+                            Response::Ok()
+                                .content_type("text/plain")
+                                .no_compress()
+                                .body(STR)
+                        }))),
+                )
+            });
+
+        for path in &["/image", "/events", "/text"] {
+            let mut response = srv
+                .get(*path)
+                .no_decompress()
+                .header(ACCEPT_ENCODING, "gzip")
+                .send()
+                .await
+                .unwrap();
+            assert!(response.headers().get(CONTENT_ENCODING).is_none());
+            let bytes = response.body().await.unwrap();
+            assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+        }
+    })
+}
+
 #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
 #[test]
 fn test_body_encoding_override() {